@@ -0,0 +1,58 @@
+//! Benchmark de desencriptación secuencial vs paralela
+//!
+//! Ejecutar con `cargo bench --bench decryption`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::prelude::*;
+
+#[path = "../src/crypto/mod.rs"]
+#[allow(dead_code)]
+mod crypto;
+
+const FIELDS_PER_ENTRY: usize = 3;
+
+fn build_vault(manager: &crypto::CryptoManager, entries: usize) -> Vec<crypto::EncryptedData> {
+    (0..entries * FIELDS_PER_ENTRY)
+        .map(|i| {
+            manager
+                .encrypt_data(format!("campo-de-prueba-{}", i).as_bytes())
+                .expect("Error al encriptar")
+        })
+        .collect()
+}
+
+fn bench_decryption(c: &mut Criterion) {
+    let mut manager = crypto::CryptoManager::new();
+    manager
+        .set_master_key("benchmark-master-password", &crypto::generate_salt())
+        .expect("Error al establecer clave maestra");
+
+    let mut group = c.benchmark_group("decrypt_vault");
+    for entries in [100usize, 1_000, 5_000] {
+        let vault = build_vault(&manager, entries);
+
+        group.bench_with_input(BenchmarkId::new("secuencial", entries), &vault, |b, vault| {
+            b.iter(|| {
+                vault
+                    .iter()
+                    .map(|data| manager.decrypt_data(data).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("paralelo", entries), &vault, |b, vault| {
+            b.iter(|| {
+                vault
+                    .par_iter()
+                    .map(|data| manager.decrypt_data(data).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+
+    black_box(&manager);
+}
+
+criterion_group!(benches, bench_decryption);
+criterion_main!(benches);
@@ -1,4 +1,4 @@
-import { Routes, Route } from 'react-router-dom'
+import { Routes, Route, useLocation } from 'react-router-dom'
 import { useEffect } from 'react'
 import { useAuthStore } from './stores/authStore'
 import Layout from './components/Layout'
@@ -8,12 +8,18 @@ import PasswordsPage from './pages/PasswordsPage'
 import GeneratorPage from './pages/GeneratorPage'
 import SettingsPage from './pages/SettingsPage'
 import SyncPage from './pages/SyncPage'
+import SecurityPage from './pages/SecurityPage'
+import QuickSearchPage from './pages/QuickSearchPage'
 import { listen } from '@tauri-apps/api/event'
 
 function App() {
   const { isAuthenticated, isInitialized, checkDatabaseStatus, resetAuthenticationOnAppStart } = useAuthStore()
+  // La ventana de búsqueda rápida comparte el frontend pero no la sesión de la ventana principal
+  const isQuickSearch = useLocation().pathname === '/quick-search'
 
   useEffect(() => {
+    if (isQuickSearch) return
+    
     // CRÍTICO: Resetear autenticación al abrir la app
     console.log('🔄 App: Iniciando aplicación...');
     resetAuthenticationOnAppStart();
@@ -29,7 +35,11 @@ function App() {
     return () => {
       unlisten.then(f => f())
     }
-  }, [checkDatabaseStatus, resetAuthenticationOnAppStart])
+  }, [checkDatabaseStatus, resetAuthenticationOnAppStart, isQuickSearch])
+
+  if (isQuickSearch) {
+    return <QuickSearchPage />
+  }
 
   // Solo mostrar LoginPage si la base de datos está inicializada pero el usuario no está autenticado
   if (isInitialized && !isAuthenticated) {
@@ -51,6 +61,7 @@ function App() {
         <Route path="/" element={<DashboardPage />} />
         <Route path="/passwords" element={<PasswordsPage />} />
         <Route path="/generator" element={<GeneratorPage />} />
+        <Route path="/security" element={<SecurityPage />} />
         <Route path="/sync" element={<SyncPage />} />
         <Route path="/settings" element={<SettingsPage />} />
       </Routes>
@@ -87,7 +87,7 @@ const BrowserAutocomplete = ({ url, onSuggestionSelect }: BrowserAutocompletePro
         password: saveData.password,
         url: url,
         notes: saveData.notes,
-        category_id: '',
+        categoryId: '',
         tags: [],
       })
       
@@ -40,17 +40,17 @@ const BrowserAutofill = ({ url, username, onFill }: BrowserAutofillProps) => {
 
   const handleGenerateNew = async () => {
     try {
-      const newPassword = await generatePassword({
+      const generated = await generatePassword({
         length: 16,
         include_uppercase: true,
         include_lowercase: true,
         include_numbers: true,
         include_symbols: true,
         exclude_similar: true,
-      })
+      }, url)
       
-      if (newPassword) {
-        onFill(username || '', newPassword)
+      if (generated) {
+        onFill(username || '', generated.password)
         toast.success('Nueva contraseña generada y aplicada')
       }
     } catch (error) {
@@ -0,0 +1,52 @@
+import React, { useEffect, useRef, useState } from 'react'
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { Globe } from 'lucide-react'
+
+interface EntryIconData {
+  domain: string
+  mimeType: string
+  data: string
+}
+
+interface EntryIconProps {
+  entryId: string
+  className?: string
+}
+
+// Favicon del sitio de la entrada; el backend lo descarga y lo guarda en caché
+const EntryIcon: React.FC<EntryIconProps> = ({ entryId, className = 'h-5 w-5' }) => {
+  const [icon, setIcon] = useState<EntryIconData | null>(null)
+  const hasIcon = useRef(false)
+
+  useEffect(() => {
+    let active = true
+    const load = () =>
+      invoke<EntryIconData | null>('get_entry_icon', { id: entryId })
+        .then(result => {
+          if (!active) return
+          hasIcon.current = result !== null
+          setIcon(result)
+        })
+        .catch(() => {})
+
+    load()
+    // Si el icono no estaba en caché, el backend avisa cuando termina la descarga
+    const unlisten = listen<string>('favicon-ready', () => {
+      if (!hasIcon.current) load()
+    })
+
+    return () => {
+      active = false
+      unlisten.then(fn => fn())
+    }
+  }, [entryId])
+
+  if (!icon) {
+    return <Globe className={`${className} text-gray-400`} />
+  }
+
+  return <img src={`data:${icon.mimeType};base64,${icon.data}`} alt={icon.domain} className={`${className} rounded`} />
+}
+
+export default EntryIcon
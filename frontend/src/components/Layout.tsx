@@ -12,7 +12,8 @@ import {
   Plus,
   Search,
   User,
-  RefreshCw
+  RefreshCw,
+  ShieldAlert
 } from 'lucide-react'
 import { useAuthStore } from '../stores/authStore'
 import { useGlobalShortcut } from '../hooks/useGlobalShortcut'
@@ -71,6 +72,7 @@ const Layout = ({ children }: LayoutProps) => {
     { name: 'Dashboard', href: '/', icon: Shield },
     { name: 'Contraseñas', href: '/passwords', icon: Lock },
     { name: 'Generador', href: '/generator', icon: Key },
+    { name: 'Seguridad', href: '/security', icon: ShieldAlert },
     { name: 'Sincronización', href: '/sync', icon: RefreshCw },
     { name: 'Configuración', href: '/settings', icon: Settings },
   ]
@@ -18,7 +18,7 @@ const SyncStatusIndicator: React.FC = () => {
     <div className="flex items-center gap-2 text-green-600">
       <Wifi className="h-4 w-4" />
       <span className="text-sm">
-        {status.connectedDevices} dispositivo{status.connectedDevices !== 1 ? 's' : ''}
+        {status.connectedDevices.length} dispositivo{status.connectedDevices.length !== 1 ? 's' : ''}
       </span>
       {status.isSyncing && (
         <RefreshCw className="h-4 w-4 animate-spin" />
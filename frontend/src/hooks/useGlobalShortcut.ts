@@ -1,5 +1,6 @@
 import { useEffect } from 'react'
 import { invoke } from '@tauri-apps/api/tauri'
+import { isAppError } from '../utils/appError'
 
 export const useGlobalShortcut = () => {
   useEffect(() => {
@@ -9,7 +10,7 @@ export const useGlobalShortcut = () => {
         event.preventDefault()
         
         try {
-          // Obtener la URL activa del navegador (esto requeriría permisos adicionales)
+          // Obtener la URL activa del navegador (puede requerir permisos de accesibilidad)
           const activeUrl = await invoke('get_active_browser_url')
           console.log('Activando autofill para:', activeUrl)
           
@@ -17,6 +18,9 @@ export const useGlobalShortcut = () => {
           // Por ahora solo mostramos un mensaje
           alert('Autofill activado! (Funcionalidad en desarrollo)')
         } catch (error) {
+          if (isAppError(error) && error.code === 'permission_denied') {
+            alert(error.message)
+          }
           console.error('Error activando autofill:', error)
         }
       }
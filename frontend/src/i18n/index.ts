@@ -1,3 +1,4 @@
+import { invoke } from '@tauri-apps/api/tauri';
 import en from '../locales/en.json';
 import es from '../locales/es.json';
 
@@ -98,6 +99,11 @@ export function setLanguage(lang: SupportedLanguage): void {
     // Actualizar el atributo lang del HTML
     document.documentElement.lang = lang;
     
+    // Sincronizar el idioma de los mensajes del backend
+    invoke('set_locale', { locale: lang }).catch(() => {
+      // Ignorar si el backend no está disponible (por ejemplo, en el navegador)
+    });
+    
     // Disparar evento personalizado para notificar el cambio
     window.dispatchEvent(new CustomEvent('languageChanged', { detail: { language: lang } }));
   }
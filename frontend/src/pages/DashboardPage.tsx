@@ -2,58 +2,49 @@ import { useState, useEffect } from 'react'
 import { Shield, Lock, Key, AlertTriangle, TrendingUp, Clock, Plus, Search } from 'lucide-react'
 import { usePasswordStore } from '../stores/passwordStore'
 import { Link } from 'react-router-dom'
+import { invoke } from '@tauri-apps/api/tauri'
+
+interface VaultStatistics {
+  totalPasswords: number
+  categories: { categoryId: string | null; name: string | null; count: number }[]
+  weakPasswords: number
+  mediumPasswords: number
+  strongPasswords: number
+  reusedPasswords: number
+  oldPasswords: number
+  averagePasswordAgeDays: number
+  securityScore: number
+}
 
 const DashboardPage = () => {
   const { passwords, fetchPasswords } = usePasswordStore()
-  const [stats, setStats] = useState({
-    total: 0,
-    weak: 0,
-    medium: 0,
-    strong: 0,
-    veryStrong: 0,
-    recent: 0,
-    categories: 0,
-  })
+  const [statistics, setStatistics] = useState<VaultStatistics | null>(null)
 
   useEffect(() => {
     fetchPasswords()
   }, [fetchPasswords])
 
+  // Las estadísticas se calculan en el backend sin desencriptar toda la bóveda
   useEffect(() => {
-    if (passwords.length > 0) {
-      // Calcular estadísticas
-      const total = passwords.length
-      const weak = passwords.filter(p => p.password.length < 8).length
-      const medium = passwords.filter(p => p.password.length >= 8 && p.password.length < 12).length
-      const strong = passwords.filter(p => p.password.length >= 12 && p.password.length < 16).length
-      const veryStrong = passwords.filter(p => p.password.length >= 16).length
-      
-      // Contar contraseñas recientes (últimos 7 días)
-      const weekAgo = new Date()
-      weekAgo.setDate(weekAgo.getDate() - 7)
-      const recent = passwords.filter(p => new Date(p.created_at) > weekAgo).length
-      
-      // Contar categorías únicas
-      const categories = new Set(passwords.map(p => p.category_id).filter(Boolean)).size
-      
-      setStats({
-        total,
-        weak,
-        medium,
-        strong,
-        veryStrong,
-        recent,
-        categories,
-      })
-    }
-  }, [passwords])
+    invoke<VaultStatistics>('get_statistics')
+      .then(setStatistics)
+      .catch(error => console.error('Error al obtener estadísticas:', error))
+  }, [passwords.length])
 
-  const getSecurityScore = () => {
-    if (stats.total === 0) return 0
-    const score = ((stats.strong + stats.veryStrong) / stats.total) * 100
-    return Math.round(score)
+  // Contar contraseñas recientes (últimos 7 días)
+  const weekAgo = new Date()
+  weekAgo.setDate(weekAgo.getDate() - 7)
+  const stats = {
+    total: statistics?.totalPasswords ?? passwords.length,
+    weak: statistics?.weakPasswords ?? 0,
+    medium: statistics?.mediumPasswords ?? 0,
+    strong: statistics?.strongPasswords ?? 0,
+    reused: statistics?.reusedPasswords ?? 0,
+    recent: passwords.filter(p => new Date(p.createdAt) > weekAgo).length,
   }
 
+  const getSecurityScore = () => statistics?.securityScore ?? 0
+
   const getSecurityColor = (score: number) => {
     if (score >= 80) return 'text-green-600 bg-green-100 dark:bg-green-900/20'
     if (score >= 60) return 'text-yellow-600 bg-yellow-100 dark:bg-yellow-900/20'
@@ -69,7 +60,7 @@ const DashboardPage = () => {
   }
 
   const recentPasswords = passwords
-    .sort((a, b) => new Date(b.created_at).getTime() - new Date(a.created_at).getTime())
+    .sort((a, b) => new Date(b.createdAt).getTime() - new Date(a.createdAt).getTime())
     .slice(0, 5)
 
   const weakPasswords = passwords
@@ -138,12 +129,12 @@ const DashboardPage = () => {
                 Contraseñas Fuertes
               </p>
               <p className="text-2xl font-bold text-green-600">
-                {stats.strong + stats.veryStrong}
+                {stats.strong}
               </p>
             </div>
           </div>
           <p className="mt-2 text-sm text-gray-600 dark:text-gray-400">
-            {stats.veryStrong} muy fuertes
+            {stats.reused} reutilizadas
           </p>
         </div>
 
@@ -204,7 +195,7 @@ const DashboardPage = () => {
                     </p>
                   </div>
                   <span className="text-xs text-gray-500 dark:text-gray-400">
-                    {new Date(password.created_at).toLocaleDateString()}
+                    {new Date(password.createdAt).toLocaleDateString()}
                   </span>
                 </div>
               ))}
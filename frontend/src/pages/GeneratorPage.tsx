@@ -1,13 +1,14 @@
-import { useState } from 'react'
-import { Copy, RefreshCw, Check, Eye, EyeOff } from 'lucide-react'
-import { usePasswordStore, PasswordGenerationRequest } from '../stores/passwordStore'
+import { useEffect, useState } from 'react'
+import { Copy, RefreshCw, Check, Eye, EyeOff, Trash2 } from 'lucide-react'
+import { usePasswordStore, PasswordGenerationRequest, PasswordStrength, GenerationMode, GenerationHistoryEntry } from '../stores/passwordStore'
 import toast from 'react-hot-toast'
 
 const GeneratorPage = () => {
   const [generatedPassword, setGeneratedPassword] = useState('')
   const [showPassword, setShowPassword] = useState(false)
   const [copied, setCopied] = useState(false)
-  const [strength, setStrength] = useState<any>(null)
+  const [strength, setStrength] = useState<PasswordStrength | null>(null)
+  const [entropyBits, setEntropyBits] = useState<number | null>(null)
   
   const [settings, setSettings] = useState<PasswordGenerationRequest>({
     length: 16,
@@ -16,18 +17,67 @@ const GeneratorPage = () => {
     include_numbers: true,
     include_symbols: true,
     exclude_similar: true,
+    mode: 'random',
   })
 
-  const { generatePassword, checkPasswordStrength } = usePasswordStore()
+  const [pinLength, setPinLength] = useState(6)
+  const [extraValue, setExtraValue] = useState<{ value: string; entropyBits: number } | null>(null)
+
+  const [history, setHistory] = useState<GenerationHistoryEntry[]>([])
+
+  const {
+    generatePassword,
+    generatePin,
+    generateUsername,
+    checkPasswordStrength,
+    fetchGenerationHistory,
+    clearGenerationHistory,
+  } = usePasswordStore()
+
+  useEffect(() => {
+    fetchGenerationHistory().then(setHistory)
+  }, [fetchGenerationHistory])
+
+  const handleClearHistory = async () => {
+    if (await clearGenerationHistory()) {
+      setHistory([])
+      toast.success('Historial borrado')
+    }
+  }
+
+  const handleCopyFromHistory = async (password: string) => {
+    try {
+      await navigator.clipboard.writeText(password)
+      toast.success('Contraseña copiada al portapapeles')
+    } catch (error) {
+      toast.error('Error al copiar la contraseña')
+    }
+  }
+
+  const handleGeneratePin = async () => {
+    const generated = await generatePin(pinLength)
+    if (generated) {
+      setExtraValue({ value: generated.password, entropyBits: generated.entropy_bits })
+    }
+  }
+
+  const handleGenerateUsername = async () => {
+    const generated = await generateUsername()
+    if (generated) {
+      setExtraValue({ value: generated.username, entropyBits: generated.entropy_bits })
+    }
+  }
 
   const handleGenerate = async () => {
-    const password = await generatePassword(settings)
-    if (password) {
-      setGeneratedPassword(password)
+    const generated = await generatePassword(settings)
+    if (generated) {
+      setGeneratedPassword(generated.password)
+      setEntropyBits(generated.entropy_bits)
       setCopied(false)
+      fetchGenerationHistory().then(setHistory)
       
       // Verificar fortaleza
-      const strengthResult = await checkPasswordStrength(password)
+      const strengthResult = await checkPasswordStrength(generated.password)
       if (strengthResult) {
         setStrength(strengthResult)
       }
@@ -80,6 +130,26 @@ const GeneratorPage = () => {
           </h3>
           
           <div className="space-y-4">
+            {/* Modo */}
+            <div>
+              <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">
+                Modo:
+              </label>
+              <select
+                value={settings.mode}
+                onChange={(e) => setSettings(prev => ({ ...prev, mode: e.target.value as GenerationMode }))}
+                className="input-field"
+              >
+                <option value="random">Aleatoria</option>
+                <option value="pronounceable">Pronunciable (fácil de escribir)</option>
+              </select>
+              {settings.mode === 'pronounceable' && (
+                <p className="mt-1 text-xs text-gray-500 dark:text-gray-400">
+                  Alterna consonantes y vocales. Es más fácil de teclear, pero tiene menos entropía que una aleatoria del mismo largo.
+                </p>
+              )}
+            </div>
+
             {/* Longitud */}
             <div>
               <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">
@@ -231,6 +301,22 @@ const GeneratorPage = () => {
                     />
                   </div>
 
+                  <p className="text-sm text-gray-600 dark:text-gray-400">
+                    Tiempo estimado para descifrarla: {strength.crack_times.offline_slow.display}
+                  </p>
+
+                  {entropyBits !== null && (
+                    <p className="text-sm text-gray-600 dark:text-gray-400">
+                      Entropía: {Math.round(entropyBits)} bits
+                    </p>
+                  )}
+
+                  {strength.feedback.length > 0 && (
+                    <p className="text-sm text-orange-600 dark:text-orange-400">
+                      {strength.feedback.join('. ')}
+                    </p>
+                  )}
+
                   {/* Sugerencias */}
                   {strength.suggestions && strength.suggestions.length > 0 && (
                     <div>
@@ -259,6 +345,75 @@ const GeneratorPage = () => {
         </div>
       </div>
 
+      {/* PIN y nombre de usuario */}
+      <div className="card">
+        <h3 className="text-lg font-semibold text-gray-900 dark:text-white mb-4">
+          PIN y nombre de usuario
+        </h3>
+        <div className="flex flex-wrap items-center gap-3">
+          <label className="text-sm text-gray-700 dark:text-gray-300">
+            Dígitos:
+            <input
+              type="number"
+              min="4"
+              max="16"
+              value={pinLength}
+              onChange={(e) => setPinLength(parseInt(e.target.value) || 4)}
+              className="input-field ml-2 w-20"
+            />
+          </label>
+          <button onClick={handleGeneratePin} className="btn-secondary">
+            Generar PIN
+          </button>
+          <button onClick={handleGenerateUsername} className="btn-secondary">
+            Generar nombre de usuario
+          </button>
+        </div>
+        {extraValue && (
+          <div className="mt-4 flex items-center justify-between">
+            <span className="font-mono text-lg text-gray-900 dark:text-white">{extraValue.value}</span>
+            <span className="text-sm text-gray-600 dark:text-gray-400">
+              Entropía: {Math.round(extraValue.entropyBits)} bits
+            </span>
+          </div>
+        )}
+      </div>
+
+      {/* Historial de generación */}
+      {history.length > 0 && (
+        <div className="card">
+          <div className="flex items-center justify-between mb-4">
+            <h3 className="text-lg font-semibold text-gray-900 dark:text-white">
+              Contraseñas generadas recientemente
+            </h3>
+            <button onClick={handleClearHistory} className="btn-secondary flex items-center gap-2">
+              <Trash2 className="h-4 w-4" />
+              Borrar historial
+            </button>
+          </div>
+          <ul className="divide-y divide-gray-200 dark:divide-gray-700">
+            {history.map((entry) => (
+              <li key={entry.id} className="py-2 flex items-center justify-between gap-4">
+                <div className="min-w-0">
+                  <span className="font-mono text-gray-900 dark:text-white break-all">{entry.password}</span>
+                  <p className="text-xs text-gray-500 dark:text-gray-400">
+                    {new Date(entry.generated_at).toLocaleString()}
+                    {entry.url && ` · ${entry.url}`}
+                  </p>
+                </div>
+                <button
+                  onClick={() => handleCopyFromHistory(entry.password)}
+                  className="p-2 text-gray-400 hover:text-gray-600"
+                  title="Copiar"
+                >
+                  <Copy className="h-4 w-4" />
+                </button>
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
+
       {/* Información de seguridad */}
       <div className="card bg-blue-50 dark:bg-blue-900/20 border-blue-200 dark:border-blue-800">
         <h3 className="text-lg font-semibold text-blue-900 dark:text-blue-100 mb-3">
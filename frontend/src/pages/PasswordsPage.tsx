@@ -1,7 +1,11 @@
 import { useState, useEffect } from 'react'
-import { Plus, Search, Edit, Trash2, Eye, EyeOff, Copy, Check, RefreshCw } from 'lucide-react'
+import { Plus, Search, Edit, Trash2, Eye, EyeOff, Copy, Check, RefreshCw, Link2 } from 'lucide-react'
 import { usePasswordStore, PasswordEntry, CreatePasswordRequest, PasswordGenerationRequest } from '../stores/passwordStore'
+import { useSyncStore } from '../stores/syncStore'
 import toast from 'react-hot-toast'
+import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
+import EntryIcon from '../components/EntryIcon'
 
 const PasswordsPage = () => {
   const [searchQuery, setSearchQuery] = useState('')
@@ -9,6 +13,12 @@ const PasswordsPage = () => {
   const [editingPassword, setEditingPassword] = useState<PasswordEntry | null>(null)
   const [showPasswords, setShowPasswords] = useState<Set<string>>(new Set())
   const [copiedId, setCopiedId] = useState<string | null>(null)
+  // Entrada que se está por compartir con un enlace, y el enlace una vez creado
+  const [sharingPassword, setSharingPassword] = useState<PasswordEntry | null>(null)
+  const [shareExpiry, setShareExpiry] = useState(60)
+  const [shareViews, setShareViews] = useState(1)
+  const [shareLink, setShareLink] = useState<string | null>(null)
+  const { shareEntry } = useSyncStore()
   
   const { 
     passwords, 
@@ -29,7 +39,7 @@ const PasswordsPage = () => {
     password: '',
     url: '',
     notes: '',
-    category_id: '',
+    categoryId: '',
     tags: [],
   })
 
@@ -74,8 +84,21 @@ const PasswordsPage = () => {
     searchPasswords(query)
   }
 
+  // Avisa si la contraseña aparece en filtraciones conocidas (solo sale el prefijo de su hash)
+  const warnIfBreached = async (password: string) => {
+    try {
+      const count = await invoke<number | null>('check_password_breached', { password })
+      if (count) {
+        toast.error(`Esta contraseña apareció ${count.toLocaleString()} veces en filtraciones conocidas`)
+      }
+    } catch (error) {
+      console.warn('No se pudo comprobar la contraseña:', error)
+    }
+  }
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault()
+    warnIfBreached(formData.password)
     
     if (editingPassword) {
       const success = await updatePassword(editingPassword.id, formData)
@@ -103,12 +126,35 @@ const PasswordsPage = () => {
       password: password.password,
       url: password.url || '',
       notes: password.notes || '',
-      category_id: password.category_id || '',
+      categoryId: password.categoryId || '',
       tags: password.tags,
     })
     setShowAddModal(true)
   }
 
+  const openShareModal = (password: PasswordEntry) => {
+    setSharingPassword(password)
+    setShareExpiry(60)
+    setShareViews(1)
+    setShareLink(null)
+  }
+
+  const handleShare = async () => {
+    if (!sharingPassword) return
+    const created = await shareEntry(sharingPassword.id, shareExpiry, shareViews)
+    if (created) {
+      setShareLink(created.link)
+    } else {
+      toast.error(useSyncStore.getState().status.error ?? 'Error al compartir la entrada')
+    }
+  }
+
+  const copyShareLink = async () => {
+    if (!shareLink) return
+    await navigator.clipboard.writeText(shareLink)
+    toast.success('Enlace copiado')
+  }
+
   const handleDelete = async (id: string) => {
     if (confirm('¿Estás seguro de que quieres eliminar esta contraseña?')) {
       const success = await deletePassword(id)
@@ -118,15 +164,16 @@ const PasswordsPage = () => {
     }
   }
 
-  const handleCopy = async (text: string, id: string) => {
+  // Copia desde el backend: el portapapeles se limpia solo tras el tiempo configurado
+  const handleCopyField = async (command: 'copy_username' | 'copy_password', entryId: string, copyId: string) => {
     try {
-      await navigator.clipboard.writeText(text)
-      setCopiedId(id)
+      await invoke(command, { id: entryId })
+      setCopiedId(copyId)
       toast.success('Copiado al portapapeles')
       
       setTimeout(() => setCopiedId(null), 2000)
     } catch (error) {
-      toast.error('Error al copiar')
+      toast.error(getErrorMessage(error, 'Error al copiar'))
     }
   }
 
@@ -147,7 +194,7 @@ const PasswordsPage = () => {
       password: '',
       url: '',
       notes: '',
-      category_id: '',
+      categoryId: '',
       tags: [],
     })
   }
@@ -165,8 +212,9 @@ const PasswordsPage = () => {
   }
 
   const handleGeneratePassword = async () => {
-    const generatedPassword = await generatePassword(passwordSettings)
-    if (generatedPassword) {
+    const generated = await generatePassword(passwordSettings, formData.url)
+    if (generated) {
+      const generatedPassword = generated.password
       setFormData(prev => ({ ...prev, password: generatedPassword }))
       toast.success('Contraseña generada automáticamente')
       
@@ -249,12 +297,13 @@ const PasswordsPage = () => {
                 <div className="flex items-start justify-between">
                   <div className="flex-1">
                     <div className="flex items-center space-x-3 mb-2">
+                      <EntryIcon entryId={password.id} />
                       <h3 className="text-lg font-semibold text-gray-900 dark:text-white">
                         {password.title}
                       </h3>
-                      {password.category_id && (
+                      {password.categoryId && (
                         <span className="px-2 py-1 bg-primary-100 dark:bg-primary-900 text-primary-800 dark:text-primary-200 text-xs rounded-full">
-                          {password.category_id}
+                          {password.categoryId}
                         </span>
                       )}
                     </div>
@@ -269,7 +318,7 @@ const PasswordsPage = () => {
                             {password.username}
                           </span>
                           <button
-                            onClick={() => handleCopy(password.username, `user-${password.id}`)}
+                            onClick={() => handleCopyField('copy_username', password.id, `user-${password.id}`)}
                             className="p-1 text-gray-400 hover:text-gray-600 dark:hover:text-gray-300"
                           >
                             {copiedId === `user-${password.id}` ? (
@@ -303,7 +352,7 @@ const PasswordsPage = () => {
                             )}
                           </button>
                           <button
-                            onClick={() => handleCopy(password.password, `pass-${password.id}`)}
+                            onClick={() => handleCopyField('copy_password', password.id, `pass-${password.id}`)}
                             className="p-1 text-gray-400 hover:text-gray-600 dark:hover:text-gray-300"
                           >
                             {copiedId === `pass-${password.id}` ? (
@@ -363,6 +412,13 @@ const PasswordsPage = () => {
                   </div>
                   
                   <div className="flex items-center space-x-2 ml-4">
+                    <button
+                      onClick={() => openShareModal(password)}
+                      title="Compartir con un enlace"
+                      className="p-2 text-gray-400 hover:text-gray-600 dark:hover:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg"
+                    >
+                      <Link2 className="h-4 w-4" />
+                    </button>
                     <button
                       onClick={() => handleEdit(password)}
                       className="p-2 text-gray-400 hover:text-gray-600 dark:hover:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg"
@@ -379,10 +435,10 @@ const PasswordsPage = () => {
                 </div>
                 
                 <div className="mt-3 pt-3 border-t border-gray-200 dark:border-gray-700 text-xs text-gray-500 dark:text-gray-400">
-                  <span>Creada: {new Date(password.created_at).toLocaleDateString()}</span>
-                  {password.last_used && (
+                  <span>Creada: {new Date(password.createdAt).toLocaleDateString()}</span>
+                  {password.lastUsed && (
                     <span className="ml-4">
-                      Último uso: {new Date(password.last_used).toLocaleDateString()}
+                      Último uso: {new Date(password.lastUsed).toLocaleDateString()}
                     </span>
                   )}
                 </div>
@@ -392,6 +448,76 @@ const PasswordsPage = () => {
         )}
       </div>
 
+      {/* Modal para compartir una entrada con un enlace */}
+      {sharingPassword && (
+        <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center p-4 z-50">
+          <div className="bg-white dark:bg-gray-800 rounded-lg max-w-md w-full">
+            <div className="p-6 space-y-4">
+              <h3 className="text-lg font-semibold text-gray-900 dark:text-white">
+                Compartir {sharingPassword.title}
+              </h3>
+              {shareLink ? (
+                <>
+                  <p className="text-sm text-gray-600 dark:text-gray-300">
+                    Quien tenga el enlace puede ver la entrada. Solo se muestra ahora: guárdalo o envíalo antes de cerrar.
+                  </p>
+                  <div className="flex space-x-2">
+                    <input type="text" readOnly value={shareLink} className="input-field flex-1 font-mono text-xs" />
+                    <button onClick={copyShareLink} className="btn-secondary flex items-center">
+                      <Copy className="h-4 w-4" />
+                    </button>
+                  </div>
+                </>
+              ) : (
+                <>
+                  <p className="text-sm text-gray-600 dark:text-gray-300">
+                    La entrada va encriptada y el relay no puede leerla. El enlace deja de funcionar al caducar, al abrirse las veces indicadas o si lo revocas.
+                  </p>
+                  <div>
+                    <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                      Caduca en
+                    </label>
+                    <select
+                      value={shareExpiry}
+                      onChange={(e) => setShareExpiry(Number(e.target.value))}
+                      className="input-field"
+                    >
+                      <option value={15}>15 minutos</option>
+                      <option value={60}>1 hora</option>
+                      <option value={24 * 60}>1 día</option>
+                      <option value={7 * 24 * 60}>7 días</option>
+                    </select>
+                  </div>
+                  <div>
+                    <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                      Veces que se puede abrir
+                    </label>
+                    <input
+                      type="number"
+                      min={1}
+                      max={100}
+                      value={shareViews}
+                      onChange={(e) => setShareViews(Math.min(100, Math.max(1, Number(e.target.value) || 1)))}
+                      className="input-field"
+                    />
+                  </div>
+                </>
+              )}
+              <div className="flex space-x-3 pt-2">
+                <button type="button" onClick={() => setSharingPassword(null)} className="btn-secondary flex-1">
+                  {shareLink ? 'Cerrar' : 'Cancelar'}
+                </button>
+                {!shareLink && (
+                  <button type="button" onClick={handleShare} className="btn-primary flex-1">
+                    Crear enlace
+                  </button>
+                )}
+              </div>
+            </div>
+          </div>
+        </div>
+      )}
+
       {/* Modal para agregar/editar contraseña */}
       {showAddModal && (
         <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center p-4 z-50">
@@ -0,0 +1,129 @@
+import { useEffect, useRef, useState } from 'react'
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { Search, User, Key } from 'lucide-react'
+import toast from 'react-hot-toast'
+import { getErrorMessage } from '../utils/appError'
+
+interface QuickSearchResult {
+  id: string
+  title: string
+  username: string
+  url: string | null
+  score: number
+}
+
+const QuickSearchPage = () => {
+  const [query, setQuery] = useState('')
+  const [results, setResults] = useState<QuickSearchResult[]>([])
+  const [selected, setSelected] = useState(0)
+  const [error, setError] = useState<string | null>(null)
+  const inputRef = useRef<HTMLInputElement>(null)
+
+  const hide = () => invoke('hide_quick_search').catch(() => {})
+
+  // Reiniciar la búsqueda cada vez que se abre la ventana con el atajo
+  useEffect(() => {
+    inputRef.current?.focus()
+    const unlisten = listen('quick-search-opened', () => {
+      setQuery('')
+      setSelected(0)
+      inputRef.current?.focus()
+    })
+    return () => {
+      unlisten.then(f => f())
+    }
+  }, [])
+
+  useEffect(() => {
+    invoke<QuickSearchResult[]>('quick_search', { query, limit: 8 })
+      .then(found => {
+        setResults(found)
+        setSelected(0)
+        setError(null)
+      })
+      .catch(err => setError(getErrorMessage(err, 'Error en búsqueda')))
+  }, [query])
+
+  const copyUsername = async (result: QuickSearchResult) => {
+    try {
+      await invoke('copy_username', { id: result.id })
+      toast.success('Usuario copiado al portapapeles')
+    } catch (err) {
+      toast.error(getErrorMessage(err, 'Error al copiar el usuario'))
+    }
+  }
+
+  const copyPassword = async (result: QuickSearchResult) => {
+    try {
+      // La contraseña se copia desde el backend y nunca llega al frontend
+      await invoke('copy_password', { id: result.id })
+      toast.success('Contraseña copiada al portapapeles')
+      hide()
+    } catch (err) {
+      toast.error(getErrorMessage(err, 'Error al copiar la contraseña'))
+    }
+  }
+
+  const handleKeyDown = (event: React.KeyboardEvent) => {
+    if (event.key === 'Escape') {
+      hide()
+    } else if (event.key === 'ArrowDown') {
+      event.preventDefault()
+      setSelected(i => Math.min(i + 1, results.length - 1))
+    } else if (event.key === 'ArrowUp') {
+      event.preventDefault()
+      setSelected(i => Math.max(i - 1, 0))
+    } else if (event.key === 'Enter' && results[selected]) {
+      if (event.shiftKey) {
+        copyUsername(results[selected])
+      } else {
+        copyPassword(results[selected])
+      }
+    }
+  }
+
+  return (
+    <div className="h-screen flex flex-col bg-white dark:bg-gray-900" onKeyDown={handleKeyDown}>
+      <div className="flex items-center gap-2 p-3 border-b border-gray-200 dark:border-gray-700">
+        <Search className="w-5 h-5 text-gray-400" />
+        <input
+          ref={inputRef}
+          value={query}
+          onChange={e => setQuery(e.target.value)}
+          placeholder="Buscar contraseñas..."
+          className="flex-1 bg-transparent outline-none text-gray-900 dark:text-white"
+        />
+      </div>
+
+      {error ? (
+        <p className="p-4 text-sm text-red-600">{error}</p>
+      ) : (
+        <ul className="flex-1 overflow-y-auto">
+          {results.map((result, index) => (
+            <li
+              key={result.id}
+              onMouseEnter={() => setSelected(index)}
+              className={`flex items-center justify-between px-4 py-2 ${index === selected ? 'bg-blue-50 dark:bg-gray-800' : ''}`}
+            >
+              <div className="min-w-0">
+                <p className="font-medium text-gray-900 dark:text-white truncate">{result.title}</p>
+                <p className="text-sm text-gray-500 truncate">{result.username}</p>
+              </div>
+              <div className="flex gap-1">
+                <button onClick={() => copyUsername(result)} title="Copiar usuario (Shift+Enter)" className="p-2 rounded hover:bg-gray-100 dark:hover:bg-gray-700">
+                  <User className="w-4 h-4" />
+                </button>
+                <button onClick={() => copyPassword(result)} title="Copiar contraseña (Enter)" className="p-2 rounded hover:bg-gray-100 dark:hover:bg-gray-700">
+                  <Key className="w-4 h-4" />
+                </button>
+              </div>
+            </li>
+          ))}
+        </ul>
+      )}
+    </div>
+  )
+}
+
+export default QuickSearchPage
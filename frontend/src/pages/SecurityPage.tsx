@@ -0,0 +1,108 @@
+import { useEffect } from 'react'
+import { ShieldAlert, RefreshCw, AlertTriangle, Copy, Clock, Globe, ShieldX } from 'lucide-react'
+import { useSecurityStore, FindingKind } from '../stores/securityStore'
+
+const FINDING_LABELS: Record<FindingKind, { label: string; className: string; icon: typeof AlertTriangle }> = {
+  breached: { label: 'Filtrada', className: 'bg-red-100 text-red-700 dark:bg-red-900/20 dark:text-red-400', icon: ShieldX },
+  reused: { label: 'Reutilizada', className: 'bg-orange-100 text-orange-700 dark:bg-orange-900/20 dark:text-orange-400', icon: Copy },
+  weak: { label: 'Débil', className: 'bg-yellow-100 text-yellow-700 dark:bg-yellow-900/20 dark:text-yellow-400', icon: AlertTriangle },
+  unsecured: { label: 'Sitio sin HTTPS', className: 'bg-blue-100 text-blue-700 dark:bg-blue-900/20 dark:text-blue-400', icon: Globe },
+  old: { label: 'Antigua', className: 'bg-gray-100 text-gray-700 dark:bg-gray-800 dark:text-gray-300', icon: Clock },
+}
+
+const SecurityPage = () => {
+  const { report, isLoading, error, fetchReport, runAudit } = useSecurityStore()
+
+  useEffect(() => {
+    fetchReport()
+  }, [fetchReport])
+
+  return (
+    <div className="space-y-6">
+      <div className="flex items-center justify-between">
+        <div>
+          <h1 className="text-2xl font-bold text-gray-900 dark:text-white">Seguridad</h1>
+          <p className="text-gray-600 dark:text-gray-400">
+            {report
+              ? `Última auditoría: ${new Date(report.generated_at).toLocaleString()}`
+              : 'Todavía no se auditó la bóveda'}
+          </p>
+        </div>
+        <button onClick={runAudit} disabled={isLoading} className="btn-primary flex items-center space-x-2">
+          <RefreshCw className={`h-4 w-4 ${isLoading ? 'animate-spin' : ''}`} />
+          <span>Auditar ahora</span>
+        </button>
+      </div>
+
+      {error && <div className="card text-red-600">{error}</div>}
+
+      {report && (
+        <>
+          <div className="grid grid-cols-2 md:grid-cols-6 gap-4">
+            <div className="card text-center">
+              <p className="text-sm text-gray-600 dark:text-gray-400">Puntuación</p>
+              <p className="text-2xl font-bold text-primary-600">{report.security_score}%</p>
+            </div>
+            <div className="card text-center">
+              <p className="text-sm text-gray-600 dark:text-gray-400">Filtradas</p>
+              <p className="text-2xl font-bold text-red-600">{report.breached_count}</p>
+            </div>
+            <div className="card text-center">
+              <p className="text-sm text-gray-600 dark:text-gray-400">Reutilizadas</p>
+              <p className="text-2xl font-bold text-orange-600">{report.reused_count}</p>
+            </div>
+            <div className="card text-center">
+              <p className="text-sm text-gray-600 dark:text-gray-400">Débiles</p>
+              <p className="text-2xl font-bold text-yellow-600">{report.weak_count}</p>
+            </div>
+            <div className="card text-center">
+              <p className="text-sm text-gray-600 dark:text-gray-400">Sin HTTPS</p>
+              <p className="text-2xl font-bold text-blue-600">{report.unsecured_count}</p>
+            </div>
+            <div className="card text-center">
+              <p className="text-sm text-gray-600 dark:text-gray-400">Antiguas</p>
+              <p className="text-2xl font-bold text-gray-600">{report.old_count}</p>
+            </div>
+          </div>
+
+          <div className="card">
+            {report.entries.length === 0 ? (
+              <div className="text-center py-8 text-gray-600 dark:text-gray-400">
+                No se encontraron problemas en {report.total_entries} entradas
+              </div>
+            ) : (
+              <ul className="divide-y divide-gray-200 dark:divide-gray-700">
+                {report.entries.map(entry => (
+                  <li key={entry.entry_id} className="py-3 flex items-center justify-between">
+                    <div className="flex items-center space-x-3">
+                      <ShieldAlert className="h-5 w-5 text-gray-400" />
+                      <div>
+                        <p className="font-medium text-gray-900 dark:text-white">{entry.title}</p>
+                        <p className="text-sm text-gray-600 dark:text-gray-400">
+                          {entry.username}{entry.url ? ` · ${entry.url}` : ''}
+                        </p>
+                      </div>
+                    </div>
+                    <div className="flex flex-wrap gap-2 justify-end">
+                      {entry.findings.map(kind => {
+                        const { label, className, icon: Icon } = FINDING_LABELS[kind]
+                        return (
+                          <span key={kind} className={`inline-flex items-center space-x-1 px-2 py-1 rounded text-xs ${className}`}>
+                            <Icon className="h-3 w-3" />
+                            <span>{label}</span>
+                          </span>
+                        )
+                      })}
+                    </div>
+                  </li>
+                ))}
+              </ul>
+            )}
+          </div>
+        </>
+      )}
+    </div>
+  )
+}
+
+export default SecurityPage
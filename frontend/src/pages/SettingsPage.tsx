@@ -1,10 +1,56 @@
 import { useAuthStore } from '../stores/authStore'
 import { useNavigate } from 'react-router-dom'
+import { useEffect, useState } from 'react'
+import { open, save } from '@tauri-apps/api/dialog'
+import { listen } from '@tauri-apps/api/event'
+import toast from 'react-hot-toast'
 import { LogOut, Shield, User, Settings as SettingsIcon, Trash2 } from 'lucide-react'
+import { useTransferStore, ExportFormat, EXPORT_EXTENSIONS, BACKUP_EXTENSION, RestoreMode, ImportFormat, ImportReport, ImportRowResolution, BrowserKind,
+  TransferProgress, IMPORT_PROGRESS_EVENT, EXPORT_PROGRESS_EVENT } from '../stores/transferStore'
+
+const BROWSER_NAMES: Record<BrowserKind, string> = {
+  chrome: 'Google Chrome',
+  edge: 'Microsoft Edge',
+  brave: 'Brave',
+  chromium: 'Chromium',
+  firefox: 'Firefox',
+}
 
 const SettingsPage = () => {
   const { logout, isAuthenticated } = useAuthStore()
   const navigate = useNavigate()
+  const {
+    exportPasswords, importFormats, fetchImportFormats, importPasswords, browserProfiles, fetchBrowserProfiles,
+    importFromBrowser, createBackup, restoreBackup, rollbackMigrationBackup, compactDatabase, isLoading,
+  } = useTransferStore()
+  const [exportFormat, setExportFormat] = useState<ExportFormat>('csv_bitwarden')
+  const [exportPassword, setExportPassword] = useState('')
+  const [importFormat, setImportFormat] = useState<ImportFormat>('bitwarden_json')
+  const [importPassword, setImportPassword] = useState('')
+  const [importKeyFile, setImportKeyFile] = useState<string | null>(null)
+  const [browserProfilePath, setBrowserProfilePath] = useState('')
+  const [browserPassword, setBrowserPassword] = useState('')
+  const [backupPassword, setBackupPassword] = useState('')
+  const [restoreMode, setRestoreMode] = useState<RestoreMode>('merge')
+  const [progress, setProgress] = useState<TransferProgress | null>(null)
+
+  useEffect(() => {
+    fetchImportFormats()
+    fetchBrowserProfiles()
+  }, [fetchImportFormats, fetchBrowserProfiles])
+
+  useEffect(() => {
+    const unlisten = [IMPORT_PROGRESS_EVENT, EXPORT_PROGRESS_EVENT].map((event) =>
+      listen<TransferProgress>(event, ({ payload }) => setProgress(payload)),
+    )
+    return () => {
+      unlisten.forEach((promise) => promise.then((f) => f()))
+    }
+  }, [])
+
+  useEffect(() => {
+    if (!isLoading) setProgress(null)
+  }, [isLoading])
 
   const handleLogout = () => {
     console.log('🔄 Frontend: Iniciando logout...')
@@ -18,14 +64,182 @@ const SettingsPage = () => {
     console.log('🔄 Frontend: Función de cambio de contraseña maestra (pendiente)')
   }
 
-  const handleExportPasswords = () => {
-    // TODO: Implementar exportación de contraseñas
-    console.log('🔄 Frontend: Función de exportación (pendiente)')
+  const handleExportPasswords = async () => {
+    const extension = EXPORT_EXTENSIONS[exportFormat]
+    const path = await save({
+      defaultPath: `alohopass-export.${extension}`,
+      filters: [{ name: extension.toUpperCase(), extensions: [extension] }],
+    })
+    if (!path) return
+    
+    const isKdbx = exportFormat === 'kdbx'
+    const result = await exportPasswords({ format: exportFormat, path, password: isKdbx ? exportPassword : null })
+    if (result) {
+      toast.success(isKdbx
+        ? `${result.exported} contraseñas exportadas a KeePass.`
+        : `${result.exported} contraseñas exportadas. El archivo no está encriptado.`)
+    } else {
+      toast.error(useTransferStore.getState().error ?? 'Error al exportar las contraseñas')
+    }
+  }
+
+  const handleCreateBackup = async () => {
+    const path = await save({
+      defaultPath: `alohopass-${new Date().toISOString().slice(0, 10)}.${BACKUP_EXTENSION}`,
+      filters: [{ name: 'Alohopass', extensions: [BACKUP_EXTENSION] }],
+    })
+    if (!path) return
+    
+    const info = await createBackup(path, backupPassword)
+    if (info) {
+      toast.success(`Copia de seguridad creada (${info.entries} contraseñas)`)
+    } else {
+      toast.error(useTransferStore.getState().error ?? 'Error al crear la copia de seguridad')
+    }
+  }
+
+  const handleRestoreBackup = async () => {
+    const path = await open({
+      multiple: false,
+      filters: [{ name: 'Alohopass', extensions: [BACKUP_EXTENSION] }],
+    })
+    if (!path || Array.isArray(path)) return
+    
+    const request = { path, backup_password: backupPassword, mode: restoreMode, preview: true }
+    const preview = await restoreBackup(request)
+    if (!preview) {
+      toast.error(useTransferStore.getState().error ?? 'Error al restaurar la copia de seguridad')
+      return
+    }
+    
+    const summary = [
+      `${preview.added} nuevas`,
+      `${preview.updated} actualizadas`,
+      `${preview.skipped} sin cambios`,
+      ...(preview.removed > 0 ? [`${preview.removed} eliminadas`] : []),
+    ].join(', ')
+    if (!window.confirm(`Al restaurar la copia: ${summary}. ¿Continuar?`)) return
+    
+    const result = await restoreBackup({ ...request, preview: false })
+    if (result) {
+      toast.success(`Copia de seguridad restaurada: ${summary}`)
+    } else {
+      toast.error(useTransferStore.getState().error ?? 'Error al restaurar la copia de seguridad')
+    }
+  }
+
+  const handleRollbackMigration = async () => {
+    if (!window.confirm('¿Volver a la copia hecha antes de la última actualización de la base de datos? Los cambios posteriores se perderán (la base actual se conserva aparte).')) return
+    
+    const info = await rollbackMigrationBackup()
+    if (!info) {
+      toast.error(useTransferStore.getState().error ?? 'Error al restaurar la copia previa a la migración')
+      return
+    }
+    console.log('✅ Frontend: Base sustituida guardada en', info.rolled_back_path)
+    toast.success('Copia restaurada. Reinicia Alohopass para abrir la bóveda.')
+    logout()
+    navigate('/login')
+  }
+
+  const handleCompactDatabase = async () => {
+    const report = await compactDatabase()
+    if (!report) {
+      toast.error(useTransferStore.getState().error ?? 'Error al compactar la base de datos')
+      return
+    }
+    const reclaimedKib = Math.round(report.reclaimed_bytes / 1024)
+    toast.success(`Base de datos compactada: ${reclaimedKib} KiB recuperados`)
   }
 
-  const handleImportPasswords = () => {
-    // TODO: Implementar importación de contraseñas
-    console.log('🔄 Frontend: Función de importación (pendiente)')
+  const selectedImportFormat = importFormats.find((info) => info.format === importFormat)
+
+  const handleChooseKeyFile = async () => {
+    const path = await open({ multiple: false })
+    setImportKeyFile(!path || Array.isArray(path) ? null : path)
+  }
+
+  const handleImportPasswords = async () => {
+    const extension = selectedImportFormat?.extension ?? 'csv'
+    const path = await open({
+      multiple: false,
+      filters: [{ name: extension.toUpperCase(), extensions: [extension] }],
+    })
+    if (!path || Array.isArray(path)) return
+    
+    const request = {
+      format: importFormat,
+      path,
+      password: selectedImportFormat?.needs_password ? importPassword : null,
+      key_file_path: selectedImportFormat?.needs_password ? importKeyFile : null,
+    }
+    await runImport(
+      (preview, resolutions) => importPasswords({ ...request, preview, resolutions }),
+      'Error al importar las contraseñas',
+    )
+  }
+
+  const selectedBrowserProfile = browserProfiles.find((profile) => profile.path === browserProfilePath) ?? browserProfiles[0]
+
+  const handleImportFromBrowser = async () => {
+    if (!selectedBrowserProfile) return
+    const request = {
+      browser: selectedBrowserProfile.browser,
+      profile_path: selectedBrowserProfile.path,
+      password: selectedBrowserProfile.browser === 'firefox' ? browserPassword : null,
+    }
+    await runImport(
+      (preview, resolutions) => importFromBrowser({ ...request, preview, resolutions }),
+      'Error al importar desde el navegador',
+    )
+  }
+
+  // Primero una vista previa: se pregunta qué hacer con cada casi duplicado
+  // (mismo sitio y usuario, otra contraseña) y se confirma antes de guardar
+  const runImport = async (
+    run: (preview: boolean, resolutions: ImportRowResolution[]) => Promise<ImportReport | null>,
+    fallbackError: string,
+  ) => {
+    const preview = await run(true, [])
+    if (!preview) {
+      toast.error(useTransferStore.getState().error ?? fallbackError)
+      return
+    }
+    
+    const resolutions: ImportRowResolution[] = preview.rows
+      .filter((row) => row.status === 'near_duplicate')
+      .map((row): ImportRowResolution => ({
+        row: row.row,
+        resolution: window.confirm(
+          `«${row.title ?? `Fila ${row.row}`}» ya existe con otra contraseña. ¿Reemplazar la guardada? (Cancelar la importa como entrada nueva)`,
+        ) ? 'overwrite' : 'import',
+      }))
+    const overwritten = resolutions.filter((choice) => choice.resolution === 'overwrite').length
+    const summary = [
+      `${preview.imported - overwritten} nuevas`,
+      ...(overwritten > 0 ? [`${overwritten} reemplazadas`] : []),
+      `${preview.skipped} duplicadas omitidas`,
+      ...(preview.failed > 0 ? [`${preview.failed} con errores`] : []),
+    ].join(', ')
+    if (!window.confirm(`Al importar: ${summary}. ¿Continuar?`)) return
+    
+    const report = await run(false, resolutions)
+    if (!report) {
+      toast.error(useTransferStore.getState().error ?? fallbackError)
+      return
+    }
+    showImportReport(report)
+  }
+
+  const showImportReport = (report: ImportReport) => {
+    toast.success(`${report.imported} contraseñas importadas, ${report.updated} reemplazadas, ${report.skipped} omitidas`)
+    if (report.failed > 0) {
+      const failures = report.rows
+        .filter((row) => row.error)
+        .map((row) => `Fila ${row.row}${row.title ? ` (${row.title})` : ''}: ${row.error}`)
+      console.warn('⚠️ Frontend: Filas no importadas', failures)
+      toast.error(`${report.failed} filas no se pudieron importar`)
+    }
   }
 
   const handleClearAllData = () => {
@@ -95,13 +309,78 @@ const SettingsPage = () => {
           </h2>
           
           <div className="space-y-4">
+            {isLoading && progress && progress.total > 0 && (
+              <div>
+                <div className="w-full h-2 bg-gray-200 dark:bg-gray-700 rounded-full overflow-hidden">
+                  <div
+                    className="h-full bg-blue-600 transition-all"
+                    style={{ width: `${Math.round((progress.processed / progress.total) * 100)}%` }}
+                  />
+                </div>
+                <p className="mt-1 text-sm text-gray-600 dark:text-gray-400 truncate">
+                  {progress.processed} de {progress.total}{progress.title ? ` — ${progress.title}` : ''}
+                </p>
+              </div>
+            )}
+
+            <select
+              value={exportFormat}
+              onChange={(e) => setExportFormat(e.target.value as ExportFormat)}
+              className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+            >
+              <option value="csv_bitwarden">CSV (Bitwarden)</option>
+              <option value="csv_chrome">CSV (Chrome)</option>
+              <option value="json">JSON</option>
+              <option value="alohopass">Alohopass (JSON completo)</option>
+              <option value="kdbx">KeePass (KDBX 4)</option>
+            </select>
+
+            {exportFormat === 'kdbx' && (
+              <input
+                type="password"
+                value={exportPassword}
+                onChange={(e) => setExportPassword(e.target.value)}
+                placeholder="Contraseña de la base de datos de KeePass"
+                className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+              />
+            )}
+
             <button
               onClick={handleExportPasswords}
-              className="w-full flex items-center justify-center gap-2 px-4 py-2 bg-green-600 text-white rounded-lg hover:bg-green-700 transition-colors"
+              disabled={exportFormat === 'kdbx' && !exportPassword}
+              className="w-full flex items-center justify-center gap-2 px-4 py-2 bg-green-600 text-white rounded-lg hover:bg-green-700 transition-colors disabled:opacity-50"
             >
               Exportar Contraseñas
             </button>
 
+            <select
+              value={importFormat}
+              onChange={(e) => setImportFormat(e.target.value as ImportFormat)}
+              className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+            >
+              {importFormats.map((info) => (
+                <option key={info.format} value={info.format}>{info.name}</option>
+              ))}
+            </select>
+
+            {selectedImportFormat?.needs_password && (
+              <div className="grid grid-cols-2 gap-4">
+                <input
+                  type="password"
+                  value={importPassword}
+                  onChange={(e) => setImportPassword(e.target.value)}
+                  placeholder="Contraseña del archivo"
+                  className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+                />
+                <button
+                  onClick={handleChooseKeyFile}
+                  className="px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-gray-700 dark:text-gray-300 hover:bg-gray-50 dark:hover:bg-gray-700 transition-colors truncate"
+                >
+                  {importKeyFile ? importKeyFile.split(/[\\/]/).pop() : 'Archivo de clave (opcional)'}
+                </button>
+              </div>
+            )}
+
             <button
               onClick={handleImportPasswords}
               className="w-full flex items-center justify-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors"
@@ -109,6 +388,88 @@ const SettingsPage = () => {
               Importar Contraseñas
             </button>
 
+            {browserProfiles.length > 0 && (
+              <>
+                <select
+                  value={selectedBrowserProfile?.path ?? ''}
+                  onChange={(e) => setBrowserProfilePath(e.target.value)}
+                  className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+                >
+                  {browserProfiles.map((profile) => (
+                    <option key={profile.path} value={profile.path}>
+                      {BROWSER_NAMES[profile.browser]} — {profile.name}
+                    </option>
+                  ))}
+                </select>
+
+                {selectedBrowserProfile?.browser === 'firefox' && (
+                  <input
+                    type="password"
+                    value={browserPassword}
+                    onChange={(e) => setBrowserPassword(e.target.value)}
+                    placeholder="Contraseña principal de Firefox (si la tiene)"
+                    className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+                  />
+                )}
+
+                <button
+                  onClick={handleImportFromBrowser}
+                  className="w-full flex items-center justify-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors"
+                >
+                  Importar desde el navegador
+                </button>
+              </>
+            )}
+
+            <input
+              type="password"
+              value={backupPassword}
+              onChange={(e) => setBackupPassword(e.target.value)}
+              placeholder="Contraseña de la copia de seguridad (distinta de la maestra)"
+              className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+            />
+
+            <select
+              value={restoreMode}
+              onChange={(e) => setRestoreMode(e.target.value as RestoreMode)}
+              className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-white"
+            >
+              <option value="merge">Restaurar combinando con la bóveda actual</option>
+              <option value="replace">Restaurar reemplazando la bóveda actual</option>
+            </select>
+
+            <div className="grid grid-cols-2 gap-4">
+              <button
+                onClick={handleCreateBackup}
+                disabled={!backupPassword}
+                className="flex items-center justify-center gap-2 px-4 py-2 bg-green-600 text-white rounded-lg hover:bg-green-700 transition-colors disabled:opacity-50"
+              >
+                Crear copia de seguridad
+              </button>
+              <button
+                onClick={handleRestoreBackup}
+                disabled={!backupPassword}
+                className="flex items-center justify-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors disabled:opacity-50"
+              >
+                Restaurar copia de seguridad
+              </button>
+            </div>
+
+            <button
+              onClick={handleRollbackMigration}
+              className="w-full flex items-center justify-center gap-2 px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-gray-700 dark:text-gray-300 hover:bg-gray-50 dark:hover:bg-gray-700 transition-colors"
+            >
+              Volver a la copia previa a la última migración
+            </button>
+
+            <button
+              onClick={handleCompactDatabase}
+              disabled={isLoading}
+              className="w-full flex items-center justify-center gap-2 px-4 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-gray-700 dark:text-gray-300 hover:bg-gray-50 dark:hover:bg-gray-700 transition-colors disabled:opacity-50"
+            >
+              Compactar base de datos
+            </button>
+
             <button
               onClick={handleClearAllData}
               className="w-full flex items-center justify-center gap-2 px-4 py-2 bg-red-600 text-white rounded-lg hover:bg-red-700 transition-colors"
@@ -1,5 +1,11 @@
 import React, { useEffect, useState } from 'react';
-import { useSyncStore } from '../stores/syncStore';
+import { listen } from '@tauri-apps/api/event';
+import {
+  useSyncStore, DeviceInfo, Incompatibility, PairingQr, EncryptionLevel, NetworkRestriction, SyncMethod, SyncDirection, SyncConfig, CloudBackend, CloudSecret,
+  CloudProvider, SharedEntry
+} from '../stores/syncStore';
+import { useCategoryStore } from '../stores/categoryStore';
+import { usePasswordStore } from '../stores/passwordStore';
 import { 
   RefreshCw, 
   Wifi, 
@@ -13,31 +19,139 @@ import {
   Clock,
   Smartphone,
   Monitor,
-  Tablet
+  Tablet,
+  QrCode,
+  History,
+  FolderMinus,
+  Send,
+  Link2,
+  AlertTriangle
 } from 'lucide-react';
 
+// Intentos por página en el historial
+const HISTORY_PAGE_SIZE = 20;
+
+// Campos de texto del buzón en la nube
+type CloudField = keyof Pick<SyncConfig,
+  'webdavUrl' | 'webdavUsername' | 's3Endpoint' | 's3Region' | 's3Bucket' | 's3Prefix' | 's3AccessKeyId' | 'relayUrl' | 'turnUrl' | 'turnUsername'>;
+
 const SyncPage: React.FC = () => {
   const {
     status,
     config,
     stats,
     devices,
+    pairings,
     loadSyncData,
     toggleSync,
+    togglePause,
     startDiscovery,
     syncNow,
     updateConfig,
-    trustDevice,
+    setCloudSecret,
+    cloudAccounts,
+    connectingAccount,
+    loadCloudAccounts,
+    connectCloudAccount,
+    disconnectCloudAccount,
+    loadPairings,
+    applyDeviceUpdate,
+    beginPairing,
+    confirmPairing,
+    createPairingQr,
+    scanPairingQr,
     removeDevice,
-    clearError
+    clearError,
+    deviceLimit,
+    dismissDeviceLimit,
+    history,
+    loadHistory,
+    scopes,
+    loadScope,
+    setScope,
+    sendEntry,
+    incomingEntries,
+    loadIncomingEntries,
+    respondToIncomingEntry,
+    sharedLinks,
+    loadSharedLinks,
+    revokeSharedLink,
+    openSharedLink
   } = useSyncStore();
+  const { categories, fetchCategories } = useCategoryStore();
+  const { passwords, fetchPasswords, createPassword } = usePasswordStore();
 
   const [activeTab, setActiveTab] = useState('overview');
+  const [pairingQr, setPairingQr] = useState<PairingQr | null>(null);
+  const [scannedPayload, setScannedPayload] = useState('');
+  // Lo que se escribe en la lista de redes, hasta salir del campo
+  const [networksDraft, setNetworksDraft] = useState<string | null>(null);
+  // Lo que se escribe en los campos del buzón en la nube, hasta salir del campo
+  const [cloudDraft, setCloudDraft] = useState<Partial<Record<CloudField, string>>>({});
+  const [secretDraft, setSecretDraft] = useState<Partial<Record<CloudSecret, string>>>({});
+  const [historyOffset, setHistoryOffset] = useState(0);
+  const [failedOnly, setFailedOnly] = useState(false);
+  // Dispositivo cuyas categorías excluidas se están editando
+  const [scopeDevice, setScopeDevice] = useState<string | null>(null);
+  // Dispositivo al que se está eligiendo una entrada para mandarle
+  const [sendDevice, setSendDevice] = useState<string | null>(null);
+  const [sendEntryId, setSendEntryId] = useState('');
+  // Enlace compartido que se pega para abrirlo, y la entrada que trajo
+  const [sharedLinkDraft, setSharedLinkDraft] = useState('');
+  const [openedEntry, setOpenedEntry] = useState<SharedEntry | null>(null);
+
+  const showPairingQr = async () => {
+    setPairingQr(await createPairingQr());
+  };
+
+  const submitScannedPayload = async () => {
+    if (await scanPairingQr(scannedPayload.trim())) {
+      setScannedPayload('');
+    }
+  };
 
   useEffect(() => {
     loadSyncData();
   }, [loadSyncData]);
 
+  useEffect(() => {
+    if (activeTab !== 'settings') return;
+    loadCloudAccounts();
+  }, [activeTab, loadCloudAccounts]);
+
+  // Conexiones, desconexiones y heartbeats de los dispositivos
+  useEffect(() => {
+    const unlisten = listen<DeviceInfo>('sync-device', ({ payload }) => applyDeviceUpdate(payload));
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, [applyDeviceUpdate]);
+
+  // El código aparece cuando el otro dispositivo responde
+  useEffect(() => {
+    if (activeTab !== 'devices') return;
+    const timer = setInterval(loadPairings, 2000);
+    return () => clearInterval(timer);
+  }, [activeTab, loadPairings]);
+
+  // Entradas que mandan otros dispositivos, en cualquier pestaña
+  useEffect(() => {
+    loadIncomingEntries();
+    const timer = setInterval(loadIncomingEntries, 2000);
+    return () => clearInterval(timer);
+  }, [loadIncomingEntries]);
+
+  useEffect(() => {
+    if (activeTab !== 'shared') return;
+    loadSharedLinks();
+    fetchPasswords();
+  }, [activeTab, loadSharedLinks, fetchPasswords]);
+
+  useEffect(() => {
+    if (activeTab !== 'history') return;
+    loadHistory({ offset: historyOffset, limit: HISTORY_PAGE_SIZE, failedOnly });
+  }, [activeTab, historyOffset, failedOnly, loadHistory]);
+
   const getDeviceIcon = (type: string) => {
     switch (type) {
       case 'mobile':
@@ -63,9 +177,183 @@ const SyncPage: React.FC = () => {
     return `Hace ${Math.floor(diffMins / 1440)}d`;
   };
 
+  const describeIncompatibility = (incompatibility: Incompatibility) => {
+    switch (incompatibility.kind) {
+      case 'peerTooOld':
+        return `Tiene Alohopass ${incompatibility.version} y hace falta al menos la ${incompatibility.required}. Actualízalo para sincronizar.`;
+      case 'localTooOld':
+        return `Pide al menos Alohopass ${incompatibility.required} y este dispositivo tiene la ${incompatibility.version}. Actualiza este dispositivo para sincronizar.`;
+      case 'invalidVersion':
+        return `Anuncia una versión que no se entiende: ${incompatibility.version}.`;
+    }
+  };
+
+  const describeRestriction = (restriction: NetworkRestriction) => {
+    switch (restriction.reason) {
+      case 'notWifi':
+        return 'Solo se sincroniza conectado por Wi-Fi.';
+      case 'networkNotAllowed':
+        return restriction.ssid
+          ? `La red Wi-Fi "${restriction.ssid}" no está entre las permitidas.`
+          : 'La red Wi-Fi actual no está entre las permitidas.';
+      case 'undetected':
+        return `No se pudo comprobar la red actual: ${restriction.message}`;
+    }
+  };
+
+  const toggleSendEntry = (deviceId: string) => {
+    setSendEntryId('');
+    if (sendDevice === deviceId) {
+      setSendDevice(null);
+      return;
+    }
+    setSendDevice(deviceId);
+    fetchPasswords();
+  };
+
+  const submitSendEntry = async (deviceId: string) => {
+    if (await sendEntry(sendEntryId, deviceId)) {
+      setSendDevice(null);
+      setSendEntryId('');
+    }
+  };
+
+  // Cada vez que se abre se gasta una de sus vistas
+  const submitSharedLink = async () => {
+    const entry = await openSharedLink(sharedLinkDraft.trim());
+    if (entry) {
+      setOpenedEntry(entry);
+      setSharedLinkDraft('');
+    }
+  };
+
+  const saveOpenedEntry = async () => {
+    if (!openedEntry) return;
+    const saved = await createPassword({
+      title: openedEntry.title,
+      username: openedEntry.username,
+      password: openedEntry.password,
+      url: openedEntry.url ?? undefined,
+      notes: openedEntry.notes ?? undefined,
+      tags: openedEntry.tags,
+    });
+    if (saved) {
+      setOpenedEntry(null);
+    }
+  };
+
+  const toggleScopeEditor = (deviceId: string) => {
+    if (scopeDevice === deviceId) {
+      setScopeDevice(null);
+      return;
+    }
+    setScopeDevice(deviceId);
+    fetchCategories();
+    loadScope(deviceId);
+  };
+
+  const toggleExcludedCategory = (deviceId: string, categoryId: string) => {
+    const excluded = scopes[deviceId] ?? [];
+    setScope(deviceId, excluded.includes(categoryId)
+      ? excluded.filter(id => id !== categoryId)
+      : [...excluded, categoryId]);
+  };
+
+  const deviceName = (deviceId: string) =>
+    devices.find(device => device.id === deviceId)?.name ?? deviceId.slice(0, 8);
+
+  const formatBytes = (bytes: number) => {
+    if (bytes < 1024) return `${bytes} B`;
+    if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+    return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+  };
+
+  const saveAllowedNetworks = () => {
+    if (networksDraft === null) return;
+    const allowedNetworks = networksDraft.split(',').map(n => n.trim()).filter(n => n.length > 0);
+    setNetworksDraft(null);
+    updateConfig({ allowedNetworks });
+  };
+
+  // Las contraseñas van después: se guardan junto con el resto de la configuración
+  const saveCloud = async () => {
+    const draft = cloudDraft;
+    const secrets = secretDraft;
+    setCloudDraft({});
+    setSecretDraft({});
+    if (Object.keys(draft).length > 0) {
+      await updateConfig(draft);
+    }
+    for (const [field, secret] of Object.entries(secrets)) {
+      if (secret) {
+        await setCloudSecret(field as CloudSecret, secret);
+      }
+    }
+  };
+
+  const inputClass = 'block w-full px-3 py-2 text-base border-gray-300 dark:border-gray-600 focus:outline-none focus:ring-blue-500 focus:border-blue-500 sm:text-sm rounded-md dark:bg-gray-600 dark:text-white';
+
+  const cloudInput = (field: CloudField, placeholder: string) => (
+    <input
+      type="text"
+      value={cloudDraft[field] ?? config[field]}
+      onChange={(e) => setCloudDraft(draft => ({ ...draft, [field]: e.target.value }))}
+      onBlur={saveCloud}
+      placeholder={placeholder}
+      className={inputClass}
+    />
+  );
+
+  const secretInput = (field: CloudSecret, placeholder: string) => (
+    <input
+      type="password"
+      value={secretDraft[field] ?? ''}
+      onChange={(e) => setSecretDraft(draft => ({ ...draft, [field]: e.target.value }))}
+      onBlur={saveCloud}
+      placeholder={`${placeholder} (se conserva la guardada si lo dejas vacío)`}
+      autoComplete="new-password"
+      className={inputClass}
+    />
+  );
+
+  const cloudAccount = (provider: CloudProvider, name: string) => {
+    const connected = provider === 'google_drive' ? cloudAccounts.googleDrive : cloudAccounts.dropbox;
+    return (
+      <div className="flex items-center justify-between">
+        <span className="text-sm text-gray-700 dark:text-gray-300">
+          {connected ? `Cuenta de ${name} conectada` : `Sin cuenta de ${name}`}
+        </span>
+        {connected ? (
+          <button
+            onClick={() => disconnectCloudAccount(provider)}
+            className="px-3 py-1 text-sm rounded-md bg-gray-200 text-gray-700 hover:bg-gray-300 dark:bg-gray-600 dark:text-gray-200 dark:hover:bg-gray-500"
+          >
+            Desconectar
+          </button>
+        ) : (
+          <button
+            onClick={() => connectCloudAccount(provider)}
+            disabled={connectingAccount !== null}
+            className="px-3 py-1 text-sm rounded-md bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-50"
+          >
+            {connectingAccount === provider ? 'Esperando al navegador…' : 'Conectar'}
+          </button>
+        )}
+      </div>
+    );
+  };
+
+  const directionLabel: Record<SyncDirection, string> = {
+    outgoing: 'Hacia',
+    incoming: 'Desde',
+    cloud: 'Por la nube con',
+  };
+
   const tabs = [
     { id: 'overview', name: 'Resumen', icon: Wifi },
     { id: 'devices', name: 'Dispositivos', icon: Monitor },
+    { id: 'shared', name: 'Compartidos', icon: Link2 },
+    { id: 'history', name: 'Historial', icon: History },
     { id: 'settings', name: 'Configuración', icon: Settings },
   ];
 
@@ -87,10 +375,12 @@ const SyncPage: React.FC = () => {
           <div className="flex items-center justify-between">
             <div className="flex items-center space-x-4">
               <div className={`w-3 h-3 rounded-full ${
-                status.isEnabled ? 'bg-green-500' : 'bg-gray-400'
+                !status.isEnabled ? 'bg-gray-400' : status.isPaused ? 'bg-yellow-500' : 'bg-green-500'
               }`} />
               <span className="text-sm font-medium text-gray-900 dark:text-white">
-                {status.isEnabled ? 'Sincronización activa' : 'Sincronización desactivada'}
+                {!status.isEnabled
+                  ? 'Sincronización desactivada'
+                  : status.isPaused ? 'Sincronización en pausa' : 'Sincronización activa'}
               </span>
             </div>
             
@@ -103,7 +393,7 @@ const SyncPage: React.FC = () => {
               </div>
               <div className="flex items-center space-x-2">
                 <Wifi className="w-4 h-4" />
-                <span>{status.connectedDevices} dispositivos</span>
+                <span>{status.connectedDevices.length} dispositivos</span>
               </div>
             </div>
 
@@ -130,6 +420,25 @@ const SyncPage: React.FC = () => {
                 {status.isSyncing ? 'Sincronizando...' : 'Sincronizar'}
               </button>
 
+              {status.isEnabled && (
+                <button
+                  onClick={togglePause}
+                  className="inline-flex items-center px-3 py-2 border border-gray-300 dark:border-gray-600 shadow-sm text-sm leading-4 font-medium rounded-md text-gray-700 dark:text-gray-300 bg-white dark:bg-gray-700 hover:bg-gray-50 dark:hover:bg-gray-600 focus:outline-none focus:ring-2 focus:ring-offset-2 focus:ring-blue-500"
+                >
+                  {status.isPaused ? (
+                    <>
+                      <Play className="w-4 h-4 mr-2" />
+                      Reanudar
+                    </>
+                  ) : (
+                    <>
+                      <Pause className="w-4 h-4 mr-2" />
+                      Pausar
+                    </>
+                  )}
+                </button>
+              )}
+
               <button
                 onClick={toggleSync}
                 className={`inline-flex items-center px-3 py-2 border border-transparent text-sm leading-4 font-medium rounded-md ${
@@ -177,6 +486,62 @@ const SyncPage: React.FC = () => {
           </div>
         )}
 
+        {/* Red no permitida */}
+        {status.networkRestriction && (
+          <div className="bg-yellow-50 dark:bg-yellow-900/20 border border-yellow-200 dark:border-yellow-800 rounded-lg p-4 mb-8">
+            <div className="flex">
+              <Wifi className="w-5 h-5 text-yellow-500 mr-3" />
+              <div className="flex-1">
+                <h3 className="text-sm font-medium text-yellow-800 dark:text-yellow-200">
+                  Sincronización en pausa
+                </h3>
+                <p className="mt-1 text-sm text-yellow-700 dark:text-yellow-300">
+                  {describeRestriction(status.networkRestriction)}
+                </p>
+              </div>
+            </div>
+          </div>
+        )}
+
+        {/* Límite de dispositivos: hay que quitar uno para agregar otro */}
+        {deviceLimit !== null && (
+          <div className="bg-yellow-50 dark:bg-yellow-900/20 border border-yellow-200 dark:border-yellow-800 rounded-lg p-4 mb-8">
+            <div className="flex">
+              <Shield className="w-5 h-5 text-yellow-500 mr-3" />
+              <div className="flex-1">
+                <h3 className="text-sm font-medium text-yellow-800 dark:text-yellow-200">
+                  Límite de {deviceLimit} dispositivos alcanzado
+                </h3>
+                <p className="mt-1 text-sm text-yellow-700 dark:text-yellow-300">
+                  Quita uno de tus dispositivos de confianza y vuelve a intentar el emparejamiento.
+                </p>
+                <ul className="mt-3 space-y-2">
+                  {devices.filter(device => device.isTrusted).map(device => (
+                    <li key={device.id} className="flex items-center justify-between text-sm text-yellow-800 dark:text-yellow-200">
+                      <span className="flex items-center">
+                        {getDeviceIcon(device.deviceType)}
+                        <span className="ml-2">{device.name}</span>
+                      </span>
+                      <button
+                        onClick={() => removeDevice(device.id)}
+                        className="px-3 py-1 text-xs font-medium rounded-md text-red-700 bg-red-100 hover:bg-red-200 dark:bg-red-900/40 dark:text-red-300"
+                      >
+                        Quitar
+                      </button>
+                    </li>
+                  ))}
+                </ul>
+              </div>
+              <button
+                onClick={dismissDeviceLimit}
+                className="text-yellow-400 hover:text-yellow-600 dark:hover:text-yellow-300"
+              >
+                <XCircle className="w-5 h-5" />
+              </button>
+            </div>
+          </div>
+        )}
+
         {/* Tabs */}
         <div className="bg-white dark:bg-gray-800 rounded-lg shadow-sm border border-gray-200 dark:border-gray-700">
           <div className="border-b border-gray-200 dark:border-gray-700">
@@ -302,6 +667,128 @@ const SyncPage: React.FC = () => {
                   </button>
                 </div>
 
+                <div className="bg-white dark:bg-gray-700 shadow sm:rounded-lg p-4 space-y-4">
+                  <div className="flex items-center justify-between">
+                    <p className="text-sm text-gray-600 dark:text-gray-300">
+                      Empareja otro dispositivo escaneando un código QR
+                    </p>
+                    <button
+                      onClick={showPairingQr}
+                      className="inline-flex items-center px-3 py-1 border border-gray-300 dark:border-gray-600 text-xs font-medium rounded text-gray-700 dark:text-gray-300 bg-white dark:bg-gray-700 hover:bg-gray-50 dark:hover:bg-gray-600"
+                    >
+                      <QrCode className="w-4 h-4 mr-1" />
+                      Mostrar código QR
+                    </button>
+                  </div>
+                  {pairingQr && (
+                    <div className="flex flex-col items-center space-y-2">
+                      <img
+                        src={`data:image/svg+xml;utf8,${encodeURIComponent(pairingQr.svg)}`}
+                        alt="Código QR de emparejamiento"
+                        className="w-60 h-60 bg-white p-2 rounded"
+                      />
+                      <p className="text-xs text-gray-500 dark:text-gray-400">
+                        Vale para un solo dispositivo durante {Math.round(pairingQr.expiresIn / 60)} minutos
+                      </p>
+                    </div>
+                  )}
+                  <div className="flex space-x-2">
+                    <input
+                      type="text"
+                      value={scannedPayload}
+                      onChange={(e) => setScannedPayload(e.target.value)}
+                      placeholder="alohopass://pair?..."
+                      className="flex-1 rounded-md border-gray-300 dark:border-gray-600 dark:bg-gray-800 dark:text-white text-sm font-mono"
+                    />
+                    <button
+                      onClick={submitScannedPayload}
+                      disabled={!scannedPayload.trim()}
+                      className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-green-700 bg-green-100 hover:bg-green-200 dark:text-green-200 dark:bg-green-900 dark:hover:bg-green-800"
+                    >
+                      Emparejar con código
+                    </button>
+                  </div>
+                </div>
+
+                {pairings.map((pairing) => (
+                  <div
+                    key={pairing.deviceId}
+                    className="bg-blue-50 dark:bg-blue-900/20 border border-blue-200 dark:border-blue-800 rounded-md p-4"
+                  >
+                    <p className="text-sm font-medium text-gray-900 dark:text-white">
+                      {pairing.role === 'initiator' ? 'Emparejando con ' : 'Solicitud de emparejamiento de '}
+                      {devices.find(device => device.id === pairing.deviceId)?.name ?? pairing.deviceId}
+                    </p>
+                    {pairing.code ? (
+                      <>
+                        <p className="mt-1 text-sm text-gray-600 dark:text-gray-300">
+                          Comprueba que el otro dispositivo muestra el mismo código
+                        </p>
+                        <div className="mt-3 flex items-center space-x-4">
+                          <span className="text-2xl font-mono tracking-widest text-gray-900 dark:text-white">
+                            {pairing.code.digits}
+                          </span>
+                          <span className="text-2xl">{pairing.code.emoji.join(' ')}</span>
+                        </div>
+                        <div className="mt-3 flex space-x-2">
+                          <button
+                            onClick={() => confirmPairing(pairing.deviceId, true)}
+                            className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-green-700 bg-green-100 hover:bg-green-200 dark:text-green-200 dark:bg-green-900 dark:hover:bg-green-800"
+                          >
+                            Coinciden
+                          </button>
+                          <button
+                            onClick={() => confirmPairing(pairing.deviceId, false)}
+                            className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-red-700 bg-red-100 hover:bg-red-200 dark:text-red-200 dark:bg-red-900 dark:hover:bg-red-800"
+                          >
+                            No coinciden
+                          </button>
+                        </div>
+                      </>
+                    ) : (
+                      <div className="mt-2 flex items-center space-x-3">
+                        <p className="text-sm text-gray-600 dark:text-gray-300">
+                          Esperando respuesta del otro dispositivo...
+                        </p>
+                        <button
+                          onClick={() => confirmPairing(pairing.deviceId, false)}
+                          className="text-xs font-medium text-red-600 hover:text-red-800 dark:text-red-400"
+                        >
+                          Cancelar
+                        </button>
+                      </div>
+                    )}
+                  </div>
+                ))}
+
+                {incomingEntries.map((incoming) => (
+                  <div
+                    key={incoming.id}
+                    className="bg-green-50 dark:bg-green-900/20 border border-green-200 dark:border-green-800 rounded-md p-4"
+                  >
+                    <p className="text-sm font-medium text-gray-900 dark:text-white">
+                      {incoming.deviceName ?? incoming.from} te envió una entrada
+                    </p>
+                    <p className="mt-1 text-sm text-gray-600 dark:text-gray-300">
+                      {incoming.title}{incoming.username && ` · ${incoming.username}`}{incoming.url && ` · ${incoming.url}`}
+                    </p>
+                    <div className="mt-3 flex space-x-2">
+                      <button
+                        onClick={() => respondToIncomingEntry(incoming.id, true)}
+                        className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-green-700 bg-green-100 hover:bg-green-200 dark:text-green-200 dark:bg-green-900 dark:hover:bg-green-800"
+                      >
+                        Guardar
+                      </button>
+                      <button
+                        onClick={() => respondToIncomingEntry(incoming.id, false)}
+                        className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-red-700 bg-red-100 hover:bg-red-200 dark:text-red-200 dark:bg-red-900 dark:hover:bg-red-800"
+                      >
+                        Rechazar
+                      </button>
+                    </div>
+                  </div>
+                ))}
+
                 {devices.length === 0 ? (
                   <div className="text-center py-12">
                     <Wifi className="mx-auto h-12 w-12 text-gray-400" />
@@ -320,7 +807,7 @@ const SyncPage: React.FC = () => {
                           <div className="px-4 py-4 flex items-center justify-between sm:px-6">
                             <div className="flex items-center">
                               <div className="flex-shrink-0">
-                                {getDeviceIcon(device.type)}
+                                {getDeviceIcon(device.deviceType)}
                               </div>
                               <div className="ml-4">
                                 <div className="flex items-center">
@@ -333,22 +820,59 @@ const SyncPage: React.FC = () => {
                                 </div>
                                 <div className="flex items-center mt-1">
                                   <span className="text-xs text-gray-500 dark:text-gray-400 capitalize">
-                                    {device.type}
+                                    {device.deviceType}
+                                  </span>
+                                  <span className="mx-2 text-gray-300 dark:text-gray-600">•</span>
+                                  <span className="text-xs text-gray-500 dark:text-gray-400">
+                                    {device.lastSeen ? formatLastSeen(device.lastSeen) : 'Nunca'}
                                   </span>
                                   <span className="mx-2 text-gray-300 dark:text-gray-600">•</span>
                                   <span className="text-xs text-gray-500 dark:text-gray-400">
-                                    {formatLastSeen(device.lastSeen)}
+                                    Última sincronización: {device.lastSync ? formatLastSeen(device.lastSync) : 'Nunca'}
                                   </span>
+                                  {device.ipAddress && (
+                                    <>
+                                      <span className="mx-2 text-gray-300 dark:text-gray-600">•</span>
+                                      <span className="text-xs text-gray-500 dark:text-gray-400 font-mono">
+                                        {device.port ? `${device.ipAddress}:${device.port}` : device.ipAddress}
+                                      </span>
+                                    </>
+                                  )}
                                 </div>
+                                {device.incompatibility && (
+                                  <div className="flex items-center mt-1 text-xs text-yellow-700 dark:text-yellow-300">
+                                    <AlertTriangle className="w-3 h-3 mr-1 flex-shrink-0" />
+                                    {describeIncompatibility(device.incompatibility)}
+                                  </div>
+                                )}
                               </div>
                             </div>
                             <div className="flex items-center space-x-2">
+                              {device.isTrusted && (
+                                <button
+                                  onClick={() => toggleSendEntry(device.id)}
+                                  className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-gray-700 bg-gray-100 hover:bg-gray-200 dark:text-gray-200 dark:bg-gray-600 dark:hover:bg-gray-500"
+                                >
+                                  <Send className="w-3 h-3 mr-1" />
+                                  Enviar entrada
+                                </button>
+                              )}
+                              {device.isTrusted && (
+                                <button
+                                  onClick={() => toggleScopeEditor(device.id)}
+                                  className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-gray-700 bg-gray-100 hover:bg-gray-200 dark:text-gray-200 dark:bg-gray-600 dark:hover:bg-gray-500"
+                                >
+                                  <FolderMinus className="w-3 h-3 mr-1" />
+                                  Categorías
+                                </button>
+                              )}
                               {!device.isTrusted && (
                                 <button
-                                  onClick={() => trustDevice(device.id)}
+                                  onClick={() => beginPairing(device.id)}
+                                  disabled={pairings.some(p => p.deviceId === device.id) || device.incompatibility !== null}
                                   className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-green-700 bg-green-100 hover:bg-green-200 dark:text-green-200 dark:bg-green-900 dark:hover:bg-green-800"
                                 >
-                                  Confiar
+                                  Emparejar
                                 </button>
                               )}
                               <button
@@ -359,6 +883,222 @@ const SyncPage: React.FC = () => {
                               </button>
                             </div>
                           </div>
+                          {sendDevice === device.id && (
+                            <div className="px-4 pb-4 sm:px-6">
+                              <p className="text-xs text-gray-500 dark:text-gray-400 mb-2">
+                                Se manda solo esta entrada; el otro dispositivo decide si la guarda
+                              </p>
+                              <div className="flex space-x-2">
+                                <select
+                                  value={sendEntryId}
+                                  onChange={(e) => setSendEntryId(e.target.value)}
+                                  className="flex-1 rounded-md border-gray-300 dark:border-gray-600 dark:bg-gray-800 dark:text-white text-sm"
+                                >
+                                  <option value="">Elige una entrada</option>
+                                  {passwords.map(entry => (
+                                    <option key={entry.id} value={entry.id}>
+                                      {entry.username ? `${entry.title} (${entry.username})` : entry.title}
+                                    </option>
+                                  ))}
+                                </select>
+                                <button
+                                  onClick={() => submitSendEntry(device.id)}
+                                  disabled={!sendEntryId}
+                                  className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-green-700 bg-green-100 hover:bg-green-200 dark:text-green-200 dark:bg-green-900 dark:hover:bg-green-800"
+                                >
+                                  Enviar
+                                </button>
+                              </div>
+                            </div>
+                          )}
+                          {scopeDevice === device.id && (
+                            <div className="px-4 pb-4 sm:px-6">
+                              <p className="text-xs text-gray-500 dark:text-gray-400 mb-2">
+                                Las categorías sin marcar, con sus subcategorías, no se sincronizan con este dispositivo
+                              </p>
+                              {categories.length === 0 ? (
+                                <p className="text-sm text-gray-500 dark:text-gray-400">No hay categorías</p>
+                              ) : (
+                                <div className="grid grid-cols-2 gap-2">
+                                  {categories.map(category => (
+                                    <label key={category.id} className="flex items-center text-sm text-gray-900 dark:text-white">
+                                      <input
+                                        type="checkbox"
+                                        checked={!(scopes[device.id] ?? []).includes(category.id)}
+                                        onChange={() => toggleExcludedCategory(device.id, category.id)}
+                                        className="mr-2 h-4 w-4 text-blue-600 focus:ring-blue-500 border-gray-300 rounded"
+                                      />
+                                      {category.name}
+                                    </label>
+                                  ))}
+                                </div>
+                              )}
+                            </div>
+                          )}
+                        </li>
+                      ))}
+                    </ul>
+                  </div>
+                )}
+              </div>
+            )}
+
+            {/* History Tab */}
+            {activeTab === 'history' && (
+              <div className="space-y-6">
+                <div className="flex justify-between items-center">
+                  <h3 className="text-lg font-medium text-gray-900 dark:text-white">
+                    Historial ({history?.total ?? 0})
+                  </h3>
+                  <label className="flex items-center text-sm text-gray-600 dark:text-gray-400">
+                    <input
+                      type="checkbox"
+                      checked={failedOnly}
+                      onChange={(e) => {
+                        setFailedOnly(e.target.checked);
+                        setHistoryOffset(0);
+                      }}
+                      className="mr-2 h-4 w-4 text-blue-600 focus:ring-blue-500 border-gray-300 rounded"
+                    />
+                    Solo las que fallaron
+                  </label>
+                </div>
+
+                {!history || history.records.length === 0 ? (
+                  <div className="text-center py-12">
+                    <History className="mx-auto h-12 w-12 text-gray-400" />
+                    <p className="mt-2 text-sm text-gray-500 dark:text-gray-400">
+                      Todavía no hay sincronizaciones registradas
+                    </p>
+                  </div>
+                ) : (
+                  <div className="bg-white dark:bg-gray-700 shadow overflow-hidden sm:rounded-md">
+                    <ul className="divide-y divide-gray-200 dark:divide-gray-600">
+                      {history.records.map((record) => (
+                        <li key={record.id} className="px-4 py-4 sm:px-6">
+                          <div className="flex items-center justify-between">
+                            <div className="flex items-center">
+                              {record.error
+                                ? <XCircle className="w-5 h-5 text-red-500" />
+                                : <CheckCircle className="w-5 h-5 text-green-500" />}
+                              <div className="ml-4">
+                                <p className="text-sm font-medium text-gray-900 dark:text-white">
+                                  {directionLabel[record.direction]} {deviceName(record.deviceId)}
+                                </p>
+                                <p className="text-sm text-gray-500 dark:text-gray-400">
+                                  {record.error ?? `${record.itemsSent} enviados, ${record.itemsReceived} recibidos · ${formatBytes(record.bytesSent + record.bytesReceived)}`}
+                                </p>
+                              </div>
+                            </div>
+                            <div className="text-right text-sm text-gray-500 dark:text-gray-400">
+                              <p>{new Date(record.startedAt).toLocaleString()}</p>
+                              <p>{record.durationMs} ms</p>
+                            </div>
+                          </div>
+                        </li>
+                      ))}
+                    </ul>
+                  </div>
+                )}
+
+                {history && history.total > HISTORY_PAGE_SIZE && (
+                  <div className="flex justify-between items-center text-sm text-gray-600 dark:text-gray-400">
+                    <button
+                      onClick={() => setHistoryOffset(Math.max(0, historyOffset - HISTORY_PAGE_SIZE))}
+                      disabled={historyOffset === 0}
+                      className="px-3 py-1 border border-gray-300 dark:border-gray-600 rounded-md disabled:opacity-50"
+                    >
+                      Más recientes
+                    </button>
+                    <span>
+                      {historyOffset + 1}–{Math.min(historyOffset + HISTORY_PAGE_SIZE, history.total)} de {history.total}
+                    </span>
+                    <button
+                      onClick={() => setHistoryOffset(historyOffset + HISTORY_PAGE_SIZE)}
+                      disabled={historyOffset + HISTORY_PAGE_SIZE >= history.total}
+                      className="px-3 py-1 border border-gray-300 dark:border-gray-600 rounded-md disabled:opacity-50"
+                    >
+                      Más antiguas
+                    </button>
+                  </div>
+                )}
+              </div>
+            )}
+
+            {/* Shared Links Tab */}
+            {activeTab === 'shared' && (
+              <div className="space-y-6">
+                <div className="bg-white dark:bg-gray-700 shadow sm:rounded-lg p-4 space-y-3">
+                  <p className="text-sm text-gray-600 dark:text-gray-300">
+                    Abre una entrada que te compartieron con un enlace
+                  </p>
+                  <div className="flex space-x-2">
+                    <input
+                      type="text"
+                      value={sharedLinkDraft}
+                      onChange={(e) => setSharedLinkDraft(e.target.value)}
+                      placeholder="https://.../v1/share/...#..."
+                      className="flex-1 rounded-md border-gray-300 dark:border-gray-600 dark:bg-gray-800 dark:text-white text-sm font-mono"
+                    />
+                    <button
+                      onClick={submitSharedLink}
+                      disabled={!sharedLinkDraft.trim()}
+                      className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-green-700 bg-green-100 hover:bg-green-200 dark:text-green-200 dark:bg-green-900 dark:hover:bg-green-800"
+                    >
+                      Abrir
+                    </button>
+                  </div>
+                  {openedEntry && (
+                    <div className="border-t border-gray-200 dark:border-gray-600 pt-3 space-y-1 text-sm text-gray-900 dark:text-white">
+                      <p className="font-medium">{openedEntry.title}</p>
+                      <p>Usuario: {openedEntry.username}</p>
+                      <p className="font-mono">Contraseña: {openedEntry.password}</p>
+                      {openedEntry.url && <p>URL: {openedEntry.url}</p>}
+                      {openedEntry.notes && <p className="text-gray-600 dark:text-gray-300">{openedEntry.notes}</p>}
+                      <div className="pt-2 flex space-x-2">
+                        <button
+                          onClick={saveOpenedEntry}
+                          className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-green-700 bg-green-100 hover:bg-green-200 dark:text-green-200 dark:bg-green-900 dark:hover:bg-green-800"
+                        >
+                          Guardar en la bóveda
+                        </button>
+                        <button
+                          onClick={() => setOpenedEntry(null)}
+                          className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-gray-700 bg-gray-100 hover:bg-gray-200 dark:text-gray-200 dark:bg-gray-600 dark:hover:bg-gray-500"
+                        >
+                          Descartar
+                        </button>
+                      </div>
+                    </div>
+                  )}
+                </div>
+
+                <h3 className="text-lg font-medium text-gray-900 dark:text-white">
+                  Enlaces activos ({sharedLinks.length})
+                </h3>
+                {sharedLinks.length === 0 ? (
+                  <p className="text-sm text-gray-500 dark:text-gray-400">
+                    Comparte una entrada con un enlace desde la lista de contraseñas
+                  </p>
+                ) : (
+                  <div className="bg-white dark:bg-gray-700 shadow overflow-hidden sm:rounded-md">
+                    <ul className="divide-y divide-gray-200 dark:divide-gray-600">
+                      {sharedLinks.map((link) => (
+                        <li key={link.id} className="px-4 py-4 flex items-center justify-between sm:px-6">
+                          <div>
+                            <p className="text-sm font-medium text-gray-900 dark:text-white">
+                              {passwords.find(entry => entry.id === link.entryId)?.title ?? 'Entrada eliminada'}
+                            </p>
+                            <p className="text-xs text-gray-500 dark:text-gray-400">
+                              Caduca: {new Date(link.expiresAt).toLocaleString()} • Hasta {link.maxViews} {link.maxViews === 1 ? 'vista' : 'vistas'}
+                            </p>
+                          </div>
+                          <button
+                            onClick={() => revokeSharedLink(link.id)}
+                            className="inline-flex items-center px-3 py-1 border border-transparent text-xs font-medium rounded text-red-700 bg-red-100 hover:bg-red-200 dark:text-red-200 dark:bg-red-900 dark:hover:bg-red-800"
+                          >
+                            Revocar
+                          </button>
                         </li>
                       ))}
                     </ul>
@@ -439,6 +1179,56 @@ const SyncPage: React.FC = () => {
                         </button>
                       </div>
 
+                      <div className="space-y-2">
+                        <label className="block text-sm font-medium text-gray-900 dark:text-white">
+                          Servidor TURN
+                        </label>
+                        {cloudInput('turnUrl', 'turn:turn.ejemplo.com:3478')}
+                        {cloudInput('turnUsername', 'Usuario')}
+                        {secretInput('turnCredential', 'Credencial')}
+                        <p className="text-sm text-gray-500 dark:text-gray-400">
+                          Para redes que no dejan conectar directo entre dispositivos. La conexión pasa por el servidor, pero sigue encriptada de punta a punta
+                        </p>
+                      </div>
+
+                      <div className="flex items-center justify-between">
+                        <div>
+                          <label className="text-sm font-medium text-gray-900 dark:text-white">
+                            Solo por Wi-Fi
+                          </label>
+                          <p className="text-sm text-gray-500 dark:text-gray-400">
+                            No sincroniza ni busca dispositivos con otras conexiones
+                          </p>
+                        </div>
+                        <button
+                          onClick={() => updateConfig({ wifiOnly: !config.wifiOnly })}
+                          className={`relative inline-flex h-6 w-11 flex-shrink-0 cursor-pointer rounded-full border-2 border-transparent transition-colors duration-200 ease-in-out focus:outline-none focus:ring-2 focus:ring-blue-500 focus:ring-offset-2 ${
+                            config.wifiOnly ? 'bg-blue-600' : 'bg-gray-200 dark:bg-gray-600'
+                          }`}
+                        >
+                          <span className={`pointer-events-none inline-block h-5 w-5 transform rounded-full bg-white shadow ring-0 transition duration-200 ease-in-out ${
+                            config.wifiOnly ? 'translate-x-5' : 'translate-x-0'
+                          }`} />
+                        </button>
+                      </div>
+
+                      <div>
+                        <label className="block text-sm font-medium text-gray-900 dark:text-white">
+                          Redes Wi-Fi permitidas
+                        </label>
+                        <input
+                          type="text"
+                          value={networksDraft ?? config.allowedNetworks.join(', ')}
+                          onChange={(e) => setNetworksDraft(e.target.value)}
+                          onBlur={saveAllowedNetworks}
+                          placeholder="Todas las redes"
+                          className="mt-1 block w-full px-3 py-2 text-base border-gray-300 dark:border-gray-600 focus:outline-none focus:ring-blue-500 focus:border-blue-500 sm:text-sm rounded-md dark:bg-gray-600 dark:text-white"
+                        />
+                        <p className="mt-1 text-sm text-gray-500 dark:text-gray-400">
+                          Nombres separados por comas; vacío para sincronizar en cualquier red
+                        </p>
+                      </div>
+
                       <div>
                         <label className="block text-sm font-medium text-gray-900 dark:text-white">
                           Intervalo de sincronización (minutos)
@@ -455,6 +1245,122 @@ const SyncPage: React.FC = () => {
                           <option value={120}>2 horas</option>
                         </select>
                       </div>
+
+                      <div>
+                        <label className="block text-sm font-medium text-gray-900 dark:text-white">
+                          Máximo de dispositivos
+                        </label>
+                        <select
+                          value={config.maxDevices}
+                          onChange={(e) => updateConfig({ maxDevices: parseInt(e.target.value) })}
+                          className="mt-1 block w-full pl-3 pr-10 py-2 text-base border-gray-300 dark:border-gray-600 focus:outline-none focus:ring-blue-500 focus:border-blue-500 sm:text-sm rounded-md dark:bg-gray-600 dark:text-white"
+                        >
+                          <option value={2}>2 dispositivos</option>
+                          <option value={5}>5 dispositivos</option>
+                          <option value={10}>10 dispositivos</option>
+                          <option value={20}>20 dispositivos</option>
+                        </select>
+                        <p className="mt-1 text-sm text-gray-500 dark:text-gray-400">
+                          Para bajarlo por debajo de los que ya tienes, quita alguno antes
+                        </p>
+                      </div>
+
+                      <div>
+                        <label className="block text-sm font-medium text-gray-900 dark:text-white">
+                          Nivel de encriptación
+                        </label>
+                        <select
+                          value={config.encryptionLevel}
+                          onChange={(e) => updateConfig({ encryptionLevel: e.target.value as EncryptionLevel })}
+                          className="mt-1 block w-full pl-3 pr-10 py-2 text-base border-gray-300 dark:border-gray-600 focus:outline-none focus:ring-blue-500 focus:border-blue-500 sm:text-sm rounded-md dark:bg-gray-600 dark:text-white"
+                        >
+                          <option value="standard">Estándar (ChaCha20-Poly1305)</option>
+                          <option value="military">Militar (AES-256-GCM + ChaCha20-Poly1305)</option>
+                        </select>
+                        <p className="mt-1 text-sm text-gray-500 dark:text-gray-400">
+                          Los dispositivos con un nivel menor no podrán sincronizar con este
+                        </p>
+                      </div>
+
+                      <div>
+                        <label className="block text-sm font-medium text-gray-900 dark:text-white">
+                          Método de sincronización
+                        </label>
+                        <select
+                          value={config.method}
+                          onChange={(e) => updateConfig({ method: e.target.value as SyncMethod })}
+                          className="mt-1 block w-full pl-3 pr-10 py-2 text-base border-gray-300 dark:border-gray-600 focus:outline-none focus:ring-blue-500 focus:border-blue-500 sm:text-sm rounded-md dark:bg-gray-600 dark:text-white"
+                        >
+                          <option value="hybrid">Híbrido (directo y, si no hay nadie a la vista, por la nube)</option>
+                          <option value="p2p">Solo directo (P2P)</option>
+                          <option value="cloud_encrypted">Solo por la nube</option>
+                          <option value="local_only">Sin sincronizar</option>
+                        </select>
+                        <p className="mt-1 text-sm text-gray-500 dark:text-gray-400">
+                          Por la nube los lotes viajan encriptados con la clave de cada emparejamiento: el servidor no puede leerlos
+                        </p>
+                      </div>
+
+                      {(config.method === 'hybrid' || config.method === 'cloud_encrypted') && (
+                        <div className="space-y-3">
+                          <label className="block text-sm font-medium text-gray-900 dark:text-white">
+                            Buzón en la nube
+                          </label>
+                          <select
+                            value={config.cloudBackend}
+                            onChange={(e) => updateConfig({ cloudBackend: e.target.value as CloudBackend })}
+                            className="block w-full pl-3 pr-10 py-2 text-base border-gray-300 dark:border-gray-600 focus:outline-none focus:ring-blue-500 focus:border-blue-500 sm:text-sm rounded-md dark:bg-gray-600 dark:text-white"
+                          >
+                            <option value="webdav">Carpeta WebDAV (Nextcloud, ownCloud…)</option>
+                            <option value="s3">Bucket S3 (AWS, MinIO, Backblaze B2…)</option>
+                            <option value="google_drive">Google Drive</option>
+                            <option value="dropbox">Dropbox</option>
+                            <option value="relay">Relay propio (alohopass-relay)</option>
+                          </select>
+                          {config.cloudBackend === 'webdav' && (
+                            <>
+                              {cloudInput('webdavUrl', 'https://nube.ejemplo.com/remote.php/dav/files/usuario/Alohopass')}
+                              {cloudInput('webdavUsername', 'Usuario')}
+                              {secretInput('webdavPassword', 'Contraseña')}
+                              <p className="text-sm text-gray-500 dark:text-gray-400">
+                                Una carpeta de Nextcloud u otro servidor WebDAV. Usa una contraseña de aplicación si tu servidor lo permite
+                              </p>
+                            </>
+                          )}
+                          {config.cloudBackend === 's3' && (
+                            <>
+                              {cloudInput('s3Endpoint', 'https://s3.us-west-004.backblazeb2.com')}
+                              {cloudInput('s3Region', 'Región (us-east-1 si lo dejas vacío)')}
+                              {cloudInput('s3Bucket', 'Bucket')}
+                              {cloudInput('s3Prefix', 'Carpeta dentro del bucket (opcional)')}
+                              {cloudInput('s3AccessKeyId', 'Access key ID')}
+                              {secretInput('s3SecretAccessKey', 'Clave secreta')}
+                              <p className="text-sm text-gray-500 dark:text-gray-400">
+                                Con el versionado del bucket activado, los lotes recogidos se borran versión por versión para no perder los que lleguen mientras tanto
+                              </p>
+                            </>
+                          )}
+                          {config.cloudBackend === 'relay' && (
+                            <>
+                              {cloudInput('relayUrl', 'https://relay.ejemplo.com')}
+                              {secretInput('relayToken', 'Token del relay')}
+                              <p className="text-sm text-gray-500 dark:text-gray-400">
+                                Un relay que montas tú con alohopass-relay: guarda los lotes encriptados hasta que el otro dispositivo los recoge
+                              </p>
+                            </>
+                          )}
+                          {config.cloudBackend === 'google_drive' && cloudAccount('google_drive', 'Google Drive')}
+                          {config.cloudBackend === 'dropbox' && cloudAccount('dropbox', 'Dropbox')}
+                          {(config.cloudBackend === 'google_drive' || config.cloudBackend === 'dropbox') && (
+                            <p className="text-sm text-gray-500 dark:text-gray-400">
+                              Se abre el navegador para que autorices a Alohopass. Solo accede a su propia carpeta y el token se guarda encriptado en la bóveda
+                            </p>
+                          )}
+                          <p className="text-sm text-gray-500 dark:text-gray-400">
+                            El buzón se revisa en cada sincronización automática
+                          </p>
+                        </div>
+                      )}
                     </div>
                   </div>
                 </div>
@@ -0,0 +1,64 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
+
+export type ActivityAction = 'create' | 'edit' | 'delete' | 'export' | 'reveal' | 'fill'
+
+/** Evento del registro de actividad; nunca contiene secretos */
+export interface ActivityEvent {
+  id: number
+  action: ActivityAction
+  entry_id: string | null
+  category_id: string | null
+  details: string | null
+  occurred_at: string
+}
+
+export interface ActivityLogRequest {
+  offset?: number
+  limit?: number
+  category_id?: string
+  entry_id?: string
+  action?: ActivityAction
+}
+
+export interface ActivityLogPage {
+  events: ActivityEvent[]
+  total: number
+  offset: number
+  limit?: number
+}
+
+interface ActivityState {
+  events: ActivityEvent[]
+  total: number
+  isLoading: boolean
+  error: string | null
+  
+  // Acciones
+  fetchActivity: (request?: ActivityLogRequest) => Promise<void>
+  clearError: () => void
+}
+
+export const useActivityStore = create<ActivityState>((set) => ({
+  events: [],
+  total: 0,
+  isLoading: false,
+  error: null,
+  
+  fetchActivity: async (request?: ActivityLogRequest) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const page = await invoke<ActivityLogPage>('get_activity_log', { request: request ?? null })
+      set({ events: page.events, total: page.total, isLoading: false })
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al cargar el registro de actividad')
+      set({ error: errorMessage, isLoading: false })
+    }
+  },
+  
+  clearError: () => {
+    set({ error: null })
+  },
+}))
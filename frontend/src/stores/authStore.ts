@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { persist } from 'zustand/middleware'
 import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
 
 interface AuthState {
   isAuthenticated: boolean
@@ -41,7 +42,7 @@ export const useAuthStore = create<AuthState>()(
           return true
         } catch (error) {
           console.error('❌ Frontend: Error al crear contraseña maestra:', error);
-          const errorMessage = error instanceof Error ? error.message : 'Error desconocido'
+          const errorMessage = getErrorMessage(error, 'Error desconocido')
           console.error('❌ Frontend: Mensaje de error:', errorMessage);
           set({ 
             error: errorMessage, 
@@ -78,7 +79,7 @@ export const useAuthStore = create<AuthState>()(
           }
         } catch (error) {
           console.error('❌ Frontend: Error en verifyMasterPassword:', error);
-          const errorMessage = error instanceof Error ? error.message : 'Error desconocido'
+          const errorMessage = getErrorMessage(error, 'Error desconocido')
           console.error('❌ Frontend: Mensaje de error:', errorMessage);
           set({ 
             error: errorMessage, 
@@ -96,7 +97,7 @@ export const useAuthStore = create<AuthState>()(
           set({ isLoading: false })
           return true
         } catch (error) {
-          const errorMessage = error instanceof Error ? error.message : 'Error desconocido'
+          const errorMessage = getErrorMessage(error, 'Error desconocido')
           set({ 
             error: errorMessage, 
             isLoading: false 
@@ -1,5 +1,6 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
 
 export interface AutocompleteSuggestion {
   title: string
@@ -36,7 +37,7 @@ export const useAutocompleteStore = create<AutocompleteState>((set, get) => ({
       const suggestions = await invoke('get_autocomplete_suggestions', { url })
       set({ suggestions, isLoading: false })
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al obtener sugerencias'
+      const errorMessage = getErrorMessage(error, 'Error al obtener sugerencias')
       set({ error: errorMessage, isLoading: false })
     }
   },
@@ -46,7 +47,7 @@ export const useAutocompleteStore = create<AutocompleteState>((set, get) => ({
       await invoke('save_autocomplete_data', { url, username, password })
       return true
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al guardar datos'
+      const errorMessage = getErrorMessage(error, 'Error al guardar datos')
       set({ error: errorMessage })
       return false
     }
@@ -0,0 +1,117 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
+
+export interface Category {
+  id: string
+  name: string
+  color: string
+  icon: string | null
+  parent_id: string | null
+  created_at: string
+  entry_count: number
+}
+
+export interface CategoryNode extends Category {
+  total_count: number
+  children: CategoryNode[]
+}
+
+export type CategoryRequest = Pick<Category, 'name' | 'color' | 'icon' | 'parent_id'>
+
+interface CategoryState {
+  categories: Category[]
+  tree: CategoryNode[]
+  isLoading: boolean
+  error: string | null
+  
+  // Acciones
+  fetchCategories: () => Promise<void>
+  createCategory: (request: CategoryRequest) => Promise<boolean>
+  updateCategory: (id: string, request: CategoryRequest) => Promise<boolean>
+  deleteCategory: (id: string, reassignTo?: string | null) => Promise<boolean>
+  moveCategory: (id: string, parentId: string | null) => Promise<boolean>
+  moveEntries: (entryIds: string[], categoryId: string | null) => Promise<boolean>
+  clearError: () => void
+}
+
+export const useCategoryStore = create<CategoryState>((set, get) => ({
+  categories: [],
+  tree: [],
+  isLoading: false,
+  error: null,
+  
+  fetchCategories: async () => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const [categories, tree] = await Promise.all([
+        invoke<Category[]>('get_categories'),
+        invoke<CategoryNode[]>('get_category_tree'),
+      ])
+      set({ categories, tree, isLoading: false })
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al obtener las categorías')
+      set({ error: errorMessage, isLoading: false })
+    }
+  },
+  
+  createCategory: async (request: CategoryRequest) => {
+    try {
+      await invoke('create_category', { request })
+      await get().fetchCategories()
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al crear la categoría') })
+      return false
+    }
+  },
+  
+  updateCategory: async (id: string, request: CategoryRequest) => {
+    try {
+      await invoke('update_category', { id, request })
+      await get().fetchCategories()
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al actualizar la categoría') })
+      return false
+    }
+  },
+  
+  deleteCategory: async (id: string, reassignTo?: string | null) => {
+    try {
+      await invoke('delete_category', { id, reassignTo: reassignTo ?? null })
+      await get().fetchCategories()
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al eliminar la categoría') })
+      return false
+    }
+  },
+  
+  moveCategory: async (id: string, parentId: string | null) => {
+    try {
+      await invoke('move_category', { id, parentId })
+      await get().fetchCategories()
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al mover la categoría') })
+      return false
+    }
+  },
+  
+  moveEntries: async (entryIds: string[], categoryId: string | null) => {
+    try {
+      await invoke<number>('move_entries_to_category', { entryIds, categoryId })
+      await get().fetchCategories()
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al mover las entradas') })
+      return false
+    }
+  },
+  
+  clearError: () => {
+    set({ error: null })
+  },
+}))
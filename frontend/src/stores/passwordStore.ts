@@ -1,5 +1,6 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
 
 export interface PasswordEntry {
   id: string
@@ -8,11 +9,31 @@ export interface PasswordEntry {
   password: string
   url?: string
   notes?: string
-  category_id?: string
+  categoryId?: string
   tags: string[]
-  created_at: string
-  updated_at: string
-  last_used?: string
+  createdAt: string
+  updatedAt: string
+  lastUsed?: string
+}
+
+/** Contenido que tuvo una entrada entre su updatedAt y revisedAt */
+export interface EntryRevision extends PasswordEntry {
+  revisionId: number
+  revisedAt: string
+}
+
+export interface PasswordListRequest {
+  offset?: number
+  limit?: number
+  sortBy?: 'title' | 'updated_at' | 'last_used'
+  sortDirection?: 'asc' | 'desc'
+}
+
+export interface PasswordEntriesPage {
+  entries: PasswordEntry[]
+  total: number
+  offset: number
+  limit?: number
 }
 
 export interface CreatePasswordRequest {
@@ -21,10 +42,46 @@ export interface CreatePasswordRequest {
   password: string
   url?: string
   notes?: string
-  category_id?: string
+  categoryId?: string
   tags: string[]
 }
 
+export interface CrackTime {
+  seconds: number
+  display: string
+}
+
+export type PatternKind = 'dictionary' | 'spatial' | 'repeat' | 'sequence' | 'date' | 'brute_force'
+
+export interface PatternMatch {
+  kind: PatternKind
+  token: string
+  start: number
+  end: number
+  guesses_log10: number
+  rank: number | null
+  l33t: boolean
+  reversed: boolean
+}
+
+export interface PasswordStrength {
+  score: number
+  rating: number
+  guesses_log10: number
+  entropy_bits: number
+  crack_times: {
+    online_throttled: CrackTime
+    online: CrackTime
+    offline_slow: CrackTime
+    offline_fast: CrackTime
+  }
+  patterns: PatternMatch[]
+  feedback: string[]
+  suggestions: string[]
+}
+
+export type GenerationMode = 'random' | 'pronounceable'
+
 export interface PasswordGenerationRequest {
   length: number
   include_uppercase: boolean
@@ -32,37 +89,69 @@ export interface PasswordGenerationRequest {
   include_numbers: boolean
   include_symbols: boolean
   exclude_similar: boolean
+  mode?: GenerationMode
+}
+
+export interface GeneratedPassword {
+  password: string
+  entropy_bits: number
+}
+
+export interface GenerationHistoryEntry {
+  id: number
+  password: string
+  url: string | null
+  generated_at: string
+}
+
+export interface UsernameGenerationRequest {
+  separator: string
+  capitalize: boolean
+  include_number: boolean
+}
+
+export interface GeneratedUsername {
+  username: string
+  entropy_bits: number
 }
 
 interface PasswordState {
   passwords: PasswordEntry[]
+  total: number
   isLoading: boolean
   error: string | null
   
   // Acciones
-  fetchPasswords: () => Promise<void>
+  fetchPasswords: (request?: PasswordListRequest) => Promise<void>
   createPassword: (request: CreatePasswordRequest) => Promise<string | null>
   updatePassword: (id: string, updates: Partial<CreatePasswordRequest>) => Promise<boolean>
   deletePassword: (id: string) => Promise<boolean>
-  generatePassword: (request: PasswordGenerationRequest) => Promise<string | null>
-  checkPasswordStrength: (password: string) => Promise<any>
+  fetchRevisions: (entryId: string) => Promise<EntryRevision[]>
+  restoreRevision: (entryId: string, revisionId: number) => Promise<boolean>
+  generatePassword: (request: PasswordGenerationRequest, url?: string) => Promise<GeneratedPassword | null>
+  generatePin: (length: number) => Promise<GeneratedPassword | null>
+  generateUsername: (request?: UsernameGenerationRequest) => Promise<GeneratedUsername | null>
+  fetchGenerationHistory: () => Promise<GenerationHistoryEntry[]>
+  clearGenerationHistory: () => Promise<boolean>
+  checkPasswordStrength: (password: string) => Promise<PasswordStrength | null>
   searchPasswords: (query: string) => Promise<void>
   clearError: () => void
 }
 
 export const usePasswordStore = create<PasswordState>((set, get) => ({
   passwords: [],
+  total: 0,
   isLoading: false,
   error: null,
   
-  fetchPasswords: async () => {
+  fetchPasswords: async (request?: PasswordListRequest) => {
     set({ isLoading: true, error: null })
     
     try {
-      const passwords = await invoke<PasswordEntry[]>('get_password_entries')
-      set({ passwords, isLoading: false })
+      const page = await invoke<PasswordEntriesPage>('get_password_entries', { request: request ?? null })
+      set({ passwords: page.entries, total: page.total, isLoading: false })
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al obtener contraseñas'
+      const errorMessage = getErrorMessage(error, 'Error al obtener contraseñas')
       set({ error: errorMessage, isLoading: false })
     }
   },
@@ -80,7 +169,7 @@ export const usePasswordStore = create<PasswordState>((set, get) => ({
       return id
     } catch (error) {
       console.error('❌ Frontend: Error en createPassword:', error);
-      const errorMessage = error instanceof Error ? error.message : 'Error al crear contraseña'
+      const errorMessage = getErrorMessage(error, 'Error al crear contraseña')
       console.error('❌ Frontend: Mensaje de error:', errorMessage);
       set({ error: errorMessage, isLoading: false })
       return null
@@ -98,7 +187,7 @@ export const usePasswordStore = create<PasswordState>((set, get) => ({
       set({ isLoading: false })
       return true
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al actualizar contraseña'
+      const errorMessage = getErrorMessage(error, 'Error al actualizar contraseña')
       set({ error: errorMessage, isLoading: false })
       return false
     }
@@ -113,29 +202,87 @@ export const usePasswordStore = create<PasswordState>((set, get) => ({
       set({ isLoading: false })
       return true
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al eliminar contraseña'
+      const errorMessage = getErrorMessage(error, 'Error al eliminar contraseña')
       set({ error: errorMessage, isLoading: false })
       return false
     }
   },
   
-  generatePassword: async (request: PasswordGenerationRequest) => {
+  fetchRevisions: async (entryId: string) => {
+    try {
+      return await invoke<EntryRevision[]>('get_entry_revisions', { entryId })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al cargar el historial de revisiones') })
+      return []
+    }
+  },
+  
+  restoreRevision: async (entryId: string, revisionId: number) => {
+    try {
+      await invoke('restore_entry_revision', { entryId, revisionId })
+      await get().fetchPasswords() // Recargar lista
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al restaurar la revisión') })
+      return false
+    }
+  },
+  
+  generatePassword: async (request: PasswordGenerationRequest, url?: string) => {
     try {
-      const password = await invoke<string>('generate_password', { request })
-      return password
+      // Con la URL se aplica la política de contraseñas del sitio, si hay una
+      const generated = await invoke<GeneratedPassword>('generate_password', { request, url: url || null })
+      return generated
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al generar contraseña'
+      const errorMessage = getErrorMessage(error, 'Error al generar contraseña')
       set({ error: errorMessage })
       return null
     }
   },
   
+  generatePin: async (length: number) => {
+    try {
+      return await invoke<GeneratedPassword>('generate_pin', { length })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al generar PIN') })
+      return null
+    }
+  },
+  
+  generateUsername: async (request?: UsernameGenerationRequest) => {
+    try {
+      return await invoke<GeneratedUsername>('generate_username', { request: request ?? null })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al generar nombre de usuario') })
+      return null
+    }
+  },
+  
+  fetchGenerationHistory: async () => {
+    try {
+      return await invoke<GenerationHistoryEntry[]>('get_generation_history')
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al cargar el historial de generación') })
+      return []
+    }
+  },
+  
+  clearGenerationHistory: async () => {
+    try {
+      await invoke<number>('clear_generation_history')
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al borrar el historial de generación') })
+      return false
+    }
+  },
+  
   checkPasswordStrength: async (password: string) => {
     try {
-      const strength = await invoke('check_password_strength', { password })
+      const strength = await invoke<PasswordStrength>('check_password_strength', { password })
       return strength
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al verificar fortaleza'
+      const errorMessage = getErrorMessage(error, 'Error al verificar fortaleza')
       set({ error: errorMessage })
       return null
     }
@@ -151,11 +298,11 @@ export const usePasswordStore = create<PasswordState>((set, get) => ({
     
     try {
       const results = await invoke<PasswordEntry[]>('search_passwords', { 
-        request: { query, category_id: null, tags: [] } 
+        request: { query, categoryId: null, tags: [] } 
       })
       set({ passwords: results, isLoading: false })
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error en búsqueda'
+      const errorMessage = getErrorMessage(error, 'Error en búsqueda')
       set({ error: errorMessage, isLoading: false })
     }
   },
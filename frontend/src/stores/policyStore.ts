@@ -0,0 +1,87 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
+
+export interface PasswordPolicy {
+  id: string
+  domain: string
+  min_length: number | null
+  max_length: number | null
+  forbidden_characters: string
+  require_uppercase: boolean
+  require_lowercase: boolean
+  require_numbers: boolean
+  require_symbols: boolean
+  created_at: string
+  updated_at: string
+}
+
+export type PasswordPolicyRequest = Omit<PasswordPolicy, 'id' | 'created_at' | 'updated_at'>
+
+interface PolicyState {
+  policies: PasswordPolicy[]
+  isLoading: boolean
+  error: string | null
+  
+  // Acciones
+  fetchPolicies: () => Promise<void>
+  createPolicy: (request: PasswordPolicyRequest) => Promise<boolean>
+  updatePolicy: (id: string, request: PasswordPolicyRequest) => Promise<boolean>
+  deletePolicy: (id: string) => Promise<boolean>
+  clearError: () => void
+}
+
+export const usePolicyStore = create<PolicyState>((set, get) => ({
+  policies: [],
+  isLoading: false,
+  error: null,
+  
+  fetchPolicies: async () => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const policies = await invoke<PasswordPolicy[]>('get_password_policies')
+      set({ policies, isLoading: false })
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al obtener las políticas de contraseñas')
+      set({ error: errorMessage, isLoading: false })
+    }
+  },
+  
+  createPolicy: async (request: PasswordPolicyRequest) => {
+    try {
+      await invoke<PasswordPolicy>('create_password_policy', { request })
+      await get().fetchPolicies()
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al crear la política') })
+      return false
+    }
+  },
+  
+  updatePolicy: async (id: string, request: PasswordPolicyRequest) => {
+    try {
+      await invoke('update_password_policy', { id, request })
+      await get().fetchPolicies()
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al actualizar la política') })
+      return false
+    }
+  },
+  
+  deletePolicy: async (id: string) => {
+    try {
+      await invoke('delete_password_policy', { id })
+      set({ policies: get().policies.filter(policy => policy.id !== id) })
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al eliminar la política') })
+      return false
+    }
+  },
+  
+  clearError: () => {
+    set({ error: null })
+  },
+}))
@@ -1,5 +1,6 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
 
 interface RecoveryState {
   recoveryKey: string | null
@@ -25,7 +26,7 @@ export const useRecoveryStore = create<RecoveryState>((set, get) => ({
       set({ recoveryKey: recoveryKey as string, isLoading: false })
       return recoveryKey as string
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al generar clave de recuperación'
+      const errorMessage = getErrorMessage(error, 'Error al generar clave de recuperación')
       set({ error: errorMessage, isLoading: false })
       return null
     }
@@ -42,7 +43,7 @@ export const useRecoveryStore = create<RecoveryState>((set, get) => ({
       set({ isLoading: false })
       return true
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : 'Error al restablecer contraseña'
+      const errorMessage = getErrorMessage(error, 'Error al restablecer contraseña')
       set({ error: errorMessage, isLoading: false })
       return false
     }
@@ -0,0 +1,75 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
+
+export type FindingKind = 'weak' | 'reused' | 'old' | 'breached' | 'unsecured'
+
+export interface EntryFindings {
+  entry_id: string
+  title: string
+  username: string
+  url: string | null
+  findings: FindingKind[]
+  strength_score: number
+  guesses_log10: number | null
+  reused_with: number
+  password_age_days: number
+  breach_count: number | null
+}
+
+export interface SecurityReport {
+  generated_at: string
+  total_entries: number
+  weak_count: number
+  reused_count: number
+  old_count: number
+  breached_count: number
+  unsecured_count: number
+  security_score: number
+  entries: EntryFindings[]
+}
+
+interface SecurityState {
+  report: SecurityReport | null
+  isLoading: boolean
+  error: string | null
+  
+  // Acciones
+  fetchReport: () => Promise<void>
+  runAudit: () => Promise<void>
+  clearError: () => void
+}
+
+export const useSecurityStore = create<SecurityState>((set) => ({
+  report: null,
+  isLoading: false,
+  error: null,
+  
+  fetchReport: async () => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const report = await invoke<SecurityReport | null>('get_security_report')
+      set({ report, isLoading: false })
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al obtener el informe de seguridad')
+      set({ error: errorMessage, isLoading: false })
+    }
+  },
+  
+  runAudit: async () => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const report = await invoke<SecurityReport>('run_security_audit')
+      set({ report, isLoading: false })
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al auditar la bóveda')
+      set({ error: errorMessage, isLoading: false })
+    }
+  },
+  
+  clearError: () => {
+    set({ error: null })
+  },
+}))
@@ -0,0 +1,175 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
+import { GenerationMode } from './passwordStore'
+
+export type Theme = 'system' | 'light' | 'dark'
+
+export type BreachCheckBackend = 'disabled' | 'hibp' | 'offline'
+
+export type EntryEncryption = 'fields' | 'record'
+
+export interface GeneratorDefaults {
+  length: number
+  include_uppercase: boolean
+  include_lowercase: boolean
+  include_numbers: boolean
+  include_symbols: boolean
+  exclude_similar: boolean
+  mode: GenerationMode
+}
+
+export interface BackupPreferences {
+  enabled: boolean
+  directory: string
+  interval_hours: number
+  keep: number
+}
+
+export interface MaintenancePreferences {
+  automatic: boolean
+  interval_days: number
+  backup_history_days: number
+}
+
+export interface RevisionPreferences {
+  keep_per_entry: number
+  max_age_days: number
+}
+
+export interface SyncPreferences {
+  auto_sync: boolean
+  sync_interval: number
+  discovery_enabled: boolean
+  allow_incoming_connections: boolean
+}
+
+export interface AppSettings {
+  auto_lock_minutes: number
+  clipboard_clear_seconds: number
+  theme: Theme
+  language: string
+  quick_search_shortcut: string
+  fetch_favicons: boolean
+  breach_check: BreachCheckBackend
+  generator: GeneratorDefaults
+  generation_history_size: number
+  sync: SyncPreferences
+  backup: BackupPreferences
+  maintenance: MaintenancePreferences
+  revisions: RevisionPreferences
+  entry_encryption: EntryEncryption
+}
+
+export interface BreachDatasetInfo {
+  items: number
+  size_bytes: number
+  false_positive_rate: number
+}
+
+export interface DatabaseLocation {
+  path: string
+  default_path: string
+  is_custom: boolean
+}
+
+interface SettingsState {
+  settings: AppSettings | null
+  breachDataset: BreachDatasetInfo | null
+  databaseLocation: DatabaseLocation | null
+  isLoading: boolean
+  error: string | null
+  
+  // Acciones
+  fetchSettings: () => Promise<void>
+  updateSettings: (settings: AppSettings) => Promise<boolean>
+  fetchBreachDataset: () => Promise<void>
+  importBreachDataset: (path: string) => Promise<boolean>
+  fetchDatabaseLocation: () => Promise<void>
+  moveDatabase: (directory: string | null) => Promise<boolean>
+  clearError: () => void
+}
+
+export const useSettingsStore = create<SettingsState>((set) => ({
+  settings: null,
+  breachDataset: null,
+  databaseLocation: null,
+  isLoading: false,
+  error: null,
+  
+  fetchSettings: async () => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const settings = await invoke<AppSettings>('get_settings')
+      set({ settings, isLoading: false })
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al obtener la configuración')
+      set({ error: errorMessage, isLoading: false })
+    }
+  },
+  
+  updateSettings: async (settings: AppSettings) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const saved = await invoke<AppSettings>('update_settings', { settings })
+      set({ settings: saved, isLoading: false })
+      return true
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al guardar la configuración')
+      set({ error: errorMessage, isLoading: false })
+      return false
+    }
+  },
+  
+  fetchBreachDataset: async () => {
+    try {
+      const breachDataset = await invoke<BreachDatasetInfo | null>('get_breach_dataset_info')
+      set({ breachDataset })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al leer el conjunto de datos de filtraciones') })
+    }
+  },
+  
+  importBreachDataset: async (path: string) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const breachDataset = await invoke<BreachDatasetInfo>('import_breach_dataset', { path })
+      set({ breachDataset, isLoading: false })
+      return true
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al importar el conjunto de datos de filtraciones')
+      set({ error: errorMessage, isLoading: false })
+      return false
+    }
+  },
+  
+  fetchDatabaseLocation: async () => {
+    try {
+      const databaseLocation = await invoke<DatabaseLocation>('get_database_location')
+      set({ databaseLocation })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al obtener la ubicación de la base de datos') })
+    }
+  },
+  
+  moveDatabase: async (directory: string | null) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const databaseLocation = await invoke<DatabaseLocation>('move_database', { directory })
+      set({ databaseLocation, isLoading: false })
+      return true
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al mover la base de datos')
+      set({ error: errorMessage, isLoading: false })
+      return false
+    }
+  },
+  
+  clearError: () => {
+    set({ error: null })
+  },
+}))
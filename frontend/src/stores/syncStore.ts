@@ -1,13 +1,48 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/tauri';
+import { PasswordEntry } from './passwordStore';
+import { getErrorMessage, isAppError } from '../utils/appError';
+
+export type DeviceType = 'mobile' | 'desktop' | 'laptop' | 'tablet' | 'server' | 'unknown';
+
+export type DeviceStatus = 'disconnected' | 'connected' | 'syncing' | 'waiting' | { error: string };
 
 export interface DeviceInfo {
   id: string;
   name: string;
-  type: 'desktop' | 'mobile' | 'tablet';
-  lastSeen: string;
+  deviceType: DeviceType;
+  os: string;
+  osVersion: string;
+  appVersion: string;
+  ipAddress: string | null;
+  port: number | null;
+  status: DeviceStatus;
+  lastSeen: string | null;
+  lastSync: string | null;
   isTrusted: boolean;
-  isOnline: boolean;
+  isOwner: boolean;
+  // Por qué no se puede sincronizar con él, según su versión; null si se puede
+  incompatibility: Incompatibility | null;
+}
+
+// Versiones que no pueden sincronizar entre sí
+export type Incompatibility =
+  | { kind: 'peerTooOld'; version: string; required: string }
+  | { kind: 'localTooOld'; version: string; required: string }
+  | { kind: 'invalidVersion'; version: string };
+
+// Nombre y tipo que eligió el usuario; null para tomarlos del sistema
+export interface DeviceProfile {
+  name: string | null;
+  deviceType: DeviceType | null;
+}
+
+// Este dispositivo, con el nombre y el tipo con los que se anuncia
+export interface LocalDevice {
+  deviceId: string;
+  profile: DeviceProfile;
+  name: string;
+  deviceType: DeviceType;
 }
 
 export interface SyncStatus {
@@ -15,14 +50,67 @@ export interface SyncStatus {
   isSyncing: boolean;
   lastSyncTime: string | null;
   error: string | null;
-  connectedDevices: number;
+  connectedDevices: DeviceInfo[];
+  retries: SyncRetry[];
+  networkRestriction: NetworkRestriction | null;
+  isPaused: boolean; // sigue en pausa tras reiniciar
+  lanAddress: string | null; // para agregar este dispositivo a mano desde otro
+}
+
+// Por qué la red actual no permite sincronizar
+export type NetworkRestriction =
+  | { reason: 'notWifi' }
+  | { reason: 'networkNotAllowed'; ssid: string | null }
+  | { reason: 'undetected'; message: string };
+
+export interface SyncRetry {
+  deviceId: string;
+  attempt: number;
+  maxAttempts: number;
+  nextRetryAt: string | null; // null si ya se dejó de reintentar
+  lastError: string;
+}
+
+export type EncryptionLevel = 'standard' | 'military';
+
+// hybrid: directo y, con los que no están a la vista, por el buzón WebDAV
+export type SyncMethod = 'p2p' | 'cloud_encrypted' | 'hybrid' | 'local_only';
+
+export type CloudBackend = 'webdav' | 's3' | 'google_drive' | 'dropbox' | 'relay';
+
+// Cuentas a las que se entra con OAuth; su token queda en la bóveda
+export type CloudProvider = 'google_drive' | 'dropbox';
+
+export interface CloudAccounts {
+  googleDrive: boolean;
+  dropbox: boolean;
 }
 
+// Contraseñas del buzón en la nube: se guardan encriptadas y nunca vuelven
+export type CloudSecret = 'webdavPassword' | 's3SecretAccessKey' | 'relayToken' | 'turnCredential';
+
 export interface SyncConfig {
   autoSync: boolean;
   syncInterval: number; // en minutos
   discoveryEnabled: boolean;
   allowIncomingConnections: boolean;
+  askOnConflict: boolean; // un campo editado a la vez en dos dispositivos espera una decisión
+  encryptionLevel: EncryptionLevel; // también el mínimo que se acepta de otros
+  wifiOnly: boolean;
+  allowedNetworks: string[]; // redes Wi-Fi permitidas; vacía = todas
+  maxDevices: number; // máximo de dispositivos de confianza
+  method: SyncMethod;
+  webdavUrl: string; // carpeta del buzón en la nube; vacía = sin configurar
+  webdavUsername: string;
+  cloudBackend: CloudBackend;
+  s3Endpoint: string; // vacía = sin configurar
+  s3Region: string; // vacía = us-east-1
+  s3Bucket: string;
+  s3Prefix: string; // carpeta dentro del bucket
+  s3AccessKeyId: string;
+  relayUrl: string;
+  turnUrl: string; // turn: o turns:; vacía = sin TURN
+  turnUsername: string;
 }
 
 export interface SyncStats {
@@ -30,6 +118,123 @@ export interface SyncStats {
   syncedPasswords: number;
   lastSyncDuration: number; // en segundos
   devicesCount: number;
+  totalSyncs: number;
+  successfulSyncs: number;
+  failedSyncs: number;
+}
+
+export interface PairingCode {
+  digits: string;
+  emoji: string[];
+}
+
+export interface PairingStatus {
+  deviceId: string;
+  role: 'initiator' | 'responder';
+  code: PairingCode | null; // null hasta que el otro dispositivo responde
+  verified: boolean; // llegó por código QR, no hace falta comparar
+}
+
+export interface PairingQr {
+  payload: string;
+  svg: string;
+  expiresIn: number; // en segundos
+}
+
+export type ConflictResolution = 'UseLocal' | 'UseRemote' | 'Merge' | 'CreateNew' | 'Delete';
+
+export interface SyncConflict {
+  id: string;
+  entryId: string;
+  detectedAt: string;
+  remoteDevice: string;
+  local: PasswordEntry | null; // null si aquí se eliminó
+  remote: PasswordEntry | null; // null si el otro dispositivo la eliminó
+}
+
+export interface SyncScope {
+  deviceId: string;
+  excludedCategories: string[]; // sus subcategorías tampoco se mandan
+}
+
+export type SyncDirection = 'outgoing' | 'incoming' | 'cloud';
+
+export interface SyncHistoryRecord {
+  id: number;
+  deviceId: string;
+  direction: SyncDirection; // incoming: lo empezó el otro dispositivo; cloud: por el buzón WebDAV
+  itemsSent: number;
+  itemsReceived: number;
+  bytesSent: number;
+  bytesReceived: number;
+  durationMs: number;
+  error: string | null; // null si salió bien
+  startedAt: string;
+}
+
+export interface SyncHistoryRequest {
+  offset?: number;
+  limit?: number;
+  deviceId?: string;
+  failedOnly?: boolean;
+}
+
+export interface SyncHistoryPage {
+  records: SyncHistoryRecord[];
+  total: number;
+  offset: number;
+  limit?: number;
+}
+
+// Totales de las sincronizaciones con un dispositivo, desde la primera
+export interface DeviceSyncStats {
+  deviceId: string;
+  syncs: number; // las que salieron bien
+  failures: number;
+  itemsSent: number;
+  itemsReceived: number;
+  bytesSent: number;
+  bytesReceived: number;
+  lastDurationMs: number;
+  lastSyncAt: string | null;
+  lastFailureAt: string | null;
+}
+
+// Entrada que mandó otro dispositivo y espera que la aceptes; sin la contraseña
+export interface IncomingEntry {
+  id: string;
+  from: string;
+  deviceName: string | null; // null si el dispositivo no está a la vista
+  title: string;
+  username: string;
+  url: string | null;
+  receivedAt: string;
+}
+
+// Entrada compartida por enlace desde aquí; la clave solo está en el enlace
+export interface SharedLink {
+  id: string;
+  entryId: string;
+  url: string;
+  maxViews: number;
+  createdAt: string;
+  expiresAt: string;
+}
+
+// El enlace solo se ve al crearlo
+export interface CreatedShareLink {
+  link: string;
+  share: SharedLink;
+}
+
+// Entrada que llega por un enlace compartido
+export interface SharedEntry {
+  title: string;
+  username: string;
+  password: string;
+  url: string | null;
+  notes: string | null;
+  tags: string[];
 }
 
 interface SyncStore {
@@ -38,18 +243,61 @@ interface SyncStore {
   config: SyncConfig;
   stats: SyncStats;
   devices: DeviceInfo[];
+  pairings: PairingStatus[];
+  conflicts: SyncConflict[];
+  deviceLimit: number | null; // se llegó al máximo de dispositivos al emparejar
+  history: SyncHistoryPage | null;
+  scopes: Record<string, string[]>; // categorías excluidas por dispositivo
+  deviceStats: Record<string, DeviceSyncStats>;
+  incomingEntries: IncomingEntry[];
+  sharedLinks: SharedLink[];
+  cloudAccounts: CloudAccounts;
+  connectingAccount: CloudProvider | null; // esperando la autorización en el navegador
+  localDevice: LocalDevice | null;
   
   // Acciones
   loadSyncData: () => Promise<void>;
   toggleSync: () => Promise<void>;
+  togglePause: () => Promise<void>;
   startDiscovery: () => Promise<void>;
   syncNow: () => Promise<void>;
   updateConfig: (config: Partial<SyncConfig>) => Promise<void>;
+  setCloudSecret: (field: CloudSecret, secret: string) => Promise<void>;
+  loadCloudAccounts: () => Promise<void>;
+  connectCloudAccount: (provider: CloudProvider) => Promise<void>;
+  disconnectCloudAccount: (provider: CloudProvider) => Promise<void>;
+  loadLocalDevice: () => Promise<void>;
+  updateLocalDevice: (profile: DeviceProfile) => Promise<boolean>;
+  applyDeviceUpdate: (device: DeviceInfo) => void;
   trustDevice: (deviceId: string) => Promise<void>;
+  loadPairings: () => Promise<void>;
+  beginPairing: (deviceId: string) => Promise<void>;
+  addDeviceManually: (ip: string, port: number) => Promise<boolean>;
+  confirmPairing: (deviceId: string, accepted: boolean) => Promise<void>;
+  createPairingQr: () => Promise<PairingQr | null>;
+  scanPairingQr: (payload: string) => Promise<boolean>;
   removeDevice: (deviceId: string) => Promise<void>;
+  loadConflicts: () => Promise<void>;
+  resolveConflict: (conflictId: string, resolution: ConflictResolution) => Promise<void>;
+  loadHistory: (request?: SyncHistoryRequest) => Promise<void>;
+  loadScope: (deviceId: string) => Promise<void>;
+  loadDeviceStats: (deviceId: string) => Promise<void>;
+  setScope: (deviceId: string, excludedCategories: string[]) => Promise<void>;
+  sendEntry: (entryId: string, deviceId: string) => Promise<boolean>;
+  loadIncomingEntries: () => Promise<void>;
+  respondToIncomingEntry: (id: string, accepted: boolean) => Promise<void>;
+  shareEntry: (entryId: string, expiresInMinutes: number, maxViews: number) => Promise<CreatedShareLink | null>;
+  loadSharedLinks: () => Promise<void>;
+  revokeSharedLink: (id: string) => Promise<void>;
+  openSharedLink: (link: string) => Promise<SharedEntry | null>;
   clearError: () => void;
+  dismissDeviceLimit: () => void;
 }
 
+// Máximo de dispositivos si el error es por haberlo alcanzado
+const deviceLimitOf = (error: unknown): number | null =>
+  isAppError(error) && error.key === 'errors.deviceLimitReached' ? Number(error.params.max) : null;
+
 export const useSyncStore = create<SyncStore>((set, get) => ({
   // Estado inicial
   status: {
@@ -57,7 +305,11 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
     isSyncing: false,
     lastSyncTime: null,
     error: null,
-    connectedDevices: 0,
+    connectedDevices: [],
+    retries: [],
+    networkRestriction: null,
+    isPaused: false,
+    lanAddress: null,
   },
   
   config: {
@@ -65,6 +317,23 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
     syncInterval: 15,
     discoveryEnabled: true,
     allowIncomingConnections: true,
+    askOnConflict: false,
+    encryptionLevel: 'standard',
+    wifiOnly: false,
+    allowedNetworks: [],
+    maxDevices: 10,
+    method: 'hybrid',
+    webdavUrl: '',
+    webdavUsername: '',
+    cloudBackend: 'webdav',
+    s3Endpoint: '',
+    s3Region: '',
+    s3Bucket: '',
+    s3Prefix: '',
+    s3AccessKeyId: '',
+    relayUrl: '',
+    turnUrl: '',
+    turnUsername: '',
   },
   
   stats: {
@@ -72,9 +341,23 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
     syncedPasswords: 0,
     lastSyncDuration: 0,
     devicesCount: 0,
+    totalSyncs: 0,
+    successfulSyncs: 0,
+    failedSyncs: 0,
   },
   
   devices: [],
+  pairings: [],
+  conflicts: [],
+  deviceLimit: null,
+  history: null,
+  scopes: {},
+  deviceStats: {},
+  incomingEntries: [],
+  sharedLinks: [],
+  cloudAccounts: { googleDrive: false, dropbox: false },
+  connectingAccount: null,
+  localDevice: null,
   
   // Acciones
   loadSyncData: async () => {
@@ -82,14 +365,15 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
       console.log('🔄 Cargando datos de sincronización...');
       
       // Cargar configuración desde el backend
-      const config = await invoke('get_sync_config');
-      const status = await invoke('get_sync_status');
-      const devices = await invoke('get_sync_devices');
-      const stats = await invoke('get_sync_stats');
+      const config = await invoke<SyncConfig>('get_sync_config');
+      const status = await invoke<SyncStatus>('get_sync_status');
+      const devices = await invoke<DeviceInfo[]>('get_sync_devices');
+      const stats = await invoke<SyncStats>('get_sync_stats');
+      const pairings = await invoke<PairingStatus[]>('get_pairings');
       
-      console.log('✅ Datos cargados:', { config, status, devices, stats });
+      console.log('✅ Datos cargados:', { config, status, devices, stats, pairings });
       
-      set({ config, status, devices, stats });
+      set({ config, status, devices, stats, pairings });
     } catch (error) {
       console.error('❌ Error loading sync data:', error);
       set(state => ({
@@ -105,31 +389,10 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
       
       console.log(`🔄 Cambiando estado de sincronización: ${currentStatus} -> ${newStatus}`);
       
-      if (newStatus) {
-        // Activar sincronización
-        await invoke('start_sync');
-        set(state => ({
-          status: { 
-            ...state.status, 
-            isEnabled: true, 
-            error: null,
-            connectedDevices: 1 // Simular dispositivo local
-          }
-        }));
-        console.log('✅ Sincronización activada');
-      } else {
-        // Desactivar sincronización
-        await invoke('stop_sync');
-        set(state => ({
-          status: { 
-            ...state.status, 
-            isEnabled: false, 
-            error: null,
-            connectedDevices: 0
-          }
-        }));
-        console.log('❌ Sincronización desactivada');
-      }
+      await invoke(newStatus ? 'start_sync' : 'stop_sync');
+      const status = await invoke<SyncStatus>('get_sync_status');
+      set({ status });
+      console.log(newStatus ? '✅ Sincronización activada' : '❌ Sincronización desactivada');
     } catch (error) {
       console.error('❌ Error toggling sync:', error);
       set(state => ({
@@ -138,32 +401,31 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
     }
   },
   
+  togglePause: async () => {
+    try {
+      const paused = !get().status.isPaused;
+      console.log(paused ? '⏸️ Pausando sincronización...' : '▶️ Reanudando sincronización...');
+      await invoke(paused ? 'pause_sync' : 'resume_sync');
+      set(state => ({
+        status: { ...state.status, isPaused: paused }
+      }));
+    } catch (error) {
+      console.error('❌ Error toggling pause:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error toggling pause' }
+      }));
+    }
+  },
+  
   startDiscovery: async () => {
     try {
       console.log('🔍 Iniciando descubrimiento de dispositivos...');
       await invoke('start_device_discovery');
       
-      // Simular dispositivos encontrados
-      const mockDevices: DeviceInfo[] = [
-        {
-          id: 'local-device',
-          name: 'MacBook Pro de Charly',
-          type: 'desktop',
-          lastSeen: new Date().toISOString(),
-          isTrusted: true,
-          isOnline: true,
-        }
-      ];
-      
-      set(state => ({
-        devices: mockDevices,
-        status: { 
-          ...state.status, 
-          connectedDevices: mockDevices.length 
-        }
-      }));
+      const devices = await invoke<DeviceInfo[]>('get_sync_devices');
+      set({ devices });
       
-      console.log('✅ Descubrimiento iniciado, dispositivos encontrados:', mockDevices.length);
+      console.log('✅ Descubrimiento iniciado, dispositivos encontrados:', devices.length);
     } catch (error) {
       console.error('❌ Error starting discovery:', error);
       set(state => ({
@@ -181,20 +443,8 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
       }));
       
       await invoke('sync_now');
-      
-      // Simular sincronización exitosa
-      setTimeout(() => {
-        set(state => ({
-          status: { 
-            ...state.status, 
-            isSyncing: false, 
-            lastSyncTime: new Date().toISOString(),
-            error: null
-          }
-        }));
-        console.log('✅ Sincronización completada');
-      }, 2000);
-      
+      await get().loadSyncData();
+      console.log('✅ Sincronización completada');
     } catch (error) {
       console.error('❌ Error syncing now:', error);
       set(state => ({
@@ -206,7 +456,8 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
   updateConfig: async (newConfig: Partial<SyncConfig>) => {
     try {
       console.log('⚙️ Actualizando configuración:', newConfig);
-      await invoke('update_sync_config', { config: newConfig });
+      // El backend espera la configuración completa, no solo lo que cambió
+      await invoke('update_sync_config', { config: { ...get().config, ...newConfig } });
       
       set(state => ({
         config: { ...state.config, ...newConfig }
@@ -221,10 +472,109 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
     }
   },
   
+  setCloudSecret: async (field: CloudSecret, secret: string) => {
+    try {
+      console.log('🔑 Guardando credencial del buzón en la nube:', field);
+      await invoke('update_sync_config', { config: { ...get().config, [field]: secret } });
+      console.log('✅ Credencial guardada');
+    } catch (error) {
+      console.error('❌ Error saving cloud credential:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error saving cloud credential' }
+      }));
+    }
+  },
+  
+  loadCloudAccounts: async () => {
+    try {
+      const cloudAccounts = await invoke<CloudAccounts>('get_cloud_accounts');
+      set({ cloudAccounts });
+    } catch (error) {
+      console.error('❌ Error loading cloud accounts:', error);
+    }
+  },
+  
+  connectCloudAccount: async (provider: CloudProvider) => {
+    try {
+      console.log('🔗 Conectando cuenta en la nube:', provider);
+      set({ connectingAccount: provider });
+      // Vuelve cuando el usuario autoriza en el navegador
+      await invoke('connect_cloud_account', { provider });
+      set(state => ({ config: { ...state.config, cloudBackend: provider } }));
+      await get().loadCloudAccounts();
+      console.log('✅ Cuenta conectada');
+    } catch (error) {
+      console.error('❌ Error connecting cloud account:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error connecting cloud account' }
+      }));
+    } finally {
+      set({ connectingAccount: null });
+    }
+  },
+  
+  disconnectCloudAccount: async (provider: CloudProvider) => {
+    try {
+      console.log('🔌 Desconectando cuenta en la nube:', provider);
+      await invoke('disconnect_cloud_account', { provider });
+      await get().loadCloudAccounts();
+      console.log('✅ Cuenta desconectada');
+    } catch (error) {
+      console.error('❌ Error disconnecting cloud account:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error disconnecting cloud account' }
+      }));
+    }
+  },
+  
+  loadLocalDevice: async () => {
+    try {
+      const localDevice = await invoke<LocalDevice>('get_local_device');
+      set({ localDevice });
+    } catch (error) {
+      console.error('❌ Error loading local device:', error);
+    }
+  },
+  
+  updateLocalDevice: async (profile: DeviceProfile) => {
+    try {
+      console.log('🏷️ Cambiando este dispositivo:', profile);
+      const localDevice = await invoke<LocalDevice>('update_local_device', { request: profile });
+      set({ localDevice });
+      return true;
+    } catch (error) {
+      console.error('❌ Error updating local device:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error updating local device' }
+      }));
+      return false;
+    }
+  },
+  
+  // Lo que avisa el backend con el evento sync-device
+  applyDeviceUpdate: (device: DeviceInfo) => {
+    set(state => {
+      const previous = state.devices.find(d => d.id === device.id);
+      const updated = { ...device, isTrusted: previous?.isTrusted ?? device.isTrusted };
+      const connected = device.status !== 'disconnected';
+      return {
+        devices: previous
+          ? state.devices.map(d => d.id === device.id ? updated : d)
+          : [...state.devices, updated],
+        status: {
+          ...state.status,
+          connectedDevices: connected
+            ? [...state.status.connectedDevices.filter(d => d.id !== device.id), updated]
+            : state.status.connectedDevices.filter(d => d.id !== device.id),
+        },
+      };
+    });
+  },
+  
   trustDevice: async (deviceId: string) => {
     try {
       console.log('🤝 Confiando dispositivo:', deviceId);
-      await invoke('trust_device', { deviceId });
+      await invoke('trust_device', { request: { deviceId } });
       
       set(state => ({
         devices: state.devices.map(device => 
@@ -237,22 +587,128 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
       console.log('✅ Dispositivo confiado');
     } catch (error) {
       console.error('❌ Error trusting device:', error);
+      const deviceLimit = deviceLimitOf(error);
+      set(state => deviceLimit !== null
+        ? { deviceLimit }
+        : { status: { ...state.status, error: 'Error trusting device' } });
+    }
+  },
+  
+  loadPairings: async () => {
+    try {
+      const pairings = await invoke<PairingStatus[]>('get_pairings');
+      set({ pairings });
+      
+      // Quien escaneó nuestro código QR ya está verificado
+      for (const pairing of pairings.filter(p => p.verified)) {
+        await get().confirmPairing(pairing.deviceId, true);
+      }
+    } catch (error) {
+      console.error('❌ Error loading pairings:', error);
+    }
+  },
+  
+  beginPairing: async (deviceId: string) => {
+    try {
+      console.log('🔗 Emparejando dispositivo:', deviceId);
+      const pairing = await invoke<PairingStatus>('begin_pairing', { request: { deviceId } });
+      
+      set(state => ({
+        pairings: [...state.pairings.filter(p => p.deviceId !== deviceId), pairing]
+      }));
+    } catch (error) {
+      console.error('❌ Error starting pairing:', error);
+      const deviceLimit = deviceLimitOf(error);
+      set(state => deviceLimit !== null
+        ? { deviceLimit }
+        : { status: { ...state.status, error: 'Error starting pairing' } });
+    }
+  },
+  
+  addDeviceManually: async (ip: string, port: number) => {
+    try {
+      console.log('🔗 Agregando dispositivo a mano:', ip, port);
+      const pairing = await invoke<PairingStatus>('add_device_manually', { request: { ip, port } });
+      const devices = await invoke<DeviceInfo[]>('get_sync_devices');
+      
+      set(state => ({
+        devices,
+        pairings: [...state.pairings.filter(p => p.deviceId !== pairing.deviceId), pairing]
+      }));
+      return true;
+    } catch (error) {
+      console.error('❌ Error adding device manually:', error);
+      const deviceLimit = deviceLimitOf(error);
+      set(state => deviceLimit !== null
+        ? { deviceLimit }
+        : { status: { ...state.status, error: 'Error adding device manually' } });
+      return false;
+    }
+  },
+  
+  confirmPairing: async (deviceId: string, accepted: boolean) => {
+    try {
+      console.log(accepted ? '✅ Confirmando emparejamiento:' : '❌ Rechazando emparejamiento:', deviceId);
+      await invoke('confirm_pairing', { request: { deviceId, accepted } });
+      
       set(state => ({
-        status: { ...state.status, error: 'Error trusting device' }
+        pairings: state.pairings.filter(p => p.deviceId !== deviceId),
+        devices: state.devices.map(device =>
+          device.id === deviceId && accepted
+            ? { ...device, isTrusted: true }
+            : device
+        )
       }));
+    } catch (error) {
+      console.error('❌ Error confirming pairing:', error);
+      const deviceLimit = deviceLimitOf(error);
+      set(state => deviceLimit !== null
+        ? { deviceLimit }
+        : { status: { ...state.status, error: 'Error confirming pairing' } });
+    }
+  },
+  
+  createPairingQr: async () => {
+    try {
+      console.log('🔳 Creando código QR de emparejamiento...');
+      return await invoke<PairingQr>('create_pairing_qr');
+    } catch (error) {
+      console.error('❌ Error creating pairing QR:', error);
+      const deviceLimit = deviceLimitOf(error);
+      set(state => deviceLimit !== null
+        ? { deviceLimit }
+        : { status: { ...state.status, error: 'Error creating pairing QR' } });
+      return null;
+    }
+  },
+  
+  scanPairingQr: async (payload: string) => {
+    try {
+      console.log('🔳 Emparejando con código QR...');
+      await invoke('scan_pairing_qr', { request: { payload } });
+      await get().loadSyncData();
+      return true;
+    } catch (error) {
+      console.error('❌ Error scanning pairing QR:', error);
+      const deviceLimit = deviceLimitOf(error);
+      set(state => deviceLimit !== null
+        ? { deviceLimit }
+        : { status: { ...state.status, error: 'Error scanning pairing QR' } });
+      return false;
     }
   },
   
   removeDevice: async (deviceId: string) => {
     try {
       console.log('🗑️ Removiendo dispositivo:', deviceId);
-      await invoke('remove_device', { deviceId });
+      await invoke('remove_device', { request: { deviceId } });
       
       set(state => ({
+        deviceLimit: null,
         devices: state.devices.filter(device => device.id !== deviceId),
         status: { 
           ...state.status, 
-          connectedDevices: Math.max(0, state.status.connectedDevices - 1)
+          connectedDevices: state.status.connectedDevices.filter(device => device.id !== deviceId)
         }
       }));
       
@@ -265,9 +721,170 @@ export const useSyncStore = create<SyncStore>((set, get) => ({
     }
   },
   
+  loadConflicts: async () => {
+    try {
+      const conflicts = await invoke<SyncConflict[]>('get_sync_conflicts');
+      set({ conflicts });
+    } catch (error) {
+      console.error('❌ Error loading conflicts:', error);
+    }
+  },
+  
+  resolveConflict: async (conflictId: string, resolution: ConflictResolution) => {
+    try {
+      console.log('🔀 Resolviendo conflicto:', conflictId, resolution);
+      await invoke('resolve_sync_conflict', { request: { conflictId, resolution } });
+      
+      set(state => ({
+        conflicts: state.conflicts.filter(conflict => conflict.id !== conflictId)
+      }));
+      
+      console.log('✅ Conflicto resuelto');
+    } catch (error) {
+      console.error('❌ Error resolving conflict:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error resolving conflict' }
+      }));
+    }
+  },
+  
+  loadHistory: async (request?: SyncHistoryRequest) => {
+    try {
+      const history = await invoke<SyncHistoryPage>('get_sync_history', { request: request ?? null });
+      set({ history });
+    } catch (error) {
+      console.error('❌ Error loading sync history:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error loading sync history' }
+      }));
+    }
+  },
+  
+  loadScope: async (deviceId: string) => {
+    try {
+      const scope = await invoke<SyncScope>('get_sync_scope', { deviceId });
+      set(state => ({ scopes: { ...state.scopes, [deviceId]: scope.excludedCategories } }));
+    } catch (error) {
+      console.error('❌ Error loading sync scope:', error);
+    }
+  },
+  
+  loadDeviceStats: async (deviceId: string) => {
+    try {
+      const stats = await invoke<DeviceSyncStats>('get_device_sync_stats', { deviceId });
+      set(state => ({ deviceStats: { ...state.deviceStats, [deviceId]: stats } }));
+    } catch (error) {
+      console.error('❌ Error loading device sync stats:', error);
+    }
+  },
+  
+  setScope: async (deviceId: string, excludedCategories: string[]) => {
+    try {
+      console.log('🗂️ Cambiando categorías excluidas de:', deviceId, excludedCategories);
+      await invoke('set_sync_scope', { request: { deviceId, excludedCategories } });
+      set(state => ({ scopes: { ...state.scopes, [deviceId]: excludedCategories } }));
+    } catch (error) {
+      console.error('❌ Error saving sync scope:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error saving sync scope' }
+      }));
+    }
+  },
+  
+  sendEntry: async (entryId: string, deviceId: string) => {
+    try {
+      console.log('📤 Enviando entrada:', entryId, deviceId);
+      await invoke('send_entry_to_device', { request: { entryId, deviceId } });
+      return true;
+    } catch (error) {
+      console.error('❌ Error sending entry:', error);
+      set(state => ({
+        status: { ...state.status, error: 'Error sending entry' }
+      }));
+      return false;
+    }
+  },
+  
+  loadIncomingEntries: async () => {
+    try {
+      const incomingEntries = await invoke<IncomingEntry[]>('get_incoming_entries');
+      set({ incomingEntries });
+    } catch (error) {
+      console.error('❌ Error loading incoming entries:', error);
+    }
+  },
+  
+  respondToIncomingEntry: async (id: string, accepted: boolean) => {
+    try {
+      await invoke<string | null>('respond_to_incoming_entry', { request: { id, accepted } });
+      set(state => ({ incomingEntries: state.incomingEntries.filter(entry => entry.id !== id) }));
+    } catch (error) {
+      console.error('❌ Error answering incoming entry:', error);
+      set(state => ({
+        incomingEntries: state.incomingEntries.filter(entry => entry.id !== id),
+        status: { ...state.status, error: 'Error saving incoming entry' }
+      }));
+    }
+  },
+  
+  shareEntry: async (entryId: string, expiresInMinutes: number, maxViews: number) => {
+    try {
+      console.log('🔗 Compartiendo entrada por enlace:', entryId);
+      const created = await invoke<CreatedShareLink>('share_entry', {
+        request: { entryId, expiresInMinutes, maxViews }
+      });
+      set(state => ({ sharedLinks: [created.share, ...state.sharedLinks] }));
+      return created;
+    } catch (error) {
+      console.error('❌ Error sharing entry:', error);
+      set(state => ({
+        status: { ...state.status, error: getErrorMessage(error, 'Error sharing entry') }
+      }));
+      return null;
+    }
+  },
+  
+  loadSharedLinks: async () => {
+    try {
+      const sharedLinks = await invoke<SharedLink[]>('get_shared_links');
+      set({ sharedLinks });
+    } catch (error) {
+      console.error('❌ Error loading shared links:', error);
+    }
+  },
+  
+  revokeSharedLink: async (id: string) => {
+    try {
+      console.log('🚫 Revocando enlace compartido:', id);
+      await invoke('revoke_shared_link', { id });
+      set(state => ({ sharedLinks: state.sharedLinks.filter(link => link.id !== id) }));
+    } catch (error) {
+      console.error('❌ Error revoking shared link:', error);
+      set(state => ({
+        status: { ...state.status, error: getErrorMessage(error, 'Error revoking shared link') }
+      }));
+    }
+  },
+  
+  openSharedLink: async (link: string) => {
+    try {
+      return await invoke<SharedEntry>('open_shared_link', { link });
+    } catch (error) {
+      console.error('❌ Error opening shared link:', error);
+      set(state => ({
+        status: { ...state.status, error: getErrorMessage(error, 'Error opening shared link') }
+      }));
+      return null;
+    }
+  },
+  
   clearError: () => {
     set(state => ({
       status: { ...state.status, error: null }
     }));
   },
+  
+  dismissDeviceLimit: () => {
+    set({ deviceLimit: null });
+  },
 }));
@@ -0,0 +1,346 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/tauri'
+import { getErrorMessage } from '../utils/appError'
+
+export type ExportFormat = 'json' | 'csv_bitwarden' | 'csv_chrome' | 'alohopass' | 'kdbx'
+
+export interface ExportRequest {
+  format: ExportFormat
+  category_id?: string | null
+  tags?: string[]
+  path?: string | null
+  password?: string | null
+}
+
+export interface ExportResult {
+  exported: number
+  content: string | null
+}
+
+export const EXPORT_EXTENSIONS: Record<ExportFormat, string> = {
+  json: 'json',
+  csv_bitwarden: 'csv',
+  csv_chrome: 'csv',
+  alohopass: 'json',
+  kdbx: 'kdbx',
+}
+
+export interface BackupInfo {
+  path: string
+  created_at: string
+  entries: number
+  categories: number
+  policies: number
+  size_bytes: number
+}
+
+export interface BackupRecord {
+  id: number
+  path: string
+  created_at: string
+  automatic: boolean
+  entries: number
+  size_bytes: number
+  error: string | null
+}
+
+export interface MaintenanceReport {
+  size_before: number
+  size_after: number
+  reclaimed_bytes: number
+  pruned_generation_history: number
+  pruned_backup_history: number
+  pruned_tombstones: number
+  pruned_revisions: number
+  completed_at: string
+}
+
+export type RestoreMode = 'replace' | 'merge'
+
+export type RestoreAction = 'add' | 'update' | 'skip' | 'remove'
+
+export interface RestoreRequest {
+  path: string
+  backup_password: string
+  mode: RestoreMode
+  preview: boolean
+}
+
+export interface RestorePreview {
+  mode: RestoreMode
+  committed: boolean
+  added: number
+  updated: number
+  skipped: number
+  removed: number
+  categories_added: number
+  policies_added: number
+  changes: { entry_id: string; title: string; action: RestoreAction }[]
+}
+
+export const BACKUP_EXTENSION = 'alohobackup'
+
+export interface MigrationBackupInfo {
+  path: string
+  schema_version: number
+  rolled_back_path: string
+}
+
+export type ImportFormat = 'bitwarden_json' | 'bitwarden_csv' | 'lastpass_csv' | 'keepass_kdbx' | 'onepassword_pux'
+
+export interface ImportFormatInfo {
+  format: ImportFormat
+  name: string
+  extension: string
+  needs_password: boolean
+}
+
+export interface ImportRequest {
+  format: ImportFormat
+  data?: string | null
+  path?: string | null
+  password?: string | null
+  key_file_path?: string | null
+  preview?: boolean
+  resolutions?: ImportRowResolution[]
+}
+
+export type BrowserKind = 'chrome' | 'edge' | 'brave' | 'chromium' | 'firefox'
+
+export interface BrowserProfile {
+  browser: BrowserKind
+  name: string
+  path: string
+}
+
+export interface BrowserImportRequest {
+  browser: BrowserKind
+  profile_path: string
+  password?: string | null
+  preview?: boolean
+  resolutions?: ImportRowResolution[]
+}
+
+export type ImportRowStatus = 'new' | 'duplicate' | 'near_duplicate' | 'failed'
+
+export type ImportResolution = 'import' | 'skip' | 'overwrite'
+
+export interface ImportRowResolution {
+  row: number
+  resolution: ImportResolution
+}
+
+export interface ImportRowResult {
+  row: number
+  title: string | null
+  status: ImportRowStatus
+  resolution: ImportResolution | null
+  existing_id: string | null
+  entry_id: string | null
+  error: string | null
+}
+
+export interface ImportReport {
+  committed: boolean
+  imported: number
+  updated: number
+  skipped: number
+  failed: number
+  categories_created: number
+  rows: ImportRowResult[]
+}
+
+/** Payload de los eventos `import-progress` y `export-progress` */
+export interface TransferProgress {
+  processed: number
+  total: number
+  title: string | null
+}
+
+export const IMPORT_PROGRESS_EVENT = 'import-progress'
+export const EXPORT_PROGRESS_EVENT = 'export-progress'
+
+interface TransferState {
+  importFormats: ImportFormatInfo[]
+  browserProfiles: BrowserProfile[]
+  backupHistory: BackupRecord[]
+  hasBackupPassword: boolean
+  isLoading: boolean
+  error: string | null
+  
+  // Acciones
+  exportPasswords: (request: ExportRequest) => Promise<ExportResult | null>
+  fetchImportFormats: () => Promise<void>
+  importPasswords: (request: ImportRequest) => Promise<ImportReport | null>
+  fetchBrowserProfiles: () => Promise<void>
+  importFromBrowser: (request: BrowserImportRequest) => Promise<ImportReport | null>
+  createBackup: (path: string, backupPassword: string) => Promise<BackupInfo | null>
+  verifyBackup: (path: string, backupPassword: string) => Promise<BackupInfo | null>
+  restoreBackup: (request: RestoreRequest) => Promise<RestorePreview | null>
+  rollbackMigrationBackup: () => Promise<MigrationBackupInfo | null>
+  compactDatabase: () => Promise<MaintenanceReport | null>
+  fetchBackupHistory: () => Promise<void>
+  setBackupPassword: (backupPassword: string | null) => Promise<boolean>
+  clearError: () => void
+}
+
+export const useTransferStore = create<TransferState>((set, get) => ({
+  importFormats: [],
+  browserProfiles: [],
+  backupHistory: [],
+  hasBackupPassword: false,
+  isLoading: false,
+  error: null,
+  
+  exportPasswords: async (request: ExportRequest) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const result = await invoke<ExportResult>('export_passwords', { request })
+      set({ isLoading: false })
+      return result
+    } catch (error) {
+      const errorMessage = getErrorMessage(error, 'Error al exportar las contraseñas')
+      set({ error: errorMessage, isLoading: false })
+      return null
+    }
+  },
+  
+  fetchImportFormats: async () => {
+    try {
+      const importFormats = await invoke<ImportFormatInfo[]>('get_import_formats')
+      set({ importFormats })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al cargar los formatos de importación') })
+    }
+  },
+  
+  importPasswords: async (request: ImportRequest) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const report = await invoke<ImportReport>('import_passwords', { request })
+      set({ isLoading: false })
+      return report
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al importar las contraseñas'), isLoading: false })
+      return null
+    }
+  },
+  
+  fetchBrowserProfiles: async () => {
+    try {
+      const browserProfiles = await invoke<BrowserProfile[]>('get_browser_profiles')
+      set({ browserProfiles })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al buscar los navegadores instalados') })
+    }
+  },
+  
+  importFromBrowser: async (request: BrowserImportRequest) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const report = await invoke<ImportReport>('import_from_browser', { request })
+      set({ isLoading: false })
+      return report
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al importar desde el navegador'), isLoading: false })
+      return null
+    }
+  },
+  
+  createBackup: async (path: string, backupPassword: string) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const info = await invoke<BackupInfo>('create_backup', { path, backupPassword })
+      set({ isLoading: false })
+      await get().fetchBackupHistory()
+      return info
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al crear la copia de seguridad'), isLoading: false })
+      await get().fetchBackupHistory()
+      return null
+    }
+  },
+  
+  verifyBackup: async (path: string, backupPassword: string) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const info = await invoke<BackupInfo>('verify_backup', { path, backupPassword })
+      set({ isLoading: false })
+      return info
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al verificar la copia de seguridad'), isLoading: false })
+      return null
+    }
+  },
+  
+  restoreBackup: async (request: RestoreRequest) => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const result = await invoke<RestorePreview>('restore_backup', { request })
+      set({ isLoading: false })
+      return result
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al restaurar la copia de seguridad'), isLoading: false })
+      return null
+    }
+  },
+  
+  rollbackMigrationBackup: async () => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const info = await invoke<MigrationBackupInfo>('rollback_last_migration_backup')
+      set({ isLoading: false })
+      return info
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al restaurar la copia previa a la migración'), isLoading: false })
+      return null
+    }
+  },
+  
+  compactDatabase: async () => {
+    set({ isLoading: true, error: null })
+    
+    try {
+      const report = await invoke<MaintenanceReport>('compact_database')
+      set({ isLoading: false })
+      return report
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al compactar la base de datos'), isLoading: false })
+      return null
+    }
+  },
+  
+  fetchBackupHistory: async () => {
+    try {
+      const [backupHistory, hasBackupPassword] = await Promise.all([
+        invoke<BackupRecord[]>('get_backup_history', { limit: null }),
+        invoke<boolean>('has_backup_password'),
+      ])
+      set({ backupHistory, hasBackupPassword })
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al cargar el historial de copias de seguridad') })
+    }
+  },
+  
+  setBackupPassword: async (backupPassword: string | null) => {
+    try {
+      await invoke('set_backup_password', { backupPassword })
+      set({ hasBackupPassword: backupPassword !== null })
+      return true
+    } catch (error) {
+      set({ error: getErrorMessage(error, 'Error al guardar la contraseña de las copias automáticas') })
+      return false
+    }
+  },
+  
+  clearError: () => {
+    set({ error: null })
+  },
+}))
@@ -0,0 +1,35 @@
+/** Tipos de error que devuelven los comandos del backend */
+export type AppErrorCode =
+  | 'locked'
+  | 'not_found'
+  | 'validation'
+  | 'permission_denied'
+  | 'database'
+  | 'crypto'
+  | 'sync'
+  | 'internal'
+
+/** Error estructurado serializado por `AppError` en Rust */
+export interface AppError {
+  code: AppErrorCode
+  /** Clave del catálogo de mensajes del backend */
+  key: string
+  params: Record<string, string>
+  /** Mensaje ya traducido al idioma activo del backend */
+  message: string
+  details: string | null
+}
+
+export const isAppError = (error: unknown): error is AppError =>
+  typeof error === 'object' &&
+  error !== null &&
+  'code' in error &&
+  'message' in error
+
+/** Obtiene un mensaje mostrable a partir de cualquier error recibido de `invoke` */
+export const getErrorMessage = (error: unknown, fallback: string): string => {
+  if (isAppError(error)) return error.message
+  if (error instanceof Error) return error.message
+  if (typeof error === 'string') return error
+  return fallback
+}
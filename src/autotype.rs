@@ -0,0 +1,178 @@
+//! Auto-type al estilo KeePass
+//!
+//! Convierte una secuencia como `{USERNAME}{TAB}{PASSWORD}{ENTER}` en pulsaciones
+//! de teclado que se envían a la ventana que tiene el foco.
+
+use anyhow::{anyhow, Result};
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use log::info;
+use std::time::Duration;
+
+/// Secuencia usada cuando ni la llamada ni la entrada definen una propia
+pub const DEFAULT_SEQUENCE: &str = "{USERNAME}{TAB}{PASSWORD}{ENTER}";
+
+/// Pausa entre acciones para que la aplicación de destino procese cada tecla
+const STEP_DELAY: Duration = Duration::from_millis(25);
+
+/// Lo máximo que puede esperar un `{DELAY n}`, en milisegundos
+pub const MAX_DELAY_MS: u64 = 10_000;
+
+/// Datos de la entrada disponibles como marcadores en la secuencia
+pub struct AutoTypeFields<'a> {
+    pub title: &'a str,
+    pub username: &'a str,
+    pub password: &'a str,
+    pub url: &'a str,
+    pub notes: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Text(String),
+    Key(Key),
+    Delay(u64),
+}
+
+/// Interpreta la secuencia y reemplaza los marcadores por los datos de la entrada.
+/// `{{}` y `{}}` escriben una llave literal.
+pub fn parse_sequence(sequence: &str, fields: &AutoTypeFields) -> Result<Vec<Action>> {
+    let mut actions = Vec::new();
+    let mut text = String::new();
+    let mut chars = sequence.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            text.push(c);
+            continue;
+        }
+        
+        let mut placeholder = String::new();
+        // `{}}` es una llave de cierre literal; `{}` solo, un marcador vacío
+        if chars.peek() == Some(&'}') {
+            chars.next();
+            if chars.peek() != Some(&'}') {
+                return Err(anyhow!("Marcador vacío en la secuencia: {{}}"));
+            }
+            placeholder.push('}');
+        }
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => placeholder.push(c),
+                None => return Err(anyhow!("Marcador sin cerrar en la secuencia: {{{}", placeholder)),
+            }
+        }
+        
+        let action = match placeholder.to_uppercase().as_str() {
+            "{" => Action::Text("{".to_string()),
+            "}" => Action::Text("}".to_string()),
+            "USERNAME" => Action::Text(fields.username.to_string()),
+            "PASSWORD" => Action::Text(fields.password.to_string()),
+            "TITLE" => Action::Text(fields.title.to_string()),
+            "URL" => Action::Text(fields.url.to_string()),
+            "NOTES" => Action::Text(fields.notes.to_string()),
+            "TAB" => Action::Key(Key::Tab),
+            "ENTER" => Action::Key(Key::Return),
+            "SPACE" => Action::Key(Key::Space),
+            "BACKSPACE" | "BS" => Action::Key(Key::Backspace),
+            "ESC" => Action::Key(Key::Escape),
+            "UP" => Action::Key(Key::UpArrow),
+            "DOWN" => Action::Key(Key::DownArrow),
+            "LEFT" => Action::Key(Key::LeftArrow),
+            "RIGHT" => Action::Key(Key::RightArrow),
+            other => match other.strip_prefix("DELAY ") {
+                Some(ms) => Action::Delay(ms.trim().parse()
+                    .ok()
+                    .filter(|ms| *ms <= MAX_DELAY_MS)
+                    .ok_or_else(|| anyhow!("Retardo inválido en la secuencia: {} (máximo {} ms)", ms, MAX_DELAY_MS))?),
+                None => return Err(anyhow!("Marcador desconocido en la secuencia: {{{}}}", placeholder)),
+            },
+        };
+        
+        match action {
+            Action::Text(value) => text.push_str(&value),
+            action => {
+                if !text.is_empty() {
+                    actions.push(Action::Text(std::mem::take(&mut text)));
+                }
+                actions.push(action);
+            }
+        }
+    }
+    
+    if !text.is_empty() {
+        actions.push(Action::Text(text));
+    }
+    Ok(actions)
+}
+
+/// Comprueba que la secuencia sea válida sin datos reales
+pub fn validate_sequence(sequence: &str) -> Result<()> {
+    let empty = AutoTypeFields { title: "", username: "", password: "", url: "", notes: "" };
+    parse_sequence(sequence, &empty).map(|_| ())
+}
+
+/// Envía las acciones como pulsaciones a la ventana con el foco
+pub fn type_actions(actions: &[Action]) -> Result<()> {
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| anyhow!("No se pudo inicializar la emulación de teclado: {}", e))?;
+    
+    info!("⌨️ Auto-type: enviando {} acciones", actions.len());
+    for action in actions {
+        match action {
+            Action::Text(text) => enigo.text(text)
+                .map_err(|e| anyhow!("Error al escribir texto: {}", e))?,
+            Action::Key(key) => enigo.key(*key, Direction::Click)
+                .map_err(|e| anyhow!("Error al pulsar tecla {:?}: {}", key, e))?,
+            Action::Delay(ms) => std::thread::sleep(Duration::from_millis(*ms)),
+        }
+        std::thread::sleep(STEP_DELAY);
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> AutoTypeFields<'static> {
+        AutoTypeFields { title: "Mail", username: "ana", password: "s3cr{t", url: "", notes: "" }
+    }
+
+    #[test]
+    fn test_parse_default_sequence() {
+        let actions = parse_sequence(DEFAULT_SEQUENCE, &fields()).unwrap();
+        assert_eq!(actions, vec![
+            Action::Text("ana".to_string()),
+            Action::Key(Key::Tab),
+            Action::Text("s3cr{t".to_string()),
+            Action::Key(Key::Return),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_literals_and_delay() {
+        let actions = parse_sequence("a{{}b{}}{DELAY 200}{username}", &fields()).unwrap();
+        assert_eq!(actions, vec![
+            Action::Text("a{b}".to_string()),
+            Action::Delay(200),
+            Action::Text("ana".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_placeholder() {
+        assert!(validate_sequence("{USERNAME}{FOO}").is_err());
+        assert!(validate_sequence("{USERNAME").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_empty_placeholder_and_long_delay() {
+        let error = validate_sequence("{}{TAB}").unwrap_err();
+        assert!(error.to_string().contains("vacío"));
+        assert!(validate_sequence("{}").is_err());
+        assert!(validate_sequence(&format!("{{DELAY {}}}", MAX_DELAY_MS)).is_ok());
+        assert!(validate_sequence(&format!("{{DELAY {}}}", MAX_DELAY_MS + 1)).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Copias de seguridad encriptadas (`.alohobackup`)
+//!
+//! La copia contiene la bóveda completa desencriptada (entradas con sus
+//! adjuntos, categorías y políticas), serializada a JSON, comprimida con gzip y encriptada con una clave
+//! derivada con Argon2 de una contraseña de copia elegida por el usuario, distinta
+//! de la contraseña maestra. Así se puede restaurar en otro equipo o tras cambiar
+//! la contraseña maestra.
+//!
+//! Formato del archivo:
+//!
+//! ```text
+//! magic "ALOHOBK1" (8) | salt (32) | nonce (12) | ChaCha20-Poly1305(gzip(JSON))
+//! ```
+//!
+//! La cabecera se autentica como datos asociados: si el archivo se modificó o la
+//! contraseña no es la correcta, el descifrado falla y no se restaura nada.
+
+pub mod restore;
+
+use crate::models::{Category, PasswordEntry, PasswordPolicy};
+use anyhow::anyhow;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::info;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Extensión de los archivos de copia de seguridad
+pub const FILE_EXTENSION: &str = "alohobackup";
+/// Versión del contenido serializado
+pub const FORMAT_VERSION: u32 = 2;
+/// Primera versión que guarda los adjuntos; las anteriores se siguen leyendo
+pub const ATTACHMENTS_VERSION: u32 = 2;
+
+/// Prefijo de los archivos creados por las copias automáticas
+const AUTOMATIC_PREFIX: &str = "alohopass-auto-";
+
+const MAGIC: &[u8; 8] = b"ALOHOBK1";
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("El archivo no es una copia de seguridad de Alohopass")]
+    NotABackup,
+    /// El descifrado autenticado falló: contraseña incorrecta o archivo dañado
+    #[error("Contraseña de la copia incorrecta o archivo dañado")]
+    Decrypt,
+    #[error("Versión de copia de seguridad no soportada: {0}")]
+    UnsupportedVersion(u32),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Entrada de la copia, con los campos que no forman parte de `PasswordEntry`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    #[serde(flatten)]
+    pub entry: PasswordEntry,
+    pub totp_secret: Option<String>,
+    pub autotype_sequence: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<BackupAttachment>,
+}
+
+/// Adjunto de una entrada de la copia; los datos van en base64
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupAttachment {
+    pub name: String,
+    #[serde(with = "base64_data")]
+    pub data: Vec<u8>,
+}
+
+mod base64_data {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Contenido completo de una copia de seguridad
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultSnapshot {
+    pub format_version: u32,
+    pub created_at: String,
+    pub entries: Vec<BackupEntry>,
+    pub categories: Vec<Category>,
+    pub policies: Vec<PasswordPolicy>,
+}
+
+impl VaultSnapshot {
+    pub fn new(entries: Vec<BackupEntry>, categories: Vec<Category>, policies: Vec<PasswordPolicy>) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            entries,
+            categories,
+            policies,
+        }
+    }
+
+    /// Si la copia trae los adjuntos. Las anteriores a
+    /// [`ATTACHMENTS_VERSION`] no los guardaban: que una entrada venga sin
+    /// adjuntos no quiere decir que no los tuviera.
+    pub fn has_attachments(&self) -> bool {
+        self.format_version >= ATTACHMENTS_VERSION
+    }
+}
+
+/// Comprime y encripta la bóveda con la contraseña de la copia
+pub fn seal(snapshot: &VaultSnapshot, password: &str) -> Result<Vec<u8>, BackupError> {
+    let json = serde_json::to_vec(snapshot).map_err(anyhow::Error::from)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    let compressed = encoder.finish()?;
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let cipher = backup_cipher(password, &salt)?;
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: &compressed, aad: &header })
+        .map_err(|e| anyhow!("Error al encriptar la copia: {}", e))?;
+
+    info!("Copia de seguridad encriptada: {} entradas, {} bytes comprimidos", snapshot.entries.len(), compressed.len());
+    let mut sealed = header;
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Verifica y desencripta una copia. Falla sin devolver datos parciales si la
+/// contraseña es incorrecta o el archivo fue alterado.
+pub fn open(data: &[u8], password: &str) -> Result<VaultSnapshot, BackupError> {
+    if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err(BackupError::NotABackup);
+    }
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let salt = &header[MAGIC.len()..MAGIC.len() + SALT_LEN];
+    let nonce = &header[MAGIC.len() + SALT_LEN..];
+
+    let cipher = backup_cipher(password, salt)?;
+    let compressed = cipher.decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| BackupError::Decrypt)?;
+
+    let mut json = Vec::new();
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut json)?;
+    let snapshot: VaultSnapshot = serde_json::from_slice(&json).map_err(anyhow::Error::from)?;
+    if snapshot.format_version > FORMAT_VERSION {
+        return Err(BackupError::UnsupportedVersion(snapshot.format_version));
+    }
+    Ok(snapshot)
+}
+
+/// Escribe la copia en disco (solo legible por el dueño en Unix).
+/// Se escribe primero a un temporal para no dejar una copia a medias.
+pub fn write_backup(path: &Path, snapshot: &VaultSnapshot, password: &str) -> Result<u64, BackupError> {
+    let sealed = seal(snapshot, password)?;
+    let tmp_path = path.with_extension(format!("{}.tmp", FILE_EXTENSION));
+    crate::export::write_file(&tmp_path, &sealed)?;
+    std::fs::rename(&tmp_path, path)?;
+    info!("Copia de seguridad escrita en {:?}", path);
+    Ok(sealed.len() as u64)
+}
+
+/// Lee y desencripta una copia desde disco
+pub fn read_backup(path: &Path, password: &str) -> Result<VaultSnapshot, BackupError> {
+    let data = std::fs::read(path)?;
+    open(&data, password)
+}
+
+/// Ruta de una copia automática nueva dentro de `directory`. El nombre lleva la
+/// fecha para que el orden alfabético sea también el cronológico.
+pub fn automatic_backup_path(directory: &Path, now: chrono::DateTime<chrono::Utc>) -> PathBuf {
+    directory.join(format!("{}{}.{}", AUTOMATIC_PREFIX, now.format("%Y%m%d-%H%M%S"), FILE_EXTENSION))
+}
+
+/// Borra las copias automáticas más antiguas de `directory` hasta dejar `keep`.
+/// Solo toca archivos con el nombre de las copias automáticas; devuelve los borrados.
+pub fn rotate(directory: &Path, keep: usize) -> Result<Vec<PathBuf>, BackupError> {
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(AUTOMATIC_PREFIX) && name.ends_with(&format!(".{}", FILE_EXTENSION))
+                })
+        })
+        .collect();
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = backups.into_iter().take(excess).collect();
+    for path in &removed {
+        std::fs::remove_file(path)?;
+        info!("Copia automática antigua eliminada: {:?}", path);
+    }
+    Ok(removed)
+}
+
+fn backup_cipher(password: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, BackupError> {
+    let key = crate::crypto::derive_key_from_password(password, salt)
+        .map_err(|e| anyhow!(e))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> VaultSnapshot {
+        let entry = PasswordEntry::sample("Correo");
+        VaultSnapshot::new(
+            vec![BackupEntry {
+                entry,
+                totp_secret: Some("JBSWY3DPEHPK3PXP".to_string()),
+                autotype_sequence: None,
+                attachments: vec![BackupAttachment { name: "llave.pem".to_string(), data: vec![0, 159, 255] }],
+            }],
+            vec![],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_roundtrip_with_correct_password() {
+        let sealed = seal(&snapshot(), "copia").unwrap();
+        let opened = open(&sealed, "copia").unwrap();
+        assert_eq!(opened.entries.len(), 1);
+        assert_eq!(opened.entries[0].entry.password, "secreta");
+        assert_eq!(opened.entries[0].totp_secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        assert_eq!(opened.entries[0].attachments, snapshot().entries[0].attachments);
+        assert!(opened.has_attachments());
+    }
+
+    #[test]
+    fn test_reads_backups_without_attachments() {
+        let mut json = serde_json::to_value(snapshot()).unwrap();
+        json["format_version"] = 1.into();
+        json["entries"][0].as_object_mut().unwrap().remove("attachments");
+        let opened: VaultSnapshot = serde_json::from_value(json).unwrap();
+        assert!(opened.entries[0].attachments.is_empty());
+        assert!(!opened.has_attachments());
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest_automatic_backups() {
+        let dir = std::env::temp_dir().join(format!("alohopass-rotate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let start = chrono::Utc::now();
+        for hours in 0..4 {
+            let path = automatic_backup_path(&dir, start + chrono::Duration::hours(hours));
+            std::fs::write(path, b"x").unwrap();
+        }
+        std::fs::write(dir.join("manual.alohobackup"), b"x").unwrap();
+
+        let removed = rotate(&dir, 2).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0], automatic_backup_path(&dir, start));
+        assert!(dir.join("manual.alohobackup").exists());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_password_or_tampering_is_rejected() {
+        let mut sealed = seal(&snapshot(), "copia").unwrap();
+        assert!(matches!(open(&sealed, "otra"), Err(BackupError::Decrypt)));
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(matches!(open(&sealed, "copia"), Err(BackupError::Decrypt)));
+        assert!(matches!(open(b"no es una copia", "copia"), Err(BackupError::NotABackup)));
+    }
+}
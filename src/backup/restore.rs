@@ -0,0 +1,144 @@
+//! Plan de restauración de una copia sobre la bóveda actual
+//!
+//! Al combinar, una entrada de la copia se considera la misma que una existente si
+//! tiene el mismo id o el mismo título, usuario y URL. En ese caso gana la versión
+//! modificada más recientemente; si la de la bóveda es igual o más nueva, la de la
+//! copia se omite. El plan se calcula antes de tocar la base de datos, así sirve
+//! también como vista previa.
+
+use super::BackupEntry;
+use crate::models::{EntryId, RestoreAction, RestoreMode};
+use std::collections::HashMap;
+
+/// Datos de una entrada de la bóveda necesarios para compararla con la copia
+#[derive(Debug, Clone)]
+pub struct ExistingEntry {
+    pub id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub url: Option<String>,
+    pub updated_at: String,
+}
+
+/// Qué hacer con una entrada de la copia
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryPlan {
+    Add,
+    /// Sobrescribir la entrada existente con este id
+    Update(EntryId),
+    Skip,
+}
+
+impl EntryPlan {
+    pub fn action(&self) -> RestoreAction {
+        match self {
+            EntryPlan::Add => RestoreAction::Add,
+            EntryPlan::Update(_) => RestoreAction::Update,
+            EntryPlan::Skip => RestoreAction::Skip,
+        }
+    }
+}
+
+/// Decide qué hacer con cada entrada de la copia, en el mismo orden
+pub fn plan_entries(mode: RestoreMode, existing: &[ExistingEntry], entries: &[BackupEntry]) -> Vec<EntryPlan> {
+    if mode == RestoreMode::Replace {
+        return vec![EntryPlan::Add; entries.len()];
+    }
+
+    let by_id: HashMap<EntryId, &ExistingEntry> = existing.iter()
+        .map(|entry| (entry.id, entry))
+        .collect();
+    let by_content: HashMap<(String, String, String), &ExistingEntry> = existing.iter()
+        .map(|entry| (content_key(&entry.title, &entry.username, entry.url.as_deref()), entry))
+        .collect();
+
+    entries.iter()
+        .map(|backup_entry| {
+            let entry = &backup_entry.entry;
+            let matched = by_id.get(&entry.id)
+                .or_else(|| by_content.get(&content_key(&entry.title, &entry.username, entry.url.as_deref())));
+            match matched {
+                None => EntryPlan::Add,
+                Some(current) if is_newer(&entry.updated_at, &current.updated_at) => EntryPlan::Update(current.id),
+                Some(_) => EntryPlan::Skip,
+            }
+        })
+        .collect()
+}
+
+/// Clave para detectar la misma cuenta guardada con otro id
+fn content_key(title: &str, username: &str, url: Option<&str>) -> (String, String, String) {
+    (
+        title.trim().to_lowercase(),
+        username.trim().to_lowercase(),
+        url.unwrap_or("").trim().trim_end_matches('/').to_lowercase(),
+    )
+}
+
+/// Compara fechas RFC 3339; si alguna no se puede interpretar, compara el texto
+fn is_newer(candidate: &str, current: &str) -> bool {
+    match (
+        chrono::DateTime::parse_from_rfc3339(candidate),
+        chrono::DateTime::parse_from_rfc3339(current),
+    ) {
+        (Ok(candidate), Ok(current)) => candidate > current,
+        _ => candidate > current,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PasswordEntry;
+
+    fn id(n: u128) -> EntryId {
+        uuid::Uuid::from_u128(n).into()
+    }
+
+    fn backup_entry(id: EntryId, title: &str, updated_at: &str) -> BackupEntry {
+        BackupEntry {
+            entry: PasswordEntry {
+                id,
+                url: Some("https://example.com/".to_string()),
+                created_at: updated_at.to_string(),
+                updated_at: updated_at.to_string(),
+                ..PasswordEntry::sample(title)
+            },
+            totp_secret: None,
+            autotype_sequence: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    fn existing(id: EntryId, title: &str, updated_at: &str) -> ExistingEntry {
+        ExistingEntry {
+            id,
+            title: title.to_string(),
+            username: "ana".to_string(),
+            url: Some("https://example.com".to_string()),
+            updated_at: updated_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_merge_skips_duplicates_and_prefers_newer() {
+        let current = vec![
+            existing(id(1), "Correo", "2024-01-01T00:00:00+00:00"),
+            existing(id(2), "Banco", "2024-06-01T00:00:00+00:00"),
+        ];
+        let entries = vec![
+            backup_entry(id(1), "Correo", "2024-03-01T00:00:00+00:00"),
+            backup_entry(id(9), "banco", "2024-02-01T00:00:00+00:00"),
+            backup_entry(id(3), "Foro", "2024-02-01T00:00:00+00:00"),
+        ];
+        let plan = plan_entries(RestoreMode::Merge, &current, &entries);
+        assert_eq!(plan, vec![EntryPlan::Update(id(1)), EntryPlan::Skip, EntryPlan::Add]);
+    }
+
+    #[test]
+    fn test_replace_adds_everything() {
+        let current = vec![existing(id(1), "Correo", "2030-01-01T00:00:00+00:00")];
+        let entries = vec![backup_entry(id(1), "Correo", "2024-01-01T00:00:00+00:00")];
+        assert_eq!(plan_entries(RestoreMode::Replace, &current, &entries), vec![EntryPlan::Add]);
+    }
+}
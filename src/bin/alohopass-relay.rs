@@ -0,0 +1,547 @@
+//! Relay de sincronización de Alohopass
+//!
+//! Guarda en memoria los lotes que unos dispositivos dejan para otros hasta
+//! que los recogen, para sincronizar dispositivos que no se ven en la red
+//! local sin depender de un proveedor. Los lotes llegan sellados con la clave
+//! de cada emparejamiento: el relay no puede leerlos. También pasa las ofertas
+//! y respuestas WebRTC para que se conecten directamente, y sirve las entradas
+//! compartidas por enlace hasta que caducan o se agotan sus vistas. El
+//! protocolo está descrito en `src/sync/relay.rs`.
+//!
+//! ```text
+//! cargo run --release --features relay-server --bin alohopass-relay -- \
+//!     --listen 0.0.0.0:8790 --token <token>
+//! ```
+//!
+//! El token también se puede pasar en `ALOHOPASS_RELAY_TOKEN`. Sin token
+//! cualquiera puede dejar lotes. Las entradas compartidas las lee cualquiera
+//! que tenga el enlace, sin token. Para usarlo desde fuera conviene ponerlo
+//! detrás de un proxy con TLS.
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const DEFAULT_LISTEN: &str = "127.0.0.1:8790";
+
+/// Tamaño máximo de un lote
+const MAX_BATCH_SIZE: usize = 64 * 1024 * 1024;
+
+/// Lo máximo que se guarda entre todos los lotes
+const MAX_STORED: usize = 1024 * 1024 * 1024;
+
+/// Lotes que nadie recoge en este tiempo se descartan
+const BATCH_TTL: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Cada cuánto se buscan lotes caducados
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Tiempo para recibir una petición completa
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Cabeceras que se aceptan en una petición
+const MAX_HEADERS: usize = 64;
+
+/// Lo máximo que puede durar una entrada compartida
+const MAX_SHARE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Las veces que se puede leer, como mucho, una entrada compartida
+const MAX_SHARE_VIEWS: u32 = 100;
+
+/// Qué guarda cada hueco: un lote de sincronización o un mensaje de
+/// señalización
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Slot {
+    Batch,
+    Offer,
+    Answer,
+}
+
+/// Lote esperando a su destino
+struct Batch {
+    sealed: Vec<u8>,
+    revision: u64,
+    stored_at: Instant,
+}
+
+/// Entrada compartida por enlace, sellada con una clave que el relay no ve
+struct Share {
+    sealed: Vec<u8>,
+    /// Quien la dejó la revoca presentándolo
+    owner: String,
+    expires_at: Instant,
+    views_left: u32,
+}
+
+/// Lotes guardados, por (hueco, destino, origen), y entradas compartidas
+#[derive(Default)]
+struct Relay {
+    batches: HashMap<(Slot, Uuid, Uuid), Batch>,
+    shares: HashMap<Uuid, Share>,
+    next_revision: u64,
+    stored: usize,
+}
+
+impl Relay {
+    /// Descarta los lotes más viejos que `BATCH_TTL` y las entradas
+    /// compartidas que caducaron
+    fn sweep(&mut self, now: Instant) {
+        let before = self.batches.len();
+        let stored = &mut self.stored;
+        self.batches.retain(|_, batch| {
+            let keep = now.duration_since(batch.stored_at) < BATCH_TTL;
+            if !keep {
+                *stored -= batch.sealed.len();
+            }
+            keep
+        });
+        if self.batches.len() < before {
+            info!("Descartados {} lotes caducados", before - self.batches.len());
+        }
+
+        let before = self.shares.len();
+        self.shares.retain(|_, share| {
+            let keep = now < share.expires_at;
+            if !keep {
+                *stored -= share.sealed.len();
+            }
+            keep
+        });
+        if self.shares.len() < before {
+            info!("Descartadas {} entradas compartidas caducadas", before - self.shares.len());
+        }
+    }
+
+    fn remove_share(&mut self, id: &Uuid) -> Option<Share> {
+        let share = self.shares.remove(id)?;
+        self.stored -= share.sealed.len();
+        Some(share)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    /// Nombres en minúsculas
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: &'static str,
+    revision: Option<u64>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn empty(status: &'static str) -> Self {
+        Self { status, revision: None, body: Vec::new() }
+    }
+}
+
+fn etag(revision: u64) -> String {
+    format!("\"{}\"", revision)
+}
+
+/// (hueco, destino, origen) de una ruta `/v1/<destino>/<origen>` o
+/// `/v1/<offer|answer>/<destino>/<origen>`
+fn parse_slot_path(path: &str) -> Option<(Slot, Uuid, Uuid)> {
+    let parts: Vec<&str> = path.strip_prefix("/v1/")?.split('/').collect();
+    let (slot, to, from) = match parts.as_slice() {
+        [to, from] => (Slot::Batch, to, from),
+        ["offer", to, from] => (Slot::Offer, to, from),
+        ["answer", to, from] => (Slot::Answer, to, from),
+        _ => return None,
+    };
+    Some((slot, Uuid::parse_str(to).ok()?, Uuid::parse_str(from).ok()?))
+}
+
+/// Entrada compartida de una ruta `/v1/share/<id>`
+fn parse_share_path(path: &str) -> Option<Uuid> {
+    Uuid::parse_str(path.strip_prefix("/v1/share/")?).ok()
+}
+
+/// Compara sin cortar en el primer byte distinto, para no dar pistas del token
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Si la petición trae el token del relay, o el relay no tiene
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    let given = request.header("authorization").and_then(|value| value.strip_prefix("Bearer "));
+    given.is_some_and(|given| same_token(given, token))
+}
+
+/// Entradas compartidas: dejarlas pide el token; leerlas, solo el enlace, y
+/// cada lectura gasta una vista; revocarlas, lo que dio quien las dejó. Una
+/// entrada caducada o agotada no existe.
+fn handle_share(relay: &mut Relay, request: Request, token: Option<&str>, id: Uuid, now: Instant) -> Response {
+    if relay.shares.get(&id).is_some_and(|share| now >= share.expires_at) {
+        relay.remove_share(&id);
+    }
+
+    match request.method.as_str() {
+        "PUT" => {
+            if !authorized(&request, token) {
+                return Response::empty("401 Unauthorized");
+            }
+            if relay.shares.contains_key(&id) {
+                return Response::empty("409 Conflict");
+            }
+            let expires_in = request.header("x-share-expires-in").and_then(|value| value.parse().ok());
+            let views = request.header("x-share-views").and_then(|value| value.parse().ok());
+            let owner = request.header("x-share-owner").filter(|owner| !owner.is_empty()).map(str::to_string);
+            let (Some(expires_in), Some(views), Some(owner)) = (expires_in, views, owner) else {
+                return Response::empty("400 Bad Request");
+            };
+            if views == 0 || views > MAX_SHARE_VIEWS {
+                return Response::empty("400 Bad Request");
+            }
+            if relay.stored + request.body.len() > MAX_STORED {
+                warn!("Relay lleno: se rechaza una entrada compartida de {} bytes", request.body.len());
+                return Response::empty("507 Insufficient Storage");
+            }
+            relay.stored += request.body.len();
+            relay.shares.insert(id, Share {
+                sealed: request.body,
+                owner,
+                expires_at: now + Duration::from_secs(expires_in).min(MAX_SHARE_TTL),
+                views_left: views,
+            });
+            Response::empty("204 No Content")
+        }
+        // HEAD no gasta una vista
+        "HEAD" => match relay.shares.get(&id) {
+            Some(_) => Response::empty("200 OK"),
+            None => Response::empty("404 Not Found"),
+        },
+        "GET" => {
+            let Some(share) = relay.shares.get_mut(&id) else {
+                return Response::empty("404 Not Found");
+            };
+            share.views_left -= 1;
+            let body = if share.views_left == 0 {
+                relay.remove_share(&id).map(|share| share.sealed).unwrap_or_default()
+            } else {
+                share.sealed.clone()
+            };
+            Response { status: "200 OK", revision: None, body }
+        }
+        "DELETE" => {
+            let Some(share) = relay.shares.get(&id) else {
+                return Response::empty("404 Not Found");
+            };
+            if !request.header("x-share-owner").is_some_and(|owner| same_token(owner, &share.owner)) {
+                return Response::empty("403 Forbidden");
+            }
+            relay.remove_share(&id);
+            Response::empty("204 No Content")
+        }
+        _ => Response::empty("405 Method Not Allowed"),
+    }
+}
+
+fn handle(relay: &mut Relay, request: Request, token: Option<&str>, now: Instant) -> Response {
+    if let Some(id) = parse_share_path(&request.path) {
+        return handle_share(relay, request, token, id, now);
+    }
+    if !authorized(&request, token) {
+        return Response::empty("401 Unauthorized");
+    }
+    let Some(key) = parse_slot_path(&request.path) else {
+        return Response::empty("404 Not Found");
+    };
+
+    match request.method.as_str() {
+        "PUT" => {
+            let replaced = relay.batches.get(&key).map_or(0, |batch| batch.sealed.len());
+            if relay.stored - replaced + request.body.len() > MAX_STORED {
+                warn!("Relay lleno: se rechaza un lote de {} bytes", request.body.len());
+                return Response::empty("507 Insufficient Storage");
+            }
+            relay.next_revision += 1;
+            let revision = relay.next_revision;
+            relay.stored = relay.stored - replaced + request.body.len();
+            relay.batches.insert(key, Batch { sealed: request.body, revision, stored_at: now });
+            Response { status: "204 No Content", revision: Some(revision), body: Vec::new() }
+        }
+        "GET" | "HEAD" => match relay.batches.get(&key) {
+            Some(batch) => Response { status: "200 OK", revision: Some(batch.revision), body: batch.sealed.clone() },
+            None => Response::empty("404 Not Found"),
+        },
+        "DELETE" => {
+            let Some(batch) = relay.batches.get(&key) else {
+                return Response::empty("404 Not Found");
+            };
+            // Si el origen ya dejó otro lote, ese se queda
+            if request.header("if-match").is_some_and(|expected| expected != etag(batch.revision)) {
+                return Response::empty("412 Precondition Failed");
+            }
+            if let Some(batch) = relay.batches.remove(&key) {
+                relay.stored -= batch.sealed.len();
+            }
+            Response::empty("204 No Content")
+        }
+        _ => Response::empty("405 Method Not Allowed"),
+    }
+}
+
+/// Lee una petición HTTP/1.1 con su cuerpo
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or_else(|| anyhow!("Petición vacía"))?.to_string();
+    let path = parts.next().ok_or_else(|| anyhow!("Petición sin ruta"))?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(anyhow!("Demasiadas cabeceras"));
+        }
+        let (name, value) = header.split_once(':').ok_or_else(|| anyhow!("Cabecera inválida"))?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let length: usize = match headers.get("content-length") {
+        Some(length) => length.parse()?,
+        None => 0,
+    };
+    if length > MAX_BATCH_SIZE {
+        return Err(anyhow!("Lote de {} bytes, demasiado grande", length));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Request { method, path, headers, body })
+}
+
+async fn write_response(stream: &mut TcpStream, response: &Response, with_body: bool) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    if let Some(revision) = response.revision {
+        head.push_str(&format!("ETag: {}\r\n", etag(revision)));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    if with_body {
+        stream.write_all(&response.body).await?;
+    }
+    Ok(())
+}
+
+/// Atiende una conexión: una petición y se cierra
+async fn serve(mut stream: TcpStream, relay: Arc<Mutex<Relay>>, token: Option<Arc<str>>) -> Result<()> {
+    let request = match tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => {
+            write_response(&mut stream, &Response::empty("400 Bad Request"), false).await?;
+            return Err(e);
+        }
+        Err(_) => return Err(anyhow!("Se agotó el tiempo para recibir la petición")),
+    };
+    let with_body = request.method != "HEAD";
+    let response = handle(&mut *relay.lock().await, request, token.as_deref(), Instant::now());
+    write_response(&mut stream, &response, with_body).await
+}
+
+struct Options {
+    listen: SocketAddr,
+    token: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut listen = DEFAULT_LISTEN.to_string();
+    let mut token = std::env::var("ALOHOPASS_RELAY_TOKEN").ok();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => listen = args.next().ok_or_else(|| anyhow!("Falta la dirección de --listen"))?,
+            "--token" => token = Some(args.next().ok_or_else(|| anyhow!("Falta el valor de --token"))?),
+            other => return Err(anyhow!("Opción desconocida: {}", other)),
+        }
+    }
+    Ok(Options {
+        listen: listen.parse().map_err(|e| anyhow!("Dirección inválida {}: {}", listen, e))?,
+        token: token.filter(|token| !token.is_empty()),
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let options = parse_args(std::env::args().skip(1))?;
+    if options.token.is_none() && !options.listen.ip().is_loopback() {
+        warn!("Relay sin token en {}: cualquiera puede dejar lotes", options.listen);
+    }
+
+    let listener = TcpListener::bind(options.listen).await?;
+    info!("Relay de Alohopass escuchando en {}", options.listen);
+    let relay = Arc::new(Mutex::new(Relay::default()));
+    let token: Option<Arc<str>> = options.token.map(Arc::from);
+
+    let sweeper = relay.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweeper.lock().await.sweep(Instant::now());
+        }
+    });
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let (relay, token) = (relay.clone(), token.clone());
+        tokio::spawn(async move {
+            if let Err(e) = serve(stream, relay, token).await {
+                warn!("Petición de {} fallida: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, headers: &[(&str, &str)], body: &[u8]) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            body: body.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_keeps_a_batch_replaced_while_it_was_read() {
+        let mut relay = Relay::default();
+        let now = Instant::now();
+        let path = format!("/v1/{}/{}", Uuid::new_v4(), Uuid::new_v4());
+
+        let first = handle(&mut relay, request("PUT", &path, &[], b"uno"), None, now).revision.unwrap();
+        let read = handle(&mut relay, request("GET", &path, &[], b""), None, now);
+        assert_eq!(read.body, b"uno");
+        assert_eq!(read.revision, Some(first));
+
+        handle(&mut relay, request("PUT", &path, &[], b"dos"), None, now);
+        let stale = handle(&mut relay, request("DELETE", &path, &[("if-match", &etag(first))], b""), None, now);
+        assert_eq!(stale.status, "412 Precondition Failed");
+
+        let current = handle(&mut relay, request("HEAD", &path, &[], b""), None, now).revision.unwrap();
+        let taken = handle(&mut relay, request("DELETE", &path, &[("if-match", &etag(current))], b""), None, now);
+        assert_eq!(taken.status, "204 No Content");
+        assert_eq!(handle(&mut relay, request("GET", &path, &[], b""), None, now).status, "404 Not Found");
+        assert_eq!(relay.stored, 0);
+    }
+
+    #[test]
+    fn test_keeps_signaling_apart_from_batches() {
+        let mut relay = Relay::default();
+        let now = Instant::now();
+        let (to, from) = (Uuid::new_v4(), Uuid::new_v4());
+        handle(&mut relay, request("PUT", &format!("/v1/{}/{}", to, from), &[], b"lote"), None, now);
+        handle(&mut relay, request("PUT", &format!("/v1/offer/{}/{}", to, from), &[], b"oferta"), None, now);
+        assert_eq!(handle(&mut relay, request("GET", &format!("/v1/{}/{}", to, from), &[], b""), None, now).body, b"lote");
+        assert_eq!(handle(&mut relay, request("GET", &format!("/v1/offer/{}/{}", to, from), &[], b""), None, now).body, b"oferta");
+        let answer = handle(&mut relay, request("GET", &format!("/v1/answer/{}/{}", to, from), &[], b""), None, now);
+        assert_eq!(answer.status, "404 Not Found");
+        assert!(parse_slot_path(&format!("/v1/otro/{}/{}", to, from)).is_none());
+    }
+
+    #[test]
+    fn test_requires_the_token() {
+        let mut relay = Relay::default();
+        let now = Instant::now();
+        let path = format!("/v1/{}/{}", Uuid::new_v4(), Uuid::new_v4());
+        let denied = handle(&mut relay, request("PUT", &path, &[("authorization", "Bearer otro")], b"x"), Some("secreto"), now);
+        assert_eq!(denied.status, "401 Unauthorized");
+        let allowed = handle(&mut relay, request("PUT", &path, &[("authorization", "Bearer secreto")], b"x"), Some("secreto"), now);
+        assert_eq!(allowed.status, "204 No Content");
+    }
+
+    #[test]
+    fn test_drops_batches_nobody_picks_up() {
+        let mut relay = Relay::default();
+        let now = Instant::now();
+        let path = format!("/v1/{}/{}", Uuid::new_v4(), Uuid::new_v4());
+        handle(&mut relay, request("PUT", &path, &[], b"lote"), None, now);
+        relay.sweep(now + BATCH_TTL);
+        assert!(relay.batches.is_empty());
+        assert_eq!(relay.stored, 0);
+        assert!(parse_slot_path("/v1/no-es-un-id/otro").is_none());
+    }
+
+    #[test]
+    fn test_serves_a_share_until_its_views_run_out() {
+        let mut relay = Relay::default();
+        let now = Instant::now();
+        let path = format!("/v1/share/{}", Uuid::new_v4());
+        let share = [("x-share-expires-in", "3600"), ("x-share-views", "2"), ("x-share-owner", "dueño")];
+
+        // Dejarla pide el token; leerla no
+        assert_eq!(handle(&mut relay, request("PUT", &path, &share, b"sellada"), Some("secreto"), now).status, "401 Unauthorized");
+        let with_token = [share.as_slice(), &[("authorization", "Bearer secreto")]].concat();
+        assert_eq!(handle(&mut relay, request("PUT", &path, &with_token, b"sellada"), Some("secreto"), now).status, "204 No Content");
+        assert_eq!(handle(&mut relay, request("PUT", &path, &with_token, b"otra"), Some("secreto"), now).status, "409 Conflict");
+        assert_eq!(handle(&mut relay, request("HEAD", &path, &[], b""), Some("secreto"), now).status, "200 OK");
+        assert_eq!(handle(&mut relay, request("GET", &path, &[], b""), Some("secreto"), now).body, b"sellada");
+        assert_eq!(handle(&mut relay, request("GET", &path, &[], b""), Some("secreto"), now).body, b"sellada");
+        assert_eq!(handle(&mut relay, request("GET", &path, &[], b""), Some("secreto"), now).status, "404 Not Found");
+        assert_eq!(relay.stored, 0);
+
+        let missing = [("x-share-views", "2"), ("x-share-owner", "dueño")];
+        assert_eq!(handle(&mut relay, request("PUT", &path, &missing, b"x"), None, now).status, "400 Bad Request");
+        assert!(parse_share_path("/v1/share/no-es-un-id").is_none());
+    }
+
+    #[test]
+    fn test_shares_expire_and_only_the_owner_revokes_them() {
+        let mut relay = Relay::default();
+        let now = Instant::now();
+        let path = format!("/v1/share/{}", Uuid::new_v4());
+        // Más de lo permitido queda en el máximo
+        let share = [("x-share-expires-in", "99999999"), ("x-share-views", "5"), ("x-share-owner", "dueño")];
+        handle(&mut relay, request("PUT", &path, &share, b"sellada"), None, now);
+        assert_eq!(handle(&mut relay, request("GET", &path, &[], b""), None, now + MAX_SHARE_TTL).status, "404 Not Found");
+        assert!(relay.shares.is_empty());
+
+        handle(&mut relay, request("PUT", &path, &share, b"sellada"), None, now);
+        let stranger = handle(&mut relay, request("DELETE", &path, &[("x-share-owner", "otro")], b""), None, now);
+        assert_eq!(stranger.status, "403 Forbidden");
+        let owner = handle(&mut relay, request("DELETE", &path, &[("x-share-owner", "dueño")], b""), None, now);
+        assert_eq!(owner.status, "204 No Content");
+        assert_eq!(handle(&mut relay, request("GET", &path, &[], b""), None, now).status, "404 Not Found");
+
+        handle(&mut relay, request("PUT", &path, &share, b"sellada"), None, now);
+        relay.sweep(now + MAX_SHARE_TTL);
+        assert!(relay.shares.is_empty());
+        assert_eq!(relay.stored, 0);
+    }
+}
@@ -0,0 +1,83 @@
+//! Consulta a la API Pwned Passwords de Have I Been Pwned
+//!
+//! Usa k-anonimato: solo se envían los 5 primeros caracteres del SHA-1 de la
+//! contraseña y la comparación con el resto del hash se hace en local. Se pide
+//! relleno (`Add-Padding`) para que el tamaño de la respuesta no revele nada.
+
+use anyhow::{anyhow, Result};
+use sha1::{Digest, Sha1};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const RANGE_API_URL: &str = "https://api.pwnedpasswords.com/range/";
+
+fn client() -> Result<&'static reqwest::Client> {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent(concat!("Alohopass/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+/// Divide el SHA-1 en mayúsculas en el prefijo que se envía y el sufijo que queda en local
+pub fn hash_parts(password: &str) -> (String, String) {
+    let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = hash.split_at(5);
+    (prefix.to_string(), suffix.to_string())
+}
+
+/// Busca el sufijo en la respuesta (`SUFIJO:VECES` por línea). Las líneas de
+/// relleno tienen cero apariciones, así que no cuentan como filtración.
+pub fn count_in_range(body: &str, suffix: &str) -> u64 {
+    body.lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Descarga el rango de hashes que comparten el prefijo
+pub async fn fetch_range(prefix: &str) -> Result<String> {
+    let response = client()?
+        .get(format!("{}{}", RANGE_API_URL, prefix))
+        .header("Add-Padding", "true")
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("La API de Have I Been Pwned respondió {}", response.status()));
+    }
+    Ok(response.text().await?)
+}
+
+/// Veces que la contraseña aparece en filtraciones conocidas
+pub async fn breach_count(password: &str) -> Result<u64> {
+    let (prefix, suffix) = hash_parts(password);
+    let body = fetch_range(&prefix).await?;
+    Ok(count_in_range(&body, &suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_parts() {
+        let (prefix, suffix) = hash_parts("password");
+        assert_eq!(prefix, "5BAA6");
+        assert_eq!(suffix, "1E4C9B93F3F0682250B6CF8331B7EE68FD8");
+    }
+
+    #[test]
+    fn test_count_in_range_ignores_padding() {
+        let body = "0018A45C4D1DEF81644B54AB7F969B88D65:10\r\n\
+                    1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\r\n\
+                    011053FD0102E94D6AE2F8B83D76FAF94F6:0\r\n";
+        assert_eq!(count_in_range(body, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"), 9659365);
+        assert_eq!(count_in_range(body, "011053FD0102E94D6AE2F8B83D76FAF94F6"), 0);
+        assert_eq!(count_in_range(body, "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), 0);
+    }
+}
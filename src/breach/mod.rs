@@ -0,0 +1,19 @@
+//! Comprobación de contraseñas filtradas
+//!
+//! El origen de los datos se elige en la configuración (`breach_check`).
+
+pub mod hibp;
+pub mod offline;
+
+use crate::models::BreachCheckBackend;
+use anyhow::Result;
+
+/// Veces que la contraseña aparece en filtraciones; `None` si la comprobación está desactivada.
+/// El conjunto de datos local solo indica presencia, así que devuelve 1 si la encuentra.
+pub async fn breach_count(backend: BreachCheckBackend, password: &str) -> Result<Option<u64>> {
+    match backend {
+        BreachCheckBackend::Disabled => Ok(None),
+        BreachCheckBackend::Hibp => hibp::breach_count(password).await.map(Some),
+        BreachCheckBackend::Offline => offline::contains_password(password).map(|found| Some(u64::from(found))),
+    }
+}
@@ -0,0 +1,272 @@
+//! Comprobación de filtraciones sin conexión mediante un filtro de Bloom local
+//!
+//! El filtro se construye a partir del listado de hashes SHA-1 de Pwned Passwords
+//! (`HASH:VECES` por línea, como lo descarga `haveibeenpwned-downloader`) o se
+//! importa ya construido. Un filtro de Bloom no guarda las veces que apareció
+//! cada contraseña, solo si apareció, y admite una pequeña tasa de falsos positivos.
+//!
+//! Formato del archivo (little endian):
+//! `ALOHOBF1` | bits: u64 | funciones hash: u32 | elementos: u64 | bits del filtro
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+const MAGIC: &[u8; 8] = b"ALOHOBF1";
+const HEADER_LEN: usize = 8 + 8 + 4 + 8;
+/// Nombre del filtro dentro del directorio de datos de la aplicación
+const FILTER_FILE_NAME: &str = "pwned-passwords.bloom";
+/// Tasa de falsos positivos al construir un filtro desde el listado de hashes
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.001;
+/// Bytes mínimos por línea del listado, para estimar cuántos hashes tiene
+const MIN_BYTES_PER_LINE: u64 = 42;
+
+/// Filtro cargado, compartido por todas las comprobaciones
+static LOADED_FILTER: RwLock<Option<Arc<BloomFilter>>> = RwLock::new(None);
+
+/// Resumen del conjunto de datos instalado
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetInfo {
+    pub items: u64,
+    pub size_bytes: u64,
+    pub false_positive_rate: f64,
+}
+
+enum FilterBits {
+    Mapped(memmap2::Mmap),
+    Owned(Vec<u8>),
+}
+
+pub struct BloomFilter {
+    bits: FilterBits,
+    bit_count: u64,
+    hashes: u32,
+    items: u64,
+}
+
+/// SHA-1 de la contraseña, igual que en los listados de Pwned Passwords
+pub fn sha1_bytes(password: &str) -> [u8; 20] {
+    Sha1::digest(password.as_bytes()).into()
+}
+
+/// Posiciones de los bits de un hash (doble hashing sobre el SHA-1, que ya es uniforme)
+fn bit_positions(hash: &[u8; 20], bit_count: u64, hashes: u32) -> impl Iterator<Item = u64> {
+    let h1 = u64::from_le_bytes(hash[0..8].try_into().expect("8 bytes"));
+    let h2 = u64::from_le_bytes(hash[8..16].try_into().expect("8 bytes")) | 1;
+    (0..hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bit_count)
+}
+
+impl BloomFilter {
+    /// Filtro vacío dimensionado para `items` elementos con la tasa de falsos positivos dada
+    pub fn with_capacity(items: u64, false_positive_rate: f64) -> Self {
+        let items = items.max(1);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = ((-(items as f64) * false_positive_rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let hashes = ((bit_count as f64 / items as f64) * ln2).round().clamp(1.0, 32.0) as u32;
+        Self {
+            bits: FilterBits::Owned(vec![0; bit_count.div_ceil(8) as usize]),
+            bit_count,
+            hashes,
+            items: 0,
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match &self.bits {
+            FilterBits::Mapped(map) => &map[HEADER_LEN..],
+            FilterBits::Owned(bytes) => bytes,
+        }
+    }
+
+    pub fn insert(&mut self, hash: &[u8; 20]) {
+        let (bit_count, hashes) = (self.bit_count, self.hashes);
+        let FilterBits::Owned(bytes) = &mut self.bits else {
+            panic!("Un filtro abierto desde disco es de solo lectura");
+        };
+        for position in bit_positions(hash, bit_count, hashes) {
+            bytes[(position / 8) as usize] |= 1 << (position % 8);
+        }
+        self.items += 1;
+    }
+
+    pub fn contains(&self, hash: &[u8; 20]) -> bool {
+        let bytes = self.bytes();
+        bit_positions(hash, self.bit_count, self.hashes)
+            .all(|position| bytes[(position / 8) as usize] & (1 << (position % 8)) != 0)
+    }
+
+    /// Tasa de falsos positivos esperada con los elementos actuales
+    pub fn false_positive_rate(&self) -> f64 {
+        let fill = 1.0 - (-(self.hashes as f64) * self.items as f64 / self.bit_count as f64).exp();
+        fill.powi(self.hashes as i32)
+    }
+
+    pub fn info(&self) -> DatasetInfo {
+        DatasetInfo {
+            items: self.items,
+            size_bytes: (HEADER_LEN + self.bytes().len()) as u64,
+            false_positive_rate: self.false_positive_rate(),
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.bit_count.to_le_bytes())?;
+        writer.write_all(&self.hashes.to_le_bytes())?;
+        writer.write_all(&self.items.to_le_bytes())?;
+        writer.write_all(self.bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Abre un filtro guardado sin cargarlo entero en memoria
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: el archivo solo lo escribe la importación, que descarga el filtro antes de reemplazarlo
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        let (bit_count, hashes, items) = parse_header(&map)?;
+        if (map.len() - HEADER_LEN) as u64 != bit_count.div_ceil(8) {
+            return Err(anyhow!("El tamaño del filtro no coincide con su cabecera"));
+        }
+        Ok(Self { bits: FilterBits::Mapped(map), bit_count, hashes, items })
+    }
+}
+
+fn parse_header(data: &[u8]) -> Result<(u64, u32, u64)> {
+    if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+        return Err(anyhow!("El archivo no es un filtro de contraseñas filtradas de Alohopass"));
+    }
+    let bit_count = u64::from_le_bytes(data[8..16].try_into()?);
+    let hashes = u32::from_le_bytes(data[16..20].try_into()?);
+    let items = u64::from_le_bytes(data[20..28].try_into()?);
+    if bit_count == 0 || hashes == 0 {
+        return Err(anyhow!("Cabecera del filtro inválida"));
+    }
+    Ok((bit_count, hashes, items))
+}
+
+/// Construye un filtro a partir de un listado de hashes SHA-1 (`HASH` o `HASH:VECES` por línea)
+pub fn build_from_hash_list<R: BufRead>(reader: R, expected_items: u64) -> Result<BloomFilter> {
+    let mut filter = BloomFilter::with_capacity(expected_items, DEFAULT_FALSE_POSITIVE_RATE);
+    let mut skipped = 0u64;
+    for line in reader.lines() {
+        let line = line?;
+        let hex_hash = line.split(':').next().unwrap_or_default().trim();
+        match hex::decode(hex_hash).ok().and_then(|bytes| <[u8; 20]>::try_from(bytes).ok()) {
+            Some(hash) => filter.insert(&hash),
+            None if hex_hash.is_empty() => {}
+            None => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        warn!("Se ignoraron {} líneas que no son hashes SHA-1", skipped);
+    }
+    if filter.items == 0 {
+        return Err(anyhow!("El archivo no contiene hashes SHA-1"));
+    }
+    Ok(filter)
+}
+
+/// Ruta del filtro instalado, en el directorio de datos de la aplicación. No
+/// acompaña a la base cuando se mueve: puede ocupar varios GB y no hace falta
+/// sincronizarlo.
+pub fn dataset_path() -> Result<PathBuf> {
+    Ok(crate::database::app_data_dir()?.join(FILTER_FILE_NAME))
+}
+
+/// Importa un filtro ya construido o un listado de hashes y lo instala como conjunto de datos local.
+/// Puede tardar varios minutos con el listado completo de Pwned Passwords.
+pub fn import_dataset(source: &Path) -> Result<DatasetInfo> {
+    let destination = dataset_path()?;
+    let temp_path = destination.with_extension("bloom.tmp");
+
+    let mut magic = [0u8; 8];
+    let is_filter = File::open(source)?.read_exact(&mut magic).is_ok() && &magic == MAGIC;
+    if is_filter {
+        info!("Importando filtro de Bloom desde {}", source.display());
+        BloomFilter::open(source)?;
+        std::fs::copy(source, &temp_path)?;
+    } else {
+        info!("Construyendo filtro de Bloom desde el listado {}", source.display());
+        let expected_items = std::fs::metadata(source)?.len() / MIN_BYTES_PER_LINE;
+        let filter = build_from_hash_list(BufReader::new(File::open(source)?), expected_items)?;
+        filter.write_to(BufWriter::new(File::create(&temp_path)?))?;
+    }
+
+    // Liberar el filtro cargado antes de reemplazar el archivo (en Windows no se puede sobrescribir un archivo mapeado)
+    unload();
+    std::fs::rename(&temp_path, &destination)?;
+    let info = load()?.info();
+    info!("Conjunto de datos de filtraciones instalado: {} hashes", info.items);
+    Ok(info)
+}
+
+fn load() -> Result<Arc<BloomFilter>> {
+    if let Some(filter) = LOADED_FILTER.read().map_err(|_| anyhow!("Error al acceder al filtro"))?.as_ref() {
+        return Ok(filter.clone());
+    }
+    let path = dataset_path()?;
+    if !path.exists() {
+        return Err(anyhow!("No hay un conjunto de datos de filtraciones importado"));
+    }
+    let filter = Arc::new(BloomFilter::open(&path)?);
+    *LOADED_FILTER.write().map_err(|_| anyhow!("Error al acceder al filtro"))? = Some(filter.clone());
+    Ok(filter)
+}
+
+fn unload() {
+    if let Ok(mut loaded) = LOADED_FILTER.write() {
+        *loaded = None;
+    }
+}
+
+/// Información del conjunto de datos instalado, si hay alguno
+pub fn dataset_info() -> Result<Option<DatasetInfo>> {
+    if !dataset_path()?.exists() {
+        return Ok(None);
+    }
+    Ok(Some(load()?.info()))
+}
+
+/// Indica si la contraseña aparece en el conjunto de datos local
+pub fn contains_password(password: &str) -> Result<bool> {
+    Ok(load()?.contains(&sha1_bytes(password)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter_round_trip() {
+        let list = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\n\
+                    7C4A8D09CA3762AF61E59520943DC26494F8941B:37359195\n\
+                    no es un hash\n";
+        let filter = build_from_hash_list(list.as_bytes(), 2).unwrap();
+        assert!(filter.contains(&sha1_bytes("password")));
+        assert!(filter.contains(&sha1_bytes("123456")));
+        assert!(!filter.contains(&sha1_bytes("una frase larga y poco común")));
+
+        let mut bytes = Vec::new();
+        filter.write_to(&mut bytes).unwrap();
+        let (bit_count, hashes, items) = parse_header(&bytes).unwrap();
+        assert_eq!((bit_count, hashes, items), (filter.bit_count, filter.hashes, 2));
+    }
+
+    #[test]
+    fn test_false_positive_rate_is_close_to_target() {
+        let mut filter = BloomFilter::with_capacity(1000, DEFAULT_FALSE_POSITIVE_RATE);
+        for i in 0..1000 {
+            filter.insert(&sha1_bytes(&format!("filtrada-{}", i)));
+        }
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(&sha1_bytes(&format!("segura-{}", i))))
+            .count();
+        assert!(false_positives < 50, "demasiados falsos positivos: {}", false_positives);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::browser_extension::protocol::*;
-use crate::sync::SyncManager;
+use crate::models::EntryId;
+use crate::sync::SharedSyncManager;
 use log::{info, error, warn};
 use serde_json;
 use std::collections::HashMap;
@@ -14,14 +15,14 @@ use tokio::sync::mpsc;
 #[derive(Clone)]
 pub struct BrowserExtensionManager {
     is_running: Arc<Mutex<bool>>,
-    sync_manager: Arc<Mutex<Option<SyncManager>>>,
+    sync_manager: SharedSyncManager,
     config: PluginConfig,
     connections: Arc<Mutex<HashMap<String, TcpStream>>>,
 }
 
 impl BrowserExtensionManager {
     /// Crear una nueva instancia del gestor
-    pub fn new(sync_manager: Arc<Mutex<Option<SyncManager>>>) -> Self {
+    pub fn new(sync_manager: SharedSyncManager) -> Self {
         Self {
             is_running: Arc::new(Mutex::new(false)),
             sync_manager,
@@ -62,7 +63,7 @@ impl BrowserExtensionManager {
     fn run_native_host(
         is_running: Arc<Mutex<bool>>,
         connections: Arc<Mutex<HashMap<String, TcpStream>>>,
-        sync_manager: Arc<Mutex<Option<SyncManager>>>,
+        sync_manager: SharedSyncManager,
         config: PluginConfig,
     ) -> Result<(), Box<dyn std::error::Error>> {
         info!("🔌 AlohoPass: Iniciando servidor TCP para Native Messaging");
@@ -154,7 +155,7 @@ impl BrowserExtensionManager {
         mut stream: TcpStream,
         stream_id: String,
         connections: Arc<Mutex<HashMap<String, TcpStream>>>,
-        sync_manager: Arc<Mutex<Option<SyncManager>>>,
+        sync_manager: SharedSyncManager,
     ) -> Result<(), Box<dyn std::error::Error>> {
         info!("🔌 AlohoPass: Manejando conexión: {}", stream_id);
 
@@ -221,7 +222,7 @@ impl BrowserExtensionManager {
     /// Procesar un mensaje del plugin
     fn process_message(
         message: BrowserMessage,
-        sync_manager: &Arc<Mutex<Option<SyncManager>>>,
+        sync_manager: &SharedSyncManager,
     ) -> BrowserResponse {
         info!("🔌 AlohoPass: Procesando mensaje: {:?}", message);
 
@@ -239,7 +240,7 @@ impl BrowserExtensionManager {
                 // Por ahora, retornar contraseñas de ejemplo
                 let passwords = vec![
                     BrowserPassword {
-                        id: "1".to_string(),
+                        id: EntryId::new(),
                         title: "Cuenta principal".to_string(),
                         username: "usuario@ejemplo.com".to_string(),
                         email: Some("usuario@ejemplo.com".to_string()),
@@ -270,7 +271,7 @@ impl BrowserExtensionManager {
                 
                 let passwords = vec![
                     BrowserPassword {
-                        id: "1".to_string(),
+                        id: EntryId::new(),
                         title: "Resultado de búsqueda".to_string(),
                         username: "usuario@ejemplo.com".to_string(),
                         email: Some("usuario@ejemplo.com".to_string()),
@@ -303,6 +304,20 @@ impl BrowserExtensionManager {
 
                 BrowserResponse::success(serde_json::to_value(stats).unwrap())
             }
+
+            BrowserMessage::GeneratePin { length } => {
+                info!("🔌 AlohoPass: Generando PIN de {} dígitos", length);
+                match crate::generator::generate_pin(length) {
+                    Ok(pin) => BrowserResponse::success(serde_json::to_value(pin).unwrap()),
+                    Err(e) => BrowserResponse::error(e.to_string()),
+                }
+            }
+
+            BrowserMessage::GenerateUsername { options } => {
+                info!("🔌 AlohoPass: Generando nombre de usuario");
+                let username = crate::generator::generate_username(&options);
+                BrowserResponse::success(serde_json::to_value(username).unwrap())
+            }
         }
     }
 
@@ -1,3 +1,4 @@
+use crate::models::EntryId;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -16,7 +17,7 @@ pub enum BrowserMessage {
     
     /// Crear nueva contraseña
     CreatePassword {
-        entry: PasswordEntry,
+        entry: CapturedCredentials,
     },
     
     /// Buscar contraseñas
@@ -29,6 +30,17 @@ pub enum BrowserMessage {
     
     /// Obtener estadísticas
     GetStats,
+    
+    /// Generar un PIN numérico para un formulario de registro
+    GeneratePin {
+        length: usize,
+    },
+    
+    /// Generar un nombre de usuario para un formulario de registro
+    GenerateUsername {
+        #[serde(default)]
+        options: crate::models::UsernameGenerationRequest,
+    },
 }
 
 /// Tipos de formularios que puede detectar el plugin
@@ -38,9 +50,10 @@ pub enum FormType {
     Signup,
 }
 
-/// Entrada de contraseña desde el plugin
+/// Credenciales que el plugin capturó de un formulario. No es una entrada de la
+/// bóveda (`models::PasswordEntry`): le faltan el id y las fechas.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PasswordEntry {
+pub struct CapturedCredentials {
     pub title: String,
     pub username: String,
     pub password: String,
@@ -96,7 +109,7 @@ pub struct BrowserStats {
 /// Contraseña para el plugin (sin datos sensibles)
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BrowserPassword {
-    pub id: String,
+    pub id: EntryId,
     pub title: String,
     pub username: String,
     pub email: Option<String>,
@@ -0,0 +1,123 @@
+//! Árbol de categorías
+//!
+//! Las categorías se guardan planas con `parent_id`; aquí se arma el árbol que
+//! muestra el frontend y se comprueba que mover una categoría no cree un ciclo.
+
+use crate::models::{Category, CategoryId, CategoryNode, CategorySummary};
+use std::collections::{HashMap, HashSet};
+
+/// Arma el árbol conservando el orden de `categories` entre hermanas. Las
+/// categorías cuyo padre no existe quedan en la raíz, igual que las que forman
+/// un ciclo (solo posible con datos dañados).
+pub fn build_tree(categories: Vec<CategorySummary>) -> Vec<CategoryNode> {
+    let ids: HashSet<CategoryId> = categories.iter().map(|summary| summary.category.id).collect();
+    let mut children: HashMap<Option<CategoryId>, Vec<CategorySummary>> = HashMap::new();
+    for summary in categories {
+        let parent = summary.category.parent_id.filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(summary);
+    }
+
+    let mut roots = attach(None, &mut children);
+    // Lo que queda sin colgar de la raíz está en un ciclo: se corta por la primera
+    while let Some(parent) = children.keys().next().copied() {
+        let mut summaries = children.remove(&parent).unwrap_or_default();
+        let first = summaries.remove(0);
+        if !summaries.is_empty() {
+            children.insert(parent, summaries);
+        }
+        roots.push(node(first, &mut children));
+    }
+    roots
+}
+
+fn attach(parent: Option<CategoryId>, children: &mut HashMap<Option<CategoryId>, Vec<CategorySummary>>) -> Vec<CategoryNode> {
+    children.remove(&parent)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|summary| node(summary, children))
+        .collect()
+}
+
+fn node(summary: CategorySummary, children: &mut HashMap<Option<CategoryId>, Vec<CategorySummary>>) -> CategoryNode {
+    let nested = attach(Some(summary.category.id), children);
+    let total_count = summary.entry_count + nested.iter().map(|child| child.total_count).sum::<usize>();
+    CategoryNode { summary, total_count, children: nested }
+}
+
+/// Indica si colgar `id` de `new_parent` crearía un ciclo: el nuevo padre es la
+/// propia categoría o una de sus descendientes
+pub fn creates_cycle(categories: &[Category], id: CategoryId, new_parent: CategoryId) -> bool {
+    let parents: HashMap<CategoryId, Option<CategoryId>> = categories.iter()
+        .map(|category| (category.id, category.parent_id))
+        .collect();
+    let mut visited = HashSet::new();
+    let mut current = Some(new_parent);
+    while let Some(ancestor) = current {
+        if ancestor == id || !visited.insert(ancestor) {
+            return true;
+        }
+        current = parents.get(&ancestor).copied().flatten();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Id fijo derivado del nombre, para poder nombrar al padre
+    fn id(name: &str) -> CategoryId {
+        let mut bytes = [0; 16];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        uuid::Uuid::from_bytes(bytes).into()
+    }
+
+    fn category(name: &str, parent: Option<&str>) -> Category {
+        Category {
+            id: id(name),
+            name: name.to_string(),
+            color: "#6B7280".to_string(),
+            icon: None,
+            parent_id: parent.map(id),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    fn summary(name: &str, parent: Option<&str>, entry_count: usize) -> CategorySummary {
+        CategorySummary { category: category(name, parent), entry_count }
+    }
+
+    #[test]
+    fn test_builds_nested_tree_with_totals() {
+        let tree = build_tree(vec![
+            summary("clientes", Some("trabajo"), 2),
+            summary("personal", None, 1),
+            summary("proyectos", Some("clientes"), 3),
+            summary("huerfana", Some("borrada"), 4),
+            summary("trabajo", None, 0),
+        ]);
+
+        let roots: Vec<&str> = tree.iter().map(|node| node.summary.category.name.as_str()).collect();
+        assert_eq!(roots, ["personal", "huerfana", "trabajo"]);
+        let work = &tree[2];
+        assert_eq!(work.total_count, 5);
+        assert_eq!(work.children[0].summary.category.name, "clientes");
+        assert_eq!(work.children[0].children[0].summary.category.name, "proyectos");
+    }
+
+    #[test]
+    fn test_breaks_stored_cycles() {
+        let tree = build_tree(vec![summary("a", Some("b"), 1), summary("b", Some("a"), 1)]);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].total_count, 2);
+    }
+
+    #[test]
+    fn test_detects_cycles() {
+        let categories = [category("trabajo", None), category("clientes", Some("trabajo")), category("proyectos", Some("clientes"))];
+        assert!(creates_cycle(&categories, id("trabajo"), id("proyectos")));
+        assert!(creates_cycle(&categories, id("clientes"), id("clientes")));
+        assert!(!creates_cycle(&categories, id("proyectos"), id("trabajo")));
+        assert!(!creates_cycle(&categories, id("trabajo"), id("desconocida")));
+    }
+}
@@ -0,0 +1,107 @@
+//! Copia de secretos al portapapeles desde el backend
+//!
+//! El valor nunca pasa por el frontend: se desencripta en Rust y se escribe
+//! directamente en el portapapeles del sistema. Cuando el sistema lo permite,
+//! la copia se marca como transitoria para que no quede en el historial ni se
+//! sincronice con el portapapeles en la nube.
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Default)]
+struct Inner {
+    clipboard: Mutex<Option<arboard::Clipboard>>,
+    /// Se incrementa en cada copia para que solo el último temporizador limpie
+    generation: AtomicU64,
+}
+
+/// Gestor del portapapeles con limpieza automática
+#[derive(Clone, Default)]
+pub struct ClipboardManager {
+    inner: Arc<Inner>,
+}
+
+impl ClipboardManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copia un secreto y programa su borrado tras `clear_after`, si se indica
+    pub fn copy_secret(&self, text: &str, clear_after: Option<Duration>) -> Result<()> {
+        self.with_clipboard(|clipboard| set_transient_text(clipboard, text))?;
+        let generation = self.inner.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        if let Some(delay) = clear_after {
+            let manager = self.clone();
+            let expected = Sha256::digest(text.as_bytes());
+            std::thread::spawn(move || {
+                std::thread::sleep(delay);
+                if manager.inner.generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                // Solo limpiar si el usuario no copió otra cosa mientras tanto
+                let result = manager.with_clipboard(|clipboard| {
+                    let still_ours = clipboard.get_text()
+                        .map(|current| Sha256::digest(current.as_bytes()) == expected)
+                        .unwrap_or(false);
+                    if still_ours {
+                        clipboard.clear()?;
+                        info!("Portapapeles limpiado automáticamente");
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    warn!("No se pudo limpiar el portapapeles: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn with_clipboard<T>(&self, f: impl FnOnce(&mut arboard::Clipboard) -> Result<T>) -> Result<T> {
+        let mut guard = self.inner.clipboard.lock()
+            .map_err(|_| anyhow!("Error al acceder al portapapeles"))?;
+        if guard.is_none() {
+            *guard = Some(arboard::Clipboard::new()?);
+        }
+        f(guard.as_mut().expect("portapapeles inicializado"))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn set_transient_text(clipboard: &mut arboard::Clipboard, text: &str) -> Result<()> {
+    use arboard::SetExtWindows;
+    clipboard.set()
+        .exclude_from_history()
+        .exclude_from_cloud()
+        .text(text)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_transient_text(clipboard: &mut arboard::Clipboard, text: &str) -> Result<()> {
+    use arboard::SetExtLinux;
+    clipboard.set()
+        .exclude_from_history()
+        .text(text)?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn set_transient_text(clipboard: &mut arboard::Clipboard, text: &str) -> Result<()> {
+    use arboard::SetExtApple;
+    clipboard.set()
+        .exclude_from_history()
+        .text(text)?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+fn set_transient_text(clipboard: &mut arboard::Clipboard, text: &str) -> Result<()> {
+    clipboard.set_text(text)?;
+    Ok(())
+}
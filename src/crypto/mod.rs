@@ -9,7 +9,7 @@ use chacha20poly1305::aead::Aead;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use argon2::password_hash::{rand_core::OsRng, SaltString};
 use base64::Engine;
-use rand::{Rng, RngCore};
+use rand::RngCore;
 use serde::{Serialize, Deserialize};
 use anyhow::{Result, anyhow};
 use log::{info, error};
@@ -18,15 +18,65 @@ use log::{info, error};
 pub struct EncryptedData {
     pub ciphertext: Vec<u8>,
     pub nonce: Vec<u8>,
+    /// Solo existe en el JSON de versiones anteriores; nunca se usó para cifrar
+    #[serde(default)]
     pub salt: Vec<u8>,
 }
 
+/// Versión del formato compacto de `EncryptedData`
+const COMPACT_VERSION: u8 = 1;
+/// Longitud del nonce de ChaCha20-Poly1305
+const NONCE_LEN: usize = 12;
+/// Longitud de la etiqueta de autenticación de ChaCha20-Poly1305
+const TAG_LEN: usize = 16;
+
+impl EncryptedData {
+    /// Formato en que se guarda: base64 de un byte de versión, el nonce y el
+    /// texto cifrado
+    pub fn to_compact(&self) -> String {
+        let mut bytes = Vec::with_capacity(1 + self.nonce.len() + self.ciphertext.len());
+        bytes.push(COMPACT_VERSION);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+    
+    /// Lee un campo guardado, tanto en formato compacto como en el JSON de
+    /// versiones anteriores
+    pub fn from_stored(stored: &str) -> Result<Self> {
+        if Self::is_legacy(stored) {
+            return Ok(serde_json::from_str(stored)?);
+        }
+        
+        let bytes = base64::engine::general_purpose::STANDARD.decode(stored.trim())?;
+        match bytes.split_first() {
+            Some((&COMPACT_VERSION, rest)) if rest.len() >= NONCE_LEN + TAG_LEN => {
+                let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+                Ok(Self {
+                    ciphertext: ciphertext.to_vec(),
+                    nonce: nonce.to_vec(),
+                    salt: Vec::new(),
+                })
+            }
+            Some((&COMPACT_VERSION, _)) => Err(anyhow!("Campo encriptado truncado")),
+            Some((version, _)) => Err(anyhow!("Versión de campo encriptado desconocida: {}", version)),
+            None => Err(anyhow!("Campo encriptado vacío")),
+        }
+    }
+    
+    /// Si el campo está guardado como JSON, el formato de versiones anteriores
+    pub fn is_legacy(stored: &str) -> bool {
+        stored.trim_start().starts_with('{')
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MasterKey {
     pub hash: String,
     pub salt: Vec<u8>,
 }
 
+#[derive(Clone)]
 pub struct CryptoManager {
     master_key: Option<Vec<u8>>,
 }
@@ -82,16 +132,13 @@ impl CryptoManager {
         rand::thread_rng().fill_bytes(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
         
-        let mut salt_bytes = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut salt_bytes);
-        
         let ciphertext = cipher.encrypt(nonce, data)
             .map_err(|e| anyhow!("Error al encriptar: {}", e))?;
         
         Ok(EncryptedData {
             ciphertext,
             nonce: nonce_bytes.to_vec(),
-            salt: salt_bytes.to_vec(),
+            salt: Vec::new(),
         })
     }
     
@@ -102,6 +149,9 @@ impl CryptoManager {
         let key = Key::from_slice(master_key);
         let cipher = ChaCha20Poly1305::new(key);
         
+        if encrypted_data.nonce.len() != NONCE_LEN {
+            return Err(anyhow!("Nonce de longitud inválida: {} bytes", encrypted_data.nonce.len()));
+        }
         let nonce = Nonce::from_slice(&encrypted_data.nonce);
         
         let plaintext = cipher.decrypt(nonce, encrypted_data.ciphertext.as_slice())
@@ -110,6 +160,27 @@ impl CryptoManager {
         Ok(plaintext)
     }
     
+    /// Huella HMAC-SHA256 de un dato, derivada de la clave maestra.
+    /// Permite detectar contraseñas repetidas sin guardarlas ni desencriptarlas.
+    pub fn fingerprint(&self, data: &[u8]) -> Result<String> {
+        use hmac::{Hmac, Mac};
+        type HmacSha256 = Hmac<sha2::Sha256>;
+        
+        let master_key = self.master_key.as_ref()
+            .ok_or_else(|| anyhow!("Master key no establecida"))?;
+        
+        // Subclave propia para no usar la clave de cifrado directamente
+        let mut subkey_mac = <HmacSha256 as Mac>::new_from_slice(master_key)
+            .map_err(|e| anyhow!("Error al derivar subclave: {}", e))?;
+        subkey_mac.update(b"alohopass-fingerprint-v1");
+        let subkey = subkey_mac.finalize().into_bytes();
+        
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&subkey)
+            .map_err(|e| anyhow!("Error al calcular huella: {}", e))?;
+        mac.update(data);
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+    
     pub fn lock(&mut self) {
         self.master_key = None;
     }
@@ -207,15 +278,3 @@ pub fn generate_salt() -> Vec<u8> {
     rand::thread_rng().fill_bytes(&mut salt);
     salt.to_vec()
 }
-
-pub fn generate_secure_password(length: usize) -> String {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!@#$%^&*";
-    let mut rng = rand::thread_rng();
-    
-    (0..length)
-        .map(|_| {
-            let idx = rng.gen_range(0..CHARS.len());
-            CHARS[idx] as char
-        })
-        .collect()
-} 
\ No newline at end of file
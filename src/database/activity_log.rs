@@ -0,0 +1,131 @@
+//! Registro de actividad de la bóveda
+//!
+//! Guarda qué se hizo con cada entrada y cuándo, sin secretos: ni títulos ni
+//! usuarios, que también van encriptados en la bóveda. Se conservan los
+//! `MAX_EVENTS` eventos más recientes.
+
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use crate::models::{ActivityAction, ActivityEvent, ActivityLogRequest, EntryId};
+
+/// Eventos que se conservan en el registro
+const MAX_EVENTS: i64 = 10_000;
+
+/// Anota un evento. La categoría se toma de la entrada tal como está en la base,
+/// así que al borrar una entrada hay que anotarlo antes de borrarla.
+pub fn record_activity(
+    connection: &Connection,
+    action: ActivityAction,
+    entry_id: Option<EntryId>,
+    details: Option<&str>,
+    occurred_at: &str,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO activity_log (action, entry_id, category_id, details, occurred_at)
+         VALUES (?1, ?2, (SELECT category_id FROM password_entries WHERE id = ?2), ?3, ?4)",
+        params![action.as_str(), entry_id, details, occurred_at],
+    )?;
+    connection.execute(
+        "DELETE FROM activity_log WHERE id <= (SELECT MAX(id) FROM activity_log) - ?",
+        [MAX_EVENTS],
+    )?;
+    Ok(())
+}
+
+/// Página de eventos que cumplen los filtros, del más reciente al más antiguo,
+/// junto con el total de eventos que los cumplen
+pub fn list_activity(connection: &Connection, request: &ActivityLogRequest) -> Result<(Vec<ActivityEvent>, usize)> {
+    const FILTER: &str = "(?1 IS NULL OR category_id = ?1) AND (?2 IS NULL OR entry_id = ?2) AND (?3 IS NULL OR action = ?3)";
+    let filters = params![
+        request.category_id,
+        request.entry_id,
+        request.action.map(ActivityAction::as_str),
+    ];
+
+    let total: i64 = connection.query_row(
+        &format!("SELECT COUNT(*) FROM activity_log WHERE {}", FILTER),
+        filters,
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = connection.prepare(&format!(
+        "SELECT id, action, entry_id, category_id, details, occurred_at FROM activity_log
+         WHERE {} ORDER BY id DESC LIMIT ?4 OFFSET ?5",
+        FILTER,
+    ))?;
+    // LIMIT -1 no pone límite
+    let limit = request.limit.map_or(-1, |limit| limit as i64);
+    let offset = request.offset.unwrap_or(0) as i64;
+    let events = stmt.query_map(
+        params![
+            request.category_id,
+            request.entry_id,
+            request.action.map(ActivityAction::as_str),
+            limit,
+            offset,
+        ],
+        |row| {
+            let action: String = row.get(1)?;
+            Ok(ActivityEvent {
+                id: row.get(0)?,
+                action: ActivityAction::parse(&action).ok_or_else(|| {
+                    rusqlite::Error::InvalidColumnType(1, "action".to_string(), rusqlite::types::Type::Text)
+                })?,
+                entry_id: row.get(2)?,
+                category_id: row.get(3)?,
+                details: row.get(4)?,
+                occurred_at: row.get(5)?,
+            })
+        },
+    )?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok((events, total as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+    use crate::models::CategoryId;
+
+    #[test]
+    fn test_records_and_filters_events() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let (work, a) = (CategoryId::new(), EntryId::new());
+        connection.execute(
+            "INSERT INTO categories (id, name, color, created_at) VALUES (?, 'Trabajo', '#6B7280', '2024-01-01T00:00:00Z')",
+            [work],
+        ).unwrap();
+        connection.execute(
+            "INSERT INTO password_entries (id, title, username, password, category_id, created_at, updated_at)
+             VALUES (?, 't', 'u', 'p', ?, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            params![a, work],
+        ).unwrap();
+
+        record_activity(&connection, ActivityAction::Create, Some(a), None, "2024-01-01T00:00:00Z").unwrap();
+        record_activity(&connection, ActivityAction::Reveal, Some(a), Some("password"), "2024-01-02T00:00:00Z").unwrap();
+        record_activity(&connection, ActivityAction::Export, None, Some("csv"), "2024-01-03T00:00:00Z").unwrap();
+        record_activity(&connection, ActivityAction::Delete, Some(a), None, "2024-01-04T00:00:00Z").unwrap();
+        connection.execute("DELETE FROM password_entries WHERE id = ?", [a]).unwrap();
+
+        let (events, total) = list_activity(&connection, &ActivityLogRequest::default()).unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(events[0].action, ActivityAction::Delete);
+        assert_eq!(events[0].category_id, Some(work));
+
+        let by_category = ActivityLogRequest {
+            category_id: Some(work),
+            offset: Some(1),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let (events, total) = list_activity(&connection, &by_category).unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].action, events[0].details.as_deref()), (ActivityAction::Reveal, Some("password")));
+
+        let exports = ActivityLogRequest { action: Some(ActivityAction::Export), ..Default::default() };
+        assert_eq!(list_activity(&connection, &exports).unwrap().1, 1);
+    }
+}
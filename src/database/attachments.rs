@@ -0,0 +1,57 @@
+use rusqlite::Connection;
+use anyhow::Result;
+use log::info;
+use crate::models::EntryId;
+
+/// Guarda un adjunto de una entrada. `data` va encriptado con la clave de la bóveda.
+pub fn insert_attachment(
+    connection: &Connection,
+    id: &str,
+    entry_id: EntryId,
+    name: &str,
+    data: &str,
+    size: usize,
+    created_at: &str,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO attachments (id, entry_id, name, data, size, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        rusqlite::params![id, entry_id, name, data, size as i64, created_at],
+    )?;
+    info!("Adjunto {} guardado en la entrada {} ({} bytes)", name, entry_id, size);
+    Ok(())
+}
+
+/// Adjunto tal como está guardado, con los datos encriptados
+#[derive(Debug, Clone)]
+pub struct StoredAttachment {
+    pub id: String,
+    pub entry_id: EntryId,
+    pub name: String,
+    pub data: String,
+    pub size: usize,
+    pub created_at: String,
+}
+
+/// Todos los adjuntos de la bóveda
+pub fn list_attachments(connection: &Connection) -> Result<Vec<StoredAttachment>> {
+    let mut stmt = connection.prepare(
+        "SELECT id, entry_id, name, data, size, created_at FROM attachments ORDER BY created_at",
+    )?;
+    let attachments = stmt.query_map([], |row| {
+        Ok(StoredAttachment {
+            id: row.get(0)?,
+            entry_id: row.get(1)?,
+            name: row.get(2)?,
+            data: row.get(3)?,
+            size: row.get::<_, i64>(4)? as usize,
+            created_at: row.get(5)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(attachments)
+}
+
+/// Borra los adjuntos de una entrada. Devuelve cuántos había.
+pub fn delete_entry_attachments(connection: &Connection, entry_id: EntryId) -> Result<usize> {
+    Ok(connection.execute("DELETE FROM attachments WHERE entry_id = ?", [entry_id])?)
+}
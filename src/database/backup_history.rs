@@ -0,0 +1,62 @@
+use rusqlite::{Connection, OptionalExtension};
+use anyhow::Result;
+use log::info;
+use crate::models::BackupRecord;
+
+/// Registros de copias que se conservan en el historial
+const MAX_HISTORY: u32 = 200;
+
+/// Registra una copia de seguridad (o un intento fallido, con `error`)
+pub fn record_backup(
+    connection: &Connection,
+    path: &str,
+    created_at: &str,
+    automatic: bool,
+    entries: usize,
+    size_bytes: u64,
+    error: Option<&str>,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO backup_history (path, created_at, automatic, entries, size_bytes, error)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        rusqlite::params![path, created_at, automatic, entries as i64, size_bytes as i64, error],
+    )?;
+    connection.execute(
+        "DELETE FROM backup_history WHERE id NOT IN (
+            SELECT id FROM backup_history ORDER BY id DESC LIMIT ?
+         )",
+        [MAX_HISTORY],
+    )?;
+    info!("Copia de seguridad registrada en el historial: {}", path);
+    Ok(())
+}
+
+/// Historial de copias, de la más reciente a la más antigua
+pub fn list_backup_history(connection: &Connection, limit: usize) -> Result<Vec<BackupRecord>> {
+    let mut stmt = connection.prepare(
+        "SELECT id, path, created_at, automatic, entries, size_bytes, error
+         FROM backup_history ORDER BY id DESC LIMIT ?",
+    )?;
+    let records = stmt.query_map([limit as i64], |row| {
+        Ok(BackupRecord {
+            id: row.get(0)?,
+            path: row.get(1)?,
+            created_at: row.get(2)?,
+            automatic: row.get(3)?,
+            entries: row.get::<_, i64>(4)? as usize,
+            size_bytes: row.get::<_, i64>(5)? as u64,
+            error: row.get(6)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(records)
+}
+
+/// Fecha del último intento de copia automática, haya salido bien o no
+pub fn last_automatic_backup_at(connection: &Connection) -> Result<Option<String>> {
+    Ok(connection.query_row(
+        "SELECT created_at FROM backup_history WHERE automatic = 1 ORDER BY id DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    ).optional()?)
+}
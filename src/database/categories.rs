@@ -0,0 +1,146 @@
+use rusqlite::{Connection, OptionalExtension};
+use anyhow::Result;
+use log::info;
+use crate::models::{Category, CategoryId, CategoryRequest, CategorySummary};
+
+const CATEGORY_COLUMNS: &str = "id, name, color, icon, parent_id, created_at";
+
+fn category_from_row(row: &rusqlite::Row) -> rusqlite::Result<Category> {
+    Ok(Category {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        color: row.get(2)?,
+        icon: row.get(3)?,
+        parent_id: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+/// Todas las categorías ordenadas por nombre
+pub fn list_categories(connection: &Connection) -> Result<Vec<Category>> {
+    let mut stmt = connection.prepare(&format!(
+        "SELECT {} FROM categories ORDER BY name", CATEGORY_COLUMNS
+    ))?;
+    let categories = stmt.query_map([], category_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(categories)
+}
+
+/// Todas las categorías ordenadas por nombre, con cuántas entradas tiene cada una
+pub fn list_categories_with_counts(connection: &Connection) -> Result<Vec<CategorySummary>> {
+    let mut stmt = connection.prepare(
+        "SELECT c.id, c.name, c.color, c.icon, c.parent_id, c.created_at, COUNT(p.id)
+         FROM categories c
+         LEFT JOIN password_entries p ON p.category_id = c.id
+         GROUP BY c.id
+         ORDER BY c.name",
+    )?;
+    let categories = stmt.query_map([], |row| {
+        Ok(CategorySummary {
+            category: category_from_row(row)?,
+            entry_count: row.get::<_, i64>(6)? as usize,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(categories)
+}
+
+pub fn get_category(connection: &Connection, id: CategoryId) -> Result<Option<Category>> {
+    Ok(connection.query_row(
+        &format!("SELECT {} FROM categories WHERE id = ?", CATEGORY_COLUMNS),
+        [id],
+        category_from_row,
+    ).optional()?)
+}
+
+/// Guarda una categoría con su id y fecha originales
+pub fn insert_category(connection: &Connection, category: &Category) -> Result<()> {
+    connection.execute(
+        "INSERT INTO categories (id, name, color, icon, parent_id, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            category.id,
+            category.name,
+            category.color,
+            category.icon,
+            category.parent_id,
+            category.created_at,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Cambia el nombre, color, icono y categoría padre; devuelve `false` si no existe
+pub fn update_category(connection: &Connection, id: CategoryId, request: &CategoryRequest) -> Result<bool> {
+    let updated = connection.execute(
+        "UPDATE categories SET name = ?, color = ?, icon = ?, parent_id = ? WHERE id = ?",
+        rusqlite::params![request.name, request.color, request.icon, request.parent_id, id],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Cuelga la categoría de `parent_id`, o la lleva a la raíz; devuelve `false` si no existe
+pub fn set_category_parent(connection: &Connection, id: CategoryId, parent_id: Option<CategoryId>) -> Result<bool> {
+    let updated = connection.execute(
+        "UPDATE categories SET parent_id = ? WHERE id = ?",
+        rusqlite::params![parent_id, id],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Elimina una categoría. Sus subcategorías pasan a colgar de la categoría padre
+/// de la eliminada; las entradas tienen que haberse movido antes. Devuelve
+/// `false` si no existe.
+pub fn delete_category(connection: &Connection, id: CategoryId) -> Result<bool> {
+    let moved = connection.execute(
+        "UPDATE categories SET parent_id = (SELECT parent_id FROM categories WHERE id = ?1) WHERE parent_id = ?1",
+        [id],
+    )?;
+    let deleted = connection.execute("DELETE FROM categories WHERE id = ?", [id])?;
+    if deleted > 0 {
+        info!("Categoría {} eliminada ({} subcategorías movidas a su categoría padre)", id, moved);
+    }
+    Ok(deleted > 0)
+}
+
+/// Elimina todas las categorías (al restaurar una copia reemplazando la bóveda)
+pub fn delete_all_categories(connection: &Connection) -> Result<()> {
+    connection.execute("DELETE FROM categories", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    fn category(name: &str, parent_id: Option<CategoryId>) -> Category {
+        Category {
+            id: CategoryId::new(),
+            name: name.to_string(),
+            color: "#6B7280".to_string(),
+            icon: None,
+            parent_id,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_deleting_a_category_keeps_its_children() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let work = category("trabajo", None);
+        let clients = category("clientes", Some(work.id));
+        let projects = category("proyectos", Some(clients.id));
+        for category in [&work, &clients, &projects] {
+            insert_category(&connection, category).unwrap();
+        }
+
+        assert!(delete_category(&connection, clients.id).unwrap());
+        assert!(!delete_category(&connection, clients.id).unwrap());
+        assert_eq!(get_category(&connection, projects.id).unwrap().unwrap().parent_id, Some(work.id));
+
+        let summaries = list_categories_with_counts(&connection).unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert!(summaries.iter().all(|summary| summary.entry_count == 0));
+    }
+}
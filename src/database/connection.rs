@@ -1,15 +1,78 @@
-use anyhow::Result;
+use log::{info, warn};
 use std::path::Path;
+use std::time::Duration;
 
-pub fn get_database_path() -> Result<String> {
-    let app_data_dir = dirs::data_local_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("alohopass");
-    
-    std::fs::create_dir_all(&app_data_dir)?;
-    Ok(app_data_dir.join("alohopass.db").to_string_lossy().to_string())
+/// Ajustes que se aplican a cada conexión al abrirla. Con WAL los lectores no
+/// bloquean al escritor, así que la sincronización y el servidor de la
+/// extensión pueden abrir su propia conexión a la misma base; `busy_timeout`
+/// hace que esperen en lugar de fallar si coinciden dos escrituras.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionSettings {
+    pub journal_mode: &'static str,
+    pub busy_timeout: Duration,
+    /// NORMAL es seguro con WAL: solo se puede perder la última transacción si
+    /// se corta la luz, nunca corromper la base
+    pub synchronous: &'static str,
+    pub foreign_keys: bool,
+    /// Caché de páginas en KiB
+    pub cache_size_kib: i64,
 }
 
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        Self {
+            journal_mode: "WAL",
+            busy_timeout: Duration::from_secs(5),
+            synchronous: "NORMAL",
+            foreign_keys: true,
+            cache_size_kib: 16 * 1024,
+        }
+    }
+}
+
+/// Aplica los ajustes a una conexión recién abierta
+pub fn configure_connection(connection: &rusqlite::Connection, settings: &ConnectionSettings) -> rusqlite::Result<()> {
+    // journal_mode devuelve el modo resultante; las bases en memoria no admiten WAL
+    let journal_mode: String = connection.query_row(
+        &format!("PRAGMA journal_mode = {}", settings.journal_mode),
+        [],
+        |row| row.get(0),
+    )?;
+    if !journal_mode.eq_ignore_ascii_case(settings.journal_mode) {
+        warn!("La base de datos usa journal_mode={} en lugar de {}", journal_mode, settings.journal_mode);
+    }
+    connection.busy_timeout(settings.busy_timeout)?;
+    connection.pragma_update(None, "synchronous", settings.synchronous)?;
+    connection.pragma_update(None, "foreign_keys", settings.foreign_keys)?;
+    // Un valor negativo se interpreta en KiB en lugar de en páginas
+    connection.pragma_update(None, "cache_size", -settings.cache_size_kib)?;
+    info!("Conexión configurada: journal_mode={}, synchronous={}, foreign_keys={}, cache_size={} KiB",
+          journal_mode, settings.synchronous, settings.foreign_keys, settings.cache_size_kib);
+    Ok(())
+}
+
+/// Abre una conexión con los ajustes por defecto
 pub fn open_database<P: AsRef<Path>>(path: P) -> rusqlite::Result<rusqlite::Connection> {
-    rusqlite::Connection::open(path)
-} 
\ No newline at end of file
+    let connection = rusqlite::Connection::open(path)?;
+    configure_connection(&connection, &ConnectionSettings::default())?;
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_in_wal_mode_with_foreign_keys() {
+        let path = std::env::temp_dir().join(format!("alohopass-{}.db", uuid::Uuid::new_v4()));
+        let connection = open_database(&path).unwrap();
+        let journal_mode: String = connection.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        let foreign_keys: bool = connection.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        assert_eq!(journal_mode, "wal");
+        assert!(foreign_keys);
+        drop(connection);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Vectores de versiones de las entradas y las categorías
+//!
+//! Cada cambio hecho aquí recibe el siguiente número del dispositivo local y
+//! queda en el vector del elemento; los cambios recibidos se unen al vector.
+//! Con eso una sincronización manda solo los elementos con números que el otro
+//! dispositivo todavía no vio, en vez de toda la lista de pendientes.
+
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use log::info;
+use crate::models::{DeviceId, EntryId, VersionVector};
+
+/// Clase de elemento versionado
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementKind {
+    Entry,
+    Category,
+}
+
+impl ElementKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ElementKind::Entry => "entry",
+            ElementKind::Category => "category",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "category" => ElementKind::Category,
+            _ => ElementKind::Entry,
+        }
+    }
+}
+
+/// Elemento con su vector de versiones
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionedElement {
+    pub element_id: EntryId,
+    pub kind: ElementKind,
+    pub versions: VersionVector,
+}
+
+/// Siguiente número de cambio de `device_id`
+fn next_counter(connection: &Connection, device_id: DeviceId) -> Result<u64> {
+    let last: i64 = connection.query_row(
+        "SELECT COALESCE(MAX(counter), 0) FROM element_versions WHERE device_id = ?",
+        [device_id],
+        |row| row.get(0),
+    )?;
+    Ok(last as u64 + 1)
+}
+
+/// Anota un cambio de `device_id` al elemento y devuelve el vector que queda
+pub fn bump_element_version(
+    connection: &Connection,
+    element_id: EntryId,
+    kind: ElementKind,
+    device_id: DeviceId,
+) -> Result<VersionVector> {
+    let counter = next_counter(connection, device_id)?;
+    connection.execute(
+        "INSERT OR REPLACE INTO element_versions (element_id, kind, device_id, counter) VALUES (?, ?, ?, ?)",
+        params![element_id, kind.as_str(), device_id, counter as i64],
+    )?;
+    get_element_versions(connection, element_id)
+}
+
+/// Vector de versiones del elemento; vacío si nunca cambió
+pub fn get_element_versions(connection: &Connection, element_id: EntryId) -> Result<VersionVector> {
+    let mut stmt = connection.prepare_cached(
+        "SELECT device_id, counter FROM element_versions WHERE element_id = ?",
+    )?;
+    let versions = stmt.query_map([element_id], |row| Ok((row.get::<_, DeviceId>(0)?, row.get::<_, i64>(1)? as u64)))?
+        .collect::<Result<VersionVector, _>>()?;
+    Ok(versions)
+}
+
+/// Une al vector del elemento el de un cambio recibido
+pub fn merge_element_versions(
+    connection: &Connection,
+    element_id: EntryId,
+    kind: ElementKind,
+    versions: &VersionVector,
+) -> Result<()> {
+    let mut stmt = connection.prepare_cached(
+        "INSERT INTO element_versions (element_id, kind, device_id, counter) VALUES (?, ?, ?, ?)
+         ON CONFLICT (element_id, device_id) DO UPDATE SET counter = MAX(counter, excluded.counter)",
+    )?;
+    for (device_id, counter) in versions.iter() {
+        stmt.execute(params![element_id, kind.as_str(), device_id, counter as i64])?;
+    }
+    Ok(())
+}
+
+/// Lo que este dispositivo vio: el último número de cada dispositivo
+pub fn local_knowledge(connection: &Connection) -> Result<VersionVector> {
+    let mut stmt = connection.prepare(
+        "SELECT device_id, MAX(counter) FROM element_versions GROUP BY device_id",
+    )?;
+    let knowledge = stmt.query_map([], |row| Ok((row.get::<_, DeviceId>(0)?, row.get::<_, i64>(1)? as u64)))?
+        .collect::<Result<VersionVector, _>>()?;
+    Ok(knowledge)
+}
+
+/// Elementos con algún cambio que no está en `known`
+pub fn elements_changed_since(connection: &Connection, known: &VersionVector) -> Result<Vec<VersionedElement>> {
+    let mut stmt = connection.prepare_cached(
+        "SELECT DISTINCT element_id, kind FROM element_versions WHERE device_id = ? AND counter > ?",
+    )?;
+    let mut changed: Vec<(EntryId, ElementKind)> = Vec::new();
+    for (device_id, last) in local_knowledge(connection)?.iter() {
+        let seen = known.get(device_id);
+        if last <= seen {
+            continue;
+        }
+        let rows = stmt.query_map(params![device_id, seen as i64], |row| {
+            Ok((row.get::<_, EntryId>(0)?, ElementKind::parse(&row.get::<_, String>(1)?)))
+        })?;
+        for row in rows {
+            let row = row?;
+            if !changed.contains(&row) {
+                changed.push(row);
+            }
+        }
+    }
+    changed.into_iter()
+        .map(|(element_id, kind)| Ok(VersionedElement {
+            element_id,
+            kind,
+            versions: get_element_versions(connection, element_id)?,
+        }))
+        .collect()
+}
+
+/// Da una versión de `device_id` a las entradas y categorías que no tienen
+/// ninguna, como las anteriores a los vectores o las importadas. Devuelve
+/// cuántas se versionaron.
+pub fn seed_element_versions(connection: &Connection, device_id: DeviceId) -> Result<usize> {
+    let mut stmt = connection.prepare(
+        "SELECT id, 'entry' FROM password_entries WHERE id NOT IN (SELECT element_id FROM element_versions)
+         UNION ALL
+         SELECT id, 'category' FROM categories WHERE id NOT IN (SELECT element_id FROM element_versions)",
+    )?;
+    let unversioned = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if unversioned.is_empty() {
+        return Ok(0);
+    }
+
+    let first = next_counter(connection, device_id)?;
+    let mut insert = connection.prepare_cached(
+        "INSERT INTO element_versions (element_id, kind, device_id, counter) VALUES (?, ?, ?, ?)",
+    )?;
+    for (offset, (element_id, kind)) in unversioned.iter().enumerate() {
+        insert.execute(params![element_id, kind, device_id, (first + offset as u64) as i64])?;
+    }
+    info!("Versionados {} elementos que no tenían versión", unversioned.len());
+    Ok(unversioned.len())
+}
+
+/// Lo que `peer_id` dijo haber visto la última vez; vacío si nunca se
+/// sincronizó con él
+pub fn get_peer_knowledge(connection: &Connection, peer_id: DeviceId) -> Result<VersionVector> {
+    let mut stmt = connection.prepare_cached(
+        "SELECT device_id, counter FROM peer_knowledge WHERE peer_id = ?",
+    )?;
+    let knowledge = stmt.query_map([peer_id], |row| Ok((row.get::<_, DeviceId>(0)?, row.get::<_, i64>(1)? as u64)))?
+        .collect::<Result<VersionVector, _>>()?;
+    Ok(knowledge)
+}
+
+/// Une lo que `peer_id` dice haber visto con lo que ya se sabía
+pub fn save_peer_knowledge(connection: &Connection, peer_id: DeviceId, knowledge: &VersionVector) -> Result<()> {
+    let mut stmt = connection.prepare_cached(
+        "INSERT INTO peer_knowledge (peer_id, device_id, counter) VALUES (?, ?, ?)
+         ON CONFLICT (peer_id, device_id) DO UPDATE SET counter = MAX(counter, excluded.counter)",
+    )?;
+    for (device_id, counter) in knowledge.iter() {
+        stmt.execute(params![peer_id, device_id, counter as i64])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    #[test]
+    fn test_sends_only_what_the_peer_has_not_seen() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        let (first, second) = (EntryId::new(), EntryId::new());
+
+        let versions = bump_element_version(&connection, first, ElementKind::Entry, laptop).unwrap();
+        assert_eq!(versions.get(laptop), 1);
+        bump_element_version(&connection, second, ElementKind::Category, laptop).unwrap();
+        let versions = bump_element_version(&connection, first, ElementKind::Entry, laptop).unwrap();
+        assert_eq!(versions.get(laptop), 3);
+
+        let remote: VersionVector = [(phone, 5)].into_iter().collect();
+        merge_element_versions(&connection, first, ElementKind::Entry, &remote).unwrap();
+        merge_element_versions(&connection, first, ElementKind::Entry, &[(phone, 2)].into_iter().collect()).unwrap();
+        let knowledge = local_knowledge(&connection).unwrap();
+        assert_eq!((knowledge.get(laptop), knowledge.get(phone)), (3, 5));
+
+        // El teléfono ya vio su cambio y el primero del portátil
+        let seen: VersionVector = [(laptop, 2), (phone, 5)].into_iter().collect();
+        let changed = elements_changed_since(&connection, &seen).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].element_id, first);
+        assert_eq!(changed[0].kind, ElementKind::Entry);
+        assert!(!changed[0].versions.exceeds(&knowledge));
+        assert_eq!(elements_changed_since(&connection, &VersionVector::new()).unwrap().len(), 2);
+        assert!(elements_changed_since(&connection, &knowledge).unwrap().is_empty());
+        assert!(seen < knowledge);
+        assert_eq!(remote.partial_cmp(&[(laptop, 1)].into_iter().collect()), None);
+
+        // Lo que no tenía versión recibe la siguiente del dispositivo local
+        let category = EntryId::new();
+        connection.execute(
+            "INSERT INTO categories (id, name, color, created_at) VALUES (?, 'Trabajo', '#000000', '2024-01-01T00:00:00Z')",
+            [category],
+        ).unwrap();
+        assert_eq!(seed_element_versions(&connection, laptop).unwrap(), 1);
+        assert_eq!(seed_element_versions(&connection, laptop).unwrap(), 0);
+        assert_eq!(get_element_versions(&connection, category).unwrap().get(laptop), 4);
+    }
+
+    #[test]
+    fn test_remembers_what_each_peer_saw() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        connection.execute(
+            "INSERT INTO trusted_devices (device_id, trusted_at) VALUES (?, '2024-01-01T00:00:00Z')",
+            [phone],
+        ).unwrap();
+        assert!(get_peer_knowledge(&connection, phone).unwrap().is_empty());
+
+        save_peer_knowledge(&connection, phone, &[(laptop, 4)].into_iter().collect()).unwrap();
+        save_peer_knowledge(&connection, phone, &[(laptop, 2), (phone, 1)].into_iter().collect()).unwrap();
+        let knowledge = get_peer_knowledge(&connection, phone).unwrap();
+        assert_eq!((knowledge.get(laptop), knowledge.get(phone)), (4, 1));
+
+        crate::database::remove_trusted_device(&connection, phone).unwrap();
+        assert!(get_peer_knowledge(&connection, phone).unwrap().is_empty());
+    }
+}
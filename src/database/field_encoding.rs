@@ -0,0 +1,104 @@
+//! Conversión de los campos encriptados al formato compacto
+//!
+//! Los campos encriptados se guardaban como JSON de `EncryptedData`, con cada
+//! byte escrito como número. Lo que se escribe ahora ya usa el formato compacto
+//! y la lectura acepta los dos, así que las filas antiguas se convierten de a
+//! poco en segundo plano. Convertir solo reempaqueta el nonce y el texto
+//! cifrado: no hace falta la clave maestra.
+
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use log::{info, warn};
+use crate::crypto::EncryptedData;
+
+/// Columnas con campos encriptados: tabla, columna y, si no todas las filas
+/// lo están, condición que delimita las encriptadas
+const ENCRYPTED_COLUMNS: &[(&str, &str, Option<&str>)] = &[
+    ("password_entries", "title", None),
+    ("password_entries", "username", None),
+    ("password_entries", "password", None),
+    ("password_entries", "totp_secret", None),
+    ("entry_revisions", "title", None),
+    ("entry_revisions", "username", None),
+    ("entry_revisions", "password", None),
+    ("entry_revisions", "totp_secret", None),
+    ("generation_history", "password", None),
+    ("attachments", "data", None),
+    ("security_reports", "report", None),
+    // El resto de la configuración es JSON sin encriptar
+    ("settings", "value", Some("key = 'backup_password'")),
+];
+
+/// Convierte al formato compacto hasta `limit` campos guardados como JSON.
+/// Devuelve cuántos convirtió; 0 significa que ya no queda nada por convertir.
+/// Los campos que no se pueden leer se dejan como están.
+pub fn compact_legacy_fields(connection: &Connection, limit: usize) -> Result<usize> {
+    let tx = connection.unchecked_transaction()?;
+    let mut converted = 0;
+    for (table, column, condition) in ENCRYPTED_COLUMNS {
+        if converted >= limit {
+            break;
+        }
+        let condition = condition.map(|condition| format!("AND {}", condition)).unwrap_or_default();
+        let legacy: Vec<(i64, String)> = tx.prepare_cached(&format!(
+            "SELECT rowid, {column} FROM {table}
+             WHERE {column} LIKE '{{%' {condition}
+             ORDER BY rowid LIMIT ?",
+        ))?
+        .query_map([(limit - converted) as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+        let mut update = tx.prepare_cached(&format!("UPDATE {table} SET {column} = ? WHERE rowid = ?"))?;
+        for (rowid, stored) in legacy {
+            match EncryptedData::from_stored(&stored) {
+                Ok(encrypted) => {
+                    update.execute(params![encrypted.to_compact(), rowid])?;
+                    converted += 1;
+                }
+                Err(e) => warn!("Campo {}.{} (fila {}) ilegible, queda sin convertir: {}", table, column, rowid, e),
+            }
+        }
+    }
+    tx.commit()?;
+    if converted > 0 {
+        info!("{} campos encriptados convertidos al formato compacto", converted);
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    #[test]
+    fn test_converts_legacy_fields_in_batches() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let legacy = |byte: u8| serde_json::to_string_pretty(&EncryptedData {
+            ciphertext: vec![byte; 20],
+            nonce: vec![byte; 12],
+            salt: vec![0; 32],
+        }).unwrap();
+        connection.execute(
+            "INSERT INTO password_entries (id, title, username, password, created_at, updated_at)
+             VALUES ('a', ?, ?, ?, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            params![legacy(1), legacy(2), legacy(3)],
+        ).unwrap();
+        connection.execute(
+            "INSERT INTO settings (key, value, updated_at) VALUES ('backup_password', ?, ''), ('app_settings', '{}', '')",
+            [legacy(4)],
+        ).unwrap();
+
+        assert_eq!(compact_legacy_fields(&connection, 2).unwrap(), 2);
+        assert_eq!(compact_legacy_fields(&connection, 10).unwrap(), 2);
+        assert_eq!(compact_legacy_fields(&connection, 10).unwrap(), 0);
+
+        let password: String = connection.query_row("SELECT password FROM password_entries", [], |row| row.get(0)).unwrap();
+        let encrypted = EncryptedData::from_stored(&password).unwrap();
+        assert!(!EncryptedData::is_legacy(&password));
+        assert_eq!((encrypted.nonce, encrypted.ciphertext), (vec![3; 12], vec![3; 20]));
+        let other: String = connection.query_row("SELECT value FROM settings WHERE key = 'app_settings'", [], |row| row.get(0)).unwrap();
+        assert_eq!(other, "{}");
+    }
+}
@@ -0,0 +1,83 @@
+//! Último cambio de cada campo de las entradas
+//!
+//! Los cambios hechos aquí sellan los campos que tocaron; los que llegan de
+//! otro dispositivo traen sus sellos y se guardan los que ganaron.
+
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use crate::models::{DeviceId, EntryField, EntryId, FieldStamp, FieldStamps};
+
+/// Sellos de los campos de la entrada; los campos que nunca se sellaron no
+/// aparecen
+pub fn get_field_stamps(connection: &Connection, entry_id: EntryId) -> Result<FieldStamps> {
+    let mut stmt = connection.prepare_cached(
+        "SELECT field, changed_at, device_id, counter FROM entry_field_stamps WHERE entry_id = ?",
+    )?;
+    let rows = stmt.query_map([entry_id], |row| {
+        Ok((row.get::<_, String>(0)?, FieldStamp {
+            changed_at: row.get(1)?,
+            device_id: row.get(2)?,
+            counter: row.get::<_, i64>(3)? as u64,
+        }))
+    })?;
+    let mut stamps = FieldStamps::new();
+    for row in rows {
+        let (field, stamp) = row?;
+        if let Some(field) = EntryField::parse(&field) {
+            stamps.insert(field, stamp);
+        }
+    }
+    Ok(stamps)
+}
+
+/// Sella los campos que cambió `device_id` en `changed_at` con su número de
+/// cambio `counter`
+pub fn stamp_entry_fields(
+    connection: &Connection,
+    entry_id: EntryId,
+    fields: &[EntryField],
+    changed_at: &str,
+    device_id: DeviceId,
+    counter: u64,
+) -> Result<()> {
+    let stamps: FieldStamps = fields.iter()
+        .map(|field| (*field, FieldStamp { changed_at: changed_at.to_string(), device_id, counter }))
+        .collect();
+    save_field_stamps(connection, entry_id, &stamps)
+}
+
+/// Guarda los sellos, reemplazando los que había de esos campos
+pub fn save_field_stamps(connection: &Connection, entry_id: EntryId, stamps: &FieldStamps) -> Result<()> {
+    let mut stmt = connection.prepare_cached(
+        "INSERT OR REPLACE INTO entry_field_stamps (entry_id, field, changed_at, device_id, counter) VALUES (?, ?, ?, ?, ?)",
+    )?;
+    for (field, stamp) in stamps {
+        stmt.execute(params![entry_id, field.as_str(), stamp.changed_at, stamp.device_id, stamp.counter as i64])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    #[test]
+    fn test_keeps_the_last_stamp_of_each_field() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let (entry_id, laptop, phone) = (EntryId::new(), DeviceId::new(), DeviceId::new());
+        assert!(get_field_stamps(&connection, entry_id).unwrap().is_empty());
+
+        stamp_entry_fields(&connection, entry_id, &EntryField::ALL, "2024-01-01T00:00:00Z", laptop, 1).unwrap();
+        stamp_entry_fields(&connection, entry_id, &[EntryField::Password], "2024-02-01T00:00:00Z", phone, 3).unwrap();
+        let stamps = get_field_stamps(&connection, entry_id).unwrap();
+        assert_eq!(stamps.len(), EntryField::ALL.len());
+        assert_eq!(stamps[&EntryField::Password], FieldStamp {
+            changed_at: "2024-02-01T00:00:00Z".to_string(),
+            device_id: phone,
+            counter: 3,
+        });
+        assert_eq!(stamps[&EntryField::Title].device_id, laptop);
+    }
+}
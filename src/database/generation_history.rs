@@ -0,0 +1,51 @@
+use rusqlite::Connection;
+use anyhow::Result;
+use log::info;
+
+/// Guarda una contraseña generada (encriptada) y descarta las más antiguas que exceden `keep`
+pub fn add_generated_password(
+    connection: &Connection,
+    encrypted_password: &str,
+    url: Option<&str>,
+    generated_at: &str,
+    keep: u32,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO generation_history (password, url, generated_at) VALUES (?, ?, ?)",
+        rusqlite::params![encrypted_password, url, generated_at],
+    )?;
+    prune_generation_history(connection, keep)?;
+    Ok(())
+}
+
+/// Conserva solo las `keep` contraseñas generadas más recientes
+pub fn prune_generation_history(connection: &Connection, keep: u32) -> Result<usize> {
+    let pruned = connection.execute(
+        "DELETE FROM generation_history WHERE id NOT IN (
+            SELECT id FROM generation_history ORDER BY id DESC LIMIT ?
+         )",
+        [keep],
+    )?;
+    if pruned > 0 {
+        info!("Historial de generación: {} contraseñas antiguas descartadas", pruned);
+    }
+    Ok(pruned)
+}
+
+/// Historial de contraseñas generadas (encriptadas), de la más reciente a la más antigua:
+/// `(id, contraseña, url, fecha)`
+pub fn list_generation_history(connection: &Connection) -> Result<Vec<(i64, String, Option<String>, String)>> {
+    let mut stmt = connection.prepare(
+        "SELECT id, password, url, generated_at FROM generation_history ORDER BY id DESC",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Borra todo el historial de generación; devuelve cuántas contraseñas se eliminaron
+pub fn clear_generation_history(connection: &Connection) -> Result<usize> {
+    let deleted = connection.execute("DELETE FROM generation_history", [])?;
+    info!("Historial de generación borrado ({} contraseñas)", deleted);
+    Ok(deleted)
+}
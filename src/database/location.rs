@@ -0,0 +1,151 @@
+//! Ubicación del archivo de la base de datos
+//!
+//! Por defecto la base vive en el directorio de datos de la aplicación
+//! (`%APPDATA%\alohopass` en Windows, `~/.local/share/alohopass` en Linux,
+//! `~/Library/Application Support/alohopass` en macOS). El usuario puede
+//! moverla a otra carpeta, por ejemplo una sincronizada: la ruta elegida se
+//! guarda en un archivo del directorio de datos, ya que la configuración vive
+//! dentro de la propia base y no se puede leer antes de saber dónde está.
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Nombre del archivo de la base, también cuando se mueve a otra carpeta
+pub const DATABASE_FILE_NAME: &str = "alohopass.db";
+/// Archivo del directorio de datos con la ruta de una base movida
+const LOCATION_FILE_NAME: &str = "database-location";
+
+/// Directorio de datos de la aplicación. Las versiones anteriores usaban
+/// `$HOME/alohopass` fuera de Windows: si ese directorio existe y el nuevo no,
+/// se sigue usando.
+pub fn app_data_dir() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow!("No se pudo determinar el directorio de datos de la aplicación"))?
+        .join("alohopass");
+    let legacy_dir = dirs::home_dir()
+        .map(|home| home.join("alohopass"))
+        .filter(|legacy| *legacy != data_dir && legacy.join(DATABASE_FILE_NAME).exists());
+    let dir = match legacy_dir {
+        Some(legacy) if !data_dir.exists() => legacy,
+        _ => data_dir,
+    };
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| anyhow!("No se pudo crear el directorio de datos {:?}: {}", dir, e))?;
+    Ok(dir)
+}
+
+/// Dónde está la base si el usuario no la movió
+pub fn default_database_path() -> Result<PathBuf> {
+    Ok(app_data_dir()?.join(DATABASE_FILE_NAME))
+}
+
+/// Ruta de la base de datos: la elegida por el usuario o la de por defecto
+pub fn get_database_path() -> Result<String> {
+    let app_dir = app_data_dir()?;
+    let path = read_location(&app_dir)?.unwrap_or_else(|| app_dir.join(DATABASE_FILE_NAME));
+    info!("Ruta de base de datos: {:?}", path);
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Guarda la ruta de la base; `None` vuelve a la ubicación por defecto
+pub fn set_database_location(path: Option<&Path>) -> Result<()> {
+    write_location(&app_data_dir()?, path)
+}
+
+fn read_location(app_dir: &Path) -> Result<Option<PathBuf>> {
+    match std::fs::read_to_string(app_dir.join(LOCATION_FILE_NAME)) {
+        Ok(content) => Ok(Some(content.trim()).filter(|path| !path.is_empty()).map(PathBuf::from)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(anyhow!("No se pudo leer la ubicación de la base de datos: {}", e)),
+    }
+}
+
+fn write_location(app_dir: &Path, path: Option<&Path>) -> Result<()> {
+    let location_file = app_dir.join(LOCATION_FILE_NAME);
+    let Some(path) = path else {
+        if location_file.exists() {
+            std::fs::remove_file(&location_file)?;
+        }
+        return Ok(());
+    };
+    // Escribir aparte y renombrar, para no dejar nunca una ruta a medias
+    let temp_file = location_file.with_extension("tmp");
+    std::fs::write(&temp_file, path.to_string_lossy().as_bytes())?;
+    std::fs::rename(&temp_file, &location_file)?;
+    Ok(())
+}
+
+/// Copia la base abierta en `connection` a `target` y comprueba la copia. Si
+/// algo falla no queda nada en `target`.
+pub fn copy_database(connection: &Connection, target: &Path) -> Result<()> {
+    if target.exists() {
+        return Err(anyhow!("Ya existe un archivo en {:?}", target));
+    }
+    info!("Copiando la base de datos a {:?}...", target);
+    let result = connection.execute("VACUUM INTO ?", [target.to_string_lossy()])
+        .map_err(anyhow::Error::from)
+        .and_then(|_| {
+            let copy = Connection::open(target)?;
+            let check: String = copy.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+            if check != "ok" {
+                return Err(anyhow!("La copia de la base de datos está dañada: {}", check));
+            }
+            Ok(())
+        });
+    if result.is_err() && target.exists() {
+        if let Err(e) = std::fs::remove_file(target) {
+            warn!("No se pudo borrar la copia incompleta {:?}: {}", target, e);
+        }
+    }
+    result
+}
+
+/// Borra el archivo de una base cerrada junto con su WAL y su memoria compartida
+pub fn remove_database_files(path: &Path) -> Result<()> {
+    for suffix in ["", "-wal", "-shm"] {
+        let file = PathBuf::from(format!("{}{}", path.to_string_lossy(), suffix));
+        match std::fs::remove_file(&file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(anyhow!("No se pudo borrar {:?}: {}", file, e));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stores_location_and_copies_database() {
+        let dir = std::env::temp_dir().join(format!("alohopass-location-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(read_location(&dir).unwrap(), None);
+        let target = dir.join("sincronizada").join(DATABASE_FILE_NAME);
+        write_location(&dir, Some(&target)).unwrap();
+        assert_eq!(read_location(&dir).unwrap(), Some(target.clone()));
+        write_location(&dir, None).unwrap();
+        assert_eq!(read_location(&dir).unwrap(), None);
+
+        let source_path = dir.join(DATABASE_FILE_NAME);
+        let source = Connection::open(&source_path).unwrap();
+        source.execute_batch("CREATE TABLE t (value TEXT); INSERT INTO t VALUES ('bóveda');").unwrap();
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        copy_database(&source, &target).unwrap();
+        assert!(copy_database(&source, &target).is_err());
+        let value: String = Connection::open(&target).unwrap()
+            .query_row("SELECT value FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, "bóveda");
+
+        drop(source);
+        remove_database_files(&source_path).unwrap();
+        assert!(!source_path.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+//! Mantenimiento de la base de datos
+//!
+//! Descarta las filas de los historiales que superan la retención configurada
+//! y compacta el archivo con `VACUUM` para devolver al sistema el espacio que
+//! dejan las filas borradas.
+
+use rusqlite::Connection;
+use anyhow::Result;
+use log::info;
+use crate::models::MaintenanceReport;
+use super::{prune_generation_history, prune_revisions, purge_acknowledged_tombstones};
+
+/// Cuánto se conserva de cada historial
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Contraseñas generadas que se conservan
+    pub generation_history_size: u32,
+    /// Días que se conservan los registros de copias; 0 = sin límite
+    pub backup_history_days: u32,
+    /// Revisiones que se conservan por entrada
+    pub revisions_per_entry: u32,
+    /// Días que se conservan las revisiones; 0 = sin límite
+    pub revision_days: u32,
+}
+
+impl RetentionPolicy {
+    /// Fecha a partir de la cual se conservan las revisiones
+    pub fn revision_cutoff(&self, now: chrono::DateTime<chrono::Utc>) -> Option<String> {
+        match self.revision_days {
+            0 => None,
+            days => Some((now - chrono::Duration::days(days as i64)).to_rfc3339()),
+        }
+    }
+}
+
+/// Tamaño de la base principal en bytes, sin contar el WAL
+pub fn database_size(connection: &Connection) -> Result<u64> {
+    let page_count: i64 = connection.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = connection.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok((page_count * page_size) as u64)
+}
+
+/// Borra los registros de copias anteriores a `cutoff` (fecha RFC 3339)
+fn prune_backup_history(connection: &Connection, cutoff: &str) -> Result<usize> {
+    Ok(connection.execute("DELETE FROM backup_history WHERE created_at < ?", [cutoff])?)
+}
+
+/// Aplica la retención a los historiales y compacta la base. No puede
+/// ejecutarse dentro de una transacción: `VACUUM` las rechaza.
+pub fn compact_database(
+    connection: &Connection,
+    retention: &RetentionPolicy,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<MaintenanceReport> {
+    info!("=== INICIO: Mantenimiento de la base de datos ===");
+    let size_before = database_size(connection)?;
+    
+    let pruned_generation_history = prune_generation_history(connection, retention.generation_history_size)?;
+    let pruned_backup_history = match retention.backup_history_days {
+        0 => 0,
+        days => prune_backup_history(connection, &(now - chrono::Duration::days(days as i64)).to_rfc3339())?,
+    };
+    let pruned_tombstones = purge_acknowledged_tombstones(connection)?;
+    let pruned_revisions = prune_revisions(
+        connection,
+        retention.revisions_per_entry,
+        retention.revision_cutoff(now).as_deref(),
+    )?;
+    
+    connection.execute_batch("VACUUM")?;
+    // Vaciar el WAL en la base y truncarlo, para que el espacio se libere de verdad
+    connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    connection.execute_batch("PRAGMA optimize")?;
+    
+    let size_after = database_size(connection)?;
+    let report = MaintenanceReport {
+        size_before,
+        size_after,
+        reclaimed_bytes: size_before.saturating_sub(size_after),
+        pruned_generation_history,
+        pruned_backup_history,
+        pruned_tombstones,
+        pruned_revisions,
+        completed_at: now.to_rfc3339(),
+    };
+    info!("=== FIN: Base compactada de {} a {} bytes ({} contraseñas generadas y {} registros de copias descartados) ===",
+          report.size_before, report.size_after, report.pruned_generation_history, report.pruned_backup_history);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{add_generated_password, record_backup, run_migrations};
+
+    #[test]
+    fn test_prunes_history_and_reclaims_space() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let now = chrono::Utc::now();
+        let old = (now - chrono::Duration::days(400)).to_rfc3339();
+        record_backup(&connection, "vieja.alohobackup", &old, true, 1, 10, None).unwrap();
+        record_backup(&connection, "nueva.alohobackup", &now.to_rfc3339(), true, 1, 10, None).unwrap();
+        let padding = "x".repeat(4096);
+        for _ in 0..50 {
+            add_generated_password(&connection, &padding, None, &now.to_rfc3339(), 200).unwrap();
+        }
+
+        let retention = RetentionPolicy {
+            generation_history_size: 5,
+            backup_history_days: 365,
+            revisions_per_entry: 20,
+            revision_days: 365,
+        };
+        let report = compact_database(&connection, &retention, now).unwrap();
+        assert_eq!(report.pruned_generation_history, 45);
+        assert_eq!(report.pruned_backup_history, 1);
+        assert!(report.reclaimed_bytes > 0);
+        assert_eq!(report.size_after, database_size(&connection).unwrap());
+    }
+}
@@ -0,0 +1,127 @@
+//! Copias de seguridad automáticas antes de migrar el esquema
+//!
+//! Antes de aplicar migraciones sobre una base con datos se hace una copia
+//! consistente del archivo (`VACUUM INTO`) junto a él, con el nombre
+//! `<base>.migration-<fecha>-v<versión>.bak`. Si una migración daña los datos
+//! se puede volver a la última copia; la base dañada se conserva aparte.
+
+use anyhow::Result;
+use log::{info, warn};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Copias que se conservan; las más antiguas se borran
+const KEEP_BACKUPS: usize = 3;
+const MARKER: &str = ".migration-";
+const EXTENSION: &str = ".bak";
+
+/// Copia previa a una migración
+#[derive(Debug, Clone)]
+pub struct MigrationBackupFile {
+    pub path: PathBuf,
+    /// Versión del esquema de la copia
+    pub schema_version: u32,
+}
+
+/// Copia la base antes de migrarla desde `from_version`. Devuelve `None` si la
+/// base no está en un archivo (por ejemplo, en memoria).
+pub fn create_migration_backup(connection: &Connection, from_version: u32) -> Result<Option<PathBuf>> {
+    let Some(db_path) = connection.path().filter(|path| !path.is_empty()).map(PathBuf::from) else {
+        return Ok(None);
+    };
+    let file_name = file_name(&db_path)?;
+    let backup = db_path.with_file_name(format!(
+        "{}{}{}-v{}{}",
+        file_name, MARKER, chrono::Utc::now().format("%Y%m%d%H%M%S"), from_version, EXTENSION
+    ));
+    info!("Copiando la base de datos antes de migrar a {:?}...", backup);
+    connection.execute("VACUUM INTO ?", [backup.to_string_lossy()])?;
+
+    for old in list_backups(&db_path)?.into_iter().skip(KEEP_BACKUPS) {
+        if let Err(e) = std::fs::remove_file(&old.path) {
+            warn!("No se pudo borrar la copia previa a migración {:?}: {}", old.path, e);
+        }
+    }
+    Ok(Some(backup))
+}
+
+/// Última copia previa a una migración de la base en `db_path`
+pub fn latest_migration_backup(db_path: &Path) -> Result<Option<MigrationBackupFile>> {
+    Ok(list_backups(db_path)?.into_iter().next())
+}
+
+/// Sustituye la base por la copia. La base actual se mueve a
+/// `<base>.rolled-back-<fecha>`, que se devuelve. La conexión tiene que estar
+/// cerrada.
+pub fn restore_migration_backup(db_path: &Path, backup: &Path) -> Result<PathBuf> {
+    let rolled_back = db_path.with_file_name(format!(
+        "{}.rolled-back-{}",
+        file_name(db_path)?,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    info!("Restaurando {:?}; la base actual se guarda en {:?}", backup, rolled_back);
+    std::fs::copy(db_path, &rolled_back)?;
+    std::fs::copy(backup, db_path)?;
+    // Un registro WAL sobrante de la base dañada se aplicaría sobre la copia
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = db_path.with_file_name(format!("{}{}", file_name(db_path)?, suffix));
+        if sidecar.exists() {
+            std::fs::remove_file(sidecar)?;
+        }
+    }
+    Ok(rolled_back)
+}
+
+fn file_name(db_path: &Path) -> Result<String> {
+    db_path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow::anyhow!("Ruta de base de datos sin nombre de archivo: {:?}", db_path))
+}
+
+/// Copias de la base, de la más nueva a la más antigua
+fn list_backups(db_path: &Path) -> Result<Vec<MigrationBackupFile>> {
+    let Some(dir) = db_path.parent() else { return Ok(Vec::new()) };
+    let prefix = format!("{}{}", file_name(db_path)?, MARKER);
+    let mut backups = Vec::new();
+    for item in std::fs::read_dir(dir)? {
+        let path = item?.path();
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        // <fecha>-v<versión>; la fecha ordena las copias
+        let Some(stamp) = name.strip_prefix(&prefix).and_then(|rest| rest.strip_suffix(EXTENSION)) else { continue };
+        let Some((date, version)) = stamp.split_once("-v") else { continue };
+        let Ok(schema_version) = version.parse() else { continue };
+        backups.push((date.to_string(), MigrationBackupFile { path, schema_version }));
+    }
+    backups.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(backups.into_iter().map(|(_, backup)| backup).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backs_up_and_restores() {
+        let dir = std::env::temp_dir().join(format!("alohopass-migration-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("alohopass.db");
+
+        let connection = Connection::open(&db_path).unwrap();
+        connection.execute_batch("CREATE TABLE t (value TEXT); INSERT INTO t VALUES ('antes');").unwrap();
+        let backup = create_migration_backup(&connection, 1).unwrap().unwrap();
+        connection.execute("UPDATE t SET value = 'dañado'", []).unwrap();
+        drop(connection);
+
+        let latest = latest_migration_backup(&db_path).unwrap().unwrap();
+        assert_eq!(latest.path, backup);
+        assert_eq!(latest.schema_version, 1);
+        let rolled_back = restore_migration_backup(&db_path, &latest.path).unwrap();
+
+        let value = |path: &Path| Connection::open(path).unwrap()
+            .query_row("SELECT value FROM t", [], |row| row.get::<_, String>(0))
+            .unwrap();
+        assert_eq!(value(&db_path), "antes");
+        assert_eq!(value(&rolled_back), "dañado");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,138 @@
-use rusqlite::Connection;
+//! Migraciones numeradas del esquema
+//!
+//! Cada migración es un script SQL en `migrations/` con un número de versión.
+//! Las aplicadas se registran en `schema_version` junto con el SHA-256 del
+//! script; al abrir la base se aplican en orden las que falten, cada una en su
+//! propia transacción, y se comprueba que ninguna ya aplicada haya cambiado.
+//! Una migración publicada no se edita nunca: los cambios van en una nueva.
+
+use rusqlite::{Connection, OptionalExtension};
 use anyhow::Result;
-use log::{info, error};
+use log::{info, error, warn};
+use sha2::{Digest, Sha256};
+use super::create_migration_backup;
+
+/// Migración del registro
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        hex::encode(Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+/// Registro de migraciones, en orden de versión y sin huecos
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Esquema inicial",
+        up: include_str!("migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "Marcas de eliminación para la sincronización",
+        up: include_str!("migrations/0002_tombstones.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "Historial de revisiones de las entradas",
+        up: include_str!("migrations/0003_entry_revisions.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "Registro de actividad",
+        up: include_str!("migrations/0004_activity_log.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "Dominio registrable de las entradas",
+        up: include_str!("migrations/0005_entry_domain.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "Entradas encriptadas como un solo registro",
+        up: include_str!("migrations/0006_entry_record.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "Categorías vacías como NULL",
+        up: include_str!("migrations/0007_empty_ids.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "Claves de sincronización de los dispositivos emparejados",
+        up: include_str!("migrations/0008_device_sync_keys.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "Nombre y huella de los dispositivos de confianza",
+        up: include_str!("migrations/0009_trusted_device_details.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "Diario de cambios de la sincronización",
+        up: include_str!("migrations/0010_sync_changes.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "Vectores de versiones de la sincronización",
+        up: include_str!("migrations/0011_version_vectors.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "Último cambio de cada campo de las entradas",
+        up: include_str!("migrations/0012_entry_field_stamps.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "Número de cambio de los sellos de campo",
+        up: include_str!("migrations/0013_field_stamp_counters.sql"),
+    },
+    Migration {
+        version: 14,
+        description: "Historial de sincronizaciones",
+        up: include_str!("migrations/0014_sync_history.sql"),
+    },
+    Migration {
+        version: 15,
+        description: "Categorías excluidas de la sincronización",
+        up: include_str!("migrations/0015_sync_scopes.sql"),
+    },
+    Migration {
+        version: 16,
+        description: "Claves estáticas de los dispositivos de confianza",
+        up: include_str!("migrations/0016_trusted_device_static_keys.sql"),
+    },
+    Migration {
+        version: 17,
+        description: "Copias iniciales de la bóveda a medio recibir",
+        up: include_str!("migrations/0017_sync_bootstraps.sql"),
+    },
+    Migration {
+        version: 18,
+        description: "Totales de sincronización por dispositivo",
+        up: include_str!("migrations/0018_device_sync_stats.sql"),
+    },
+    Migration {
+        version: 19,
+        description: "Entradas compartidas por enlace",
+        up: include_str!("migrations/0019_shared_links.sql"),
+    },
+    Migration {
+        version: 20,
+        description: "Dominios de las entradas según la Public Suffix List",
+        up: include_str!("migrations/0020_recompute_entry_domains.sql"),
+    },
+];
+
+/// Versión del esquema que espera esta versión de la aplicación
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
 
 /// Función de utilidad para verificar si una tabla existe
 fn table_exists(connection: &Connection, table_name: &str) -> bool {
@@ -20,135 +152,190 @@ fn table_exists(connection: &Connection, table_name: &str) -> bool {
     }
 }
 
-pub fn run_migrations(connection: &Connection) -> Result<()> {
-    info!("=== INICIO: Ejecutando migraciones de base de datos ===");
-    info!("Conexión recibida: {:?}", connection);
-    
-    // Verificar si la tabla users ya existe antes de crearla
-    info!("Verificando si la tabla users ya existe...");
-    let table_exists_before = table_exists(connection, "users");
-    info!("Tabla users existe antes de migraciones: {}", table_exists_before);
-    
-    // Crear tablas si no existen
-    info!("Creando tabla users...");
-    match connection.execute(
-        "CREATE TABLE IF NOT EXISTS users (
-            id TEXT PRIMARY KEY,
-            email TEXT,
-            master_password_hash TEXT NOT NULL,
-            salt TEXT NOT NULL,
-            created_at TEXT NOT NULL,
-            last_login TEXT
+/// Función de utilidad para verificar si una columna existe en una tabla
+fn column_exists(connection: &Connection, table_name: &str, column_name: &str) -> Result<bool> {
+    let mut stmt = connection.prepare(&format!("PRAGMA table_info({})", table_name))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(columns.iter().any(|name| name == column_name))
+}
+
+fn create_version_table(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL
         )",
         [],
-    ) {
-        Ok(_) => info!("Tabla users creada/verificada correctamente"),
-        Err(e) => {
-            error!("ERROR al crear tabla users: {}", e);
-            return Err(anyhow::anyhow!("Error al crear tabla users: {}", e));
+    )?;
+    Ok(())
+}
+
+/// Versión actual del esquema (0 si la base es nueva o anterior al registro)
+pub fn current_version(connection: &Connection) -> Result<u32> {
+    if !table_exists(connection, "schema_version") {
+        return Ok(0);
+    }
+    let version: Option<u32> = connection
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+        .optional()?
+        .flatten();
+    Ok(version.unwrap_or(0))
+}
+
+/// Migraciones del registro que todavía no se aplicaron
+pub fn pending_migrations(connection: &Connection) -> Result<Vec<&'static Migration>> {
+    let current = current_version(connection)?;
+    Ok(MIGRATIONS.iter().filter(|migration| migration.version > current).collect())
+}
+
+/// Comprueba que las migraciones aplicadas coinciden con las del registro
+fn verify_applied(connection: &Connection) -> Result<()> {
+    let mut stmt = connection.prepare("SELECT version, checksum FROM schema_version ORDER BY version")?;
+    let applied = stmt.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (version, checksum) in applied {
+        let Some(migration) = MIGRATIONS.iter().find(|migration| migration.version == version) else {
+            error!("La base de datos tiene la migración {} y esta versión solo conoce hasta la {}", version, latest_version());
+            return Err(anyhow::anyhow!(
+                "La base de datos usa un esquema más nuevo (versión {}) que esta versión de la aplicación", version
+            ));
+        };
+        if migration.checksum() != checksum {
+            error!("La migración {} aplicada no coincide con la del registro", version);
+            return Err(anyhow::anyhow!("La migración {} ({}) cambió después de aplicarse", version, migration.description));
         }
     }
-    
-    // Verificar si la tabla users existe después de crearla
-    info!("Verificando si la tabla users existe después de crearla...");
-    let table_exists_after = table_exists(connection, "users");
-    info!("Tabla users existe después de crearla: {}", table_exists_after);
-    
-    if !table_exists_after {
-        error!("ERROR CRÍTICO: La tabla users no existe después de intentar crearla");
-        return Err(anyhow::anyhow!("La tabla users no existe después de intentar crearla"));
+    Ok(())
+}
+
+/// Bases creadas antes del registro de versiones: el esquema se fue ampliando
+/// con columnas agregadas al vuelo, así que se completan las que falten antes de
+/// dar la migración 1 por aplicada
+fn upgrade_unversioned(connection: &Connection) -> Result<()> {
+    if !table_exists(connection, "password_entries") {
+        return Ok(());
     }
-    
-    info!("Creando tabla categories...");
-    match connection.execute(
-        "CREATE TABLE IF NOT EXISTS categories (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            color TEXT NOT NULL,
-            icon TEXT,
-            parent_id TEXT,
-            created_at TEXT NOT NULL,
-            FOREIGN KEY (parent_id) REFERENCES categories (id)
-        )",
-        [],
-    ) {
-        Ok(_) => info!("Tabla categories creada/verificada correctamente"),
-        Err(e) => {
-            error!("ERROR al crear tabla categories: {}", e);
-            return Err(anyhow::anyhow!("Error al crear tabla categories: {}", e));
+    info!("Base de datos anterior al registro de versiones, completando columnas...");
+    for (column, definition) in [
+        ("autotype_sequence", "TEXT"),
+        ("totp_secret", "TEXT"),
+        ("password_strength", "INTEGER"),
+        ("password_guesses_log10", "REAL"),
+        ("password_fingerprint", "TEXT"),
+        ("password_changed_at", "TEXT"),
+        ("password_breach_count", "INTEGER"),
+    ] {
+        if !column_exists(connection, "password_entries", column)? {
+            info!("Agregando columna {} a password_entries...", column);
+            connection.execute(&format!("ALTER TABLE password_entries ADD COLUMN {} {}", column, definition), [])?;
         }
     }
-    
-    info!("Creando tabla password_entries...");
-    match connection.execute(
-        "CREATE TABLE IF NOT EXISTS password_entries (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            username TEXT NOT NULL,
-            password TEXT NOT NULL,
-            url TEXT,
-            notes TEXT,
-            category_id TEXT,
-            tags TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            last_used TEXT,
-            FOREIGN KEY (category_id) REFERENCES categories (id)
-        )",
-        [],
-    ) {
-        Ok(_) => info!("Tabla password_entries creada/verificada correctamente"),
-        Err(e) => {
-            error!("ERROR al crear tabla password_entries: {}", e);
-            return Err(anyhow::anyhow!("Error al crear tabla password_entries: {}", e));
+    Ok(())
+}
+
+fn apply(connection: &Connection, migration: &Migration) -> Result<()> {
+    info!("Aplicando migración {}: {}...", migration.version, migration.description);
+    let tx = connection.unchecked_transaction()?;
+    tx.execute_batch(migration.up)?;
+    tx.execute(
+        "INSERT INTO schema_version (version, description, checksum, applied_at) VALUES (?, ?, ?, ?)",
+        rusqlite::params![
+            migration.version,
+            migration.description,
+            migration.checksum(),
+            chrono::Utc::now().to_rfc3339(),
+        ],
+    )?;
+    tx.commit()?;
+    info!("Migración {} aplicada correctamente", migration.version);
+    Ok(())
+}
+
+pub fn run_migrations(connection: &Connection) -> Result<()> {
+    info!("=== INICIO: Ejecutando migraciones de base de datos ===");
+
+    let unversioned = !table_exists(connection, "schema_version");
+    let from_version = current_version(connection)?;
+    create_version_table(connection)?;
+    verify_applied(connection)?;
+
+    let pending = pending_migrations(connection)?;
+    if pending.is_empty() {
+        info!("Esquema al día (versión {})", latest_version());
+    } else if from_version > 0 || table_exists(connection, "password_entries") {
+        // Hay datos que una migración defectuosa podría dañar: primero se copian
+        match create_migration_backup(connection, from_version) {
+            Ok(Some(backup)) => info!("Copia previa a la migración creada en {:?}", backup),
+            Ok(None) => info!("Base de datos sin archivo, no se hace copia previa a la migración"),
+            Err(e) => {
+                error!("ERROR al copiar la base de datos antes de migrarla: {}", e);
+                return Err(anyhow::anyhow!("No se pudo copiar la base de datos antes de migrarla: {}", e));
+            }
         }
     }
-    
-    // Crear tabla de recovery keys (comentada temporalmente)
-    // connection.execute(
-    //     "CREATE TABLE IF NOT EXISTS recovery_keys (
-    //         id TEXT PRIMARY KEY,
-    //         encrypted_master TEXT NOT NULL,
-    //         created_at TEXT NOT NULL
-    //     )",
-    //     [],
-    // ).map_err(|e| format!("Error creando tabla recovery_keys: {}", e))?;
-    
-    // Crear índices para mejor performance
-    info!("Creando índices...");
-    match connection.execute(
-        "CREATE INDEX IF NOT EXISTS idx_password_entries_title ON password_entries (title)",
-        [],
-    ) {
-        Ok(_) => info!("Índice idx_password_entries_title creado/verificado correctamente"),
-        Err(e) => {
-            error!("ERROR al crear índice idx_password_entries_title: {}", e);
-            return Err(anyhow::anyhow!("Error al crear índice idx_password_entries_title: {}", e));
-        }
+    if unversioned {
+        upgrade_unversioned(connection)?;
     }
-    
-    match connection.execute(
-        "CREATE INDEX IF NOT EXISTS idx_password_entries_category ON password_entries (category_id)",
-        [],
-    ) {
-        Ok(_) => info!("Índice idx_password_entries_category creado/verificado correctamente"),
-        Err(e) => {
-            error!("ERROR al crear índice idx_password_entries_category: {}", e);
-            return Err(anyhow::anyhow!("Error al crear índice idx_password_entries_category: {}", e));
+    for migration in pending {
+        if let Err(e) = apply(connection, migration) {
+            error!("ERROR al aplicar la migración {}: {}", migration.version, e);
+            return Err(anyhow::anyhow!("Error al aplicar la migración {} ({}): {}", migration.version, migration.description, e));
         }
     }
-    
-    match connection.execute(
-        "CREATE INDEX IF NOT EXISTS idx_password_entries_username ON password_entries (username)",
-        [],
-    ) {
-        Ok(_) => info!("Índice idx_password_entries_username creado/verificado correctamente"),
-        Err(e) => {
-            error!("ERROR al crear índice idx_password_entries_username: {}", e);
-            return Err(anyhow::anyhow!("Error al crear índice idx_password_entries_username: {}", e));
-        }
+
+    if !table_exists(connection, "users") {
+        warn!("La tabla users no existe después de las migraciones");
     }
-    
-    info!("=== FIN: Migraciones completadas exitosamente ===");
+    info!("=== FIN: Migraciones completadas exitosamente (versión {}) ===", current_version(connection)?);
     Ok(())
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_are_sequential() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, index + 1);
+        }
+    }
+
+    #[test]
+    fn test_migrates_new_and_unversioned_databases() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        run_migrations(&connection).unwrap();
+        assert_eq!(current_version(&connection).unwrap(), latest_version());
+        assert!(pending_migrations(&connection).unwrap().is_empty());
+
+        let legacy = Connection::open_in_memory().unwrap();
+        legacy.execute_batch(
+            "CREATE TABLE password_entries (id TEXT PRIMARY KEY, title TEXT NOT NULL, username TEXT NOT NULL,
+                password TEXT NOT NULL, url TEXT, notes TEXT, category_id TEXT, tags TEXT,
+                created_at TEXT NOT NULL, updated_at TEXT NOT NULL, last_used TEXT, totp_secret TEXT)",
+        ).unwrap();
+        run_migrations(&legacy).unwrap();
+        assert!(column_exists(&legacy, "password_entries", "password_breach_count").unwrap());
+        assert_eq!(current_version(&legacy).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_rejects_changed_or_unknown_migrations() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        connection.execute("UPDATE schema_version SET checksum = 'x' WHERE version = 1", []).unwrap();
+        assert!(run_migrations(&connection).is_err());
+
+        let newer = Connection::open_in_memory().unwrap();
+        run_migrations(&newer).unwrap();
+        newer.execute(
+            "INSERT INTO schema_version (version, description, checksum, applied_at) VALUES (999, 'futura', '', '')",
+            [],
+        ).unwrap();
+        assert!(run_migrations(&newer).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+-- Esquema inicial: el que creaban las migraciones idempotentes anteriores al
+-- registro de versiones. Con IF NOT EXISTS se puede aplicar sobre esas bases.
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    email TEXT,
+    master_password_hash TEXT NOT NULL,
+    salt TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    last_login TEXT
+);
+
+CREATE TABLE IF NOT EXISTS categories (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL,
+    icon TEXT,
+    parent_id TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (parent_id) REFERENCES categories (id)
+);
+
+CREATE TABLE IF NOT EXISTS password_entries (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    username TEXT NOT NULL,
+    password TEXT NOT NULL,
+    url TEXT,
+    notes TEXT,
+    category_id TEXT,
+    tags TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    last_used TEXT,
+    -- Secuencia de auto-type propia de la entrada (NULL = secuencia por defecto)
+    autotype_sequence TEXT,
+    -- Secreto TOTP encriptado (NULL = sin segundo factor)
+    totp_secret TEXT,
+    -- Metadatos de la contraseña para estadísticas sin desencriptar la bóveda
+    password_strength INTEGER,
+    password_guesses_log10 REAL, -- NULL = puntaje calculado con el medidor anterior
+    password_fingerprint TEXT,
+    password_changed_at TEXT,
+    password_breach_count INTEGER, -- NULL = no comprobada
+    FOREIGN KEY (category_id) REFERENCES categories (id)
+);
+
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS security_reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    generated_at TEXT NOT NULL,
+    report TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS password_policies (
+    id TEXT PRIMARY KEY,
+    domain TEXT NOT NULL UNIQUE,
+    min_length INTEGER,
+    max_length INTEGER,
+    forbidden_characters TEXT NOT NULL DEFAULT '',
+    require_uppercase INTEGER NOT NULL DEFAULT 0,
+    require_lowercase INTEGER NOT NULL DEFAULT 0,
+    require_numbers INTEGER NOT NULL DEFAULT 0,
+    require_symbols INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Últimas contraseñas generadas, encriptadas, por si se pierden antes de guardarlas
+CREATE TABLE IF NOT EXISTS generation_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    password TEXT NOT NULL,
+    url TEXT,
+    generated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS backup_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    automatic INTEGER NOT NULL DEFAULT 0,
+    entries INTEGER NOT NULL DEFAULT 0,
+    size_bytes INTEGER NOT NULL DEFAULT 0,
+    error TEXT
+);
+
+-- Archivos adjuntos de las entradas, encriptados como los demás campos
+CREATE TABLE IF NOT EXISTS attachments (
+    id TEXT PRIMARY KEY,
+    entry_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    data TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (entry_id) REFERENCES password_entries (id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_entries_title ON password_entries (title);
+CREATE INDEX IF NOT EXISTS idx_password_entries_category ON password_entries (category_id);
+CREATE INDEX IF NOT EXISTS idx_password_entries_username ON password_entries (username);
+CREATE INDEX IF NOT EXISTS idx_attachments_entry ON attachments (entry_id);
@@ -0,0 +1,24 @@
+-- Entradas eliminadas, para que la sincronización no las resucite desde otro
+-- dispositivo. Se purgan cuando todos los dispositivos de confianza las confirman.
+CREATE TABLE IF NOT EXISTS tombstones (
+    entry_id TEXT PRIMARY KEY,
+    deleted_at TEXT NOT NULL
+);
+
+-- Dispositivos de confianza con los que se sincroniza la bóveda
+CREATE TABLE IF NOT EXISTS trusted_devices (
+    device_id TEXT PRIMARY KEY,
+    trusted_at TEXT NOT NULL
+);
+
+-- Qué dispositivo confirmó cada eliminación
+CREATE TABLE IF NOT EXISTS tombstone_acks (
+    entry_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    acknowledged_at TEXT NOT NULL,
+    PRIMARY KEY (entry_id, device_id),
+    FOREIGN KEY (entry_id) REFERENCES tombstones (entry_id) ON DELETE CASCADE,
+    FOREIGN KEY (device_id) REFERENCES trusted_devices (device_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_tombstones_deleted_at ON tombstones (deleted_at);
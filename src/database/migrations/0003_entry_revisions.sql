@@ -0,0 +1,28 @@
+-- Estado completo de una entrada antes de cada modificación, con los campos
+-- sensibles encriptados igual que en password_entries. La revisión vale desde
+-- su updated_at hasta revised_at.
+CREATE TABLE IF NOT EXISTS entry_revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id TEXT NOT NULL,
+    revised_at TEXT NOT NULL,
+    title TEXT NOT NULL,
+    username TEXT NOT NULL,
+    password TEXT NOT NULL,
+    url TEXT,
+    notes TEXT,
+    category_id TEXT,
+    tags TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    last_used TEXT,
+    autotype_sequence TEXT,
+    totp_secret TEXT,
+    password_strength INTEGER,
+    password_guesses_log10 REAL,
+    password_fingerprint TEXT,
+    password_changed_at TEXT,
+    password_breach_count INTEGER,
+    FOREIGN KEY (entry_id) REFERENCES password_entries (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_entry_revisions_entry ON entry_revisions (entry_id, id);
@@ -0,0 +1,14 @@
+-- Operaciones sobre la bóveda, sin secretos. No hay clave foránea a
+-- password_entries: los eventos de una entrada se conservan al borrarla.
+-- category_id es la categoría de la entrada cuando ocurrió el evento.
+CREATE TABLE IF NOT EXISTS activity_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    action TEXT NOT NULL,
+    entry_id TEXT,
+    category_id TEXT,
+    details TEXT,
+    occurred_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_activity_log_category ON activity_log (category_id, id);
+CREATE INDEX IF NOT EXISTS idx_activity_log_entry ON activity_log (entry_id, id);
@@ -0,0 +1,6 @@
+-- Dominio registrable (eTLD+1) de la URL, para buscar las entradas de un sitio
+-- al autocompletar. Lo calcula la aplicación al guardar la entrada; las
+-- entradas anteriores a esta columna se completan al abrir la base.
+ALTER TABLE password_entries ADD COLUMN domain TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_password_entries_domain ON password_entries (domain);
@@ -0,0 +1,5 @@
+-- Registro encriptado con el título, el usuario y la contraseña juntos, para
+-- las entradas que se guardan como un solo registro. En esas entradas las
+-- columnas title, username y password quedan vacías.
+ALTER TABLE password_entries ADD COLUMN record TEXT;
+ALTER TABLE entry_revisions ADD COLUMN record TEXT;
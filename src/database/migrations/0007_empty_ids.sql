@@ -0,0 +1,6 @@
+-- Las categorías vacías se guardaban a veces como texto vacío en lugar de NULL.
+-- Los ids ahora se leen como UUID, así que el texto vacío pasa a ser NULL.
+UPDATE categories SET parent_id = NULL WHERE parent_id = '';
+UPDATE password_entries SET category_id = NULL WHERE category_id = '';
+UPDATE entry_revisions SET category_id = NULL WHERE category_id = '';
+UPDATE activity_log SET category_id = NULL WHERE category_id = '';
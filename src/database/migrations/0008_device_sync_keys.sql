@@ -0,0 +1,4 @@
+-- Clave de sincronización acordada al emparejar cada dispositivo, encriptada
+-- con la clave maestra. Los dispositivos de confianza anteriores no la tienen
+-- y tienen que volver a emparejarse.
+ALTER TABLE trusted_devices ADD COLUMN sync_key TEXT;
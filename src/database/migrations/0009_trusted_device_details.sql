@@ -0,0 +1,4 @@
+-- Nombre del dispositivo de confianza y huella de la clave con la que se
+-- emparejó, para reconocerlo aunque no esté conectado
+ALTER TABLE trusted_devices ADD COLUMN name TEXT;
+ALTER TABLE trusted_devices ADD COLUMN key_fingerprint TEXT;
@@ -0,0 +1,18 @@
+-- Diario de cambios de la sincronización. Un cambio sin `synced_at` todavía no
+-- llegó a los dispositivos de confianza y se vuelve a cargar al iniciar.
+CREATE TABLE IF NOT EXISTS sync_changes (
+    id TEXT PRIMARY KEY,
+    element_id TEXT NOT NULL,
+    change_type TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    source_device TEXT NOT NULL,
+    element_data BLOB,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    version INTEGER NOT NULL,
+    previous_hash TEXT,
+    current_hash TEXT NOT NULL,
+    synced_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_changes_element_id ON sync_changes (element_id);
+CREATE INDEX IF NOT EXISTS idx_sync_changes_timestamp ON sync_changes (timestamp);
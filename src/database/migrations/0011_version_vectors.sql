@@ -0,0 +1,25 @@
+-- Vector de versiones de cada elemento (entrada o categoría): por dispositivo,
+-- el número de su último cambio al elemento. Las filas quedan aunque el
+-- elemento se elimine, así la eliminación también tiene versión.
+CREATE TABLE IF NOT EXISTS element_versions (
+    element_id TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'entry',
+    device_id TEXT NOT NULL,
+    counter INTEGER NOT NULL,
+    PRIMARY KEY (element_id, device_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_element_versions_device ON element_versions (device_id, counter);
+
+-- Lo que cada dispositivo de confianza dijo haber visto en la última
+-- sincronización: el último número que conoce de cada dispositivo
+CREATE TABLE IF NOT EXISTS peer_knowledge (
+    peer_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    counter INTEGER NOT NULL,
+    PRIMARY KEY (peer_id, device_id),
+    FOREIGN KEY (peer_id) REFERENCES trusted_devices (device_id) ON DELETE CASCADE
+);
+
+-- Vector del elemento después de cada cambio del diario, en JSON
+ALTER TABLE sync_changes ADD COLUMN versions TEXT NOT NULL DEFAULT '{}';
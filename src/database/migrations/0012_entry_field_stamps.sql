@@ -0,0 +1,9 @@
+-- Último cambio de cada campo de las entradas, para combinar campo por campo
+-- las ediciones concurrentes de dos dispositivos
+CREATE TABLE IF NOT EXISTS entry_field_stamps (
+    entry_id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    changed_at TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    PRIMARY KEY (entry_id, field)
+);
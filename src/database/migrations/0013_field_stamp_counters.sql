@@ -0,0 +1,3 @@
+-- Número de cambio del dispositivo que selló cada campo, para saber si el
+-- otro dispositivo ya vio ese cambio. 0 en los sellos anteriores.
+ALTER TABLE entry_field_stamps ADD COLUMN counter INTEGER NOT NULL DEFAULT 0;
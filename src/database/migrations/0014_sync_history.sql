@@ -0,0 +1,16 @@
+-- Cada intento de sincronización con otro dispositivo, salga bien o no.
+-- error es NULL en los que salieron bien.
+CREATE TABLE IF NOT EXISTS sync_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    device_id TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    items_sent INTEGER NOT NULL DEFAULT 0,
+    items_received INTEGER NOT NULL DEFAULT 0,
+    bytes_sent INTEGER NOT NULL DEFAULT 0,
+    bytes_received INTEGER NOT NULL DEFAULT 0,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    started_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_history_device ON sync_history (device_id, id);
@@ -0,0 +1,25 @@
+-- Categorías que no se mandan a cada dispositivo de confianza; sus
+-- subcategorías tampoco se mandan
+CREATE TABLE IF NOT EXISTS sync_scopes (
+    device_id TEXT NOT NULL,
+    category_id TEXT NOT NULL,
+    PRIMARY KEY (device_id, category_id),
+    FOREIGN KEY (device_id) REFERENCES trusted_devices (device_id) ON DELETE CASCADE,
+    FOREIGN KEY (category_id) REFERENCES categories (id) ON DELETE CASCADE
+);
+
+-- Entradas que cada dispositivo no tiene por estar en una categoría excluida,
+-- para mandárselas si vuelven a estar a su alcance
+CREATE TABLE IF NOT EXISTS sync_withheld (
+    device_id TEXT NOT NULL,
+    entry_id TEXT NOT NULL,
+    PRIMARY KEY (device_id, entry_id),
+    FOREIGN KEY (device_id) REFERENCES trusted_devices (device_id) ON DELETE CASCADE
+);
+
+-- Entradas que se quitaron de aquí porque otro dispositivo las dejó fuera de
+-- lo que manda. Conservan sus versiones, pero no viajan como eliminaciones.
+CREATE TABLE IF NOT EXISTS sync_out_of_scope (
+    entry_id TEXT PRIMARY KEY,
+    removed_at TEXT NOT NULL
+);
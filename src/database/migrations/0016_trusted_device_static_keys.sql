@@ -0,0 +1,4 @@
+-- Clave estática del saludo Noise fijada al emparejar cada dispositivo. Los
+-- dispositivos de confianza anteriores no la tienen y tienen que volver a
+-- emparejarse.
+ALTER TABLE trusted_devices ADD COLUMN static_key TEXT;
@@ -0,0 +1,10 @@
+-- Copia inicial de la bóveda que se está recibiendo de cada dispositivo de
+-- confianza, para seguir desde la última página si se corta. `knowledge` es
+-- el vector de versiones en JSON.
+CREATE TABLE IF NOT EXISTS sync_bootstraps (
+    device_id TEXT PRIMARY KEY,
+    position TEXT,
+    knowledge TEXT NOT NULL,
+    complete INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (device_id) REFERENCES trusted_devices (device_id) ON DELETE CASCADE
+);
@@ -0,0 +1,31 @@
+-- Totales de las sincronizaciones con cada dispositivo. A diferencia del
+-- historial, que se recorta, se acumulan desde la primera. Arrancan con lo
+-- que ya había en el historial.
+CREATE TABLE IF NOT EXISTS device_sync_stats (
+    device_id TEXT PRIMARY KEY,
+    syncs INTEGER NOT NULL DEFAULT 0,
+    failures INTEGER NOT NULL DEFAULT 0,
+    items_sent INTEGER NOT NULL DEFAULT 0,
+    items_received INTEGER NOT NULL DEFAULT 0,
+    bytes_sent INTEGER NOT NULL DEFAULT 0,
+    bytes_received INTEGER NOT NULL DEFAULT 0,
+    last_duration_ms INTEGER NOT NULL DEFAULT 0,
+    last_sync_at TEXT,
+    last_failure_at TEXT
+);
+
+INSERT OR IGNORE INTO device_sync_stats
+    (device_id, syncs, failures, items_sent, items_received, bytes_sent, bytes_received, last_duration_ms, last_sync_at, last_failure_at)
+SELECT
+    device_id,
+    SUM(error IS NULL),
+    SUM(error IS NOT NULL),
+    SUM(items_sent),
+    SUM(items_received),
+    SUM(bytes_sent),
+    SUM(bytes_received),
+    (SELECT last.duration_ms FROM sync_history AS last WHERE last.device_id = history.device_id ORDER BY last.id DESC LIMIT 1),
+    MAX(CASE WHEN error IS NULL THEN started_at END),
+    MAX(CASE WHEN error IS NOT NULL THEN started_at END)
+FROM sync_history AS history
+GROUP BY device_id;
@@ -0,0 +1,14 @@
+-- Entradas compartidas por enlace desde este dispositivo, para poder
+-- revocarlas. `owner` es el secreto que las revoca en el relay, encriptado con
+-- la clave maestra. La clave que abre la entrada no se guarda.
+CREATE TABLE IF NOT EXISTS shared_links (
+    id TEXT PRIMARY KEY,
+    entry_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    max_views INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_shared_links_expires_at ON shared_links (expires_at);
@@ -0,0 +1,3 @@
+-- El dominio registrable pasa a calcularse con la Public Suffix List completa.
+-- Se vacía para que la aplicación lo vuelva a calcular al abrir la base.
+UPDATE password_entries SET domain = NULL;
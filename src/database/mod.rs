@@ -1,18 +1,68 @@
 mod connection;
 mod migrations;
+mod migration_backup;
 mod repository;
+mod settings;
+mod security_reports;
+mod password_policies;
+mod generation_history;
+mod categories;
+mod backup_history;
+mod attachments;
+mod worker;
+mod users;
+mod maintenance;
+mod tombstones;
+mod trusted_devices;
+mod sync_changes;
+mod element_versions;
+mod field_stamps;
+mod revisions;
+mod activity_log;
+mod sync_history;
+mod sync_scopes;
+mod sync_bootstraps;
+mod shared_links;
+mod field_encoding;
+mod location;
 
 pub use connection::*;
 pub use migrations::*;
+pub use migration_backup::*;
 pub use repository::*;
+pub use settings::*;
+pub use security_reports::*;
+pub use password_policies::*;
+pub use generation_history::*;
+pub use categories::*;
+pub use backup_history::*;
+pub use attachments::*;
+pub use worker::*;
+pub use users::*;
+pub use maintenance::*;
+pub use tombstones::*;
+pub use trusted_devices::*;
+pub use sync_changes::*;
+pub use element_versions::*;
+pub use field_stamps::*;
+pub use revisions::*;
+pub use activity_log::*;
+pub use sync_history::*;
+pub use sync_scopes::*;
+pub use sync_bootstraps::*;
+pub use shared_links::*;
+pub use field_encoding::*;
+pub use location::*;
 
 use rusqlite::Connection;
 use anyhow::Result;
-use std::path::Path;
-use log::{info, error};
+use std::path::{Path, PathBuf};
+use log::{info, warn, error};
 
 pub struct DatabaseManager {
     connection: Connection,
+    path: PathBuf,
+    settings: ConnectionSettings,
 }
 
 impl DatabaseManager {
@@ -21,7 +71,8 @@ impl DatabaseManager {
         info!("Ruta de base de datos: {:?}", path.as_ref());
         
         info!("Abriendo conexión a SQLite...");
-        let connection = match rusqlite::Connection::open(path.as_ref()) {
+        let settings = ConnectionSettings::default();
+        let connection = match Self::open_configured(path.as_ref(), &settings) {
             Ok(conn) => {
                 info!("Conexión a SQLite abierta exitosamente");
                 conn
@@ -32,7 +83,7 @@ impl DatabaseManager {
             }
         };
         
-        let mut manager = Self { connection };
+        let mut manager = Self { connection, path: path.as_ref().to_path_buf(), settings };
         info!("DatabaseManager creado, ejecutando migraciones...");
         
         // Ejecutar migraciones
@@ -44,6 +95,11 @@ impl DatabaseManager {
             }
         }
         
+        // Las entradas guardadas antes de existir la columna domain no la tienen
+        if let Err(e) = PasswordRepository::new(&manager.connection).backfill_domains() {
+            warn!("No se pudo calcular el dominio de las entradas antiguas: {}", e);
+        }
+        
         info!("=== FIN: Base de datos inicializada correctamente ===");
         Ok(manager)
     }
@@ -53,7 +109,8 @@ impl DatabaseManager {
         info!("Ruta de base de datos: {:?}", path.as_ref());
         
         info!("Abriendo conexión a SQLite...");
-        let connection = match rusqlite::Connection::open(path.as_ref()) {
+        let settings = ConnectionSettings::default();
+        let connection = match Self::open_configured(path.as_ref(), &settings) {
             Ok(conn) => {
                 info!("Conexión a SQLite abierta exitosamente");
                 conn
@@ -64,7 +121,7 @@ impl DatabaseManager {
             }
         };
         
-        let manager = Self { connection };
+        let manager = Self { connection, path: path.as_ref().to_path_buf(), settings };
         info!("DatabaseManager creado SIN migraciones");
         
         info!("=== FIN: DatabaseManager creado correctamente ===");
@@ -79,6 +136,29 @@ impl DatabaseManager {
         &mut self.connection
     }
     
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Ajustes con los que se abrió la conexión
+    pub fn connection_settings(&self) -> &ConnectionSettings {
+        &self.settings
+    }
+    
+    /// Abre otra conexión a la misma base con los mismos ajustes, para los
+    /// componentes que trabajan en su propio hilo (sincronización, servidor de
+    /// la extensión) sin pasar por el lock del estado
+    pub fn open_shared_connection(&self) -> Result<Connection> {
+        Self::open_configured(&self.path, &self.settings)
+            .map_err(|e| anyhow::anyhow!("Error al abrir conexión SQLite: {}", e))
+    }
+    
+    fn open_configured(path: &Path, settings: &ConnectionSettings) -> rusqlite::Result<Connection> {
+        let connection = rusqlite::Connection::open(path)?;
+        configure_connection(&connection, settings)?;
+        Ok(connection)
+    }
+    
     fn run_migrations(&mut self) -> Result<()> {
         info!("=== INICIO: Ejecutando migraciones ===");
         let result = migrations::run_migrations(&self.connection);
@@ -137,36 +217,3 @@ impl DatabaseManager {
         Ok(is_initialized)
     }
 }
-
-pub fn get_database_path() -> Result<String> {
-    info!("=== INICIO: Obteniendo ruta de base de datos ===");
-    
-    info!("Obteniendo variable de entorno APPDATA...");
-    let app_data = std::env::var("APPDATA")
-        .or_else(|_| {
-            info!("APPDATA no encontrada, intentando HOME...");
-            std::env::var("HOME")
-        })
-        .map_err(|_| {
-            error!("No se pudo determinar el directorio de datos de la aplicación");
-            anyhow::anyhow!("No se pudo determinar el directorio de datos de la aplicación")
-        })?;
-    info!("Directorio base obtenido: {}", app_data);
-    
-    let db_dir = format!("{}/alohopass", app_data);
-    info!("Directorio de base de datos: {}", db_dir);
-    
-    info!("Creando directorio si no existe...");
-    std::fs::create_dir_all(&db_dir)
-        .map_err(|e| {
-            error!("No se pudo crear el directorio de la base de datos: {}", e);
-            anyhow::anyhow!("No se pudo crear el directorio de la base de datos: {}", e)
-        })?;
-    info!("Directorio creado/verificado correctamente");
-    
-    let db_path = format!("{}/alohopass.db", db_dir);
-    info!("Ruta final de base de datos: {}", db_path);
-    info!("=== FIN: Ruta de base de datos obtenida ===");
-    
-    Ok(db_path)
-} 
\ No newline at end of file
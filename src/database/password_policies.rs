@@ -0,0 +1,113 @@
+use rusqlite::{Connection, OptionalExtension, Row};
+use anyhow::Result;
+use log::info;
+use crate::models::{PasswordPolicy, PasswordPolicyRequest};
+
+const POLICY_COLUMNS: &str = "id, domain, min_length, max_length, forbidden_characters,
+    require_uppercase, require_lowercase, require_numbers, require_symbols, created_at, updated_at";
+
+fn policy_from_row(row: &Row) -> rusqlite::Result<PasswordPolicy> {
+    Ok(PasswordPolicy {
+        id: row.get(0)?,
+        domain: row.get(1)?,
+        min_length: row.get::<_, Option<i64>>(2)?.map(|length| length as usize),
+        max_length: row.get::<_, Option<i64>>(3)?.map(|length| length as usize),
+        forbidden_characters: row.get(4)?,
+        require_uppercase: row.get(5)?,
+        require_lowercase: row.get(6)?,
+        require_numbers: row.get(7)?,
+        require_symbols: row.get(8)?,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+    })
+}
+
+/// Todas las políticas, ordenadas por dominio
+pub fn list_password_policies(connection: &Connection) -> Result<Vec<PasswordPolicy>> {
+    let mut stmt = connection.prepare(&format!(
+        "SELECT {} FROM password_policies ORDER BY domain",
+        POLICY_COLUMNS
+    ))?;
+    let policies = stmt.query_map([], policy_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(policies)
+}
+
+/// Política más específica para el host: la del propio dominio o la del dominio padre más cercano
+pub fn find_password_policy(connection: &Connection, host: &str) -> Result<Option<PasswordPolicy>> {
+    let mut stmt = connection.prepare(&format!(
+        "SELECT {} FROM password_policies
+         WHERE ?1 = domain OR ?1 LIKE '%.' || domain
+         ORDER BY LENGTH(domain) DESC LIMIT 1",
+        POLICY_COLUMNS
+    ))?;
+    Ok(stmt.query_row([host], policy_from_row).optional()?)
+}
+
+/// Guarda una política nueva; `domain` ya debe venir normalizado
+pub fn insert_password_policy(
+    connection: &Connection,
+    id: &str,
+    request: &PasswordPolicyRequest,
+    now: &str,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO password_policies (id, domain, min_length, max_length, forbidden_characters,
+            require_uppercase, require_lowercase, require_numbers, require_symbols, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            id,
+            request.domain,
+            request.min_length.map(|length| length as i64),
+            request.max_length.map(|length| length as i64),
+            request.forbidden_characters,
+            request.require_uppercase,
+            request.require_lowercase,
+            request.require_numbers,
+            request.require_symbols,
+            now,
+            now,
+        ],
+    )?;
+    info!("Política de contraseñas guardada para {}", request.domain);
+    Ok(())
+}
+
+/// Reemplaza las reglas de una política; devuelve `false` si no existe
+pub fn update_password_policy(
+    connection: &Connection,
+    id: &str,
+    request: &PasswordPolicyRequest,
+    now: &str,
+) -> Result<bool> {
+    let updated = connection.execute(
+        "UPDATE password_policies SET domain = ?, min_length = ?, max_length = ?, forbidden_characters = ?,
+            require_uppercase = ?, require_lowercase = ?, require_numbers = ?, require_symbols = ?, updated_at = ?
+         WHERE id = ?",
+        rusqlite::params![
+            request.domain,
+            request.min_length.map(|length| length as i64),
+            request.max_length.map(|length| length as i64),
+            request.forbidden_characters,
+            request.require_uppercase,
+            request.require_lowercase,
+            request.require_numbers,
+            request.require_symbols,
+            now,
+            id,
+        ],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Elimina una política; devuelve `false` si no existe
+pub fn delete_password_policy(connection: &Connection, id: &str) -> Result<bool> {
+    let deleted = connection.execute("DELETE FROM password_policies WHERE id = ?", [id])?;
+    Ok(deleted > 0)
+}
+
+/// Elimina todas las políticas (al restaurar una copia reemplazando la bóveda)
+pub fn delete_all_password_policies(connection: &Connection) -> Result<()> {
+    connection.execute("DELETE FROM password_policies", [])?;
+    Ok(())
+}
@@ -1,5 +1,146 @@
-use rusqlite::{Connection, Result, params};
-use crate::models::{PasswordEntry, Category, User};
+//! Acceso a `password_entries`
+//!
+//! Todo el SQL de las entradas vive aquí. El repositorio trabaja con los campos
+//! sensibles tal como se guardan (`EncryptedData` en formato compacto): encriptar y
+//! desencriptar es cosa de `vault::EntryCipher`, que se ejecuta fuera del hilo de
+//! la base de datos. El título, el usuario y la contraseña van en su columna
+//! cada uno o, si la entrada se guardó como un solo registro, juntos en
+//! `record` y con sus columnas vacías.
+
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use crate::models::{CategoryCount, CategoryId, EntryId, PasswordListRequest, SortDirection, SortField};
+use log::info;
+use crate::domain::registrable_domain_from_url;
+use super::{clear_tombstone, record_revision, record_tombstone};
+
+const ENTRY_COLUMNS: &str = "id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used, record";
+
+/// Fila de password_entries con los campos sensibles todavía encriptados
+#[derive(Debug, Clone)]
+pub struct EncryptedEntryRow {
+    pub id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub category_id: Option<CategoryId>,
+    pub tags: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub last_used: Option<String>,
+    /// Título, usuario y contraseña encriptados juntos, si la entrada se guardó así
+    pub record: Option<String>,
+}
+
+/// Fortaleza y huella de una contraseña, guardadas junto a la entrada
+/// para calcular estadísticas sin desencriptar la bóveda
+#[derive(Debug, Clone)]
+pub struct PasswordMetadata {
+    pub strength: u8,
+    pub guesses_log10: f64,
+    pub fingerprint: String,
+}
+
+/// Título, usuario y contraseña ya encriptados, cada uno en su columna o juntos
+/// en un registro
+#[derive(Debug, Clone)]
+pub struct SealedCredentials {
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub record: Option<String>,
+}
+
+/// Entrada con los campos sensibles ya encriptados, lista para guardarse
+#[derive(Debug, Clone)]
+pub struct SealedEntry {
+    pub id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub category_id: Option<CategoryId>,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub last_used: Option<String>,
+    pub meta: PasswordMetadata,
+    pub password_changed_at: String,
+    pub breach_count: Option<u64>,
+    pub totp_secret: Option<String>,
+    pub autotype_sequence: Option<String>,
+    /// Título, usuario y contraseña encriptados juntos; las columnas de cada
+    /// uno quedan vacías
+    pub record: Option<String>,
+}
+
+/// Columnas encriptadas que se leen sueltas, sin desencriptar el resto de la entrada
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretColumn {
+    TotpSecret,
+}
+
+impl SecretColumn {
+    pub fn name(self) -> &'static str {
+        match self {
+            SecretColumn::TotpSecret => "totp_secret",
+        }
+    }
+}
+
+/// Metadatos de una entrada para auditarla, con el título y el usuario encriptados
+#[derive(Debug, Clone)]
+pub struct AuditRow {
+    pub id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub record: Option<String>,
+    pub url: Option<String>,
+    pub strength: u8,
+    /// Cantidad de otras entradas con la misma huella
+    pub reused_with: i64,
+    pub password_changed_at: String,
+    pub breach_count: Option<i64>,
+    pub guesses_log10: Option<f64>,
+}
+
+/// Escritura de una entrada dentro de un lote
+#[derive(Debug, Clone, Copy)]
+pub enum EntryWrite<'e> {
+    Insert(&'e SealedEntry),
+    /// Como `PasswordRepository::replace` sobre la entrada indicada
+    Replace(EntryId, &'e SealedEntry),
+    /// Como `PasswordRepository::merge_credentials` sobre la entrada indicada
+    MergeCredentials(EntryId, &'e SealedEntry),
+}
+
+pub(super) fn read_encrypted_row(row: &Row) -> Result<EncryptedEntryRow> {
+    Ok(EncryptedEntryRow {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        username: row.get(2)?,
+        password: row.get(3)?,
+        url: row.get(4)?,
+        notes: row.get(5)?,
+        category_id: row.get(6)?,
+        tags: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        last_used: row.get(10)?,
+        record: row.get(11)?,
+    })
+}
+
+/// Dominio registrable de la URL, que se guarda aparte para autocompletar
+fn url_domain(url: Option<&str>) -> Option<String> {
+    url.and_then(registrable_domain_from_url)
+}
+
+fn tags_json(tags: &[String]) -> String {
+    serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string())
+}
 
 pub struct PasswordRepository<'a> {
     connection: &'a Connection,
@@ -9,152 +150,648 @@ impl<'a> PasswordRepository<'a> {
     pub fn new(connection: &'a Connection) -> Self {
         Self { connection }
     }
-    
-    pub fn create_password(&self, entry: &PasswordEntry) -> Result<()> {
-        let tags_json = serde_json::to_string(&entry.tags)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-        
+
+    pub fn count(&self) -> Result<usize> {
+        let total: i64 = self.connection.query_row("SELECT COUNT(*) FROM password_entries", [], |row| row.get(0))?;
+        Ok(total as usize)
+    }
+
+    /// Filas necesarias para la página pedida junto con el total de entradas. Al
+    /// ordenar por título se devuelven todas las filas, ya que el orden solo puede
+    /// calcularse tras desencriptar.
+    pub fn list(&self, request: &PasswordListRequest) -> Result<(Vec<EncryptedEntryRow>, usize)> {
+        let offset = request.offset.unwrap_or(0);
+        let sort_by = request.sort_by.unwrap_or_default();
+        let sort_direction = request.sort_direction.unwrap_or_default();
+        info!("Paginación: offset={}, limit={:?}, orden={:?} {:?}", offset, request.limit, sort_by, sort_direction);
+
+        let total = self.count()?;
+        if sort_by == SortField::Title {
+            return Ok((self.list_all()?, total));
+        }
+
+        let column = match sort_by {
+            SortField::LastUsed => "COALESCE(last_used, '')",
+            _ => "updated_at",
+        };
+        let direction = match sort_direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        // SQLite interpreta LIMIT -1 como "sin límite"
+        let limit = request.limit.map(|l| l as i64).unwrap_or(-1);
+
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT {} FROM password_entries ORDER BY {} {} LIMIT ? OFFSET ?",
+            ENTRY_COLUMNS, column, direction
+        ))?;
+        let rows = stmt.query_map(params![limit, offset as i64], read_encrypted_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok((rows, total))
+    }
+
+    /// Todas las entradas, sin orden
+    pub fn list_all(&self) -> Result<Vec<EncryptedEntryRow>> {
+        let mut stmt = self.connection.prepare(&format!("SELECT {} FROM password_entries", ENTRY_COLUMNS))?;
+        let rows = stmt.query_map([], read_encrypted_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Todas las entradas con el secreto TOTP y la secuencia de auto-type, para las copias
+    pub fn list_all_with_secrets(&self) -> Result<Vec<(EncryptedEntryRow, Option<String>, Option<String>)>> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT {}, totp_secret, autotype_sequence FROM password_entries", ENTRY_COLUMNS
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((read_encrypted_row(row)?, row.get::<_, Option<String>>(12)?, row.get::<_, Option<String>>(13)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn get(&self, id: EntryId) -> Result<Option<EncryptedEntryRow>> {
+        self.connection.query_row(
+            &format!("SELECT {} FROM password_entries WHERE id = ?", ENTRY_COLUMNS),
+            params![id],
+            read_encrypted_row,
+        ).optional()
+    }
+
+    /// La entrada junto con su secuencia de auto-type propia
+    pub fn get_with_autotype(&self, id: EntryId) -> Result<Option<(EncryptedEntryRow, Option<String>)>> {
+        self.connection.query_row(
+            &format!("SELECT {}, autotype_sequence FROM password_entries WHERE id = ?", ENTRY_COLUMNS),
+            params![id],
+            |row| Ok((read_encrypted_row(row)?, row.get::<_, Option<String>>(12)?)),
+        ).optional()
+    }
+
+    pub fn insert(&self, entry: &SealedEntry) -> Result<()> {
+        self.connection.prepare_cached(
+            "INSERT INTO password_entries (id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used,
+                password_strength, password_guesses_log10, password_fingerprint, password_changed_at, password_breach_count,
+                totp_secret, autotype_sequence, domain, record)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )?.execute(params![
+            entry.id,
+            entry.title,
+            entry.username,
+            entry.password,
+            entry.url,
+            entry.notes,
+            entry.category_id,
+            tags_json(&entry.tags),
+            entry.created_at,
+            entry.updated_at,
+            entry.last_used,
+            entry.meta.strength,
+            entry.meta.guesses_log10,
+            entry.meta.fingerprint,
+            entry.password_changed_at,
+            entry.breach_count.map(|count| count as i64),
+            entry.totp_secret,
+            entry.autotype_sequence,
+            url_domain(entry.url.as_deref()),
+            entry.record,
+        ])?;
+        clear_tombstone(self.connection, entry.id)
+    }
+
+    /// Guarda los cambios hechos a una entrada existente. El TOTP, la secuencia de
+    /// auto-type y la fecha de último uso se conservan; si la contraseña no cambió
+    /// también se conservan su fecha de cambio y su recuento de filtraciones. El
+    /// contenido anterior queda como revisión. Devuelve `false` si no existe.
+    pub fn update(&self, entry: &SealedEntry) -> Result<bool> {
+        if !record_revision(self.connection, entry.id, &entry.updated_at)? {
+            return Ok(false);
+        }
+        self.connection.prepare_cached(
+            "UPDATE password_entries SET title = ?1, username = ?2, password = ?3, url = ?4, notes = ?5, category_id = ?6,
+                tags = ?7, updated_at = ?8, password_strength = ?9, password_guesses_log10 = ?10,
+                password_changed_at = CASE WHEN password_fingerprint IS ?11 THEN password_changed_at ELSE ?12 END,
+                password_breach_count = CASE WHEN password_fingerprint IS ?11 THEN password_breach_count ELSE ?13 END,
+                password_fingerprint = ?11, domain = ?15, record = ?16
+             WHERE id = ?14",
+        )?.execute(params![
+            entry.title,
+            entry.username,
+            entry.password,
+            entry.url,
+            entry.notes,
+            entry.category_id,
+            tags_json(&entry.tags),
+            entry.updated_at,
+            entry.meta.strength,
+            entry.meta.guesses_log10,
+            entry.meta.fingerprint,
+            entry.password_changed_at,
+            entry.breach_count.map(|count| count as i64),
+            entry.id,
+            url_domain(entry.url.as_deref()),
+            entry.record,
+        ])?;
+        Ok(true)
+    }
+
+    /// Sobrescribe por completo la entrada `target_id` con `entry`, conservando su id.
+    /// El contenido anterior queda como revisión. Devuelve `false` si no existe.
+    pub fn replace(&self, target_id: EntryId, entry: &SealedEntry) -> Result<bool> {
+        if !record_revision(self.connection, target_id, &entry.updated_at)? {
+            return Ok(false);
+        }
+        let updated = self.connection.prepare_cached(
+            "UPDATE password_entries SET title = ?, username = ?, password = ?, url = ?, notes = ?, category_id = ?, tags = ?,
+                updated_at = ?, last_used = ?, password_strength = ?, password_guesses_log10 = ?, password_fingerprint = ?,
+                password_changed_at = ?, password_breach_count = ?, totp_secret = ?, autotype_sequence = ?, domain = ?,
+                record = ?
+             WHERE id = ?",
+        )?.execute(params![
+            entry.title,
+            entry.username,
+            entry.password,
+            entry.url,
+            entry.notes,
+            entry.category_id,
+            tags_json(&entry.tags),
+            entry.updated_at,
+            entry.last_used,
+            entry.meta.strength,
+            entry.meta.guesses_log10,
+            entry.meta.fingerprint,
+            entry.password_changed_at,
+            entry.breach_count.map(|count| count as i64),
+            entry.totp_secret,
+            entry.autotype_sequence,
+            url_domain(entry.url.as_deref()),
+            entry.record,
+            target_id,
+        ])?;
+        Ok(updated > 0)
+    }
+
+    /// Actualiza las credenciales de `target_id` con las de `entry`. La categoría
+    /// y las etiquetas se conservan; la URL, las notas y el TOTP solo se cambian
+    /// si `entry` los trae. El título se guarda junto con el usuario y la
+    /// contraseña, así que `entry` tiene que traer el de la entrada existente.
+    /// El contenido anterior queda como revisión.
+    pub fn merge_credentials(&self, target_id: EntryId, entry: &SealedEntry) -> Result<bool> {
+        if !record_revision(self.connection, target_id, &entry.updated_at)? {
+            return Ok(false);
+        }
+        let updated = self.connection.prepare_cached(
+            "UPDATE password_entries SET username = ?1, password = ?2, url = COALESCE(?3, url), notes = COALESCE(?4, notes),
+                updated_at = ?5, password_strength = ?6, password_guesses_log10 = ?7, password_fingerprint = ?8,
+                password_changed_at = ?9, totp_secret = COALESCE(?10, totp_secret),
+                domain = CASE WHEN ?3 IS NULL THEN domain ELSE ?12 END, title = ?13, record = ?14
+             WHERE id = ?11",
+        )?.execute(params![
+            entry.username,
+            entry.password,
+            entry.url,
+            entry.notes,
+            entry.updated_at,
+            entry.meta.strength,
+            entry.meta.guesses_log10,
+            entry.meta.fingerprint,
+            entry.password_changed_at,
+            entry.totp_secret,
+            target_id,
+            url_domain(entry.url.as_deref()),
+            entry.title,
+            entry.record,
+        ])?;
+        Ok(updated > 0)
+    }
+
+    /// Aplica un lote de escrituras en una sola transacción, reutilizando las
+    /// sentencias preparadas. Si ya hay una transacción abierta el lote queda
+    /// dentro de ella. Devuelve el resultado de cada escritura en orden: una que
+    /// falla no deshace las demás; el error general solo aparece si no se pudo
+    /// abrir o confirmar la transacción.
+    pub fn write_batch(&self, writes: &[EntryWrite<'_>]) -> Result<Vec<Result<bool>>> {
+        self.connection.execute_batch("SAVEPOINT entry_batch")?;
+        let results: Vec<_> = writes.iter()
+            .map(|write| match *write {
+                EntryWrite::Insert(entry) => self.insert(entry).map(|_| true),
+                EntryWrite::Replace(target_id, entry) => self.replace(target_id, entry),
+                EntryWrite::MergeCredentials(target_id, entry) => self.merge_credentials(target_id, entry),
+            })
+            .collect();
+        if let Err(e) = self.connection.execute_batch("RELEASE entry_batch") {
+            let _ = self.connection.execute_batch("ROLLBACK TO entry_batch; RELEASE entry_batch");
+            return Err(e);
+        }
+        info!("Lote de {} escrituras aplicado ({} fallidas)", writes.len(), results.iter().filter(|result| result.is_err()).count());
+        Ok(results)
+    }
+
+    /// Borra la entrada y sus adjuntos y deja una marca de eliminación para la
+    /// sincronización. Devuelve `false` si no existe.
+    pub fn delete(&self, id: EntryId, deleted_at: &str) -> Result<bool> {
+        self.connection.execute("DELETE FROM attachments WHERE entry_id = ?", params![id])?;
+        self.connection.execute("DELETE FROM entry_field_stamps WHERE entry_id = ?", params![id])?;
+        let deleted = self.connection.execute("DELETE FROM password_entries WHERE id = ?", params![id])?;
+        if deleted > 0 {
+            record_tombstone(self.connection, id, deleted_at)?;
+        }
+        Ok(deleted > 0)
+    }
+
+    /// Borra una entrada sin dejar marca de eliminación: no se eliminó, solo
+    /// dejó de estar aquí
+    pub fn discard(&self, id: EntryId) -> Result<bool> {
+        self.connection.execute("DELETE FROM attachments WHERE entry_id = ?", params![id])?;
+        self.connection.execute("DELETE FROM entry_field_stamps WHERE entry_id = ?", params![id])?;
+        let deleted = self.connection.execute("DELETE FROM password_entries WHERE id = ?", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Borra todas las entradas y sus adjuntos, dejando una marca de eliminación
+    /// por cada una
+    pub fn delete_all(&self, deleted_at: &str) -> Result<()> {
         self.connection.execute(
-            "INSERT INTO password_entries (id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            params![
-                entry.id,
-                entry.title,
-                entry.username,
-                entry.password,
-                entry.url,
-                entry.notes,
-                entry.category_id,
-                tags_json,
-                entry.created_at,
-                entry.updated_at,
-                entry.last_used
-            ],
+            "INSERT OR REPLACE INTO tombstones (entry_id, deleted_at) SELECT id, ? FROM password_entries",
+            [deleted_at],
         )?;
-        
+        self.connection.execute("DELETE FROM attachments", [])?;
+        self.connection.execute("DELETE FROM entry_field_stamps", [])?;
+        self.connection.execute("DELETE FROM password_entries", [])?;
         Ok(())
     }
-    
-    pub fn get_all_passwords(&self) -> Result<Vec<PasswordEntry>> {
+
+    /// Valor encriptado de una columna; `None` si la entrada no existe, `Some(None)`
+    /// si la columna está vacía
+    pub fn secret(&self, id: EntryId, column: SecretColumn) -> Result<Option<Option<String>>> {
+        self.connection.query_row(
+            &format!("SELECT {} FROM password_entries WHERE id = ?", column.name()),
+            params![id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .map(|value| value.map(|value| value.filter(|v| !v.is_empty())))
+    }
+
+    /// Mueve las entradas de la categoría `from` a `to`, o las deja sin categoría.
+    /// Devuelve cuántas se movieron.
+    pub fn reassign_category(&self, from: CategoryId, to: Option<CategoryId>, now: &str) -> Result<usize> {
+        self.connection.execute(
+            "UPDATE password_entries SET category_id = ?, updated_at = ? WHERE category_id = ?",
+            params![to, now, from],
+        )
+    }
+
+    /// Ids de las entradas que están directamente en `category_id`
+    pub fn ids_in_category(&self, category_id: CategoryId) -> Result<Vec<EntryId>> {
+        let mut stmt = self.connection.prepare("SELECT id FROM password_entries WHERE category_id = ?")?;
+        let ids = stmt.query_map([category_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// Mueve las entradas indicadas a `category_id`, o las deja sin categoría.
+    /// Devuelve cuántas existían.
+    pub fn move_to_category(&self, ids: &[EntryId], category_id: Option<CategoryId>, now: &str) -> Result<usize> {
         let mut stmt = self.connection.prepare(
-            "SELECT id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used
-             FROM password_entries ORDER BY updated_at DESC"
+            "UPDATE password_entries SET category_id = ?, updated_at = ? WHERE id = ?",
         )?;
-        
-        let entries = stmt.query_map([], |row| {
-            let tags_json: String = row.get(7)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json)
-                .unwrap_or_default();
-            
-            Ok(PasswordEntry {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                username: row.get(2)?,
-                password: row.get(3)?,
-                url: row.get(4)?,
-                notes: row.get(5)?,
-                category_id: row.get(6)?,
-                tags,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-                last_used: row.get(10)?,
-            })
-        })?;
-        
-        entries.collect()
+        let mut moved = 0;
+        for id in ids {
+            moved += stmt.execute(params![category_id, now, id])?;
+        }
+        Ok(moved)
     }
-    
-    pub fn get_password_by_id(&self, id: &str) -> Result<Option<PasswordEntry>> {
+
+    /// Secretos TOTP encriptados de las entradas que tienen uno
+    pub fn totp_secrets(&self) -> Result<Vec<(EntryId, String)>> {
+        let mut stmt = self.connection.prepare("SELECT id, totp_secret FROM password_entries WHERE totp_secret IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Guarda el secreto TOTP ya encriptado (`None` lo quita). Devuelve `false` si
+    /// la entrada no existe.
+    pub fn set_totp_secret(&self, id: EntryId, encrypted: Option<&str>) -> Result<bool> {
+        let updated = self.connection.execute(
+            "UPDATE password_entries SET totp_secret = ? WHERE id = ?",
+            params![encrypted, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// URL de la entrada; `None` si la entrada no existe
+    pub fn url(&self, id: EntryId) -> Result<Option<Option<String>>> {
+        self.connection.query_row(
+            "SELECT url FROM password_entries WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        ).optional()
+    }
+
+    /// Secuencia de auto-type propia de la entrada; `None` si la entrada no existe
+    pub fn autotype_sequence(&self, id: EntryId) -> Result<Option<Option<String>>> {
+        self.connection.query_row(
+            "SELECT autotype_sequence FROM password_entries WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        ).optional()
+    }
+
+    pub fn set_autotype_sequence(&self, id: EntryId, sequence: Option<&str>) -> Result<bool> {
+        let updated = self.connection.execute(
+            "UPDATE password_entries SET autotype_sequence = ? WHERE id = ?",
+            params![sequence, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Entradas creadas antes de existir los metadatos: id, contraseña encriptada y
+    /// registro, si la entrada se guardó así
+    pub fn missing_metadata(&self) -> Result<Vec<(EntryId, String, Option<String>)>> {
         let mut stmt = self.connection.prepare(
-            "SELECT id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used
-             FROM password_entries WHERE id = ?"
+            "SELECT id, password, record FROM password_entries
+             WHERE password_strength IS NULL OR password_guesses_log10 IS NULL OR password_fingerprint IS NULL",
         )?;
-        
-        let mut entries = stmt.query_map(params![id], |row| {
-            let tags_json: String = row.get(7)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json)
-                .unwrap_or_default();
-            
-            Ok(PasswordEntry {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                username: row.get(2)?,
-                password: row.get(3)?,
-                url: row.get(4)?,
-                notes: row.get(5)?,
-                category_id: row.get(6)?,
-                tags,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-                last_used: row.get(10)?,
-            })
-        })?;
-        
-        Ok(entries.next().transpose()?)
-    }
-    
-    pub fn update_password(&self, entry: &PasswordEntry) -> Result<()> {
-        let tags_json = serde_json::to_string(&entry.tags)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
-        
-        self.connection.execute(
-            "UPDATE password_entries 
-             SET title = ?, username = ?, password = ?, url = ?, notes = ?, category_id = ?, tags = ?, updated_at = ?
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn set_metadata(&self, id: EntryId, meta: &PasswordMetadata) -> Result<()> {
+        self.connection.prepare_cached(
+            "UPDATE password_entries SET password_strength = ?, password_guesses_log10 = ?, password_fingerprint = ?,
+                password_changed_at = COALESCE(password_changed_at, updated_at)
              WHERE id = ?",
-            params![
-                entry.title,
-                entry.username,
-                entry.password,
-                entry.url,
-                entry.notes,
-                entry.category_id,
-                tags_json,
-                entry.updated_at,
-                entry.id
-            ],
-        )?;
-        
+        )?.execute(params![meta.strength, meta.guesses_log10, meta.fingerprint, id])?;
         Ok(())
     }
-    
-    pub fn delete_password(&self, id: &str) -> Result<()> {
-        self.connection.execute(
-            "DELETE FROM password_entries WHERE id = ?",
-            params![id],
-        )?;
-        
+
+    /// Id, contraseña encriptada, registro y huella de cada entrada
+    pub fn passwords(&self) -> Result<Vec<(EntryId, String, Option<String>, Option<String>)>> {
+        let mut stmt = self.connection.prepare("SELECT id, password, record, password_fingerprint FROM password_entries")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Reemplaza el título, el usuario y la contraseña encriptados sin tocar el
+    /// resto de la entrada ni guardar revisión: el contenido es el mismo, solo
+    /// cambia cómo está encriptado. Devuelve `false` si la entrada no existe.
+    pub fn reseal_credentials(&self, id: EntryId, credentials: &SealedCredentials) -> Result<bool> {
+        let updated = self.connection.prepare_cached(
+            "UPDATE password_entries SET title = ?, username = ?, password = ?, record = ? WHERE id = ?",
+        )?.execute(params![credentials.title, credentials.username, credentials.password, credentials.record, id])?;
+        Ok(updated > 0)
+    }
+
+    pub fn set_breach_count(&self, id: EntryId, count: u64) -> Result<()> {
+        self.connection.prepare_cached(
+            "UPDATE password_entries SET password_breach_count = ? WHERE id = ?",
+        )?.execute(params![count as i64, id])?;
         Ok(())
     }
-    
-    pub fn search_passwords(&self, query: &str) -> Result<Vec<PasswordEntry>> {
-        let search_query = format!("%{}%", query);
+
+    pub fn audit_rows(&self) -> Result<Vec<AuditRow>> {
         let mut stmt = self.connection.prepare(
-            "SELECT id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used
-             FROM password_entries 
-             WHERE title LIKE ? OR username LIKE ? OR url LIKE ? OR notes LIKE ?
-             ORDER BY updated_at DESC"
+            "SELECT id, title, username, url, password_strength,
+                    COUNT(*) OVER (PARTITION BY password_fingerprint) - 1,
+                    COALESCE(password_changed_at, updated_at),
+                    password_breach_count, password_guesses_log10, record
+             FROM password_entries",
         )?;
-        
-        let entries = stmt.query_map(params![search_query, search_query, search_query, search_query], |row| {
-            let tags_json: String = row.get(7)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json)
-                .unwrap_or_default();
-            
-            Ok(PasswordEntry {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                username: row.get(2)?,
-                password: row.get(3)?,
-                url: row.get(4)?,
-                notes: row.get(5)?,
-                category_id: row.get(6)?,
-                tags,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-                last_used: row.get(10)?,
-            })
-        })?;
-        
-        entries.collect()
+        let rows = stmt.query_map([], |row| Ok(AuditRow {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            username: row.get(2)?,
+            url: row.get(3)?,
+            strength: row.get::<_, Option<u8>>(4)?.unwrap_or(0),
+            reused_with: row.get(5)?,
+            password_changed_at: row.get(6)?,
+            breach_count: row.get(7)?,
+            guesses_log10: row.get(8)?,
+            record: row.get(9)?,
+        }))?
+        .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
     }
-} 
\ No newline at end of file
+
+    /// Entradas por categoría, de la más usada a la menos
+    pub fn category_counts(&self) -> Result<Vec<CategoryCount>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT NULLIF(e.category_id, ''), c.name, COUNT(*) FROM password_entries e
+             LEFT JOIN categories c ON c.id = e.category_id
+             GROUP BY NULLIF(e.category_id, '') ORDER BY COUNT(*) DESC",
+        )?;
+        let counts = stmt.query_map([], |row| Ok(CategoryCount {
+            category_id: row.get(0)?,
+            name: row.get(1)?,
+            count: row.get::<_, i64>(2)? as usize,
+        }))?
+        .collect::<Result<Vec<_>>>()?;
+        Ok(counts)
+    }
+
+    /// Entradas cuyo dominio registrable es `domain`, para autocompletar
+    pub fn credentials_for_domain(&self, domain: &str) -> Result<Vec<EncryptedEntryRow>> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT {} FROM password_entries WHERE domain = ?",
+            ENTRY_COLUMNS,
+        ))?;
+        let rows = stmt.query_map([domain], read_encrypted_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Recalcula el dominio de una entrada a partir de su URL guardada
+    pub fn refresh_domain(&self, id: EntryId) -> Result<()> {
+        let Some(url) = self.url(id)? else {
+            return Ok(());
+        };
+        self.connection.execute(
+            "UPDATE password_entries SET domain = ? WHERE id = ?",
+            params![url_domain(url.as_deref()), id],
+        )?;
+        Ok(())
+    }
+
+    /// Calcula el dominio de las entradas con URL que todavía no lo tienen
+    /// (guardadas antes de existir la columna). Devuelve cuántas se completaron.
+    pub fn backfill_domains(&self) -> Result<usize> {
+        let pending: Vec<(EntryId, String)> = self.connection
+            .prepare("SELECT id, url FROM password_entries WHERE domain IS NULL AND url IS NOT NULL AND url != ''")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_>>()?;
+        let mut filled = 0;
+        let mut stmt = self.connection.prepare_cached("UPDATE password_entries SET domain = ? WHERE id = ?")?;
+        for (id, url) in pending {
+            if let Some(domain) = registrable_domain_from_url(&url) {
+                filled += stmt.execute(params![domain, id])?;
+            }
+        }
+        if filled > 0 {
+            info!("Dominio calculado para {} entradas", filled);
+        }
+        Ok(filled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sealed(id: EntryId) -> SealedEntry {
+        SealedEntry {
+            id,
+            title: "titulo".to_string(),
+            username: "usuario".to_string(),
+            password: "clave".to_string(),
+            url: Some("https://example.com".to_string()),
+            notes: None,
+            category_id: None,
+            tags: vec!["trabajo".to_string()],
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+            last_used: None,
+            meta: PasswordMetadata { strength: 80, guesses_log10: 12.0, fingerprint: "huella".to_string() },
+            password_changed_at: "2024-01-01T00:00:00Z".to_string(),
+            breach_count: None,
+            totp_secret: None,
+            autotype_sequence: None,
+            record: None,
+        }
+    }
+
+    #[test]
+    fn test_stores_and_updates_entries() {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::run_migrations(&connection).unwrap();
+        let repository = PasswordRepository::new(&connection);
+        let [a, b, c] = [EntryId::new(), EntryId::new(), EntryId::new()];
+
+        repository.insert(&sealed(a)).unwrap();
+        repository.insert(&sealed(b)).unwrap();
+        assert_eq!(repository.count().unwrap(), 2);
+        assert_eq!(repository.get(a).unwrap().unwrap().tags.as_deref(), Some("[\"trabajo\"]"));
+        assert!(repository.get(c).unwrap().is_none());
+
+        let mut update = sealed(a);
+        update.url = None;
+        update.password = "nueva".to_string();
+        assert!(repository.merge_credentials(a, &update).unwrap());
+        let row = repository.get(a).unwrap().unwrap();
+        assert_eq!(row.password, "nueva");
+        assert_eq!(row.url.as_deref(), Some("https://example.com"));
+
+        let mut edit = sealed(a);
+        edit.title = "editado".to_string();
+        edit.password = "nueva".to_string();
+        edit.updated_at = "2024-01-05T00:00:00Z".to_string();
+        edit.password_changed_at = edit.updated_at.clone();
+        edit.breach_count = Some(3);
+        repository.set_breach_count(a, 0).unwrap();
+        assert!(repository.update(&edit).unwrap());
+        assert!(!repository.update(&sealed(EntryId::new())).unwrap());
+        let changed_at: (String, Option<i64>) = connection.query_row(
+            "SELECT password_changed_at, password_breach_count FROM password_entries WHERE id = ?",
+            [a],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(changed_at, ("2024-01-01T00:00:00Z".to_string(), Some(0)));
+        assert_eq!(crate::database::list_revisions(&connection, a).unwrap().len(), 2);
+
+        assert!(repository.set_totp_secret(b, Some("secreto")).unwrap());
+        assert_eq!(repository.secret(b, SecretColumn::TotpSecret).unwrap(), Some(Some("secreto".to_string())));
+        assert_eq!(repository.secret(a, SecretColumn::TotpSecret).unwrap(), Some(None));
+        assert_eq!(repository.audit_rows().unwrap()[0].reused_with, 1);
+
+        let record = SealedCredentials {
+            title: String::new(),
+            username: String::new(),
+            password: String::new(),
+            record: Some("registro".to_string()),
+        };
+        assert!(repository.reseal_credentials(b, &record).unwrap());
+        let row = repository.get(b).unwrap().unwrap();
+        assert_eq!((row.password.as_str(), row.record.as_deref(), row.updated_at.as_str()), ("", Some("registro"), "2024-01-01T00:00:00Z"));
+
+        let work = CategoryId::new();
+        crate::database::insert_category(&connection, &crate::models::Category {
+            id: work,
+            name: "Trabajo".to_string(),
+            color: "#6B7280".to_string(),
+            icon: None,
+            parent_id: None,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        }).unwrap();
+        let mut categorized = sealed(c);
+        categorized.category_id = Some(work);
+        repository.insert(&categorized).unwrap();
+        assert_eq!(repository.reassign_category(work, None, "2024-02-01T00:00:00Z").unwrap(), 1);
+        let row = repository.get(c).unwrap().unwrap();
+        assert_eq!((row.category_id, row.updated_at.as_str()), (None, "2024-02-01T00:00:00Z"));
+        assert_eq!(repository.move_to_category(&[c, EntryId::new()], Some(work), "2024-03-01T00:00:00Z").unwrap(), 1);
+        assert_eq!(repository.get(c).unwrap().unwrap().category_id, Some(work));
+        assert!(repository.delete(c, "2024-03-02T00:00:00Z").unwrap());
+
+        assert!(repository.delete(a, "2024-04-01T00:00:00Z").unwrap());
+        assert!(!repository.delete(a, "2024-04-01T00:00:00Z").unwrap());
+        assert_eq!(crate::database::list_tombstones(&connection, None).unwrap().len(), 2);
+        repository.insert(&sealed(a)).unwrap();
+        assert_eq!(crate::database::list_tombstones(&connection, None).unwrap().len(), 1);
+        repository.delete(a, "2024-04-02T00:00:00Z").unwrap();
+        assert_eq!(repository.list(&PasswordListRequest::default()).unwrap().1, 1);
+    }
+
+    #[test]
+    fn test_writes_batches_in_one_transaction() {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::run_migrations(&connection).unwrap();
+        let repository = PasswordRepository::new(&connection);
+        let a = EntryId::new();
+        repository.insert(&sealed(a)).unwrap();
+
+        let entries: Vec<_> = (0..300).map(|_| sealed(EntryId::new())).collect();
+        let mut update = sealed(a);
+        update.password = "nueva".to_string();
+        let mut writes: Vec<_> = entries.iter().map(EntryWrite::Insert).collect();
+        writes.push(EntryWrite::Replace(a, &update));
+        writes.push(EntryWrite::MergeCredentials(EntryId::new(), &update));
+        // Un id repetido falla solo, sin deshacer el resto del lote
+        writes.push(EntryWrite::Insert(&entries[0]));
+
+        let results = repository.write_batch(&writes).unwrap();
+        assert_eq!(results.len(), 303);
+        assert!(results[..301].iter().all(|result| matches!(result, Ok(true))));
+        assert!(matches!(results[301], Ok(false)));
+        assert!(results[302].is_err());
+        assert!(connection.is_autocommit());
+        assert_eq!(repository.count().unwrap(), 301);
+        assert_eq!(repository.get(a).unwrap().unwrap().password, "nueva");
+    }
+
+    #[test]
+    fn test_finds_credentials_by_registrable_domain() {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::run_migrations(&connection).unwrap();
+        let repository = PasswordRepository::new(&connection);
+        let a = EntryId::new();
+        let mut login = sealed(a);
+        login.url = Some("https://login.example.co.uk/entrar".to_string());
+        repository.insert(&login).unwrap();
+        let mut other = sealed(EntryId::new());
+        other.url = Some("https://example.com".to_string());
+        repository.insert(&other).unwrap();
+        connection.execute("UPDATE password_entries SET domain = NULL", []).unwrap();
+
+        assert_eq!(repository.backfill_domains().unwrap(), 2);
+        let found = repository.credentials_for_domain("example.co.uk").unwrap();
+        assert_eq!(found.iter().map(|row| row.id).collect::<Vec<_>>(), [a]);
+
+        let mut moved = sealed(a);
+        moved.url = Some("https://www.example.com/".to_string());
+        repository.merge_credentials(a, &moved).unwrap();
+        assert_eq!(repository.credentials_for_domain("example.com").unwrap().len(), 2);
+        assert!(repository.credentials_for_domain("example.co.uk").unwrap().is_empty());
+    }
+}
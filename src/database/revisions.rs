@@ -0,0 +1,161 @@
+//! Historial de revisiones de las entradas
+//!
+//! Antes de modificar una entrada se copia la fila tal como está, con los
+//! campos sensibles todavía encriptados, así que guardar una revisión no
+//! necesita la clave maestra. Restaurar una revisión copia la fila de vuelta.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use anyhow::Result;
+use log::info;
+use crate::models::EntryId;
+use super::{EncryptedEntryRow, PasswordRepository};
+use super::repository::read_encrypted_row;
+
+/// Columnas de password_entries que se copian en cada revisión, salvo el id
+const REVISION_COLUMNS: &str = "title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used,
+    autotype_sequence, totp_secret, password_strength, password_guesses_log10, password_fingerprint, password_changed_at,
+    password_breach_count, record";
+
+/// Revisión guardada, con los campos sensibles todavía encriptados
+#[derive(Debug, Clone)]
+pub struct EntryRevisionRow {
+    pub id: i64,
+    /// Momento en que la entrada dejó de tener este contenido
+    pub revised_at: String,
+    pub entry: EncryptedEntryRow,
+}
+
+/// Guarda el contenido actual de `entry_id` como revisión. Devuelve `false` si
+/// la entrada no existe.
+pub fn record_revision(connection: &Connection, entry_id: EntryId, revised_at: &str) -> rusqlite::Result<bool> {
+    let recorded = connection.prepare_cached(&format!(
+        "INSERT INTO entry_revisions (entry_id, revised_at, {columns})
+         SELECT id, ?, {columns} FROM password_entries WHERE id = ?",
+        columns = REVISION_COLUMNS,
+    ))?.execute(params![revised_at, entry_id])?;
+    Ok(recorded > 0)
+}
+
+/// Revisiones de una entrada, de la más reciente a la más antigua
+pub fn list_revisions(connection: &Connection, entry_id: EntryId) -> Result<Vec<EntryRevisionRow>> {
+    let mut stmt = connection.prepare(
+        "SELECT entry_id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used,
+                record, id, revised_at
+         FROM entry_revisions WHERE entry_id = ? ORDER BY id DESC",
+    )?;
+    let revisions = stmt.query_map([entry_id], |row| {
+        Ok(EntryRevisionRow {
+            entry: read_encrypted_row(row)?,
+            id: row.get(12)?,
+            revised_at: row.get(13)?,
+        })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(revisions)
+}
+
+/// Devuelve `entry_id` al contenido de la revisión `revision_id`. El contenido
+/// que se reemplaza queda a su vez como revisión, así que restaurar también se
+/// puede deshacer. Si la categoría de la revisión ya no existe, la entrada queda
+/// sin categoría. Devuelve `false` si la revisión no es de esa entrada.
+pub fn restore_revision(connection: &Connection, entry_id: EntryId, revision_id: i64, now: &str) -> Result<bool> {
+    let exists = connection.query_row(
+        "SELECT 1 FROM entry_revisions WHERE id = ? AND entry_id = ?",
+        params![revision_id, entry_id],
+        |_| Ok(()),
+    ).optional()?;
+    if exists.is_none() || !record_revision(connection, entry_id, now)? {
+        return Ok(false);
+    }
+
+    connection.execute(
+        "UPDATE password_entries SET
+            (title, username, password, url, notes, category_id, tags, last_used, autotype_sequence, totp_secret,
+             password_strength, password_guesses_log10, password_fingerprint, password_changed_at, password_breach_count,
+             record) =
+            (SELECT title, username, password, url, notes,
+                    (SELECT c.id FROM categories c WHERE c.id = r.category_id),
+                    tags, last_used, autotype_sequence, totp_secret,
+                    password_strength, password_guesses_log10, password_fingerprint, password_changed_at, password_breach_count,
+                    record
+             FROM entry_revisions r WHERE r.id = ?1),
+            updated_at = ?3
+         WHERE id = ?2",
+        params![revision_id, entry_id, now],
+    )?;
+    // Las revisiones no guardan el dominio: se recalcula de la URL restaurada
+    PasswordRepository::new(connection).refresh_domain(entry_id)?;
+    info!("Entrada {} restaurada a la revisión {}", entry_id, revision_id);
+    Ok(true)
+}
+
+/// Conserva como mucho `keep_per_entry` revisiones por entrada y descarta las
+/// anteriores a `cutoff` (fecha RFC 3339). Devuelve cuántas se borraron.
+pub fn prune_revisions(connection: &Connection, keep_per_entry: u32, cutoff: Option<&str>) -> Result<usize> {
+    let mut pruned = connection.execute(
+        "DELETE FROM entry_revisions WHERE (
+            SELECT COUNT(*) FROM entry_revisions newer
+            WHERE newer.entry_id = entry_revisions.entry_id AND newer.id > entry_revisions.id
+         ) >= ?",
+        [keep_per_entry],
+    )?;
+    if let Some(cutoff) = cutoff {
+        pruned += connection.execute("DELETE FROM entry_revisions WHERE revised_at < ?", [cutoff])?;
+    }
+    if pruned > 0 {
+        info!("Historial de revisiones: {} revisiones antiguas descartadas", pruned);
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    fn set_password(connection: &Connection, id: EntryId, password: &str, now: &str) {
+        record_revision(connection, id, now).unwrap();
+        connection.execute(
+            "UPDATE password_entries SET password = ?, updated_at = ? WHERE id = ?",
+            params![password, now, id],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_records_restores_and_prunes_revisions() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let a = EntryId::new();
+        connection.execute(
+            "INSERT INTO password_entries (id, title, username, password, created_at, updated_at, totp_secret)
+             VALUES (?, 'titulo', 'usuario', 'primera', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z', 'totp')",
+            [a],
+        ).unwrap();
+        assert!(!record_revision(&connection, EntryId::new(), "2024-01-02T00:00:00Z").unwrap());
+        set_password(&connection, a, "segunda", "2024-02-01T00:00:00Z");
+        set_password(&connection, a, "tercera", "2024-03-01T00:00:00Z");
+
+        let revisions = list_revisions(&connection, a).unwrap();
+        let passwords: Vec<_> = revisions.iter().map(|revision| revision.entry.password.as_str()).collect();
+        assert_eq!(passwords, ["segunda", "primera"]);
+        assert_eq!(revisions[1].revised_at, "2024-02-01T00:00:00Z");
+
+        let first = revisions[1].id;
+        assert!(!restore_revision(&connection, EntryId::new(), first, "2024-04-01T00:00:00Z").unwrap());
+        assert!(restore_revision(&connection, a, first, "2024-04-01T00:00:00Z").unwrap());
+        let (password, updated_at, totp): (String, String, Option<String>) = connection.query_row(
+            "SELECT password, updated_at, totp_secret FROM password_entries WHERE id = ?",
+            [a],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!((password.as_str(), updated_at.as_str(), totp.as_deref()), ("primera", "2024-04-01T00:00:00Z", Some("totp")));
+        assert_eq!(list_revisions(&connection, a).unwrap()[0].entry.password, "tercera");
+
+        assert_eq!(prune_revisions(&connection, 2, Some("2024-03-15T00:00:00Z")).unwrap(), 2);
+        let remaining: Vec<_> = list_revisions(&connection, a).unwrap().into_iter().map(|revision| revision.entry.password).collect();
+        assert_eq!(remaining, ["tercera"]);
+
+        connection.execute("DELETE FROM password_entries WHERE id = ?", [a]).unwrap();
+        assert!(list_revisions(&connection, a).unwrap().is_empty());
+    }
+}
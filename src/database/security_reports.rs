@@ -0,0 +1,23 @@
+use rusqlite::{Connection, OptionalExtension};
+use anyhow::Result;
+use log::info;
+
+/// Guarda el último informe de seguridad (encriptado) y descarta los anteriores
+pub fn save_security_report(connection: &Connection, encrypted_report: &str, generated_at: &str) -> Result<()> {
+    connection.execute("DELETE FROM security_reports", [])?;
+    connection.execute(
+        "INSERT INTO security_reports (generated_at, report) VALUES (?, ?)",
+        rusqlite::params![generated_at, encrypted_report],
+    )?;
+    info!("Informe de seguridad guardado");
+    Ok(())
+}
+
+/// Devuelve el último informe de seguridad guardado (encriptado), si existe
+pub fn load_security_report(connection: &Connection) -> Result<Option<String>> {
+    Ok(connection.query_row(
+        "SELECT report FROM security_reports ORDER BY generated_at DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    ).optional()?)
+}
@@ -0,0 +1,89 @@
+use rusqlite::{Connection, OptionalExtension};
+use anyhow::Result;
+use log::{info, warn};
+use crate::models::{AppSettings, DeviceId};
+
+/// Clave de la fila de `settings` que guarda la configuración de la aplicación
+const APP_SETTINGS_KEY: &str = "app";
+/// Clave de la fila de `settings` con el id de este dispositivo
+const DEVICE_ID_KEY: &str = "device_id";
+
+/// Carga la configuración guardada o la configuración por defecto si no hay ninguna
+pub fn load_settings(connection: &Connection) -> Result<AppSettings> {
+    let value: Option<String> = connection.query_row(
+        "SELECT value FROM settings WHERE key = ?",
+        [APP_SETTINGS_KEY],
+        |row| row.get(0),
+    ).optional()?;
+    
+    match value {
+        Some(json) => match serde_json::from_str(&json) {
+            Ok(settings) => {
+                info!("Configuración cargada desde la base de datos");
+                Ok(settings)
+            }
+            Err(e) => {
+                warn!("Configuración guardada inválida, usando valores por defecto: {}", e);
+                Ok(AppSettings::default())
+            }
+        },
+        None => {
+            info!("No hay configuración guardada, usando valores por defecto");
+            Ok(AppSettings::default())
+        }
+    }
+}
+
+/// Guarda la configuración reemplazando la anterior
+pub fn save_settings(connection: &Connection, settings: &AppSettings) -> Result<()> {
+    let json = serde_json::to_string(settings)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    
+    connection.execute(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        rusqlite::params![APP_SETTINGS_KEY, json, now],
+    )?;
+    
+    info!("Configuración guardada en la base de datos");
+    Ok(())
+}
+
+/// Lee un valor suelto de `settings` (por ejemplo, un secreto encriptado)
+pub fn load_setting_value(connection: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(connection.query_row(
+        "SELECT value FROM settings WHERE key = ?",
+        [key],
+        |row| row.get(0),
+    ).optional()?)
+}
+
+/// Guarda un valor suelto en `settings`; `None` lo elimina
+pub fn save_setting_value(connection: &Connection, key: &str, value: Option<&str>) -> Result<()> {
+    match value {
+        Some(value) => {
+            let now = chrono::Utc::now().to_rfc3339();
+            connection.execute(
+                "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                rusqlite::params![key, value, now],
+            )?;
+        }
+        None => {
+            connection.execute("DELETE FROM settings WHERE key = ?", [key])?;
+        }
+    }
+    Ok(())
+}
+
+/// Id con el que este dispositivo se presenta a los demás. Se crea la primera
+/// vez que hace falta y después no cambia.
+pub fn local_device_id(connection: &Connection) -> Result<DeviceId> {
+    if let Some(device_id) = load_setting_value(connection, DEVICE_ID_KEY)? {
+        return Ok(device_id.parse()?);
+    }
+    let device_id = DeviceId::new();
+    save_setting_value(connection, DEVICE_ID_KEY, Some(&device_id.to_string()))?;
+    info!("Id de este dispositivo creado: {}", device_id);
+    Ok(device_id)
+}
@@ -0,0 +1,98 @@
+//! Entradas compartidas por enlace
+//!
+//! Se anota cada entrada compartida desde este dispositivo con el secreto que
+//! la revoca en el relay. El relay la borra solo al caducar o agotar sus
+//! vistas; aquí se olvidan las caducadas al compartir otra.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use anyhow::Result;
+use crate::models::{ShareId, SharedLink};
+
+/// Anota una entrada compartida con `owner`, el secreto que la revoca ya
+/// encriptado, y olvida las que caducaron antes de `link.created_at`
+pub fn add_shared_link(connection: &Connection, link: &SharedLink, owner: &str) -> Result<()> {
+    connection.execute("DELETE FROM shared_links WHERE expires_at <= ?", [&link.created_at])?;
+    connection.execute(
+        "INSERT INTO shared_links (id, entry_id, url, owner, max_views, created_at, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![link.id, link.entry_id, link.url, owner, link.max_views, link.created_at, link.expires_at],
+    )?;
+    Ok(())
+}
+
+/// Entradas compartidas que no caducaron a la hora `now`, la más reciente
+/// primero
+pub fn get_shared_links(connection: &Connection, now: &str) -> Result<Vec<SharedLink>> {
+    let mut stmt = connection.prepare(
+        "SELECT id, entry_id, url, max_views, created_at, expires_at FROM shared_links
+         WHERE expires_at > ? ORDER BY created_at DESC",
+    )?;
+    let links = stmt.query_map([now], |row| {
+        Ok(SharedLink {
+            id: row.get(0)?,
+            entry_id: row.get(1)?,
+            url: row.get(2)?,
+            max_views: row.get(3)?,
+            created_at: row.get(4)?,
+            expires_at: row.get(5)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(links)
+}
+
+/// Dirección y secreto encriptado de una entrada compartida, para revocarla
+pub fn shared_link_owner(connection: &Connection, id: ShareId) -> Result<Option<(String, String)>> {
+    let owner = connection.query_row(
+        "SELECT url, owner FROM shared_links WHERE id = ?",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+    Ok(owner)
+}
+
+/// Olvida una entrada compartida. Devuelve si estaba.
+pub fn remove_shared_link(connection: &Connection, id: ShareId) -> Result<bool> {
+    Ok(connection.execute("DELETE FROM shared_links WHERE id = ?", [id])? > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+    use crate::models::EntryId;
+
+    fn link(created_at: &str, expires_at: &str) -> SharedLink {
+        SharedLink {
+            id: ShareId::new(),
+            entry_id: EntryId::new(),
+            url: "https://relay.example.com/v1/share/x".to_string(),
+            max_views: 1,
+            created_at: created_at.to_string(),
+            expires_at: expires_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_forgets_expired_and_revoked_links() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+
+        let old = link("2024-01-01T00:00:00+00:00", "2024-01-02T00:00:00+00:00");
+        add_shared_link(&connection, &old, "secreto-viejo").unwrap();
+        let current = link("2024-01-03T00:00:00+00:00", "2024-01-04T00:00:00+00:00");
+        add_shared_link(&connection, &current, "secreto").unwrap();
+        // La caducada se olvidó al compartir la nueva
+        assert!(shared_link_owner(&connection, old.id).unwrap().is_none());
+        assert_eq!(
+            shared_link_owner(&connection, current.id).unwrap(),
+            Some((current.url.clone(), "secreto".to_string())),
+        );
+
+        assert_eq!(get_shared_links(&connection, "2024-01-03T12:00:00+00:00").unwrap(), vec![current.clone()]);
+        assert!(get_shared_links(&connection, "2024-01-04T00:00:00+00:00").unwrap().is_empty());
+
+        assert!(remove_shared_link(&connection, current.id).unwrap());
+        assert!(!remove_shared_link(&connection, current.id).unwrap());
+        assert!(get_shared_links(&connection, "2024-01-03T12:00:00+00:00").unwrap().is_empty());
+    }
+}
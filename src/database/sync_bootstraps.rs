@@ -0,0 +1,82 @@
+//! Copias iniciales de la bóveda a medio recibir
+//!
+//! Un dispositivo recién emparejado recibe la bóveda entera del otro por
+//! páginas antes de pasar a la sincronización incremental. Después de cada
+//! página se anota hasta dónde llegó, para seguir desde ahí si se corta.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use anyhow::Result;
+use crate::models::{DeviceId, SyncBootstrap};
+
+/// Copia que se está recibiendo de `device_id`, si hay una a medias
+pub fn get_sync_bootstrap(connection: &Connection, device_id: DeviceId) -> Result<Option<SyncBootstrap>> {
+    let row = connection.query_row(
+        "SELECT position, knowledge, complete FROM sync_bootstraps WHERE device_id = ?",
+        [device_id],
+        |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?)),
+    ).optional()?;
+    let Some((position, knowledge, complete)) = row else {
+        return Ok(None);
+    };
+    Ok(Some(SyncBootstrap {
+        position,
+        knowledge: serde_json::from_str(&knowledge)?,
+        complete,
+    }))
+}
+
+/// Anota hasta dónde llegó la copia que se recibe de `device_id`
+pub fn save_sync_bootstrap(connection: &Connection, device_id: DeviceId, bootstrap: &SyncBootstrap) -> Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO sync_bootstraps (device_id, position, knowledge, complete) VALUES (?, ?, ?, ?)",
+        params![device_id, bootstrap.position, serde_json::to_string(&bootstrap.knowledge)?, bootstrap.complete],
+    )?;
+    Ok(())
+}
+
+/// La copia de `device_id` terminó: lo que sigue es incremental
+pub fn clear_sync_bootstrap(connection: &Connection, device_id: DeviceId) -> Result<()> {
+    connection.execute("DELETE FROM sync_bootstraps WHERE device_id = ?", [device_id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{add_trusted_device, remove_trusted_device, run_migrations};
+    use crate::models::{TrustedDevice, VersionVector};
+
+    #[test]
+    fn test_resumes_where_the_copy_stopped() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let phone = DeviceId::new();
+        add_trusted_device(&connection, &TrustedDevice {
+            device_id: phone,
+            name: None,
+            key_fingerprint: None,
+            static_key: None,
+            trusted_at: "2024-01-01T00:00:00Z".to_string(),
+        }, None).unwrap();
+        assert!(get_sync_bootstrap(&connection, phone).unwrap().is_none());
+
+        let mut knowledge = VersionVector::new();
+        knowledge.observe(phone, 12);
+        let mut bootstrap = SyncBootstrap { position: None, knowledge, complete: false };
+        save_sync_bootstrap(&connection, phone, &bootstrap).unwrap();
+        assert_eq!(get_sync_bootstrap(&connection, phone).unwrap().as_ref(), Some(&bootstrap));
+
+        bootstrap.position = Some("1a".to_string());
+        bootstrap.complete = true;
+        save_sync_bootstrap(&connection, phone, &bootstrap).unwrap();
+        assert_eq!(get_sync_bootstrap(&connection, phone).unwrap(), Some(bootstrap.clone()));
+
+        clear_sync_bootstrap(&connection, phone).unwrap();
+        assert!(get_sync_bootstrap(&connection, phone).unwrap().is_none());
+
+        // Al dejar de confiar en el dispositivo se olvida la copia a medias
+        save_sync_bootstrap(&connection, phone, &bootstrap).unwrap();
+        assert!(remove_trusted_device(&connection, phone).unwrap());
+        assert!(get_sync_bootstrap(&connection, phone).unwrap().is_none());
+    }
+}
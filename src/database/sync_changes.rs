@@ -0,0 +1,164 @@
+//! Diario de cambios de la sincronización
+//!
+//! Cada cambio que hay que enviar a los dispositivos de confianza se guarda
+//! aquí, así no se pierde al cerrar la aplicación. Al sincronizarse queda con
+//! la fecha en `synced_at` hasta que se descarta por antiguo.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use anyhow::Result;
+use log::info;
+use crate::models::{DeviceId, EntryId};
+
+/// Columnas de sync_changes en el orden de [`read_sync_change`]
+const SYNC_CHANGE_COLUMNS: &str = "id, element_id, change_type, timestamp, source_device, element_data, metadata,
+    version, previous_hash, current_hash, versions";
+
+/// Cambio guardado en el diario. El tipo de cambio y los metadatos van como
+/// texto; la sincronización los interpreta.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncChangeRow {
+    pub id: String,
+    pub element_id: EntryId,
+    pub change_type: String,
+    /// Fecha RFC 3339 del cambio
+    pub timestamp: String,
+    pub source_device: DeviceId,
+    pub element_data: Option<Vec<u8>>,
+    /// Metadatos en JSON
+    pub metadata: String,
+    pub version: i64,
+    pub previous_hash: Option<String>,
+    pub current_hash: String,
+    /// Vector de versiones del elemento en JSON
+    pub versions: String,
+}
+
+fn read_sync_change(row: &Row) -> rusqlite::Result<SyncChangeRow> {
+    Ok(SyncChangeRow {
+        id: row.get(0)?,
+        element_id: row.get(1)?,
+        change_type: row.get(2)?,
+        timestamp: row.get(3)?,
+        source_device: row.get(4)?,
+        element_data: row.get(5)?,
+        metadata: row.get(6)?,
+        version: row.get(7)?,
+        previous_hash: row.get(8)?,
+        current_hash: row.get(9)?,
+        versions: row.get(10)?,
+    })
+}
+
+/// Guarda un cambio pendiente. Si ya estaba se reemplaza y vuelve a quedar
+/// pendiente.
+pub fn insert_sync_change(connection: &Connection, change: &SyncChangeRow) -> rusqlite::Result<()> {
+    connection.prepare_cached(&format!(
+        "INSERT OR REPLACE INTO sync_changes ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        SYNC_CHANGE_COLUMNS,
+    ))?.execute(params![
+        change.id,
+        change.element_id,
+        change.change_type,
+        change.timestamp,
+        change.source_device,
+        change.element_data,
+        change.metadata,
+        change.version,
+        change.previous_hash,
+        change.current_hash,
+        change.versions,
+    ])?;
+    Ok(())
+}
+
+/// Cambios todavía sin sincronizar, del más antiguo al más reciente
+pub fn list_pending_sync_changes(connection: &Connection) -> Result<Vec<SyncChangeRow>> {
+    let mut stmt = connection.prepare(&format!(
+        "SELECT {} FROM sync_changes WHERE synced_at IS NULL ORDER BY timestamp, rowid",
+        SYNC_CHANGE_COLUMNS,
+    ))?;
+    let changes = stmt.query_map([], read_sync_change)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(changes)
+}
+
+/// Último cambio anotado de un elemento, sincronizado o no; de él salen la
+/// versión y el hash anterior del siguiente
+pub fn last_sync_change(connection: &Connection, element_id: EntryId) -> Result<Option<SyncChangeRow>> {
+    Ok(connection.query_row(
+        &format!(
+            "SELECT {} FROM sync_changes WHERE element_id = ? ORDER BY version DESC, timestamp DESC LIMIT 1",
+            SYNC_CHANGE_COLUMNS,
+        ),
+        [element_id],
+        read_sync_change,
+    ).optional()?)
+}
+
+/// Marca los cambios como sincronizados. Devuelve cuántos estaban pendientes.
+pub fn mark_sync_changes_synced(connection: &Connection, change_ids: &[String], synced_at: &str) -> Result<usize> {
+    let mut stmt = connection.prepare_cached(
+        "UPDATE sync_changes SET synced_at = ? WHERE id = ? AND synced_at IS NULL",
+    )?;
+    let mut marked = 0;
+    for change_id in change_ids {
+        marked += stmt.execute(params![synced_at, change_id])?;
+    }
+    Ok(marked)
+}
+
+/// Descarta los cambios sincronizados antes de `cutoff` (fecha RFC 3339).
+/// Devuelve cuántos se borraron.
+pub fn prune_synced_changes(connection: &Connection, cutoff: &str) -> Result<usize> {
+    let pruned = connection.execute(
+        "DELETE FROM sync_changes WHERE synced_at IS NOT NULL AND synced_at < ?",
+        [cutoff],
+    )?;
+    if pruned > 0 {
+        info!("Diario de sincronización: {} cambios sincronizados descartados", pruned);
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    fn change(id: &str, timestamp: &str) -> SyncChangeRow {
+        SyncChangeRow {
+            id: id.to_string(),
+            element_id: EntryId::new(),
+            change_type: "Modified".to_string(),
+            timestamp: timestamp.to_string(),
+            source_device: DeviceId::new(),
+            element_data: Some(vec![1, 2, 3]),
+            metadata: "{}".to_string(),
+            version: 2,
+            previous_hash: None,
+            current_hash: "hash".to_string(),
+            versions: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_keeps_pending_changes_until_synced() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let (older, newer) = (change("a", "2024-01-01T00:00:00Z"), change("b", "2024-02-01T00:00:00Z"));
+        insert_sync_change(&connection, &newer).unwrap();
+        insert_sync_change(&connection, &older).unwrap();
+        assert_eq!(list_pending_sync_changes(&connection).unwrap(), vec![older.clone(), newer.clone()]);
+
+        let ids = vec!["a".to_string(), "desconocido".to_string()];
+        assert_eq!(mark_sync_changes_synced(&connection, &ids, "2024-03-01T00:00:00Z").unwrap(), 1);
+        assert_eq!(mark_sync_changes_synced(&connection, &ids, "2024-03-02T00:00:00Z").unwrap(), 0);
+        assert_eq!(list_pending_sync_changes(&connection).unwrap(), vec![newer]);
+        assert_eq!(last_sync_change(&connection, older.element_id).unwrap(), Some(older.clone()));
+        assert_eq!(last_sync_change(&connection, EntryId::new()).unwrap(), None);
+
+        assert_eq!(prune_synced_changes(&connection, "2024-02-15T00:00:00Z").unwrap(), 0);
+        assert_eq!(prune_synced_changes(&connection, "2024-03-15T00:00:00Z").unwrap(), 1);
+        assert_eq!(list_pending_sync_changes(&connection).unwrap().len(), 1);
+    }
+}
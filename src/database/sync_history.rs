@@ -0,0 +1,194 @@
+//! Historial de sincronizaciones
+//!
+//! Cada intento de sincronizar con otro dispositivo queda aquí con lo que
+//! viajó, cuánto tardó y el error si falló, para poder revisar qué se
+//! sincronizó y cuándo. Se conservan los `MAX_RECORDS` más recientes.
+//!
+//! Además se llevan los totales de cada dispositivo en `device_sync_stats`,
+//! que no se recortan con el historial.
+
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use crate::models::{DeviceId, DeviceSyncStats, SyncDirection, SyncHistoryRecord, SyncHistoryRequest};
+
+/// Intentos que se conservan en el historial
+const MAX_RECORDS: i64 = 5_000;
+
+/// Anota un intento de sincronización y lo suma a los totales del
+/// dispositivo; el id de `record` se ignora
+pub fn record_sync(connection: &Connection, record: &SyncHistoryRecord) -> Result<()> {
+    connection.execute(
+        "INSERT INTO sync_history
+            (device_id, direction, items_sent, items_received, bytes_sent, bytes_received, duration_ms, error, started_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            record.device_id,
+            record.direction.as_str(),
+            record.items_sent as i64,
+            record.items_received as i64,
+            record.bytes_sent as i64,
+            record.bytes_received as i64,
+            record.duration_ms as i64,
+            record.error,
+            record.started_at,
+        ],
+    )?;
+    connection.execute(
+        "DELETE FROM sync_history WHERE id <= (SELECT MAX(id) FROM sync_history) - ?",
+        [MAX_RECORDS],
+    )?;
+
+    let failed = record.error.is_some();
+    connection.execute(
+        "INSERT INTO device_sync_stats
+            (device_id, syncs, failures, items_sent, items_received, bytes_sent, bytes_received, last_duration_ms, last_sync_at, last_failure_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT (device_id) DO UPDATE SET
+            syncs = syncs + excluded.syncs,
+            failures = failures + excluded.failures,
+            items_sent = items_sent + excluded.items_sent,
+            items_received = items_received + excluded.items_received,
+            bytes_sent = bytes_sent + excluded.bytes_sent,
+            bytes_received = bytes_received + excluded.bytes_received,
+            last_duration_ms = excluded.last_duration_ms,
+            last_sync_at = COALESCE(excluded.last_sync_at, last_sync_at),
+            last_failure_at = COALESCE(excluded.last_failure_at, last_failure_at)",
+        params![
+            record.device_id,
+            !failed as i64,
+            failed as i64,
+            record.items_sent as i64,
+            record.items_received as i64,
+            record.bytes_sent as i64,
+            record.bytes_received as i64,
+            record.duration_ms as i64,
+            (!failed).then_some(&record.started_at),
+            failed.then_some(&record.started_at),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Totales de las sincronizaciones con un dispositivo; en cero si nunca se
+/// sincronizó con él
+pub fn device_sync_stats(connection: &Connection, device_id: DeviceId) -> Result<DeviceSyncStats> {
+    let stats = connection.query_row(
+        "SELECT syncs, failures, items_sent, items_received, bytes_sent, bytes_received, last_duration_ms, last_sync_at, last_failure_at
+         FROM device_sync_stats WHERE device_id = ?",
+        [device_id],
+        |row| Ok(DeviceSyncStats {
+            device_id,
+            syncs: row.get::<_, i64>(0)? as u64,
+            failures: row.get::<_, i64>(1)? as u64,
+            items_sent: row.get::<_, i64>(2)? as u64,
+            items_received: row.get::<_, i64>(3)? as u64,
+            bytes_sent: row.get::<_, i64>(4)? as u64,
+            bytes_received: row.get::<_, i64>(5)? as u64,
+            last_duration_ms: row.get::<_, i64>(6)? as u64,
+            last_sync_at: row.get(7)?,
+            last_failure_at: row.get(8)?,
+        }),
+    );
+    match stats {
+        Ok(stats) => Ok(stats),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DeviceSyncStats::empty(device_id)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Página de intentos que cumplen los filtros, del más reciente al más
+/// antiguo, junto con el total de intentos que los cumplen
+pub fn list_sync_history(connection: &Connection, request: &SyncHistoryRequest) -> Result<(Vec<SyncHistoryRecord>, usize)> {
+    const FILTER: &str = "(?1 IS NULL OR device_id = ?1) AND (?2 = 0 OR error IS NOT NULL)";
+
+    let total: i64 = connection.query_row(
+        &format!("SELECT COUNT(*) FROM sync_history WHERE {}", FILTER),
+        params![request.device_id, request.failed_only],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = connection.prepare(&format!(
+        "SELECT id, device_id, direction, items_sent, items_received, bytes_sent, bytes_received, duration_ms, error, started_at
+         FROM sync_history WHERE {} ORDER BY id DESC LIMIT ?3 OFFSET ?4",
+        FILTER,
+    ))?;
+    // LIMIT -1 no pone límite
+    let limit = request.limit.map_or(-1, |limit| limit as i64);
+    let offset = request.offset.unwrap_or(0) as i64;
+    let records = stmt.query_map(
+        params![request.device_id, request.failed_only, limit, offset],
+        |row| {
+            let direction: String = row.get(2)?;
+            Ok(SyncHistoryRecord {
+                id: row.get(0)?,
+                device_id: row.get(1)?,
+                direction: SyncDirection::parse(&direction).ok_or_else(|| {
+                    rusqlite::Error::InvalidColumnType(2, "direction".to_string(), rusqlite::types::Type::Text)
+                })?,
+                items_sent: row.get::<_, i64>(3)? as u64,
+                items_received: row.get::<_, i64>(4)? as u64,
+                bytes_sent: row.get::<_, i64>(5)? as u64,
+                bytes_received: row.get::<_, i64>(6)? as u64,
+                duration_ms: row.get::<_, i64>(7)? as u64,
+                error: row.get(8)?,
+                started_at: row.get(9)?,
+            })
+        },
+    )?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok((records, total as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    #[test]
+    fn test_records_and_pages_sync_attempts() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        let attempt = |device_id, direction, error: Option<&str>, started_at: &str| SyncHistoryRecord {
+            id: 0,
+            device_id,
+            direction,
+            items_sent: 2,
+            items_received: 1,
+            bytes_sent: 300,
+            bytes_received: 120,
+            duration_ms: 45,
+            error: error.map(str::to_string),
+            started_at: started_at.to_string(),
+        };
+
+        record_sync(&connection, &attempt(laptop, SyncDirection::Outgoing, None, "2024-01-01T00:00:00Z")).unwrap();
+        record_sync(&connection, &attempt(phone, SyncDirection::Incoming, None, "2024-01-02T00:00:00Z")).unwrap();
+        record_sync(&connection, &attempt(laptop, SyncDirection::Outgoing, Some("Conexión perdida"), "2024-01-03T00:00:00Z")).unwrap();
+
+        let (records, total) = list_sync_history(&connection, &SyncHistoryRequest::default()).unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(records[0].error.as_deref(), Some("Conexión perdida"));
+        assert_eq!(records[1].direction, SyncDirection::Incoming);
+        assert_eq!((records[2].items_sent, records[2].bytes_received, records[2].duration_ms), (2, 120, 45));
+
+        let laptop_page = SyncHistoryRequest { device_id: Some(laptop), offset: Some(1), limit: Some(1), ..Default::default() };
+        let (records, total) = list_sync_history(&connection, &laptop_page).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].started_at, "2024-01-01T00:00:00Z");
+
+        let failed = SyncHistoryRequest { failed_only: true, ..Default::default() };
+        assert_eq!(list_sync_history(&connection, &failed).unwrap().1, 1);
+
+        // Los totales de cada dispositivo
+        let stats = device_sync_stats(&connection, laptop).unwrap();
+        assert_eq!((stats.syncs, stats.failures), (1, 1));
+        assert_eq!((stats.items_sent, stats.bytes_received, stats.last_duration_ms), (4, 240, 45));
+        assert_eq!(stats.last_sync_at.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(stats.last_failure_at.as_deref(), Some("2024-01-03T00:00:00Z"));
+        assert_eq!(device_sync_stats(&connection, phone).unwrap().syncs, 1);
+        let stranger = DeviceId::new();
+        assert_eq!(device_sync_stats(&connection, stranger).unwrap(), DeviceSyncStats::empty(stranger));
+    }
+}
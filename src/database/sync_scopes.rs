@@ -0,0 +1,170 @@
+//! Categorías excluidas de la sincronización con cada dispositivo
+//!
+//! El usuario puede dejar categorías, con sus subcategorías, fuera de lo que
+//! se manda a un dispositivo. Del lado que las excluye se anotan las entradas
+//! que ese dispositivo no tiene por eso, para mandárselas si vuelven a estar a
+//! su alcance. Del lado que recibe se anotan las que se quitaron por quedar
+//! fuera: conservan sus versiones, pero no viajan como eliminaciones.
+
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use std::collections::HashSet;
+use crate::models::{CategoryId, DeviceId, EntryId};
+
+/// Categorías que el usuario excluyó de lo que se manda a `device_id`
+pub fn get_sync_scope(connection: &Connection, device_id: DeviceId) -> Result<Vec<CategoryId>> {
+    let mut stmt = connection.prepare(
+        "SELECT category_id FROM sync_scopes WHERE device_id = ? ORDER BY category_id",
+    )?;
+    let excluded = stmt.query_map([device_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(excluded)
+}
+
+/// Reemplaza las categorías excluidas de lo que se manda a `device_id`
+pub fn set_sync_scope(connection: &Connection, device_id: DeviceId, excluded: &[CategoryId]) -> Result<()> {
+    connection.execute("DELETE FROM sync_scopes WHERE device_id = ?", [device_id])?;
+    let mut stmt = connection.prepare("INSERT OR IGNORE INTO sync_scopes (device_id, category_id) VALUES (?, ?)")?;
+    for category_id in excluded {
+        stmt.execute(params![device_id, category_id])?;
+    }
+    Ok(())
+}
+
+/// Entradas que no se mandan a `device_id`: las de las categorías excluidas
+/// y sus subcategorías
+pub fn excluded_entries(connection: &Connection, device_id: DeviceId) -> Result<HashSet<EntryId>> {
+    let mut stmt = connection.prepare_cached(
+        "WITH RECURSIVE excluded (id) AS (
+            SELECT category_id FROM sync_scopes WHERE device_id = ?
+            UNION
+            SELECT c.id FROM categories c JOIN excluded e ON c.parent_id = e.id
+         )
+         SELECT id FROM password_entries WHERE category_id IN (SELECT id FROM excluded)",
+    )?;
+    let entries = stmt.query_map([device_id], |row| row.get(0))?
+        .collect::<Result<HashSet<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Entradas que `device_id` no tiene por estar excluidas
+pub fn withheld_entries(connection: &Connection, device_id: DeviceId) -> Result<HashSet<EntryId>> {
+    let mut stmt = connection.prepare_cached("SELECT entry_id FROM sync_withheld WHERE device_id = ?")?;
+    let entries = stmt.query_map([device_id], |row| row.get(0))?
+        .collect::<Result<HashSet<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Anota que `device_id` ya no tiene las entradas `withheld` y sí tiene las
+/// `released`
+pub fn update_withheld_entries(
+    connection: &Connection,
+    device_id: DeviceId,
+    withheld: &[EntryId],
+    released: &[EntryId],
+) -> Result<()> {
+    let mut withhold = connection.prepare_cached("INSERT OR IGNORE INTO sync_withheld (device_id, entry_id) VALUES (?, ?)")?;
+    for entry_id in withheld {
+        withhold.execute(params![device_id, entry_id])?;
+    }
+    let mut release = connection.prepare_cached("DELETE FROM sync_withheld WHERE device_id = ? AND entry_id = ?")?;
+    for entry_id in released {
+        release.execute(params![device_id, entry_id])?;
+    }
+    Ok(())
+}
+
+/// Anota que la entrada se quitó porque otro dispositivo la dejó fuera
+pub fn mark_out_of_scope(connection: &Connection, entry_id: EntryId, removed_at: &str) -> Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO sync_out_of_scope (entry_id, removed_at) VALUES (?, ?)",
+        params![entry_id, removed_at],
+    )?;
+    Ok(())
+}
+
+/// Entradas que se quitaron por quedar fuera de lo que manda otro dispositivo
+pub fn out_of_scope_entries(connection: &Connection) -> Result<HashSet<EntryId>> {
+    let mut stmt = connection.prepare_cached("SELECT entry_id FROM sync_out_of_scope")?;
+    let entries = stmt.query_map([], |row| row.get(0))?
+        .collect::<Result<HashSet<_>, _>>()?;
+    Ok(entries)
+}
+
+/// La entrada volvió a llegar: deja de contar como quitada
+pub fn clear_out_of_scope(connection: &Connection, entry_id: EntryId) -> Result<()> {
+    connection.execute("DELETE FROM sync_out_of_scope WHERE entry_id = ?", [entry_id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{add_trusted_device, insert_category, run_migrations};
+    use crate::models::{Category, TrustedDevice};
+
+    fn category(name: &str, parent_id: Option<CategoryId>) -> Category {
+        Category {
+            id: CategoryId::new(),
+            name: name.to_string(),
+            color: "#000000".to_string(),
+            icon: None,
+            parent_id,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    fn insert_entry(connection: &Connection, category_id: Option<CategoryId>) -> EntryId {
+        let id = EntryId::new();
+        connection.execute(
+            "INSERT INTO password_entries (id, title, username, password, category_id, created_at, updated_at)
+             VALUES (?, '', '', '', ?, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            params![id, category_id],
+        ).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_excludes_categories_with_their_subcategories() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let phone = DeviceId::new();
+        add_trusted_device(&connection, &TrustedDevice {
+            device_id: phone,
+            name: None,
+            key_fingerprint: None,
+            static_key: None,
+            trusted_at: "2024-01-01T00:00:00Z".to_string(),
+        }, None).unwrap();
+        let work = category("Trabajo", None);
+        let clients = category("Clientes", Some(work.id));
+        let personal = category("Personal", None);
+        for category in [&work, &clients, &personal] {
+            insert_category(&connection, category).unwrap();
+        }
+        let (report, invoice, bank, loose) = (
+            insert_entry(&connection, Some(work.id)),
+            insert_entry(&connection, Some(clients.id)),
+            insert_entry(&connection, Some(personal.id)),
+            insert_entry(&connection, None),
+        );
+
+        assert!(excluded_entries(&connection, phone).unwrap().is_empty());
+        set_sync_scope(&connection, phone, &[work.id]).unwrap();
+        assert_eq!(get_sync_scope(&connection, phone).unwrap(), vec![work.id]);
+        assert_eq!(excluded_entries(&connection, phone).unwrap(), HashSet::from([report, invoice]));
+        assert!(excluded_entries(&connection, DeviceId::new()).unwrap().is_empty());
+
+        update_withheld_entries(&connection, phone, &[report, invoice], &[]).unwrap();
+        update_withheld_entries(&connection, phone, &[], &[invoice, bank]).unwrap();
+        assert_eq!(withheld_entries(&connection, phone).unwrap(), HashSet::from([report]));
+
+        set_sync_scope(&connection, phone, &[personal.id]).unwrap();
+        assert_eq!(excluded_entries(&connection, phone).unwrap(), HashSet::from([bank]));
+
+        mark_out_of_scope(&connection, loose, "2024-01-02T00:00:00Z").unwrap();
+        assert_eq!(out_of_scope_entries(&connection).unwrap(), HashSet::from([loose]));
+        clear_out_of_scope(&connection, loose).unwrap();
+        assert!(out_of_scope_entries(&connection).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+//! Marcas de eliminación
+//!
+//! Al borrar una entrada queda una marca con su id y la fecha. La
+//! sincronización la envía a los dispositivos de confianza, que la confirman;
+//! cuando todos la confirmaron ya nadie puede resucitar la entrada y la marca
+//! se purga.
+
+use rusqlite::{params, Connection};
+use anyhow::Result;
+use log::info;
+use crate::models::{DeviceId, EntryId, Tombstone};
+
+/// Registra la eliminación de `entry_id`. Si ya estaba eliminada se conserva la
+/// fecha nueva y se descartan las confirmaciones anteriores.
+pub fn record_tombstone(connection: &Connection, entry_id: EntryId, deleted_at: &str) -> rusqlite::Result<()> {
+    connection.execute("DELETE FROM tombstones WHERE entry_id = ?", [entry_id])?;
+    connection.execute(
+        "INSERT INTO tombstones (entry_id, deleted_at) VALUES (?, ?)",
+        params![entry_id, deleted_at],
+    )?;
+    Ok(())
+}
+
+/// Quita la marca de una entrada que vuelve a existir (restaurada o importada con el mismo id)
+pub fn clear_tombstone(connection: &Connection, entry_id: EntryId) -> rusqlite::Result<()> {
+    connection.prepare_cached("DELETE FROM tombstones WHERE entry_id = ?")?.execute([entry_id])?;
+    Ok(())
+}
+
+/// Marcas posteriores a `since` (todas con `None`), de la más antigua a la más reciente
+pub fn list_tombstones(connection: &Connection, since: Option<&str>) -> Result<Vec<Tombstone>> {
+    let mut stmt = connection.prepare(
+        "SELECT entry_id, deleted_at FROM tombstones WHERE ?1 IS NULL OR deleted_at > ?1 ORDER BY deleted_at",
+    )?;
+    let tombstones = stmt.query_map([since], |row| {
+        Ok(Tombstone { entry_id: row.get(0)?, deleted_at: row.get(1)? })
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+    Ok(tombstones)
+}
+
+/// Anota que `device_id` aplicó las eliminaciones indicadas. Las marcas que no
+/// existen se ignoran, igual que todo si el dispositivo no es de confianza.
+/// Devuelve cuántas se confirmaron.
+pub fn acknowledge_tombstones(
+    connection: &Connection,
+    device_id: DeviceId,
+    entry_ids: &[EntryId],
+    acknowledged_at: &str,
+) -> Result<usize> {
+    let mut stmt = connection.prepare(
+        "INSERT OR REPLACE INTO tombstone_acks (entry_id, device_id, acknowledged_at)
+         SELECT entry_id, ?2, ?3 FROM tombstones
+         WHERE entry_id = ?1 AND EXISTS (SELECT 1 FROM trusted_devices WHERE device_id = ?2)",
+    )?;
+    let mut acknowledged = 0;
+    for entry_id in entry_ids {
+        acknowledged += stmt.execute(params![entry_id, device_id, acknowledged_at])?;
+    }
+    Ok(acknowledged)
+}
+
+/// Borra las marcas que ya confirmaron todos los dispositivos de confianza.
+/// Sin dispositivos de confianza no hay con quién sincronizar y se borran todas.
+pub fn purge_acknowledged_tombstones(connection: &Connection) -> Result<usize> {
+    let purged = connection.execute(
+        "DELETE FROM tombstones WHERE NOT EXISTS (
+            SELECT 1 FROM trusted_devices d
+            WHERE NOT EXISTS (
+                SELECT 1 FROM tombstone_acks a
+                WHERE a.entry_id = tombstones.entry_id AND a.device_id = d.device_id
+            )
+         )",
+        [],
+    )?;
+    if purged > 0 {
+        info!("{} marcas de eliminación confirmadas por todos los dispositivos purgadas", purged);
+    }
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{add_trusted_device, remove_trusted_device, run_migrations};
+    use crate::models::TrustedDevice;
+
+    #[test]
+    fn test_purges_once_every_trusted_device_acknowledged() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let [laptop, phone] = [DeviceId::new(), DeviceId::new()];
+        let [a, b] = [EntryId::new(), EntryId::new()];
+        for device_id in [laptop, phone] {
+            let device = TrustedDevice {
+                device_id,
+                name: None,
+                key_fingerprint: None,
+                static_key: None,
+                trusted_at: "2024-01-01T00:00:00Z".to_string(),
+            };
+            add_trusted_device(&connection, &device, None).unwrap();
+        }
+        record_tombstone(&connection, a, "2024-02-01T00:00:00Z").unwrap();
+        record_tombstone(&connection, b, "2024-03-01T00:00:00Z").unwrap();
+
+        let since = list_tombstones(&connection, Some("2024-02-15T00:00:00Z")).unwrap();
+        assert_eq!(since, vec![Tombstone { entry_id: b, deleted_at: "2024-03-01T00:00:00Z".to_string() }]);
+
+        let ids = vec![a, b, EntryId::new()];
+        assert_eq!(acknowledge_tombstones(&connection, laptop, &ids, "2024-03-02T00:00:00Z").unwrap(), 2);
+        assert_eq!(acknowledge_tombstones(&connection, DeviceId::new(), &ids, "2024-03-02T00:00:00Z").unwrap(), 0);
+        assert_eq!(purge_acknowledged_tombstones(&connection).unwrap(), 0);
+        acknowledge_tombstones(&connection, phone, &ids[..1], "2024-03-02T00:00:00Z").unwrap();
+        assert_eq!(purge_acknowledged_tombstones(&connection).unwrap(), 1);
+
+        // Un dispositivo que deja de ser de confianza no bloquea la purga
+        assert!(remove_trusted_device(&connection, phone).unwrap());
+        assert_eq!(purge_acknowledged_tombstones(&connection).unwrap(), 1);
+        assert!(list_tombstones(&connection, None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+//! Dispositivos de confianza
+//!
+//! Solo se sincroniza con los dispositivos de esta tabla. Se agregan al
+//! confirmar un emparejamiento, con la clave de sincronización acordada
+//! encriptada con la clave maestra, o cuando el usuario confía en uno a mano.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use anyhow::Result;
+use log::info;
+use crate::models::{DeviceId, TrustedDevice};
+
+/// Da de alta un dispositivo de confianza. Si ya lo era se conserva la fecha y
+/// se actualizan el nombre y, tras un nuevo emparejamiento, la huella, la clave
+/// estática y la clave de sincronización.
+pub fn add_trusted_device(connection: &Connection, device: &TrustedDevice, sync_key: Option<&str>) -> Result<()> {
+    connection.execute(
+        "INSERT INTO trusted_devices (device_id, name, key_fingerprint, static_key, sync_key, trusted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (device_id) DO UPDATE SET
+            name = COALESCE(?2, name),
+            key_fingerprint = COALESCE(?3, key_fingerprint),
+            static_key = COALESCE(?4, static_key),
+            sync_key = COALESCE(?5, sync_key)",
+        params![device.device_id, device.name, device.key_fingerprint, device.static_key, sync_key, device.trusted_at],
+    )?;
+    info!("Dispositivo de confianza guardado: {}", device.device_id);
+    Ok(())
+}
+
+/// Dispositivos de confianza, del más antiguo al más reciente
+pub fn list_trusted_devices(connection: &Connection) -> Result<Vec<TrustedDevice>> {
+    let mut stmt = connection.prepare(
+        "SELECT device_id, name, key_fingerprint, static_key, trusted_at FROM trusted_devices ORDER BY trusted_at, device_id",
+    )?;
+    let devices = stmt.query_map([], read_trusted_device)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(devices)
+}
+
+pub fn get_trusted_device(connection: &Connection, device_id: DeviceId) -> Result<Option<TrustedDevice>> {
+    Ok(connection.query_row(
+        "SELECT device_id, name, key_fingerprint, static_key, trusted_at FROM trusted_devices WHERE device_id = ?",
+        [device_id],
+        read_trusted_device,
+    ).optional()?)
+}
+
+/// Clave de sincronización del dispositivo, encriptada con la clave maestra;
+/// `None` si no es de confianza o no se emparejó
+pub fn get_sync_key(connection: &Connection, device_id: DeviceId) -> Result<Option<String>> {
+    Ok(connection.query_row(
+        "SELECT sync_key FROM trusted_devices WHERE device_id = ?",
+        [device_id],
+        |row| row.get(0),
+    ).optional()?.flatten())
+}
+
+/// Deja de confiar en un dispositivo junto con sus confirmaciones, lo que se
+/// sabía que había visto y las categorías que no se le mandaban; devuelve
+/// `false` si no era de confianza
+pub fn remove_trusted_device(connection: &Connection, device_id: DeviceId) -> Result<bool> {
+    connection.execute("DELETE FROM tombstone_acks WHERE device_id = ?", [device_id])?;
+    connection.execute("DELETE FROM peer_knowledge WHERE peer_id = ?", [device_id])?;
+    connection.execute("DELETE FROM sync_scopes WHERE device_id = ?", [device_id])?;
+    connection.execute("DELETE FROM sync_withheld WHERE device_id = ?", [device_id])?;
+    connection.execute("DELETE FROM sync_bootstraps WHERE device_id = ?", [device_id])?;
+    let removed = connection.execute("DELETE FROM trusted_devices WHERE device_id = ?", [device_id])?;
+    Ok(removed > 0)
+}
+
+fn read_trusted_device(row: &rusqlite::Row<'_>) -> rusqlite::Result<TrustedDevice> {
+    Ok(TrustedDevice {
+        device_id: row.get(0)?,
+        name: row.get(1)?,
+        key_fingerprint: row.get(2)?,
+        static_key: row.get(3)?,
+        trusted_at: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::run_migrations;
+
+    #[test]
+    fn test_keeps_trust_and_updates_pairing_details() {
+        let connection = Connection::open_in_memory().unwrap();
+        run_migrations(&connection).unwrap();
+        let phone = DeviceId::new();
+        let trusted = TrustedDevice {
+            device_id: phone,
+            name: Some("teléfono".to_string()),
+            key_fingerprint: None,
+            static_key: None,
+            trusted_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        add_trusted_device(&connection, &trusted, None).unwrap();
+
+        let paired = TrustedDevice {
+            name: None,
+            key_fingerprint: Some("huella".to_string()),
+            static_key: Some("clave estática".to_string()),
+            trusted_at: "2024-01-02T00:00:00Z".to_string(),
+            ..trusted.clone()
+        };
+        add_trusted_device(&connection, &paired, Some("clave")).unwrap();
+        let stored = get_trusted_device(&connection, phone).unwrap().unwrap();
+        assert_eq!(stored, TrustedDevice {
+            key_fingerprint: Some("huella".to_string()),
+            static_key: Some("clave estática".to_string()),
+            ..trusted
+        });
+        assert_eq!(get_sync_key(&connection, phone).unwrap().as_deref(), Some("clave"));
+        assert_eq!(get_sync_key(&connection, DeviceId::new()).unwrap(), None);
+        assert_eq!(list_trusted_devices(&connection).unwrap(), vec![stored]);
+
+        assert!(remove_trusted_device(&connection, phone).unwrap());
+        assert!(!remove_trusted_device(&connection, phone).unwrap());
+        assert!(get_trusted_device(&connection, phone).unwrap().is_none());
+    }
+}
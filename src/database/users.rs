@@ -0,0 +1,25 @@
+use rusqlite::{Connection, OptionalExtension};
+use anyhow::Result;
+
+/// Guarda el usuario con el hash de la contraseña maestra y su salt en base64
+pub fn insert_user(connection: &Connection, id: &str, password_hash: &str, salt: &str, created_at: &str) -> Result<()> {
+    connection.execute(
+        "INSERT INTO users (id, master_password_hash, salt, created_at) VALUES (?, ?, ?, ?)",
+        [id, password_hash, salt, created_at],
+    )?;
+    Ok(())
+}
+
+/// Hash de la contraseña maestra y salt en base64; `None` si todavía no hay usuario
+pub fn load_master_credentials(connection: &Connection) -> Result<Option<(String, String)>> {
+    Ok(connection.query_row(
+        "SELECT master_password_hash, salt FROM users LIMIT 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?)
+}
+
+/// Número de usuarios registrados
+pub fn count_users(connection: &Connection) -> Result<i64> {
+    Ok(connection.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?)
+}
@@ -0,0 +1,102 @@
+//! Hilo dedicado a la base de datos
+//!
+//! La conexión de SQLite vive en un único hilo y los comandos le envían
+//! trabajos por un canal. El resultado vuelve por un `oneshot`, así que un
+//! comando async espera sin ocupar un hilo del runtime de Tauri mientras SQLite
+//! hace E/S. Los trabajos se ejecutan de a uno y en orden de llegada, igual que
+//! cuando la conexión estaba detrás de un mutex.
+
+use super::DatabaseManager;
+use log::{error, info};
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use tokio::sync::{mpsc, oneshot};
+
+type Job = Box<dyn FnOnce(&mut Option<DatabaseManager>) + Send>;
+
+/// Acceso a la base abierta en el hilo de la base de datos. Clonarlo es barato:
+/// todos los clones envían a la misma cola.
+#[derive(Clone)]
+pub struct DatabaseWorker {
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+/// El trabajo no devolvió resultado: entró en pánico o el hilo ya no existe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerError;
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "La tarea de base de datos no terminó")
+    }
+}
+
+impl std::error::Error for WorkerError {}
+
+impl DatabaseWorker {
+    /// Inicia el hilo, todavía sin base abierta
+    pub fn start() -> Self {
+        let (jobs, mut receiver) = mpsc::unbounded_channel::<Job>();
+        std::thread::Builder::new()
+            .name("alohopass-db".to_string())
+            .spawn(move || {
+                info!("Hilo de la base de datos iniciado");
+                let mut database: Option<DatabaseManager> = None;
+                while let Some(job) = receiver.blocking_recv() {
+                    // Un trabajo que entra en pánico no debe dejar sin base al resto
+                    if std::panic::catch_unwind(AssertUnwindSafe(|| job(&mut database))).is_err() {
+                        error!("❌ Una tarea de base de datos entró en pánico");
+                    }
+                }
+                info!("Hilo de la base de datos detenido");
+            })
+            .expect("No se pudo iniciar el hilo de la base de datos");
+        Self { jobs }
+    }
+
+    /// Ejecuta `job` en el hilo de la base de datos y espera su resultado
+    pub async fn call<T, F>(&self, job: F) -> Result<T, WorkerError>
+    where
+        F: FnOnce(&mut Option<DatabaseManager>) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |database| {
+                let _ = sender.send(job(database));
+            }))
+            .map_err(|_| WorkerError)?;
+        receiver.await.map_err(|_| WorkerError)
+    }
+
+    /// Encola `job` sin esperar a que termine. Los trabajos que se envíen
+    /// después se ejecutan detrás de él.
+    pub fn spawn<F>(&self, job: F) -> Result<(), WorkerError>
+    where
+        F: FnOnce(&mut Option<DatabaseManager>) + Send + 'static,
+    {
+        self.jobs.send(Box::new(job)).map_err(|_| WorkerError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_jobs_in_order_on_one_thread() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let worker = DatabaseWorker::start();
+        runtime.block_on(async {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            worker.spawn(move |_| sender.send(std::thread::current().id()).unwrap()).unwrap();
+            let (thread, empty) = worker.call(|database| (std::thread::current().id(), database.is_none())).await.unwrap();
+            assert_eq!(receiver.try_recv().unwrap(), thread);
+            assert!(empty);
+            assert_ne!(thread, std::thread::current().id());
+
+            assert_eq!(worker.call(|_| -> () { panic!("fallo") }).await, Err(WorkerError));
+            assert_eq!(worker.call(|_| 7).await.unwrap(), 7);
+        });
+    }
+}
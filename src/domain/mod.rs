@@ -0,0 +1,91 @@
+//! Dominio registrable (eTLD+1) de las URL de las entradas
+//!
+//! El autocompletado busca las entradas por el dominio que se puede registrar:
+//! `login.example.co.uk` y `www.example.co.uk` comparten `example.co.uk`. Los
+//! sufijos salen de la Public Suffix List, incluida su sección privada: en los
+//! alojamientos donde cada subdominio tiene un dueño distinto (github.io,
+//! ngrok.io, vercel.app...) cada subdominio es un sitio aparte.
+//!
+//! Las direcciones IP y los hosts de un solo nivel (`localhost`, un equipo de
+//! la red local) no tienen sufijo: solo coinciden consigo mismos.
+
+use publicsuffix::{List, Psl};
+use std::sync::OnceLock;
+
+/// Public Suffix List, tal como se publica en
+/// <https://publicsuffix.org/list/public_suffix_list.dat>
+const PUBLIC_SUFFIX_LIST: &str = include_str!("public_suffix_list.dat");
+
+fn public_suffixes() -> &'static List {
+    static LIST: OnceLock<List> = OnceLock::new();
+    LIST.get_or_init(|| PUBLIC_SUFFIX_LIST.parse().expect("Public Suffix List incluida inválida"))
+}
+
+/// Dominio registrable de un host: el sufijo público más un nivel. Las
+/// direcciones IP y los hosts de un solo nivel se devuelven tal cual; un host
+/// que es en sí un sufijo público no tiene dominio registrable.
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+    if host.is_empty() || host.split('.').any(str::is_empty) {
+        return None;
+    }
+    if host.parse::<std::net::IpAddr>().is_ok() || !host.contains('.') {
+        return Some(host);
+    }
+    let domain = public_suffixes().domain(host.as_bytes())?;
+    std::str::from_utf8(domain.as_bytes()).ok().map(str::to_string)
+}
+
+/// Dominio registrable de la URL de una entrada. Sin esquema se toma como
+/// `https`.
+pub fn registrable_domain_from_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    let parsed = reqwest::Url::parse(url)
+        .ok()
+        .filter(|parsed| parsed.has_host())
+        .or_else(|| reqwest::Url::parse(&format!("https://{}", url)).ok())?;
+    // Las IPv6 vienen entre corchetes
+    let host = parsed.host_str()?;
+    registrable_domain(host.trim_start_matches('[').trim_end_matches(']'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(registrable_domain_from_url("https://accounts.google.com/login"), Some("google.com".to_string()));
+        assert_eq!(registrable_domain_from_url("www.bbc.co.uk"), Some("bbc.co.uk".to_string()));
+        assert_eq!(registrable_domain_from_url("https://login.shop.example.com.ar"), Some("example.com.ar".to_string()));
+        assert_eq!(registrable_domain_from_url("https://alice.github.io/blog"), Some("alice.github.io".to_string()));
+        assert_eq!(registrable_domain_from_url("http://192.168.1.1/admin"), Some("192.168.1.1".to_string()));
+        assert_eq!(registrable_domain_from_url("http://[::1]:8080/"), Some("::1".to_string()));
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain_from_url(""), None);
+    }
+
+    #[test]
+    fn test_private_suffixes_separate_sites() {
+        for (alice, attacker) in [
+            ("https://alice.ngrok.io", "https://attacker.ngrok.io"),
+            ("https://alice.vercel.app", "https://attacker.vercel.app"),
+            ("https://alice.s3.amazonaws.com", "https://attacker.s3.amazonaws.com"),
+            ("https://alice.azurewebsites.net", "https://attacker.azurewebsites.net"),
+        ] {
+            assert_ne!(registrable_domain_from_url(alice), registrable_domain_from_url(attacker));
+        }
+        assert_eq!(registrable_domain_from_url("https://app.alice.onrender.com"), Some("alice.onrender.com".to_string()));
+        assert_eq!(registrable_domain("glitch.me"), None);
+    }
+
+    #[test]
+    fn test_single_label_hosts_match_exactly() {
+        assert_eq!(registrable_domain_from_url("localhost"), Some("localhost".to_string()));
+        assert_eq!(registrable_domain_from_url("http://localhost:3000/login"), Some("localhost".to_string()));
+        assert_eq!(registrable_domain_from_url("http://nas/"), Some("nas".to_string()));
+    }
+}
@@ -0,0 +1,155 @@
+//! Tipo de error estructurado que devuelven los comandos de Tauri
+//!
+//! Cada error se serializa como `{ code, key, params, message, details }`:
+//! - `code`: tipo de error estable para que el frontend pueda ramificar
+//! - `key` y `params`: mensaje traducible (ver `crate::i18n`)
+//! - `message`: texto ya traducido al idioma activo, apto para mostrar al usuario
+//! - `details`: información técnica opcional (errores de SQLite, IO, etc.)
+
+use crate::i18n::{self, Message};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// Resultado estándar de los comandos
+pub type AppResult<T> = Result<T, AppError>;
+
+#[derive(Debug, Clone)]
+pub enum AppError {
+    /// La bóveda está bloqueada: no hay clave maestra en memoria
+    Locked,
+    /// El recurso pedido no existe
+    NotFound(Message),
+    /// Los datos recibidos no son válidos
+    Validation(Message),
+    /// El sistema operativo no concedió un permiso necesario
+    PermissionDenied(Message),
+    /// Error de acceso o consulta a la base de datos
+    Database { message: Message, details: Option<String> },
+    /// Error al encriptar, desencriptar o derivar claves
+    Crypto { message: Message, details: Option<String> },
+    /// Error del sistema de sincronización
+    Sync { message: Message, details: Option<String> },
+    /// Cualquier otro error interno
+    Internal { message: Message, details: Option<String> },
+}
+
+impl AppError {
+    pub fn not_found(message: impl Into<Message>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn validation(message: impl Into<Message>) -> Self {
+        AppError::Validation(message.into())
+    }
+
+    pub fn permission_denied(message: impl Into<Message>) -> Self {
+        AppError::PermissionDenied(message.into())
+    }
+
+    pub fn database(message: impl Into<Message>, details: impl fmt::Display) -> Self {
+        AppError::Database { message: message.into(), details: Some(details.to_string()) }
+    }
+
+    pub fn crypto(message: impl Into<Message>, details: impl fmt::Display) -> Self {
+        AppError::Crypto { message: message.into(), details: Some(details.to_string()) }
+    }
+
+    pub fn sync(message: impl Into<Message>) -> Self {
+        AppError::Sync { message: message.into(), details: None }
+    }
+
+    pub fn sync_with(message: impl Into<Message>, details: impl fmt::Display) -> Self {
+        AppError::Sync { message: message.into(), details: Some(details.to_string()) }
+    }
+
+    pub fn internal(message: impl Into<Message>) -> Self {
+        AppError::Internal { message: message.into(), details: None }
+    }
+
+    pub fn internal_with(message: impl Into<Message>, details: impl fmt::Display) -> Self {
+        AppError::Internal { message: message.into(), details: Some(details.to_string()) }
+    }
+
+    /// La base de datos todavía no se abrió (no hay login ni contraseña maestra)
+    pub fn db_not_initialized() -> Self {
+        AppError::Database { message: Message::new("errors.dbNotInitialized"), details: None }
+    }
+
+    /// No se pudo tomar el lock de un componente del estado global
+    pub fn state_lock(component: &'static str) -> Self {
+        AppError::internal(Message::new("errors.stateLock").with_key("component", component))
+    }
+
+    /// Código estable del tipo de error
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Locked => "locked",
+            AppError::NotFound(_) => "not_found",
+            AppError::Validation(_) => "validation",
+            AppError::PermissionDenied(_) => "permission_denied",
+            AppError::Database { .. } => "database",
+            AppError::Crypto { .. } => "crypto",
+            AppError::Sync { .. } => "sync",
+            AppError::Internal { .. } => "internal",
+        }
+    }
+
+    /// Mensaje traducible del error
+    pub fn message(&self) -> Message {
+        match self {
+            AppError::Locked => Message::new("errors.locked"),
+            AppError::NotFound(message)
+            | AppError::Validation(message)
+            | AppError::PermissionDenied(message) => message.clone(),
+            AppError::Database { message, .. }
+            | AppError::Crypto { message, .. }
+            | AppError::Sync { message, .. }
+            | AppError::Internal { message, .. } => message.clone(),
+        }
+    }
+
+    /// Detalles técnicos, si los hay
+    pub fn details(&self) -> Option<&str> {
+        match self {
+            AppError::Database { details, .. }
+            | AppError::Crypto { details, .. }
+            | AppError::Sync { details, .. }
+            | AppError::Internal { details, .. } => details.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = self.message().render(i18n::current_locale());
+        match self.details() {
+            Some(details) => write!(f, "{}: {}", message, details),
+            None => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let locale = i18n::current_locale();
+        let message = self.message();
+
+        let mut state = serializer.serialize_struct("AppError", 5)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("key", message.key)?;
+        state.serialize_field("params", &message.resolved_params(locale))?;
+        state.serialize_field("message", &message.render(locale))?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(error: rusqlite::Error) -> Self {
+        AppError::database("errors.dbQuery", error)
+    }
+}
@@ -0,0 +1,319 @@
+//! Exportación de la bóveda a otros formatos
+//!
+//! Los formatos de texto contienen las contraseñas sin encriptar: el archivo se
+//! crea con permisos restringidos y es responsabilidad del usuario guardarlo en un
+//! lugar seguro. Los CSV usan las columnas que esperan los importadores de
+//! Bitwarden y de Chrome para que el archivo se pueda cargar sin editarlo.
+//!
+//! La exportación a KeePass (KDBX 4) va encriptada con una contraseña elegida al
+//! exportar e incluye las categorías como grupos, los secretos TOTP y los adjuntos.
+
+use crate::kdbx;
+use crate::models::{Category, CategoryId, EntryId, ExportData, ExportFormat, PasswordEntry};
+use anyhow::Result;
+use base64::Engine;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Versión del formato propio (`ExportData`)
+pub const EXPORT_VERSION: &str = "1.0";
+
+const BITWARDEN_HEADER: &[&str] = &[
+    "folder", "favorite", "type", "name", "notes", "fields", "reprompt",
+    "login_uri", "login_username", "login_password", "login_totp",
+];
+const CHROME_HEADER: &[&str] = &["name", "url", "username", "password", "note"];
+
+/// Nombre de la base de datos y del grupo raíz en las exportaciones KDBX
+const KDBX_ROOT_GROUP: &str = "Alohopass";
+
+/// Entrada del JSON plano: solo los datos útiles fuera de Alohopass
+#[derive(Serialize)]
+struct PlainEntry<'a> {
+    title: &'a str,
+    username: &'a str,
+    password: &'a str,
+    url: Option<&'a str>,
+    notes: Option<&'a str>,
+    tags: &'a [String],
+}
+
+/// Conserva las entradas de la categoría indicada que tienen todas las etiquetas
+/// pedidas (sin distinguir mayúsculas)
+pub fn filter_entries(entries: Vec<PasswordEntry>, category_id: Option<CategoryId>, tags: &[String]) -> Vec<PasswordEntry> {
+    entries.into_iter()
+        .filter(|entry| category_id.is_none_or(|id| entry.category_id == Some(id)))
+        .filter(|entry| tags.iter().all(|tag| entry.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+        .collect()
+}
+
+/// Genera el contenido del archivo de exportación en el formato pedido
+pub fn render(format: ExportFormat, entries: &[PasswordEntry], categories: &[Category]) -> Result<String> {
+    let content = match format {
+        ExportFormat::Json => {
+            let plain: Vec<PlainEntry> = entries.iter()
+                .map(|entry| PlainEntry {
+                    title: &entry.title,
+                    username: &entry.username,
+                    password: &entry.password,
+                    url: entry.url.as_deref(),
+                    notes: entry.notes.as_deref(),
+                    tags: &entry.tags,
+                })
+                .collect();
+            serde_json::to_string_pretty(&plain)?
+        }
+        ExportFormat::Alohopass => {
+            let data = ExportData {
+                version: EXPORT_VERSION.to_string(),
+                exported_at: chrono::Utc::now().to_rfc3339(),
+                entries: entries.to_vec(),
+                categories: categories.to_vec(),
+            };
+            serde_json::to_string_pretty(&data)?
+        }
+        ExportFormat::CsvBitwarden => {
+            let mut csv = csv_line(BITWARDEN_HEADER);
+            for entry in entries {
+                let folder = entry.category_id
+                    .and_then(|id| categories.iter().find(|c| c.id == id))
+                    .map(|c| c.name.as_str())
+                    .unwrap_or("");
+                csv.push_str(&csv_line(&[
+                    folder,
+                    "",
+                    "login",
+                    &entry.title,
+                    entry.notes.as_deref().unwrap_or(""),
+                    "",
+                    "0",
+                    entry.url.as_deref().unwrap_or(""),
+                    &entry.username,
+                    &entry.password,
+                    "",
+                ]));
+            }
+            csv
+        }
+        ExportFormat::Kdbx => anyhow::bail!("KDBX es un formato binario; usar kdbx_database"),
+        ExportFormat::CsvChrome => {
+            let mut csv = csv_line(CHROME_HEADER);
+            for entry in entries {
+                csv.push_str(&csv_line(&[
+                    &entry.title,
+                    entry.url.as_deref().unwrap_or(""),
+                    &entry.username,
+                    &entry.password,
+                    entry.notes.as_deref().unwrap_or(""),
+                ]));
+            }
+            csv
+        }
+    };
+    Ok(content)
+}
+
+/// Convierte las entradas en una base de datos de KeePass. Cada categoría es un
+/// grupo con la misma jerarquía; las entradas sin categoría quedan en la raíz y
+/// los grupos sin entradas se omiten. `totp` y `attachments` van por id de entrada.
+pub fn kdbx_database(
+    entries: &[PasswordEntry],
+    categories: &[Category],
+    totp: &HashMap<EntryId, String>,
+    mut attachments: HashMap<EntryId, Vec<kdbx::Attachment>>,
+) -> kdbx::Database {
+    let mut by_category: HashMap<Option<CategoryId>, Vec<kdbx::Entry>> = HashMap::new();
+    for entry in entries {
+        let attachments = attachments.remove(&entry.id).unwrap_or_default();
+        by_category.entry(entry.category_id)
+            .or_default()
+            .push(kdbx_entry(entry, totp.get(&entry.id), attachments));
+    }
+
+    let mut root = kdbx::Group {
+        uuid: String::new(),
+        name: KDBX_ROOT_GROUP.to_string(),
+        entries: by_category.remove(&None).unwrap_or_default(),
+        groups: Vec::new(),
+    };
+    root.groups = kdbx_groups(None, categories, &mut by_category);
+    // Entradas con una categoría que ya no existe
+    root.entries.extend(by_category.into_values().flatten());
+    kdbx::Database { name: KDBX_ROOT_GROUP.to_string(), root, recycle_bin: None }
+}
+
+fn kdbx_groups(
+    parent_id: Option<CategoryId>,
+    categories: &[Category],
+    by_category: &mut HashMap<Option<CategoryId>, Vec<kdbx::Entry>>,
+) -> Vec<kdbx::Group> {
+    categories.iter()
+        .filter(|category| category.parent_id == parent_id)
+        .filter_map(|category| {
+            let group = kdbx::Group {
+                uuid: kdbx_uuid(category.id.as_uuid()),
+                name: category.name.clone(),
+                groups: kdbx_groups(Some(category.id), categories, by_category),
+                entries: by_category.remove(&Some(category.id)).unwrap_or_default(),
+            };
+            (!group.entries.is_empty() || !group.groups.is_empty()).then_some(group)
+        })
+        .collect()
+}
+
+fn kdbx_entry(entry: &PasswordEntry, totp: Option<&String>, attachments: Vec<kdbx::Attachment>) -> kdbx::Entry {
+    let field = |key: &str, value: &str, protected: bool| kdbx::Field {
+        key: key.to_string(),
+        value: value.to_string(),
+        protected,
+    };
+    let mut fields = vec![
+        field("Title", &entry.title, false),
+        field("UserName", &entry.username, false),
+        field("Password", &entry.password, true),
+        field("URL", entry.url.as_deref().unwrap_or(""), false),
+        field("Notes", entry.notes.as_deref().unwrap_or(""), false),
+    ];
+    // KeePassXC guarda el TOTP como URI en el campo `otp`
+    if let Some(secret) = totp {
+        let uri = if secret.starts_with("otpauth://") {
+            secret.clone()
+        } else {
+            let secret: String = secret.chars().filter(|c| !c.is_whitespace()).collect();
+            format!("otpauth://totp/{}?secret={}", uri_component(&entry.title), secret.to_uppercase())
+        };
+        fields.push(field("otp", &uri, true));
+    }
+    let time = |value: &str| chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&chrono::Utc));
+
+    kdbx::Entry {
+        uuid: kdbx_uuid(entry.id.as_uuid()),
+        fields,
+        attachments,
+        tags: entry.tags.clone(),
+        created_at: time(&entry.created_at),
+        updated_at: time(&entry.updated_at),
+    }
+}
+
+/// UUID de KDBX (base64 de los 16 bytes)
+fn kdbx_uuid(uuid: &uuid::Uuid) -> String {
+    base64::engine::general_purpose::STANDARD.encode(uuid.as_bytes())
+}
+
+/// Codifica un texto para usarlo dentro de una URI
+fn uri_component(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Escribe un archivo con datos sensibles. En Unix solo el dueño puede leerlo.
+pub fn write_file(path: &Path, content: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(content.as_ref())?;
+    file.sync_all()
+}
+
+fn csv_line(fields: &[&str]) -> String {
+    let mut line = fields.iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+/// Entrecomilla el campo si contiene separadores, comillas o saltos de línea (RFC 4180)
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) || value.starts_with(' ') || value.ends_with(' ') {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, password: &str, category_id: Option<CategoryId>, tags: &[&str]) -> PasswordEntry {
+        PasswordEntry {
+            username: "ana@example.com".to_string(),
+            password: password.to_string(),
+            url: Some("https://example.com".to_string()),
+            category_id,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..PasswordEntry::sample(title)
+        }
+    }
+
+    #[test]
+    fn test_csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("simple"), "simple");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("di \"hola\""), "\"di \"\"hola\"\"\"");
+        assert_eq!(csv_field("dos\nlíneas"), "\"dos\nlíneas\"");
+    }
+
+    #[test]
+    fn test_chrome_csv_has_one_row_per_entry() {
+        let csv = render(ExportFormat::CsvChrome, &[entry("Correo", "p,ss", None, &[])], &[]).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "name,url,username,password,note");
+        assert_eq!(lines[1], "Correo,https://example.com,ana@example.com,\"p,ss\",");
+    }
+
+    #[test]
+    fn test_filter_matches_category_and_all_tags() {
+        let work = CategoryId::new();
+        let entries = vec![
+            entry("A", "1", Some(work), &["Email", "vpn"]),
+            entry("B", "2", Some(work), &["email"]),
+            entry("C", "3", None, &["email", "vpn"]),
+        ];
+        let tags = vec!["email".to_string(), "VPN".to_string()];
+        let filtered = filter_entries(entries, Some(work), &tags);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].title, "A");
+    }
+
+    #[test]
+    fn test_kdbx_groups_follow_categories() {
+        let category = |name: &str, parent_id: Option<CategoryId>| Category {
+            id: CategoryId::new(),
+            name: name.to_uppercase(),
+            color: String::new(),
+            icon: None,
+            parent_id,
+            created_at: String::new(),
+        };
+        let work = category("trabajo", None);
+        let vpn = category("vpn", Some(work.id));
+        let entries = vec![entry("A", "1", Some(vpn.id), &[]), entry("B", "2", None, &[])];
+        let categories = vec![work, vpn, category("vacia", None)];
+        let totp = HashMap::from([(entries[1].id, "jbsw y3dp".to_string())]);
+
+        let database = kdbx_database(&entries, &categories, &totp, HashMap::new());
+        assert_eq!(database.root.entries[0].get("Title"), Some("B"));
+        assert_eq!(database.root.entries[0].get("otp"), Some("otpauth://totp/B?secret=JBSWY3DP"));
+        assert_eq!(database.root.groups.len(), 1);
+        assert_eq!(database.root.groups[0].name, "TRABAJO");
+        assert_eq!(database.root.groups[0].groups[0].entries[0].get("Password"), Some("1"));
+    }
+}
@@ -0,0 +1,322 @@
+//! Descarga y caché de favicons de los sitios guardados
+//!
+//! Los iconos se guardan en disco por dominio (`<dominio>.icon`) para no repetir
+//! peticiones de red. Si un sitio no tiene icono se guarda un marcador
+//! `<dominio>.missing` y no se vuelve a intentar hasta que caduque. Las descargas
+//! se hacen en segundo plano; al terminar se emite el evento `favicon-ready`
+//! con el dominio para que la interfaz vuelva a pedir el icono.
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+use tokio::sync::{mpsc, Semaphore};
+
+/// Evento emitido cuando se resolvió el icono de un dominio
+pub const READY_EVENT: &str = "favicon-ready";
+
+/// Tiempo tras el cual un icono en caché se vuelve a descargar
+const ICON_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Tiempo durante el que no se reintenta un dominio sin icono
+const MISSING_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Tamaño máximo aceptado para la página y para el icono
+const MAX_HTML_BYTES: usize = 512 * 1024;
+const MAX_ICON_BYTES: usize = 256 * 1024;
+/// Descargas simultáneas
+const MAX_CONCURRENT_FETCHES: usize = 4;
+
+/// Icono leído de la caché
+#[derive(Debug, Clone)]
+pub struct CachedIcon {
+    pub mime_type: &'static str,
+    pub data: Vec<u8>,
+    /// El icono caducó y se pidió una nueva descarga
+    pub stale: bool,
+}
+
+#[derive(Clone)]
+pub struct FaviconService {
+    cache_dir: PathBuf,
+    queue: mpsc::UnboundedSender<String>,
+    pending: Arc<Mutex<HashSet<String>>>,
+}
+
+impl FaviconService {
+    /// Crea el servicio e inicia el trabajador de descargas en el runtime de Tauri
+    pub fn start(app: tauri::AppHandle) -> Result<Self> {
+        let cache_dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("No se pudo determinar el directorio de caché"))?
+            .join("alohopass")
+            .join("favicons");
+        std::fs::create_dir_all(&cache_dir)?;
+        info!("Caché de favicons en {}", cache_dir.display());
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(concat!("Alohopass/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        let (queue, receiver) = mpsc::unbounded_channel();
+        let service = Self {
+            cache_dir,
+            queue,
+            pending: Arc::new(Mutex::new(HashSet::new())),
+        };
+        tauri::async_runtime::spawn(service.clone().run(app, client, receiver));
+        Ok(service)
+    }
+
+    /// Devuelve el icono en caché; si no existe o caducó, encola su descarga
+    pub fn get_or_request(&self, domain: &str, fetch_enabled: bool) -> Option<CachedIcon> {
+        let icon_path = self.icon_path(domain);
+        if let Ok(data) = std::fs::read(&icon_path) {
+            let stale = is_older_than(&icon_path, ICON_TTL);
+            if stale && fetch_enabled {
+                self.request(domain);
+            }
+            return detect_mime(&data).map(|mime_type| CachedIcon { mime_type, data, stale });
+        }
+
+        let missing_path = self.missing_path(domain);
+        if fetch_enabled && (!missing_path.exists() || is_older_than(&missing_path, MISSING_TTL)) {
+            self.request(domain);
+        }
+        None
+    }
+
+    /// Encola la descarga del icono de un dominio, salvo que ya esté en curso
+    pub fn request(&self, domain: &str) {
+        let is_new = self.pending.lock()
+            .map(|mut pending| pending.insert(domain.to_string()))
+            .unwrap_or(false);
+        if is_new && self.queue.send(domain.to_string()).is_err() {
+            warn!("El trabajador de favicons no está activo");
+        }
+    }
+
+    fn icon_path(&self, domain: &str) -> PathBuf {
+        self.cache_dir.join(format!("{}.icon", domain))
+    }
+
+    fn missing_path(&self, domain: &str) -> PathBuf {
+        self.cache_dir.join(format!("{}.missing", domain))
+    }
+
+    async fn run(self, app: tauri::AppHandle, client: reqwest::Client, mut receiver: mpsc::UnboundedReceiver<String>) {
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_FETCHES));
+        while let Some(domain) = receiver.recv().await {
+            let Ok(permit) = permits.clone().acquire_owned().await else { break };
+            let service = self.clone();
+            let client = client.clone();
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let found = service.fetch_and_store(&client, &domain).await;
+                if let Ok(mut pending) = service.pending.lock() {
+                    pending.remove(&domain);
+                }
+                if found {
+                    let _ = app.emit_all(READY_EVENT, &domain);
+                }
+                drop(permit);
+            });
+        }
+    }
+
+    /// Descarga el icono y lo guarda; devuelve si se encontró alguno
+    async fn fetch_and_store(&self, client: &reqwest::Client, domain: &str) -> bool {
+        match fetch_icon(client, domain).await {
+            Ok(Some(data)) => {
+                let _ = std::fs::remove_file(self.missing_path(domain));
+                if let Err(e) = std::fs::write(self.icon_path(domain), data) {
+                    warn!("No se pudo guardar el favicon de {}: {}", domain, e);
+                    return false;
+                }
+                info!("Favicon de {} guardado en caché", domain);
+                true
+            }
+            Ok(None) => {
+                info!("{} no tiene favicon", domain);
+                let _ = std::fs::write(self.missing_path(domain), []);
+                false
+            }
+            Err(e) => {
+                // Errores de red: no se marca como faltante para reintentar más adelante
+                warn!("Error al descargar el favicon de {}: {}", domain, e);
+                false
+            }
+        }
+    }
+}
+
+/// Extrae el dominio de la URL de una entrada, apto como nombre de archivo
+pub fn domain_from_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    let parsed = reqwest::Url::parse(url)
+        .ok()
+        .filter(|parsed| parsed.has_host())
+        .or_else(|| reqwest::Url::parse(&format!("https://{}", url)).ok())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = parsed.host_str()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let is_safe = !host.starts_with('.')
+        && host.contains('.')
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    is_safe.then(|| host.to_string())
+}
+
+async fn fetch_icon(client: &reqwest::Client, domain: &str) -> Result<Option<Vec<u8>>> {
+    let page_url = reqwest::Url::parse(&format!("https://{}/", domain))?;
+    let mut candidates = Vec::new();
+
+    // Los iconos declarados en la página tienen prioridad sobre /favicon.ico
+    let response = client.get(page_url.clone()).send().await?;
+    let base_url = response.url().clone();
+    if response.status().is_success() {
+        let html = read_limited(response, MAX_HTML_BYTES).await?;
+        candidates = parse_icon_links(&String::from_utf8_lossy(&html))
+            .into_iter()
+            .filter_map(|href| base_url.join(&href).ok())
+            .collect();
+    }
+    candidates.push(base_url.join("/favicon.ico")?);
+
+    for candidate in candidates {
+        let Ok(response) = client.get(candidate.clone()).send().await else { continue };
+        if !response.status().is_success() {
+            continue;
+        }
+        let Ok(data) = read_limited(response, MAX_ICON_BYTES).await else { continue };
+        if detect_mime(&data).is_some() {
+            return Ok(Some(data));
+        }
+    }
+    Ok(None)
+}
+
+/// Lee el cuerpo de la respuesta sin superar `limit` bytes
+async fn read_limited(mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() > limit {
+            return Err(anyhow!("La respuesta supera {} bytes", limit));
+        }
+    }
+    Ok(body)
+}
+
+/// Busca las etiquetas `<link rel="icon">` del HTML, de la mejor a la peor
+fn parse_icon_links(html: &str) -> Vec<String> {
+    let lower = html.to_ascii_lowercase();
+    let mut icons = Vec::new();
+    let mut position = 0;
+    while let Some(start) = lower[position..].find("<link") {
+        let start = position + start;
+        let Some(end) = lower[start..].find('>') else { break };
+        let tag = &html[start..start + end];
+        position = start + end;
+
+        let rel = attribute(tag, "rel").unwrap_or_default().to_lowercase();
+        let Some(href) = attribute(tag, "href") else { continue };
+        if !rel.split_whitespace().any(|r| r == "icon" || r == "apple-touch-icon") {
+            continue;
+        }
+        // Preferir iconos grandes: se muestran nítidos en pantallas de alta densidad
+        let size = attribute(tag, "sizes")
+            .and_then(|sizes| sizes.split(['x', 'X']).next().and_then(|s| s.trim().parse::<u32>().ok()))
+            .unwrap_or(if rel.contains("apple-touch-icon") { 180 } else { 32 });
+        let score = if size > 256 { 0 } else { size };
+        icons.push((score, href));
+    }
+    icons.sort_by(|a, b| b.0.cmp(&a.0));
+    icons.into_iter().map(|(_, href)| href).collect()
+}
+
+/// Valor de un atributo HTML, con o sin comillas
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(name) {
+        let start = search + found;
+        search = start + name.len();
+        let preceded_by_space = lower[..start].ends_with(|c: char| c.is_whitespace());
+        let rest = lower[search..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let value = match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+            _ => value.split(char::is_whitespace).next()?,
+        };
+        return Some(value.to_string());
+    }
+    None
+}
+
+/// Tipo MIME según la firma del archivo; `None` si no es una imagen aceptada
+pub fn detect_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if data.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        Some("image/x-icon")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.len() > 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        let head = String::from_utf8_lossy(&data[..data.len().min(512)]).to_lowercase();
+        head.contains("<svg").then_some("image/svg+xml")
+    }
+}
+
+fn is_older_than(path: &std::path::Path, ttl: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|age| age > ttl)
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_from_url() {
+        assert_eq!(domain_from_url("https://www.GitHub.com/login"), Some("github.com".to_string()));
+        assert_eq!(domain_from_url("accounts.google.com"), Some("accounts.google.com".to_string()));
+        assert_eq!(domain_from_url("ftp://example.com"), None);
+        assert_eq!(domain_from_url(""), None);
+    }
+
+    #[test]
+    fn test_parse_icon_links_prefers_larger_icons() {
+        let html = r#"<head>
+            <link rel="stylesheet" href="/style.css">
+            <LINK REL="shortcut icon" href="/favicon-16.png" sizes="16x16">
+            <link rel=apple-touch-icon href=/touch.png>
+            <link href='/icon-64.png' rel='icon' sizes='64x64'/>
+        </head>"#;
+        assert_eq!(parse_icon_links(html), vec!["/touch.png", "/icon-64.png", "/favicon-16.png"]);
+    }
+
+    #[test]
+    fn test_detect_mime() {
+        assert_eq!(detect_mime(&[0x89, b'P', b'N', b'G', 0x0D]), Some("image/png"));
+        assert_eq!(detect_mime(b"<?xml version=\"1.0\"?><svg></svg>"), Some("image/svg+xml"));
+        assert_eq!(detect_mime(b"<!DOCTYPE html><html>"), None);
+    }
+}
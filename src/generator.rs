@@ -0,0 +1,334 @@
+//! Generación de contraseñas
+//!
+//! Cada modo devuelve la contraseña junto con su entropía en bits, calculada a partir
+//! de las decisiones aleatorias que se tomaron al generarla, para que el usuario
+//! pueda comparar el costo de una contraseña fácil de escribir con una aleatoria.
+
+use crate::error::{AppError, AppResult};
+use crate::i18n::Message;
+use crate::models::{
+    GeneratedPassword, GeneratedUsername, GenerationMode, PasswordGenerationRequest, PasswordPolicy,
+    UsernameGenerationRequest,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+pub const MIN_LENGTH: usize = 4;
+pub const MAX_LENGTH: usize = 128;
+pub const MIN_PIN_LENGTH: usize = 4;
+pub const MAX_PIN_LENGTH: usize = 16;
+
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const NUMBERS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.?";
+/// Caracteres que se confunden entre sí al leerlos
+const SIMILAR: &str = "Il1O0o";
+
+const CONSONANTS: &str = "bcdfghjklmnprstvz";
+const VOWELS: &str = "aeiou";
+/// Símbolos fáciles de encontrar en cualquier distribución de teclado
+const PRONOUNCEABLE_SYMBOLS: &str = "!@#$%&*?";
+
+const ADJECTIVES: &[&str] = &[
+    "agile", "amber", "ancient", "bold", "brave", "bright", "calm", "clever",
+    "cosmic", "crimson", "curious", "daring", "dusty", "eager", "electric", "fancy",
+    "fierce", "frosty", "gentle", "giant", "golden", "happy", "hidden", "humble",
+    "icy", "jolly", "keen", "lazy", "lively", "lucky", "lunar", "magic",
+    "mellow", "misty", "noble", "odd", "polar", "proud", "quick", "quiet",
+    "rapid", "rusty", "shiny", "silent", "silver", "sleepy", "sly", "smooth",
+    "solar", "sonic", "spicy", "steady", "stormy", "sunny", "swift", "tidy",
+    "tiny", "urban", "velvet", "vivid", "wild", "windy", "wise", "zesty",
+];
+
+const NOUNS: &[&str] = &[
+    "badger", "beacon", "bison", "canyon", "cedar", "comet", "condor", "coral",
+    "coyote", "dolphin", "dragon", "eagle", "ember", "falcon", "fern", "fox",
+    "galaxy", "gecko", "glacier", "harbor", "hawk", "heron", "island", "jaguar",
+    "koala", "lagoon", "lemur", "lion", "llama", "lynx", "maple", "meadow",
+    "meteor", "moose", "nebula", "otter", "owl", "panda", "parrot", "pebble",
+    "pine", "planet", "puma", "quartz", "raven", "reef", "river", "rocket",
+    "sailor", "salmon", "sparrow", "summit", "thunder", "tiger", "toucan", "tundra",
+    "turtle", "valley", "viper", "walrus", "willow", "wizard", "wolf", "yak",
+];
+
+/// Caracteres de `source` que se pueden usar, sin los similares (si se pidió) ni los prohibidos
+fn charset(source: &str, exclude_similar: bool, forbidden: &str) -> Vec<char> {
+    source
+        .chars()
+        .filter(|c| !exclude_similar || !SIMILAR.contains(*c))
+        .filter(|c| !forbidden.contains(*c))
+        .collect()
+}
+
+/// Genera una contraseña según el modo y las opciones pedidas
+pub fn generate(request: &PasswordGenerationRequest) -> AppResult<GeneratedPassword> {
+    generate_excluding(request, "")
+}
+
+/// Genera una contraseña que cumpla la política del sitio: ajusta la longitud a sus
+/// límites, activa los tipos de carácter obligatorios y evita los prohibidos
+pub fn generate_for_policy(
+    request: &PasswordGenerationRequest,
+    policy: Option<&PasswordPolicy>,
+) -> AppResult<GeneratedPassword> {
+    let Some(policy) = policy else {
+        return generate(request);
+    };
+    let mut request = request.clone();
+    if let Some(max_length) = policy.max_length {
+        request.length = request.length.min(max_length);
+    }
+    if let Some(min_length) = policy.min_length {
+        request.length = request.length.max(min_length);
+    }
+    request.include_uppercase |= policy.require_uppercase;
+    request.include_lowercase |= policy.require_lowercase;
+    request.include_numbers |= policy.require_numbers;
+    request.include_symbols |= policy.require_symbols;
+    generate_excluding(&request, &policy.forbidden_characters)
+}
+
+fn generate_excluding(request: &PasswordGenerationRequest, forbidden: &str) -> AppResult<GeneratedPassword> {
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&request.length) {
+        return Err(AppError::validation(
+            Message::new("errors.passwordLength").with("min", MIN_LENGTH).with("max", MAX_LENGTH),
+        ));
+    }
+    match request.mode {
+        GenerationMode::Random => random(request, forbidden),
+        GenerationMode::Pronounceable => pronounceable(request, forbidden),
+    }
+}
+
+/// Caracteres aleatorios de los conjuntos elegidos, con al menos uno de cada conjunto
+fn random(request: &PasswordGenerationRequest, forbidden: &str) -> AppResult<GeneratedPassword> {
+    let sets: Vec<Vec<char>> = [
+        (request.include_uppercase, UPPERCASE),
+        (request.include_lowercase, LOWERCASE),
+        (request.include_numbers, NUMBERS),
+        (request.include_symbols, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, source)| charset(source, request.exclude_similar, forbidden))
+    .collect();
+    if sets.is_empty() || sets.iter().any(|set| set.is_empty()) {
+        return Err(AppError::validation("errors.generatorNoCharset"));
+    }
+
+    let mut rng = rand::thread_rng();
+    let pool: Vec<char> = sets.concat();
+    let mut password: Vec<char> = sets.iter().filter_map(|set| set.choose(&mut rng).copied()).collect();
+    while password.len() < request.length {
+        password.push(pool[rng.gen_range(0..pool.len())]);
+    }
+    password.shuffle(&mut rng);
+
+    Ok(GeneratedPassword {
+        password: password.into_iter().collect(),
+        // Aproximación: exigir un carácter de cada conjunto apenas reduce el espacio
+        entropy_bits: request.length as f64 * (pool.len() as f64).log2(),
+    })
+}
+
+/// Sílabas alternando consonantes y vocales, con una mayúscula, dos dígitos y un símbolo
+/// opcionales al final para cumplir las políticas habituales de los sitios
+fn pronounceable(request: &PasswordGenerationRequest, forbidden: &str) -> AppResult<GeneratedPassword> {
+    let consonants = charset(CONSONANTS, request.exclude_similar, forbidden);
+    let vowels = charset(VOWELS, false, forbidden);
+    let digits = charset(NUMBERS, request.exclude_similar, forbidden);
+    let symbols = charset(PRONOUNCEABLE_SYMBOLS, false, forbidden);
+    let missing_set = consonants.is_empty()
+        || vowels.is_empty()
+        || (request.include_numbers && digits.is_empty())
+        || (request.include_symbols && symbols.is_empty());
+    if missing_set {
+        return Err(AppError::validation("errors.generatorNoCharset"));
+    }
+
+    let suffix_len = if request.include_numbers { 2 } else { 0 } + usize::from(request.include_symbols);
+    let letters_len = request.length.saturating_sub(suffix_len).max(1);
+
+    let mut rng = rand::thread_rng();
+    let mut entropy_bits = 1.0; // empezar por consonante o por vocal
+    let mut use_consonant = rng.gen_bool(0.5);
+    let mut password: Vec<char> = Vec::with_capacity(request.length);
+    let uppercase_allowed = |c: char| !forbidden.contains(c.to_ascii_uppercase());
+    for _ in 0..letters_len {
+        let set = if use_consonant { &consonants } else { &vowels };
+        password.push(set[rng.gen_range(0..set.len())]);
+        entropy_bits += (set.len() as f64).log2();
+        use_consonant = !use_consonant;
+    }
+    let capitalizable: Vec<usize> = (0..password.len()).filter(|i| uppercase_allowed(password[*i])).collect();
+    if request.include_uppercase && !capitalizable.is_empty() {
+        let position = capitalizable[rng.gen_range(0..capitalizable.len())];
+        password[position] = password[position].to_ascii_uppercase();
+        entropy_bits += (capitalizable.len() as f64).log2();
+    }
+    if request.include_numbers {
+        for _ in 0..2 {
+            password.push(digits[rng.gen_range(0..digits.len())]);
+            entropy_bits += (digits.len() as f64).log2();
+        }
+    }
+    if request.include_symbols {
+        password.push(symbols[rng.gen_range(0..symbols.len())]);
+        entropy_bits += (symbols.len() as f64).log2();
+    }
+    password.truncate(request.length);
+
+    Ok(GeneratedPassword { password: password.into_iter().collect(), entropy_bits })
+}
+
+/// PIN trivial que cualquiera probaría primero: todos los dígitos iguales o una secuencia
+fn is_trivial_pin(pin: &[u8]) -> bool {
+    let steps: Vec<i16> = pin.windows(2).map(|pair| pair[1] as i16 - pair[0] as i16).collect();
+    steps.iter().all(|step| *step == 0) || steps.iter().all(|step| *step == 1) || steps.iter().all(|step| *step == -1)
+}
+
+/// PIN numérico aleatorio, descartando los triviales (0000, 1234, 9876...)
+pub fn generate_pin(length: usize) -> AppResult<GeneratedPassword> {
+    if !(MIN_PIN_LENGTH..=MAX_PIN_LENGTH).contains(&length) {
+        return Err(AppError::validation(
+            Message::new("errors.passwordLength").with("min", MIN_PIN_LENGTH).with("max", MAX_PIN_LENGTH),
+        ));
+    }
+    let mut rng = rand::thread_rng();
+    let pin = loop {
+        let digits: Vec<u8> = (0..length).map(|_| rng.gen_range(0..10)).collect();
+        if !is_trivial_pin(&digits) {
+            break digits;
+        }
+    };
+    // 10 PIN con todos los dígitos iguales y las secuencias ascendentes y descendentes que caben
+    let excluded = 10.0 + 2.0 * 11usize.saturating_sub(length) as f64;
+    Ok(GeneratedPassword {
+        password: pin.iter().map(|digit| char::from(b'0' + digit)).collect(),
+        entropy_bits: (10f64.powi(length as i32) - excluded).log2(),
+    })
+}
+
+/// Nombre de usuario del estilo adjetivo-sustantivo-número ("swift_otter42")
+pub fn generate_username(request: &UsernameGenerationRequest) -> GeneratedUsername {
+    let mut rng = rand::thread_rng();
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+    };
+    let pick = |words: &[&str], rng: &mut rand::rngs::ThreadRng| {
+        let word = words.choose(rng).copied().unwrap_or_default();
+        if request.capitalize { capitalize(word) } else { word.to_string() }
+    };
+
+    let mut username = format!("{}{}{}", pick(ADJECTIVES, &mut rng), request.separator, pick(NOUNS, &mut rng));
+    let mut entropy_bits = (ADJECTIVES.len() as f64).log2() + (NOUNS.len() as f64).log2();
+    if request.include_number {
+        username.push_str(&rng.gen_range(0..100).to_string());
+        entropy_bits += 100f64.log2();
+    }
+    GeneratedUsername { username, entropy_bits }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(mode: GenerationMode, length: usize) -> PasswordGenerationRequest {
+        PasswordGenerationRequest {
+            length,
+            include_uppercase: true,
+            include_lowercase: true,
+            include_numbers: true,
+            include_symbols: true,
+            exclude_similar: true,
+            mode,
+        }
+    }
+
+    #[test]
+    fn test_random_includes_every_set() {
+        let generated = generate(&request(GenerationMode::Random, 8)).unwrap();
+        let password = generated.password;
+        assert_eq!(password.chars().count(), 8);
+        assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+        assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+        assert!(password.chars().any(|c| c.is_ascii_digit()));
+        assert!(password.chars().any(|c| SYMBOLS.contains(c)));
+        assert!(!password.chars().any(|c| SIMILAR.contains(c)));
+    }
+
+    #[test]
+    fn test_pronounceable_alternates_consonants_and_vowels() {
+        let generated = generate(&request(GenerationMode::Pronounceable, 16)).unwrap();
+        let password: Vec<char> = generated.password.chars().collect();
+        assert_eq!(password.len(), 16);
+        let letters: Vec<char> = password[..13].iter().map(|c| c.to_ascii_lowercase()).collect();
+        assert!(letters.windows(2).all(|pair| VOWELS.contains(pair[0]) != VOWELS.contains(pair[1])));
+        assert!(password[13..15].iter().all(|c| c.is_ascii_digit()));
+        // Más fácil de escribir, pero con menos entropía que una aleatoria del mismo largo
+        let random = generate(&request(GenerationMode::Random, 16)).unwrap();
+        assert!(generated.entropy_bits < random.entropy_bits);
+        assert!(generated.entropy_bits > 40.0);
+    }
+
+    #[test]
+    fn test_rejects_invalid_requests() {
+        assert!(generate(&request(GenerationMode::Random, 2)).is_err());
+        let empty = PasswordGenerationRequest {
+            include_uppercase: false,
+            include_lowercase: false,
+            include_numbers: false,
+            include_symbols: false,
+            ..request(GenerationMode::Random, 12)
+        };
+        assert!(generate(&empty).is_err());
+    }
+
+    #[test]
+    fn test_policy_limits_length_and_characters() {
+        let policy = PasswordPolicy {
+            id: "1".into(),
+            domain: "banco.com".into(),
+            min_length: None,
+            max_length: Some(10),
+            forbidden_characters: "!@#$%^&*()-_=+[]{};:,".into(),
+            require_uppercase: false,
+            require_lowercase: false,
+            require_numbers: true,
+            require_symbols: false,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        let mut base = request(GenerationMode::Random, 20);
+        base.include_numbers = false;
+        for _ in 0..50 {
+            let password = generate_for_policy(&base, Some(&policy)).unwrap().password;
+            assert_eq!(password.chars().count(), 10);
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+            assert!(!password.chars().any(|c| policy.forbidden_characters.contains(c)));
+        }
+    }
+
+    #[test]
+    fn test_pin_has_only_digits_and_is_not_trivial() {
+        for _ in 0..100 {
+            let pin = generate_pin(4).unwrap().password;
+            assert_eq!(pin.len(), 4);
+            assert!(pin.chars().all(|c| c.is_ascii_digit()));
+            assert!(!is_trivial_pin(&pin.bytes().map(|b| b - b'0').collect::<Vec<_>>()));
+        }
+        assert!(is_trivial_pin(&[9, 8, 7, 6]));
+        assert!(generate_pin(3).is_err());
+    }
+
+    #[test]
+    fn test_username_uses_separator() {
+        let request = UsernameGenerationRequest { separator: "-".into(), capitalize: false, include_number: false };
+        let generated = generate_username(&request);
+        let (adjective, noun) = generated.username.split_once('-').unwrap();
+        assert!(ADJECTIVES.contains(&adjective) && NOUNS.contains(&noun));
+        assert_eq!(generated.entropy_bits, 12.0);
+    }
+}
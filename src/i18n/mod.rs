@@ -0,0 +1,165 @@
+//! Localización de los mensajes que el backend devuelve al frontend
+//!
+//! Los errores y mensajes de estado se identifican con una clave
+//! (`errors.locked`, `status.migrationsOk`, ...) y parámetros opcionales.
+//! El texto final se obtiene de los catálogos por idioma (`es.json`, `en.json`)
+//! según el idioma activo, que el frontend elige con `set_locale`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Idiomas soportados por los catálogos del backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Es,
+    En,
+}
+
+impl Locale {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::Es => "es",
+            Locale::En => "en",
+        }
+    }
+
+    /// Interpreta códigos como `es`, `en-US` o `es_AR`
+    pub fn from_code(code: &str) -> Option<Self> {
+        let main = code.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        match main.as_str() {
+            "es" => Some(Locale::Es),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+}
+
+/// Parámetro de un mensaje: texto literal u otra clave que también se traduce
+#[derive(Debug, Clone)]
+pub enum Param {
+    Text(String),
+    Key(&'static str),
+}
+
+/// Mensaje traducible: clave del catálogo más sus parámetros
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub key: &'static str,
+    pub params: Vec<(&'static str, Param)>,
+}
+
+impl Message {
+    pub fn new(key: &'static str) -> Self {
+        Self { key, params: Vec::new() }
+    }
+
+    /// Agrega un parámetro de texto literal
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.params.push((name, Param::Text(value.to_string())));
+        self
+    }
+
+    /// Agrega un parámetro que es a su vez una clave del catálogo
+    pub fn with_key(mut self, name: &'static str, key: &'static str) -> Self {
+        self.params.push((name, Param::Key(key)));
+        self
+    }
+
+    /// Parámetros ya resueltos en el idioma indicado
+    pub fn resolved_params(&self, locale: Locale) -> HashMap<&'static str, String> {
+        self.params
+            .iter()
+            .map(|(name, param)| {
+                let value = match param {
+                    Param::Text(text) => text.clone(),
+                    Param::Key(key) => lookup(locale, key),
+                };
+                (*name, value)
+            })
+            .collect()
+    }
+
+    /// Texto del mensaje en el idioma indicado
+    pub fn render(&self, locale: Locale) -> String {
+        let mut text = lookup(locale, self.key);
+        for (name, value) in self.resolved_params(locale) {
+            text = text.replace(&format!("{{{}}}", name), &value);
+        }
+        text
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(key: &'static str) -> Self {
+        Message::new(key)
+    }
+}
+
+static CURRENT_LOCALE: RwLock<Locale> = RwLock::new(Locale::Es);
+
+/// Idioma activo para los mensajes del backend
+pub fn current_locale() -> Locale {
+    CURRENT_LOCALE.read().map(|locale| *locale).unwrap_or_default()
+}
+
+pub fn set_locale(locale: Locale) {
+    if let Ok(mut current) = CURRENT_LOCALE.write() {
+        *current = locale;
+    }
+}
+
+/// Traduce una clave sin parámetros en el idioma activo
+pub fn t(key: &'static str) -> String {
+    lookup(current_locale(), key)
+}
+
+fn catalog(locale: Locale) -> &'static HashMap<String, String> {
+    static ES: OnceLock<HashMap<String, String>> = OnceLock::new();
+    static EN: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+    match locale {
+        Locale::Es => ES.get_or_init(|| parse_catalog(include_str!("es.json"))),
+        Locale::En => EN.get_or_init(|| parse_catalog(include_str!("en.json"))),
+    }
+}
+
+fn parse_catalog(source: &str) -> HashMap<String, String> {
+    serde_json::from_str(source).unwrap_or_else(|e| {
+        log::error!("Catálogo de idioma inválido: {}", e);
+        HashMap::new()
+    })
+}
+
+/// Busca la clave en el idioma pedido, luego en español y si no existe devuelve la clave
+fn lookup(locale: Locale, key: &str) -> String {
+    catalog(locale)
+        .get(key)
+        .or_else(|| catalog(Locale::Es).get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_have_same_keys() {
+        let mut es: Vec<_> = catalog(Locale::Es).keys().collect();
+        let mut en: Vec<_> = catalog(Locale::En).keys().collect();
+        es.sort();
+        en.sort();
+        assert!(!es.is_empty());
+        assert_eq!(es, en);
+    }
+
+    #[test]
+    fn test_render_with_params() {
+        let message = Message::new("errors.decryptField").with_key("field", "fields.title");
+        assert_eq!(message.render(Locale::Es), "Error al desencriptar título");
+        assert_eq!(message.render(Locale::En), "Could not decrypt the title");
+    }
+}
@@ -0,0 +1,224 @@
+//! Exportaciones de Bitwarden en JSON y CSV
+//!
+//! Las carpetas pasan a ser categorías. Los elementos de tipo login y nota segura
+//! se importan; las tarjetas e identidades no tienen equivalente y se informan
+//! como fallidas. Las exportaciones JSON protegidas con contraseña no se pueden
+//! leer: hay que exportar sin encriptar.
+
+use super::{csv, finish, ImportInput, ImportRow, ImportedEntry, Importer};
+use crate::i18n::Message;
+use crate::models::ImportFormat;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const TYPE_LOGIN: u64 = 1;
+const TYPE_SECURE_NOTE: u64 = 2;
+const TYPE_CARD: u64 = 3;
+const TYPE_IDENTITY: u64 = 4;
+/// Campo personalizado vinculado a otro campo: no tiene valor propio
+const FIELD_TYPE_LINKED: u64 = 3;
+
+pub struct JsonImporter;
+
+impl Importer for JsonImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::BitwardenJson
+    }
+
+    fn name(&self) -> &'static str {
+        "Bitwarden (JSON)"
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn parse(&self, input: &ImportInput) -> Result<Vec<ImportRow>, Message> {
+        parse_json(input.text()?)
+    }
+}
+
+pub struct CsvImporter;
+
+impl Importer for CsvImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::BitwardenCsv
+    }
+
+    fn name(&self) -> &'static str {
+        "Bitwarden (CSV)"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn parse(&self, input: &ImportInput) -> Result<Vec<ImportRow>, Message> {
+        parse_csv(input.text()?)
+    }
+}
+
+pub fn parse_json(data: &str) -> Result<Vec<ImportRow>, Message> {
+    let root: Value = serde_json::from_str(data)
+        .map_err(|e| Message::new("errors.importFormat").with("error", e))?;
+    if root.get("encrypted").and_then(Value::as_bool).unwrap_or(false) {
+        return Err(Message::new("errors.importEncrypted"));
+    }
+    let items = root.get("items")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Message::new("errors.importFormat").with("error", "items"))?;
+
+    let folders: HashMap<&str, &str> = root.get("folders")
+        .and_then(Value::as_array)
+        .map(|folders| {
+            folders.iter()
+                .filter_map(|folder| Some((folder.get("id")?.as_str()?, folder.get("name")?.as_str()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(items.iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let row = index + 1;
+            let title = text(item, "name");
+            match json_item(item, &folders) {
+                Ok(entry) => ImportRow::new(row, finish(entry)),
+                Err(error) => ImportRow::failed(row, title, error),
+            }
+        })
+        .collect())
+}
+
+fn json_item(item: &Value, folders: &HashMap<&str, &str>) -> Result<ImportedEntry, Message> {
+    let kind = item.get("type").and_then(Value::as_u64).unwrap_or(TYPE_LOGIN);
+    match kind {
+        TYPE_LOGIN | TYPE_SECURE_NOTE => {}
+        TYPE_CARD => return Err(Message::new("errors.importUnsupportedType").with("type", "card")),
+        TYPE_IDENTITY => return Err(Message::new("errors.importUnsupportedType").with("type", "identity")),
+        other => return Err(Message::new("errors.importUnsupportedType").with("type", other)),
+    }
+
+    let login = item.get("login");
+    let custom_fields = item.get("fields")
+        .and_then(Value::as_array)
+        .map(|fields| {
+            fields.iter()
+                .filter(|field| field.get("type").and_then(Value::as_u64) != Some(FIELD_TYPE_LINKED))
+                .filter_map(|field| Some((text(field, "name")?, text(field, "value").unwrap_or_default())))
+                .collect()
+        })
+        .unwrap_or_default();
+    let favorite = item.get("favorite").and_then(Value::as_bool).unwrap_or(false);
+
+    Ok(ImportedEntry {
+        title: text(item, "name").unwrap_or_default(),
+        username: login.and_then(|login| text(login, "username")).unwrap_or_default(),
+        password: login.and_then(|login| text(login, "password")).unwrap_or_default(),
+        url: login
+            .and_then(|login| login.get("uris"))
+            .and_then(Value::as_array)
+            .and_then(|uris| uris.iter().find_map(|uri| text(uri, "uri"))),
+        notes: text(item, "notes"),
+        folder: item.get("folderId")
+            .and_then(Value::as_str)
+            .and_then(|id| folders.get(id))
+            .map(|name| folder_path(name))
+            .unwrap_or_default(),
+        tags: if favorite { vec!["favorite".to_string()] } else { Vec::new() },
+        totp: login.and_then(|login| text(login, "totp")),
+        custom_fields,
+        attachments: Vec::new(),
+    })
+}
+
+pub fn parse_csv(data: &str) -> Result<Vec<ImportRow>, Message> {
+    let (header, rows) = super::csv_table(data, &["name", "login_password"])?;
+    Ok(rows.iter()
+        .enumerate()
+        .map(|(index, fields)| {
+            let row = index + 1;
+            let record = csv::Record::new(&header, fields);
+            let kind = record.get("type").trim();
+            if !(kind.is_empty() || kind.eq_ignore_ascii_case("login") || kind.eq_ignore_ascii_case("note")) {
+                let error = Message::new("errors.importUnsupportedType").with("type", kind);
+                return ImportRow::failed(row, record.optional("name"), error);
+            }
+            let entry = ImportedEntry {
+                title: record.get("name").to_string(),
+                username: record.get("login_username").to_string(),
+                password: record.get("login_password").to_string(),
+                // Varias URIs van separadas por comas; se usa la primera
+                url: record.get("login_uri").split(',').map(str::trim).find(|uri| !uri.is_empty()).map(str::to_string),
+                notes: record.optional("notes"),
+                folder: folder_path(record.get("folder")),
+                tags: if record.get("favorite").trim() == "1" { vec!["favorite".to_string()] } else { Vec::new() },
+                totp: record.optional("login_totp"),
+                custom_fields: record.get("fields")
+                    .lines()
+                    .filter_map(|line| line.split_once(": "))
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                attachments: Vec::new(),
+            };
+            ImportRow::new(row, finish(entry))
+        })
+        .collect())
+}
+
+/// Bitwarden anida las carpetas con `/` en el nombre
+fn folder_path(name: &str) -> Vec<String> {
+    name.split('/').map(str::to_string).collect()
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value.get(key)
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_maps_folders_fields_and_unsupported_types() {
+        let data = r#"{
+            "encrypted": false,
+            "folders": [{"id": "f1", "name": "Trabajo"}],
+            "items": [
+                {"type": 1, "name": "Correo", "folderId": "f1", "notes": "nota",
+                 "fields": [{"name": "PIN", "value": "1234", "type": 1}],
+                 "login": {"username": "ana", "password": "secreta", "totp": "JBSWY3DPEHPK3PXP",
+                           "uris": [{"uri": "https://mail.example.com"}]}},
+                {"type": 3, "name": "Visa", "card": {}}
+            ]
+        }"#;
+        let rows = parse_json(data).unwrap();
+        assert_eq!(rows.len(), 2);
+        let entry = rows[0].result.as_ref().unwrap();
+        assert_eq!(entry.folder, vec!["Trabajo"]);
+        assert_eq!(entry.url.as_deref(), Some("https://mail.example.com"));
+        assert_eq!(entry.notes_with_fields().as_deref(), Some("nota\n\nPIN: 1234"));
+        assert!(rows[1].result.is_err());
+        assert_eq!(rows[1].title.as_deref(), Some("Visa"));
+    }
+
+    #[test]
+    fn test_encrypted_json_is_rejected() {
+        assert!(parse_json(r#"{"encrypted": true, "passwordProtected": true}"#).is_err());
+    }
+
+    #[test]
+    fn test_csv_reads_bitwarden_columns() {
+        let data = "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp\n\
+                    Social,1,login,Foro,,\"color: azul\",0,\"https://foro.example.com,https://m.foro.example.com\",ana,\"p,ss\",\n";
+        let rows = parse_csv(data).unwrap();
+        let entry = rows[0].result.as_ref().unwrap();
+        assert_eq!(entry.password, "p,ss");
+        assert_eq!(entry.url.as_deref(), Some("https://foro.example.com"));
+        assert_eq!(entry.tags, vec!["favorite"]);
+        assert_eq!(entry.custom_fields, vec![("color".to_string(), "azul".to_string())]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Navegadores basados en Chromium: Chrome, Edge, Brave y Chromium
+//!
+//! Las contraseñas están en la base SQLite `Login Data` de cada perfil, con un
+//! prefijo que indica cómo se encriptaron:
+//! - `v10` en Linux: AES-128-CBC con la clave fija `peanuts`
+//! - `v11` en Linux: AES-128-CBC con la contraseña guardada en el Secret Service
+//! - `v10` en macOS: AES-128-CBC con la contraseña del Keychain
+//! - `v10`/`v11` en Windows: AES-256-GCM con la clave de `Local State`,
+//!   protegida con DPAPI
+//! - `v20` en Windows: cifrado ligado a la aplicación (Chrome 127+), que solo
+//!   puede desencriptar el propio navegador: no soportado
+
+use super::{browser_name, site_title, ProfileCopy};
+use crate::i18n::Message;
+use crate::import::{finish, ImportRow, ImportedEntry};
+use crate::models::{BrowserKind, BrowserProfile};
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use log::{info, warn};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+pub const BROWSERS: &[BrowserKind] = &[BrowserKind::Chrome, BrowserKind::Edge, BrowserKind::Brave, BrowserKind::Chromium];
+
+const LOGIN_DATA: &str = "Login Data";
+const LOCAL_STATE: &str = "Local State";
+/// Desde esta versión de `Login Data` el texto lleva delante el SHA-256 del dominio
+const DOMAIN_HASH_VERSION: i64 = 24;
+const DOMAIN_HASH_LEN: usize = 32;
+
+const CBC_SALT: &[u8] = b"saltysalt";
+const CBC_IV: [u8; 16] = [b' '; 16];
+
+/// Carpeta de datos del navegador, si está instalado
+pub fn user_data_dir(browser: BrowserKind) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let (base, relative) = (dirs::data_local_dir()?, match browser {
+        BrowserKind::Chrome => "Google/Chrome/User Data",
+        BrowserKind::Edge => "Microsoft/Edge/User Data",
+        BrowserKind::Brave => "BraveSoftware/Brave-Browser/User Data",
+        BrowserKind::Chromium => "Chromium/User Data",
+        BrowserKind::Firefox => return None,
+    });
+    #[cfg(target_os = "macos")]
+    let (base, relative) = (dirs::config_dir()?, match browser {
+        BrowserKind::Chrome => "Google/Chrome",
+        BrowserKind::Edge => "Microsoft Edge",
+        BrowserKind::Brave => "BraveSoftware/Brave-Browser",
+        BrowserKind::Chromium => "Chromium",
+        BrowserKind::Firefox => return None,
+    });
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let (base, relative) = (dirs::config_dir()?, match browser {
+        BrowserKind::Chrome => "google-chrome",
+        BrowserKind::Edge => "microsoft-edge",
+        BrowserKind::Brave => "BraveSoftware/Brave-Browser",
+        BrowserKind::Chromium => "chromium",
+        BrowserKind::Firefox => return None,
+    });
+    let dir = base.join(relative);
+    dir.is_dir().then_some(dir)
+}
+
+/// Perfiles con una base `Login Data`. Los nombres visibles están en `Local State`.
+pub fn profiles(browser: BrowserKind, user_data: &Path) -> Vec<BrowserProfile> {
+    let local_state: Value = std::fs::read(user_data.join(LOCAL_STATE))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default();
+    let Ok(entries) = std::fs::read_dir(user_data) else { return Vec::new() };
+
+    let mut profiles: Vec<BrowserProfile> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.join(LOGIN_DATA).is_file())
+        .map(|path| {
+            let dir = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let name = local_state.pointer(&format!("/profile/info_cache/{}/name", dir.replace('~', "~0").replace('/', "~1")))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or(dir);
+            BrowserProfile { browser, name, path: path.to_string_lossy().into_owned() }
+        })
+        .collect();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    profiles
+}
+
+pub fn read(browser: BrowserKind, profile: &Path) -> Result<Vec<ImportRow>, Message> {
+    let user_data = profile.parent().unwrap_or(profile);
+    let keys = Keys::load(browser, user_data)?;
+    let copy = ProfileCopy::new(&profile.join(LOGIN_DATA))?;
+    let conn = copy.open()?;
+    let profile_error = |e: rusqlite::Error| Message::new("errors.importBrowserProfile").with("error", e);
+
+    let version: i64 = conn.query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|version| version.parse().ok())
+        .unwrap_or(0);
+    let mut stmt = conn.prepare(
+        "SELECT origin_url, username_value, password_value FROM logins WHERE blacklisted_by_user = 0 ORDER BY id",
+    ).map_err(profile_error)?;
+    let logins = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Vec<u8>>(2)?)))
+        .map_err(profile_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(profile_error)?;
+    info!("{} contraseñas guardadas en el perfil de {} (Login Data v{})", logins.len(), browser_name(browser), version);
+
+    Ok(logins.into_iter()
+        .enumerate()
+        .map(|(index, (url, username, encrypted))| {
+            let row = index + 1;
+            let title = site_title(&url);
+            let password = keys.decrypt(&encrypted).map(|mut plain| {
+                if version >= DOMAIN_HASH_VERSION && plain.len() >= DOMAIN_HASH_LEN {
+                    plain.drain(..DOMAIN_HASH_LEN);
+                }
+                String::from_utf8_lossy(&plain).into_owned()
+            });
+            match password {
+                Ok(password) => ImportRow::new(row, finish(ImportedEntry {
+                    title,
+                    username,
+                    password,
+                    url: Some(url),
+                    ..ImportedEntry::default()
+                })),
+                Err(error) => ImportRow::failed(row, Some(title), error),
+            }
+        })
+        .collect())
+}
+
+enum Cipher {
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    Cbc([u8; 16]),
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Gcm([u8; 32]),
+}
+
+/// Claves de cada prefijo; `None` si no se pudo obtener
+struct Keys {
+    browser: BrowserKind,
+    v10: Option<Cipher>,
+    v11: Option<Cipher>,
+}
+
+impl Keys {
+    #[cfg(target_os = "windows")]
+    fn load(browser: BrowserKind, user_data: &Path) -> Result<Self, Message> {
+        use base64::Engine;
+        let key_error = || Message::new("errors.importBrowserKey").with("browser", browser_name(browser));
+        let local_state: Value = std::fs::read(user_data.join(LOCAL_STATE))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .ok_or_else(key_error)?;
+        let encrypted = local_state.pointer("/os_crypt/encrypted_key")
+            .and_then(Value::as_str)
+            .and_then(|key| base64::engine::general_purpose::STANDARD.decode(key).ok())
+            .ok_or_else(key_error)?;
+        let encrypted = encrypted.strip_prefix(b"DPAPI").ok_or_else(key_error)?;
+        let key: [u8; 32] = dpapi_unprotect(encrypted)
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(key_error)?;
+        Ok(Self { browser, v10: Some(Cipher::Gcm(key)), v11: Some(Cipher::Gcm(key)) })
+    }
+
+    #[cfg(target_os = "macos")]
+    fn load(browser: BrowserKind, _user_data: &Path) -> Result<Self, Message> {
+        // macOS pide permiso al usuario para leer la entrada del Keychain
+        let service = format!("{} Safe Storage", match browser {
+            BrowserKind::Edge => "Microsoft Edge",
+            BrowserKind::Brave => "Brave",
+            BrowserKind::Chromium => "Chromium",
+            _ => "Chrome",
+        });
+        let password = command_output("security", &["find-generic-password", "-w", "-s", &service])
+            .ok_or_else(|| Message::new("errors.importBrowserKey").with("browser", browser_name(browser)))?;
+        Ok(Self { browser, v10: Some(Cipher::Cbc(cbc_key(&password, 1003))), v11: None })
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn load(browser: BrowserKind, _user_data: &Path) -> Result<Self, Message> {
+        let application = match browser {
+            BrowserKind::Edge => "microsoft-edge",
+            BrowserKind::Brave => "brave",
+            BrowserKind::Chromium => "chromium",
+            _ => "chrome",
+        };
+        // Sin Secret Service (p. ej. sin sesión gráfica) solo se leen las contraseñas `v10`
+        let keyring = command_output("secret-tool", &["lookup", "application", application]);
+        if keyring.is_none() {
+            warn!("No se pudo leer la clave de {} del Secret Service", application);
+        }
+        Ok(Self {
+            browser,
+            v10: Some(Cipher::Cbc(cbc_key("peanuts", 1))),
+            v11: keyring.map(|password| Cipher::Cbc(cbc_key(&password, 1))),
+        })
+    }
+
+    fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>, Message> {
+        if value.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (cipher, data) = match value.split_at(value.len().min(3)) {
+            (b"v10", data) => (&self.v10, data),
+            (b"v11", data) => (&self.v11, data),
+            (b"v20", _) => return Err(Message::new("errors.importBrowserUnsupported").with("feature", "v20")),
+            _ => return Err(Message::new("errors.importBrowserUnsupported").with("feature", "DPAPI")),
+        };
+        let cipher = cipher.as_ref()
+            .ok_or_else(|| Message::new("errors.importBrowserKey").with("browser", browser_name(self.browser)))?;
+        let plain = match cipher {
+            Cipher::Cbc(key) => cbc::Decryptor::<aes::Aes128>::new(key.into(), &CBC_IV.into())
+                .decrypt_padded_vec_mut::<Pkcs7>(data)
+                .ok(),
+            Cipher::Gcm(key) => {
+                use aes_gcm::aead::{Aead, KeyInit};
+                (data.len() > 12)
+                    .then(|| data.split_at(12))
+                    .and_then(|(nonce, ciphertext)| {
+                        aes_gcm::Aes256Gcm::new(key.into())
+                            .decrypt(aes_gcm::Nonce::from_slice(nonce), ciphertext)
+                            .ok()
+                    })
+            }
+        };
+        plain.ok_or_else(|| Message::new("errors.importBrowserDecrypt"))
+    }
+}
+
+/// Clave AES-128 derivada de la contraseña del llavero
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn cbc_key(password: &str, iterations: u32) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password.as_bytes(), CBC_SALT, iterations, &mut key);
+    key
+}
+
+/// Salida de un programa del sistema, sin el salto de línea final
+#[cfg(not(target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']).to_string();
+    (output.status.success() && !text.is_empty()).then_some(text)
+}
+
+/// Desencripta con DPAPI en nombre del usuario actual, mediante PowerShell
+#[cfg(target_os = "windows")]
+fn dpapi_unprotect(data: &[u8]) -> Option<Vec<u8>> {
+    use base64::Engine;
+    use std::os::windows::process::CommandExt;
+    /// Evita que se abra una consola al lanzar PowerShell
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    const SCRIPT: &str = "Add-Type -AssemblyName System.Security; \
+        $bytes = [Convert]::FromBase64String($env:ALOHOPASS_DPAPI); \
+        [Convert]::ToBase64String([Security.Cryptography.ProtectedData]::Unprotect($bytes, $null, 'CurrentUser'))";
+
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-ExecutionPolicy", "Bypass", "-Command", SCRIPT])
+        .env("ALOHOPASS_DPAPI", base64::engine::general_purpose::STANDARD.encode(data))
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        warn!("DPAPI no pudo desencriptar la clave: {}", String::from_utf8_lossy(&output.stderr).trim());
+        return None;
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(String::from_utf8_lossy(&output.stdout).trim())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+
+    #[test]
+    fn test_decrypts_v10_with_the_fixed_linux_key() {
+        let key = cbc_key("peanuts", 1);
+        let mut value = b"v10".to_vec();
+        value.extend(cbc::Encryptor::<aes::Aes128>::new(&key.into(), &CBC_IV.into()).encrypt_padded_vec_mut::<Pkcs7>(b"secreta"));
+
+        let keys = Keys { browser: BrowserKind::Chrome, v10: Some(Cipher::Cbc(key)), v11: None };
+        assert_eq!(keys.decrypt(&value).unwrap(), b"secreta");
+        assert!(keys.decrypt(b"v11abc").is_err());
+        assert!(keys.decrypt(b"v20abc").is_err());
+    }
+}
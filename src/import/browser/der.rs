@@ -0,0 +1,75 @@
+//! Lector mínimo de ASN.1 DER para las estructuras encriptadas de Firefox
+
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_SEQUENCE: u8 = 0x30;
+
+/// Recorre los elementos de un valor DER, uno detrás de otro
+pub struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Siguiente elemento: etiqueta y contenido
+    pub fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.data.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (len, rest) = if first & 0x80 == 0 {
+            (first as usize, rest)
+        } else {
+            // Forma larga: los 7 bits bajos indican cuántos bytes ocupa la longitud
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return None;
+            }
+            let len = rest[..count].iter().fold(0usize, |len, byte| (len << 8) | *byte as usize);
+            (len, &rest[count..])
+        };
+        if rest.len() < len {
+            return None;
+        }
+        let (content, rest) = rest.split_at(len);
+        self.data = rest;
+        Some((tag, content))
+    }
+
+    /// Siguiente elemento, si tiene la etiqueta esperada
+    pub fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        self.next().filter(|(found, _)| *found == tag).map(|(_, content)| content)
+    }
+
+    pub fn sequence(&mut self) -> Option<Der<'a>> {
+        self.expect(TAG_SEQUENCE).map(Der::new)
+    }
+
+    pub fn integer(&mut self) -> Option<u64> {
+        let content = self.expect(TAG_INTEGER)?;
+        if content.len() > 9 {
+            return None;
+        }
+        Some(content.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_nested_values() {
+        let mut long = vec![TAG_OCTET_STRING, 0x81, 200];
+        long.extend([7u8; 200]);
+        let mut data = vec![TAG_SEQUENCE, 0x81, (long.len() + 4) as u8, TAG_INTEGER, 0x02, 0x27, 0x10];
+        data.extend(&long);
+
+        let mut sequence = Der::new(&data).sequence().unwrap();
+        assert_eq!(sequence.integer(), Some(10_000));
+        assert_eq!(sequence.expect(TAG_OCTET_STRING).map(<[u8]>::len), Some(200));
+        assert!(sequence.next().is_none());
+    }
+}
@@ -0,0 +1,230 @@
+//! Firefox: `logins.json` con los datos encriptados y `key4.db` con la clave
+//!
+//! La clave maestra está en `nssPrivate`, encriptada con PBES2 (PBKDF2-SHA256 y
+//! AES-256-CBC) a partir de `SHA-1(sal global || contraseña principal)`; sin
+//! contraseña principal se usa la cadena vacía. Con esa clave cada usuario y
+//! contraseña de `logins.json` va encriptado con 3DES-CBC (o AES-256-CBC en las
+//! versiones recientes). Los perfiles anteriores a Firefox 58 (`key3.db`) no
+//! están soportados.
+
+use super::der::{Der, TAG_OCTET_STRING, TAG_OID};
+use super::{site_title, ProfileCopy};
+use crate::i18n::Message;
+use crate::import::{finish, ImportRow, ImportedEntry};
+use crate::models::{BrowserKind, BrowserProfile};
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use base64::Engine;
+use log::info;
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+
+const LOGINS: &str = "logins.json";
+const KEY_DB: &str = "key4.db";
+const PASSWORD_CHECK: &[u8] = b"password-check";
+
+/// 1.2.840.113549.1.5.13
+const OID_PBES2: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x05, 0x0D];
+/// 1.2.840.113549.3.7
+const OID_DES_EDE3_CBC: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x03, 0x07];
+/// 2.16.840.1.101.3.4.1.42
+const OID_AES256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2A];
+
+/// Carpeta con `profiles.ini`
+pub fn firefox_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let dir = dirs::config_dir()?.join("Mozilla").join("Firefox");
+    #[cfg(target_os = "macos")]
+    let dir = dirs::config_dir()?.join("Firefox");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let dir = dirs::home_dir()?.join(".mozilla").join("firefox");
+    dir.is_dir().then_some(dir)
+}
+
+/// Perfiles declarados en `profiles.ini` que tienen contraseñas guardadas
+pub fn profiles(firefox_dir: &Path) -> Vec<BrowserProfile> {
+    let Ok(ini) = std::fs::read_to_string(firefox_dir.join("profiles.ini")) else { return Vec::new() };
+    let mut profiles = Vec::new();
+    let mut section: Option<(Option<String>, Option<String>, bool)> = None;
+    for line in ini.lines().map(str::trim).chain(std::iter::once("[]")) {
+        if line.starts_with('[') {
+            if let Some((Some(name), Some(path), relative)) = section.take() {
+                let path = if relative { firefox_dir.join(path) } else { PathBuf::from(path) };
+                if path.join(LOGINS).is_file() {
+                    profiles.push(BrowserProfile {
+                        browser: BrowserKind::Firefox,
+                        name,
+                        path: path.to_string_lossy().into_owned(),
+                    });
+                }
+            }
+            if line.starts_with("[Profile") {
+                section = Some((None, None, true));
+            }
+        } else if let (Some((name, path, relative)), Some((key, value))) = (section.as_mut(), line.split_once('=')) {
+            match key {
+                "Name" => *name = Some(value.to_string()),
+                "Path" => *path = Some(value.to_string()),
+                "IsRelative" => *relative = value == "1",
+                _ => {}
+            }
+        }
+    }
+    profiles
+}
+
+pub fn read(profile: &Path, password: &str) -> Result<Vec<ImportRow>, Message> {
+    if !profile.join(KEY_DB).is_file() && profile.join("key3.db").is_file() {
+        return Err(Message::new("errors.importBrowserUnsupported").with("feature", "key3.db"));
+    }
+    let key = master_key(profile, password)?;
+    let logins: Value = std::fs::read(profile.join(LOGINS))
+        .map_err(|e| Message::new("errors.importBrowserProfile").with("error", e))
+        .and_then(|data| serde_json::from_slice(&data)
+            .map_err(|e| Message::new("errors.importBrowserProfile").with("error", e)))?;
+    let logins = logins.get("logins").and_then(Value::as_array).cloned().unwrap_or_default();
+    info!("{} contraseñas guardadas en el perfil de Firefox", logins.len());
+
+    Ok(logins.iter()
+        .enumerate()
+        .map(|(index, login)| {
+            let row = index + 1;
+            let url = login.get("hostname").and_then(Value::as_str).unwrap_or_default().to_string();
+            let title = site_title(&url);
+            let field = |name: &str| {
+                login.get(name)
+                    .and_then(Value::as_str)
+                    .and_then(|value| decrypt_login(&key, value))
+                    .ok_or_else(|| Message::new("errors.importBrowserDecrypt"))
+            };
+            match field("encryptedUsername").and_then(|username| Ok((username, field("encryptedPassword")?))) {
+                Ok((username, password)) => ImportRow::new(row, finish(ImportedEntry {
+                    title,
+                    username,
+                    password,
+                    url: Some(url).filter(|url| !url.is_empty()),
+                    ..ImportedEntry::default()
+                })),
+                Err(error) => ImportRow::failed(row, Some(title), error),
+            }
+        })
+        .collect())
+}
+
+/// Clave maestra del perfil; comprueba antes la contraseña principal
+fn master_key(profile: &Path, password: &str) -> Result<Vec<u8>, Message> {
+    let copy = ProfileCopy::new(&profile.join(KEY_DB))?;
+    let conn = copy.open()?;
+    let profile_error = |e: rusqlite::Error| Message::new("errors.importBrowserProfile").with("error", e);
+
+    let (global_salt, check): (Vec<u8>, Vec<u8>) = conn
+        .query_row("SELECT item1, item2 FROM metaData WHERE id = 'password'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(profile_error)?;
+    if decrypt_pbe(&check, &global_salt, password)?.as_deref() != Some(PASSWORD_CHECK) {
+        return Err(Message::new("errors.importBrowserPassword"));
+    }
+
+    let mut stmt = conn.prepare("SELECT a11 FROM nssPrivate").map_err(profile_error)?;
+    let keys = stmt.query_map([], |row| row.get::<_, Vec<u8>>(0))
+        .map_err(profile_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(profile_error)?;
+    for encrypted in keys {
+        if let Some(key) = decrypt_pbe(&encrypted, &global_salt, password)?.filter(|key| key.len() >= 24) {
+            return Ok(key);
+        }
+    }
+    Err(Message::new("errors.importBrowserKey").with("browser", "Firefox"))
+}
+
+/// Desencripta una estructura PBES2 de NSS. `None` si la contraseña no es la correcta.
+fn decrypt_pbe(data: &[u8], global_salt: &[u8], password: &str) -> Result<Option<Vec<u8>>, Message> {
+    let invalid = || Message::new("errors.importBrowserProfile").with("error", "key4.db");
+    let mut outer = Der::new(data).sequence().ok_or_else(invalid)?;
+    let mut algorithm = outer.sequence().ok_or_else(invalid)?;
+    let oid = algorithm.expect(TAG_OID).ok_or_else(invalid)?;
+    if oid != OID_PBES2 {
+        return Err(Message::new("errors.importBrowserUnsupported").with("feature", hex::encode(oid)));
+    }
+    let mut parameters = algorithm.sequence().ok_or_else(invalid)?;
+    let mut kdf = parameters.sequence().ok_or_else(invalid)?;
+    kdf.expect(TAG_OID).ok_or_else(invalid)?;
+    let mut kdf_parameters = kdf.sequence().ok_or_else(invalid)?;
+    let salt = kdf_parameters.expect(TAG_OCTET_STRING).ok_or_else(invalid)?;
+    let iterations = kdf_parameters.integer().ok_or_else(invalid)?;
+    let mut cipher = parameters.sequence().ok_or_else(invalid)?;
+    cipher.expect(TAG_OID).ok_or_else(invalid)?;
+    // NSS guarda 14 bytes del IV; los dos primeros son la cabecera DER del propio IV
+    let iv_tail = cipher.expect(TAG_OCTET_STRING).ok_or_else(invalid)?;
+    let ciphertext = outer.expect(TAG_OCTET_STRING).ok_or_else(invalid)?;
+
+    let mut iv = vec![TAG_OCTET_STRING, iv_tail.len() as u8];
+    iv.extend_from_slice(iv_tail);
+    let iv: [u8; 16] = iv.try_into().map_err(|_| invalid())?;
+    let mut hasher = Sha1::new();
+    hasher.update(global_salt);
+    hasher.update(password.as_bytes());
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(&hasher.finalize(), salt, iterations as u32, &mut key);
+
+    Ok(cbc::Decryptor::<aes::Aes256>::new(&key.into(), &iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .ok())
+}
+
+/// Usuario o contraseña de `logins.json`: base64 de una estructura DER con el
+/// algoritmo, el IV y el texto encriptado con la clave maestra
+fn decrypt_login(key: &[u8], value: &str) -> Option<String> {
+    let data = base64::engine::general_purpose::STANDARD.decode(value).ok()?;
+    let mut outer = Der::new(&data).sequence()?;
+    outer.expect(TAG_OCTET_STRING)?;
+    let mut algorithm = outer.sequence()?;
+    let oid = algorithm.expect(TAG_OID)?;
+    let iv = algorithm.expect(TAG_OCTET_STRING)?;
+    let ciphertext = outer.expect(TAG_OCTET_STRING)?;
+
+    let plain = if oid == OID_DES_EDE3_CBC {
+        cbc::Decryptor::<des::TdesEde3>::new_from_slices(&key[..24], iv).ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+            .ok()?
+    } else if oid == OID_AES256_CBC && key.len() >= 32 {
+        cbc::Decryptor::<aes::Aes256>::new_from_slices(&key[..32], iv).ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+            .ok()?
+    } else {
+        return None;
+    };
+    String::from_utf8(plain).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if content.len() < 0x80 {
+            out.push(content.len() as u8);
+        } else {
+            out.extend([0x81, content.len() as u8]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    #[test]
+    fn test_decrypts_3des_login() {
+        let key = [9u8; 24];
+        let iv = [1u8; 8];
+        let ciphertext = cbc::Encryptor::<des::TdesEde3>::new_from_slices(&key, &iv).unwrap()
+            .encrypt_padded_vec_mut::<Pkcs7>(b"ana@example.com");
+        let algorithm = [der(TAG_OID, OID_DES_EDE3_CBC), der(TAG_OCTET_STRING, &iv)].concat();
+        let value = der(0x30, &[der(TAG_OCTET_STRING, &[0; 16]), der(0x30, &algorithm), der(TAG_OCTET_STRING, &ciphertext)].concat());
+
+        let encoded = base64::engine::general_purpose::STANDARD.encode(value);
+        assert_eq!(decrypt_login(&key, &encoded).as_deref(), Some("ana@example.com"));
+        assert_ne!(decrypt_login(&[0x42u8; 24], &encoded).as_deref(), Some("ana@example.com"));
+    }
+}
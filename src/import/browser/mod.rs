@@ -0,0 +1,101 @@
+//! Importación directa desde los navegadores instalados
+//!
+//! Lee las contraseñas guardadas en los perfiles de Chrome, Edge, Brave,
+//! Chromium y Firefox sin pasar por una exportación manual. Cada navegador las
+//! encripta con una clave propia que se obtiene por la vía del sistema:
+//! - Chromium: llavero del sistema (Secret Service en Linux, Keychain en macOS)
+//!   o DPAPI en Windows
+//! - Firefox: `key4.db` del perfil, protegido opcionalmente con la contraseña
+//!   principal
+//!
+//! Las bases de datos se copian antes de leerlas porque el navegador las
+//! mantiene bloqueadas mientras está abierto.
+
+mod chromium;
+mod der;
+mod firefox;
+
+use super::ImportRow;
+use crate::i18n::Message;
+use crate::models::{BrowserKind, BrowserProfile};
+use log::info;
+use std::path::{Path, PathBuf};
+
+/// Perfiles con contraseñas guardadas de todos los navegadores detectados
+pub fn profiles() -> Vec<BrowserProfile> {
+    let mut profiles = Vec::new();
+    for browser in chromium::BROWSERS {
+        if let Some(user_data) = chromium::user_data_dir(*browser) {
+            profiles.extend(chromium::profiles(*browser, &user_data));
+        }
+    }
+    if let Some(firefox_dir) = firefox::firefox_dir() {
+        profiles.extend(firefox::profiles(&firefox_dir));
+    }
+    info!("{} perfiles de navegador con contraseñas guardadas", profiles.len());
+    profiles
+}
+
+/// Lee las contraseñas guardadas en un perfil. `password` es la contraseña
+/// principal de Firefox, si el perfil la tiene.
+pub fn read(browser: BrowserKind, profile: &Path, password: Option<&str>) -> Result<Vec<ImportRow>, Message> {
+    match browser {
+        BrowserKind::Firefox => firefox::read(profile, password.unwrap_or_default()),
+        _ => chromium::read(browser, profile),
+    }
+}
+
+/// Nombre del navegador para mostrar
+pub fn browser_name(browser: BrowserKind) -> &'static str {
+    match browser {
+        BrowserKind::Chrome => "Google Chrome",
+        BrowserKind::Edge => "Microsoft Edge",
+        BrowserKind::Brave => "Brave",
+        BrowserKind::Chromium => "Chromium",
+        BrowserKind::Firefox => "Firefox",
+    }
+}
+
+/// Copia un archivo del perfil a un temporal para leerlo mientras el navegador
+/// está abierto; el temporal se borra al soltar el valor
+struct ProfileCopy(PathBuf);
+
+impl ProfileCopy {
+    fn new(source: &Path) -> Result<Self, Message> {
+        let copy = std::env::temp_dir().join(format!("alohopass-{}", uuid::Uuid::new_v4()));
+        std::fs::copy(source, &copy)
+            .map_err(|e| Message::new("errors.importBrowserProfile").with("error", e))?;
+        Ok(Self(copy))
+    }
+
+    fn open(&self) -> Result<rusqlite::Connection, Message> {
+        rusqlite::Connection::open_with_flags(&self.0, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| Message::new("errors.importBrowserProfile").with("error", e))
+    }
+}
+
+impl Drop for ProfileCopy {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Título de una entrada a partir de la URL del sitio: el dominio sin `www.`
+fn site_title(url: &str) -> String {
+    let host = url.split("://").nth(1).unwrap_or(url);
+    let host = host.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit('@').next().unwrap_or_default();
+    host.strip_prefix("www.").unwrap_or(host).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_site_title_is_the_host() {
+        assert_eq!(site_title("https://www.example.com/login?x=1"), "example.com");
+        assert_eq!(site_title("android://hash@com.example.app/"), "com.example.app");
+        assert_eq!(site_title("mail.example.com"), "mail.example.com");
+    }
+}
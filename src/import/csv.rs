@@ -0,0 +1,91 @@
+//! Lector de CSV mínimo (RFC 4180): campos entre comillas con comillas dobladas
+//! y saltos de línea dentro del campo
+
+/// Separa el texto en filas de campos. Las filas vacías se descartan.
+pub fn parse(data: &str) -> Vec<Vec<String>> {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                row.push(std::mem::take(&mut field));
+                push_row(&mut rows, std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        push_row(&mut rows, row);
+    }
+    rows
+}
+
+fn push_row(rows: &mut Vec<Vec<String>>, row: Vec<String>) {
+    if !(row.len() == 1 && row[0].is_empty()) {
+        rows.push(row);
+    }
+}
+
+/// Fila con acceso a los campos por nombre de columna
+pub struct Record<'a> {
+    header: &'a [String],
+    fields: &'a [String],
+}
+
+impl<'a> Record<'a> {
+    pub fn new(header: &'a [String], fields: &'a [String]) -> Self {
+        Self { header, fields }
+    }
+
+    /// Valor de la columna (sin distinguir mayúsculas); vacío si no existe
+    pub fn get(&self, column: &str) -> &'a str {
+        self.header.iter()
+            .position(|name| name.trim().eq_ignore_ascii_case(column))
+            .and_then(|index| self.fields.get(index))
+            .map(|value| value.as_str())
+            .unwrap_or("")
+    }
+
+    /// Como `get`, pero `None` si el valor está vacío
+    pub fn optional(&self, column: &str) -> Option<String> {
+        let value = self.get(column).trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_quotes_and_multiline_fields() {
+        let rows = parse("a,b,c\r\n\"x,1\",\"di \"\"hola\"\"\",\"dos\nlíneas\"\n\n");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1], vec!["x,1", "di \"hola\"", "dos\nlíneas"]);
+    }
+
+    #[test]
+    fn test_keeps_trailing_empty_field() {
+        assert_eq!(parse("a,b,\n"), vec![vec!["a", "b", ""]]);
+    }
+}
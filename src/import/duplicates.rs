@@ -0,0 +1,105 @@
+//! Detección de duplicados antes de importar
+//!
+//! Dos entradas son del mismo sitio y cuenta si coinciden el dominio de la URL
+//! (o el título, si no hay URL) y el usuario, sin distinguir mayúsculas. Con la
+//! misma contraseña son un duplicado exacto; con otra, un casi duplicado que el
+//! usuario decide si importar aparte, omitir o usar para reemplazar la existente.
+
+use super::ImportedEntry;
+use crate::favicon::domain_from_url;
+use crate::models::{EntryId, PasswordEntry};
+use std::collections::HashMap;
+
+/// Coincidencia de una entrada importada con lo que ya hay en la bóveda
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Match {
+    New,
+    /// Mismo sitio, usuario y contraseña. `None` si el duplicado es otra fila
+    /// del mismo archivo.
+    Duplicate(Option<EntryId>),
+    /// Mismo sitio y usuario con otra contraseña; id de la entrada existente
+    NearDuplicate(EntryId),
+}
+
+/// Índice de entradas por sitio y usuario
+#[derive(Debug, Default)]
+pub struct DuplicateIndex {
+    /// (sitio, usuario) → (id si ya está guardada, contraseña)
+    entries: HashMap<(String, String), Vec<(Option<EntryId>, String)>>,
+}
+
+impl DuplicateIndex {
+    pub fn new(existing: &[PasswordEntry]) -> Self {
+        let mut index = Self::default();
+        for entry in existing {
+            index.entries
+                .entry(key(entry.url.as_deref(), &entry.title, &entry.username))
+                .or_default()
+                .push((Some(entry.id), entry.password.clone()));
+        }
+        index
+    }
+
+    pub fn check(&self, entry: &ImportedEntry) -> Match {
+        let Some(candidates) = self.entries.get(&key(entry.url.as_deref(), &entry.title, &entry.username)) else {
+            return Match::New;
+        };
+        if let Some((id, _)) = candidates.iter().find(|(_, password)| *password == entry.password) {
+            return Match::Duplicate(*id);
+        }
+        candidates.iter()
+            .find_map(|(id, _)| *id)
+            .map_or(Match::New, Match::NearDuplicate)
+    }
+
+    /// Registra una fila que se va a importar, para reconocer sus repeticiones
+    /// dentro del mismo archivo
+    pub fn insert(&mut self, entry: &ImportedEntry) {
+        self.entries
+            .entry(key(entry.url.as_deref(), &entry.title, &entry.username))
+            .or_default()
+            .push((None, entry.password.clone()));
+    }
+}
+
+fn key(url: Option<&str>, title: &str, username: &str) -> (String, String) {
+    let site = url
+        .and_then(domain_from_url)
+        .or_else(|| url.map(|url| url.trim().to_lowercase()).filter(|url| !url.is_empty()))
+        .unwrap_or_else(|| title.trim().to_lowercase());
+    (site, username.trim().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imported(url: &str, username: &str, password: &str) -> ImportedEntry {
+        ImportedEntry {
+            title: "Ejemplo".to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            url: Some(url.to_string()),
+            ..ImportedEntry::default()
+        }
+    }
+
+    #[test]
+    fn test_classifies_against_existing_entries() {
+        let existing = PasswordEntry {
+            username: "ana@example.com".to_string(),
+            url: Some("https://www.example.com/login".to_string()),
+            ..PasswordEntry::sample("Ejemplo")
+        };
+        let id = existing.id;
+        let mut index = DuplicateIndex::new(&[existing]);
+
+        assert_eq!(index.check(&imported("example.com", "Ana@example.com", "secreta")), Match::Duplicate(Some(id)));
+        assert_eq!(index.check(&imported("https://example.com", "ana@example.com", "otra")), Match::NearDuplicate(id));
+        assert_eq!(index.check(&imported("https://example.com", "luis", "secreta")), Match::New);
+
+        let repeated = imported("https://other.org", "luis", "clave");
+        index.insert(&repeated);
+        assert_eq!(index.check(&repeated), Match::Duplicate(None));
+    }
+}
@@ -0,0 +1,192 @@
+//! Bases de datos de KeePass (KDBX 3.1 y 4)
+//!
+//! Los grupos pasan a ser categorías anidadas (el grupo raíz no, sus entradas
+//! quedan sin categoría) y la papelera se omite. Los campos que no son los
+//! estándar se conservan como campos personalizados y los adjuntos se importan
+//! tal cual. El secreto TOTP se reconoce en los formatos de KeePassXC (`otp`,
+//! y el antiguo `TOTP Seed`) y de KeePass (`TimeOtp-*`).
+
+use super::{finish, ImportInput, ImportRow, ImportedAttachment, ImportedEntry, Importer};
+use crate::i18n::Message;
+use crate::kdbx::{self, KdbxError};
+use crate::models::ImportFormat;
+use base64::Engine;
+
+const STANDARD_FIELDS: &[&str] = &["Title", "UserName", "Password", "URL", "Notes"];
+
+pub struct KdbxImporter;
+
+impl Importer for KdbxImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::KeepassKdbx
+    }
+
+    fn name(&self) -> &'static str {
+        "KeePass (KDBX)"
+    }
+
+    fn extension(&self) -> &'static str {
+        "kdbx"
+    }
+
+    fn needs_password(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, input: &ImportInput) -> Result<Vec<ImportRow>, Message> {
+        parse_kdbx(input)
+    }
+}
+
+pub fn parse_kdbx(input: &ImportInput) -> Result<Vec<ImportRow>, Message> {
+    let key = kdbx::CompositeKey::new(input.password, input.key_file).map_err(kdbx_message)?;
+    let database = kdbx::read(input.data, &key).map_err(kdbx_message)?;
+    let mut rows = Vec::new();
+    collect_group(&database.root, &[], database.recycle_bin.as_deref(), &mut rows);
+    Ok(rows)
+}
+
+fn collect_group(group: &kdbx::Group, path: &[String], recycle_bin: Option<&str>, rows: &mut Vec<ImportRow>) {
+    for entry in &group.entries {
+        rows.push(ImportRow::new(rows.len() + 1, finish(imported_entry(entry, path))));
+    }
+    for child in &group.groups {
+        if recycle_bin == Some(child.uuid.as_str()) {
+            continue;
+        }
+        let mut child_path = path.to_vec();
+        child_path.push(child.name.clone());
+        collect_group(child, &child_path, recycle_bin, rows);
+    }
+}
+
+fn imported_entry(entry: &kdbx::Entry, folder: &[String]) -> ImportedEntry {
+    let (totp, otp_fields) = otp(entry);
+    let custom_fields = entry.fields.iter()
+        .filter(|field| !STANDARD_FIELDS.contains(&field.key.as_str()))
+        .filter(|field| !otp_fields.contains(&field.key.as_str()))
+        .filter(|field| !field.value.is_empty())
+        .map(|field| (field.key.clone(), field.value.clone()))
+        .collect();
+
+    ImportedEntry {
+        title: entry.get("Title").unwrap_or_default().to_string(),
+        username: entry.get("UserName").unwrap_or_default().to_string(),
+        password: entry.get("Password").unwrap_or_default().to_string(),
+        url: entry.get("URL").map(str::to_string),
+        notes: entry.get("Notes").filter(|notes| !notes.is_empty()).map(str::to_string),
+        folder: folder.to_vec(),
+        tags: entry.tags.clone(),
+        totp,
+        custom_fields,
+        attachments: entry.attachments.iter()
+            .map(|attachment| ImportedAttachment { name: attachment.name.clone(), data: attachment.data.clone() })
+            .collect(),
+    }
+}
+
+/// Secreto TOTP de la entrada, como URI `otpauth` o secreto en base32, y los
+/// campos de los que se sacó (que no se repiten como campos personalizados)
+fn otp(entry: &kdbx::Entry) -> (Option<String>, Vec<&str>) {
+    if let Some(uri) = entry.get("otp").filter(|uri| !uri.is_empty()) {
+        return (Some(uri.to_string()), vec!["otp"]);
+    }
+
+    // KeePass 2.47+: el secreto en alguna de sus codificaciones y los parámetros aparte
+    let secret = entry.get("TimeOtp-Secret-Base32").map(|secret| secret.replace(' ', "").to_uppercase())
+        .or_else(|| entry.get("TimeOtp-Secret-Hex").and_then(|secret| hex::decode(secret.replace(' ', "")).ok()).map(base32))
+        .or_else(|| entry.get("TimeOtp-Secret-Base64")
+            .and_then(|secret| base64::engine::general_purpose::STANDARD.decode(secret.trim()).ok())
+            .map(base32))
+        .or_else(|| entry.get("TimeOtp-Secret").map(|secret| base32(secret.as_bytes().to_vec())));
+    if let Some(secret) = secret.filter(|secret| !secret.is_empty()) {
+        let mut uri = format!("otpauth://totp/?secret={}", secret);
+        if let Some(digits) = entry.get("TimeOtp-Length") {
+            uri.push_str(&format!("&digits={}", digits));
+        }
+        if let Some(period) = entry.get("TimeOtp-Period") {
+            uri.push_str(&format!("&period={}", period));
+        }
+        if let Some(algorithm) = entry.get("TimeOtp-Algorithm") {
+            uri.push_str(&format!("&algorithm={}", algorithm.replace("HMAC-", "").replace('-', "")));
+        }
+        let fields = entry.fields.iter()
+            .map(|field| field.key.as_str())
+            .filter(|key| key.starts_with("TimeOtp-"))
+            .collect();
+        return (Some(uri), fields);
+    }
+
+    // KeePassXC antiguo: "TOTP Seed" y "TOTP Settings" con "periodo;dígitos"
+    if let Some(seed) = entry.get("TOTP Seed").filter(|seed| !seed.is_empty()) {
+        let mut uri = format!("otpauth://totp/?secret={}", seed.replace(' ', ""));
+        if let Some((period, digits)) = entry.get("TOTP Settings").and_then(|settings| settings.split_once(';')) {
+            if digits.chars().all(|c| c.is_ascii_digit()) {
+                uri.push_str(&format!("&period={}&digits={}", period, digits));
+            }
+        }
+        return (Some(uri), vec!["TOTP Seed", "TOTP Settings"]);
+    }
+    (None, Vec::new())
+}
+
+fn base32(bytes: Vec<u8>) -> String {
+    data_encoding::BASE32_NOPAD.encode(&bytes)
+}
+
+fn kdbx_message(error: KdbxError) -> Message {
+    match error {
+        KdbxError::WrongKey => Message::new("errors.importKdbxKey"),
+        KdbxError::MissingKey => Message::new("errors.importPasswordRequired"),
+        KdbxError::NotKdbx => Message::new("errors.importFormat").with("error", error),
+        KdbxError::UnsupportedVersion(_) | KdbxError::Unsupported(_) => {
+            Message::new("errors.importKdbxUnsupported").with("feature", error)
+        }
+        KdbxError::Corrupt(_) => Message::new("errors.importKdbxCorrupt").with("error", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(key: &str, value: &str) -> kdbx::Field {
+        kdbx::Field { key: key.to_string(), value: value.to_string(), protected: false }
+    }
+
+    #[test]
+    fn test_maps_fields_otp_and_groups() {
+        let entry = kdbx::Entry {
+            fields: vec![
+                field("Title", "Servidor"),
+                field("UserName", "admin"),
+                field("Password", "s3cr3t"),
+                field("TimeOtp-Secret-Base32", "jbsw y3dp"),
+                field("TimeOtp-Period", "60"),
+                field("TimeOtp-Algorithm", "HMAC-SHA-256"),
+                field("Puerto", "22"),
+            ],
+            ..Default::default()
+        };
+        let mut root = kdbx::Group { name: "Raíz".to_string(), ..Default::default() };
+        let trash = kdbx::Group {
+            uuid: "papelera".to_string(),
+            entries: vec![entry.clone()],
+            ..Default::default()
+        };
+        let work = kdbx::Group {
+            name: "Trabajo".to_string(),
+            groups: vec![kdbx::Group { name: "Servidores".to_string(), entries: vec![entry], ..Default::default() }],
+            ..Default::default()
+        };
+        root.groups = vec![trash, work];
+
+        let mut rows = Vec::new();
+        collect_group(&root, &[], Some("papelera"), &mut rows);
+        assert_eq!(rows.len(), 1);
+        let imported = rows[0].result.as_ref().unwrap();
+        assert_eq!(imported.folder, vec!["Trabajo", "Servidores"]);
+        assert_eq!(imported.totp.as_deref(), Some("otpauth://totp/?secret=JBSWY3DP&period=60&algorithm=SHA256"));
+        assert_eq!(imported.custom_fields, vec![("Puerto".to_string(), "22".to_string())]);
+    }
+}
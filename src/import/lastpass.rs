@@ -0,0 +1,233 @@
+//! Exportaciones CSV de LastPass
+//!
+//! Columnas: `url,username,password,totp,extra,name,grouping,fav` (las
+//! exportaciones antiguas no tienen `totp`). El grupo pasa a ser la categoría y
+//! las notas seguras, que LastPass guarda con la URL `http://sn`, se importan
+//! como entradas sin contraseña con el texto en las notas.
+//!
+//! El CSV de LastPass tiene dos rarezas conocidas que se corrigen aquí:
+//! - La exportación desde el navegador escapa el texto como HTML (`&amp;`,
+//!   `&quot;`...).
+//! - La columna `extra` a veces no va entre comillas aunque tenga comas, y la
+//!   fila queda con más campos que la cabecera.
+
+use super::{csv, finish, ImportInput, ImportRow, ImportedEntry, Importer};
+use crate::i18n::Message;
+use crate::models::ImportFormat;
+
+/// URL con la que LastPass marca las notas seguras
+const SECURE_NOTE_URL: &str = "http://sn";
+/// Grupo que LastPass usa para las entradas sin carpeta
+const NO_GROUP: &str = "(none)";
+const HTML_ENTITIES: &[(&str, &str)] = &[
+    ("&amp;", "&"),
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&quot;", "\""),
+    ("&#39;", "'"),
+    ("&#039;", "'"),
+];
+
+pub struct CsvImporter;
+
+impl Importer for CsvImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::LastpassCsv
+    }
+
+    fn name(&self) -> &'static str {
+        "LastPass (CSV)"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn parse(&self, input: &ImportInput) -> Result<Vec<ImportRow>, Message> {
+        parse_csv(input.text()?)
+    }
+}
+
+pub fn parse_csv(data: &str) -> Result<Vec<ImportRow>, Message> {
+    let unescaped;
+    let data = if is_html_escaped(data) {
+        unescaped = unescape_html(data);
+        unescaped.as_str()
+    } else {
+        data
+    };
+
+    let (header, rows) = super::csv_table(data, &["url", "username", "password", "extra", "name"])?;
+    let extra_index = header.iter().position(|name| name.trim().eq_ignore_ascii_case("extra"));
+    Ok(rows.into_iter()
+        .enumerate()
+        .map(|(index, fields)| {
+            let fields = match extra_index {
+                Some(extra_index) => rejoin_extra(fields, header.len(), extra_index),
+                None => fields,
+            };
+            let record = csv::Record::new(&header, &fields);
+            ImportRow::new(index + 1, finish(entry(&record)))
+        })
+        .collect())
+}
+
+fn entry(record: &csv::Record) -> ImportedEntry {
+    let url = record.optional("url")
+        // LastPass guarda "http://" en las entradas sin sitio
+        .filter(|url| url != "http://" && url != "https://");
+    let folder = record.optional("grouping")
+        .filter(|group| group != NO_GROUP)
+        // Los subgrupos van separados por barras invertidas
+        .map(|group| group.split('\\').map(str::to_string).collect())
+        .unwrap_or_default();
+    let tags = if record.get("fav").trim() == "1" { vec!["favorite".to_string()] } else { Vec::new() };
+
+    let mut entry = ImportedEntry {
+        title: record.get("name").to_string(),
+        username: record.get("username").to_string(),
+        password: record.get("password").to_string(),
+        url,
+        notes: record.optional("extra"),
+        folder,
+        tags,
+        totp: record.optional("totp"),
+        custom_fields: Vec::new(),
+        attachments: Vec::new(),
+    };
+    if entry.url.as_deref() == Some(SECURE_NOTE_URL) {
+        entry.url = None;
+        secure_note(&mut entry);
+    }
+    entry
+}
+
+/// Las notas seguras con plantilla ("NoteType:Server", "NoteType:Credit Card"...)
+/// guardan sus datos como líneas `Campo:Valor`. Usuario, contraseña y sitio van a
+/// sus columnas; el resto queda como campos personalizados.
+fn secure_note(entry: &mut ImportedEntry) {
+    let Some(notes) = entry.notes.take() else { return };
+    if !notes.starts_with("NoteType:") {
+        entry.notes = Some(notes);
+        return;
+    }
+
+    let mut free_text = Vec::new();
+    let mut lines = notes.lines();
+    while let Some(line) = lines.next() {
+        let Some((name, value)) = line.split_once(':') else {
+            free_text.push(line.to_string());
+            continue;
+        };
+        match name {
+            "NoteType" | "Language" => {}
+            // Las notas de la plantilla ocupan el resto del texto
+            "Notes" => {
+                free_text.push(value.to_string());
+                free_text.extend(lines.by_ref().map(str::to_string));
+            }
+            "Username" if entry.username.is_empty() => entry.username = value.to_string(),
+            "Password" if entry.password.is_empty() => entry.password = value.to_string(),
+            "Hostname" | "URL" | "Website" if entry.url.is_none() && !value.is_empty() => {
+                entry.url = Some(value.to_string());
+            }
+            _ if value.is_empty() => {}
+            _ => entry.custom_fields.push((name.to_string(), value.to_string())),
+        }
+    }
+    let free_text = free_text.join("\n");
+    entry.notes = (!free_text.trim().is_empty()).then(|| free_text.trim().to_string());
+}
+
+/// Si la fila tiene campos de más, son comas de `extra` que no iban entre
+/// comillas: se vuelven a unir para que las columnas siguientes queden en su sitio
+fn rejoin_extra(mut fields: Vec<String>, columns: usize, extra_index: usize) -> Vec<String> {
+    if fields.len() <= columns || extra_index >= fields.len() {
+        return fields;
+    }
+    let excess = fields.len() - columns;
+    let extra = fields.drain(extra_index..=extra_index + excess).collect::<Vec<_>>().join(",");
+    fields.insert(extra_index, extra);
+    fields
+}
+
+/// El archivo está escapado como HTML si todos sus `&` empiezan una entidad conocida
+fn is_html_escaped(data: &str) -> bool {
+    let mut ampersands = data.match_indices('&').peekable();
+    ampersands.peek().is_some()
+        && ampersands.all(|(index, _)| HTML_ENTITIES.iter().any(|(entity, _)| data[index..].starts_with(entity)))
+}
+
+fn unescape_html(data: &str) -> String {
+    let mut result = String::with_capacity(data.len());
+    let mut rest = data;
+    while let Some(index) = rest.find('&') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+        match HTML_ENTITIES.iter().find(|(entity, _)| rest.starts_with(entity)) {
+            Some((entity, text)) => {
+                result.push_str(text);
+                rest = &rest[entity.len()..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "url,username,password,totp,extra,name,grouping,fav\n";
+
+    #[test]
+    fn test_maps_groups_favorites_and_unquoted_extra() {
+        let data = format!(
+            "{}https://mail.example.com,ana,secreta,,nota, con comas,Correo,Trabajo\\Email,1\nhttp://,bob,clave,,,Foro,(none),0\n",
+            HEADER,
+        );
+        let rows = parse_csv(&data).unwrap();
+        let entry = rows[0].result.as_ref().unwrap();
+        assert_eq!(entry.title, "Correo");
+        assert_eq!(entry.notes.as_deref(), Some("nota, con comas"));
+        assert_eq!(entry.folder, vec!["Trabajo", "Email"]);
+        assert_eq!(entry.tags, vec!["favorite"]);
+        let entry = rows[1].result.as_ref().unwrap();
+        assert_eq!(entry.url, None);
+        assert!(entry.folder.is_empty());
+    }
+
+    #[test]
+    fn test_secure_notes_keep_text_and_template_fields() {
+        let data = format!(
+            "{}http://sn,,,,\"Código de la alarma: 1234\",Casa,,0\n\
+             http://sn,,,,\"NoteType:Server\nLanguage:es-ES\nHostname:db.example.com\nUsername:admin\nPassword:s3cr3t\nPort:5432\nNotes:Solo por VPN\",Servidor,,0\n",
+            HEADER,
+        );
+        let rows = parse_csv(&data).unwrap();
+        let note = rows[0].result.as_ref().unwrap();
+        assert_eq!(note.url, None);
+        assert_eq!(note.notes.as_deref(), Some("Código de la alarma: 1234"));
+        let server = rows[1].result.as_ref().unwrap();
+        assert_eq!(server.username, "admin");
+        assert_eq!(server.password, "s3cr3t");
+        assert_eq!(server.url.as_deref(), Some("db.example.com"));
+        assert_eq!(server.custom_fields, vec![("Port".to_string(), "5432".to_string())]);
+        assert_eq!(server.notes.as_deref(), Some("Solo por VPN"));
+    }
+
+    #[test]
+    fn test_html_escaped_export_is_decoded() {
+        let data = format!("{}https://a.example.com,ana,p&amp;ss,,,A &amp; B,,0\n", HEADER);
+        let rows = parse_csv(&data).unwrap();
+        let entry = rows[0].result.as_ref().unwrap();
+        assert_eq!(entry.password, "p&ss");
+        assert_eq!(entry.title, "A & B");
+        assert!(!is_html_escaped("p&ss y &amp;"));
+    }
+}
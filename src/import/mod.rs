@@ -0,0 +1,177 @@
+//! Importación de contraseñas exportadas por otros gestores
+//!
+//! Cada formato se convierte en una lista de filas con la entrada leída o el
+//! motivo por el que no se pudo leer. Una fila inválida no detiene la
+//! importación: se informa y se sigue con las demás. Los errores que afectan a
+//! todo el archivo (formato irreconocible, exportación encriptada) se devuelven
+//! antes de importar nada.
+//!
+//! Cada formato es un `Importer` registrado en `IMPORTERS`; para soportar un
+//! gestor nuevo basta con implementar el trait y agregarlo a la lista.
+
+pub mod bitwarden;
+pub mod browser;
+mod csv;
+pub mod duplicates;
+pub mod keepass;
+pub mod lastpass;
+pub mod onepassword;
+
+use crate::i18n::Message;
+use crate::models::ImportFormat;
+
+/// Entrada leída del archivo de otro gestor, todavía sin encriptar
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportedEntry {
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    /// Ruta de carpetas en el gestor de origen, de la más externa a la más
+    /// interna; cada nivel se convierte en una categoría anidada
+    pub folder: Vec<String>,
+    pub tags: Vec<String>,
+    pub totp: Option<String>,
+    /// Campos personalizados (nombre, valor); no tienen columna propia y se
+    /// agregan al final de las notas
+    pub custom_fields: Vec<(String, String)>,
+    pub attachments: Vec<ImportedAttachment>,
+}
+
+/// Archivo adjunto a una entrada importada
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedAttachment {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl ImportedEntry {
+    /// Notas originales seguidas de los campos personalizados
+    pub fn notes_with_fields(&self) -> Option<String> {
+        let fields = self.custom_fields.iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match (self.notes.as_deref().filter(|notes| !notes.trim().is_empty()), fields.is_empty()) {
+            (Some(notes), true) => Some(notes.to_string()),
+            (Some(notes), false) => Some(format!("{}\n\n{}", notes, fields)),
+            (None, false) => Some(fields),
+            (None, true) => None,
+        }
+    }
+}
+
+/// Fila del archivo de origen
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    /// Número de fila o elemento, empezando en 1
+    pub row: usize,
+    /// Título leído, si lo hay, para identificar la fila en el informe
+    pub title: Option<String>,
+    pub result: Result<ImportedEntry, Message>,
+}
+
+impl ImportRow {
+    pub fn new(row: usize, result: Result<ImportedEntry, Message>) -> Self {
+        Self {
+            row,
+            title: result.as_ref().ok().map(|entry| entry.title.clone()),
+            result,
+        }
+    }
+
+    pub fn failed(row: usize, title: Option<String>, error: Message) -> Self {
+        Self { row, title, result: Err(error) }
+    }
+}
+
+/// Archivo a importar junto con la clave para abrirlo, si está encriptado
+pub struct ImportInput<'a> {
+    pub data: &'a [u8],
+    pub password: Option<&'a str>,
+    pub key_file: Option<&'a [u8]>,
+}
+
+impl<'a> ImportInput<'a> {
+    /// Contenido de los formatos de texto
+    pub fn text(&self) -> Result<&'a str, Message> {
+        std::str::from_utf8(self.data)
+            .map_err(|e| Message::new("errors.importFormat").with("error", e))
+    }
+}
+
+/// Lector de las exportaciones de un gestor
+pub trait Importer: Sync {
+    fn format(&self) -> ImportFormat;
+    /// Nombre para mostrar en la interfaz
+    fn name(&self) -> &'static str;
+    /// Extensión de los archivos que genera el gestor
+    fn extension(&self) -> &'static str;
+    /// Si el archivo está encriptado y hace falta contraseña o archivo de clave
+    fn needs_password(&self) -> bool {
+        false
+    }
+    /// Lee el archivo completo
+    fn parse(&self, input: &ImportInput) -> Result<Vec<ImportRow>, Message>;
+}
+
+/// Importadores disponibles, en el orden en que se ofrecen
+static IMPORTERS: &[&dyn Importer] = &[
+    &bitwarden::JsonImporter,
+    &bitwarden::CsvImporter,
+    &lastpass::CsvImporter,
+    &keepass::KdbxImporter,
+    &onepassword::PuxImporter,
+];
+
+pub fn importers() -> &'static [&'static dyn Importer] {
+    IMPORTERS
+}
+
+pub fn importer(format: ImportFormat) -> Option<&'static dyn Importer> {
+    IMPORTERS.iter().copied().find(|importer| importer.format() == format)
+}
+
+/// Lee el archivo completo en el formato indicado
+pub fn parse(format: ImportFormat, input: &ImportInput) -> Result<Vec<ImportRow>, Message> {
+    let importer = importer(format)
+        .ok_or_else(|| Message::new("errors.importUnsupportedFormat"))?;
+    importer.parse(input)
+}
+
+/// Completa el título si falta (con la URL o el usuario) y descarta filas vacías
+fn finish(mut entry: ImportedEntry) -> Result<ImportedEntry, Message> {
+    entry.title = entry.title.trim().to_string();
+    entry.url = entry.url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    entry.totp = entry.totp.map(|totp| totp.trim().to_string()).filter(|totp| !totp.is_empty());
+    entry.folder = entry.folder.iter()
+        .map(|folder| folder.trim())
+        .filter(|folder| !folder.is_empty())
+        .map(str::to_string)
+        .collect();
+    if entry.title.is_empty() {
+        entry.title = entry.url.clone()
+            .or_else(|| (!entry.username.is_empty()).then(|| entry.username.clone()))
+            .unwrap_or_default();
+    }
+    let has_notes = entry.notes.as_deref().is_some_and(|notes| !notes.trim().is_empty());
+    if entry.title.is_empty() && entry.password.is_empty() && !has_notes {
+        return Err(Message::new("errors.importEmptyRow"));
+    }
+    Ok(entry)
+}
+
+/// Valida que el CSV tenga las columnas necesarias y devuelve cabecera y filas
+fn csv_table(data: &str, required: &[&'static str]) -> Result<(Vec<String>, Vec<Vec<String>>), Message> {
+    let mut rows = csv::parse(data).into_iter();
+    let header = rows.next().ok_or_else(|| Message::new("errors.importEmptyFile"))?;
+    let missing: Vec<&str> = required.iter()
+        .copied()
+        .filter(|column| !header.iter().any(|name| name.trim().eq_ignore_ascii_case(column)))
+        .collect();
+    if !missing.is_empty() {
+        return Err(Message::new("errors.importColumns").with("columns", missing.join(", ")));
+    }
+    Ok((header, rows.collect()))
+}
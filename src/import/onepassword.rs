@@ -0,0 +1,309 @@
+//! Exportaciones de 1Password en formato 1PUX
+//!
+//! El archivo es un zip con el contenido en `export.data` (JSON) y los adjuntos
+//! en `files/`. Cada bóveda pasa a ser una categoría. Se importan inicios de
+//! sesión, contraseñas, notas seguras, tarjetas e identidades; los datos de las
+//! tarjetas e identidades no tienen columna propia y se guardan como campos
+//! personalizados. El resto de tipos (documentos, licencias, cuentas
+//! bancarias...) se informan como no soportados y los elementos en la papelera
+//! se omiten.
+
+use super::{finish, ImportInput, ImportRow, ImportedAttachment, ImportedEntry, Importer};
+use crate::i18n::Message;
+use crate::models::ImportFormat;
+use log::warn;
+use serde_json::Value;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+const EXPORT_DATA: &str = "export.data";
+
+const CATEGORY_LOGIN: &str = "001";
+const CATEGORY_CREDIT_CARD: &str = "002";
+const CATEGORY_SECURE_NOTE: &str = "003";
+const CATEGORY_IDENTITY: &str = "004";
+const CATEGORY_PASSWORD: &str = "005";
+
+/// Nombres de los tipos no soportados, para el informe
+const UNSUPPORTED_CATEGORIES: &[(&str, &str)] = &[
+    ("006", "document"),
+    ("100", "software_license"),
+    ("101", "bank_account"),
+    ("102", "database"),
+    ("103", "driver_license"),
+    ("104", "outdoor_license"),
+    ("105", "membership"),
+    ("106", "passport"),
+    ("107", "reward_program"),
+    ("108", "social_security_number"),
+    ("109", "wireless_router"),
+    ("110", "server"),
+    ("111", "email_account"),
+    ("112", "api_credential"),
+    ("113", "medical_record"),
+    ("114", "ssh_key"),
+    ("115", "crypto_wallet"),
+];
+
+pub struct PuxImporter;
+
+impl Importer for PuxImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::OnepasswordPux
+    }
+
+    fn name(&self) -> &'static str {
+        "1Password (1PUX)"
+    }
+
+    fn extension(&self) -> &'static str {
+        "1pux"
+    }
+
+    fn parse(&self, input: &ImportInput) -> Result<Vec<ImportRow>, Message> {
+        parse_pux(input.data)
+    }
+}
+
+/// Lee un archivo 1PUX; también acepta el `export.data` suelto
+pub fn parse_pux(data: &[u8]) -> Result<Vec<ImportRow>, Message> {
+    let mut archive = if data.starts_with(b"PK") {
+        Some(ZipArchive::new(Cursor::new(data)).map_err(|e| Message::new("errors.importFormat").with("error", e))?)
+    } else {
+        None
+    };
+    let export_data = match archive.as_mut() {
+        Some(archive) => read_file(archive, EXPORT_DATA)
+            .ok_or_else(|| Message::new("errors.importFormat").with("error", EXPORT_DATA))?,
+        None => data.to_vec(),
+    };
+    let root: Value = serde_json::from_slice(&export_data)
+        .map_err(|e| Message::new("errors.importFormat").with("error", e))?;
+    let accounts = root.get("accounts")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Message::new("errors.importFormat").with("error", "accounts"))?;
+
+    let mut rows = Vec::new();
+    for vault in accounts.iter().flat_map(|account| array(account, "vaults")) {
+        let vault_name = vault.get("attrs").and_then(|attrs| text(attrs, "name"));
+        for item in array(vault, "items") {
+            // Algunas versiones envuelven cada elemento en `item`
+            let item = item.get("item").unwrap_or(item);
+            if item.get("trashed").and_then(Value::as_bool).unwrap_or(false) || text(item, "state").as_deref() == Some("deleted") {
+                continue;
+            }
+            let row = rows.len() + 1;
+            let title = item.get("overview").and_then(|overview| text(overview, "title"));
+            rows.push(match pux_item(item, vault_name.as_deref(), archive.as_mut()) {
+                Ok(entry) => ImportRow::new(row, finish(entry)),
+                Err(error) => ImportRow::failed(row, title, error),
+            });
+        }
+    }
+    Ok(rows)
+}
+
+fn pux_item(
+    item: &Value,
+    vault_name: Option<&str>,
+    mut archive: Option<&mut ZipArchive<Cursor<&[u8]>>>,
+) -> Result<ImportedEntry, Message> {
+    let category = text(item, "categoryUuid").unwrap_or_else(|| CATEGORY_LOGIN.to_string());
+    let tag = match category.as_str() {
+        CATEGORY_LOGIN | CATEGORY_PASSWORD | CATEGORY_SECURE_NOTE => None,
+        CATEGORY_CREDIT_CARD => Some("credit_card"),
+        CATEGORY_IDENTITY => Some("identity"),
+        other => {
+            let name = UNSUPPORTED_CATEGORIES.iter()
+                .find(|(uuid, _)| *uuid == other)
+                .map_or(other, |(_, name)| *name);
+            return Err(Message::new("errors.importUnsupportedType").with("type", name));
+        }
+    };
+
+    let overview = item.get("overview");
+    let details = item.get("details");
+    let mut entry = ImportedEntry {
+        title: overview.and_then(|overview| text(overview, "title")).unwrap_or_default(),
+        url: overview.and_then(|overview| text(overview, "url"))
+            .or_else(|| overview.and_then(|overview| array(overview, "urls").find_map(|url| text(url, "url")))),
+        notes: details.and_then(|details| text(details, "notesPlain")),
+        folder: vault_name.map(|name| vec![name.to_string()]).unwrap_or_default(),
+        tags: overview.map(|overview| array(overview, "tags").filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default(),
+        ..ImportedEntry::default()
+    };
+    if item.get("favIndex").and_then(Value::as_u64).unwrap_or(0) > 0 {
+        entry.tags.push("favorite".to_string());
+    }
+    entry.tags.extend(tag.map(str::to_string));
+
+    let Some(details) = details else { return Ok(entry) };
+    for field in array(details, "loginFields") {
+        let value = text(field, "value").unwrap_or_default();
+        match text(field, "designation").as_deref() {
+            Some("username") => entry.username = value,
+            Some("password") => entry.password = value,
+            _ => {
+                // Botones y casillas del formulario no tienen valor útil
+                if !value.is_empty() && !matches!(text(field, "fieldType").as_deref(), Some("B") | Some("C") | Some("I")) {
+                    entry.custom_fields.push((text(field, "name").unwrap_or_default(), value));
+                }
+            }
+        }
+    }
+    // Los elementos de tipo contraseña guardan el valor en `password`
+    if entry.password.is_empty() {
+        entry.password = text(details, "password").unwrap_or_default();
+    }
+
+    for section in array(details, "sections") {
+        for field in array(section, "fields") {
+            let id = text(field, "id").unwrap_or_default();
+            let name = text(field, "title").unwrap_or_else(|| id.clone());
+            let Some(value) = field.get("value") else { continue };
+
+            if let Some(totp) = value.get("totp").and_then(Value::as_str).filter(|totp| !totp.is_empty()) {
+                if entry.totp.is_none() {
+                    entry.totp = Some(totp.to_string());
+                } else {
+                    entry.custom_fields.push((name, totp.to_string()));
+                }
+                continue;
+            }
+            if let Some(file) = value.get("file") {
+                if let Some(attachment) = archive.as_deref_mut().and_then(|archive| attachment(archive, file)) {
+                    entry.attachments.push(attachment);
+                }
+                continue;
+            }
+            let Some(value) = field_value(value) else { continue };
+            // La identidad no tiene usuario propio: se usa el de la sección de internet o el correo
+            if category == CATEGORY_IDENTITY && entry.username.is_empty() && (id == "username" || id == "email") {
+                entry.username = value.clone();
+            }
+            entry.custom_fields.push((name, value));
+        }
+    }
+
+    // Los documentos llevan el archivo en `documentAttributes`
+    if let (Some(archive), Some(document)) = (archive, details.get("documentAttributes")) {
+        entry.attachments.extend(attachment(archive, document));
+    }
+    Ok(entry)
+}
+
+/// Texto de un campo de sección, según su tipo
+fn field_value(value: &Value) -> Option<String> {
+    let (kind, value) = value.as_object()?.iter().next()?;
+    let text = match (kind.as_str(), value) {
+        (_, Value::String(text)) => text.clone(),
+        ("monthYear", Value::Number(number)) => {
+            let month_year = number.as_u64()?;
+            format!("{:02}/{}", month_year % 100, month_year / 100)
+        }
+        ("date", Value::Number(number)) => chrono::DateTime::from_timestamp(number.as_i64()?, 0)?
+            .format("%Y-%m-%d")
+            .to_string(),
+        ("email", Value::Object(email)) => email.get("email_address")?.as_str()?.to_string(),
+        ("address", Value::Object(address)) => ["street", "city", "state", "zip", "country"].iter()
+            .filter_map(|part| address.get(*part).and_then(Value::as_str))
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(", "),
+        (_, Value::Number(number)) => number.to_string(),
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// Adjunto guardado en `files/<documentId>__<fileName>`
+fn attachment(archive: &mut ZipArchive<Cursor<&[u8]>>, file: &Value) -> Option<ImportedAttachment> {
+    let name = text(file, "fileName")?;
+    let path = format!("files/{}__{}", text(file, "documentId")?, name);
+    match read_file(archive, &path) {
+        Some(data) => Some(ImportedAttachment { name, data }),
+        None => {
+            warn!("Adjunto {} no encontrado en el archivo 1PUX", path);
+            None
+        }
+    }
+}
+
+fn read_file(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Option<Vec<u8>> {
+    let mut file = archive.by_name(name).ok()?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value.get(key).and_then(Value::as_array).into_iter().flatten()
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value.get(key)
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const EXPORT: &str = r#"{
+        "accounts": [{"attrs": {"accountName": "Ana"}, "vaults": [{
+            "attrs": {"uuid": "v1", "name": "Personal"},
+            "items": [
+                {"uuid": "i1", "favIndex": 1, "categoryUuid": "001",
+                 "overview": {"title": "Correo", "url": "https://mail.example.com", "tags": ["trabajo"]},
+                 "details": {
+                    "loginFields": [
+                        {"value": "ana", "name": "email", "fieldType": "E", "designation": "username"},
+                        {"value": "secreta", "name": "password", "fieldType": "P", "designation": "password"}
+                    ],
+                    "notesPlain": "nota",
+                    "sections": [{"title": "", "fields": [
+                        {"title": "código", "id": "otp", "value": {"totp": "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP"}},
+                        {"title": "contrato", "id": "doc", "value": {"file": {"fileName": "a.txt", "documentId": "d1"}}}
+                    ]}]
+                 }},
+                {"uuid": "i2", "categoryUuid": "002", "overview": {"title": "Visa"},
+                 "details": {"sections": [{"title": "", "fields": [
+                    {"title": "número", "id": "ccnum", "value": {"creditCardNumber": "4111111111111111"}},
+                    {"title": "vencimiento", "id": "expiry", "value": {"monthYear": 202712}}
+                 ]}]}},
+                {"uuid": "i3", "categoryUuid": "101", "overview": {"title": "Banco"}, "details": {}},
+                {"uuid": "i4", "categoryUuid": "003", "trashed": true, "overview": {"title": "Borrada"}, "details": {}}
+            ]
+        }]}]
+    }"#;
+
+    #[test]
+    fn test_reads_zip_with_attachments_and_reports_unsupported_types() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file(EXPORT_DATA, options).unwrap();
+        zip.write_all(EXPORT.as_bytes()).unwrap();
+        zip.start_file("files/d1__a.txt", options).unwrap();
+        zip.write_all(b"hola").unwrap();
+        let data = zip.finish().unwrap().into_inner();
+
+        let rows = parse_pux(&data).unwrap();
+        assert_eq!(rows.len(), 3);
+        let login = rows[0].result.as_ref().unwrap();
+        assert_eq!(login.folder, vec!["Personal"]);
+        assert_eq!((login.username.as_str(), login.password.as_str()), ("ana", "secreta"));
+        assert_eq!(login.tags, vec!["trabajo", "favorite"]);
+        assert!(login.totp.as_deref().unwrap().starts_with("otpauth://"));
+        assert_eq!(login.attachments, vec![ImportedAttachment { name: "a.txt".to_string(), data: b"hola".to_vec() }]);
+
+        let card = rows[1].result.as_ref().unwrap();
+        assert_eq!(card.tags, vec!["credit_card"]);
+        assert_eq!(card.custom_fields[1], ("vencimiento".to_string(), "12/2027".to_string()));
+        assert_eq!(rows[2].title.as_deref(), Some("Banco"));
+        assert!(rows[2].result.is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! Primitivas criptográficas de KDBX: derivación de clave, cifrado del contenido,
+//! flujo interno de los campos protegidos y HMAC de los bloques
+
+use super::dictionary::{Variant, VariantDictionary};
+use super::KdbxError;
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecryptMut, BlockEncrypt, BlockEncryptMut, KeyInit, KeyIvInit, StreamCipher};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+
+pub const CIPHER_AES256: [u8; 16] = [
+    0x31, 0xc1, 0xf2, 0xe6, 0xbf, 0x71, 0x43, 0x50, 0xbe, 0x58, 0x05, 0x21, 0x6a, 0xfc, 0x5a, 0xff,
+];
+pub const CIPHER_CHACHA20: [u8; 16] = [
+    0xd6, 0x03, 0x8a, 0x2b, 0x8b, 0x6f, 0x4c, 0xb5, 0xa5, 0x24, 0x33, 0x9a, 0x31, 0xdb, 0xb5, 0x9a,
+];
+pub const CIPHER_TWOFISH: [u8; 16] = [
+    0xad, 0x68, 0xf2, 0x9f, 0x57, 0x6f, 0x4b, 0xb9, 0xa3, 0x6a, 0xd4, 0x7a, 0xf9, 0x65, 0x34, 0x6c,
+];
+
+/// AES-KDF tal como lo escribe KDBX 4
+pub const KDF_AES: [u8; 16] = [
+    0xc9, 0xd9, 0xf3, 0x9a, 0x62, 0x8a, 0x44, 0x60, 0xbf, 0x74, 0x0d, 0x08, 0xc1, 0x8a, 0x4f, 0xea,
+];
+/// AES-KDF con el identificador que usa KeePassXC para KDBX 3.1
+pub const KDF_AES_LEGACY: [u8; 16] = [
+    0x7c, 0x02, 0xbb, 0x82, 0x79, 0xa7, 0x4a, 0xc0, 0x92, 0x7d, 0x11, 0x4a, 0x00, 0x64, 0x82, 0x38,
+];
+pub const KDF_ARGON2D: [u8; 16] = [
+    0xef, 0x63, 0x6d, 0xdf, 0x8c, 0x29, 0x44, 0x4b, 0x91, 0xf7, 0xa9, 0xa4, 0x03, 0xe3, 0x0a, 0x0c,
+];
+pub const KDF_ARGON2ID: [u8; 16] = [
+    0x9e, 0x29, 0x8b, 0x19, 0x56, 0xdb, 0x47, 0x73, 0xb2, 0x3d, 0xfc, 0x3e, 0xc6, 0xf0, 0xa1, 0xe6,
+];
+
+/// Parámetros de Argon2d de las bases de datos que exportamos: los valores por
+/// defecto de KeePassXC salvo las iteraciones, fijas en vez de medidas
+const EXPORT_ARGON2_MEMORY: u64 = 64 * 1024 * 1024;
+const EXPORT_ARGON2_ITERATIONS: u64 = 10;
+const EXPORT_ARGON2_PARALLELISM: u32 = 2;
+
+/// Flujo interno con el que se ocultan los campos protegidos
+pub const INNER_STREAM_SALSA20: u32 = 2;
+pub const INNER_STREAM_CHACHA20: u32 = 3;
+const SALSA20_NONCE: [u8; 8] = [0xE8, 0x30, 0x09, 0x4B, 0x97, 0x20, 0x5D, 0x2A];
+
+/// Derivación de la clave compuesta
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kdf {
+    Aes { seed: Vec<u8>, rounds: u64 },
+    Argon2 {
+        algorithm: argon2::Algorithm,
+        salt: Vec<u8>,
+        /// Memoria en bytes
+        memory: u64,
+        iterations: u64,
+        parallelism: u32,
+        version: u32,
+    },
+}
+
+impl Kdf {
+    pub fn from_dictionary(parameters: &VariantDictionary) -> Result<Self, KdbxError> {
+        let uuid = parameters.bytes("$UUID")?;
+        if uuid == KDF_AES || uuid == KDF_AES_LEGACY {
+            return Ok(Kdf::Aes {
+                seed: parameters.bytes("S")?.to_vec(),
+                rounds: parameters.unsigned("R")?,
+            });
+        }
+        let algorithm = if uuid == KDF_ARGON2D {
+            argon2::Algorithm::Argon2d
+        } else if uuid == KDF_ARGON2ID {
+            argon2::Algorithm::Argon2id
+        } else {
+            return Err(KdbxError::Unsupported(format!("derivación de clave desconocida ({})", hex::encode(uuid))));
+        };
+        Ok(Kdf::Argon2 {
+            algorithm,
+            salt: parameters.bytes("S")?.to_vec(),
+            memory: parameters.unsigned("M")?,
+            iterations: parameters.unsigned("I")?,
+            parallelism: parameters.unsigned("P")? as u32,
+            version: parameters.unsigned("V")? as u32,
+        })
+    }
+
+    /// Argon2d con una sal nueva, para exportar
+    pub fn export_default() -> Self {
+        Kdf::Argon2 {
+            algorithm: argon2::Algorithm::Argon2d,
+            salt: random_bytes(32),
+            memory: EXPORT_ARGON2_MEMORY,
+            iterations: EXPORT_ARGON2_ITERATIONS,
+            parallelism: EXPORT_ARGON2_PARALLELISM,
+            version: 0x13,
+        }
+    }
+
+    pub fn to_dictionary(&self) -> VariantDictionary {
+        let mut parameters = VariantDictionary::default();
+        match self {
+            Kdf::Aes { seed, rounds } => {
+                parameters.insert("$UUID", Variant::Bytes(KDF_AES.to_vec()));
+                parameters.insert("R", Variant::U64(*rounds));
+                parameters.insert("S", Variant::Bytes(seed.clone()));
+            }
+            Kdf::Argon2 { algorithm, salt, memory, iterations, parallelism, version } => {
+                let uuid = if *algorithm == argon2::Algorithm::Argon2id { KDF_ARGON2ID } else { KDF_ARGON2D };
+                parameters.insert("$UUID", Variant::Bytes(uuid.to_vec()));
+                parameters.insert("S", Variant::Bytes(salt.clone()));
+                parameters.insert("P", Variant::U32(*parallelism));
+                parameters.insert("M", Variant::U64(*memory));
+                parameters.insert("I", Variant::U64(*iterations));
+                parameters.insert("V", Variant::U32(*version));
+            }
+        }
+        parameters
+    }
+
+    /// Aplica la derivación a la clave compuesta
+    pub fn transform(&self, composite: &[u8; 32]) -> Result<[u8; 32], KdbxError> {
+        match self {
+            Kdf::Aes { seed, rounds } => {
+                if seed.len() != 32 {
+                    return Err(KdbxError::Corrupt("semilla de AES-KDF inválida".to_string()));
+                }
+                let cipher = aes::Aes256::new(GenericArray::from_slice(seed));
+                let mut blocks = [
+                    *GenericArray::from_slice(&composite[..16]),
+                    *GenericArray::from_slice(&composite[16..]),
+                ];
+                for _ in 0..*rounds {
+                    cipher.encrypt_blocks(&mut blocks);
+                }
+                let mut hasher = Sha256::new();
+                hasher.update(blocks[0]);
+                hasher.update(blocks[1]);
+                Ok(hasher.finalize().into())
+            }
+            Kdf::Argon2 { algorithm, salt, memory, iterations, parallelism, version } => {
+                let version = if *version == 0x10 { argon2::Version::V0x10 } else { argon2::Version::V0x13 };
+                let params = argon2::Params::new(
+                    (*memory / 1024) as u32,
+                    *iterations as u32,
+                    *parallelism,
+                    Some(32),
+                )
+                .map_err(|e| KdbxError::Unsupported(format!("parámetros de Argon2 inválidos: {}", e)))?;
+                let mut key = [0u8; 32];
+                argon2::Argon2::new(*algorithm, version, params)
+                    .hash_password_into(composite, salt, &mut key)
+                    .map_err(|e| KdbxError::Unsupported(format!("error en Argon2: {}", e)))?;
+                Ok(key)
+            }
+        }
+    }
+}
+
+/// Cifrado del contenido de la base de datos
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cipher {
+    Aes256,
+    ChaCha20,
+}
+
+impl Cipher {
+    pub fn from_uuid(uuid: &[u8]) -> Result<Self, KdbxError> {
+        if uuid == CIPHER_AES256 {
+            Ok(Cipher::Aes256)
+        } else if uuid == CIPHER_CHACHA20 {
+            Ok(Cipher::ChaCha20)
+        } else if uuid == CIPHER_TWOFISH {
+            Err(KdbxError::Unsupported("el cifrado Twofish no está soportado".to_string()))
+        } else {
+            Err(KdbxError::Unsupported(format!("cifrado desconocido ({})", hex::encode(uuid))))
+        }
+    }
+
+    pub fn uuid(&self) -> [u8; 16] {
+        match self {
+            Cipher::Aes256 => CIPHER_AES256,
+            Cipher::ChaCha20 => CIPHER_CHACHA20,
+        }
+    }
+
+    pub fn encrypt(&self, key: &[u8; 32], iv: &[u8], data: &[u8]) -> Result<Vec<u8>, KdbxError> {
+        match self {
+            Cipher::Aes256 => {
+                let encryptor = cbc::Encryptor::<aes::Aes256>::new_from_slices(key, iv)
+                    .map_err(|_| KdbxError::Corrupt("vector de inicialización inválido".to_string()))?;
+                Ok(encryptor.encrypt_padded_vec_mut::<Pkcs7>(data))
+            }
+            Cipher::ChaCha20 => {
+                let mut cipher = chacha20::ChaCha20::new_from_slices(key, iv)
+                    .map_err(|_| KdbxError::Corrupt("vector de inicialización inválido".to_string()))?;
+                let mut encrypted = data.to_vec();
+                cipher.apply_keystream(&mut encrypted);
+                Ok(encrypted)
+            }
+        }
+    }
+
+    /// Desencripta el contenido; `None` si el relleno no es válido (clave incorrecta
+    /// en KDBX 3, archivo dañado en KDBX 4)
+    pub fn decrypt(&self, key: &[u8; 32], iv: &[u8], data: &[u8]) -> Result<Option<Vec<u8>>, KdbxError> {
+        match self {
+            Cipher::Aes256 => {
+                let decryptor = cbc::Decryptor::<aes::Aes256>::new_from_slices(key, iv)
+                    .map_err(|_| KdbxError::Corrupt("vector de inicialización inválido".to_string()))?;
+                Ok(decryptor.decrypt_padded_vec_mut::<Pkcs7>(data).ok())
+            }
+            Cipher::ChaCha20 => {
+                let mut cipher = chacha20::ChaCha20::new_from_slices(key, iv)
+                    .map_err(|_| KdbxError::Corrupt("vector de inicialización inválido".to_string()))?;
+                let mut plain = data.to_vec();
+                cipher.apply_keystream(&mut plain);
+                Ok(Some(plain))
+            }
+        }
+    }
+}
+
+/// Flujo de bytes con el que se hace XOR de los valores protegidos, en el orden
+/// en que aparecen en el XML
+pub enum InnerStream {
+    Salsa20(salsa20::Salsa20),
+    ChaCha20(chacha20::ChaCha20),
+}
+
+impl InnerStream {
+    pub fn new(id: u32, key: &[u8]) -> Result<Self, KdbxError> {
+        match id {
+            INNER_STREAM_SALSA20 => {
+                let key = Sha256::digest(key);
+                Ok(InnerStream::Salsa20(salsa20::Salsa20::new(&key, GenericArray::from_slice(&SALSA20_NONCE))))
+            }
+            INNER_STREAM_CHACHA20 => {
+                let hash = Sha512::digest(key);
+                Ok(InnerStream::ChaCha20(chacha20::ChaCha20::new(
+                    GenericArray::from_slice(&hash[..32]),
+                    GenericArray::from_slice(&hash[32..44]),
+                )))
+            }
+            other => Err(KdbxError::Unsupported(format!("flujo interno no soportado ({})", other))),
+        }
+    }
+
+    pub fn apply(&mut self, data: &mut [u8]) {
+        match self {
+            InnerStream::Salsa20(cipher) => cipher.apply_keystream(data),
+            InnerStream::ChaCha20(cipher) => cipher.apply_keystream(data),
+        }
+    }
+}
+
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Claves de KDBX 4 derivadas de la semilla maestra y la clave transformada
+pub fn master_keys(master_seed: &[u8], transformed: &[u8; 32]) -> ([u8; 32], [u8; 64]) {
+    let mut hasher = Sha256::new();
+    hasher.update(master_seed);
+    hasher.update(transformed);
+    let cipher_key = hasher.finalize().into();
+
+    let mut hasher = Sha512::new();
+    hasher.update(master_seed);
+    hasher.update(transformed);
+    hasher.update([1u8]);
+    (cipher_key, hasher.finalize().into())
+}
+
+/// HMAC-SHA-256 del bloque `index` (la cabecera usa `u64::MAX`)
+pub fn block_hmac(hmac_key: &[u8; 64], index: u64, parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut hasher = Sha512::new();
+    hasher.update(index.to_le_bytes());
+    hasher.update(hmac_key);
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&hasher.finalize())
+        .expect("HMAC acepta claves de cualquier longitud");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
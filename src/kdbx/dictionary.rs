@@ -0,0 +1,117 @@
+//! `VariantDictionary` de KDBX 4: lista de pares clave → valor tipado que guarda
+//! los parámetros de la derivación de clave
+
+use super::{ByteReader, KdbxError};
+
+const VERSION: u16 = 0x0100;
+/// Byte alto de la versión: las versiones menores son compatibles
+const VERSION_CRITICAL_MASK: u16 = 0xFF00;
+
+const TYPE_END: u8 = 0x00;
+const TYPE_U32: u8 = 0x04;
+const TYPE_U64: u8 = 0x05;
+const TYPE_BOOL: u8 = 0x08;
+const TYPE_I32: u8 = 0x0C;
+const TYPE_I64: u8 = 0x0D;
+const TYPE_STRING: u8 = 0x18;
+const TYPE_BYTES: u8 = 0x42;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Variant {
+    U32(u32),
+    U64(u64),
+    Bool(bool),
+    I32(i32),
+    I64(i64),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VariantDictionary(Vec<(String, Variant)>);
+
+impl VariantDictionary {
+    pub fn parse(data: &[u8]) -> Result<Self, KdbxError> {
+        let mut input = ByteReader::new(data);
+        let version = input.u16()?;
+        if version & VERSION_CRITICAL_MASK != VERSION & VERSION_CRITICAL_MASK {
+            return Err(KdbxError::Unsupported(format!("VariantDictionary versión {:#06x}", version)));
+        }
+
+        let mut items = Vec::new();
+        loop {
+            let kind = input.u8()?;
+            if kind == TYPE_END {
+                break;
+            }
+            let key_len = input.u32()? as usize;
+            let key = String::from_utf8_lossy(input.take(key_len)?).into_owned();
+            let value_len = input.u32()? as usize;
+            let value = input.take(value_len)?;
+            let mut value_reader = ByteReader::new(value);
+            let variant = match kind {
+                TYPE_U32 => Variant::U32(value_reader.u32()?),
+                TYPE_U64 => Variant::U64(value_reader.u64()?),
+                TYPE_BOOL => Variant::Bool(value.first().is_some_and(|b| *b != 0)),
+                TYPE_I32 => Variant::I32(value_reader.i32()?),
+                TYPE_I64 => Variant::I64(value_reader.u64()? as i64),
+                TYPE_STRING => Variant::String(String::from_utf8_lossy(value).into_owned()),
+                TYPE_BYTES => Variant::Bytes(value.to_vec()),
+                other => return Err(KdbxError::Corrupt(format!("tipo de VariantDictionary desconocido: {:#04x}", other))),
+            };
+            items.push((key, variant));
+        }
+        Ok(Self(items))
+    }
+
+    pub fn insert(&mut self, key: &str, value: Variant) {
+        self.0.retain(|(k, _)| k != key);
+        self.0.push((key.to_string(), value));
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = VERSION.to_le_bytes().to_vec();
+        for (key, value) in &self.0 {
+            let (kind, bytes) = match value {
+                Variant::U32(value) => (TYPE_U32, value.to_le_bytes().to_vec()),
+                Variant::U64(value) => (TYPE_U64, value.to_le_bytes().to_vec()),
+                Variant::Bool(value) => (TYPE_BOOL, vec![*value as u8]),
+                Variant::I32(value) => (TYPE_I32, value.to_le_bytes().to_vec()),
+                Variant::I64(value) => (TYPE_I64, value.to_le_bytes().to_vec()),
+                Variant::String(value) => (TYPE_STRING, value.as_bytes().to_vec()),
+                Variant::Bytes(value) => (TYPE_BYTES, value.clone()),
+            };
+            out.push(kind);
+            out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            out.extend_from_slice(key.as_bytes());
+            out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            out.extend_from_slice(&bytes);
+        }
+        out.push(TYPE_END);
+        out
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Variant> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, value)| value)
+    }
+
+    pub fn bytes(&self, key: &str) -> Result<&[u8], KdbxError> {
+        match self.get(key) {
+            Some(Variant::Bytes(bytes)) => Ok(bytes),
+            _ => Err(missing(key)),
+        }
+    }
+
+    /// Entero sin signo, sea cual sea el ancho con el que se guardó
+    pub fn unsigned(&self, key: &str) -> Result<u64, KdbxError> {
+        match self.get(key) {
+            Some(Variant::U32(value)) => Ok(*value as u64),
+            Some(Variant::U64(value)) => Ok(*value),
+            _ => Err(missing(key)),
+        }
+    }
+}
+
+fn missing(key: &str) -> KdbxError {
+    KdbxError::Corrupt(format!("falta el parámetro {} de la derivación de clave", key))
+}
@@ -0,0 +1,217 @@
+//! Bases de datos de KeePass (KDBX 3.1 y 4)
+//!
+//! Solo se implementa lo necesario para importar y exportar la bóveda: el
+//! archivo se desencripta completo en memoria y se convierte en un árbol de
+//! grupos y entradas. Cifrados soportados: AES-256 y ChaCha20 (Twofish no);
+//! derivación de clave AES-KDF, Argon2d y Argon2id.
+//!
+//! La clave compuesta se arma igual que en KeePass:
+//! `SHA-256(SHA-256(contraseña) || clave del archivo de clave)`, con el archivo de
+//! clave en cualquiera de sus formatos (XML 1.0/2.0, 32 bytes, 64 dígitos hex o
+//! un archivo cualquiera, del que se usa el SHA-256).
+
+mod crypto;
+mod dictionary;
+mod reader;
+mod writer;
+mod xml;
+
+pub use reader::read;
+pub use writer::write;
+
+use sha2::{Digest, Sha256};
+
+/// Primera firma del archivo, común a todos los formatos de KeePass 2
+const SIGNATURE_1: u32 = 0x9AA2_D903;
+/// Segunda firma: base de datos KDBX (las de KeePass 1 usan otra)
+const SIGNATURE_2: u32 = 0xB54B_FB67;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KdbxError {
+    #[error("El archivo no es una base de datos de KeePass")]
+    NotKdbx,
+    #[error("Versión de KDBX no soportada: {0}")]
+    UnsupportedVersion(u16),
+    #[error("{0}")]
+    Unsupported(String),
+    /// La clave no descifra el archivo: contraseña o archivo de clave incorrectos
+    #[error("Contraseña o archivo de clave incorrectos")]
+    WrongKey,
+    #[error("Base de datos de KeePass dañada: {0}")]
+    Corrupt(String),
+    #[error("Hace falta una contraseña o un archivo de clave")]
+    MissingKey,
+}
+
+/// Base de datos desencriptada
+#[derive(Debug, Clone, Default)]
+pub struct Database {
+    pub name: String,
+    pub root: Group,
+    /// UUID del grupo de la papelera, si está activada
+    pub recycle_bin: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Group {
+    /// UUID en base64, como aparece en el XML
+    pub uuid: String,
+    pub name: String,
+    pub groups: Vec<Group>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Entry {
+    pub uuid: String,
+    /// Campos en el orden del archivo; los estándar son `Title`, `UserName`,
+    /// `Password`, `URL` y `Notes`
+    pub fields: Vec<Field>,
+    pub attachments: Vec<Attachment>,
+    pub tags: Vec<String>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Entry {
+    /// Valor del campo, si existe
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.iter()
+            .find(|field| field.key == key)
+            .map(|field| field.value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub key: String,
+    pub value: String,
+    /// KeePass oculta y encripta en memoria los campos protegidos
+    pub protected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Contraseña y/o archivo de clave con los que se abre la base de datos
+pub struct CompositeKey {
+    password: Option<String>,
+    key_file: Option<Vec<u8>>,
+}
+
+impl CompositeKey {
+    pub fn new(password: Option<&str>, key_file: Option<&[u8]>) -> Result<Self, KdbxError> {
+        if password.is_none() && key_file.is_none() {
+            return Err(KdbxError::MissingKey);
+        }
+        Ok(Self {
+            password: password.map(str::to_string),
+            key_file: key_file.map(<[u8]>::to_vec),
+        })
+    }
+
+    /// Clave compuesta, antes de aplicar la derivación del archivo
+    fn hash(&self) -> Result<[u8; 32], KdbxError> {
+        let mut hasher = Sha256::new();
+        if let Some(password) = &self.password {
+            hasher.update(Sha256::digest(password.as_bytes()));
+        }
+        if let Some(key_file) = &self.key_file {
+            hasher.update(key_file_key(key_file)?);
+        }
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Clave contenida en un archivo de clave, según su formato
+fn key_file_key(data: &[u8]) -> Result<[u8; 32], KdbxError> {
+    if let Some(key) = xml::key_file(data)? {
+        return Ok(key);
+    }
+    if data.len() == 32 {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(data);
+        return Ok(key);
+    }
+    if data.len() == 64 {
+        if let Ok(decoded) = hex::decode(data) {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&decoded);
+            return Ok(key);
+        }
+    }
+    Ok(Sha256::digest(data).into())
+}
+
+/// Lector de enteros little-endian sobre el archivo
+struct ByteReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], KdbxError> {
+        let end = self.position.checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| KdbxError::Corrupt("archivo truncado".to_string()))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let bytes = &self.data[self.position..];
+        self.position = self.data.len();
+        bytes
+    }
+
+    fn u8(&mut self) -> Result<u8, KdbxError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, KdbxError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, KdbxError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, KdbxError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, KdbxError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_file_formats() {
+        let raw = [7u8; 32];
+        assert_eq!(key_file_key(&raw).unwrap(), raw);
+        assert_eq!(key_file_key(hex::encode(raw).as_bytes()).unwrap(), raw);
+
+        let xml_v2 = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<KeyFile><Meta><Version>2.0</Version></Meta>\
+             <Key><Data Hash=\"00000000\">{} {}</Data></Key></KeyFile>",
+            hex::encode(&raw[..16]).to_uppercase(),
+            hex::encode(&raw[16..]).to_uppercase(),
+        );
+        assert_eq!(key_file_key(xml_v2.as_bytes()).unwrap(), raw);
+
+        let other = b"cualquier archivo";
+        assert_eq!(key_file_key(other).unwrap(), <[u8; 32]>::from(Sha256::digest(other)));
+    }
+}
@@ -0,0 +1,214 @@
+//! Lectura de archivos KDBX 3.1 y 4
+
+use super::crypto::{self, Cipher, InnerStream, Kdf};
+use super::dictionary::VariantDictionary;
+use super::{xml, ByteReader, CompositeKey, Database, KdbxError, SIGNATURE_1, SIGNATURE_2};
+use flate2::read::GzDecoder;
+use hmac::Mac;
+use log::info;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+
+pub(super) const HEADER_END: u8 = 0;
+pub(super) const HEADER_CIPHER_ID: u8 = 2;
+pub(super) const HEADER_COMPRESSION: u8 = 3;
+pub(super) const HEADER_MASTER_SEED: u8 = 4;
+pub(super) const HEADER_TRANSFORM_SEED: u8 = 5;
+pub(super) const HEADER_TRANSFORM_ROUNDS: u8 = 6;
+pub(super) const HEADER_ENCRYPTION_IV: u8 = 7;
+pub(super) const HEADER_PROTECTED_STREAM_KEY: u8 = 8;
+pub(super) const HEADER_STREAM_START_BYTES: u8 = 9;
+pub(super) const HEADER_INNER_RANDOM_STREAM_ID: u8 = 10;
+pub(super) const HEADER_KDF_PARAMETERS: u8 = 11;
+
+pub(super) const INNER_HEADER_END: u8 = 0;
+pub(super) const INNER_HEADER_STREAM_ID: u8 = 1;
+pub(super) const INNER_HEADER_STREAM_KEY: u8 = 2;
+pub(super) const INNER_HEADER_BINARY: u8 = 3;
+
+/// Campos de la cabecera exterior (sin encriptar)
+#[derive(Default)]
+struct Header {
+    cipher: Option<Cipher>,
+    compressed: bool,
+    master_seed: Vec<u8>,
+    iv: Vec<u8>,
+    kdf_parameters: Option<VariantDictionary>,
+    // Solo en KDBX 3
+    transform_seed: Vec<u8>,
+    transform_rounds: u64,
+    protected_stream_key: Vec<u8>,
+    stream_start_bytes: Vec<u8>,
+    inner_stream_id: u32,
+}
+
+impl Header {
+    fn parse(input: &mut ByteReader, major: u16) -> Result<Self, KdbxError> {
+        let mut header = Header::default();
+        loop {
+            let id = input.u8()?;
+            let size = if major >= 4 { input.u32()? as usize } else { input.u16()? as usize };
+            let data = input.take(size)?;
+            let mut value = ByteReader::new(data);
+            match id {
+                HEADER_END => break,
+                HEADER_CIPHER_ID => header.cipher = Some(Cipher::from_uuid(data)?),
+                HEADER_COMPRESSION => header.compressed = match value.u32()? {
+                    0 => false,
+                    1 => true,
+                    other => return Err(KdbxError::Unsupported(format!("compresión desconocida ({})", other))),
+                },
+                HEADER_MASTER_SEED => header.master_seed = data.to_vec(),
+                HEADER_TRANSFORM_SEED => header.transform_seed = data.to_vec(),
+                HEADER_TRANSFORM_ROUNDS => header.transform_rounds = value.u64()?,
+                HEADER_ENCRYPTION_IV => header.iv = data.to_vec(),
+                HEADER_PROTECTED_STREAM_KEY => header.protected_stream_key = data.to_vec(),
+                HEADER_STREAM_START_BYTES => header.stream_start_bytes = data.to_vec(),
+                HEADER_INNER_RANDOM_STREAM_ID => header.inner_stream_id = value.u32()?,
+                HEADER_KDF_PARAMETERS => header.kdf_parameters = Some(VariantDictionary::parse(data)?),
+                // Comentario y datos públicos: no afectan al contenido
+                _ => {}
+            }
+        }
+        Ok(header)
+    }
+
+    fn kdf(&self) -> Result<Kdf, KdbxError> {
+        match &self.kdf_parameters {
+            Some(parameters) => Kdf::from_dictionary(parameters),
+            None => Ok(Kdf::Aes { seed: self.transform_seed.clone(), rounds: self.transform_rounds }),
+        }
+    }
+
+    fn cipher(&self) -> Result<Cipher, KdbxError> {
+        self.cipher.ok_or_else(|| KdbxError::Corrupt("falta el cifrado en la cabecera".to_string()))
+    }
+}
+
+/// Desencripta una base de datos de KeePass
+pub fn read(data: &[u8], key: &CompositeKey) -> Result<Database, KdbxError> {
+    let mut input = ByteReader::new(data);
+    if input.u32().ok() != Some(SIGNATURE_1) || input.u32().ok() != Some(SIGNATURE_2) {
+        return Err(KdbxError::NotKdbx);
+    }
+    // Versión menor: los cambios entre versiones menores son compatibles
+    input.u16()?;
+    let major = input.u16()?;
+    if major != 3 && major != 4 {
+        return Err(KdbxError::UnsupportedVersion(major));
+    }
+
+    let header = Header::parse(&mut input, major)?;
+    let header_bytes = &data[..input.position];
+    let transformed = header.kdf()?.transform(&key.hash()?)?;
+    info!("Abriendo base de datos KDBX {} ({:?}, comprimida: {})", major, header.cipher()?, header.compressed);
+
+    if major == 3 {
+        read_v3(&header, &mut input, &transformed)
+    } else {
+        read_v4(&header, header_bytes, &mut input, &transformed)
+    }
+}
+
+fn read_v3(header: &Header, input: &mut ByteReader, transformed: &[u8; 32]) -> Result<Database, KdbxError> {
+    let (cipher_key, _) = crypto::master_keys(&header.master_seed, transformed);
+    let plain = header.cipher()?
+        .decrypt(&cipher_key, &header.iv, input.rest())?
+        .ok_or(KdbxError::WrongKey)?;
+    // Los primeros bytes desencriptados deben coincidir con los de la cabecera
+    if plain.len() < header.stream_start_bytes.len() || plain[..header.stream_start_bytes.len()] != header.stream_start_bytes[..] {
+        return Err(KdbxError::WrongKey);
+    }
+
+    let payload = read_hashed_blocks(&plain[header.stream_start_bytes.len()..])?;
+    let xml = if header.compressed { gunzip(&payload)? } else { payload };
+    let stream = InnerStream::new(header.inner_stream_id, &header.protected_stream_key)?;
+    xml::parse(&xml, stream, Vec::new())
+}
+
+fn read_v4(header: &Header, header_bytes: &[u8], input: &mut ByteReader, transformed: &[u8; 32]) -> Result<Database, KdbxError> {
+    if input.take(32)? != Sha256::digest(header_bytes).as_slice() {
+        return Err(KdbxError::Corrupt("la cabecera no coincide con su hash".to_string()));
+    }
+    let (cipher_key, hmac_key) = crypto::master_keys(&header.master_seed, transformed);
+    let header_hmac = input.take(32)?;
+    crypto::block_hmac(&hmac_key, u64::MAX, &[header_bytes])
+        .verify_slice(header_hmac)
+        .map_err(|_| KdbxError::WrongKey)?;
+
+    let encrypted = read_hmac_blocks(input, &hmac_key)?;
+    let plain = header.cipher()?
+        .decrypt(&cipher_key, &header.iv, &encrypted)?
+        .ok_or_else(|| KdbxError::Corrupt("relleno inválido".to_string()))?;
+    let payload = if header.compressed { gunzip(&plain)? } else { plain };
+
+    // Cabecera interior: flujo de los campos protegidos y adjuntos
+    let mut inner = ByteReader::new(&payload);
+    let mut stream_id = 0;
+    let mut stream_key = Vec::new();
+    let mut binaries = Vec::new();
+    loop {
+        let id = inner.u8()?;
+        let size = inner.u32()? as usize;
+        let data = inner.take(size)?;
+        match id {
+            INNER_HEADER_END => break,
+            INNER_HEADER_STREAM_ID => stream_id = ByteReader::new(data).u32()?,
+            INNER_HEADER_STREAM_KEY => stream_key = data.to_vec(),
+            // El primer byte son indicadores (protección en memoria)
+            INNER_HEADER_BINARY => binaries.push(data.get(1..).unwrap_or_default().to_vec()),
+            _ => {}
+        }
+    }
+    let stream = InnerStream::new(stream_id, &stream_key)?;
+    xml::parse(inner.rest(), stream, binaries)
+}
+
+/// Bloques de KDBX 3: índice, SHA-256 y datos; termina con un bloque vacío
+fn read_hashed_blocks(data: &[u8]) -> Result<Vec<u8>, KdbxError> {
+    let mut input = ByteReader::new(data);
+    let mut payload = Vec::new();
+    loop {
+        input.u32()?;
+        let hash = input.take(32)?;
+        let size = input.u32()? as usize;
+        if size == 0 {
+            break;
+        }
+        let block = input.take(size)?;
+        if Sha256::digest(block).as_slice() != hash {
+            return Err(KdbxError::Corrupt("bloque con hash incorrecto".to_string()));
+        }
+        payload.extend_from_slice(block);
+    }
+    Ok(payload)
+}
+
+/// Bloques de KDBX 4: HMAC, tamaño y datos; termina con un bloque vacío
+fn read_hmac_blocks(input: &mut ByteReader, hmac_key: &[u8; 64]) -> Result<Vec<u8>, KdbxError> {
+    let mut encrypted = Vec::new();
+    for index in 0u64.. {
+        let hmac = input.take(32)?;
+        let size = input.i32()?;
+        if size < 0 {
+            return Err(KdbxError::Corrupt("tamaño de bloque negativo".to_string()));
+        }
+        let block = input.take(size as usize)?;
+        crypto::block_hmac(hmac_key, index, &[&index.to_le_bytes(), &size.to_le_bytes(), block])
+            .verify_slice(hmac)
+            .map_err(|_| KdbxError::Corrupt(format!("el bloque {} no pasó la verificación HMAC", index)))?;
+        if size == 0 {
+            break;
+        }
+        encrypted.extend_from_slice(block);
+    }
+    Ok(encrypted)
+}
+
+fn gunzip(data: &[u8]) -> Result<Vec<u8>, KdbxError> {
+    let mut plain = Vec::new();
+    GzDecoder::new(data)
+        .read_to_end(&mut plain)
+        .map_err(|e| KdbxError::Corrupt(format!("error al descomprimir: {}", e)))?;
+    Ok(plain)
+}
@@ -0,0 +1,138 @@
+//! Escritura de archivos KDBX 4 (AES-256, gzip, Argon2d)
+
+use super::crypto::{self, Cipher, InnerStream, Kdf, INNER_STREAM_CHACHA20};
+use super::reader::{
+    HEADER_CIPHER_ID, HEADER_COMPRESSION, HEADER_ENCRYPTION_IV, HEADER_END, HEADER_KDF_PARAMETERS,
+    HEADER_MASTER_SEED, INNER_HEADER_BINARY, INNER_HEADER_END, INNER_HEADER_STREAM_ID, INNER_HEADER_STREAM_KEY,
+};
+use super::{xml, CompositeKey, Database, KdbxError, SIGNATURE_1, SIGNATURE_2};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::Mac;
+use log::info;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+/// KDBX 4.0
+const VERSION_MINOR: u16 = 0;
+const VERSION_MAJOR: u16 = 4;
+/// Tamaño de los bloques con HMAC, el mismo que usa KeePass
+const BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Encripta la base de datos con la clave indicada y la derivación por defecto
+pub fn write(database: &Database, key: &CompositeKey) -> Result<Vec<u8>, KdbxError> {
+    write_with_kdf(database, key, &Kdf::export_default())
+}
+
+pub fn write_with_kdf(database: &Database, key: &CompositeKey, kdf: &Kdf) -> Result<Vec<u8>, KdbxError> {
+    let cipher = Cipher::Aes256;
+    let master_seed = crypto::random_bytes(32);
+    let iv = crypto::random_bytes(16);
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&SIGNATURE_1.to_le_bytes());
+    header.extend_from_slice(&SIGNATURE_2.to_le_bytes());
+    header.extend_from_slice(&VERSION_MINOR.to_le_bytes());
+    header.extend_from_slice(&VERSION_MAJOR.to_le_bytes());
+    push_field(&mut header, HEADER_CIPHER_ID, &cipher.uuid());
+    push_field(&mut header, HEADER_COMPRESSION, &1u32.to_le_bytes());
+    push_field(&mut header, HEADER_MASTER_SEED, &master_seed);
+    push_field(&mut header, HEADER_ENCRYPTION_IV, &iv);
+    push_field(&mut header, HEADER_KDF_PARAMETERS, &kdf.to_dictionary().serialize());
+    push_field(&mut header, HEADER_END, b"\r\n\r\n");
+
+    let transformed = kdf.transform(&key.hash()?)?;
+    let (cipher_key, hmac_key) = crypto::master_keys(&master_seed, &transformed);
+
+    // Cabecera interior y XML, con los campos protegidos ocultos por el flujo interno
+    let stream_key = crypto::random_bytes(64);
+    let mut stream = InnerStream::new(INNER_STREAM_CHACHA20, &stream_key)?;
+    let (document, binaries) = xml::write(database, &mut stream);
+    let mut payload = Vec::new();
+    push_field(&mut payload, INNER_HEADER_STREAM_ID, &INNER_STREAM_CHACHA20.to_le_bytes());
+    push_field(&mut payload, INNER_HEADER_STREAM_KEY, &stream_key);
+    for binary in &binaries {
+        let mut data = Vec::with_capacity(binary.len() + 1);
+        data.push(0);
+        data.extend_from_slice(binary);
+        push_field(&mut payload, INNER_HEADER_BINARY, &data);
+    }
+    push_field(&mut payload, INNER_HEADER_END, &[]);
+    payload.extend_from_slice(document.as_bytes());
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&payload)
+        .and_then(|_| encoder.flush())
+        .map_err(|e| KdbxError::Corrupt(format!("error al comprimir: {}", e)))?;
+    let compressed = encoder.finish()
+        .map_err(|e| KdbxError::Corrupt(format!("error al comprimir: {}", e)))?;
+    let encrypted = cipher.encrypt(&cipher_key, &iv, &compressed)?;
+
+    let mut out = header.clone();
+    out.extend_from_slice(&Sha256::digest(&header));
+    out.extend_from_slice(&crypto::block_hmac(&hmac_key, u64::MAX, &[&header]).finalize().into_bytes());
+    let blocks = encrypted.chunks(BLOCK_SIZE).chain(std::iter::once(&[][..]));
+    for (index, block) in blocks.enumerate() {
+        let index = index as u64;
+        let size = (block.len() as i32).to_le_bytes();
+        let hmac = crypto::block_hmac(&hmac_key, index, &[&index.to_le_bytes(), &size, block]).finalize();
+        out.extend_from_slice(&hmac.into_bytes());
+        out.extend_from_slice(&size);
+        out.extend_from_slice(block);
+    }
+    info!("Base de datos KDBX 4 generada: {} bytes, {} adjuntos", out.len(), binaries.len());
+    Ok(out)
+}
+
+/// Campo de cabecera de KDBX 4: id, tamaño de 32 bits y datos
+fn push_field(out: &mut Vec<u8>, id: u8, data: &[u8]) {
+    out.push(id);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbx::{read, Attachment, Entry, Field, Group};
+
+    #[test]
+    fn test_roundtrip() {
+        let entry = Entry {
+            uuid: String::new(),
+            fields: vec![
+                Field { key: "Title".to_string(), value: "Correo <personal> & \"otro\"".to_string(), protected: false },
+                Field { key: "Password".to_string(), value: "contraseña-secreta".to_string(), protected: true },
+                Field { key: "otp".to_string(), value: "otpauth://totp/Correo?secret=JBSWY3DPEHPK3PXP".to_string(), protected: true },
+            ],
+            attachments: vec![Attachment { name: "nota.txt".to_string(), data: b"hola".to_vec() }],
+            tags: vec!["trabajo".to_string()],
+            created_at: Some(chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap()),
+            updated_at: None,
+        };
+        let database = Database {
+            name: "Bóveda".to_string(),
+            root: Group {
+                name: "Alohopass".to_string(),
+                groups: vec![Group { name: "Correo".to_string(), entries: vec![entry.clone()], ..Group::default() }],
+                ..Group::default()
+            },
+            recycle_bin: None,
+        };
+        let kdf = Kdf::Aes { seed: crypto::random_bytes(32), rounds: 10 };
+        let key = CompositeKey::new(Some("clave"), None).unwrap();
+        let data = write_with_kdf(&database, &key, &kdf).unwrap();
+
+        let read_back = read(&data, &key).unwrap();
+        assert_eq!(read_back.name, "Bóveda");
+        let group = &read_back.root.groups[0];
+        assert_eq!(group.name, "Correo");
+        assert_eq!(group.entries[0].fields, entry.fields);
+        assert_eq!(group.entries[0].attachments, entry.attachments);
+        assert_eq!(group.entries[0].tags, entry.tags);
+        assert_eq!(group.entries[0].created_at, entry.created_at);
+
+        let wrong = CompositeKey::new(Some("otra"), None).unwrap();
+        assert!(matches!(read(&data, &wrong), Err(KdbxError::WrongKey)));
+    }
+}
@@ -0,0 +1,306 @@
+//! Contenido XML de KDBX y archivos de clave XML
+
+use super::crypto::InnerStream;
+use super::{Attachment, Database, Entry, Field, Group, KdbxError};
+use base64::Engine;
+use flate2::read::GzDecoder;
+use roxmltree::{Document, Node, NodeId};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::Read;
+
+/// Programa que figura como autor de las bases de datos exportadas
+const GENERATOR: &str = "Alohopass";
+
+/// Convierte el XML desencriptado en el árbol de grupos y entradas. `binaries`
+/// son los adjuntos de la cabecera interior (KDBX 4); en KDBX 3 están en `Meta`.
+pub fn parse(xml: &[u8], mut stream: InnerStream, binaries: Vec<Vec<u8>>) -> Result<Database, KdbxError> {
+    let text = std::str::from_utf8(xml)
+        .map_err(|_| KdbxError::Corrupt("el XML no es UTF-8".to_string()))?;
+    let document = Document::parse(text)
+        .map_err(|e| KdbxError::Corrupt(format!("XML inválido: {}", e)))?;
+
+    // El flujo interno se consume en el orden del documento, incluido el
+    // historial, así que los valores protegidos se descifran todos primero
+    let mut protected: HashMap<NodeId, Vec<u8>> = HashMap::new();
+    for node in document.descendants().filter(|node| is_true(node.attribute("Protected"))) {
+        let mut value = base64::engine::general_purpose::STANDARD
+            .decode(node.text().unwrap_or_default().trim())
+            .map_err(|_| KdbxError::Corrupt("valor protegido inválido".to_string()))?;
+        stream.apply(&mut value);
+        protected.insert(node.id(), value);
+    }
+
+    let file = document.root_element();
+    let meta = child(file, "Meta");
+    let binaries = if binaries.is_empty() {
+        meta.and_then(|meta| child(meta, "Binaries"))
+            .map(|list| meta_binaries(list, &protected))
+            .transpose()?
+            .unwrap_or_default()
+    } else {
+        binaries
+    };
+    let recycle_bin = meta
+        .filter(|meta| !child_text(*meta, "RecycleBinEnabled").is_some_and(|enabled| enabled.eq_ignore_ascii_case("false")))
+        .and_then(|meta| child_text(meta, "RecycleBinUUID"));
+
+    let root = child(file, "Root")
+        .and_then(|root| child(root, "Group"))
+        .ok_or_else(|| KdbxError::Corrupt("falta el grupo raíz".to_string()))?;
+    Ok(Database {
+        name: meta.and_then(|meta| child_text(meta, "DatabaseName")).unwrap_or_default(),
+        root: group(root, &protected, &binaries),
+        recycle_bin,
+    })
+}
+
+/// Genera el XML de la base de datos. Los valores protegidos se ocultan con
+/// `stream` en el orden del documento; los adjuntos se devuelven aparte para la
+/// cabecera interior y el XML solo los referencia por posición.
+pub fn write(database: &Database, stream: &mut InnerStream) -> (String, Vec<Vec<u8>>) {
+    let mut writer = XmlWriter { out: String::new(), stream, binaries: Vec::new() };
+    writer.out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n<KeePassFile>\n<Meta>\n");
+    writer.element("Generator", GENERATOR);
+    writer.element("DatabaseName", &database.name);
+    writer.element("RecycleBinEnabled", "False");
+    writer.out.push_str("</Meta>\n<Root>\n");
+    writer.group(&database.root);
+    writer.out.push_str("</Root>\n</KeePassFile>\n");
+    (writer.out, writer.binaries)
+}
+
+struct XmlWriter<'a> {
+    out: String,
+    stream: &'a mut InnerStream,
+    binaries: Vec<Vec<u8>>,
+}
+
+impl XmlWriter<'_> {
+    fn element(&mut self, name: &str, text: &str) {
+        let _ = writeln!(self.out, "<{0}>{1}</{0}>", name, escape(text));
+    }
+
+    fn uuid(&mut self, uuid: &str) {
+        let uuid = if uuid.is_empty() {
+            base64::engine::general_purpose::STANDARD.encode(super::crypto::random_bytes(16))
+        } else {
+            uuid.to_string()
+        };
+        self.element("UUID", &uuid);
+    }
+
+    fn group(&mut self, group: &Group) {
+        self.out.push_str("<Group>\n");
+        self.uuid(&group.uuid);
+        self.element("Name", &group.name);
+        for entry in &group.entries {
+            self.entry(entry);
+        }
+        for child in &group.groups {
+            self.group(child);
+        }
+        self.out.push_str("</Group>\n");
+    }
+
+    fn entry(&mut self, entry: &Entry) {
+        self.out.push_str("<Entry>\n");
+        self.uuid(&entry.uuid);
+        self.element("Tags", &entry.tags.join(";"));
+        self.out.push_str("<Times>\n");
+        if let Some(created_at) = entry.created_at {
+            self.element("CreationTime", &format_time(created_at));
+        }
+        if let Some(updated_at) = entry.updated_at {
+            self.element("LastModificationTime", &format_time(updated_at));
+        }
+        self.element("Expires", "False");
+        self.out.push_str("</Times>\n");
+
+        for field in &entry.fields {
+            let _ = write!(self.out, "<String><Key>{}</Key>", escape(&field.key));
+            if field.protected {
+                let mut value = field.value.as_bytes().to_vec();
+                self.stream.apply(&mut value);
+                let _ = write!(
+                    self.out,
+                    "<Value Protected=\"True\">{}</Value>",
+                    base64::engine::general_purpose::STANDARD.encode(value),
+                );
+            } else {
+                let _ = write!(self.out, "<Value>{}</Value>", escape(&field.value));
+            }
+            self.out.push_str("</String>\n");
+        }
+        for attachment in &entry.attachments {
+            let _ = writeln!(
+                self.out,
+                "<Binary><Key>{}</Key><Value Ref=\"{}\"/></Binary>",
+                escape(&attachment.name),
+                self.binaries.len(),
+            );
+            self.binaries.push(attachment.data.clone());
+        }
+        self.out.push_str("</Entry>\n");
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Clave de un archivo de clave XML (versiones 1.0 y 2.0); `None` si el archivo
+/// no es un archivo de clave XML
+pub fn key_file(data: &[u8]) -> Result<Option<[u8; 32]>, KdbxError> {
+    let Ok(text) = std::str::from_utf8(data) else { return Ok(None) };
+    let Ok(document) = Document::parse(text.trim_start_matches('\u{feff}')) else { return Ok(None) };
+    let file = document.root_element();
+    if file.tag_name().name() != "KeyFile" {
+        return Ok(None);
+    }
+
+    let invalid = || KdbxError::Corrupt("archivo de clave XML inválido".to_string());
+    let version = child(file, "Meta").and_then(|meta| child_text(meta, "Version")).unwrap_or_default();
+    let data = child(file, "Key")
+        .and_then(|key| child(key, "Data"))
+        .and_then(|data| data.text())
+        .ok_or_else(invalid)?;
+    let key = if version.starts_with("2.") {
+        let digits: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+        hex::decode(digits).map_err(|_| invalid())?
+    } else {
+        base64::engine::general_purpose::STANDARD.decode(data.trim()).map_err(|_| invalid())?
+    };
+    key.try_into().map(Some).map_err(|_| invalid())
+}
+
+fn meta_binaries(list: Node, protected: &HashMap<NodeId, Vec<u8>>) -> Result<Vec<Vec<u8>>, KdbxError> {
+    let mut binaries = Vec::new();
+    for binary in list.children().filter(|node| node.has_tag_name("Binary")) {
+        let index: usize = binary.attribute("ID")
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| KdbxError::Corrupt("adjunto sin ID".to_string()))?;
+        let mut data = match protected.get(&binary.id()) {
+            Some(data) => data.clone(),
+            None => base64::engine::general_purpose::STANDARD
+                .decode(binary.text().unwrap_or_default().trim())
+                .map_err(|_| KdbxError::Corrupt("adjunto inválido".to_string()))?,
+        };
+        if is_true(binary.attribute("Compressed")) {
+            let mut plain = Vec::new();
+            GzDecoder::new(data.as_slice())
+                .read_to_end(&mut plain)
+                .map_err(|e| KdbxError::Corrupt(format!("adjunto comprimido inválido: {}", e)))?;
+            data = plain;
+        }
+        if binaries.len() <= index {
+            binaries.resize(index + 1, Vec::new());
+        }
+        binaries[index] = data;
+    }
+    Ok(binaries)
+}
+
+fn group(node: Node, protected: &HashMap<NodeId, Vec<u8>>, binaries: &[Vec<u8>]) -> Group {
+    Group {
+        uuid: child_text(node, "UUID").unwrap_or_default(),
+        name: child_text(node, "Name").unwrap_or_default(),
+        groups: node.children()
+            .filter(|child| child.has_tag_name("Group"))
+            .map(|child| group(child, protected, binaries))
+            .collect(),
+        entries: node.children()
+            .filter(|child| child.has_tag_name("Entry"))
+            .map(|child| entry(child, protected, binaries))
+            .collect(),
+    }
+}
+
+fn entry(node: Node, protected: &HashMap<NodeId, Vec<u8>>, binaries: &[Vec<u8>]) -> Entry {
+    let fields = node.children()
+        .filter(|child| child.has_tag_name("String"))
+        .filter_map(|string| {
+            let key = child_text(string, "Key")?;
+            let value_node = child(string, "Value");
+            let (value, is_protected) = match value_node.and_then(|value| protected.get(&value.id())) {
+                Some(bytes) => (String::from_utf8_lossy(bytes).into_owned(), true),
+                None => (value_node.and_then(|value| value.text()).unwrap_or_default().to_string(), false),
+            };
+            Some(Field { key, value, protected: is_protected })
+        })
+        .collect();
+
+    // Las referencias apuntan a la lista de adjuntos compartida
+    let attachments = node.children()
+        .filter(|child| child.has_tag_name("Binary"))
+        .filter_map(|binary| {
+            let name = child_text(binary, "Key")?;
+            let index: usize = child(binary, "Value")?.attribute("Ref")?.parse().ok()?;
+            Some(Attachment { name, data: binaries.get(index)?.clone() })
+        })
+        .collect();
+
+    let times = child(node, "Times");
+    Entry {
+        uuid: child_text(node, "UUID").unwrap_or_default(),
+        fields,
+        attachments,
+        tags: child_text(node, "Tags")
+            .map(|tags| {
+                tags.split([';', ','])
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        created_at: times.and_then(|times| child_text(times, "CreationTime")).and_then(|time| parse_time(&time)),
+        updated_at: times.and_then(|times| child_text(times, "LastModificationTime")).and_then(|time| parse_time(&time)),
+    }
+}
+
+/// Fecha en ISO 8601 (KDBX 3) o en segundos desde el año 1 codificados en base64 (KDBX 4)
+fn parse_time(text: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&chrono::Utc));
+    }
+    let bytes: [u8; 8] = base64::engine::general_purpose::STANDARD.decode(text).ok()?.try_into().ok()?;
+    let seconds = i64::from_le_bytes(bytes);
+    let epoch = chrono::NaiveDate::from_ymd_opt(1, 1, 1)?.and_hms_opt(0, 0, 0)?;
+    let time = epoch.checked_add_signed(chrono::Duration::seconds(seconds))?;
+    Some(chrono::DateTime::from_naive_utc_and_offset(time, chrono::Utc))
+}
+
+/// Fecha en el formato de KDBX 4
+fn format_time(time: chrono::DateTime<chrono::Utc>) -> String {
+    let epoch = chrono::NaiveDate::from_ymd_opt(1, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("fecha válida");
+    let seconds = (time.naive_utc() - epoch).num_seconds();
+    base64::engine::general_purpose::STANDARD.encode(seconds.to_le_bytes())
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map(str::to_string)
+}
+
+fn is_true(value: Option<&str>) -> bool {
+    value.is_some_and(|value| value.eq_ignore_ascii_case("true"))
+}
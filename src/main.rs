@@ -9,6 +9,27 @@ mod database;
 mod models;
 mod sync;
 mod browser_extension;
+mod error;
+mod i18n;
+mod quick_search;
+mod autotype;
+mod clipboard;
+mod totp;
+mod platform;
+mod favicon;
+mod domain;
+mod generator;
+mod strength;
+mod security;
+mod security_report;
+mod breach;
+mod export;
+mod backup;
+mod import;
+mod kdbx;
+mod progress;
+mod vault;
+mod category_tree;
 
 use tauri::Manager;
 use std::sync::Mutex;
@@ -18,6 +39,10 @@ use log::{info, error, warn};
 use env_logger;
 use crate::sync::commands::*;
 use std::sync::Arc;
+use rayon::prelude::*;
+use crate::error::{AppError, AppResult};
+use crate::i18n::Message;
+use crate::sync::smart_sync::ChangeType;
 
 /// Función de utilidad para verificar si una tabla existe
 fn table_exists(connection: &rusqlite::Connection, table_name: &str) -> bool {
@@ -40,24 +65,91 @@ fn table_exists(connection: &rusqlite::Connection, table_name: &str) -> bool {
 /// Estado global de la aplicación
 pub struct AppState {
     pub crypto_manager: Mutex<crypto::CryptoManager>,
-    pub database_manager: Mutex<Option<database::DatabaseManager>>,
+    /// Hilo que tiene la conexión a la base; `None` dentro hasta que se abre
+    pub database: database::DatabaseWorker,
     pub is_initialized: Mutex<bool>,
-    pub sync_manager: Arc<Mutex<Option<sync::SyncManager>>>,
+    pub sync_manager: sync::SharedSyncManager,
     pub browser_extension_manager: Mutex<Option<browser_extension::BrowserExtensionManager>>,
+    pub settings: Mutex<models::AppSettings>,
+    pub clipboard: clipboard::ClipboardManager,
+    pub favicon_service: Mutex<Option<favicon::FaviconService>>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             crypto_manager: Mutex::new(crypto::CryptoManager::new()),
-            database_manager: Mutex::new(None),
+            database: database::DatabaseWorker::start(),
             is_initialized: Mutex::new(false),
-            sync_manager: Arc::new(Mutex::new(None)),
+            sync_manager: Arc::new(std::sync::OnceLock::new()),
             browser_extension_manager: Mutex::new(None),
+            settings: Mutex::new(models::AppSettings::default()),
+            clipboard: clipboard::ClipboardManager::new(),
+            favicon_service: Mutex::new(None),
         }
     }
 }
 
+impl AppState {
+    /// Devuelve una copia del crypto manager desbloqueado y libera el lock de inmediato,
+    /// para que el trabajo criptográfico no bloquee a otros comandos
+    pub fn unlocked_crypto(&self) -> AppResult<crypto::CryptoManager> {
+        let crypto_manager = self.crypto_manager.lock()
+            .map_err(|_| AppError::state_lock("components.cryptoManager"))?;
+        if !crypto_manager.is_unlocked() {
+            error!("❌ Crypto manager NO está desbloqueado");
+            return Err(AppError::Locked);
+        }
+        Ok(crypto_manager.clone())
+    }
+    
+    /// Encriptado de las entradas con la clave maestra; falla si la bóveda está bloqueada
+    pub fn entry_cipher(&self) -> AppResult<vault::EntryCipher> {
+        let encryption = self.settings.lock()
+            .map_err(|_| AppError::state_lock("components.settings"))?
+            .entry_encryption;
+        Ok(vault::EntryCipher::new(self.unlocked_crypto()?).with_encryption(encryption))
+    }
+    
+    /// Ejecuta `task` en el hilo de la base de datos, con la base abierta. La
+    /// tarea no puede tomar prestado nada del comando: lo que necesite se mueve.
+    pub async fn with_db<T, F>(&self, task: F) -> AppResult<T>
+    where
+        F: FnOnce(&mut database::DatabaseManager) -> AppResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.database
+            .call(move |database| match database.as_mut() {
+                Some(db_manager) => task(db_manager),
+                None => {
+                    error!("❌ Database manager es None en el estado");
+                    Err(AppError::db_not_initialized())
+                }
+            })
+            .await
+            .map_err(|e| AppError::internal_with("errors.dbWorker", e))?
+    }
+    
+    /// Sustituye la base abierta (o la cierra, con `None`)
+    pub async fn set_db(&self, db_manager: Option<database::DatabaseManager>) -> AppResult<()> {
+        self.database
+            .call(move |database| *database = db_manager)
+            .await
+            .map_err(|e| AppError::internal_with("errors.dbWorker", e))
+    }
+}
+
+/// Ejecuta trabajo criptográfico pesado (Argon2, desencriptado masivo) fuera del runtime async
+async fn run_blocking<T, F>(task: F) -> AppResult<T>
+where
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| AppError::internal_with("errors.backgroundTask", e))?
+}
+
 fn main() {
     // Inicializar logging
     env_logger::init();
@@ -76,14 +168,29 @@ fn main() {
             if let Ok(db_path) = database::get_database_path() {
                 if std::path::Path::new(&db_path).exists() {
                     info!("Base de datos existente encontrada, inicializando database_manager...");
-                    match database::DatabaseManager::new_without_migrations(&db_path) {
+                    // Ejecutar migraciones para que las tablas nuevas existan en bases de datos antiguas
+                    match database::DatabaseManager::new(&db_path) {
                         Ok(db_manager) => {
                             info!("Database manager creado exitosamente");
                             // Obtener el estado y configurar el database_manager
                             let state = app.state::<AppState>();
-                            let mut db_state = state.database_manager.lock()
-                                .map_err(|_| "Error al acceder al database manager")?;
-                            *db_state = Some(db_manager);
+                            
+                            // Cargar la configuración guardada
+                            match database::load_settings(db_manager.get_connection()) {
+                                Ok(settings) => {
+                                    if let Some(locale) = i18n::Locale::from_code(&settings.language) {
+                                        i18n::set_locale(locale);
+                                    }
+                                    let mut settings_state = state.settings.lock()
+                                        .map_err(|_| "Error al acceder a la configuración")?;
+                                    *settings_state = settings;
+                                    info!("Configuración cargada en el estado");
+                                }
+                                Err(e) => warn!("No se pudo cargar la configuración: {}", e),
+                            }
+                            
+                            state.database.spawn(move |database| *database = Some(db_manager))
+                                .map_err(|_| "Error al acceder al hilo de la base de datos")?;
                             info!("Database manager configurado en el estado");
                         }
                         Err(e) => {
@@ -98,40 +205,55 @@ fn main() {
                 info!("No se pudo obtener ruta de base de datos");
             }
             
+            // Registrar el atajo global de búsqueda rápida
+            let shortcut = app.state::<AppState>().settings.lock()
+                .map(|settings| settings.quick_search_shortcut.clone())
+                .map_err(|_| "Error al acceder a la configuración")?;
+            if let Err(e) = quick_search::register_shortcut(&app_handle, None, &shortcut) {
+                warn!("No se pudo registrar el atajo de búsqueda rápida '{}': {}", shortcut, e);
+            }
+            
+            // Iniciar el servicio de favicons
+            match favicon::FaviconService::start(app_handle.clone()) {
+                Ok(service) => {
+                    let state = app.state::<AppState>();
+                    let mut favicon_state = state.favicon_service.lock()
+                        .map_err(|_| "Error al acceder al servicio de favicons")?;
+                    *favicon_state = Some(service);
+                    info!("Servicio de favicons iniciado");
+                }
+                Err(e) => warn!("No se pudo iniciar el servicio de favicons: {}", e),
+            }
+            
+            // Programar las copias de seguridad automáticas
+            start_backup_scheduler(app_handle.clone());
+            start_maintenance_scheduler(app_handle.clone());
+            start_field_compaction(app_handle.clone());
+            
             // Emitir evento de inicialización
             app_handle.emit_all("app-ready", ()).unwrap();
             
             // Inicializar el gestor de sincronización
             info!("=== INICIO: Inicializando gestor de sincronización ===");
-            let sync_manager = sync::SyncManager::new_default();
-            info!("✅ SyncManager creado exitosamente");
-            
             let state = app.state::<AppState>();
             info!("✅ Estado de la aplicación obtenido");
             
-            let mut sync_state = state.sync_manager.lock()
-                .map_err(|e| {
-                    error!("❌ Error al acceder al sync manager: {:?}", e);
-                    "Error al acceder al sync manager"
-                })?;
-            info!("✅ Lock del sync manager obtenido");
-            
-            *sync_state = Some(sync_manager);
-            info!("✅ Sync manager guardado en el estado");
+            // Con la configuración guardada, si ya se cargó de la base
+            let sync_config = state.settings.lock()
+                .map(|settings| sync::SyncConfig::from(&settings.sync))
+                .map_err(|_| "Error al acceder a la configuración")?;
+            let sync_store = Arc::new(sync::VaultSyncStore::new(app_handle.clone()));
+            let mut sync_manager = sync::SyncManager::new(sync_config)
+                .with_store(sync_store.clone())
+                .with_journal(sync_store);
+            sync_manager.set_event_handler(Box::new(sync::AppSyncEventHandler::new(app_handle.clone())));
+            info!("✅ SyncManager creado exitosamente");
             
-            // Verificar que se guardó correctamente
-            drop(sync_state);
-            let sync_state_check = state.sync_manager.lock()
-                .map_err(|e| {
-                    error!("❌ Error al verificar sync manager: {:?}", e);
-                    "Error al verificar sync manager"
-                })?;
-            if sync_state_check.is_some() {
-                info!("✅ SyncManager verificado en el estado - INICIALIZACIÓN COMPLETA");
-            } else {
-                error!("❌ SyncManager NO está en el estado - INICIALIZACIÓN FALLIDA");
+            if state.sync_manager.set(sync_manager).is_err() {
+                error!("❌ El SyncManager ya estaba inicializado");
                 return Err("SyncManager no se pudo inicializar".into());
             }
+            info!("✅ Sync manager guardado en el estado");
             
             info!("=== FIN: Gestor de sincronización inicializado ===");
             
@@ -187,44 +309,131 @@ fn main() {
             // Gestión de contraseñas
             create_password_entry,
             get_password_entries,
+            get_password_entry_summaries,
+            quick_search,
+            hide_quick_search,
+            
+            // Auto-type
+            auto_type_entry,
+            get_autotype_sequence,
+            set_autotype_sequence,
+            
+            // Portapapeles y TOTP
+            copy_username,
+            copy_password,
+            copy_totp,
+            set_totp_secret,
             get_password_entry,
+            get_entry_icon,
             update_password_entry,
             delete_password_entry,
+            get_entry_revisions,
+            restore_entry_revision,
+            get_activity_log,
             search_passwords,
             
             // Generador de contraseñas
             generate_password,
+            generate_pin,
+            generate_username,
+            get_generation_history,
+            clear_generation_history,
+            
+            // Políticas de contraseñas por sitio
+            get_password_policies,
+            create_password_policy,
+            update_password_policy,
+            delete_password_policy,
             check_password_strength,
+            check_password_breached,
+            import_breach_dataset,
+            get_breach_dataset_info,
             
             // Categorías
             create_category,
             get_categories,
             update_category,
             delete_category,
+            get_category_tree,
+            move_category,
+            move_entries_to_category,
             
             // Utilidades
             export_passwords,
+            create_backup,
+            verify_backup,
+            restore_backup,
+            rollback_last_migration_backup,
+            set_backup_password,
+            has_backup_password,
+            get_backup_history,
+            compact_database,
+            get_database_location,
+            move_database,
+            get_import_formats,
             import_passwords,
+            get_browser_profiles,
+            import_from_browser,
             get_statistics,
             
+            // Auditoría de seguridad
+            run_security_audit,
+            get_security_report,
+            
             // Autocompletado
             get_autocomplete_suggestions,
             save_autocomplete_data,
             get_active_browser_url,
             check_database_status,
+            
+            // Idioma
+            get_locale,
+            set_locale,
+            
+            // Configuración
+            get_settings,
+            update_settings,
 
             // Sincronización
             get_sync_config,
             get_sync_status,
             get_sync_devices,
             get_sync_stats,
+            get_connection_diagnostics,
             start_sync,
             stop_sync,
+            pause_sync,
+            get_sync_history,
+            get_device_sync_stats,
+            resume_sync,
             start_device_discovery,
             sync_now,
             update_sync_config,
+            connect_cloud_account,
+            disconnect_cloud_account,
+            get_cloud_accounts,
+            get_local_device,
+            update_local_device,
+            begin_pairing,
+            add_device_manually,
+            get_pairings,
+            confirm_pairing,
+            create_pairing_qr,
+            scan_pairing_qr,
             trust_device,
             remove_device,
+            get_sync_scope,
+            set_sync_scope,
+            get_tombstones,
+            get_sync_conflicts,
+            resolve_sync_conflict,
+            send_entry_to_device,
+            get_incoming_entries,
+            respond_to_incoming_entry,
+            share_entry,
+            get_shared_links,
+            revoke_shared_link,
+            open_shared_link,
         ])
         .run(tauri::generate_context!())
         .expect("Error al ejecutar la aplicación");
@@ -236,84 +445,87 @@ fn main() {
 async fn initialize_master_password(
     password: String,
     state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     info!("=== INICIO: Inicializando contraseña maestra ===");
     
     // Validar contraseña
     if password.len() < 8 {
-        return Err("La contraseña debe tener al menos 8 caracteres".to_string());
+        return Err(AppError::validation(Message::new("errors.passwordTooShort").with("min", 8)));
     }
     
     info!("Contraseña validada, obteniendo ruta de base de datos...");
     info!("Llamando a database::get_database_path()...");
     let db_path = database::get_database_path()
-        .map_err(|e| format!("Error al obtener ruta de base de datos: {}", e))?;
+        .map_err(|e| AppError::database("errors.dbPath", e))?;
     info!("Ruta de base de datos obtenida: {}", db_path);
     
     info!("Verificando si el archivo de base de datos existe...");
     let db_exists = std::path::Path::new(&db_path).exists();
     info!("Archivo de base de datos existe: {}", db_exists);
     
-    // EJECUTAR MIGRACIONES PRIMERO
-    info!("=== EJECUTANDO MIGRACIONES ANTES DE CREAR DATABASE MANAGER ===");
-    let connection = rusqlite::Connection::open(&db_path)
-        .map_err(|e| format!("Error al abrir conexión SQLite: {}", e))?;
-    info!("Conexión SQLite abierta para migraciones");
-    
-    info!("Ejecutando migraciones...");
-    database::run_migrations(&connection)
-        .map_err(|e| format!("Error al ejecutar migraciones: {}", e))?;
-    info!("Migraciones ejecutadas exitosamente");
-    
-    // Verificar que las migraciones se ejecutaron correctamente
-    info!("Verificando que la tabla users existe después de las migraciones...");
-    let users_table_exists = table_exists(&connection, "users");
-    info!("Tabla users existe después de migraciones: {}", users_table_exists);
-    
-    if !users_table_exists {
-        error!("ERROR CRÍTICO: La tabla users no existe después de las migraciones");
-        return Err("Error: La tabla users no existe después de ejecutar las migraciones.".to_string());
-    }
-    
-    info!("Verificando estructura de la tabla users...");
-    let table_info = connection.query_row("PRAGMA table_info(users)", [], |row| {
-        let name: String = row.get(1)?;
-        let typ: String = row.get(2)?;
-        Ok((name, typ))
-    });
-    match table_info {
-        Ok(_) => info!("Estructura de tabla users verificada correctamente"),
-        Err(e) => {
-            error!("Error al verificar estructura de tabla users: {}", e);
-            return Err(format!("Error al verificar estructura de tabla users: {}", e));
+    // Abrir la base y migrarla en segundo plano: es E/S de disco
+    let db_manager = run_blocking(move || {
+        // EJECUTAR MIGRACIONES PRIMERO
+        info!("=== EJECUTANDO MIGRACIONES ANTES DE CREAR DATABASE MANAGER ===");
+        let connection = database::open_database(&db_path)
+            .map_err(|e| AppError::database("errors.dbOpen", e))?;
+        info!("Conexión SQLite abierta para migraciones");
+        
+        info!("Ejecutando migraciones...");
+        database::run_migrations(&connection)
+            .map_err(|e| AppError::database("errors.migrations", e))?;
+        info!("Migraciones ejecutadas exitosamente");
+        
+        // Verificar que las migraciones se ejecutaron correctamente
+        info!("Verificando que la tabla users existe después de las migraciones...");
+        let users_table_exists = table_exists(&connection, "users");
+        info!("Tabla users existe después de migraciones: {}", users_table_exists);
+        
+        if !users_table_exists {
+            error!("ERROR CRÍTICO: La tabla users no existe después de las migraciones");
+            return Err(AppError::internal("errors.usersTableMissing"));
         }
-    }
-    
-    // AHORA crear el DatabaseManager (que ya no necesita ejecutar migraciones)
-    info!("Creando database manager (sin migraciones)...");
-    let db_manager = database::DatabaseManager::new_without_migrations(&db_path)
-        .map_err(|e| format!("Error al crear database manager: {}", e))?;
-    info!("Database manager creado correctamente");
-    
-    info!("Obteniendo conexión a base de datos...");
-    let conn = db_manager.get_connection();
-    info!("Conexión a base de datos obtenida");
-    
-    // Obtener crypto manager
-    info!("Obteniendo crypto manager...");
-    let mut crypto_manager = state.crypto_manager.lock()
-        .map_err(|_| "Error al acceder al crypto manager")?;
-    info!("Crypto manager obtenido");
-    
-    // Generar salt y hash
+        
+        info!("Verificando estructura de la tabla users...");
+        let table_info = connection.query_row("PRAGMA table_info(users)", [], |row| {
+            let name: String = row.get(1)?;
+            let typ: String = row.get(2)?;
+            Ok((name, typ))
+        });
+        match table_info {
+            Ok(_) => info!("Estructura de tabla users verificada correctamente"),
+            Err(e) => {
+                error!("Error al verificar estructura de tabla users: {}", e);
+                return Err(AppError::database("errors.usersTableStructure", e));
+            }
+        }
+        
+        // AHORA crear el DatabaseManager (que ya no necesita ejecutar migraciones)
+        info!("Creando database manager (sin migraciones)...");
+        let db_manager = database::DatabaseManager::new_without_migrations(&db_path)
+            .map_err(|e| AppError::database("errors.dbManager", e))?;
+        info!("Database manager creado correctamente");
+        Ok(db_manager)
+    }).await?;
+    
+    // Generar salt, hash y clave maestra fuera del runtime async: Argon2 es costoso
     info!("Generando salt...");
     let salt = crypto::generate_salt();
     info!("Salt generado, longitud: {} bytes", salt.len());
     
-    info!("Generando hash de contraseña...");
-    let hash = crypto::hash_password(&password, &salt)
-        .map_err(|e| format!("Error al generar hash: {}", e))?;
-    info!("Hash generado correctamente");
+    info!("Generando hash de contraseña y derivando clave maestra...");
+    let (hash, unlocked_manager) = {
+        let salt = salt.clone();
+        run_blocking(move || {
+            let hash = crypto::hash_password(&password, &salt)
+                .map_err(|e| AppError::crypto("errors.hashPassword", e))?;
+            let mut manager = crypto::CryptoManager::new();
+            manager.set_master_key(&password, &salt)
+                .map_err(|e| AppError::crypto("errors.setMasterKey", e))?;
+            Ok((hash, manager))
+        }).await?
+    };
+    info!("Hash generado y clave maestra derivada correctamente");
     
     // Codificar salt como string para la base de datos
     info!("Codificando salt para base de datos...");
@@ -326,24 +538,20 @@ async fn initialize_master_password(
     let now = chrono::Utc::now().to_rfc3339();
     
     info!("Insertando usuario con ID: {}", user_id);
-    conn.execute(
-        "INSERT INTO users (id, master_password_hash, salt, created_at) VALUES (?, ?, ?, ?)",
-        [&user_id, &hash, &salt_encoded, &now],
-    ).map_err(|e| format!("Error al insertar usuario: {}", e))?;
-    info!("Usuario insertado correctamente");
-    
-    // Configurar crypto manager
-    info!("Configurando crypto manager...");
-    crypto_manager.set_master_key(&password, &salt)
-        .map_err(|e| format!("Error al configurar crypto manager: {}", e))?;
-    info!("Crypto manager configurado correctamente");
+    state.database.call(move |database| -> AppResult<()> {
+        database::insert_user(db_manager.get_connection(), &user_id, &hash, &salt_encoded, &now)
+            .map_err(|e| AppError::database("errors.insertUser", e))?;
+        info!("Usuario insertado correctamente");
+        *database = Some(db_manager);
+        Ok(())
+    }).await.map_err(|e| AppError::internal_with("errors.dbWorker", e))??;
     
     // Actualizar estado
     info!("Actualizando estado de la aplicación...");
     {
-        let mut db_state = state.database_manager.lock()
-            .map_err(|_| "Error al acceder al database manager del estado")?;
-        *db_state = Some(db_manager);
+        let mut crypto_manager = state.crypto_manager.lock()
+            .map_err(|_| AppError::state_lock("components.cryptoManager"))?;
+        *crypto_manager = unlocked_manager;
     }
     info!("Estado de la aplicación actualizado");
     
@@ -355,116 +563,75 @@ async fn initialize_master_password(
 async fn verify_master_password(
     password: String,
     state: tauri::State<'_, AppState>,
-) -> Result<bool, String> {
+) -> AppResult<bool> {
     info!("🚨🚨🚨 COMANDO verify_master_password EJECUTÁNDOSE 🚨🚨🚨");
     info!("=== INICIO: Verificando contraseña maestra ===");
     info!("Longitud de contraseña recibida: {} caracteres", password.len());
     
-    info!("🔍 Verificando estado del AppState...");
-    info!("🔍 database_manager lock obtenido: {}", state.database_manager.try_lock().is_ok());
-    
     if password.is_empty() {
-        return Err("La contraseña no puede estar vacía".to_string());
-    }
-    
-    info!("Obteniendo database manager...");
-    let db_manager_guard = state.database_manager.lock().map_err(|_| "Error al acceder al database manager")?;
-    info!("Database manager guard obtenido");
-    
-    info!("Verificando si database_manager está presente en el estado...");
-    if db_manager_guard.is_none() {
-        error!("❌ Database manager es None en el estado");
-        return Err("Base de datos no inicializada - database_manager es None".to_string());
+        return Err(AppError::validation("errors.passwordEmpty"));
     }
-    info!("✅ Database manager presente en el estado");
     
-    let db_manager = db_manager_guard.as_ref()
-        .ok_or("Base de datos no inicializada")?;
-    info!("Base de datos inicializada correctamente");
-    
-    info!("Obteniendo conexión...");
-    let conn = db_manager.get_connection();
-    info!("Conexión a base de datos obtenida");
-    
-    info!("Preparando consulta...");
-    let mut stmt = conn.prepare("SELECT master_password_hash, salt FROM users LIMIT 1")
-        .map_err(|e| format!("Error al preparar consulta: {}", e))?;
-    info!("Consulta preparada correctamente");
+    // Leer hash y salt en el hilo de la base de datos
+    info!("Leyendo credenciales guardadas...");
+    let stored_credentials = state.with_db(|db_manager| {
+        info!("✅ Database manager presente en el estado");
+        database::load_master_credentials(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    
+    let (hash, salt_base64) = match stored_credentials {
+        Some(credentials) => credentials,
+        None => {
+            info!("No se encontró usuario en la base de datos");
+            info!("=== FIN: No hay usuario para verificar ===");
+            return Err(AppError::not_found("errors.userNotFound"));
+        }
+    };
+    info!("Hash leído: {} caracteres, salt leído: {} caracteres", hash.len(), salt_base64.len());
     
-    info!("Ejecutando consulta...");
-    let mut rows = stmt.query([])
-        .map_err(|e| format!("Error al ejecutar consulta: {}", e))?;
-    info!("Consulta ejecutada correctamente");
+    info!("Decodificando salt...");
+    let salt = base64::engine::general_purpose::STANDARD.decode(&salt_base64)
+        .map_err(|e| AppError::crypto("errors.decodeSalt", e))?;
+    info!("Salt decodificado: {} bytes", salt.len());
     
-    info!("Leyendo fila...");
-    if let Some(row) = rows.next().map_err(|e| format!("Error al leer fila: {}", e))? {
-        info!("Fila encontrada en la base de datos");
-        
-        let hash: String = row.get(0)
-            .map_err(|e| format!("Error al leer hash: {}", e))?;
-        info!("Hash leído: {} caracteres", hash.len());
-        
-        let salt_base64: String = row.get(1)
-            .map_err(|e| format!("Error al leer salt: {}", e))?;
-        info!("Salt leído: {} caracteres", salt_base64.len());
-        
-        info!("Decodificando salt...");
-        let salt = base64::engine::general_purpose::STANDARD.decode(&salt_base64)
-            .map_err(|e| format!("Error al decodificar salt: {}", e))?;
-        info!("Salt decodificado: {} bytes", salt.len());
-        
-        // Verificar contraseña usando la misma función que se usó para crear
-        info!("Verificando contraseña usando crypto::verify_password...");
-        info!("Hash almacenado en BD: {} caracteres", hash.len());
-        info!("Salt decodificado: {} bytes", salt.len());
-        
+    // Verificar la contraseña y derivar la clave fuera del runtime async (Argon2)
+    info!("Verificando contraseña en segundo plano...");
+    let unlocked_manager = run_blocking(move || {
         let is_valid = crypto::verify_password(&password, &hash)
             .map_err(|e| {
                 error!("❌ Error en crypto::verify_password: {}", e);
-                format!("Error al verificar contraseña: {}", e)
+                AppError::crypto("errors.verifyPassword", e)
             })?;
         info!("Resultado de verificación: {}", is_valid);
+        if !is_valid {
+            return Ok(None);
+        }
         
-        if is_valid {
+        let mut manager = crypto::CryptoManager::new();
+        manager.set_master_key(&password, &salt)
+            .map_err(|e| AppError::crypto("errors.setMasterKey", e))?;
+        Ok(Some(manager))
+    }).await?;
+    
+    match unlocked_manager {
+        Some(manager) => {
             info!("Contraseña válida, estableciendo clave maestra...");
-            {
-                let mut crypto_manager = state.crypto_manager.lock().map_err(|_| "Error al acceder al crypto manager")?;
-                info!("Crypto manager obtenido correctamente");
-                
-                crypto_manager.set_master_key(&password, &salt)
-                    .map_err(|e| format!("Error al establecer clave maestra: {}", e))?;
-                info!("Clave maestra establecida correctamente");
-                
-                // Verificar que el crypto manager esté desbloqueado
-                info!("Verificando estado del crypto manager...");
-                if crypto_manager.is_unlocked() {
-                    info!("✅ Crypto manager está desbloqueado correctamente");
-                } else {
-                    error!("❌ Crypto manager NO está desbloqueado después de set_master_key");
-                }
-            } // El lock se libera aquí
-            
-            // Verificar nuevamente el estado después de liberar el lock
-            info!("Verificando estado del crypto manager después de liberar lock...");
-            let crypto_manager_check = state.crypto_manager.lock().map_err(|_| "Error al acceder al crypto manager")?;
-            if crypto_manager_check.is_unlocked() {
-                info!("✅ Crypto manager sigue desbloqueado en el estado global");
+            let mut crypto_manager = state.crypto_manager.lock().map_err(|_| AppError::state_lock("components.cryptoManager"))?;
+            *crypto_manager = manager;
+            if crypto_manager.is_unlocked() {
+                info!("✅ Crypto manager está desbloqueado correctamente");
             } else {
-                error!("❌ Crypto manager NO está desbloqueado en el estado global");
+                error!("❌ Crypto manager NO está desbloqueado después de set_master_key");
             }
             
             info!("=== FIN: Contraseña maestra verificada correctamente ===");
-            info!("Retornando true - login exitoso");
             Ok(true)
-        } else {
+        }
+        None => {
             info!("=== FIN: Contraseña maestra incorrecta ===");
-            info!("Retornando false - contraseña incorrecta");
             Ok(false)
         }
-    } else {
-        info!("No se encontró usuario en la base de datos");
-        info!("=== FIN: No hay usuario para verificar ===");
-        Err("No se encontró usuario en la base de datos. Debes crear una contraseña maestra primero.".to_string())
     }
 }
 
@@ -473,7 +640,7 @@ async fn change_master_password(
     _old_password: String,
     _new_password: String,
     _state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     // TODO: Implementar cambio de contraseña maestra
     Ok(())
 }
@@ -484,220 +651,465 @@ async fn change_master_password(
 async fn create_password_entry(
     request: models::CreatePasswordRequest,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
+) -> AppResult<models::EntryId> {
     info!("🚨🚨🚨 COMANDO create_password_entry EJECUTÁNDOSE 🚨🚨🚨");
     info!("=== INICIO: Creando nueva entrada de contraseña ===");
     info!("Datos recibidos: title={}, username={}, password_length={}", 
           request.title, request.username, request.password.len());
     
     info!("Verificando crypto manager...");
-    let crypto_manager = state.crypto_manager.lock().map_err(|_| "Error al acceder al crypto manager")?;
-    info!("Crypto manager obtenido");
-    
-    info!("Verificando si crypto manager está desbloqueado...");
-    if !crypto_manager.is_unlocked() {
-        error!("❌ Crypto manager NO está desbloqueado en create_password_entry");
-        return Err("Clave maestra no establecida. Debes hacer login primero.".to_string());
-    }
+    let cipher = state.entry_cipher()?;
     info!("✅ Crypto manager está desbloqueado correctamente");
     
-    info!("Verificando database manager...");
-    let db_manager_guard = state.database_manager.lock().map_err(|_| "Error al acceder al database manager")?;
-    let db_manager = db_manager_guard.as_ref()
-        .ok_or("Base de datos no inicializada")?;
-    info!("Database manager obtenido correctamente");
-    
-    let id = uuid::Uuid::new_v4().to_string();
+    let id = models::EntryId::new();
     let now = chrono::Utc::now().to_rfc3339();
     info!("ID generado: {}, timestamp: {}", id, now);
     
-    info!("Encriptando datos sensibles...");
-    let encrypted_password = crypto_manager.encrypt_data(request.password.as_bytes())
-        .map_err(|e| format!("Error al encriptar contraseña: {}", e))?;
-    info!("Contraseña encriptada correctamente");
-    
-    let encrypted_username = crypto_manager.encrypt_data(request.username.as_bytes())
-        .map_err(|e| format!("Error al encriptar usuario: {}", e))?;
-    info!("Usuario encriptado correctamente");
+    let breach_count = check_breach(&state, &request.password).await;
     
-    let encrypted_title = crypto_manager.encrypt_data(request.title.as_bytes())
-        .map_err(|e| format!("Error al encriptar título: {}", e))?;
-    info!("Título encriptado correctamente");
+    info!("Encriptando datos sensibles...");
+    let entry = models::PasswordEntry {
+        id,
+        title: request.title,
+        username: request.username,
+        password: request.password,
+        url: Some(request.url.unwrap_or_default()),
+        notes: Some(request.notes.unwrap_or_default()),
+        category_id: request.category_id,
+        tags: request.tags,
+        created_at: now.clone(),
+        updated_at: now,
+        last_used: None,
+    };
+    let seal_cipher = cipher.clone();
+    let mut sealed = run_blocking(move || seal_cipher.seal(&entry)).await?;
+    sealed.breach_count = breach_count;
+    info!("Datos sensibles encriptados correctamente");
     
     info!("Guardando en base de datos...");
-    let conn = db_manager.get_connection();
-    info!("Conexión a base de datos obtenida");
-    
-    // Manejar category_id correctamente para evitar errores de clave foránea
-    let category_id: Option<&str> = request.category_id.as_ref()
-        .filter(|&id| !id.is_empty())
-        .map(|x| x.as_str());
-    
-    info!("Category ID a insertar: {:?}", category_id);
-    
-    // Usar rusqlite::params! para manejar Option correctamente
-    conn.execute(
-        "INSERT INTO password_entries (id, title, username, password, url, notes, category_id, tags, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-        rusqlite::params![
-            id,
-            serde_json::to_string(&encrypted_title).unwrap(),
-            serde_json::to_string(&encrypted_username).unwrap(),
-            serde_json::to_string(&encrypted_password).unwrap(),
-            request.url.unwrap_or_default(),
-            request.notes.unwrap_or_default(),
-            category_id,
-            serde_json::to_string(&request.tags).unwrap(),
-            now,
-            now,
-        ],
-    ).map_err(|e| format!("Error al guardar entrada: {}", e))?;
+    let change = state.with_db(move |db_manager| {
+        info!("Category ID a insertar: {:?}", sealed.category_id);
+        let tx = db_manager.get_connection_mut().transaction()?;
+        database::PasswordRepository::new(&tx)
+            .insert(&sealed)
+            .map_err(|e| AppError::database("errors.saveEntry", e))?;
+        record_entry_activity(&tx, models::ActivityAction::Create, sealed.id, None, &sealed.created_at)?;
+        let change = sync::journal_entry_change(&tx, &cipher, sealed.id, ChangeType::Created, &models::EntryField::ALL, &sealed.created_at)?;
+        tx.commit().map_err(|e| AppError::database("errors.saveEntry", e))?;
+        Ok(change)
+    }).await?;
+    sync::track_changes(&state, change.into_iter().collect()).await;
     
     info!("=== FIN: Entrada de contraseña creada exitosamente con ID: {} ===", id);
     Ok(id)
 }
 
+/// Comprueba la contraseña con el origen de filtraciones configurado.
+/// Un fallo de red no impide guardar la entrada: se deja sin comprobar.
+async fn check_breach(state: &AppState, password: &str) -> Option<u64> {
+    let backend = state.settings.lock()
+        .map(|settings| settings.breach_check)
+        .unwrap_or_default();
+    match breach::breach_count(backend, password).await {
+        Ok(count) => {
+            if let Some(count) = count.filter(|count| *count > 0) {
+                warn!("⚠️ La contraseña aparece {} veces en filtraciones conocidas", count);
+            }
+            count
+        }
+        Err(e) => {
+            warn!("No se pudo comprobar si la contraseña está filtrada: {}", e);
+            None
+        }
+    }
+}
+
+/// Lee las filas de la página pedida en el hilo de la base de datos
+async fn load_entry_rows(
+    state: &AppState,
+    request: models::PasswordListRequest,
+) -> AppResult<(Vec<database::EncryptedEntryRow>, usize)> {
+    state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection())
+            .list(&request)
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await
+}
+
 #[tauri::command]
 async fn get_password_entries(
+    request: Option<models::PasswordListRequest>,
     state: tauri::State<'_, AppState>,
-) -> Result<Vec<models::PasswordEntry>, String> {
+) -> AppResult<models::PasswordEntriesPage> {
     info!("=== INICIO: Obteniendo entradas de contraseñas ===");
+    let request = request.unwrap_or_default();
+    let (offset, limit) = (request.offset.unwrap_or(0), request.limit);
     
     info!("Verificando crypto manager...");
-    let crypto_manager = state.crypto_manager.lock().map_err(|_| "Error al acceder al crypto manager")?;
-    info!("Crypto manager obtenido");
-    
-    info!("Verificando si crypto manager está desbloqueado...");
-    if !crypto_manager.is_unlocked() {
-        error!("Crypto manager NO está desbloqueado");
-        return Err("Clave maestra no establecida. Debes hacer login primero.".to_string());
-    }
+    let cipher = state.entry_cipher()?;
     info!("Crypto manager está desbloqueado correctamente");
     
-    info!("Verificando database manager...");
-    let db_manager_guard = state.database_manager.lock().map_err(|_| "Error al acceder al database manager")?;
-    let db_manager = db_manager_guard.as_ref()
-        .ok_or("Base de datos no inicializada")?;
-    info!("Database manager obtenido correctamente");
-    
-    info!("Obteniendo conexión a base de datos...");
-    let conn = db_manager.get_connection();
-    info!("Conexión a base de datos obtenida");
-    
-    let mut stmt = conn.prepare("SELECT id, title, username, password, url, notes, category_id, tags, created_at, updated_at, last_used FROM password_entries ORDER BY updated_at DESC")
-        .map_err(|e| format!("Error al preparar consulta: {}", e))?;
-    
-    let mut entries = Vec::new();
-    let mut rows = stmt.query([])
-        .map_err(|e| format!("Error al ejecutar consulta: {}", e))?;
-    
-    while let Some(row) = rows.next().map_err(|e| format!("Error al leer fila: {}", e))? {
-        let encrypted_title: String = row.get(1)
-            .map_err(|e| format!("Error al leer título: {}", e))?;
-        let encrypted_username: String = row.get(2)
-            .map_err(|e| format!("Error al leer usuario: {}", e))?;
-        let encrypted_password: String = row.get(3)
-            .map_err(|e| format!("Error al leer contraseña: {}", e))?;
-        
-        // Desencriptar datos
-        let encrypted_title_data: crypto::EncryptedData = serde_json::from_str(&encrypted_title)
-            .map_err(|e| format!("Error al parsear título: {}", e))?;
-        let encrypted_username_data: crypto::EncryptedData = serde_json::from_str(&encrypted_username)
-            .map_err(|e| format!("Error al parsear usuario: {}", e))?;
-        let encrypted_password_data: crypto::EncryptedData = serde_json::from_str(&encrypted_password)
-            .map_err(|e| format!("Error al parsear contraseña: {}", e))?;
-        
-        let title = String::from_utf8(crypto_manager.decrypt_data(&encrypted_title_data)
-            .map_err(|e| format!("Error al desencriptar título: {}", e))?)
-            .map_err(|e| format!("Error al convertir título: {}", e))?;
-        
-        let username = String::from_utf8(crypto_manager.decrypt_data(&encrypted_username_data)
-            .map_err(|e| format!("Error al desencriptar usuario: {}", e))?)
-            .map_err(|e| format!("Error al convertir usuario: {}", e))?;
-        
-        let password = String::from_utf8(crypto_manager.decrypt_data(&encrypted_password_data)
-            .map_err(|e| format!("Error al desencriptar contraseña: {}", e))?)
-            .map_err(|e| format!("Error al convertir contraseña: {}", e))?;
-        
-        let entry = models::PasswordEntry {
-            id: row.get::<_, String>(0).unwrap(),
-            title,
-            username,
-            password,
-            url: Some(row.get::<_, String>(4).unwrap()),
-            notes: Some(row.get::<_, String>(5).unwrap()),
-            category_id: row.get::<_, Option<String>>(6).unwrap_or(None),
-            tags: serde_json::from_str(&row.get::<_, String>(7).unwrap()).unwrap_or_default(),
-            created_at: row.get::<_, String>(8).unwrap(),
-            updated_at: row.get::<_, String>(9).unwrap(),
-            last_used: row.get::<_, Option<String>>(10).unwrap_or(None),
-        };
+    let (rows, total) = load_entry_rows(&state, request.clone()).await?;
+    
+    // Desencriptar fuera del runtime async para no bloquear otros comandos
+    let entries = run_blocking(move || {
+        let rows = cipher.paginate(rows, &request)?;
+        cipher.open_all(rows)
+    }).await?;
+    let entries: Vec<models::PasswordEntryDto> = entries.into_iter().map(Into::into).collect();
+    
+    info!("Obtenidas {} de {} entradas de contraseñas", entries.len(), total);
+    Ok(models::PasswordEntriesPage {
+        entries,
+        total,
+        offset,
+        limit,
+    })
+}
+
+/// Listado ligero de entradas: solo desencripta título y usuario, nunca la contraseña
+#[tauri::command]
+async fn get_password_entry_summaries(
+    request: Option<models::PasswordListRequest>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::PasswordSummariesPage> {
+    info!("=== INICIO: Obteniendo resúmenes de entradas ===");
+    let request = request.unwrap_or_default();
+    let (offset, limit) = (request.offset.unwrap_or(0), request.limit);
+    
+    let cipher = state.entry_cipher()?;
+    let (rows, total) = load_entry_rows(&state, request.clone()).await?;
+    
+    let summaries = run_blocking(move || {
+        let rows = cipher.paginate(rows, &request)?;
+        let cipher = &cipher;
+        rows.into_par_iter()
+            .map(|row| {
+                let (title, username) = cipher.open_title_and_username(&row.title, &row.username, row.record.as_deref())?;
+                Ok(models::PasswordEntrySummary {
+                    title,
+                    username,
+                    id: row.id,
+                    url: row.url,
+                    category_id: row.category_id,
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()
+    }).await?;
+    
+    info!("Obtenidos {} de {} resúmenes de entradas", summaries.len(), total);
+    Ok(models::PasswordSummariesPage {
+        entries: summaries,
+        total,
+        offset,
+        limit,
+    })
+}
+
+/// Búsqueda rápida por título, usuario y URL: devuelve las `limit` mejores coincidencias
+#[tauri::command]
+async fn quick_search(
+    query: String,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<models::QuickSearchResult>> {
+    let cipher = state.entry_cipher()?;
+    let (rows, _) = load_entry_rows(&state, models::PasswordListRequest::default()).await?;
+    let query = query.trim().to_lowercase();
+    let limit = limit.unwrap_or(8);
+    
+    let results = run_blocking(move || {
+        let cipher = &cipher;
+        let mut results = rows.into_par_iter()
+            .map(|row| {
+                let (title, username) = cipher.open_title_and_username(&row.title, &row.username, row.record.as_deref())?;
+                let score = quick_search::match_score(&query, &title, &username, row.url.as_deref());
+                Ok(score.map(|score| models::QuickSearchResult {
+                    id: row.id,
+                    title,
+                    username,
+                    url: row.url,
+                    score,
+                }))
+            })
+            .filter_map(|result| result.transpose())
+            .collect::<AppResult<Vec<_>>>()?;
         
-        entries.push(entry);
-    }
-    
-    info!("Obtenidas {} entradas de contraseñas", entries.len());
-    Ok(entries)
+        results.sort_by(|a, b| b.score.cmp(&a.score)
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase())));
+        results.truncate(limit);
+        Ok(results)
+    }).await?;
+    
+    info!("Búsqueda rápida: {} resultados", results.len());
+    Ok(results)
+}
+
+#[tauri::command]
+async fn hide_quick_search(app: tauri::AppHandle) -> AppResult<()> {
+    quick_search::hide_window(&app)
+        .map_err(|e| AppError::internal_with("errors.quickSearchWindow", e))
 }
 
 #[tauri::command]
 async fn get_password_entry(
-    _id: String,
-    _state: tauri::State<'_, AppState>,
-) -> Result<models::PasswordEntry, String> {
-    // TODO: Implementar obtención de entrada específica
-    Err("No implementado".to_string())
+    id: models::EntryId,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::PasswordEntryDto> {
+    info!("=== INICIO: Obteniendo entrada de contraseña {} ===", id);
+    
+    let cipher = state.entry_cipher()?;
+    
+    let row = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection())
+            .get(id)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))
+    }).await?;
+    
+    let entry = run_blocking(move || cipher.open(row)).await?;
+    log_activity(&state, models::ActivityAction::Reveal, Some(entry.id), Some("entry"));
+    info!("=== FIN: Entrada de contraseña obtenida ===");
+    Ok(entry.into())
 }
 
 #[tauri::command]
 async fn update_password_entry(
-    _request: models::UpdatePasswordRequest,
-    _state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    // TODO: Implementar actualización de entrada
+    request: models::UpdatePasswordRequest,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    info!("=== INICIO: Actualizando entrada de contraseña {} ===", request.id);
+    let cipher = state.entry_cipher()?;
+    let retention = retention_policy(&state)?;
+    
+    let entry_id = request.id;
+    let row = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection())
+            .get(entry_id)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))
+    }).await?;
+    let open_cipher = cipher.clone();
+    let mut entry = run_blocking(move || open_cipher.open(row)).await?;
+    let before = entry.clone();
+    
+    // Solo se vuelve a comprobar la contraseña si cambió
+    let breach_count = match request.password {
+        Some(password) if password != entry.password => {
+            let count = check_breach(&state, &password).await;
+            entry.password = password;
+            count
+        }
+        _ => None,
+    };
+    if let Some(title) = request.title {
+        entry.title = title;
+    }
+    if let Some(username) = request.username {
+        entry.username = username;
+    }
+    if request.url.is_some() {
+        entry.url = request.url;
+    }
+    if request.notes.is_some() {
+        entry.notes = request.notes;
+    }
+    if let Some(category_id) = request.category_id {
+        entry.category_id = category_id;
+    }
+    if let Some(tags) = request.tags {
+        entry.tags = tags;
+    }
+    entry.updated_at = chrono::Utc::now().to_rfc3339();
+    let changed_fields = models::changed_fields(&before, &entry);
+    
+    info!("Encriptando datos sensibles...");
+    let seal_cipher = cipher.clone();
+    let mut sealed = run_blocking(move || seal_cipher.seal(&entry)).await?;
+    sealed.breach_count = breach_count;
+    
+    info!("Guardando cambios y revisión anterior...");
+    let change = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        let updated = database::PasswordRepository::new(&tx)
+            .update(&sealed)
+            .map_err(|e| AppError::database("errors.updateEntry", e))?;
+        if !updated {
+            return Err(AppError::not_found("errors.entryNotFound"));
+        }
+        prune_entry_revisions(&tx, &retention)?;
+        record_entry_activity(&tx, models::ActivityAction::Edit, sealed.id, None, &sealed.updated_at)?;
+        let change = sync::journal_entry_change(&tx, &cipher, sealed.id, ChangeType::Modified, &changed_fields, &sealed.updated_at)?;
+        tx.commit().map_err(|e| AppError::database("errors.updateEntry", e))?;
+        Ok(change)
+    }).await?;
+    sync::track_changes(&state, change.into_iter().collect()).await;
+    
+    info!("=== FIN: Entrada de contraseña actualizada ===");
+    Ok(())
+}
+
+/// Aplica la retención del historial de revisiones después de modificar una entrada
+fn prune_entry_revisions(conn: &rusqlite::Connection, retention: &database::RetentionPolicy) -> AppResult<()> {
+    let cutoff = retention.revision_cutoff(chrono::Utc::now());
+    database::prune_revisions(conn, retention.revisions_per_entry, cutoff.as_deref())
+        .map(|_| ())
+        .map_err(|e| AppError::database("errors.revisions", e))
+}
+
+/// Anota un evento de una entrada dentro de la transacción que la modifica
+fn record_entry_activity(
+    conn: &rusqlite::Connection,
+    action: models::ActivityAction,
+    entry_id: models::EntryId,
+    details: Option<&str>,
+    occurred_at: &str,
+) -> AppResult<()> {
+    database::record_activity(conn, action, Some(entry_id), details, occurred_at)
+        .map_err(|e| AppError::database("errors.activityLog", e))
+}
+
+/// Anota un evento que no modifica la bóveda sin esperar a que se guarde. Un
+/// fallo aquí nunca impide la operación.
+fn log_activity(state: &AppState, action: models::ActivityAction, entry_id: Option<models::EntryId>, details: Option<&str>) {
+    let details = details.map(str::to_string);
+    let occurred_at = chrono::Utc::now().to_rfc3339();
+    let queued = state.database.spawn(move |database| {
+        let Some(db_manager) = database.as_ref() else {
+            return;
+        };
+        let recorded = database::record_activity(
+            db_manager.get_connection(),
+            action,
+            entry_id,
+            details.as_deref(),
+            &occurred_at,
+        );
+        if let Err(e) = recorded {
+            warn!("No se pudo anotar la actividad {}: {}", action.as_str(), e);
+        }
+    });
+    if let Err(e) = queued {
+        warn!("No se pudo anotar la actividad {}: {}", action.as_str(), e);
+    }
+}
+
+/// Registro de actividad paginado, filtrable por categoría, entrada y operación
+#[tauri::command]
+async fn get_activity_log(
+    request: Option<models::ActivityLogRequest>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::ActivityLogPage> {
+    let request = request.unwrap_or_default();
+    info!("Obteniendo registro de actividad (categoría {:?})", request.category_id);
+    state.unlocked_crypto()?;
+    
+    let (offset, limit) = (request.offset.unwrap_or(0), request.limit);
+    let (events, total) = state.with_db(move |db_manager| {
+        database::list_activity(db_manager.get_connection(), &request)
+            .map_err(|e| AppError::database("errors.activityLog", e))
+    }).await?;
+    
+    Ok(models::ActivityLogPage { events, total, offset, limit })
+}
+
+/// Contenidos anteriores de una entrada, del más reciente al más antiguo
+#[tauri::command]
+async fn get_entry_revisions(
+    entry_id: models::EntryId,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<models::EntryRevision>> {
+    info!("Obteniendo historial de revisiones de la entrada {}", entry_id);
+    let cipher = state.entry_cipher()?;
+    
+    let revisions = state.with_db(move |db_manager| {
+        database::list_revisions(db_manager.get_connection(), entry_id)
+            .map_err(|e| AppError::database("errors.revisions", e))
+    }).await?;
+    
+    run_blocking(move || {
+        revisions.into_iter()
+            .map(|revision| Ok(models::EntryRevision {
+                revision_id: revision.id,
+                revised_at: revision.revised_at,
+                entry: cipher.open(revision.entry)?.into(),
+            }))
+            .collect()
+    }).await
+}
+
+/// Devuelve la entrada al contenido que tenía en una revisión. El contenido
+/// actual se guarda como revisión, así que la restauración se puede deshacer.
+#[tauri::command]
+async fn restore_entry_revision(
+    entry_id: models::EntryId,
+    revision_id: i64,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    info!("Restaurando la entrada {} a la revisión {}", entry_id, revision_id);
+    let cipher = state.entry_cipher()?;
+    let retention = retention_policy(&state)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    
+    let change = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        let restored = database::restore_revision(&tx, entry_id, revision_id, &now)
+            .map_err(|e| AppError::database("errors.revisions", e))?;
+        if !restored {
+            return Err(AppError::not_found("errors.revisionNotFound"));
+        }
+        prune_entry_revisions(&tx, &retention)?;
+        let details = format!("revision:{}", revision_id);
+        record_entry_activity(&tx, models::ActivityAction::Edit, entry_id, Some(&details), &now)?;
+        // Restaurar reescribe la entrada entera
+        let change = sync::journal_entry_change(&tx, &cipher, entry_id, ChangeType::Modified, &models::EntryField::ALL, &now)?;
+        tx.commit().map_err(|e| AppError::database("errors.revisions", e))?;
+        Ok(change)
+    }).await?;
+    sync::track_changes(&state, change.into_iter().collect()).await;
+    
+    info!("✅ Entrada restaurada");
     Ok(())
 }
 
 #[tauri::command]
 async fn delete_password_entry(
-    id: String,
+    id: models::EntryId,
     state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     info!("🚨🚨🚨 COMANDO delete_password_entry EJECUTÁNDOSE 🚨🚨🚨");
     info!("=== INICIO: Eliminando entrada de contraseña ===");
     info!("ID a eliminar: {}", id);
     
     info!("Verificando crypto manager...");
-    let crypto_manager = state.crypto_manager.lock().map_err(|_| "Error al acceder al crypto manager")?;
-    info!("Crypto manager obtenido");
-    
-    info!("Verificando si crypto manager está desbloqueado...");
-    if !crypto_manager.is_unlocked() {
-        error!("❌ Crypto manager NO está desbloqueado en delete_password_entry");
-        return Err("Clave maestra no establecida. Debes hacer login primero.".to_string());
-    }
+    let cipher = state.entry_cipher()?;
     info!("✅ Crypto manager está desbloqueado correctamente");
     
-    info!("Verificando database manager...");
-    let db_manager_guard = state.database_manager.lock().map_err(|_| "Error al acceder al database manager")?;
-    let db_manager = db_manager_guard.as_ref()
-        .ok_or("Base de datos no inicializada")?;
-    info!("Database manager obtenido correctamente");
-    
     info!("Eliminando entrada de la base de datos...");
-    let conn = db_manager.get_connection();
-    info!("Conexión a base de datos obtenida");
-    
-    let rows_affected = conn.execute(
-        "DELETE FROM password_entries WHERE id = ?",
-        rusqlite::params![id]
-    ).map_err(|e| format!("Error al eliminar entrada: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let change = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        // Se anota antes de borrar para conservar la categoría; si no existía se descarta
+        record_entry_activity(&tx, models::ActivityAction::Delete, id, None, &now)?;
+        let deleted = database::PasswordRepository::new(&tx)
+            .delete(id, &now)
+            .map_err(|e| AppError::database("errors.deleteEntry", e))?;
+        if !deleted {
+            return Ok(None);
+        }
+        // La marca de eliminación viaja como cambio `Deleted`
+        let change = sync::journal_entry_change(&tx, &cipher, id, ChangeType::Deleted, &[], &now)?;
+        tx.commit().map_err(|e| AppError::database("errors.deleteEntry", e))?;
+        Ok(change)
+    }).await?;
     
-    if rows_affected == 0 {
+    let Some(change) = change else {
         info!("⚠️ No se encontró entrada con ID: {}", id);
-        return Err("No se encontró la entrada de contraseña".to_string());
-    }
+        return Err(AppError::not_found("errors.entryNotFound"));
+    };
+    sync::track_changes(&state, vec![change]).await;
     
-    info!("✅ Entrada eliminada exitosamente. Filas afectadas: {}", rows_affected);
+    info!("✅ Entrada eliminada exitosamente");
     info!("=== FIN: Entrada de contraseña eliminada exitosamente ===");
     Ok(())
 }
@@ -706,7 +1118,7 @@ async fn delete_password_entry(
 async fn search_passwords(
     _request: models::SearchRequest,
     _state: tauri::State<'_, AppState>,
-) -> Result<Vec<models::PasswordEntry>, String> {
+) -> AppResult<Vec<models::PasswordEntryDto>> {
     // TODO: Implementar búsqueda
     Ok(Vec::new())
 }
@@ -716,215 +1128,2358 @@ async fn search_passwords(
 #[tauri::command]
 async fn generate_password(
     request: models::PasswordGenerationRequest,
-) -> Result<String, String> {
-    info!("Generando contraseña segura...");
+    url: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::GeneratedPassword> {
+    info!("Generando contraseña ({:?}, {} caracteres)...", request.mode, request.length);
+    
+    // Aplicar la política del sitio, si se genera para una URL que tiene una
+    let policy = match url.as_deref().and_then(favicon::domain_from_url) {
+        Some(host) => state.database
+            .call(move |database| match database.as_ref() {
+                Some(db_manager) => database::find_password_policy(db_manager.get_connection(), &host)
+                    .map_err(|e| AppError::database("errors.dbQuery", e)),
+                None => Ok(None),
+            })
+            .await
+            .map_err(|e| AppError::internal_with("errors.dbWorker", e))??,
+        None => None,
+    };
+    if let Some(policy) = &policy {
+        info!("Aplicando política de contraseñas de {}", policy.domain);
+    }
     
-    let password = crypto::generate_secure_password(request.length);
+    let generated = generator::generate_for_policy(&request, policy.as_ref())?;
+    record_generated_password(&state, &generated.password, url.as_deref());
     
-    info!("Contraseña generada exitosamente");
-    Ok(password)
+    info!("Contraseña generada exitosamente ({:.0} bits de entropía)", generated.entropy_bits);
+    Ok(generated)
 }
 
+/// PIN numérico para cuentas que no aceptan contraseñas alfanuméricas
 #[tauri::command]
-async fn check_password_strength(
-    password: String,
-) -> Result<serde_json::Value, String> {
-    info!("Verificando fortaleza de contraseña...");
-    
-    let mut score = 0;
-    let mut feedback = Vec::new();
-    let mut suggestions = Vec::new();
-    
-    // Verificar longitud
-    if password.len() >= 12 {
-        score += 2;
-    } else if password.len() >= 8 {
-        score += 1;
-        suggestions.push("Usa al menos 12 caracteres para mayor seguridad");
-    } else {
-        feedback.push("La contraseña es muy corta");
-        suggestions.push("Usa al menos 8 caracteres");
-    }
-    
-    // Verificar mayúsculas
-    if password.chars().any(|c| c.is_uppercase()) {
-        score += 1;
-    } else {
-        suggestions.push("Incluye al menos una letra mayúscula");
-    }
-    
-    // Verificar minúsculas
-    if password.chars().any(|c| c.is_lowercase()) {
-        score += 1;
-    } else {
-        suggestions.push("Incluye al menos una letra minúscula");
-    }
-    
-    // Verificar números
-    if password.chars().any(|c| c.is_numeric()) {
-        score += 1;
-    } else {
-        suggestions.push("Incluye al menos un número");
-    }
-    
-    // Verificar símbolos
-    if password.chars().any(|c| !c.is_alphanumeric()) {
-        score += 1;
-    } else {
-        suggestions.push("Incluye al menos un símbolo especial");
-    }
-    
-    // Verificar patrones comunes
-    if password.to_lowercase().contains("password") || 
-       password.to_lowercase().contains("123") ||
-       password.to_lowercase().contains("qwerty") {
-        score -= 2;
-        feedback.push("Evita patrones comunes y secuencias");
-        suggestions.push("No uses palabras o secuencias comunes");
-    }
-    
-    // Normalizar score a 0-100
-    let normalized_score = ((score as f32 / 6.0) * 100.0).max(0.0).min(100.0) as u8;
-    
-    let result = serde_json::json!({
-        "score": normalized_score,
-        "feedback": feedback,
-        "suggestions": suggestions
-    });
-    
-    info!("Fortaleza de contraseña verificada: {}%", normalized_score);
-    Ok(result)
+async fn generate_pin(length: usize) -> AppResult<models::GeneratedPassword> {
+    info!("Generando PIN de {} dígitos...", length);
+    generator::generate_pin(length)
 }
 
-// ===== CATEGORÍAS =====
-
+/// Nombre de usuario aleatorio para registrarse en un sitio nuevo
 #[tauri::command]
-async fn create_category(
-    _name: String,
-    _color: String,
-    _state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    // TODO: Implementar creación de categoría
-    Ok("".to_string())
+async fn generate_username(
+    request: Option<models::UsernameGenerationRequest>,
+) -> AppResult<models::GeneratedUsername> {
+    info!("Generando nombre de usuario...");
+    Ok(generator::generate_username(&request.unwrap_or_default()))
 }
 
-#[tauri::command]
-async fn get_categories(
-    _state: tauri::State<'_, AppState>,
-) -> Result<Vec<serde_json::Value>, String> {
-    // TODO: Implementar obtención de categorías
-    Ok(Vec::new())
+/// Agrega la contraseña al historial de generación. Si la bóveda está bloqueada no se
+/// puede encriptar, así que no se guarda; un fallo aquí nunca impide generar.
+fn record_generated_password(state: &AppState, password: &str, url: Option<&str>) {
+    let keep = state.settings.lock()
+        .map(|settings| settings.generation_history_size)
+        .unwrap_or_default();
+    if keep == 0 {
+        return;
+    }
+    let Ok(cipher) = state.entry_cipher() else {
+        return;
+    };
+    let encrypted = match cipher.encrypt(password.as_bytes(), "fields.generatedPassword") {
+        Ok(encrypted) => encrypted,
+        Err(e) => {
+            warn!("No se pudo guardar la contraseña en el historial de generación: {}", e);
+            return;
+        }
+    };
+    let url = url.filter(|url| !url.is_empty()).map(str::to_string);
+    // No hace falta esperar a que se guarde para devolver la contraseña
+    let queued = state.database.spawn(move |database| {
+        let Some(db_manager) = database.as_ref() else {
+            warn!("No se pudo guardar la contraseña en el historial de generación: base de datos no inicializada");
+            return;
+        };
+        let now = chrono::Utc::now().to_rfc3339();
+        if let Err(e) = database::add_generated_password(db_manager.get_connection(), &encrypted, url.as_deref(), &now, keep) {
+            warn!("No se pudo guardar la contraseña en el historial de generación: {}", e);
+        }
+    });
+    if let Err(e) = queued {
+        warn!("No se pudo guardar la contraseña en el historial de generación: {}", e);
+    }
 }
 
+/// Últimas contraseñas generadas, de la más reciente a la más antigua
 #[tauri::command]
-async fn update_category(
-    _id: String,
-    _name: String,
-    _color: String,
-    _state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    // TODO: Implementar actualización de categoría
-    Ok(())
+async fn get_generation_history(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<models::GenerationHistoryEntry>> {
+    let cipher = state.entry_cipher()?;
+    let rows = state.with_db(|db_manager| {
+        database::list_generation_history(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.generationHistory", e))
+    }).await?;
+    
+    rows.into_iter()
+        .map(|(id, encrypted, url, generated_at)| {
+            Ok(models::GenerationHistoryEntry {
+                id,
+                password: cipher.decrypt(&encrypted, "fields.generatedPassword")?,
+                url,
+                generated_at,
+            })
+        })
+        .collect()
 }
 
+/// Borra el historial de generación; devuelve cuántas contraseñas se eliminaron
 #[tauri::command]
-async fn delete_category(
-    _id: String,
-    _state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    // TODO: Implementar eliminación de categoría
-    Ok(())
+async fn clear_generation_history(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<usize> {
+    state.unlocked_crypto()?;
+    state.with_db(|db_manager| {
+        database::clear_generation_history(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.generationHistory", e))
+    }).await
 }
 
-// ===== UTILIDADES =====
+#[tauri::command]
+async fn check_password_strength(
+    password: String,
+) -> AppResult<models::PasswordStrength> {
+    info!("Verificando fortaleza de contraseña...");
+    let result = security::evaluate_strength(&password);
+    info!("Fortaleza de contraseña verificada: {}%", result.score);
+    Ok(result)
+}
 
+/// Veces que la contraseña aparece en filtraciones conocidas; `None` si la comprobación está desactivada
 #[tauri::command]
-async fn export_passwords(
-    _state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    // TODO: Implementar exportación
-    Ok("".to_string())
+async fn check_password_breached(
+    password: String,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Option<u64>> {
+    let backend = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .breach_check;
+    breach::breach_count(backend, &password).await
+        .map_err(|e| AppError::internal_with("errors.breachCheck", e))
 }
 
+/// Importa un listado de hashes de Pwned Passwords o un filtro ya construido para comprobar sin conexión
 #[tauri::command]
-async fn import_passwords(
-    _data: String,
-    _state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    // TODO: Implementar importación
-    Ok(())
+async fn import_breach_dataset(path: String) -> AppResult<breach::offline::DatasetInfo> {
+    info!("=== INICIO: Importando conjunto de datos de filtraciones ===");
+    let dataset = run_blocking(move || {
+        breach::offline::import_dataset(std::path::Path::new(&path))
+            .map_err(|e| AppError::internal_with("errors.breachDatasetImport", e))
+    }).await?;
+    info!("=== FIN: Conjunto de datos importado ({} hashes, {} bytes) ===", dataset.items, dataset.size_bytes);
+    Ok(dataset)
 }
 
+/// Información del conjunto de datos de filtraciones local, si hay uno importado
 #[tauri::command]
-async fn get_statistics(
-    _state: tauri::State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
-    // TODO: Implementar estadísticas
-    Ok(serde_json::json!({
-        "total_passwords": 0,
-        "weak_passwords": 0,
-        "strong_passwords": 0,
-        "security_score": 0
-    }))
+async fn get_breach_dataset_info() -> AppResult<Option<breach::offline::DatasetInfo>> {
+    run_blocking(|| {
+        breach::offline::dataset_info()
+            .map_err(|e| AppError::internal_with("errors.breachDatasetImport", e))
+    }).await
 }
 
-// ===== AUTOMÁTICO COMPLETADO =====
+// ===== CATEGORÍAS =====
 
-#[tauri::command]
-async fn get_autocomplete_suggestions(
-    request: models::AutofillRequest,
-    state: tauri::State<'_, AppState>,
-) -> Result<Vec<serde_json::Value>, String> {
-    info!("Obteniendo sugerencias de autocompletado para: {}", request.url);
-    
-    let crypto_manager = state.crypto_manager.lock().map_err(|_| "Error al acceder al crypto manager")?;
-    if !crypto_manager.is_unlocked() {
-        return Err("Clave maestra no establecida".to_string());
+/// Longitud máxima del nombre de una categoría
+const MAX_CATEGORY_NAME_LENGTH: usize = 100;
+
+/// Acepta `#RGB` y `#RRGGBB` y lo devuelve como `#RRGGBB` en mayúsculas
+fn normalize_color(color: &str) -> Option<String> {
+    let hex = color.trim().strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
     }
-    
-    let db_manager_guard = state.database_manager.lock().map_err(|_| "Error al acceder al database manager")?;
-    let db_manager = db_manager_guard.as_ref()
-        .ok_or("Base de datos no inicializada")?;
-    
-    // Buscar entradas que coincidan con la URL
+    let hex: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return None,
+    };
+    Some(format!("#{}", hex.to_ascii_uppercase()))
+}
+
+/// Normaliza y valida los datos de una categoría antes de guardarla
+fn validate_category(mut request: models::CategoryRequest) -> AppResult<models::CategoryRequest> {
+    let invalid = |field: &str| AppError::validation(Message::new("errors.invalidCategory").with("field", field));
+    request.name = request.name.trim().to_string();
+    if request.name.is_empty() || request.name.chars().count() > MAX_CATEGORY_NAME_LENGTH {
+        return Err(invalid("name"));
+    }
+    request.color = normalize_color(&request.color).ok_or_else(|| invalid("color"))?;
+    request.icon = request.icon
+        .map(|icon| icon.trim().to_string())
+        .filter(|icon| !icon.is_empty());
+    Ok(request)
+}
+
+/// Comprueba que la categoría exista
+fn require_category(conn: &rusqlite::Connection, id: models::CategoryId) -> AppResult<()> {
+    database::get_category(conn, id)
+        .map_err(|e| AppError::database("errors.dbQuery", e))?
+        .map(|_| ())
+        .ok_or_else(|| AppError::not_found(Message::new("errors.categoryNotFound").with("id", id)))
+}
+
+/// Comprueba que `parent_id` exista y que colgar `id` de ella no cree un ciclo
+fn check_category_parent(conn: &rusqlite::Connection, id: models::CategoryId, parent_id: models::CategoryId) -> AppResult<()> {
+    require_category(conn, parent_id)?;
+    let categories = database::list_categories(conn)
+        .map_err(|e| AppError::database("errors.dbQuery", e))?;
+    if category_tree::creates_cycle(&categories, id, parent_id) {
+        return Err(AppError::validation("errors.categoryCycle"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn create_category(
+    request: models::CategoryRequest,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::Category> {
+    let cipher = state.entry_cipher()?;
+    let request = validate_category(request)?;
+    info!("Creando categoría {}", request.name);
+    
+    let category = models::Category {
+        id: models::CategoryId::new(),
+        name: request.name,
+        color: request.color,
+        icon: request.icon,
+        parent_id: request.parent_id,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let (category, change) = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        if let Some(parent_id) = category.parent_id {
+            require_category(&tx, parent_id)?;
+        }
+        database::insert_category(&tx, &category)
+            .map_err(|e| AppError::database("errors.saveCategory", e))?;
+        let change = sync::journal_category_change(&tx, &cipher, category.id, ChangeType::Created)?;
+        tx.commit().map_err(|e| AppError::database("errors.saveCategory", e))?;
+        info!("Categoría {} creada", category.id);
+        Ok((category, change))
+    }).await?;
+    sync::track_changes(&state, change.into_iter().collect()).await;
+    Ok(category)
+}
+
+/// Categorías ordenadas por nombre, con el número de entradas de cada una
+#[tauri::command]
+async fn get_categories(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<models::CategorySummary>> {
+    state.with_db(|db_manager| {
+        database::list_categories_with_counts(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await
+}
+
+#[tauri::command]
+async fn update_category(
+    id: models::CategoryId,
+    request: models::CategoryRequest,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    let request = validate_category(request)?;
+    info!("Actualizando categoría {}", id);
+    
+    let change = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        if let Some(parent_id) = request.parent_id {
+            check_category_parent(&tx, id, parent_id)?;
+        }
+        let updated = database::update_category(&tx, id, &request)
+            .map_err(|e| AppError::database("errors.saveCategory", e))?;
+        if !updated {
+            return Ok(None);
+        }
+        let change = sync::journal_category_change(&tx, &cipher, id, ChangeType::Modified)?;
+        tx.commit().map_err(|e| AppError::database("errors.saveCategory", e))?;
+        Ok(change)
+    }).await?;
+    let Some(change) = change else {
+        return Err(AppError::not_found(Message::new("errors.categoryNotFound").with("id", id)));
+    };
+    sync::track_changes(&state, vec![change]).await;
+    Ok(())
+}
+
+/// Categorías anidadas según `parent_id`, con el número de entradas de cada
+/// rama
+#[tauri::command]
+async fn get_category_tree(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<models::CategoryNode>> {
+    let categories = state.with_db(|db_manager| {
+        database::list_categories_with_counts(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    Ok(category_tree::build_tree(categories))
+}
+
+/// Cuelga la categoría de `parent_id`, o la lleva a la raíz con `None`
+#[tauri::command]
+async fn move_category(
+    id: models::CategoryId,
+    parent_id: Option<models::CategoryId>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    info!("Moviendo categoría {} a {:?}", id, parent_id);
+    
+    let change = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        if let Some(parent_id) = parent_id {
+            check_category_parent(&tx, id, parent_id)?;
+        }
+        let moved = database::set_category_parent(&tx, id, parent_id)
+            .map_err(|e| AppError::database("errors.saveCategory", e))?;
+        if !moved {
+            return Ok(None);
+        }
+        let change = sync::journal_category_change(&tx, &cipher, id, ChangeType::Moved)?;
+        tx.commit().map_err(|e| AppError::database("errors.saveCategory", e))?;
+        Ok(change)
+    }).await?;
+    let Some(change) = change else {
+        return Err(AppError::not_found(Message::new("errors.categoryNotFound").with("id", id)));
+    };
+    sync::track_changes(&state, vec![change]).await;
+    Ok(())
+}
+
+/// Mueve varias entradas a `category_id`, o las deja sin categoría con `None`.
+/// Devuelve cuántas se movieron.
+#[tauri::command]
+async fn move_entries_to_category(
+    entry_ids: Vec<models::EntryId>,
+    category_id: Option<models::CategoryId>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<usize> {
+    let cipher = state.entry_cipher()?;
+    info!("Moviendo {} entradas a la categoría {:?}", entry_ids.len(), category_id);
+    
+    let now = chrono::Utc::now().to_rfc3339();
+    let (moved, changes) = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        if let Some(category_id) = category_id {
+            require_category(&tx, category_id)?;
+        }
+        let moved = database::PasswordRepository::new(&tx)
+            .move_to_category(&entry_ids, category_id, &now)
+            .map_err(|e| AppError::database("errors.saveEntry", e))?;
+        let mut changes = Vec::with_capacity(moved);
+        for entry_id in entry_ids {
+            changes.extend(sync::journal_entry_change(&tx, &cipher, entry_id, ChangeType::Modified, &[models::EntryField::Category], &now)?);
+        }
+        tx.commit()?;
+        Ok((moved, changes))
+    }).await?;
+    sync::track_changes(&state, changes).await;
+    info!("{} entradas movidas", moved);
+    Ok(moved)
+}
+
+/// Elimina una categoría. Sus entradas pasan a `reassign_to` o quedan sin
+/// categoría, y sus subcategorías pasan a la categoría padre de la eliminada.
+#[tauri::command]
+async fn delete_category(
+    id: models::CategoryId,
+    reassign_to: Option<models::CategoryId>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    if reassign_to == Some(id) {
+        return Err(AppError::validation(Message::new("errors.invalidCategory").with("field", "reassign_to")));
+    }
+    info!("Eliminando categoría {} (entradas a {:?})", id, reassign_to);
+    
+    let now = chrono::Utc::now().to_rfc3339();
+    let changes = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        if let Some(target) = reassign_to {
+            require_category(&tx, target)?;
+        }
+        let repository = database::PasswordRepository::new(&tx);
+        let moved_ids = repository.ids_in_category(id)
+            .map_err(|e| AppError::database("errors.deleteCategory", e))?;
+        let moved = repository.reassign_category(id, reassign_to, &now)
+            .map_err(|e| AppError::database("errors.deleteCategory", e))?;
+        let deleted = database::delete_category(&tx, id)
+            .map_err(|e| AppError::database("errors.deleteCategory", e))?;
+        if !deleted {
+            return Ok(None);
+        }
+        // Las entradas movidas viajan antes que la eliminación de su categoría
+        let mut changes = Vec::with_capacity(moved_ids.len() + 1);
+        for entry_id in moved_ids {
+            changes.extend(sync::journal_entry_change(&tx, &cipher, entry_id, ChangeType::Modified, &[models::EntryField::Category], &now)?);
+        }
+        changes.extend(sync::journal_category_change(&tx, &cipher, id, ChangeType::Deleted)?);
+        tx.commit().map_err(|e| AppError::database("errors.deleteCategory", e))?;
+        info!("{} entradas movidas al eliminar la categoría {}", moved, id);
+        Ok(Some(changes))
+    }).await?;
+    let Some(changes) = changes else {
+        return Err(AppError::not_found(Message::new("errors.categoryNotFound").with("id", id)));
+    };
+    sync::track_changes(&state, changes).await;
+    Ok(())
+}
+
+// ===== UTILIDADES =====
+
+/// Exporta las entradas (filtradas por categoría y etiquetas) en texto plano.
+/// Si se indica una ruta el archivo se escribe ahí; si no, se devuelve el contenido.
+/// KDBX se escribe siempre en un archivo, encriptado con la contraseña indicada.
+#[tauri::command]
+async fn export_passwords(
+    request: models::ExportRequest,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::ExportResult> {
+    info!("=== INICIO: Exportando contraseñas ({:?}) ===", request.format);
+    let cipher = state.entry_cipher()?;
+    let format = serde_json::to_value(request.format).ok()
+        .and_then(|format| format.as_str().map(str::to_string));
+    if request.format == models::ExportFormat::Kdbx {
+        let result = export_kdbx(request, cipher, app, &state).await?;
+        log_activity(&state, models::ActivityAction::Export, None, format.as_deref());
+        return Ok(result);
+    }
+    
+    let (rows, categories) = state.with_db(|db_manager| {
+        let rows = database::PasswordRepository::new(db_manager.get_connection()).list_all()
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        let categories = database::list_categories(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        Ok((rows, categories))
+    }).await?;
+    
+    let result = run_blocking(move || {
+        let progress = progress::Progress::start(app, progress::EXPORT_EVENT, rows.len());
+        let entries = cipher.open_all_with(rows, |entry| progress.advance(&entry.title))?;
+        let entries = export::filter_entries(entries, request.category_id, &request.tags);
+        let content = export::render(request.format, &entries, &categories)
+            .map_err(|e| AppError::internal_with("errors.export", e))?;
+        
+        let content = match request.path {
+            Some(path) => {
+                export::write_file(std::path::Path::new(&path), &content)
+                    .map_err(|e| AppError::internal_with("errors.exportWrite", e))?;
+                info!("Exportación escrita en {}", path);
+                None
+            }
+            None => Some(content),
+        };
+        Ok(models::ExportResult { exported: entries.len(), content })
+    }).await?;
+    
+    log_activity(&state, models::ActivityAction::Export, None, format.as_deref());
+    info!("=== FIN: {} contraseñas exportadas ===", result.exported);
+    Ok(result)
+}
+
+/// Exportación a KeePass: incluye los secretos TOTP y los adjuntos de las entradas
+async fn export_kdbx(
+    request: models::ExportRequest,
+    cipher: vault::EntryCipher,
+    app: tauri::AppHandle,
+    state: &AppState,
+) -> AppResult<models::ExportResult> {
+    let path = request.path.clone()
+        .ok_or_else(|| AppError::validation(Message::new("errors.exportPathRequired")))?;
+    let password = request.password.clone()
+        .filter(|password| !password.is_empty())
+        .ok_or_else(|| AppError::validation(Message::new("errors.exportPasswordRequired")))?;
+    
+    let (rows, categories, totp, attachments) = state.with_db(|db_manager| {
+        let conn = db_manager.get_connection();
+        let repository = database::PasswordRepository::new(conn);
+        let rows = repository.list_all()
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        let totp = repository.totp_secrets()
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        let categories = database::list_categories(conn)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        let attachments = database::list_attachments(conn)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        Ok((rows, categories, totp, attachments))
+    }).await?;
+    
+    let exported = run_blocking(move || {
+        let progress = progress::Progress::start(app, progress::EXPORT_EVENT, rows.len());
+        let entries = cipher.open_all_with(rows, |entry| progress.advance(&entry.title))?;
+        let entries = export::filter_entries(entries, request.category_id, &request.tags);
+        let totp = totp.into_iter()
+            .map(|(id, secret)| Ok((id, cipher.decrypt(&secret, "fields.totp")?)))
+            .collect::<AppResult<std::collections::HashMap<_, _>>>()?;
+        let mut by_entry: std::collections::HashMap<models::EntryId, Vec<kdbx::Attachment>> = std::collections::HashMap::new();
+        for attachment in attachments {
+            let data = cipher.decrypt_bytes(&attachment.data, "fields.attachment")?;
+            by_entry.entry(attachment.entry_id)
+                .or_default()
+                .push(kdbx::Attachment { name: attachment.name, data });
+        }
+        
+        let database = export::kdbx_database(&entries, &categories, &totp, by_entry);
+        let key = kdbx::CompositeKey::new(Some(&password), None)
+            .map_err(|e| AppError::internal_with("errors.export", e))?;
+        let content = kdbx::write(&database, &key)
+            .map_err(|e| AppError::internal_with("errors.export", e))?;
+        export::write_file(std::path::Path::new(&path), &content)
+            .map_err(|e| AppError::internal_with("errors.exportWrite", e))?;
+        info!("Base de datos KDBX escrita en {}", path);
+        Ok(entries.len())
+    }).await?;
+    
+    info!("=== FIN: {} contraseñas exportadas a KDBX ===", exported);
+    Ok(models::ExportResult { exported, content: None })
+}
+
+/// Formatos de otros gestores que se pueden importar
+#[tauri::command]
+async fn get_import_formats() -> AppResult<Vec<models::ImportFormatInfo>> {
+    Ok(import::importers().iter()
+        .map(|importer| models::ImportFormatInfo {
+            format: importer.format(),
+            name: importer.name().to_string(),
+            extension: importer.extension().to_string(),
+            needs_password: importer.needs_password(),
+        })
+        .collect())
+}
+
+/// Perfiles de los navegadores instalados que tienen contraseñas guardadas
+#[tauri::command]
+async fn get_browser_profiles() -> AppResult<Vec<models::BrowserProfile>> {
+    run_blocking(|| Ok(import::browser::profiles())).await
+}
+
+/// Importa las contraseñas guardadas en un perfil de navegador, sin pasar por
+/// una exportación manual
+#[tauri::command]
+async fn import_from_browser(
+    request: models::BrowserImportRequest,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::ImportReport> {
+    info!("=== INICIO: Importando contraseñas de {:?} ({}) ===", request.browser, request.profile_path);
+    let cipher = state.entry_cipher()?;
+    
+    let (preview, resolutions) = (request.preview, request.resolutions.clone());
+    
+    let rows = run_blocking(move || {
+        let password = request.password.as_deref().filter(|password| !password.is_empty());
+        import::browser::read(request.browser, std::path::Path::new(&request.profile_path), password)
+            .map_err(AppError::validation)
+    }).await?;
+    
+    save_import(&state, app, cipher, rows, preview, resolutions).await
+}
+
+/// Color de las categorías creadas a partir de carpetas importadas
+const IMPORTED_CATEGORY_COLOR: &str = "#6B7280";
+
+/// Fila leída y clasificada, con la resolución que se le va a aplicar
+struct PendingImport {
+    row: usize,
+    status: models::ImportRowStatus,
+    resolution: models::ImportResolution,
+    existing_id: Option<models::EntryId>,
+    entry: import::ImportedEntry,
+}
+
+/// Entrada importada ya encriptada, lista para guardarse
+struct SealedImport {
+    entry: database::SealedEntry,
+    /// Adjuntos encriptados: nombre, datos y tamaño original
+    attachments: Vec<(String, String, usize)>,
+    item: PendingImport,
+}
+
+/// Id de la categoría que corresponde a una ruta de carpetas importada, creando
+/// los niveles que falten. `known` asocia (padre, nombre en minúsculas) → id.
+fn import_category(
+    tx: &rusqlite::Transaction,
+    path: &[String],
+    known: &mut std::collections::HashMap<(Option<models::CategoryId>, String), models::CategoryId>,
+    now: &str,
+    created: &mut Vec<models::CategoryId>,
+) -> AppResult<Option<models::CategoryId>> {
+    let mut parent_id: Option<models::CategoryId> = None;
+    for name in path {
+        let key = (parent_id, name.to_lowercase());
+        let id = match known.get(&key) {
+            Some(id) => *id,
+            None => {
+                let category = models::Category {
+                    id: models::CategoryId::new(),
+                    name: name.clone(),
+                    color: IMPORTED_CATEGORY_COLOR.to_string(),
+                    icon: None,
+                    parent_id,
+                    created_at: now.to_string(),
+                };
+                database::insert_category(tx, &category)
+                    .map_err(|e| AppError::database("errors.importSave", e))?;
+                created.push(category.id);
+                known.insert(key, category.id);
+                category.id
+            }
+        };
+        parent_id = Some(id);
+    }
+    Ok(parent_id)
+}
+
+/// Importa las entradas exportadas por otro gestor. Con `preview` solo informa
+/// qué filas son nuevas, duplicadas o casi duplicadas; la importación real
+/// aplica la resolución elegida para cada fila en una única transacción.
+#[tauri::command]
+async fn import_passwords(
+    request: models::ImportRequest,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::ImportReport> {
+    info!("=== INICIO: Importando contraseñas ({:?}) ===", request.format);
+    let cipher = state.entry_cipher()?;
+    let (preview, resolutions) = (request.preview, request.resolutions.clone());
+    
+    let rows = run_blocking(move || {
+        let data = match (request.data, request.path) {
+            (Some(data), _) => data.into_bytes(),
+            (None, Some(path)) => std::fs::read(&path)
+                .map_err(|e| AppError::internal_with("errors.importRead", e))?,
+            (None, None) => return Err(AppError::validation("errors.importNoData")),
+        };
+        let key_file = request.key_file_path
+            .map(|path| std::fs::read(path).map_err(|e| AppError::internal_with("errors.importRead", e)))
+            .transpose()?;
+        let input = import::ImportInput {
+            data: &data,
+            password: request.password.as_deref().filter(|password| !password.is_empty()),
+            key_file: key_file.as_deref(),
+        };
+        import::parse(request.format, &input).map_err(AppError::validation)
+    }).await?;
+    
+    save_import(&state, app, cipher, rows, preview, resolutions).await
+}
+
+/// Compara las filas leídas por un importador con la bóveda y aplica la
+/// resolución de cada una: crear una entrada, omitirla o reemplazar la
+/// existente. En la vista previa solo se clasifican; si no, todo se guarda en
+/// una única transacción y las filas que no se pudieron leer o guardar se
+/// informan una a una.
+async fn save_import(
+    state: &AppState,
+    app: tauri::AppHandle,
+    cipher: vault::EntryCipher,
+    rows: Vec<import::ImportRow>,
+    preview: bool,
+    resolutions: Vec<models::ImportRowResolution>,
+) -> AppResult<models::ImportReport> {
+    let locale = i18n::current_locale();
+    let resolutions: std::collections::HashMap<usize, models::ImportResolution> = resolutions.into_iter()
+        .map(|choice| (choice.row, choice.resolution))
+        .collect();
+    
+    let (existing_rows, _) = load_entry_rows(state, models::PasswordListRequest::default()).await?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let journal_cipher = cipher.clone();
+    
+    let (mut sealed, mut results) = run_blocking(move || {
+        let existing = cipher.open_all(existing_rows)?;
+        let mut index = import::duplicates::DuplicateIndex::new(&existing);
+        let existing_titles: std::collections::HashMap<models::EntryId, &str> = existing.iter()
+            .map(|entry| (entry.id, entry.title.as_str()))
+            .collect();
+        let mut results = Vec::new();
+        let mut pending = Vec::new();
+        for row in rows {
+            let entry = match row.result {
+                Ok(entry) => entry,
+                Err(error) => {
+                    results.push(models::ImportRowResult {
+                        row: row.row,
+                        title: row.title,
+                        status: models::ImportRowStatus::Failed,
+                        resolution: None,
+                        existing_id: None,
+                        entry_id: None,
+                        error: Some(error.render(locale)),
+                    });
+                    continue;
+                }
+            };
+            let (status, existing_id) = match index.check(&entry) {
+                import::duplicates::Match::New => (models::ImportRowStatus::New, None),
+                import::duplicates::Match::Duplicate(id) => (models::ImportRowStatus::Duplicate, id),
+                import::duplicates::Match::NearDuplicate(id) => (models::ImportRowStatus::NearDuplicate, Some(id)),
+            };
+            let resolution = match resolutions.get(&row.row).copied().or_else(|| status.default_resolution()) {
+                // Solo se puede reemplazar una entrada que ya está en la bóveda
+                Some(models::ImportResolution::Overwrite) if existing_id.is_none() => models::ImportResolution::Import,
+                Some(resolution) => resolution,
+                None => models::ImportResolution::Skip,
+            };
+            if resolution != models::ImportResolution::Skip {
+                index.insert(&entry);
+            }
+            pending.push(PendingImport { row: row.row, status, resolution, existing_id, entry });
+        }
+        
+        let (to_save, skipped): (Vec<_>, Vec<_>) = pending.into_iter()
+            .partition(|item| !preview && item.resolution != models::ImportResolution::Skip);
+        results.extend(skipped.into_iter().map(|item| models::ImportRowResult {
+            row: item.row,
+            title: Some(item.entry.title),
+            status: item.status,
+            resolution: Some(item.resolution),
+            existing_id: item.existing_id,
+            entry_id: None,
+            error: None,
+        }));
+        
+        // La vista previa no encripta nada: solo hay progreso al importar de verdad
+        let progress = (!preview).then(|| progress::Progress::start(app, progress::IMPORT_EVENT, to_save.len()));
+        let sealed = to_save.into_par_iter()
+            .map(|mut item| {
+                // Un secreto TOTP que no podemos usar se conserva en las notas
+                let totp = item.entry.totp.take();
+                let totp_secret = match totp {
+                    Some(secret) if totp::TotpConfig::parse(&secret).is_ok() => Some(cipher.encrypt(secret.as_bytes(), "fields.totp")?),
+                    Some(secret) => {
+                        item.entry.custom_fields.push(("TOTP".to_string(), secret));
+                        None
+                    }
+                    None => None,
+                };
+                let attachments = item.entry.attachments.iter()
+                    .map(|attachment| {
+                        let encrypted = cipher.encrypt(&attachment.data, "fields.attachment")?;
+                        Ok((attachment.name.clone(), encrypted, attachment.data.len()))
+                    })
+                    .collect::<AppResult<Vec<_>>>()?;
+                if let Some(progress) = &progress {
+                    progress.advance(&item.entry.title);
+                }
+                // Al reemplazar se conserva el título de la entrada existente, que se
+                // encripta junto con las credenciales nuevas
+                let title = match (item.resolution, &item.existing_id) {
+                    (models::ImportResolution::Overwrite, Some(existing_id)) => existing_titles.get(existing_id).copied(),
+                    _ => None,
+                };
+                let mut entry = cipher.seal(&models::PasswordEntry {
+                    id: models::EntryId::new(),
+                    title: title.unwrap_or(&item.entry.title).to_string(),
+                    username: item.entry.username.clone(),
+                    password: item.entry.password.clone(),
+                    url: item.entry.url.clone(),
+                    notes: item.entry.notes_with_fields(),
+                    category_id: None,
+                    tags: item.entry.tags.clone(),
+                    created_at: now.clone(),
+                    updated_at: now.clone(),
+                    last_used: None,
+                })?;
+                entry.totp_secret = totp_secret;
+                Ok(SealedImport { entry, attachments, item })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok((sealed, results))
+    }).await?;
+    
+    if preview {
+        results.sort_by_key(|result| result.row);
+        let report = import_report(false, 0, results);
+        info!("=== FIN: Vista previa de la importación: {} nuevas, {} omitidas, {} filas fallidas ===",
+              report.imported, report.skipped, report.failed);
+        return Ok(report);
+    }
+    
+    let (mut results, categories_created, changes) = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        
+        // Carpetas del origen → categorías anidadas, reutilizando las que ya existen
+        // con el mismo nombre bajo el mismo padre
+        let mut category_ids: std::collections::HashMap<(Option<models::CategoryId>, String), models::CategoryId> = database::list_categories(&tx)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?
+            .into_iter()
+            .map(|category| ((category.parent_id, category.name.to_lowercase()), category.id))
+            .collect();
+        let mut categories_created = Vec::new();
+        let now = chrono::Utc::now().to_rfc3339();
+        
+        // Las entradas nuevas van a la categoría de su carpeta; las que reemplazan
+        // a una existente conservan la suya
+        for sealed_item in &mut sealed {
+            let item = &sealed_item.item;
+            if !(item.resolution == models::ImportResolution::Overwrite && item.existing_id.is_some()) {
+                sealed_item.entry.category_id = import_category(&tx, &item.entry.folder, &mut category_ids, &now, &mut categories_created)?;
+            }
+        }
+        let writes: Vec<_> = sealed.iter()
+            .map(|sealed_item| match (sealed_item.item.resolution, &sealed_item.item.existing_id) {
+                (models::ImportResolution::Overwrite, Some(existing_id)) => database::EntryWrite::MergeCredentials(*existing_id, &sealed_item.entry),
+                _ => database::EntryWrite::Insert(&sealed_item.entry),
+            })
+            .collect();
+        let saved = database::PasswordRepository::new(&tx)
+            .write_batch(&writes)
+            .map_err(|e| AppError::database("errors.importSave", e))?;
+        
+        let mut changes = Vec::new();
+        for &category_id in &categories_created {
+            changes.extend(sync::journal_category_change(&tx, &journal_cipher, category_id, ChangeType::Created)?);
+        }
+        for (sealed_item, saved) in sealed.iter().zip(saved) {
+            let item = &sealed_item.item;
+            let saved = saved.map(|_| match (item.resolution, &item.existing_id) {
+                (models::ImportResolution::Overwrite, Some(existing_id)) => *existing_id,
+                _ => sealed_item.entry.id,
+            });
+            let (entry_id, error) = match saved {
+                Ok(id) => {
+                    for (name, data, size) in &sealed_item.attachments {
+                        database::insert_attachment(&tx, &uuid::Uuid::new_v4().to_string(), id, name, data, *size, &now)
+                            .map_err(|e| AppError::database("errors.importSave", e))?;
+                    }
+                    let change_type = if id == sealed_item.entry.id { ChangeType::Created } else { ChangeType::Modified };
+                    changes.extend(sync::journal_entry_change(&tx, &journal_cipher, id, change_type, &models::EntryField::ALL, &now)?);
+                    (Some(id), None)
+                }
+                Err(e) => {
+                    warn!("No se pudo guardar la fila {} importada: {}", item.row, e);
+                    (None, Some(Message::new("errors.importSave").render(locale)))
+                }
+            };
+            results.push(models::ImportRowResult {
+                row: item.row,
+                title: Some(item.entry.title.clone()),
+                status: item.status,
+                resolution: Some(item.resolution),
+                existing_id: item.existing_id,
+                entry_id,
+                error,
+            });
+        }
+        tx.commit().map_err(|e| AppError::database("errors.importSave", e))?;
+        Ok((results, categories_created.len(), changes))
+    }).await?;
+    sync::track_changes(state, changes).await;
+    
+    results.sort_by_key(|result| result.row);
+    let report = import_report(true, categories_created, results);
+    info!("=== FIN: {} contraseñas importadas, {} reemplazadas, {} omitidas, {} filas fallidas, {} categorías creadas ===",
+          report.imported, report.updated, report.skipped, report.failed, categories_created);
+    Ok(report)
+}
+
+/// Informe con los totales calculados a partir del resultado de cada fila
+fn import_report(
+    committed: bool,
+    categories_created: usize,
+    rows: Vec<models::ImportRowResult>,
+) -> models::ImportReport {
+    let count = |resolution: models::ImportResolution| rows.iter()
+        .filter(|row| row.error.is_none() && row.resolution == Some(resolution))
+        .count();
+    models::ImportReport {
+        committed,
+        imported: count(models::ImportResolution::Import),
+        updated: count(models::ImportResolution::Overwrite),
+        skipped: count(models::ImportResolution::Skip),
+        failed: rows.iter().filter(|row| row.error.is_some()).count(),
+        categories_created,
+        rows,
+    }
+}
+
+/// Calcula los metadatos de las entradas que todavía no los tienen (creadas antes
+/// de existir las columnas). Solo se desencriptan esas contraseñas, una única vez.
+async fn backfill_password_metadata(
+    state: &AppState,
+    cipher: vault::EntryCipher,
+) -> AppResult<()> {
+    let pending = state.with_db(|db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection())
+            .missing_metadata()
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+    
+    info!("Calculando metadatos de {} contraseñas...", pending.len());
+    let computed = run_blocking(move || {
+        pending.into_par_iter()
+            .map(|(id, encrypted, record)| {
+                let password = cipher.open_credential(&encrypted, record.as_deref(), vault::Credential::Password)?;
+                Ok((id, cipher.metadata(&password)?))
+            })
+            .collect::<AppResult<Vec<_>>>()
+    }).await?;
+    
+    let saved = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        let repository = database::PasswordRepository::new(&tx);
+        for (id, meta) in &computed {
+            repository.set_metadata(*id, meta)?;
+        }
+        tx.commit()?;
+        Ok(computed.len())
+    }).await?;
+    info!("Metadatos de {} contraseñas guardados", saved);
+    Ok(())
+}
+
+/// Vuelve a encriptar el título, el usuario y la contraseña de las entradas que no
+/// están guardadas como indica `cipher`. El contenido es el mismo, así que no se
+/// guardan revisiones ni cambia la fecha de modificación.
+async fn reseal_entries(
+    state: &AppState,
+    cipher: vault::EntryCipher,
+) -> AppResult<usize> {
+    let rows = state.with_db(|db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection())
+            .list_all()
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    
+    let as_record = cipher.encryption() == models::EntryEncryption::Record;
+    let resealed = run_blocking(move || {
+        rows.into_par_iter()
+            .filter(|row| row.record.is_some() != as_record)
+            .map(|row| {
+                let credentials = cipher.open_credentials(&row)?;
+                let sealed = cipher.seal_credentials(&credentials.title, &credentials.username, &credentials.password)?;
+                Ok((row.id, sealed))
+            })
+            .collect::<AppResult<Vec<_>>>()
+    }).await?;
+    
+    state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        let repository = database::PasswordRepository::new(&tx);
+        for (id, credentials) in &resealed {
+            repository.reseal_credentials(*id, credentials)?;
+        }
+        tx.commit()?;
+        Ok(resealed.len())
+    }).await
+}
+
+/// Lee los metadatos de todas las entradas para auditarlas, junto con el título, el
+/// usuario y el registro todavía encriptados
+fn query_audit_inputs(
+    conn: &rusqlite::Connection,
+) -> AppResult<Vec<(security_report::AuditInput, String, String, Option<String>)>> {
+    let rows = database::PasswordRepository::new(conn).audit_rows()?;
+    
+    let now = chrono::Utc::now();
+    Ok(rows.into_iter()
+        .map(|row| {
+            let password_age_days = chrono::DateTime::parse_from_rfc3339(&row.password_changed_at)
+                .map(|changed| (now - changed.with_timezone(&chrono::Utc)).num_seconds() as f64 / 86_400.0)
+                .unwrap_or(0.0)
+                .max(0.0);
+            let input = security_report::AuditInput {
+                entry_id: row.id,
+                url: row.url.filter(|url| !url.is_empty()),
+                strength_score: row.strength,
+                guesses_log10: row.guesses_log10,
+                reused_with: row.reused_with.max(0) as usize,
+                password_age_days,
+                breach_count: row.breach_count.map(|count| count.max(0) as u64),
+            };
+            (input, row.title, row.username, row.record)
+        })
+        .collect())
+}
+
+#[tauri::command]
+async fn get_statistics(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::VaultStatistics> {
+    info!("=== INICIO: Calculando estadísticas ===");
+    let cipher = state.entry_cipher()?;
+    backfill_password_metadata(&state, cipher).await?;
+    
+    let (categories, inputs) = state.with_db(|db_manager| {
+        let conn = db_manager.get_connection();
+        let categories = database::PasswordRepository::new(conn).category_counts()?;
+
+        let inputs: Vec<_> = query_audit_inputs(conn)?
+            .into_iter()
+            .map(|(input, ..)| input)
+            .collect();
+        Ok((categories, inputs))
+    }).await?;
+    
+    let total = inputs.len();
+    let count_level = |level| inputs.iter()
+        .filter(|input| security::StrengthLevel::from_score(input.strength_score) == level)
+        .count();
+    let statistics = models::VaultStatistics {
+        total_passwords: total,
+        categories,
+        weak_passwords: count_level(security::StrengthLevel::Weak),
+        medium_passwords: count_level(security::StrengthLevel::Medium),
+        strong_passwords: count_level(security::StrengthLevel::Strong),
+        reused_passwords: inputs.iter().filter(|input| input.reused_with > 0).count(),
+        old_passwords: inputs.iter()
+            .filter(|input| input.password_age_days > security::OLD_PASSWORD_DAYS)
+            .count(),
+        average_password_age_days: if total == 0 {
+            0.0
+        } else {
+            inputs.iter().map(|input| input.password_age_days).sum::<f64>() / total as f64
+        },
+        security_score: security::vault_security_score(inputs.iter().map(|input| input.risk())),
+    };
+    
+    info!("=== FIN: Estadísticas calculadas: {} entradas, puntaje {} ===", total, statistics.security_score);
+    Ok(statistics)
+}
+
+// ===== COPIAS DE SEGURIDAD =====
+
+const MIN_BACKUP_PASSWORD_LENGTH: usize = 8;
+
+fn validate_backup_password(password: &str) -> AppResult<()> {
+    if password.chars().count() < MIN_BACKUP_PASSWORD_LENGTH {
+        return Err(AppError::validation(
+            Message::new("errors.backupPasswordTooShort").with("min", MIN_BACKUP_PASSWORD_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn backup_error(error: backup::BackupError) -> AppError {
+    match error {
+        backup::BackupError::NotABackup => AppError::validation("errors.backupNotABackup"),
+        backup::BackupError::Decrypt => AppError::validation("errors.backupDecrypt"),
+        backup::BackupError::UnsupportedVersion(version) => {
+            AppError::validation(Message::new("errors.backupVersion").with("version", version))
+        }
+        other => AppError::internal_with("errors.backup", other),
+    }
+}
+
+fn backup_info(path: &str, snapshot: &backup::VaultSnapshot, size_bytes: u64) -> models::BackupInfo {
+    models::BackupInfo {
+        path: path.to_string(),
+        created_at: snapshot.created_at.clone(),
+        entries: snapshot.entries.len(),
+        categories: snapshot.categories.len(),
+        policies: snapshot.policies.len(),
+        size_bytes,
+    }
+}
+
+/// Desencripta la bóveda completa para guardarla en una copia de seguridad
+async fn collect_vault_snapshot(
+    state: &AppState,
+    cipher: vault::EntryCipher,
+) -> AppResult<backup::VaultSnapshot> {
+    let (rows, categories, policies, attachments) = state.with_db(|db_manager| {
+        let conn = db_manager.get_connection();
+        let rows = database::PasswordRepository::new(conn).list_all_with_secrets()?;
+        let categories = database::list_categories(conn)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        let policies = database::list_password_policies(conn)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        let attachments = database::list_attachments(conn)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        Ok((rows, categories, policies, attachments))
+    }).await?;
+    
+    run_blocking(move || {
+        let mut by_entry: std::collections::HashMap<models::EntryId, Vec<backup::BackupAttachment>> = std::collections::HashMap::new();
+        for attachment in attachments {
+            let data = cipher.decrypt_bytes(&attachment.data, "fields.attachment")?;
+            by_entry.entry(attachment.entry_id)
+                .or_default()
+                .push(backup::BackupAttachment { name: attachment.name, data });
+        }
+        let entries = rows.into_par_iter()
+            .map(|(row, totp_secret, autotype_sequence)| {
+                let totp_secret = totp_secret
+                    .map(|secret| cipher.decrypt(&secret, "fields.totp"))
+                    .transpose()?;
+                let attachments = by_entry.get(&row.id).cloned().unwrap_or_default();
+                Ok(backup::BackupEntry {
+                    entry: cipher.open(row)?,
+                    totp_secret,
+                    autotype_sequence,
+                    attachments,
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok(backup::VaultSnapshot::new(entries, categories, policies))
+    }).await
+}
+
+/// Entrada de una copia ya encriptada con la clave maestra, lista para guardarse
+struct SealedBackupEntry {
+    plan: backup::restore::EntryPlan,
+    entry: database::SealedEntry,
+    /// Adjuntos encriptados: nombre, datos y tamaño original
+    attachments: Vec<(String, String, usize)>,
+}
+
+fn policy_request(policy: &models::PasswordPolicy) -> models::PasswordPolicyRequest {
+    models::PasswordPolicyRequest {
+        domain: policy.domain.clone(),
+        min_length: policy.min_length,
+        max_length: policy.max_length,
+        forbidden_characters: policy.forbidden_characters.clone(),
+        require_uppercase: policy.require_uppercase,
+        require_lowercase: policy.require_lowercase,
+        require_numbers: policy.require_numbers,
+        require_symbols: policy.require_symbols,
+    }
+}
+
+/// Calcula qué cambiaría al restaurar la copia y, salvo que sea una vista previa,
+/// lo aplica en una única transacción: si algo falla la bóveda queda como estaba
+async fn restore_vault(
+    state: &AppState,
+    cipher: vault::EntryCipher,
+    snapshot: backup::VaultSnapshot,
+    mode: models::RestoreMode,
+    preview: bool,
+) -> AppResult<models::RestorePreview> {
+    let (rows, current_categories, current_policies) = state.with_db(|db_manager| {
+        let conn = db_manager.get_connection();
+        let rows = database::PasswordRepository::new(conn).list_all()?;
+        let categories = database::list_categories(conn)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        let policies = database::list_password_policies(conn)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
+        Ok((rows, categories, policies))
+    }).await?;
+    
+    let with_attachments = snapshot.has_attachments();
+    let backup::VaultSnapshot { entries, categories, policies, .. } = snapshot;
+    let replace = mode == models::RestoreMode::Replace;
+    let categories: Vec<models::Category> = categories.into_iter()
+        .filter(|category| replace || !current_categories.iter().any(|current| current.id == category.id))
+        .collect();
+    let policies: Vec<models::PasswordPolicy> = policies.into_iter()
+        .filter(|policy| replace || !current_policies.iter().any(|current| current.domain == policy.domain))
+        .collect();
+    let (categories_added, policies_added) = (categories.len(), policies.len());
+    let existing_ids: std::collections::HashSet<models::EntryId> = rows.iter().map(|row| row.id).collect();
+    let existing_categories: std::collections::HashSet<models::CategoryId> = current_categories.iter()
+        .map(|category| category.id)
+        .collect();
+    let journal_cipher = cipher.clone();
+    
+    let (mut result, sealed) = run_blocking(move || {
+        let existing = rows.into_par_iter()
+            .map(|row| {
+                let (title, username) = cipher.open_title_and_username(&row.title, &row.username, row.record.as_deref())?;
+                Ok(backup::restore::ExistingEntry {
+                    title,
+                    username,
+                    id: row.id,
+                    url: row.url,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        let plan = backup::restore::plan_entries(mode, &existing, &entries);
+        
+        let mut result = models::RestorePreview {
+            mode,
+            committed: false,
+            added: 0,
+            updated: 0,
+            skipped: 0,
+            removed: 0,
+            categories_added,
+            policies_added,
+            changes: Vec::with_capacity(entries.len()),
+        };
+        if replace {
+            result.removed = existing.len();
+            result.changes.extend(existing.iter().map(|entry| models::RestoreChange {
+                entry_id: entry.id,
+                title: entry.title.clone(),
+                action: models::RestoreAction::Remove,
+            }));
+        }
+        for (entry_plan, backup_entry) in plan.iter().zip(&entries) {
+            match entry_plan {
+                backup::restore::EntryPlan::Add => result.added += 1,
+                backup::restore::EntryPlan::Update(_) => result.updated += 1,
+                backup::restore::EntryPlan::Skip => result.skipped += 1,
+            }
+            result.changes.push(models::RestoreChange {
+                entry_id: backup_entry.entry.id,
+                title: backup_entry.entry.title.clone(),
+                action: entry_plan.action(),
+            });
+        }
+        if preview {
+            return Ok((result, Vec::new()));
+        }
+        
+        let sealed = plan.into_par_iter()
+            .zip(entries)
+            .filter(|(entry_plan, _)| *entry_plan != backup::restore::EntryPlan::Skip)
+            .map(|(plan, source)| {
+                let mut entry = cipher.seal(&source.entry)?;
+                entry.totp_secret = source.totp_secret.as_deref()
+                    .map(|secret| cipher.encrypt(secret.as_bytes(), "fields.totp"))
+                    .transpose()?;
+                entry.autotype_sequence = source.autotype_sequence;
+                let attachments = source.attachments.iter()
+                    .map(|attachment| {
+                        let encrypted = cipher.encrypt(&attachment.data, "fields.attachment")?;
+                        Ok((attachment.name.clone(), encrypted, attachment.data.len()))
+                    })
+                    .collect::<AppResult<Vec<_>>>()?;
+                Ok(SealedBackupEntry { plan, entry, attachments })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok((result, sealed))
+    }).await?;
+    
+    if preview {
+        info!("Vista previa de restauración: {} nuevas, {} actualizadas, {} omitidas, {} eliminadas",
+              result.added, result.updated, result.skipped, result.removed);
+        return Ok(result);
+    }
+    
+    let now = chrono::Utc::now().to_rfc3339();
+    let changes = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        let restore_error = |e: rusqlite::Error| AppError::database("errors.restoreBackup", e);
+        // Las categorías de la copia no vienen ordenadas de padres a hijas: las
+        // claves foráneas se comprueban al confirmar la transacción
+        tx.pragma_update(None, "defer_foreign_keys", true).map_err(restore_error)?;
+        
+        let repository = database::PasswordRepository::new(&tx);
+        // Una copia que no guardaba los adjuntos no puede llevárselos: las
+        // entradas que vuelven conservan los suyos
+        let kept_attachments = if replace && !with_attachments {
+            database::list_attachments(&tx).map_err(|e| AppError::database("errors.restoreBackup", e))?
+        } else {
+            Vec::new()
+        };
+        if replace {
+            repository.delete_all(&now).map_err(restore_error)?;
+            database::delete_all_categories(&tx)
+                .map_err(|e| AppError::database("errors.restoreBackup", e))?;
+            database::delete_all_password_policies(&tx)
+                .map_err(|e| AppError::database("errors.restoreBackup", e))?;
+        }
+        for category in &categories {
+            database::insert_category(&tx, category)
+                .map_err(|e| AppError::database("errors.restoreBackup", e))?;
+        }
+        let writes: Vec<_> = sealed.iter()
+            .map(|sealed_entry| match &sealed_entry.plan {
+                backup::restore::EntryPlan::Update(target_id) => database::EntryWrite::Replace(*target_id, &sealed_entry.entry),
+                _ => database::EntryWrite::Insert(&sealed_entry.entry),
+            })
+            .collect();
+        for written in repository.write_batch(&writes).map_err(restore_error)? {
+            written.map_err(restore_error)?;
+        }
+        let restored: std::collections::HashSet<models::EntryId> = sealed.iter()
+            .map(|sealed_entry| match &sealed_entry.plan {
+                backup::restore::EntryPlan::Update(target_id) => *target_id,
+                _ => sealed_entry.entry.id,
+            })
+            .collect();
+        if with_attachments {
+            for sealed_entry in &sealed {
+                let entry_id = match &sealed_entry.plan {
+                    backup::restore::EntryPlan::Update(target_id) => {
+                        database::delete_entry_attachments(&tx, *target_id)
+                            .map_err(|e| AppError::database("errors.restoreBackup", e))?;
+                        *target_id
+                    }
+                    _ => sealed_entry.entry.id,
+                };
+                for (name, data, size) in &sealed_entry.attachments {
+                    database::insert_attachment(&tx, &uuid::Uuid::new_v4().to_string(), entry_id, name, data, *size, &now)
+                        .map_err(|e| AppError::database("errors.restoreBackup", e))?;
+                }
+            }
+        }
+        for attachment in kept_attachments.iter().filter(|attachment| restored.contains(&attachment.entry_id)) {
+            database::insert_attachment(&tx, &attachment.id, attachment.entry_id, &attachment.name, &attachment.data, attachment.size, &attachment.created_at)
+                .map_err(|e| AppError::database("errors.restoreBackup", e))?;
+        }
+        for policy in &policies {
+            database::insert_password_policy(&tx, &policy.id, &policy_request(policy), &policy.created_at)
+                .map_err(|e| AppError::database("errors.restoreBackup", e))?;
+        }
+        
+        // Los demás dispositivos reciben la bóveda restaurada como cambios:
+        // lo que no volvió, como eliminado
+        let restored_categories: std::collections::HashSet<models::CategoryId> = categories.iter()
+            .map(|category| category.id)
+            .collect();
+        let mut changes = Vec::new();
+        if replace {
+            for &category_id in existing_categories.difference(&restored_categories) {
+                changes.extend(sync::journal_category_change(&tx, &journal_cipher, category_id, ChangeType::Deleted)?);
+            }
+            for &entry_id in existing_ids.difference(&restored) {
+                changes.extend(sync::journal_entry_change(&tx, &journal_cipher, entry_id, ChangeType::Deleted, &[], &now)?);
+            }
+        }
+        for &category_id in &restored_categories {
+            let change_type = if existing_categories.contains(&category_id) { ChangeType::Modified } else { ChangeType::Created };
+            changes.extend(sync::journal_category_change(&tx, &journal_cipher, category_id, change_type)?);
+        }
+        for &entry_id in &restored {
+            let change_type = if existing_ids.contains(&entry_id) { ChangeType::Modified } else { ChangeType::Created };
+            changes.extend(sync::journal_entry_change(&tx, &journal_cipher, entry_id, change_type, &models::EntryField::ALL, &now)?);
+        }
+        tx.commit().map_err(restore_error)?;
+        Ok(changes)
+    }).await?;
+    sync::track_changes(state, changes).await;
+    
+    result.committed = true;
+    info!("Bóveda restaurada ({:?}): {} nuevas, {} actualizadas, {} omitidas, {} eliminadas",
+          mode, result.added, result.updated, result.skipped, result.removed);
+    Ok(result)
+}
+
+/// Clave de `settings` donde se guarda, encriptada con la clave maestra, la
+/// contraseña de las copias automáticas
+const BACKUP_PASSWORD_SETTING: &str = "backup_password";
+/// Cada cuánto se comprueba si toca una copia automática
+const BACKUP_SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+async fn record_backup_result(
+    state: &AppState,
+    path: &str,
+    automatic: bool,
+    result: &AppResult<models::BackupInfo>,
+) -> AppResult<()> {
+    let path = path.to_string();
+    let (entries, size_bytes, error) = match result {
+        Ok(info) => (info.entries, info.size_bytes, None),
+        Err(e) => (0, 0, Some(e.to_string())),
+    };
+    state.with_db(move |db_manager| {
+        let now = chrono::Utc::now().to_rfc3339();
+        database::record_backup(db_manager.get_connection(), &path, &now, automatic, entries, size_bytes, error.as_deref())
+            .map_err(|e| AppError::database("errors.backupHistory", e))
+    }).await
+}
+
+/// Revisa si toca una copia automática y la crea. Solo puede hacerse con la bóveda
+/// desbloqueada y con una contraseña de copia configurada; si no, espera.
+async fn run_scheduled_backup(state: &AppState) -> AppResult<()> {
+    let preferences = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .backup.clone();
+    if !preferences.enabled || preferences.directory.trim().is_empty() {
+        return Ok(());
+    }
+    let Ok(cipher) = state.entry_cipher() else {
+        return Ok(());
+    };
+    
+    let stored = state.database
+        .call(|database| -> AppResult<_> {
+            let Some(db_manager) = database.as_ref() else {
+                return Ok(None);
+            };
+            let conn = db_manager.get_connection();
+            Ok(Some((
+                database::last_automatic_backup_at(conn).map_err(|e| AppError::database("errors.backupHistory", e))?,
+                database::load_setting_value(conn, BACKUP_PASSWORD_SETTING).map_err(|e| AppError::database("errors.dbQuery", e))?,
+            )))
+        })
+        .await
+        .map_err(|e| AppError::internal_with("errors.dbWorker", e))??;
+    let Some((last_backup, encrypted_password)) = stored else {
+        return Ok(());
+    };
+    let Some(encrypted_password) = encrypted_password else {
+        return Ok(());
+    };
+    let interval = chrono::Duration::hours(preferences.interval_hours as i64);
+    let due = last_backup
+        .and_then(|last| chrono::DateTime::parse_from_rfc3339(&last).ok())
+        .is_none_or(|last| chrono::Utc::now() - last.with_timezone(&chrono::Utc) >= interval);
+    if !due {
+        return Ok(());
+    }
+    
+    info!("=== INICIO: Copia de seguridad automática ===");
+    let directory = std::path::PathBuf::from(preferences.directory.trim());
+    let path = backup::automatic_backup_path(&directory, chrono::Utc::now());
+    let path_string = path.to_string_lossy().to_string();
+    
+    let result = async {
+        let backup_password = cipher.decrypt(&encrypted_password, "fields.backupPassword")?;
+        let snapshot = collect_vault_snapshot(state, cipher).await?;
+        let keep = preferences.keep as usize;
+        let path_string = path_string.clone();
+        run_blocking(move || {
+            std::fs::create_dir_all(&directory)
+                .map_err(|e| AppError::internal_with("errors.backup", e))?;
+            let size = backup::write_backup(&path, &snapshot, &backup_password)
+                .map_err(backup_error)?;
+            backup::rotate(&directory, keep).map_err(backup_error)?;
+            Ok(backup_info(&path_string, &snapshot, size))
+        }).await
+    }.await;
+    
+    match &result {
+        Ok(info) => info!("=== FIN: Copia automática creada en {} ({} entradas) ===", info.path, info.entries),
+        Err(e) => error!("❌ Error en la copia de seguridad automática: {}", e),
+    }
+    record_backup_result(state, &path_string, true, &result).await?;
+    result.map(|_| ())
+}
+
+/// Tarea en segundo plano que crea las copias automáticas según la configuración
+fn start_backup_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(BACKUP_SCHEDULER_TICK);
+        loop {
+            ticker.tick().await;
+            let state = app.state::<AppState>();
+            if let Err(e) = run_scheduled_backup(&state).await {
+                warn!("No se pudo completar la copia de seguridad automática: {}", e);
+            }
+        }
+    });
+}
+
+/// Guarda (encriptada con la clave maestra) la contraseña de las copias automáticas;
+/// `None` la elimina y detiene las copias automáticas
+#[tauri::command]
+async fn set_backup_password(
+    backup_password: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    let encrypted = match backup_password {
+        Some(password) => {
+            validate_backup_password(&password)?;
+            Some(cipher.encrypt(password.as_bytes(), "fields.backupPassword")?)
+        }
+        None => None,
+    };
+    
+    let updated = encrypted.is_some();
+    state.with_db(move |db_manager| {
+        database::save_setting_value(db_manager.get_connection(), BACKUP_PASSWORD_SETTING, encrypted.as_deref())
+            .map_err(|e| AppError::database("errors.saveSettings", e))
+    }).await?;
+    info!("Contraseña de copias automáticas {}", if updated { "actualizada" } else { "eliminada" });
+    Ok(())
+}
+
+/// Indica si hay una contraseña configurada para las copias automáticas
+#[tauri::command]
+async fn has_backup_password(state: tauri::State<'_, AppState>) -> AppResult<bool> {
+    let value = state.with_db(|db_manager| {
+        database::load_setting_value(db_manager.get_connection(), BACKUP_PASSWORD_SETTING)
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    Ok(value.is_some())
+}
+
+/// Copias creadas (manuales y automáticas), de la más reciente a la más antigua
+#[tauri::command]
+async fn get_backup_history(
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<models::BackupRecord>> {
+    state.with_db(move |db_manager| {
+        database::list_backup_history(db_manager.get_connection(), limit.unwrap_or(50))
+            .map_err(|e| AppError::database("errors.backupHistory", e))
+    }).await
+}
+
+/// Crea una copia `.alohobackup` de la bóveda encriptada con `backup_password`
+#[tauri::command]
+async fn create_backup(
+    path: String,
+    backup_password: String,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::BackupInfo> {
+    info!("=== INICIO: Creando copia de seguridad en {} ===", path);
+    validate_backup_password(&backup_password)?;
+    let cipher = state.entry_cipher()?;
+    
+    let snapshot = collect_vault_snapshot(&state, cipher).await?;
+    let result = {
+        let path = path.clone();
+        run_blocking(move || {
+            let size = backup::write_backup(std::path::Path::new(&path), &snapshot, &backup_password)
+                .map_err(backup_error)?;
+            Ok(backup_info(&path, &snapshot, size))
+        }).await
+    };
+    if let Err(e) = record_backup_result(&state, &path, false, &result).await {
+        warn!("No se pudo registrar la copia en el historial: {}", e);
+    }
+    let info = result?;
+    
+    info!("=== FIN: Copia de seguridad creada ({} entradas) ===", info.entries);
+    Ok(info)
+}
+
+/// Comprueba que la copia se puede desencriptar y está íntegra, sin restaurarla
+#[tauri::command]
+async fn verify_backup(
+    path: String,
+    backup_password: String,
+) -> AppResult<models::BackupInfo> {
+    run_blocking(move || {
+        let size = std::fs::metadata(&path)
+            .map_err(|e| AppError::internal_with("errors.backup", e))?
+            .len();
+        let snapshot = backup::read_backup(std::path::Path::new(&path), &backup_password)
+            .map_err(backup_error)?;
+        info!("Copia de seguridad verificada: {} ({} entradas)", path, snapshot.entries.len());
+        Ok(backup_info(&path, &snapshot, size))
+    }).await
+}
+
+/// Restaura una copia `.alohobackup`, reemplazando la bóveda o combinándola con
+/// ella. Con `preview` solo devuelve los cambios que se harían.
+#[tauri::command]
+async fn restore_backup(
+    request: models::RestoreRequest,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::RestorePreview> {
+    info!("=== INICIO: Restaurando copia de seguridad {} ({:?}, vista previa: {}) ===",
+          request.path, request.mode, request.preview);
+    let cipher = state.entry_cipher()?;
+    
+    let models::RestoreRequest { path, backup_password, mode, preview } = request;
+    let snapshot = run_blocking(move || {
+        backup::read_backup(std::path::Path::new(&path), &backup_password)
+            .map_err(backup_error)
+    }).await?;
+    
+    let result = restore_vault(&state, cipher, snapshot, mode, preview).await?;
+    
+    info!("=== FIN: Restauración {} ===", if result.committed { "aplicada" } else { "calculada" });
+    Ok(result)
+}
+
+/// Vuelve a la copia que se hizo automáticamente antes de la última migración
+/// del esquema, por si la migración dañó los datos. No pide la contraseña
+/// maestra: si la migración falló puede que la bóveda no se pueda abrir. La
+/// base sustituida se conserva junto a la copia; la bóveda queda bloqueada y la
+/// base cerrada hasta reiniciar la aplicación.
+#[tauri::command]
+async fn rollback_last_migration_backup(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::MigrationBackupInfo> {
+    info!("=== INICIO: Restaurando la copia previa a la última migración ===");
+    let db_path = database::get_database_path()
+        .map_err(|e| AppError::database("errors.dbPath", e))?;
+    let backup = database::latest_migration_backup(std::path::Path::new(&db_path))
+        .map_err(|e| AppError::internal_with("errors.migrationRollback", e))?
+        .ok_or_else(|| AppError::not_found("errors.migrationBackupNotFound"))?;
+    info!("Última copia previa a una migración: {:?} (esquema v{})", backup.path, backup.schema_version);
+    
+    // Cerrar la conexión antes de sustituir el archivo
+    state.crypto_manager.lock()
+        .map_err(|_| AppError::state_lock("components.cryptoManager"))?
+        .lock();
+    state.set_db(None).await?;
+    
+    let backup_path = backup.path.clone();
+    let rolled_back = run_blocking(move || {
+        database::restore_migration_backup(std::path::Path::new(&db_path), &backup_path)
+            .map_err(|e| AppError::internal_with("errors.migrationRollback", e))
+    }).await?;
+    
+    info!("=== FIN: Copia restaurada; la base sustituida quedó en {:?} ===", rolled_back);
+    Ok(models::MigrationBackupInfo {
+        path: backup.path.to_string_lossy().into_owned(),
+        schema_version: backup.schema_version,
+        rolled_back_path: rolled_back.to_string_lossy().into_owned(),
+    })
+}
+
+// ===== UBICACIÓN DE LA BASE DE DATOS =====
+
+fn database_location() -> AppResult<models::DatabaseLocation> {
+    let path = database::get_database_path()
+        .map_err(|e| AppError::database("errors.dbPath", e))?;
+    let default_path = database::default_database_path()
+        .map_err(|e| AppError::database("errors.dbPath", e))?
+        .to_string_lossy()
+        .into_owned();
+    Ok(models::DatabaseLocation {
+        is_custom: path != default_path,
+        path,
+        default_path,
+    })
+}
+
+/// Ruta actual de la base de datos y la ruta por defecto
+#[tauri::command]
+async fn get_database_location() -> AppResult<models::DatabaseLocation> {
+    database_location()
+}
+
+/// Mueve la base de datos a `directory`, o de vuelta a la ubicación por defecto
+/// con `None`. Primero se copia y se comprueba la copia; solo después se pasa a
+/// usarla y se borra la original, así que si algo falla antes todo sigue como
+/// estaba. No sobrescribe una base que ya exista en el destino.
+#[tauri::command]
+async fn move_database(
+    directory: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::DatabaseLocation> {
+    info!("=== INICIO: Moviendo la base de datos ===");
+    state.unlocked_crypto()?;
+    
+    let default_path = database::default_database_path()
+        .map_err(|e| AppError::database("errors.dbPath", e))?;
+    let target = match directory.as_deref().map(str::trim).filter(|directory| !directory.is_empty()) {
+        Some(directory) => std::path::Path::new(directory)
+            .canonicalize()
+            .ok()
+            .filter(|directory| directory.is_dir())
+            .ok_or_else(|| AppError::validation("errors.databaseDirectoryMissing"))?
+            .join(database::DATABASE_FILE_NAME),
+        None => default_path.clone(),
+    };
+    info!("Destino de la base de datos: {:?}", target);
+    
+    let source = state.with_db(move |db_manager| {
+        let source = db_manager.path().to_path_buf();
+        if source == target {
+            return Err(AppError::validation("errors.databaseLocationUnchanged"));
+        }
+        if target.exists() {
+            return Err(AppError::validation("errors.databaseLocationExists"));
+        }
+        database::copy_database(db_manager.get_connection(), &target)
+            .map_err(|e| AppError::internal_with("errors.moveDatabase", e))?;
+        
+        let location = (target != default_path).then_some(target.as_path());
+        let moved = database::DatabaseManager::new(&target)
+            .and_then(|moved| database::set_database_location(location).map(|_| moved));
+        match moved {
+            Ok(moved) => *db_manager = moved,
+            Err(e) => {
+                if let Err(e) = database::remove_database_files(&target) {
+                    warn!("No se pudo borrar la copia de la base: {}", e);
+                }
+                return Err(AppError::internal_with("errors.moveDatabase", e));
+            }
+        }
+        Ok(source)
+    }).await?;
+    
+    // La base ya está abierta en el destino: la original solo ocupa espacio
+    if let Err(e) = run_blocking(move || {
+        database::remove_database_files(&source).map_err(|e| AppError::internal_with("errors.moveDatabase", e))
+    }).await {
+        warn!("La base se movió pero no se pudo borrar la original: {}", e);
+    }
+    
+    let location = database_location()?;
+    info!("=== FIN: Base de datos movida a {} ===", location.path);
+    Ok(location)
+}
+
+// ===== MANTENIMIENTO =====
+
+/// Clave de `settings` con la fecha del último mantenimiento de la base
+const LAST_MAINTENANCE_SETTING: &str = "last_maintenance_at";
+/// Cada cuánto se comprueba si toca el mantenimiento automático
+const MAINTENANCE_SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Retención de los historiales según la configuración actual
+fn retention_policy(state: &AppState) -> AppResult<database::RetentionPolicy> {
+    let settings = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?;
+    Ok(database::RetentionPolicy {
+        generation_history_size: settings.generation_history_size,
+        backup_history_days: settings.maintenance.backup_history_days,
+        revisions_per_entry: settings.revisions.keep_per_entry,
+        revision_days: settings.revisions.max_age_days,
+    })
+}
+
+/// Compacta la base en el hilo de la base de datos y anota la fecha
+fn compact_and_record(
+    db_manager: &database::DatabaseManager,
+    retention: &database::RetentionPolicy,
+) -> AppResult<models::MaintenanceReport> {
     let conn = db_manager.get_connection();
-    let mut stmt = conn.prepare("SELECT title, username, password FROM password_entries WHERE url LIKE ? OR title LIKE ?")
-        .map_err(|e| format!("Error al preparar consulta: {}", e))?;
+    let report = database::compact_database(conn, retention, chrono::Utc::now())
+        .map_err(|e| AppError::database("errors.maintenance", e))?;
+    database::save_setting_value(conn, LAST_MAINTENANCE_SETTING, Some(&report.completed_at))
+        .map_err(|e| AppError::database("errors.maintenance", e))?;
+    Ok(report)
+}
+
+/// Descarta lo que exceda la retención de los historiales y compacta la base,
+/// devolviendo el espacio recuperado
+#[tauri::command]
+async fn compact_database(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::MaintenanceReport> {
+    state.unlocked_crypto()?;
+    let retention = retention_policy(&state)?;
+    state.with_db(move |db_manager| compact_and_record(db_manager, &retention)).await
+}
+
+/// Compacta la base si el mantenimiento automático está activado y ya pasó el
+/// intervalo configurado. Con la base cerrada no hace nada.
+async fn run_scheduled_maintenance(state: &AppState) -> AppResult<()> {
+    let preferences = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .maintenance.clone();
+    if !preferences.automatic {
+        return Ok(());
+    }
+    let retention = retention_policy(state)?;
+    let interval = chrono::Duration::days(preferences.interval_days as i64);
+    
+    let report = state.database
+        .call(move |database| -> AppResult<_> {
+            let Some(db_manager) = database.as_ref() else {
+                return Ok(None);
+            };
+            let last = database::load_setting_value(db_manager.get_connection(), LAST_MAINTENANCE_SETTING)
+                .map_err(|e| AppError::database("errors.dbQuery", e))?;
+            let due = last
+                .and_then(|last| chrono::DateTime::parse_from_rfc3339(&last).ok())
+                .is_none_or(|last| chrono::Utc::now() - last.with_timezone(&chrono::Utc) >= interval);
+            if !due {
+                return Ok(None);
+            }
+            compact_and_record(db_manager, &retention).map(Some)
+        })
+        .await
+        .map_err(|e| AppError::internal_with("errors.dbWorker", e))??;
+    if let Some(report) = report {
+        info!("Mantenimiento automático completado: {} bytes recuperados", report.reclaimed_bytes);
+    }
+    Ok(())
+}
+
+/// Tarea en segundo plano que compacta la base según la configuración
+fn start_maintenance_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(MAINTENANCE_SCHEDULER_TICK);
+        loop {
+            ticker.tick().await;
+            let state = app.state::<AppState>();
+            if let Err(e) = run_scheduled_maintenance(&state).await {
+                warn!("No se pudo completar el mantenimiento automático: {}", e);
+            }
+        }
+    });
+}
+
+/// Campos encriptados que se convierten al formato compacto en cada lote
+const FIELD_COMPACTION_BATCH: usize = 200;
+
+/// Tarea en segundo plano que convierte al formato compacto los campos
+/// encriptados que quedan en el JSON de versiones anteriores. Va por lotes
+/// para que las demás operaciones de la base no esperen a que termine.
+fn start_field_compaction(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut total = 0;
+        loop {
+            let state = app.state::<AppState>();
+            let converted = state.database
+                .call(|database| -> AppResult<usize> {
+                    let Some(db_manager) = database.as_ref() else {
+                        return Ok(0);
+                    };
+                    database::compact_legacy_fields(db_manager.get_connection(), FIELD_COMPACTION_BATCH)
+                        .map_err(|e| AppError::database("errors.dbQuery", e))
+                })
+                .await
+                .map_err(|e| AppError::internal_with("errors.dbWorker", e))
+                .and_then(|result| result);
+            match converted {
+                Ok(0) => break,
+                Ok(converted) => total += converted,
+                Err(e) => {
+                    warn!("No se pudieron convertir los campos encriptados al formato compacto: {}", e);
+                    break;
+                }
+            }
+            tokio::task::yield_now().await;
+        }
+        if total > 0 {
+            info!("Conversión al formato compacto terminada: {} campos encriptados", total);
+        }
+    });
+}
+
+// ===== AUDITORÍA DE SEGURIDAD =====
+
+/// Vuelve a comprobar todas las contraseñas contra el origen de filtraciones.
+/// Las contraseñas repetidas se consultan una sola vez; si la consulta falla se
+/// conserva el resultado anterior.
+async fn refresh_breach_counts(
+    state: &AppState,
+    cipher: vault::EntryCipher,
+) -> AppResult<()> {
+    use futures::StreamExt;
+    
+    let backend = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .breach_check;
+    if backend == models::BreachCheckBackend::Disabled {
+        return Ok(());
+    }
     
-    let search_pattern = format!("%{}%", request.url);
-    let mut rows = stmt.query([&search_pattern, &search_pattern])
-        .map_err(|e| format!("Error al ejecutar consulta: {}", e))?;
+    let rows = state.with_db(|db_manager| {
+        Ok(database::PasswordRepository::new(db_manager.get_connection()).passwords()?)
+    }).await?;
     
-    let mut suggestions = Vec::new();
-    while let Some(row) = rows.next().map_err(|e| format!("Error al leer fila: {}", e))? {
-        let encrypted_title: String = row.get(0).unwrap();
-        let encrypted_username: String = row.get(1).unwrap();
-        let encrypted_password: String = row.get(2).unwrap();
+    // Agrupar por huella para no desencriptar ni consultar dos veces la misma contraseña
+    let mut groups: std::collections::HashMap<String, ((String, Option<String>), Vec<models::EntryId>)> = std::collections::HashMap::new();
+    for (id, encrypted, record, fingerprint) in rows {
+        let key = fingerprint.unwrap_or_else(|| id.to_string());
+        groups.entry(key).or_insert_with(|| ((encrypted, record), Vec::new())).1.push(id);
+    }
+    let groups: Vec<((String, Option<String>), Vec<models::EntryId>)> = groups.into_values().collect();
+    let passwords = run_blocking(move || {
+        groups.into_par_iter()
+            .map(|((encrypted, record), ids)| {
+                Ok((cipher.open_credential(&encrypted, record.as_deref(), vault::Credential::Password)?, ids))
+            })
+            .collect::<AppResult<Vec<_>>>()
+    }).await?;
+    
+    info!("Comprobando {} contraseñas distintas contra filtraciones...", passwords.len());
+    let results: Vec<(u64, Vec<models::EntryId>)> = futures::stream::iter(passwords)
+        .map(|(password, ids)| async move {
+            match breach::breach_count(backend, &password).await {
+                Ok(count) => count.map(|count| (count, ids)),
+                Err(e) => {
+                    warn!("No se pudo comprobar una contraseña: {}", e);
+                    None
+                }
+            }
+        })
+        .buffer_unordered(8)
+        .filter_map(|result| async move { result })
+        .collect()
+        .await;
+    
+    state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        let repository = database::PasswordRepository::new(&tx);
+        for (count, ids) in &results {
+            for id in ids {
+                repository.set_breach_count(*id, *count)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }).await
+}
+
+/// Audita la bóveda y guarda el informe encriptado como último informe
+#[tauri::command]
+async fn run_security_audit(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::SecurityReport> {
+    info!("=== INICIO: Auditoría de seguridad ===");
+    let cipher = state.entry_cipher()?;
+    backfill_password_metadata(&state, cipher.clone()).await?;
+    refresh_breach_counts(&state, cipher.clone()).await?;
+    
+    let rows = state.with_db(|db_manager| {
+        query_audit_inputs(db_manager.get_connection())
+    }).await?;
+    
+    let (report, encrypted_report) = run_blocking(move || {
+        let mut encrypted_fields = std::collections::HashMap::new();
+        let inputs = rows.into_iter()
+            .map(|(input, title, username, record)| {
+                encrypted_fields.insert(input.entry_id, (title, username, record));
+                input
+            })
+            .collect();
         
-        // Desencriptar datos
-        let encrypted_title_data: crypto::EncryptedData = serde_json::from_str(&encrypted_title)
-            .map_err(|e| format!("Error al parsear título: {}", e))?;
-        let encrypted_username_data: crypto::EncryptedData = serde_json::from_str(&encrypted_username)
-            .map_err(|e| format!("Error al parsear usuario: {}", e))?;
-        let encrypted_password_data: crypto::EncryptedData = serde_json::from_str(&encrypted_password)
-            .map_err(|e| format!("Error al parsear contraseña: {}", e))?;
+        // Solo se desencriptan el título y el usuario de las entradas con problemas
+        let report = security_report::build_report(inputs, |input| {
+            let (title, username, record) = &encrypted_fields[&input.entry_id];
+            cipher.open_title_and_username(title, username, record.as_deref())
+        })?;
         
-        let title = String::from_utf8(crypto_manager.decrypt_data(&encrypted_title_data)
-            .map_err(|e| format!("Error al desencriptar título: {}", e))?)
-            .map_err(|e| format!("Error al convertir título: {}", e))?;
+        let json = serde_json::to_vec(&report)
+            .map_err(|e| AppError::internal_with("errors.securityReport", e))?;
+        let encrypted = cipher.encrypt(&json, "fields.securityReport")?;
+        Ok((report, encrypted))
+    }).await?;
+    
+    let generated_at = report.generated_at.clone();
+    state.with_db(move |db_manager| {
+        database::save_security_report(db_manager.get_connection(), &encrypted_report, &generated_at)
+            .map_err(|e| AppError::database("errors.securityReport", e))
+    }).await?;
+    
+    info!("=== FIN: Auditoría completada: {} entradas con problemas, puntaje {} ===",
+          report.entries.len(), report.security_score);
+    Ok(report)
+}
+
+/// Último informe de seguridad guardado, sin volver a auditar
+#[tauri::command]
+async fn get_security_report(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Option<models::SecurityReport>> {
+    let cipher = state.entry_cipher()?;
+    let encrypted = state.with_db(|db_manager| {
+        database::load_security_report(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.securityReport", e))
+    }).await?;
+    let Some(encrypted) = encrypted else {
+        return Ok(None);
+    };
+    
+    let json = cipher.decrypt(&encrypted, "fields.securityReport")?;
+    let report = serde_json::from_str(&json)
+        .map_err(|e| AppError::internal_with("errors.securityReport", e))?;
+    Ok(Some(report))
+}
+
+// ===== POLÍTICAS DE CONTRASEÑAS POR SITIO =====
+
+/// Normaliza y valida los datos de una política antes de guardarla
+fn validate_policy(mut request: models::PasswordPolicyRequest) -> AppResult<models::PasswordPolicyRequest> {
+    let invalid = |field: &str| AppError::validation(Message::new("errors.invalidPolicy").with("field", field));
+    request.domain = favicon::domain_from_url(&request.domain)
+        .ok_or_else(|| invalid("domain"))?;
+    let valid_length = |length: Option<usize>| {
+        length.is_none_or(|length| (generator::MIN_LENGTH..=generator::MAX_LENGTH).contains(&length))
+    };
+    if !valid_length(request.min_length) {
+        return Err(invalid("min_length"));
+    }
+    if !valid_length(request.max_length) {
+        return Err(invalid("max_length"));
+    }
+    if let (Some(min_length), Some(max_length)) = (request.min_length, request.max_length) {
+        if min_length > max_length {
+            return Err(invalid("min_length"));
+        }
+    }
+    // Sin duplicados para que el texto guardado sea legible
+    let mut forbidden = String::new();
+    for c in request.forbidden_characters.chars().filter(|c| !c.is_whitespace()) {
+        if !forbidden.contains(c) {
+            forbidden.push(c);
+        }
+    }
+    request.forbidden_characters = forbidden;
+    Ok(request)
+}
+
+/// El dominio ya tiene una política (violación de la restricción UNIQUE)
+fn policy_write_error(error: anyhow::Error, domain: &str) -> AppError {
+    let duplicated = matches!(
+        error.downcast_ref::<rusqlite::Error>(),
+        Some(rusqlite::Error::SqliteFailure(failure, _)) if failure.code == rusqlite::ErrorCode::ConstraintViolation
+    );
+    if duplicated {
+        AppError::validation(Message::new("errors.policyExists").with("domain", domain))
+    } else {
+        AppError::database("errors.savePolicy", error)
+    }
+}
+
+#[tauri::command]
+async fn get_password_policies(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<models::PasswordPolicy>> {
+    state.with_db(|db_manager| {
+        database::list_password_policies(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await
+}
+
+#[tauri::command]
+async fn create_password_policy(
+    request: models::PasswordPolicyRequest,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::PasswordPolicy> {
+    state.unlocked_crypto()?;
+    let request = validate_policy(request)?;
+    info!("Creando política de contraseñas para {}", request.domain);
+    
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    let request = {
+        let (id, now) = (id.clone(), now.clone());
+        state.with_db(move |db_manager| {
+            database::insert_password_policy(db_manager.get_connection(), &id, &request, &now)
+                .map_err(|e| policy_write_error(e, &request.domain))?;
+            Ok(request)
+        }).await?
+    };
+    
+    Ok(models::PasswordPolicy {
+        id,
+        domain: request.domain,
+        min_length: request.min_length,
+        max_length: request.max_length,
+        forbidden_characters: request.forbidden_characters,
+        require_uppercase: request.require_uppercase,
+        require_lowercase: request.require_lowercase,
+        require_numbers: request.require_numbers,
+        require_symbols: request.require_symbols,
+        created_at: now.clone(),
+        updated_at: now,
+    })
+}
+
+#[tauri::command]
+async fn update_password_policy(
+    id: String,
+    request: models::PasswordPolicyRequest,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    let request = validate_policy(request)?;
+    info!("Actualizando política de contraseñas {}", id);
+    
+    let now = chrono::Utc::now().to_rfc3339();
+    let policy_id = id.clone();
+    let updated = state.with_db(move |db_manager| {
+        database::update_password_policy(db_manager.get_connection(), &policy_id, &request, &now)
+            .map_err(|e| policy_write_error(e, &request.domain))
+    }).await?;
+    if !updated {
+        return Err(AppError::not_found(Message::new("errors.policyNotFound").with("id", id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_password_policy(
+    id: String,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    info!("Eliminando política de contraseñas {}", id);
+    
+    let policy_id = id.clone();
+    let deleted = state.with_db(move |db_manager| {
+        database::delete_password_policy(db_manager.get_connection(), &policy_id)
+            .map_err(|e| AppError::database("errors.savePolicy", e))
+    }).await?;
+    if !deleted {
+        return Err(AppError::not_found(Message::new("errors.policyNotFound").with("id", id)));
+    }
+    Ok(())
+}
+
+/// Favicon del sitio de la entrada desde la caché. Si todavía no está, se descarga
+/// en segundo plano y se emite `favicon-ready` con el dominio al terminar.
+#[tauri::command]
+async fn get_entry_icon(
+    id: models::EntryId,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Option<models::EntryIcon>> {
+    let url = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection()).url(id)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))
+    }).await?;
+    let Some(domain) = url.as_deref().and_then(favicon::domain_from_url) else {
+        return Ok(None);
+    };
+    
+    let fetch_enabled = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .fetch_favicons;
+    let service = state.favicon_service.lock()
+        .map_err(|_| AppError::state_lock("components.faviconService"))?
+        .clone();
+    let Some(service) = service else {
+        return Ok(None);
+    };
+    
+    Ok(service.get_or_request(&domain, fetch_enabled).map(|icon| models::EntryIcon {
+        domain,
+        mime_type: icon.mime_type.to_string(),
+        data: base64::engine::general_purpose::STANDARD.encode(icon.data),
+    }))
+}
+
+// ===== AUTO-TYPE =====
+
+/// Escribe los datos de la entrada en la ventana que tenía el foco antes de AlohoPass.
+/// La secuencia se toma de la llamada, luego de la entrada y por último la de por defecto.
+#[tauri::command]
+async fn auto_type_entry(
+    id: models::EntryId,
+    sequence: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    info!("=== INICIO: Auto-type de la entrada {} ===", id);
+    let cipher = state.entry_cipher()?;
+    
+    let (row, entry_sequence) = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection()).get_with_autotype(id)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))
+    }).await?;
+    let sequence = sequence
+        .or(entry_sequence)
+        .unwrap_or_else(|| autotype::DEFAULT_SEQUENCE.to_string());
+    
+    let domain = run_blocking(move || {
+        let entry = cipher.open(row)?;
+        let fields = autotype::AutoTypeFields {
+            title: &entry.title,
+            username: &entry.username,
+            password: &entry.password,
+            url: entry.url.as_deref().unwrap_or_default(),
+            notes: entry.notes.as_deref().unwrap_or_default(),
+        };
+        let actions = autotype::parse_sequence(&sequence, &fields)
+            .map_err(|e| AppError::validation(Message::new("errors.autotypeSequence").with("error", e)))?;
         
-        let username = String::from_utf8(crypto_manager.decrypt_data(&encrypted_username_data)
-            .map_err(|e| format!("Error al desencriptar usuario: {}", e))?)
-            .map_err(|e| format!("Error al convertir usuario: {}", e))?;
+        // Ocultar AlohoPass para que el foco vuelva a la ventana anterior
+        for (label, window) in app.windows() {
+            let result = if label == quick_search::WINDOW_LABEL { window.hide() } else { window.minimize() };
+            if let Err(e) = result {
+                warn!("No se pudo ocultar la ventana {}: {}", label, e);
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(300));
         
-        let password = String::from_utf8(crypto_manager.decrypt_data(&encrypted_password_data)
-            .map_err(|e| format!("Error al desencriptar contraseña: {}", e))?)
-            .map_err(|e| format!("Error al convertir contraseña: {}", e))?;
+        autotype::type_actions(&actions)
+            .map_err(|e| AppError::internal_with("errors.autotype", e))?;
+        Ok(entry.url.as_deref().and_then(domain::registrable_domain_from_url))
+    }).await?;
+    
+    log_activity(&state, models::ActivityAction::Fill, Some(id), domain.as_deref());
+    info!("=== FIN: Auto-type completado ===");
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_autotype_sequence(
+    id: models::EntryId,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Option<String>> {
+    state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection()).autotype_sequence(id)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))
+    }).await
+}
+
+/// Guarda la secuencia de auto-type propia de la entrada; `None` o vacía vuelve a la de por defecto
+#[tauri::command]
+async fn set_autotype_sequence(
+    id: models::EntryId,
+    sequence: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    
+    let sequence = sequence.filter(|s| !s.trim().is_empty());
+    if let Some(sequence) = &sequence {
+        autotype::validate_sequence(sequence)
+            .map_err(|e| AppError::validation(Message::new("errors.autotypeSequence").with("error", e)))?;
+    }
+    
+    let updated = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection())
+            .set_autotype_sequence(id, sequence.as_deref())
+            .map_err(|e| AppError::database("errors.saveEntry", e))
+    }).await?;
+    
+    if !updated {
+        return Err(AppError::not_found("errors.entryNotFound"));
+    }
+    
+    info!("Secuencia de auto-type actualizada para la entrada {}", id);
+    Ok(())
+}
+
+// ===== PORTAPAPELES =====
+
+/// Lee una columna encriptada de la entrada; `None` si la columna está vacía
+async fn load_encrypted_column(
+    state: &AppState,
+    id: models::EntryId,
+    column: database::SecretColumn,
+) -> AppResult<Option<String>> {
+    state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection()).secret(id, column)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))
+    }).await
+}
+
+/// Tiempo tras el cual se limpia el portapapeles según la configuración (0 = nunca)
+fn clipboard_clear_delay(state: &AppState) -> AppResult<Option<std::time::Duration>> {
+    let settings = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?;
+    Ok(match settings.clipboard_clear_seconds {
+        0 => None,
+        seconds => Some(std::time::Duration::from_secs(seconds as u64)),
+    })
+}
+
+/// Desencripta un campo de la entrada y lo copia al portapapeles sin pasar por el frontend
+async fn copy_entry_field(
+    state: &AppState,
+    id: models::EntryId,
+    credential: vault::Credential,
+) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    let row = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection()).get(id)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))
+    }).await?;
+    let clear_after = clipboard_clear_delay(state)?;
+    let clipboard = state.clipboard.clone();
+    
+    run_blocking(move || {
+        let value = cipher.open_credential(credential.column(&row), row.record.as_deref(), credential)?;
+        clipboard.copy_secret(&value, clear_after)
+            .map_err(|e| AppError::internal_with("errors.clipboard", e))
+    }).await?;
+    
+    log_activity(state, models::ActivityAction::Reveal, Some(id), Some(credential.name()));
+    info!("Campo {} de la entrada {} copiado al portapapeles", credential.name(), id);
+    Ok(())
+}
+
+#[tauri::command]
+async fn copy_username(id: models::EntryId, state: tauri::State<'_, AppState>) -> AppResult<()> {
+    copy_entry_field(&state, id, vault::Credential::Username).await
+}
+
+#[tauri::command]
+async fn copy_password(id: models::EntryId, state: tauri::State<'_, AppState>) -> AppResult<()> {
+    copy_entry_field(&state, id, vault::Credential::Password).await
+}
+
+/// Copia el código TOTP actual y devuelve los segundos que le quedan de validez
+#[tauri::command]
+async fn copy_totp(id: models::EntryId, state: tauri::State<'_, AppState>) -> AppResult<u64> {
+    let cipher = state.entry_cipher()?;
+    let encrypted = load_encrypted_column(&state, id, database::SecretColumn::TotpSecret).await?
+        .ok_or_else(|| AppError::not_found("errors.totpNotConfigured"))?;
+    let clear_after = clipboard_clear_delay(&state)?;
+    let clipboard = state.clipboard.clone();
+    
+    let remaining = run_blocking(move || {
+        let secret = cipher.decrypt(&encrypted, "fields.totp")?;
+        let config = totp::TotpConfig::parse(&secret)
+            .map_err(|e| AppError::validation(Message::new("errors.totpSecret").with("error", e)))?;
+        let (code, remaining) = config.generate_now();
+        // El código deja de ser válido al terminar su periodo: no tiene sentido dejarlo más tiempo
+        let clear_after = clear_after.map(|delay| delay.min(std::time::Duration::from_secs(remaining)));
+        clipboard.copy_secret(&code, clear_after)
+            .map_err(|e| AppError::internal_with("errors.clipboard", e))?;
+        Ok(remaining)
+    }).await?;
+    
+    log_activity(&state, models::ActivityAction::Reveal, Some(id), Some(database::SecretColumn::TotpSecret.name()));
+    info!("Código TOTP de la entrada {} copiado al portapapeles", id);
+    Ok(remaining)
+}
+
+/// Guarda el secreto TOTP (base32 o URI `otpauth://`) de la entrada; `None` o vacío lo elimina
+#[tauri::command]
+async fn set_totp_secret(
+    id: models::EntryId,
+    secret: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    
+    let encrypted = match secret.filter(|s| !s.trim().is_empty()) {
+        Some(secret) => {
+            totp::TotpConfig::parse(&secret)
+                .map_err(|e| AppError::validation(Message::new("errors.totpSecret").with("error", e)))?;
+            Some(cipher.encrypt(secret.trim().as_bytes(), "fields.totp")?)
+        }
+        None => None,
+    };
+    
+    let updated = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection())
+            .set_totp_secret(id, encrypted.as_deref())
+            .map_err(|e| AppError::database("errors.saveEntry", e))
+    }).await?;
+    
+    if !updated {
+        return Err(AppError::not_found("errors.entryNotFound"));
+    }
+    
+    info!("Secreto TOTP actualizado para la entrada {}", id);
+    Ok(())
+}
+
+// ===== AUTOMÁTICO COMPLETADO =====
+
+#[tauri::command]
+async fn get_autocomplete_suggestions(
+    request: models::AutofillRequest,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Vec<serde_json::Value>> {
+    info!("Obteniendo sugerencias de autocompletado para: {}", request.url);
+    
+    let cipher = state.entry_cipher()?;
+    
+    // Buscar las entradas del mismo dominio registrable que la URL
+    let Some(domain) = domain::registrable_domain_from_url(&request.url) else {
+        info!("La URL no tiene un dominio registrable, sin sugerencias");
+        return Ok(Vec::new());
+    };
+    let lookup = domain.clone();
+    let rows = state.with_db(move |db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection()).credentials_for_domain(&lookup)
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    
+    let mut suggestions = Vec::new();
+    for row in rows {
+        // Desencriptar datos
+        let entry = cipher.open(row)?;
         
         let suggestion = serde_json::json!({
-            "title": title,
-            "username": username,
-            "password": password
+            "title": entry.title,
+            "username": entry.username,
+            "password": entry.password
         });
         
         suggestions.push(suggestion);
@@ -940,55 +3495,70 @@ async fn save_autocomplete_data(
     _username: String,
     _password: String,
     _state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     // TODO: Implementar guardado de datos de autocompletado
     Ok(())
 } 
 
+/// URL de la pestaña activa del navegador en primer plano
 #[tauri::command]
-async fn get_active_browser_url() -> Result<String, String> {
-    // Por ahora retornamos una URL de ejemplo
-    // En una implementación real, esto requeriría permisos del sistema
-    // para detectar la ventana activa del navegador
-    Ok("https://example.com".to_string())
-} 
+async fn get_active_browser_url() -> AppResult<String> {
+    use platform::active_window::{self, ActiveWindowError};
+    
+    active_window::active_browser_url().await.map_err(|e| {
+        warn!("No se pudo obtener la URL del navegador activo: {}", e);
+        match e {
+            ActiveWindowError::PermissionDenied(hint) => AppError::permission_denied(
+                Message::new("errors.activeWindowPermission").with("hint", hint),
+            ),
+            ActiveWindowError::NotABrowser(app) => AppError::not_found(
+                Message::new("errors.notABrowser").with("app", app),
+            ),
+            ActiveWindowError::UrlNotFound(app) => AppError::not_found(
+                Message::new("errors.browserUrlNotFound").with("app", app),
+            ),
+            ActiveWindowError::Unsupported => AppError::internal("errors.activeWindowUnsupported"),
+            ActiveWindowError::Failed(details) => AppError::internal_with("errors.activeWindow", details),
+        }
+    })
+}
 
 #[tauri::command]
 async fn generate_recovery_key(
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
+) -> AppResult<String> {
     info!("Generando clave de recuperación...");
     
-    let crypto_manager = state.crypto_manager.lock().map_err(|_| "Error al acceder al crypto manager")?;
+    let crypto_manager = state.crypto_manager.lock().map_err(|_| AppError::state_lock("components.cryptoManager"))?;
     
     if !crypto_manager.is_unlocked() {
-        return Err("Debes estar autenticado para generar una clave de recuperación".to_string());
+        return Err(AppError::Locked);
     }
     
     // Generar clave de recuperación aleatoria
     let recovery_key = crypto::generate_recovery_key()
-        .map_err(|e| format!("Error al generar clave de recuperación: {}", e))?;
+        .map_err(|e| AppError::crypto("errors.recoveryKey", e))?;
     
     info!("Clave de recuperación generada correctamente");
     Ok(recovery_key)
 }
 
 #[tauri::command]
-async fn check_database_status(_state: tauri::State<'_, AppState>) -> Result<bool, String> {
+async fn check_database_status(_state: tauri::State<'_, AppState>) -> AppResult<bool> {
     info!("=== INICIO: Verificando estado de la base de datos ===");
     
     // Crear un nuevo database manager temporal solo para verificar
     let db_path = database::get_database_path()
-        .map_err(|e| format!("Error al obtener ruta de BD: {}", e))?;
+        .map_err(|e| AppError::database("errors.dbPath", e))?;
     info!("Ruta de base de datos obtenida: {}", db_path);
     
     let db_manager = database::DatabaseManager::new(&db_path)
-        .map_err(|e| format!("Error al crear database manager: {}", e))?;
+        .map_err(|e| AppError::database("errors.dbManager", e))?;
     info!("Database manager creado exitosamente");
     
     // Usar la nueva función de verificación
     let is_initialized = db_manager.check_database_status()
-        .map_err(|e| format!("Error al verificar estado de BD: {}", e))?;
+        .map_err(|e| AppError::database("errors.dbStatus", e))?;
     
     info!("Estado de inicialización: {}", is_initialized);
     info!("=== FIN: Verificación completada ===");
@@ -1000,31 +3570,195 @@ async fn check_database_status(_state: tauri::State<'_, AppState>) -> Result<boo
 //     recovery_key: String,
 //     new_password: String,
 //     state: tauri::State<'_, AppState>,
-// ) -> Result<(), String> {
+// ) -> AppResult<()> {
 //     // TODO: Implementar cuando se corrijan los errores de tipos
 //     Ok(())
 // } 
 
+// ===== IDIOMA =====
+
+#[tauri::command]
+async fn get_locale() -> AppResult<i18n::Locale> {
+    Ok(i18n::current_locale())
+}
+
+/// Cambia el idioma de los mensajes del backend (errores y estados)
+#[tauri::command]
+async fn set_locale(
+    locale: String,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<i18n::Locale> {
+    let parsed = i18n::Locale::from_code(&locale)
+        .ok_or_else(|| AppError::validation(Message::new("errors.unsupportedLocale").with("locale", &locale)))?;
+    let mut settings = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .clone();
+    settings.language = parsed.code().to_string();
+    
+    // Se guarda antes de aplicarlo, así el próximo arranque usa el mismo idioma
+    state.with_db(move |db_manager| {
+        database::save_settings(db_manager.get_connection(), &settings)
+            .map_err(|e| AppError::database("errors.saveSettings", e))
+    }).await?;
+    
+    i18n::set_locale(parsed);
+    state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .language = parsed.code().to_string();
+    info!("Idioma del backend cambiado a: {}", parsed.code());
+    Ok(parsed)
+}
+
+// ===== CONFIGURACIÓN =====
+
+#[tauri::command]
+async fn get_settings(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::AppSettings> {
+    let settings = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?;
+    Ok(settings.clone())
+}
+
+/// Valida y guarda la configuración, y la aplica al estado de la aplicación
+#[tauri::command]
+async fn update_settings(
+    settings: models::AppSettings,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<models::AppSettings> {
+    info!("=== INICIO: Actualizando configuración ===");
+    
+    // Cambiar opciones de seguridad como el bloqueo automático requiere sesión iniciada
+    state.unlocked_crypto()?;
+    
+    let invalid = |setting: &str| AppError::validation(Message::new("errors.invalidSetting").with("setting", setting));
+    if settings.auto_lock_minutes > 24 * 60 {
+        return Err(invalid("auto_lock_minutes"));
+    }
+    if settings.clipboard_clear_seconds > 600 {
+        return Err(invalid("clipboard_clear_seconds"));
+    }
+    if settings.generation_history_size > 200 {
+        return Err(invalid("generation_history_size"));
+    }
+    if !(generator::MIN_LENGTH..=generator::MAX_LENGTH).contains(&settings.generator.length) {
+        return Err(invalid("generator.length"));
+    }
+    if settings.sync.sync_interval == 0 {
+        return Err(invalid("sync.sync_interval"));
+    }
+    if !(1..=24 * 30).contains(&settings.backup.interval_hours) {
+        return Err(invalid("backup.interval_hours"));
+    }
+    if !(1..=100).contains(&settings.backup.keep) {
+        return Err(invalid("backup.keep"));
+    }
+    if !(1..=365).contains(&settings.maintenance.interval_days) {
+        return Err(invalid("maintenance.interval_days"));
+    }
+    if settings.maintenance.backup_history_days > 3650 {
+        return Err(invalid("maintenance.backup_history_days"));
+    }
+    if settings.revisions.keep_per_entry > 200 {
+        return Err(invalid("revisions.keep_per_entry"));
+    }
+    if settings.revisions.max_age_days > 3650 {
+        return Err(invalid("revisions.max_age_days"));
+    }
+    if settings.backup.enabled && settings.backup.directory.trim().is_empty() {
+        return Err(AppError::validation("errors.backupDirectoryMissing"));
+    }
+    let locale = i18n::Locale::from_code(&settings.language)
+        .ok_or_else(|| invalid("language"))?;
+    if settings.breach_check == models::BreachCheckBackend::Offline {
+        let dataset_installed = breach::offline::dataset_path()
+            .map(|path| path.exists())
+            .unwrap_or(false);
+        if !dataset_installed {
+            return Err(AppError::validation("errors.breachDatasetMissing"));
+        }
+    }
+    
+    // Volver a registrar el atajo de búsqueda rápida si cambió
+    let (previous_shortcut, previous_encryption) = {
+        let current = state.settings.lock()
+            .map_err(|_| AppError::state_lock("components.settings"))?;
+        (current.quick_search_shortcut.clone(), current.entry_encryption)
+    };
+    let new_shortcut = settings.quick_search_shortcut.clone();
+    let shortcut_changed = previous_shortcut != new_shortcut;
+    if shortcut_changed {
+        quick_search::register_shortcut(&app, Some(&previous_shortcut), &new_shortcut)
+            .map_err(|e| {
+                warn!("No se pudo registrar el atajo '{}': {}", new_shortcut, e);
+                AppError::validation(Message::new("errors.invalidShortcut").with("shortcut", &new_shortcut))
+            })?;
+    }
+    
+    let saved = state.with_db(move |db_manager| {
+        database::save_settings(db_manager.get_connection(), &settings)
+            .map_err(|e| AppError::database("errors.saveSettings", e))?;
+        database::prune_generation_history(db_manager.get_connection(), settings.generation_history_size)
+            .map_err(|e| AppError::database("errors.generationHistory", e))?;
+        Ok(settings)
+    }).await;
+    let settings = match saved {
+        Ok(settings) => settings,
+        Err(e) => {
+            // Sin guardar, el atajo vuelve a ser el de la configuración que sigue vigente
+            if shortcut_changed {
+                if let Err(restore) = quick_search::register_shortcut(&app, Some(&new_shortcut), &previous_shortcut) {
+                    warn!("No se pudo volver al atajo '{}': {}", previous_shortcut, restore);
+                }
+            }
+            return Err(e);
+        }
+    };
+    
+    i18n::set_locale(locale);
+    *state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))? = settings.clone();
+    
+    if let Some(sync_manager) = state.sync_manager.get() {
+        if let Err(e) = sync_manager.update_config(sync::SyncConfig::from(&settings.sync)).await {
+            warn!("No se pudo aplicar la configuración de sincronización: {}", e);
+        }
+    }
+    
+    // Las entradas ya guardadas pasan a encriptarse como indica la nueva opción.
+    // Si falla, las que queden se siguen leyendo igual y se convierten al editarlas.
+    if previous_encryption != settings.entry_encryption {
+        match reseal_entries(&state, state.entry_cipher()?).await {
+            Ok(resealed) => info!("{} entradas encriptadas de nuevo como {:?}", resealed, settings.entry_encryption),
+            Err(e) => warn!("No se pudieron volver a encriptar las entradas: {}", e),
+        }
+    }
+    
+    info!("=== FIN: Configuración actualizada ===");
+    Ok(settings)
+}
+
 // ===== COMANDO DE TEST =====
 
 #[tauri::command]
-async fn test_migrations() -> Result<String, String> {
+async fn test_migrations() -> AppResult<String> {
     info!("=== INICIO: TEST DE MIGRACIONES ===");
     
     // Obtener ruta de base de datos
     let db_path = database::get_database_path()
-        .map_err(|e| format!("Error al obtener ruta de base de datos: {}", e))?;
+        .map_err(|e| AppError::database("errors.dbPath", e))?;
     info!("Ruta de base de datos: {}", db_path);
     
     // Crear conexión
-    let connection = rusqlite::Connection::open(&db_path)
-        .map_err(|e| format!("Error al abrir conexión SQLite: {}", e))?;
+    let connection = database::open_database(&db_path)
+        .map_err(|e| AppError::database("errors.dbOpen", e))?;
     info!("Conexión SQLite abierta");
     
     // Ejecutar migraciones
     info!("Ejecutando migraciones...");
     database::run_migrations(&connection)
-        .map_err(|e| format!("Error al ejecutar migraciones: {}", e))?;
+        .map_err(|e| AppError::database("errors.migrations", e))?;
     info!("Migraciones ejecutadas");
     
     // Verificar tablas
@@ -1032,7 +3766,7 @@ async fn test_migrations() -> Result<String, String> {
         "SELECT COUNT(*) FROM sqlite_master WHERE type='table'",
         [],
         |row| row.get::<_, i64>(0)
-    ).map_err(|e| format!("Error al consultar tablas: {}", e))?;
+    ).map_err(|e| AppError::database("errors.dbQuery", e))?;
     info!("Número de tablas: {}", tables);
     
     // Verificar tabla users específicamente
@@ -1040,14 +3774,11 @@ async fn test_migrations() -> Result<String, String> {
     info!("Tabla users existe: {}", users_exists);
     
     if users_exists {
-        let user_count = connection.query_row(
-            "SELECT COUNT(*) FROM users",
-            [],
-            |row| row.get::<_, i64>(0)
-        ).map_err(|e| format!("Error al contar usuarios: {}", e))?;
+        let user_count = database::count_users(&connection)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?;
         info!("Número de usuarios: {}", user_count);
     }
     
     info!("=== FIN: TEST DE MIGRACIONES COMPLETADO ===");
-    Ok("Migraciones funcionando correctamente".to_string())
+    Ok(i18n::t("status.migrationsOk"))
 } 
\ No newline at end of file
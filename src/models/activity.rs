@@ -0,0 +1,70 @@
+use serde::{Serialize, Deserialize};
+use super::{CategoryId, EntryId};
+
+/// Operación sobre la bóveda que queda en el registro de actividad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityAction {
+    Create,
+    Edit,
+    Delete,
+    Export,
+    /// Se mostró o copió un campo sensible
+    Reveal,
+    /// Se escribieron las credenciales en otra ventana con auto-type
+    Fill,
+}
+
+impl ActivityAction {
+    /// Nombre con que se guarda en la base de datos
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ActivityAction::Create => "create",
+            ActivityAction::Edit => "edit",
+            ActivityAction::Delete => "delete",
+            ActivityAction::Export => "export",
+            ActivityAction::Reveal => "reveal",
+            ActivityAction::Fill => "fill",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        [Self::Create, Self::Edit, Self::Delete, Self::Export, Self::Reveal, Self::Fill]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == action)
+    }
+}
+
+/// Evento del registro de actividad. Nunca contiene secretos: solo el id de la
+/// entrada, su categoría en ese momento y un detalle como el campo revelado, el
+/// formato exportado o el dominio autocompletado.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    pub id: i64,
+    pub action: ActivityAction,
+    pub entry_id: Option<EntryId>,
+    pub category_id: Option<CategoryId>,
+    pub details: Option<String>,
+    pub occurred_at: String,
+}
+
+/// Filtros y paginación del registro de actividad
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivityLogRequest {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    #[serde(default, deserialize_with = "super::empty_as_none")]
+    pub category_id: Option<CategoryId>,
+    #[serde(default, deserialize_with = "super::empty_as_none")]
+    pub entry_id: Option<EntryId>,
+    pub action: Option<ActivityAction>,
+}
+
+/// Página del registro de actividad, del evento más reciente al más antiguo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityLogPage {
+    pub events: Vec<ActivityEvent>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
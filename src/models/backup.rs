@@ -0,0 +1,94 @@
+use serde::{Serialize, Deserialize};
+use super::EntryId;
+
+/// Resumen de una copia de seguridad creada, verificada o restaurada
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub created_at: String,
+    pub entries: usize,
+    pub categories: usize,
+    pub policies: usize,
+    pub size_bytes: u64,
+}
+
+/// Copia registrada en el historial
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub id: i64,
+    pub path: String,
+    pub created_at: String,
+    /// Creada por la programación automática y no a mano
+    pub automatic: bool,
+    pub entries: usize,
+    pub size_bytes: u64,
+    /// Motivo del fallo si la copia no se pudo crear
+    pub error: Option<String>,
+}
+
+/// Cómo se combina una copia con la bóveda actual al restaurarla
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMode {
+    /// Borra la bóveda actual y la sustituye por la copia
+    Replace,
+    /// Agrega lo que falta y actualiza solo lo que en la copia es más nuevo
+    #[default]
+    Merge,
+}
+
+/// Parámetros de la restauración
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreRequest {
+    pub path: String,
+    pub backup_password: String,
+    #[serde(default)]
+    pub mode: RestoreMode,
+    /// Solo calcular los cambios, sin aplicarlos
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Qué pasa con una entrada al restaurar
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreAction {
+    Add,
+    Update,
+    Skip,
+    Remove,
+}
+
+/// Cambio sobre una entrada concreta
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreChange {
+    pub entry_id: EntryId,
+    pub title: String,
+    pub action: RestoreAction,
+}
+
+/// Resumen de una restauración, aplicada o solo prevista
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePreview {
+    pub mode: RestoreMode,
+    /// `false` si es solo una vista previa
+    pub committed: bool,
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub removed: usize,
+    pub categories_added: usize,
+    pub policies_added: usize,
+    pub changes: Vec<RestoreChange>,
+}
+
+/// Copia de la base hecha automáticamente antes de migrar el esquema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationBackupInfo {
+    /// Copia restaurada
+    pub path: String,
+    /// Versión del esquema de la copia
+    pub schema_version: u32,
+    /// Dónde quedó la base que se sustituyó
+    pub rolled_back_path: String,
+}
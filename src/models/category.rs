@@ -1,11 +1,42 @@
 use serde::{Serialize, Deserialize};
+use super::CategoryId;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Category {
-    pub id: String,
+    pub id: CategoryId,
     pub name: String,
     pub color: String,
     pub icon: Option<String>,
-    pub parent_id: Option<String>,
+    pub parent_id: Option<CategoryId>,
     pub created_at: String,
-} 
\ No newline at end of file
+}
+
+/// Datos para crear o modificar una categoría
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryRequest {
+    pub name: String,
+    /// Color en formato `#RRGGBB`
+    pub color: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default, deserialize_with = "super::empty_as_none")]
+    pub parent_id: Option<CategoryId>,
+}
+
+/// Categoría con el número de entradas que tiene directamente (sin contar subcategorías)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySummary {
+    #[serde(flatten)]
+    pub category: Category,
+    pub entry_count: usize,
+}
+
+/// Categoría dentro del árbol de carpetas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryNode {
+    #[serde(flatten)]
+    pub summary: CategorySummary,
+    /// Entradas de la categoría y de todas sus subcategorías
+    pub total_count: usize,
+    pub children: Vec<CategoryNode>,
+}
@@ -0,0 +1,285 @@
+//! Campos de una entrada como registros independientes
+//!
+//! Cada campo guarda cuándo y en qué dispositivo se cambió por última vez. Si
+//! dos dispositivos editan a la vez la misma entrada, de cada campo gana el
+//! cambio más reciente: ediciones de campos distintos se combinan sin
+//! conflicto y las del mismo campo se resuelven igual en los dos lados, o se
+//! dejan para el usuario si así lo pide la estrategia de la sincronización.
+
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use super::{DeviceId, PasswordEntry, VersionVector};
+
+/// Campo de una entrada que se sincroniza por separado
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryField {
+    Title,
+    Username,
+    Password,
+    Url,
+    Notes,
+    Category,
+    Tags,
+}
+
+impl EntryField {
+    pub const ALL: [EntryField; 7] = [
+        EntryField::Title,
+        EntryField::Username,
+        EntryField::Password,
+        EntryField::Url,
+        EntryField::Notes,
+        EntryField::Category,
+        EntryField::Tags,
+    ];
+
+    /// Nombre con que se guarda en la base
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryField::Title => "title",
+            EntryField::Username => "username",
+            EntryField::Password => "password",
+            EntryField::Url => "url",
+            EntryField::Notes => "notes",
+            EntryField::Category => "category",
+            EntryField::Tags => "tags",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.as_str() == value)
+    }
+
+    /// Si el campo tiene el mismo valor en las dos entradas
+    fn same(self, a: &PasswordEntry, b: &PasswordEntry) -> bool {
+        match self {
+            EntryField::Title => a.title == b.title,
+            EntryField::Username => a.username == b.username,
+            EntryField::Password => a.password == b.password,
+            EntryField::Url => a.url == b.url,
+            EntryField::Notes => a.notes == b.notes,
+            EntryField::Category => a.category_id == b.category_id,
+            EntryField::Tags => a.tags == b.tags,
+        }
+    }
+
+    /// Copia el campo de `from` a `to`
+    fn copy(self, from: &PasswordEntry, to: &mut PasswordEntry) {
+        match self {
+            EntryField::Title => to.title = from.title.clone(),
+            EntryField::Username => to.username = from.username.clone(),
+            EntryField::Password => to.password = from.password.clone(),
+            EntryField::Url => to.url = from.url.clone(),
+            EntryField::Notes => to.notes = from.notes.clone(),
+            EntryField::Category => to.category_id = from.category_id,
+            EntryField::Tags => to.tags = from.tags.clone(),
+        }
+    }
+}
+
+/// Campos que cambiaron de `before` a `after`
+pub fn changed_fields(before: &PasswordEntry, after: &PasswordEntry) -> Vec<EntryField> {
+    EntryField::ALL.into_iter().filter(|field| !field.same(before, after)).collect()
+}
+
+/// Último cambio de un campo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldStamp {
+    /// Fecha RFC 3339 del cambio
+    pub changed_at: String,
+    pub device_id: DeviceId,
+    /// Número de cambio de `device_id` que lo selló; 0 si no se conoce
+    #[serde(default)]
+    pub counter: u64,
+}
+
+impl FieldStamp {
+    /// Si este cambio gana sobre `other`: el más reciente y, a la misma hora,
+    /// el del dispositivo con el id mayor, así los dos lados eligen igual
+    pub fn wins_over(&self, other: &FieldStamp) -> bool {
+        let parse = |stamp: &FieldStamp| chrono::DateTime::parse_from_rfc3339(&stamp.changed_at).ok();
+        match (parse(self), parse(other)) {
+            (Some(a), Some(b)) if a != b => a > b,
+            (Some(_), Some(_)) => self.device_id > other.device_id,
+            _ => (&self.changed_at, self.device_id) > (&other.changed_at, other.device_id),
+        }
+    }
+
+    /// Si el dispositivo con el vector `versions` ya vio este cambio. Un sello
+    /// sin número cuenta como visto.
+    pub fn seen_by(&self, versions: &VersionVector) -> bool {
+        self.counter <= versions.get(self.device_id)
+    }
+}
+
+/// Último cambio de cada campo de una entrada
+pub type FieldStamps = BTreeMap<EntryField, FieldStamp>;
+
+/// Sello del campo; si no tiene, el de la última modificación de la entrada
+fn stamp_or_default(stamps: &FieldStamps, field: EntryField, entry: &PasswordEntry, device_id: DeviceId) -> FieldStamp {
+    stamps.get(&field).cloned().unwrap_or_else(|| FieldStamp {
+        changed_at: entry.updated_at.clone(),
+        device_id,
+        counter: 0,
+    })
+}
+
+/// Combina dos versiones concurrentes de una entrada campo por campo.
+/// `local_device` y `remote_device` sellan los campos que no tienen sello.
+/// Devuelve la entrada combinada y los sellos que le corresponden.
+pub fn merge_entry_fields(
+    local: &PasswordEntry,
+    local_stamps: &FieldStamps,
+    local_device: DeviceId,
+    remote: &PasswordEntry,
+    remote_stamps: &FieldStamps,
+    remote_device: DeviceId,
+) -> (PasswordEntry, FieldStamps) {
+    let mut merged = local.clone();
+    let mut stamps = FieldStamps::new();
+    for field in EntryField::ALL {
+        let local_stamp = stamp_or_default(local_stamps, field, local, local_device);
+        let remote_stamp = stamp_or_default(remote_stamps, field, remote, remote_device);
+        if remote_stamp.wins_over(&local_stamp) {
+            field.copy(remote, &mut merged);
+            stamps.insert(field, remote_stamp);
+        } else {
+            stamps.insert(field, local_stamp);
+        }
+    }
+    let remote_is_later = FieldStamp { changed_at: remote.updated_at.clone(), device_id: remote_device, counter: 0 }
+        .wins_over(&FieldStamp { changed_at: local.updated_at.clone(), device_id: local_device, counter: 0 });
+    if remote_is_later {
+        merged.updated_at = remote.updated_at.clone();
+    }
+    (merged, stamps)
+}
+
+/// Campos que las dos versiones cambiaron sin ver el cambio de la otra y que
+/// quedaron con valores distintos: los que no se pueden combinar solos.
+/// `local_versions` y `remote_versions` son los vectores de la entrada en cada
+/// lado.
+pub fn diverging_fields(
+    local: &PasswordEntry,
+    local_stamps: &FieldStamps,
+    local_versions: &VersionVector,
+    remote: &PasswordEntry,
+    remote_stamps: &FieldStamps,
+    remote_versions: &VersionVector,
+) -> Vec<EntryField> {
+    EntryField::ALL.into_iter()
+        .filter(|field| !field.same(local, remote))
+        .filter(|field| match (local_stamps.get(field), remote_stamps.get(field)) {
+            (Some(local_stamp), Some(remote_stamp)) => {
+                !local_stamp.seen_by(remote_versions) && !remote_stamp.seen_by(local_versions)
+            }
+            _ => false,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(updated_at: &str) -> PasswordEntry {
+        PasswordEntry { updated_at: updated_at.to_string(), ..PasswordEntry::sample("Correo") }
+    }
+
+    fn stamp(changed_at: &str, device_id: DeviceId) -> FieldStamp {
+        FieldStamp { changed_at: changed_at.to_string(), device_id, counter: 0 }
+    }
+
+    #[test]
+    fn test_merges_edits_to_different_fields() {
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        let base = entry("2024-01-01T00:00:00Z");
+
+        // El portátil cambió la contraseña y el teléfono, después, el usuario
+        let mut local = base.clone();
+        local.password = "nueva".to_string();
+        local.updated_at = "2024-02-01T00:00:00Z".to_string();
+        let mut remote = base.clone();
+        remote.username = "ana.g".to_string();
+        remote.notes = Some("del teléfono".to_string());
+        remote.updated_at = "2024-02-02T00:00:00Z".to_string();
+        assert_eq!(changed_fields(&base, &remote), vec![EntryField::Username, EntryField::Notes]);
+
+        let base_stamps: FieldStamps = EntryField::ALL.into_iter()
+            .map(|field| (field, stamp("2024-01-01T00:00:00Z", laptop)))
+            .collect();
+        let mut local_stamps = base_stamps.clone();
+        local_stamps.insert(EntryField::Password, stamp("2024-02-01T00:00:00Z", laptop));
+        let mut remote_stamps = base_stamps;
+        remote_stamps.insert(EntryField::Username, stamp("2024-02-02T00:00:00Z", phone));
+        remote_stamps.insert(EntryField::Notes, stamp("2024-02-02T00:00:00Z", phone));
+
+        let (merged, stamps) = merge_entry_fields(&local, &local_stamps, laptop, &remote, &remote_stamps, phone);
+        assert_eq!(merged.password, "nueva");
+        assert_eq!(merged.username, "ana.g");
+        assert_eq!(merged.notes.as_deref(), Some("del teléfono"));
+        assert_eq!(merged.updated_at, "2024-02-02T00:00:00Z");
+        assert_eq!(stamps[&EntryField::Password].device_id, laptop);
+        assert_eq!(stamps[&EntryField::Username].device_id, phone);
+
+        // El otro lado llega al mismo resultado
+        let (other, other_stamps) = merge_entry_fields(&remote, &remote_stamps, phone, &local, &local_stamps, laptop);
+        assert!(changed_fields(&merged, &other).is_empty());
+        assert_eq!(stamps, other_stamps);
+    }
+
+    #[test]
+    fn test_same_field_goes_to_the_latest_edit() {
+        let (a, b) = (DeviceId::new(), DeviceId::new());
+        let mut local = entry("2024-02-01T00:00:00Z");
+        local.title = "Banco".to_string();
+        let mut remote = entry("2024-02-01T10:00:00+02:00");
+        remote.title = "Banco (remoto)".to_string();
+
+        // Sin sellos cuenta la última modificación de cada entrada
+        let (merged, _) = merge_entry_fields(&local, &FieldStamps::new(), a, &remote, &FieldStamps::new(), b);
+        assert_eq!(merged.title, "Banco (remoto)");
+
+        // A la misma hora decide el id del dispositivo, igual en los dos lados
+        let same_time = |device_id| stamp("2024-03-01T00:00:00Z", device_id);
+        assert_ne!(same_time(a).wins_over(&same_time(b)), same_time(b).wins_over(&same_time(a)));
+        assert_eq!(EntryField::parse("tags"), Some(EntryField::Tags));
+        assert_eq!(EntryField::parse("otro"), None);
+    }
+
+    #[test]
+    fn test_only_the_same_field_edited_on_both_sides_diverges() {
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        let base = entry("2024-01-01T00:00:00Z");
+        let numbered = |changed_at: &str, device_id, counter| FieldStamp { changed_at: changed_at.to_string(), device_id, counter };
+        let base_stamps: FieldStamps = EntryField::ALL.into_iter()
+            .map(|field| (field, numbered("2024-01-01T00:00:00Z", laptop, 1)))
+            .collect();
+
+        // Los dos partieron del primer cambio del portátil
+        let mut local = base.clone();
+        local.password = "del portátil".to_string();
+        local.title = "Correo personal".to_string();
+        let mut local_stamps = base_stamps.clone();
+        local_stamps.insert(EntryField::Password, numbered("2024-02-01T00:00:00Z", laptop, 2));
+        local_stamps.insert(EntryField::Title, numbered("2024-02-01T00:00:00Z", laptop, 2));
+        let local_versions: VersionVector = [(laptop, 2)].into_iter().collect();
+
+        let mut remote = base.clone();
+        remote.password = "del teléfono".to_string();
+        remote.username = "ana.g".to_string();
+        let mut remote_stamps = base_stamps;
+        remote_stamps.insert(EntryField::Password, numbered("2024-02-02T00:00:00Z", phone, 1));
+        remote_stamps.insert(EntryField::Username, numbered("2024-02-02T00:00:00Z", phone, 1));
+        let remote_versions: VersionVector = [(laptop, 1), (phone, 1)].into_iter().collect();
+
+        let diverging = diverging_fields(&local, &local_stamps, &local_versions, &remote, &remote_stamps, &remote_versions);
+        assert_eq!(diverging, vec![EntryField::Password]);
+
+        // Si el teléfono ya había visto la contraseña del portátil, la suya es posterior
+        let remote_versions: VersionVector = [(laptop, 2), (phone, 1)].into_iter().collect();
+        assert!(diverging_fields(&local, &local_stamps, &local_versions, &remote, &remote_stamps, &remote_versions).is_empty());
+    }
+}
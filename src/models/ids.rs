@@ -0,0 +1,151 @@
+//! Identificadores de entradas, categorías, dispositivos y enlaces compartidos
+//!
+//! Cada tipo de id es un UUID con su propio tipo, así que no se puede pasar el
+//! id de una categoría donde se espera el de una entrada. Los textos se
+//! convierten al deserializar los comandos, los mensajes de la extensión y las
+//! filas de la base: un id mal formado se rechaza ahí y no llega más adentro.
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+macro_rules! uuid_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(Uuid);
+
+        impl $name {
+            /// Id nuevo al azar
+            pub fn new() -> Self {
+                Self(Uuid::new_v4())
+            }
+
+            pub fn as_uuid(&self) -> &Uuid {
+                &self.0
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl From<Uuid> for $name {
+            fn from(uuid: Uuid) -> Self {
+                Self(uuid)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = uuid::Error;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Uuid::parse_str(value).map(Self)
+            }
+        }
+
+        impl ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(ToSqlOutput::from(self.0.to_string()))
+            }
+        }
+
+        impl FromSql for $name {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                value.as_str()?.parse().map_err(|e| FromSqlError::Other(Box::new(e)))
+            }
+        }
+    };
+}
+
+uuid_id!(
+    /// Id de una entrada de la bóveda
+    EntryId
+);
+
+uuid_id!(
+    /// Id de una categoría
+    CategoryId
+);
+
+uuid_id!(
+    /// Id de un dispositivo con el que se sincroniza
+    DeviceId
+);
+
+uuid_id!(
+    /// Id de una entrada compartida por enlace
+    ShareId
+);
+
+/// Para `#[serde(deserialize_with)]` en los campos opcionales que llegan del
+/// frontend, que manda un texto vacío cuando no se eligió nada
+pub fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.is_empty() => value.parse().map(Some).map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Como [`empty_as_none`], para los cambios en que falta el campo significa
+/// "sin cambios" y el texto vacío significa "quitar"
+pub fn clearable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if value.is_empty() => Ok(Some(None)),
+        Some(value) => value.parse().map(|id| Some(Some(id))).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_parses_at_the_boundary() {
+        let id = EntryId::new();
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", id));
+        assert_eq!(serde_json::from_str::<EntryId>(&json).unwrap(), id);
+        assert!(serde_json::from_str::<EntryId>("\"trabajo\"").is_err());
+
+        #[derive(Deserialize)]
+        struct Request {
+            #[serde(default, deserialize_with = "empty_as_none")]
+            category_id: Option<CategoryId>,
+        }
+        let parse = |json: &str| serde_json::from_str::<Request>(json).map(|request| request.category_id);
+        assert_eq!(parse(r#"{"category_id": ""}"#).unwrap(), None);
+        assert_eq!(parse("{}").unwrap(), None);
+        assert!(parse(r#"{"category_id": "x"}"#).is_err());
+
+        let connection = Connection::open_in_memory().unwrap();
+        let stored: String = connection.query_row("SELECT ?", [id], |row| row.get(0)).unwrap();
+        assert_eq!(stored, id.to_string());
+        let read: EntryId = connection.query_row("SELECT ?", [&stored], |row| row.get(0)).unwrap();
+        assert_eq!(read, id);
+        assert!(connection.query_row("SELECT 'a'", [], |row| row.get::<_, EntryId>(0)).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Serialize, Deserialize};
+use super::EntryId;
+
+/// Formato del archivo a importar
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    BitwardenJson,
+    BitwardenCsv,
+    LastpassCsv,
+    KeepassKdbx,
+    OnepasswordPux,
+}
+
+/// Formato de importación disponible, para ofrecerlo en la interfaz
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportFormatInfo {
+    pub format: ImportFormat,
+    pub name: String,
+    pub extension: String,
+    /// El archivo está encriptado: hay que pedir contraseña y/o archivo de clave
+    pub needs_password: bool,
+}
+
+/// Parámetros de la importación: el contenido o la ruta del archivo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRequest {
+    pub format: ImportFormat,
+    pub data: Option<String>,
+    pub path: Option<String>,
+    /// Contraseña del archivo, para los formatos encriptados
+    pub password: Option<String>,
+    /// Archivo de clave de KeePass
+    pub key_file_path: Option<String>,
+    /// Solo clasificar las filas, sin guardar nada
+    #[serde(default)]
+    pub preview: bool,
+    /// Qué hacer con filas concretas; las demás siguen la opción por defecto de
+    /// su estado
+    #[serde(default)]
+    pub resolutions: Vec<ImportRowResolution>,
+}
+
+/// Navegador del que se pueden importar las contraseñas guardadas
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowserKind {
+    Chrome,
+    Edge,
+    Brave,
+    Chromium,
+    Firefox,
+}
+
+/// Perfil de navegador con contraseñas guardadas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserProfile {
+    pub browser: BrowserKind,
+    pub name: String,
+    /// Carpeta del perfil
+    pub path: String,
+}
+
+/// Parámetros de la importación desde un navegador
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserImportRequest {
+    pub browser: BrowserKind,
+    pub profile_path: String,
+    /// Contraseña principal de Firefox, si el perfil la tiene
+    pub password: Option<String>,
+    /// Solo clasificar las filas, sin guardar nada
+    #[serde(default)]
+    pub preview: bool,
+    /// Qué hacer con filas concretas; las demás siguen la opción por defecto de
+    /// su estado
+    #[serde(default)]
+    pub resolutions: Vec<ImportRowResolution>,
+}
+
+/// Estado de una fila comparada con las entradas de la bóveda
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportRowStatus {
+    New,
+    /// Mismo sitio, usuario y contraseña que una entrada existente (u otra fila)
+    Duplicate,
+    /// Mismo sitio y usuario con otra contraseña
+    NearDuplicate,
+    Failed,
+}
+
+/// Qué hacer con una fila al aplicar la importación
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportResolution {
+    /// Crear una entrada nueva
+    Import,
+    Skip,
+    /// Reemplazar usuario, contraseña, URL y notas de la entrada existente
+    Overwrite,
+}
+
+impl ImportRowStatus {
+    /// Opción por defecto: los duplicados exactos se omiten y el resto se importa
+    pub fn default_resolution(self) -> Option<ImportResolution> {
+        match self {
+            Self::New | Self::NearDuplicate => Some(ImportResolution::Import),
+            Self::Duplicate => Some(ImportResolution::Skip),
+            Self::Failed => None,
+        }
+    }
+}
+
+/// Resolución elegida para una fila concreta
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowResolution {
+    pub row: usize,
+    pub resolution: ImportResolution,
+}
+
+/// Resultado de importar una fila del archivo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowResult {
+    pub row: usize,
+    pub title: Option<String>,
+    pub status: ImportRowStatus,
+    /// Opción aplicada (o que se aplicaría, en la vista previa)
+    pub resolution: Option<ImportResolution>,
+    /// Entrada de la bóveda con la que coincide, si la hay
+    pub existing_id: Option<EntryId>,
+    /// Id de la entrada creada o reemplazada
+    pub entry_id: Option<EntryId>,
+    /// Motivo del fallo, en el idioma activo
+    pub error: Option<String>,
+}
+
+/// Informe de la importación
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// `false` en la vista previa: no se guardó nada
+    pub committed: bool,
+    pub imported: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub categories_created: usize,
+    pub rows: Vec<ImportRowResult>,
+}
+
+/// Progreso de una importación o exportación, emitido como evento
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub processed: usize,
+    pub total: usize,
+    /// Título del elemento que se acaba de procesar
+    pub title: Option<String>,
+}
@@ -0,0 +1,27 @@
+use serde::{Serialize, Deserialize};
+
+/// Resultado de compactar la base de datos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub reclaimed_bytes: u64,
+    /// Contraseñas generadas descartadas por exceder el tamaño del historial
+    pub pruned_generation_history: usize,
+    /// Registros de copias descartados por antigüedad
+    pub pruned_backup_history: usize,
+    /// Marcas de eliminación que ya confirmaron todos los dispositivos de confianza
+    pub pruned_tombstones: usize,
+    /// Revisiones de entradas que exceden la retención configurada
+    pub pruned_revisions: usize,
+    pub completed_at: String,
+}
+
+/// Dónde está el archivo de la base de datos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseLocation {
+    pub path: String,
+    /// Dónde estaría si el usuario no la hubiera movido
+    pub default_path: String,
+    pub is_custom: bool,
+}
@@ -1,7 +1,39 @@
+mod ids;
 mod password_entry;
 mod category;
-mod user;
+mod settings;
+mod statistics;
+mod security;
+mod policy;
+mod backup;
+mod import;
+mod maintenance;
+mod tombstone;
+mod trusted_device;
+mod revision;
+mod activity;
+mod version_vector;
+mod entry_fields;
+mod sync_history;
+mod sync_bootstrap;
+mod shared_link;
 
+pub use ids::*;
 pub use password_entry::*;
 pub use category::*;
-pub use user::*; 
\ No newline at end of file
+pub use settings::*;
+pub use statistics::*;
+pub use security::*;
+pub use policy::*;
+pub use backup::*;
+pub use import::*;
+pub use maintenance::*;
+pub use tombstone::*;
+pub use trusted_device::*;
+pub use revision::*;
+pub use activity::*;
+pub use version_vector::*;
+pub use entry_fields::*; 
+pub use sync_history::*;
+pub use sync_bootstrap::*;
+pub use shared_link::*;
\ No newline at end of file
@@ -1,57 +1,211 @@
 use serde::{Serialize, Deserialize};
-use super::Category;
+use super::{Category, CategoryId, EntryId};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PasswordEntry {
-    pub id: String,
+    pub id: EntryId,
     pub title: String,
     pub username: String,
     pub password: String,
     pub url: Option<String>,
     pub notes: Option<String>,
-    pub category_id: Option<String>,
+    pub category_id: Option<CategoryId>,
     pub tags: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
     pub last_used: Option<String>,
 }
 
+#[cfg(test)]
+impl PasswordEntry {
+    /// Entrada de prueba con `title`: de "ana", con contraseña "secreta" y
+    /// sin nada más
+    pub fn sample(title: &str) -> Self {
+        Self {
+            id: EntryId::new(),
+            title: title.to_string(),
+            username: "ana".to_string(),
+            password: "secreta".to_string(),
+            url: None,
+            notes: None,
+            category_id: None,
+            tags: Vec::new(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+            last_used: None,
+        }
+    }
+}
+
+/// Entrada tal como la recibe el frontend. `PasswordEntry` también se guarda en
+/// las copias de seguridad y en la exportación propia, que siguen en snake_case.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordEntryDto {
+    pub id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub category_id: Option<CategoryId>,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub last_used: Option<String>,
+}
+
+impl From<PasswordEntry> for PasswordEntryDto {
+    fn from(entry: PasswordEntry) -> Self {
+        Self {
+            id: entry.id,
+            title: entry.title,
+            username: entry.username,
+            password: entry.password,
+            url: entry.url,
+            notes: entry.notes,
+            category_id: entry.category_id,
+            tags: entry.tags,
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+            last_used: entry.last_used,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CreatePasswordRequest {
     pub title: String,
     pub username: String,
     pub password: String,
     pub url: Option<String>,
     pub notes: Option<String>,
-    pub category_id: Option<String>,
+    #[serde(default, deserialize_with = "super::empty_as_none")]
+    pub category_id: Option<CategoryId>,
     pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UpdatePasswordRequest {
-    pub id: String,
+    pub id: EntryId,
     pub title: Option<String>,
     pub username: Option<String>,
     pub password: Option<String>,
     pub url: Option<String>,
     pub notes: Option<String>,
-    pub category_id: Option<String>,
+    /// `None` deja la categoría como está; un texto vacío la quita
+    #[serde(default, deserialize_with = "super::clearable")]
+    pub category_id: Option<Option<CategoryId>>,
     pub tags: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SearchRequest {
     pub query: String,
-    pub category_id: Option<String>,
+    #[serde(default, deserialize_with = "super::empty_as_none")]
+    pub category_id: Option<CategoryId>,
     pub tags: Vec<String>,
 }
 
+/// Campo por el que se ordena el listado de entradas
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Title,
+    #[default]
+    UpdatedAt,
+    LastUsed,
+}
+
+/// Dirección del ordenamiento
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Parámetros de paginación y ordenamiento para el listado de entradas
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordListRequest {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    pub sort_by: Option<SortField>,
+    pub sort_direction: Option<SortDirection>,
+}
+
+/// Página de entradas junto con el total disponible
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordEntriesPage {
+    pub entries: Vec<PasswordEntryDto>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// Resumen de una entrada para listados, sin la contraseña
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordEntrySummary {
+    pub id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub url: Option<String>,
+    pub category_id: Option<CategoryId>,
+}
+
+/// Página de resúmenes de entradas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordSummariesPage {
+    pub entries: Vec<PasswordEntrySummary>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// Resultado de la búsqueda rápida, ordenado por relevancia
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickSearchResult {
+    pub id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub url: Option<String>,
+    pub score: u32,
+}
+
+/// Favicon del sitio de una entrada, listo para usarse como data URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryIcon {
+    pub domain: String,
+    pub mime_type: String,
+    pub data: String, // base64
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutofillRequest {
     pub url: String,
     pub username: Option<String>,
 }
 
+/// Modo del generador de contraseñas
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationMode {
+    #[default]
+    Random,
+    /// Sílabas pronunciables, más fáciles de escribir a mano a cambio de menos entropía
+    Pronounceable,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PasswordGenerationRequest {
     pub length: usize,
@@ -60,23 +214,167 @@ pub struct PasswordGenerationRequest {
     pub include_numbers: bool,
     pub include_symbols: bool,
     pub exclude_similar: bool,
+    #[serde(default)]
+    pub mode: GenerationMode,
+}
+
+/// Contraseña del historial de generación
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationHistoryEntry {
+    pub id: i64,
+    pub password: String,
+    /// Sitio para el que se generó, si se conocía
+    pub url: Option<String>,
+    pub generated_at: String,
+}
+
+/// Opciones del generador de nombres de usuario
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsernameGenerationRequest {
+    /// Texto entre el adjetivo y el sustantivo
+    pub separator: String,
+    pub capitalize: bool,
+    /// Agregar un número de dos cifras al final
+    pub include_number: bool,
+}
+
+impl Default for UsernameGenerationRequest {
+    fn default() -> Self {
+        Self {
+            separator: "_".to_string(),
+            capitalize: false,
+            include_number: true,
+        }
+    }
+}
+
+/// Nombre de usuario generado junto con su entropía
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedUsername {
+    pub username: String,
+    pub entropy_bits: f64,
+}
+
+/// Contraseña generada junto con su entropía
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+    pub entropy_bits: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchQuery {
     pub text: String,
-    pub category_id: Option<String>,
+    pub category_id: Option<CategoryId>,
     pub tags: Vec<String>,
     pub include_archived: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PasswordStrength {
+    /// Puntaje de 0 a 100 derivado de los intentos estimados
     pub score: u8,
+    /// Puntaje de 0 a 4 con los mismos umbrales que zxcvbn
+    pub rating: u8,
+    /// Logaritmo en base 10 de los intentos necesarios para adivinarla
+    pub guesses_log10: f64,
+    pub entropy_bits: f64,
+    pub crack_times: CrackTimes,
+    /// Patrones con los que un atacante la adivinaría antes, en orden
+    pub patterns: Vec<PatternMatch>,
     pub feedback: Vec<String>,
     pub suggestions: Vec<String>,
 }
 
+/// Tiempo estimado para adivinar una contraseña
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrackTime {
+    pub seconds: f64,
+    /// Texto legible en el idioma activo ("3 horas", "siglos"...)
+    pub display: String,
+}
+
+/// Tiempos estimados según el escenario de ataque
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrackTimes {
+    /// Ataque en línea con límite de intentos (100 por hora)
+    pub online_throttled: CrackTime,
+    /// Ataque en línea sin límite (10 por segundo)
+    pub online: CrackTime,
+    /// Ataque sin conexión contra un hash lento como Argon2 (10⁴ por segundo)
+    pub offline_slow: CrackTime,
+    /// Ataque sin conexión contra un hash rápido (10¹⁰ por segundo)
+    pub offline_fast: CrackTime,
+}
+
+/// Tipo de patrón reconocido en una contraseña
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    Dictionary,
+    Spatial,
+    Repeat,
+    Sequence,
+    Date,
+    BruteForce,
+}
+
+/// Fragmento de la contraseña que coincide con un patrón
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternMatch {
+    pub kind: PatternKind,
+    pub token: String,
+    /// Posiciones (en caracteres) del inicio y el fin, ambos inclusive
+    pub start: usize,
+    pub end: usize,
+    pub guesses_log10: f64,
+    /// Posición en la lista de contraseñas o palabras comunes
+    pub rank: Option<usize>,
+    pub l33t: bool,
+    pub reversed: bool,
+}
+
+/// Formato del archivo de exportación
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Lista JSON simple con título, usuario, contraseña, URL, notas y etiquetas
+    Json,
+    /// CSV con las columnas del importador de Bitwarden
+    CsvBitwarden,
+    /// CSV con las columnas del importador de Chrome
+    CsvChrome,
+    /// `ExportData` completo, con categorías y fechas
+    Alohopass,
+    /// Base de datos de KeePass encriptada con `ExportRequest::password`
+    Kdbx,
+}
+
+/// Parámetros de la exportación
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRequest {
+    pub format: ExportFormat,
+    #[serde(default, deserialize_with = "super::empty_as_none")]
+    pub category_id: Option<CategoryId>,
+    /// Solo entradas que tengan todas estas etiquetas
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Archivo de destino; sin ruta el contenido se devuelve en la respuesta
+    pub path: Option<String>,
+    /// Contraseña de la base de datos exportada (solo KDBX)
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Resultado de la exportación
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub exported: usize,
+    /// Contenido exportado, solo cuando no se pidió escribirlo en un archivo
+    pub content: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportData {
     pub version: String,
@@ -0,0 +1,37 @@
+use serde::{Serialize, Deserialize};
+
+/// Reglas de contraseña que exige un sitio, aplicadas al generar contraseñas para él
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    pub id: String,
+    /// Dominio al que se aplica; también cubre sus subdominios
+    pub domain: String,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Caracteres que el sitio no acepta
+    pub forbidden_characters: String,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_numbers: bool,
+    pub require_symbols: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Datos para crear o modificar una política
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordPolicyRequest {
+    pub domain: String,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    #[serde(default)]
+    pub forbidden_characters: String,
+    #[serde(default)]
+    pub require_uppercase: bool,
+    #[serde(default)]
+    pub require_lowercase: bool,
+    #[serde(default)]
+    pub require_numbers: bool,
+    #[serde(default)]
+    pub require_symbols: bool,
+}
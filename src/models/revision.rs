@@ -0,0 +1,12 @@
+use serde::{Serialize, Deserialize};
+use super::PasswordEntryDto;
+
+/// Contenido que tuvo una entrada entre su `updated_at` y `revised_at`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryRevision {
+    pub revision_id: i64,
+    pub revised_at: String,
+    #[serde(flatten)]
+    pub entry: PasswordEntryDto,
+}
@@ -0,0 +1,47 @@
+use serde::{Serialize, Deserialize};
+use super::EntryId;
+
+/// Tipo de problema detectado en una entrada
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    Weak,
+    Reused,
+    Old,
+    Breached,
+    Unsecured, // URL con http:// en lugar de https://
+}
+
+/// Problemas detectados en una entrada de la bóveda
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryFindings {
+    pub entry_id: EntryId,
+    pub title: String,
+    pub username: String,
+    pub url: Option<String>,
+    pub findings: Vec<FindingKind>,
+    pub strength_score: u8,
+    /// Intentos estimados para adivinar la contraseña (log10)
+    #[serde(default)]
+    pub guesses_log10: Option<f64>,
+    /// Cantidad de otras entradas con la misma contraseña
+    pub reused_with: usize,
+    pub password_age_days: f64,
+    /// Veces que la contraseña aparece en filtraciones conocidas, si se comprobó
+    pub breach_count: Option<u64>,
+}
+
+/// Resultado de una auditoría de seguridad de la bóveda
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityReport {
+    pub generated_at: String,
+    pub total_entries: usize,
+    pub weak_count: usize,
+    pub reused_count: usize,
+    pub old_count: usize,
+    pub breached_count: usize,
+    pub unsecured_count: usize,
+    pub security_score: u8,
+    /// Solo las entradas con algún problema, de la más a la menos grave
+    pub entries: Vec<EntryFindings>,
+}
@@ -0,0 +1,301 @@
+use serde::{Serialize, Deserialize};
+use super::GenerationMode;
+
+/// Tema visual de la interfaz
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Origen de los datos para detectar contraseñas filtradas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BreachCheckBackend {
+    Disabled,
+    /// API Pwned Passwords de Have I Been Pwned (k-anonimato)
+    #[default]
+    Hibp,
+    /// Filtro de Bloom local importado por el usuario, sin llamadas de red
+    Offline,
+}
+
+/// Cómo se encriptan el título, el usuario y la contraseña de las entradas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryEncryption {
+    /// Cada campo por separado, con su propio nonce
+    #[default]
+    Fields,
+    /// Los tres juntos en un solo registro: ocupa menos y se desencripta una
+    /// sola vez, pero para leer un campo hay que desencriptar los tres
+    Record,
+}
+
+/// Opciones por defecto del generador de contraseñas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorDefaults {
+    pub length: usize,
+    pub include_uppercase: bool,
+    pub include_lowercase: bool,
+    pub include_numbers: bool,
+    pub include_symbols: bool,
+    pub exclude_similar: bool,
+    pub mode: GenerationMode,
+}
+
+impl Default for GeneratorDefaults {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            include_uppercase: true,
+            include_lowercase: true,
+            include_numbers: true,
+            include_symbols: true,
+            exclude_similar: false,
+            mode: GenerationMode::Random,
+        }
+    }
+}
+
+/// Encriptación de los lotes de sincronización. Un dispositivo rechaza los
+/// lotes encriptados con un nivel menor que el suyo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptionLevel {
+    /// ChaCha20-Poly1305
+    #[default]
+    Standard,
+    /// AES-256-GCM dentro de ChaCha20-Poly1305, cada uno con su clave
+    Military,
+}
+
+/// Por dónde se sincroniza con los dispositivos de confianza
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMethod {
+    /// Directamente con los dispositivos de la red local
+    #[serde(rename = "p2p")]
+    P2P,
+    /// Solo por el buzón WebDAV, con los lotes encriptados de punta a punta
+    CloudEncrypted,
+    /// Directamente, y por el buzón WebDAV con los que no estén a la vista
+    #[default]
+    Hybrid,
+    /// Sin sincronizar
+    LocalOnly,
+}
+
+impl SyncMethod {
+    /// Si se sincroniza directamente con los dispositivos de la red local
+    pub fn uses_p2p(self) -> bool {
+        matches!(self, SyncMethod::P2P | SyncMethod::Hybrid)
+    }
+
+    /// Si se sincroniza por el buzón en la nube
+    pub fn uses_cloud(self) -> bool {
+        matches!(self, SyncMethod::CloudEncrypted | SyncMethod::Hybrid)
+    }
+}
+
+/// Dónde está el buzón en la nube
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CloudBackend {
+    /// Carpeta WebDAV: Nextcloud, ownCloud…
+    #[default]
+    #[serde(rename = "webdav")]
+    WebDav,
+    /// Bucket compatible con S3: AWS, MinIO, Backblaze B2…
+    S3,
+    /// Carpeta oculta de la aplicación en Google Drive
+    GoogleDrive,
+    /// Carpeta de la aplicación en Dropbox
+    Dropbox,
+    /// Relay propio (`alohopass-relay`)
+    Relay,
+}
+
+/// Preferencias de sincronización guardadas por el usuario
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncPreferences {
+    pub auto_sync: bool,
+    pub sync_interval: u64, // en minutos
+    pub discovery_enabled: bool,
+    pub allow_incoming_connections: bool,
+    /// Dejar para que decida el usuario los campos que dos dispositivos
+    /// editaron a la vez, en vez de quedarse con la edición más reciente
+    pub ask_on_conflict: bool,
+    pub encryption_level: EncryptionLevel,
+    /// Sincronizar solo conectado por Wi-Fi
+    pub wifi_only: bool,
+    /// Redes Wi-Fi en las que se sincroniza; vacía = todas
+    pub allowed_networks: Vec<String>,
+    /// Máximo de dispositivos de confianza
+    pub max_devices: u32,
+    /// Sincronización en pausa; se conserva entre reinicios
+    pub paused: bool,
+    pub method: SyncMethod,
+    /// Carpeta WebDAV del buzón en la nube; vacía = sin configurar. La
+    /// contraseña se guarda aparte, encriptada con la clave maestra.
+    pub webdav_url: String,
+    pub webdav_username: String,
+    pub cloud_backend: CloudBackend,
+    /// URL del servicio S3; vacía = sin configurar. La clave secreta se
+    /// guarda aparte, encriptada con la clave maestra.
+    pub s3_endpoint: String,
+    /// Vacía = us-east-1
+    pub s3_region: String,
+    pub s3_bucket: String,
+    /// Carpeta dentro del bucket; vacía = la raíz
+    pub s3_prefix: String,
+    pub s3_access_key_id: String,
+    /// URL del relay propio; vacía = sin configurar. Su token de acceso se
+    /// guarda aparte, encriptado con la clave maestra.
+    pub relay_url: String,
+    /// Servidor TURN (`turn:` o `turns:`) para las redes que no dejan
+    /// conectar directo; vacío = sin TURN. La credencial se guarda aparte,
+    /// encriptada con la clave maestra.
+    pub turn_url: String,
+    pub turn_username: String,
+}
+
+impl Default for SyncPreferences {
+    fn default() -> Self {
+        Self {
+            auto_sync: true,
+            sync_interval: 15,
+            discovery_enabled: true,
+            allow_incoming_connections: true,
+            ask_on_conflict: false,
+            encryption_level: EncryptionLevel::Standard,
+            wifi_only: false,
+            allowed_networks: Vec::new(),
+            max_devices: 10,
+            paused: false,
+            method: SyncMethod::default(),
+            webdav_url: String::new(),
+            webdav_username: String::new(),
+            cloud_backend: CloudBackend::default(),
+            s3_endpoint: String::new(),
+            s3_region: String::new(),
+            s3_bucket: String::new(),
+            s3_prefix: String::new(),
+            s3_access_key_id: String::new(),
+            relay_url: String::new(),
+            turn_url: String::new(),
+            turn_username: String::new(),
+        }
+    }
+}
+
+/// Copias de seguridad automáticas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupPreferences {
+    pub enabled: bool,
+    /// Carpeta donde se guardan las copias; vacía = sin configurar
+    pub directory: String,
+    pub interval_hours: u32,
+    /// Copias automáticas que se conservan; las más antiguas se borran
+    pub keep: u32,
+}
+
+impl Default for BackupPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: String::new(),
+            interval_hours: 24,
+            keep: 7,
+        }
+    }
+}
+
+/// Mantenimiento periódico de la base de datos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenancePreferences {
+    /// Compactar la base automáticamente cada `interval_days`
+    pub automatic: bool,
+    pub interval_days: u32,
+    /// Días que se conservan los registros del historial de copias; 0 = sin límite
+    pub backup_history_days: u32,
+}
+
+impl Default for MaintenancePreferences {
+    fn default() -> Self {
+        Self {
+            automatic: true,
+            interval_days: 7,
+            backup_history_days: 365,
+        }
+    }
+}
+
+/// Historial de revisiones de las entradas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RevisionPreferences {
+    /// Revisiones que se conservan por entrada; 0 = no guardar historial
+    pub keep_per_entry: u32,
+    /// Días que se conservan las revisiones; 0 = sin límite
+    pub max_age_days: u32,
+}
+
+impl Default for RevisionPreferences {
+    fn default() -> Self {
+        Self {
+            keep_per_entry: 20,
+            max_age_days: 365,
+        }
+    }
+}
+
+/// Configuración persistente de la aplicación.
+/// Los campos que falten en la base de datos toman su valor por defecto,
+/// así se pueden agregar opciones nuevas sin migrar los datos guardados.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub auto_lock_minutes: u32, // 0 = nunca bloquear automáticamente
+    pub clipboard_clear_seconds: u32, // 0 = no limpiar el portapapeles
+    pub theme: Theme,
+    pub language: String,
+    pub quick_search_shortcut: String, // vacío = búsqueda rápida desactivada
+    pub fetch_favicons: bool,
+    pub breach_check: BreachCheckBackend,
+    pub generator: GeneratorDefaults,
+    pub generation_history_size: u32, // 0 = no guardar las contraseñas generadas
+    pub sync: SyncPreferences,
+    pub backup: BackupPreferences,
+    pub maintenance: MaintenancePreferences,
+    pub revisions: RevisionPreferences,
+    pub entry_encryption: EntryEncryption,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            auto_lock_minutes: 5,
+            clipboard_clear_seconds: 30,
+            theme: Theme::default(),
+            language: "es".to_string(),
+            quick_search_shortcut: "CommandOrControl+Shift+Space".to_string(),
+            fetch_favicons: true,
+            breach_check: BreachCheckBackend::default(),
+            generator: GeneratorDefaults::default(),
+            generation_history_size: 20,
+            sync: SyncPreferences::default(),
+            backup: BackupPreferences::default(),
+            maintenance: MaintenancePreferences::default(),
+            revisions: RevisionPreferences::default(),
+            entry_encryption: EntryEncryption::default(),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Serialize, Deserialize};
+use super::{EntryId, ShareId};
+
+/// Entrada compartida por enlace desde este dispositivo. No guarda la clave
+/// que la abre: esa solo está en el enlace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedLink {
+    pub id: ShareId,
+    pub entry_id: EntryId,
+    /// Dirección del relay que la sirve
+    pub url: String,
+    /// Las veces que se puede abrir; el relay lleva la cuenta
+    pub max_views: u32,
+    pub created_at: String,
+    pub expires_at: String,
+}
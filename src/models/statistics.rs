@@ -0,0 +1,28 @@
+use serde::{Serialize, Deserialize};
+use super::CategoryId;
+
+/// Cantidad de entradas de una categoría
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryCount {
+    pub category_id: Option<CategoryId>, // None = sin categoría
+    pub name: Option<String>,
+    pub count: usize,
+}
+
+/// Estadísticas de la bóveda
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatistics {
+    pub total_passwords: usize,
+    pub categories: Vec<CategoryCount>,
+    pub weak_passwords: usize,
+    pub medium_passwords: usize,
+    pub strong_passwords: usize,
+    /// Entradas cuya contraseña aparece en más de una entrada
+    pub reused_passwords: usize,
+    /// Entradas con la contraseña sin cambiar desde hace más de un año
+    pub old_passwords: usize,
+    pub average_password_age_days: f64,
+    pub security_score: u8, // 0-100
+}
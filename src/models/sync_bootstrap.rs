@@ -0,0 +1,19 @@
+use serde::{Serialize, Deserialize};
+use super::VersionVector;
+
+/// Copia inicial de la bóveda que este dispositivo está recibiendo de otro.
+/// Se guarda después de cada página para seguir desde ahí si se corta.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncBootstrap {
+    /// Último elemento recibido, en el orden en que el otro manda la copia;
+    /// `None` antes de la primera página
+    pub position: Option<String>,
+    /// Lo que había visto este dispositivo al empezar, más lo que tenía el
+    /// otro en la primera página. Es lo que se dice haber visto al pasar a la
+    /// sincronización incremental: lo aplicado por páginas no cuenta hasta
+    /// entonces.
+    pub knowledge: VersionVector,
+    /// Si ya llegó la última página
+    pub complete: bool,
+}
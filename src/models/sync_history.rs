@@ -0,0 +1,109 @@
+use serde::{Serialize, Deserialize};
+use super::DeviceId;
+
+/// Quién empezó una sincronización
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    /// Este dispositivo mandó su lote al otro
+    Outgoing,
+    /// El otro dispositivo mandó su lote y este respondió
+    Incoming,
+    /// Se recogió y se dejó un lote en el buzón en la nube
+    Cloud,
+}
+
+impl SyncDirection {
+    /// Nombre con que se guarda en la base de datos
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncDirection::Outgoing => "outgoing",
+            SyncDirection::Incoming => "incoming",
+            SyncDirection::Cloud => "cloud",
+        }
+    }
+
+    pub fn parse(direction: &str) -> Option<Self> {
+        [Self::Outgoing, Self::Incoming, Self::Cloud]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == direction)
+    }
+}
+
+/// Intento de sincronización con otro dispositivo, tal como queda en el
+/// historial. Los cambios recibidos son los que se aplicaron aquí.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHistoryRecord {
+    pub id: i64,
+    pub device_id: DeviceId,
+    pub direction: SyncDirection,
+    pub items_sent: u64,
+    pub items_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub duration_ms: u64,
+    /// `None` si salió bien
+    pub error: Option<String>,
+    pub started_at: String,
+}
+
+/// Filtros y paginación del historial de sincronizaciones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHistoryRequest {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    pub device_id: Option<DeviceId>,
+    /// Solo los intentos que fallaron
+    #[serde(default)]
+    pub failed_only: bool,
+}
+
+/// Página del historial de sincronizaciones, de la más reciente a la más antigua
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHistoryPage {
+    pub records: Vec<SyncHistoryRecord>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// Totales de las sincronizaciones con un dispositivo, desde la primera
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceSyncStats {
+    pub device_id: DeviceId,
+    /// Sincronizaciones que salieron bien
+    pub syncs: u64,
+    pub failures: u64,
+    pub items_sent: u64,
+    pub items_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Lo que tardó la última, saliera bien o no
+    pub last_duration_ms: u64,
+    /// Comienzo de la última que salió bien
+    pub last_sync_at: Option<String>,
+    /// Comienzo de la última que falló
+    pub last_failure_at: Option<String>,
+}
+
+impl DeviceSyncStats {
+    /// Sin ninguna sincronización todavía
+    pub fn empty(device_id: DeviceId) -> Self {
+        Self {
+            device_id,
+            syncs: 0,
+            failures: 0,
+            items_sent: 0,
+            items_received: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            last_duration_ms: 0,
+            last_sync_at: None,
+            last_failure_at: None,
+        }
+    }
+}
@@ -0,0 +1,10 @@
+use serde::{Serialize, Deserialize};
+use super::EntryId;
+
+/// Marca de una entrada eliminada que se envía a los demás dispositivos
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tombstone {
+    pub entry_id: EntryId,
+    pub deleted_at: String,
+}
@@ -0,0 +1,17 @@
+use serde::{Serialize, Deserialize};
+use super::DeviceId;
+
+/// Dispositivo con el que el usuario decidió sincronizar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedDevice {
+    pub device_id: DeviceId,
+    /// Nombre con el que se vio el dispositivo, si se conocía
+    pub name: Option<String>,
+    /// SHA-256 de la clave pública con la que se emparejó; `None` si se
+    /// confió en él sin emparejarlo
+    pub key_fingerprint: Option<String>,
+    /// Clave estática X25519 del saludo Noise fijada al emparejarlo
+    pub static_key: Option<String>,
+    pub trusted_at: String,
+}
@@ -0,0 +1,80 @@
+//! Vectores de versiones de la sincronización
+//!
+//! Cada dispositivo numera sus cambios con un contador que solo crece. El
+//! vector de un elemento guarda, por dispositivo, el número de su último cambio
+//! a ese elemento; el de un dispositivo entero guarda el último número que vio
+//! de cada uno. Comparando vectores se sabe si un cambio ya se vio, si es
+//! posterior o si se hizo a la vez que otro.
+
+use serde::{Serialize, Deserialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use super::DeviceId;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VersionVector(BTreeMap<DeviceId, u64>);
+
+impl VersionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Último número visto de `device_id`; 0 si no se vio ninguno
+    pub fn get(&self, device_id: DeviceId) -> u64 {
+        self.0.get(&device_id).copied().unwrap_or(0)
+    }
+
+    /// Anota el número `counter` de `device_id` si es mayor que el que había
+    pub fn observe(&mut self, device_id: DeviceId, counter: u64) {
+        if counter == 0 {
+            return;
+        }
+        let current = self.0.entry(device_id).or_insert(0);
+        *current = (*current).max(counter);
+    }
+
+    /// Une los dos vectores quedándose con el mayor número de cada dispositivo
+    pub fn merge(&mut self, other: &VersionVector) {
+        for (device_id, counter) in other.iter() {
+            self.observe(device_id, counter);
+        }
+    }
+
+    /// Si tiene algún número que `other` no vio
+    pub fn exceeds(&self, other: &VersionVector) -> bool {
+        self.iter().any(|(device_id, counter)| counter > other.get(device_id))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (DeviceId, u64)> + '_ {
+        self.0.iter().map(|(device_id, counter)| (*device_id, *counter))
+    }
+}
+
+impl FromIterator<(DeviceId, u64)> for VersionVector {
+    fn from_iter<I: IntoIterator<Item = (DeviceId, u64)>>(iter: I) -> Self {
+        let mut vector = Self::new();
+        for (device_id, counter) in iter {
+            vector.observe(device_id, counter);
+        }
+        vector
+    }
+}
+
+/// `Less` si `other` ya vio todo lo de este vector y algo más, `Greater` al
+/// revés y `None` si cada uno tiene cambios que el otro no vio: son
+/// concurrentes.
+impl PartialOrd for VersionVector {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.exceeds(other), other.exceeds(self)) {
+            (false, false) => Some(Ordering::Equal),
+            (false, true) => Some(Ordering::Less),
+            (true, false) => Some(Ordering::Greater),
+            (true, true) => None,
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Ventana activa en Linux mediante AT-SPI
+//!
+//! Se recorre el árbol de accesibilidad del bus AT-SPI buscando el marco con el
+//! estado ACTIVE; funciona igual en X11 y en Wayland. Dentro del navegador, la
+//! barra de direcciones es el primer campo de texto fuera del documento web.
+
+use super::{is_browser, normalize_url, ActiveWindow, ActiveWindowError};
+use log::{info, warn};
+use std::collections::VecDeque;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::Connection;
+
+const REGISTRY: &str = "org.a11y.atspi.Registry";
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const ACCESSIBLE: &str = "org.a11y.atspi.Accessible";
+const TEXT: &str = "org.a11y.atspi.Text";
+
+// Valores de `AtspiRole` y `AtspiStateType`
+const ROLE_TEXT: u32 = 61;
+const ROLE_ENTRY: u32 = 79;
+const ROLE_DOCUMENT_WEB: u32 = 95;
+const STATE_ACTIVE: u32 = 1;
+
+/// Límite de nodos visitados para no recorrer árboles enormes
+const MAX_NODES: usize = 4000;
+
+/// Referencia a un objeto accesible: nombre en el bus y ruta del objeto
+type AccessibleRef = (String, OwnedObjectPath);
+
+fn failed(e: zbus::Error) -> ActiveWindowError {
+    ActiveWindowError::Failed(e.to_string())
+}
+
+/// Abre una conexión al bus de accesibilidad, cuya dirección publica el bus de sesión
+async fn connect_a11y_bus() -> Result<Connection, ActiveWindowError> {
+    let unavailable = |e: zbus::Error| ActiveWindowError::PermissionDenied(format!(
+        "el bus de accesibilidad AT-SPI no está disponible ({}); instala y activa at-spi2-core", e
+    ));
+    let session = Connection::session().await.map_err(unavailable)?;
+    let reply = session
+        .call_method(Some("org.a11y.Bus"), "/org/a11y/bus", Some("org.a11y.Bus"), "GetAddress", &())
+        .await
+        .map_err(unavailable)?;
+    let address: String = reply.body().deserialize().map_err(failed)?;
+    zbus::connection::Builder::address(address.as_str())
+        .map_err(failed)?
+        .build()
+        .await
+        .map_err(unavailable)
+}
+
+/// Indica si los toolkits tienen la accesibilidad activada; Chromium no expone su interfaz si no
+async fn toolkit_accessibility_enabled() -> bool {
+    let Ok(session) = Connection::session().await else { return false };
+    let reply = session
+        .call_method(
+            Some("org.a11y.Bus"),
+            "/org/a11y/bus",
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &("org.a11y.Status", "IsEnabled"),
+        )
+        .await;
+    match reply {
+        Ok(reply) => reply.body().deserialize::<OwnedValue>().ok()
+            .and_then(|value| bool::try_from(value).ok())
+            .unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+async fn children(conn: &Connection, (bus, path): &AccessibleRef) -> Result<Vec<AccessibleRef>, ActiveWindowError> {
+    let reply = conn
+        .call_method(Some(bus.as_str()), path.as_str(), Some(ACCESSIBLE), "GetChildren", &())
+        .await
+        .map_err(failed)?;
+    reply.body().deserialize().map_err(failed)
+}
+
+async fn role(conn: &Connection, (bus, path): &AccessibleRef) -> Result<u32, ActiveWindowError> {
+    let reply = conn
+        .call_method(Some(bus.as_str()), path.as_str(), Some(ACCESSIBLE), "GetRole", &())
+        .await
+        .map_err(failed)?;
+    reply.body().deserialize().map_err(failed)
+}
+
+async fn has_state(conn: &Connection, (bus, path): &AccessibleRef, state: u32) -> Result<bool, ActiveWindowError> {
+    let reply = conn
+        .call_method(Some(bus.as_str()), path.as_str(), Some(ACCESSIBLE), "GetState", &())
+        .await
+        .map_err(failed)?;
+    let states: Vec<u32> = reply.body().deserialize().map_err(failed)?;
+    let word = states.get((state / 32) as usize).copied().unwrap_or(0);
+    Ok(word & (1 << (state % 32)) != 0)
+}
+
+async fn name(conn: &Connection, (bus, path): &AccessibleRef) -> Result<String, ActiveWindowError> {
+    let reply = conn
+        .call_method(
+            Some(bus.as_str()),
+            path.as_str(),
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &(ACCESSIBLE, "Name"),
+        )
+        .await
+        .map_err(failed)?;
+    let value: OwnedValue = reply.body().deserialize().map_err(failed)?;
+    String::try_from(value).map_err(|e| ActiveWindowError::Failed(e.to_string()))
+}
+
+async fn text(conn: &Connection, (bus, path): &AccessibleRef) -> Result<String, ActiveWindowError> {
+    let reply = conn
+        .call_method(Some(bus.as_str()), path.as_str(), Some(TEXT), "GetText", &(0i32, -1i32))
+        .await
+        .map_err(failed)?;
+    reply.body().deserialize().map_err(failed)
+}
+
+/// Busca la aplicación y el marco que tienen el foco
+async fn find_active_frame(conn: &Connection) -> Result<Option<(AccessibleRef, AccessibleRef)>, ActiveWindowError> {
+    let root = (REGISTRY.to_string(), OwnedObjectPath::try_from(ROOT_PATH).map_err(|e| failed(e.into()))?);
+    for app in children(conn, &root).await? {
+        // Aplicaciones colgadas o que ya cerraron no deben impedir la búsqueda
+        let frames = match children(conn, &app).await {
+            Ok(frames) => frames,
+            Err(e) => {
+                warn!("No se pudo leer la aplicación {}: {}", app.0, e);
+                continue;
+            }
+        };
+        for frame in frames {
+            if has_state(conn, &frame, STATE_ACTIVE).await.unwrap_or(false) {
+                return Ok(Some((app, frame)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Recorre el marco en anchura buscando la barra de direcciones
+async fn find_address_bar(conn: &Connection, frame: AccessibleRef) -> Result<Option<String>, ActiveWindowError> {
+    let mut queue = VecDeque::from([frame]);
+    let mut visited = 0;
+    while let Some(node) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_NODES {
+            break;
+        }
+        match role(conn, &node).await? {
+            // El contenido de la página puede tener sus propios campos de texto
+            ROLE_DOCUMENT_WEB => continue,
+            ROLE_ENTRY | ROLE_TEXT => {
+                if let Some(url) = text(conn, &node).await.ok().and_then(|t| normalize_url(&t)) {
+                    return Ok(Some(url));
+                }
+            }
+            _ => {}
+        }
+        queue.extend(children(conn, &node).await.unwrap_or_default());
+    }
+    Ok(None)
+}
+
+pub async fn active_window() -> Result<ActiveWindow, ActiveWindowError> {
+    let conn = connect_a11y_bus().await?;
+    let (app, frame) = find_active_frame(&conn).await?
+        .ok_or_else(|| ActiveWindowError::Failed("ninguna aplicación accesible tiene el foco".to_string()))?;
+    let app_name = name(&conn, &app).await?;
+    info!("Ventana activa: {}", app_name);
+
+    if !is_browser(&app_name) {
+        return Ok(ActiveWindow { app_name, url: None });
+    }
+
+    let url = find_address_bar(&conn, frame).await?;
+    if url.is_none() && !toolkit_accessibility_enabled().await {
+        return Err(ActiveWindowError::PermissionDenied(
+            "la accesibilidad del escritorio está desactivada; actívala para leer la URL del navegador".to_string(),
+        ));
+    }
+    Ok(ActiveWindow { app_name, url })
+}
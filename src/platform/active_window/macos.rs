@@ -0,0 +1,64 @@
+//! Ventana activa en macOS mediante AppleScript
+//!
+//! Leer la aplicación en primer plano requiere permiso de Accesibilidad sobre
+//! System Events, y leer la URL requiere permiso de Automatización sobre cada
+//! navegador. macOS pide ambos la primera vez; si el usuario los rechaza,
+//! `osascript` devuelve los errores -1743 o -25211.
+
+use super::{is_browser, normalize_url, ActiveWindow, ActiveWindowError};
+
+const FRONT_APP_SCRIPT: &str =
+    r#"tell application "System Events" to get name of first application process whose frontmost is true"#;
+
+/// Script para leer la URL de la pestaña activa según la familia del navegador
+fn url_script(app_name: &str) -> Option<String> {
+    let name = app_name.to_lowercase();
+    if name.contains("safari") {
+        Some(format!(r#"tell application "{}" to get URL of front document"#, app_name))
+    } else if name.contains("firefox") || name.contains("librewolf") || name.contains("zen") {
+        // Firefox no expone su modelo a AppleScript: se lee la barra de direcciones por accesibilidad
+        Some(format!(
+            r#"tell application "System Events" to tell process "{}" to get value of UI element 1 of combo box 1 of toolbar "Navigation" of first group of front window"#,
+            app_name
+        ))
+    } else if is_browser(app_name) {
+        // Chrome, Edge, Brave, Opera, Vivaldi y Arc comparten el diccionario de Chromium
+        Some(format!(r#"tell application "{}" to get URL of active tab of front window"#, app_name))
+    } else {
+        None
+    }
+}
+
+async fn run_osascript(script: &str) -> Result<String, ActiveWindowError> {
+    let output = tokio::process::Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .await
+        .map_err(|e| ActiveWindowError::Failed(format!("No se pudo ejecutar osascript: {}", e)))?;
+
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("-1743") {
+        Err(ActiveWindowError::PermissionDenied(
+            "habilita Alohopass en Ajustes del Sistema > Privacidad y seguridad > Automatización".to_string(),
+        ))
+    } else if stderr.contains("-25211") || stderr.contains("-1719") {
+        Err(ActiveWindowError::PermissionDenied(
+            "habilita Alohopass en Ajustes del Sistema > Privacidad y seguridad > Accesibilidad".to_string(),
+        ))
+    } else {
+        Err(ActiveWindowError::Failed(stderr.trim().to_string()))
+    }
+}
+
+pub async fn active_window() -> Result<ActiveWindow, ActiveWindowError> {
+    let app_name = run_osascript(FRONT_APP_SCRIPT).await?;
+    let url = match url_script(&app_name) {
+        Some(script) => normalize_url(&run_osascript(&script).await?),
+        None => None,
+    };
+    Ok(ActiveWindow { app_name, url })
+}
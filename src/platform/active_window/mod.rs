@@ -0,0 +1,120 @@
+//! Detección de la ventana activa y de la URL del navegador en primer plano
+//!
+//! Cada sistema usa su API de accesibilidad:
+//! - Windows: UI Automation (a través de PowerShell y `UIAutomationClient`)
+//! - macOS: AppleScript sobre el navegador activo (requiere permiso de Automatización)
+//! - Linux: AT-SPI sobre D-Bus, válido tanto en X11 como en Wayland
+//!
+//! Los errores de permisos se distinguen del resto para que la interfaz pueda
+//! indicar al usuario qué debe habilitar.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use serde::{Deserialize, Serialize};
+
+/// Información de la ventana en primer plano
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveWindow {
+    /// Nombre de la aplicación o del proceso
+    pub app_name: String,
+    /// URL de la pestaña activa, si la aplicación es un navegador conocido
+    pub url: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ActiveWindowError {
+    /// El sistema no permite leer la ventana activa; incluye una pista para el usuario
+    #[error("Permiso denegado: {0}")]
+    PermissionDenied(String),
+    #[error("La ventana activa no es un navegador soportado: {0}")]
+    NotABrowser(String),
+    #[error("No se encontró la barra de direcciones en {0}")]
+    UrlNotFound(String),
+    #[error("Detección de ventana activa no soportada en esta plataforma")]
+    Unsupported,
+    #[error("Error al consultar la ventana activa: {0}")]
+    Failed(String),
+}
+
+/// Navegadores reconocidos, por fragmento del nombre de proceso o aplicación
+const KNOWN_BROWSERS: &[&str] = &[
+    "firefox", "librewolf", "waterfox", "chrome", "chromium", "msedge", "microsoft edge",
+    "brave", "opera", "vivaldi", "safari",
+];
+
+/// Navegadores cuyo nombre es demasiado corto para buscarlo como fragmento
+const KNOWN_BROWSERS_EXACT: &[&str] = &["arc", "zen"];
+
+/// Indica si el nombre de la aplicación corresponde a un navegador conocido
+pub fn is_browser(app_name: &str) -> bool {
+    let name = app_name.trim().to_lowercase();
+    KNOWN_BROWSERS.iter().any(|browser| name.contains(browser))
+        || KNOWN_BROWSERS_EXACT.contains(&name.as_str())
+}
+
+/// Convierte el texto de la barra de direcciones en una URL absoluta.
+/// Los navegadores basados en Chromium ocultan el esquema `https://`.
+pub fn normalize_url(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    if text.contains("://") || text.starts_with("about:") {
+        return Some(text.to_string());
+    }
+    // Sin esquema: solo aceptar algo con forma de dominio
+    let host = text.split(['/', '?', '#']).next().unwrap_or_default();
+    if host.contains('.') || host.starts_with("localhost") {
+        Some(format!("https://{}", text))
+    } else {
+        None
+    }
+}
+
+/// Obtiene la ventana activa y, si es un navegador, la URL de la pestaña visible
+pub async fn active_window() -> Result<ActiveWindow, ActiveWindowError> {
+    #[cfg(target_os = "linux")]
+    return linux::active_window().await;
+    #[cfg(target_os = "macos")]
+    return macos::active_window().await;
+    #[cfg(target_os = "windows")]
+    return windows::active_window().await;
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    return Err(ActiveWindowError::Unsupported);
+}
+
+/// URL del navegador en primer plano
+pub async fn active_browser_url() -> Result<String, ActiveWindowError> {
+    let window = active_window().await?;
+    if !is_browser(&window.app_name) {
+        return Err(ActiveWindowError::NotABrowser(window.app_name));
+    }
+    window.url.ok_or(ActiveWindowError::UrlNotFound(window.app_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(normalize_url("github.com/n3c4s"), Some("https://github.com/n3c4s".to_string()));
+        assert_eq!(normalize_url("http://localhost:3000"), Some("http://localhost:3000".to_string()));
+        assert_eq!(normalize_url("buscar algo"), None);
+        assert_eq!(normalize_url("palabra"), None);
+    }
+
+    #[test]
+    fn test_is_browser() {
+        assert!(is_browser("Google Chrome"));
+        assert!(is_browser("firefox-esr"));
+        assert!(is_browser("Arc"));
+        assert!(!is_browser("gnome-terminal"));
+        assert!(!is_browser("Search"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Ventana activa en Windows mediante UI Automation
+//!
+//! Se ejecuta un script de PowerShell que usa `UIAutomationClient` para leer el
+//! primer control de edición de la ventana en primer plano, que en todos los
+//! navegadores conocidos es la barra de direcciones.
+
+use super::{normalize_url, ActiveWindow, ActiveWindowError};
+use std::os::windows::process::CommandExt;
+
+/// Evita que se abra una consola al lanzar PowerShell
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+const SCRIPT: &str = r#"
+$ErrorActionPreference = 'Stop'
+Add-Type -AssemblyName UIAutomationClient, UIAutomationTypes
+Add-Type @'
+using System;
+using System.Runtime.InteropServices;
+public static class AlohoWin32 {
+    [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+    [DllImport("user32.dll")] public static extern uint GetWindowThreadProcessId(IntPtr hWnd, out uint pid);
+}
+'@
+$hwnd = [AlohoWin32]::GetForegroundWindow()
+$processId = 0
+[void][AlohoWin32]::GetWindowThreadProcessId($hwnd, [ref]$processId)
+Write-Output (Get-Process -Id $processId).ProcessName
+$root = [System.Windows.Automation.AutomationElement]::FromHandle($hwnd)
+$condition = New-Object System.Windows.Automation.PropertyCondition(
+    [System.Windows.Automation.AutomationElement]::ControlTypeProperty,
+    [System.Windows.Automation.ControlType]::Edit)
+$edit = $root.FindFirst([System.Windows.Automation.TreeScope]::Descendants, $condition)
+if ($edit) {
+    $pattern = $edit.GetCurrentPattern([System.Windows.Automation.ValuePattern]::Pattern)
+    Write-Output $pattern.Current.Value
+}
+"#;
+
+pub async fn active_window() -> Result<ActiveWindow, ActiveWindowError> {
+    let output = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-ExecutionPolicy", "Bypass", "-Command", SCRIPT])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .await
+        .map_err(|e| ActiveWindowError::Failed(format!("No se pudo ejecutar PowerShell: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Las ventanas de procesos elevados no son accesibles desde un proceso sin elevar
+        if stderr.contains("UnauthorizedAccess") || stderr.contains("Access is denied") || stderr.contains("Acceso denegado") {
+            return Err(ActiveWindowError::PermissionDenied(
+                "la ventana activa pertenece a un proceso con privilegios de administrador".to_string(),
+            ));
+        }
+        return Err(ActiveWindowError::Failed(stderr.trim().to_string()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let app_name = lines.next().unwrap_or_default().trim().to_string();
+    let url = lines.next().and_then(normalize_url);
+    Ok(ActiveWindow { app_name, url })
+}
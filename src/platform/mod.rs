@@ -0,0 +1,4 @@
+//! Integraciones específicas de cada sistema operativo
+
+pub mod active_window;
+pub mod network;
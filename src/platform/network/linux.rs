@@ -0,0 +1,76 @@
+//! Red actual en Linux mediante `nmcli`
+//!
+//! NetworkManager lista primero los dispositivos con más prioridad. Se toma el
+//! primer Wi-Fi o Ethernet conectado; los puentes, túneles y el loopback no
+//! dicen por qué red física sale el equipo.
+
+use super::{run, CurrentNetwork, NetworkError, NetworkKind};
+
+/// Separa una línea de `nmcli -t`, que escapa los `:` de los valores con `\`
+fn split_terse(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields.last_mut().unwrap().extend(chars.next()),
+            ':' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Tipo de la conexión principal según `nmcli device status`
+fn parse_device_status(output: &str) -> NetworkKind {
+    let connected: Vec<String> = output.lines()
+        .map(split_terse)
+        .filter(|fields| fields.get(1).is_some_and(|state| state.starts_with("connected")))
+        .filter_map(|fields| fields.into_iter().next())
+        .filter(|kind| !matches!(kind.as_str(), "loopback" | "bridge" | "tun" | "veth" | "wireguard"))
+        .collect();
+    match connected.iter().find(|kind| matches!(kind.as_str(), "wifi" | "ethernet")).map(String::as_str) {
+        Some("wifi") => NetworkKind::Wifi,
+        Some(_) => NetworkKind::Ethernet,
+        None if connected.is_empty() => NetworkKind::Disconnected,
+        None => NetworkKind::Other,
+    }
+}
+
+/// Nombre de la red Wi-Fi activa según `nmcli device wifi list`
+fn parse_active_ssid(output: &str) -> Option<String> {
+    output.lines()
+        .map(split_terse)
+        .find(|fields| fields.first().is_some_and(|active| active == "yes"))
+        .and_then(|fields| fields.into_iter().nth(1))
+        .filter(|ssid| !ssid.is_empty())
+}
+
+pub async fn current_network() -> Result<CurrentNetwork, NetworkError> {
+    let status = run("nmcli", &["-t", "-f", "TYPE,STATE", "device", "status"]).await?;
+    let kind = parse_device_status(&status);
+    let ssid = if kind == NetworkKind::Wifi {
+        let networks = run("nmcli", &["-t", "-f", "ACTIVE,SSID", "device", "wifi", "list", "--rescan", "no"]).await?;
+        parse_active_ssid(&networks)
+    } else {
+        None
+    };
+    Ok(CurrentNetwork { kind, ssid })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nmcli() {
+        let status = "bridge:connected (externally)\nethernet:unavailable\nwifi:connected\nloopback:connected (externally)\n";
+        assert_eq!(parse_device_status(status), NetworkKind::Wifi);
+        assert_eq!(parse_device_status("ethernet:connected\nwifi:connected\n"), NetworkKind::Ethernet);
+        assert_eq!(parse_device_status("gsm:connected\nwifi:disconnected\n"), NetworkKind::Other);
+        assert_eq!(parse_device_status("loopback:connected (externally)\n"), NetworkKind::Disconnected);
+
+        let networks = "no:Vecinos\nyes:Casa\\:5G\nno:\n";
+        assert_eq!(parse_active_ssid(networks), Some("Casa:5G".to_string()));
+        assert_eq!(parse_active_ssid("no:Vecinos\n"), None);
+    }
+}
@@ -0,0 +1,52 @@
+//! Red actual en macOS mediante la ruta por defecto y `networksetup`
+//!
+//! La interfaz de la ruta por defecto es por la que sale el tráfico; su puerto
+//! de hardware dice si es Wi-Fi. macOS puede ocultar el nombre de la red a las
+//! aplicaciones sin permiso de Localización, en cuyo caso queda sin nombre.
+
+use super::{run, CurrentNetwork, NetworkError, NetworkKind};
+
+/// Interfaz de la ruta por defecto según `route -n get default`
+fn parse_default_interface(output: &str) -> Option<String> {
+    output.lines()
+        .find_map(|line| line.trim().strip_prefix("interface:"))
+        .map(|interface| interface.trim().to_string())
+}
+
+/// Puerto de hardware de `device` según `networksetup -listallhardwareports`
+fn parse_hardware_port(output: &str, device: &str) -> Option<String> {
+    let mut port = None;
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("Hardware Port:") {
+            port = Some(name.trim().to_string());
+        } else if line.strip_prefix("Device:").is_some_and(|name| name.trim() == device) {
+            return port;
+        }
+    }
+    None
+}
+
+pub async fn current_network() -> Result<CurrentNetwork, NetworkError> {
+    // Sin ruta por defecto `route` falla: no hay conexión
+    let Some(interface) = run("route", &["-n", "get", "default"]).await.ok()
+        .and_then(|output| parse_default_interface(&output))
+    else {
+        return Ok(CurrentNetwork { kind: NetworkKind::Disconnected, ssid: None });
+    };
+
+    let ports = run("networksetup", &["-listallhardwareports"]).await?;
+    let kind = match parse_hardware_port(&ports, &interface).as_deref() {
+        Some("Wi-Fi" | "AirPort") => NetworkKind::Wifi,
+        Some(port) if port.contains("Ethernet") || port.contains("LAN") => NetworkKind::Ethernet,
+        _ => NetworkKind::Other,
+    };
+    let ssid = if kind == NetworkKind::Wifi {
+        run("networksetup", &["-getairportnetwork", &interface]).await?
+            .trim()
+            .strip_prefix("Current Wi-Fi Network:")
+            .map(|ssid| ssid.trim().to_string())
+    } else {
+        None
+    };
+    Ok(CurrentNetwork { kind, ssid })
+}
@@ -0,0 +1,76 @@
+//! Red a la que está conectado el equipo
+//!
+//! Sirve para respetar las preferencias de sincronizar solo por Wi-Fi o solo
+//! en ciertas redes. Cada sistema se consulta con su herramienta de red:
+//! - Windows: `netsh wlan show interfaces`
+//! - macOS: la ruta por defecto y `networksetup`
+//! - Linux: `nmcli` de NetworkManager
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use serde::{Deserialize, Serialize};
+
+/// Tipo de conexión por la que sale el tráfico
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NetworkKind {
+    Wifi,
+    Ethernet,
+    /// Conexión de otro tipo, como datos móviles o una VPN
+    Other,
+    /// Sin conexión
+    Disconnected,
+}
+
+/// Red actual
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentNetwork {
+    pub kind: NetworkKind,
+    /// Nombre de la red Wi-Fi, si se pudo leer
+    pub ssid: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NetworkError {
+    #[error("Detección de la red no soportada en esta plataforma")]
+    Unsupported,
+    #[error("Error al consultar la red actual: {0}")]
+    Failed(String),
+}
+
+/// Obtiene la red por la que está conectado el equipo
+pub async fn current_network() -> Result<CurrentNetwork, NetworkError> {
+    #[cfg(target_os = "linux")]
+    return linux::current_network().await;
+    #[cfg(target_os = "macos")]
+    return macos::current_network().await;
+    #[cfg(target_os = "windows")]
+    return windows::current_network().await;
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    return Err(NetworkError::Unsupported);
+}
+
+/// Ejecuta una herramienta del sistema y devuelve su salida
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+async fn run(program: &str, args: &[&str]) -> Result<String, NetworkError> {
+    let mut command = tokio::process::Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // Evita que se abra una consola
+        command.creation_flags(0x0800_0000);
+    }
+    let output = command.output().await
+        .map_err(|e| NetworkError::Failed(format!("No se pudo ejecutar {}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(NetworkError::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,24 @@
+//! Red actual en Windows mediante `netsh`
+//!
+//! `netsh wlan show interfaces` traduce las etiquetas al idioma del sistema,
+//! salvo `SSID`, que solo aparece con el Wi-Fi conectado. Sin Wi-Fi, o sin el
+//! servicio de WLAN en equipos que no lo tienen, la conexión se da por otra.
+
+use super::{run, CurrentNetwork, NetworkError, NetworkKind};
+
+/// Nombre de la red Wi-Fi conectada según `netsh wlan show interfaces`
+fn parse_ssid(output: &str) -> Option<String> {
+    output.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(label, _)| label.trim() == "SSID")
+        .map(|(_, ssid)| ssid.trim().to_string())
+}
+
+pub async fn current_network() -> Result<CurrentNetwork, NetworkError> {
+    let ssid = run("netsh", &["wlan", "show", "interfaces"]).await.ok()
+        .and_then(|output| parse_ssid(&output));
+    Ok(match ssid {
+        Some(ssid) => CurrentNetwork { kind: NetworkKind::Wifi, ssid: Some(ssid) },
+        None => CurrentNetwork { kind: NetworkKind::Other, ssid: None },
+    })
+}
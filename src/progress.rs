@@ -0,0 +1,65 @@
+//! Progreso de las operaciones largas sobre la bóveda
+//!
+//! Las importaciones y exportaciones grandes pueden tardar varios segundos.
+//! Mientras tanto se emiten eventos (`import-progress`, `export-progress`) con
+//! los elementos procesados, el total y el título del elemento actual, para que
+//! la interfaz muestre una barra de progreso. Los eventos se espacian para no
+//! saturar la interfaz; el último (todo procesado) se emite siempre.
+
+use crate::models::TransferProgress;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+pub const IMPORT_EVENT: &str = "import-progress";
+pub const EXPORT_EVENT: &str = "export-progress";
+
+/// Tiempo mínimo entre dos eventos
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Contador de progreso que se puede compartir entre los hilos de rayon
+pub struct Progress {
+    app: tauri::AppHandle,
+    event: &'static str,
+    total: usize,
+    processed: AtomicUsize,
+    last_emit: Mutex<Instant>,
+}
+
+impl Progress {
+    /// Empieza a contar y emite el evento inicial con 0 procesados
+    pub fn start(app: tauri::AppHandle, event: &'static str, total: usize) -> Self {
+        let progress = Self {
+            app,
+            event,
+            total,
+            processed: AtomicUsize::new(0),
+            last_emit: Mutex::new(Instant::now()),
+        };
+        progress.emit(0, None);
+        progress
+    }
+
+    /// Cuenta un elemento procesado
+    pub fn advance(&self, title: &str) {
+        let processed = self.processed.fetch_add(1, Ordering::Relaxed) + 1;
+        if processed < self.total {
+            // Si otro hilo está emitiendo, este elemento se cuenta en el próximo evento
+            let Ok(mut last_emit) = self.last_emit.try_lock() else { return };
+            if last_emit.elapsed() < MIN_INTERVAL {
+                return;
+            }
+            *last_emit = Instant::now();
+        }
+        self.emit(processed, Some(title));
+    }
+
+    fn emit(&self, processed: usize, title: Option<&str>) {
+        let _ = self.app.emit_all(self.event, TransferProgress {
+            processed,
+            total: self.total,
+            title: title.map(str::to_string),
+        });
+    }
+}
@@ -0,0 +1,122 @@
+//! Ventana de búsqueda rápida abierta con un atajo global
+//!
+//! El atajo se configura en `AppSettings::quick_search_shortcut` y muestra una
+//! ventana pequeña, siempre visible, que consulta el comando `quick_search`.
+
+use anyhow::Result;
+use log::{info, error};
+use tauri::{AppHandle, GlobalShortcutManager, Manager, WindowBuilder, WindowUrl};
+
+/// Etiqueta de la ventana de búsqueda rápida
+pub const WINDOW_LABEL: &str = "quick-search";
+
+/// Registra el atajo global que abre la búsqueda rápida, reemplazando el anterior.
+/// Un atajo vacío desactiva la búsqueda rápida.
+pub fn register_shortcut(app: &AppHandle, previous: Option<&str>, accelerator: &str) -> Result<()> {
+    let mut shortcuts = app.global_shortcut_manager();
+    
+    if let Some(previous) = previous.filter(|p| !p.is_empty()) {
+        if shortcuts.is_registered(previous).unwrap_or(false) {
+            shortcuts.unregister(previous)?;
+            info!("Atajo de búsqueda rápida anterior liberado: {}", previous);
+        }
+    }
+    
+    if accelerator.is_empty() {
+        info!("Búsqueda rápida desactivada (sin atajo)");
+        return Ok(());
+    }
+    
+    let handle = app.clone();
+    shortcuts.register(accelerator, move || {
+        if let Err(e) = show_window(&handle) {
+            error!("❌ Error al abrir la búsqueda rápida: {}", e);
+        }
+    })?;
+    
+    info!("✅ Atajo de búsqueda rápida registrado: {}", accelerator);
+    Ok(())
+}
+
+/// Muestra la ventana de búsqueda rápida, creándola la primera vez
+pub fn show_window(app: &AppHandle) -> Result<()> {
+    if let Some(window) = app.get_window(WINDOW_LABEL) {
+        window.show()?;
+        window.set_focus()?;
+        window.emit("quick-search-opened", ())?;
+        return Ok(());
+    }
+    
+    info!("Creando ventana de búsqueda rápida...");
+    WindowBuilder::new(app, WINDOW_LABEL, WindowUrl::App("quick-search".into()))
+        .title("AlohoPass")
+        .inner_size(640.0, 420.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .focused(true)
+        .build()?;
+    
+    Ok(())
+}
+
+/// Oculta la ventana de búsqueda rápida si está abierta
+pub fn hide_window(app: &AppHandle) -> Result<()> {
+    if let Some(window) = app.get_window(WINDOW_LABEL) {
+        window.hide()?;
+    }
+    Ok(())
+}
+
+/// Puntúa una entrada para la consulta dada (ya en minúsculas).
+/// Devuelve `None` si la entrada no coincide.
+pub fn match_score(query: &str, title: &str, username: &str, url: Option<&str>) -> Option<u32> {
+    if query.is_empty() {
+        return Some(1);
+    }
+    
+    let title = title.to_lowercase();
+    let username = username.to_lowercase();
+    let url = url.unwrap_or_default().to_lowercase();
+    
+    if title == query {
+        Some(100)
+    } else if title.starts_with(query) {
+        Some(80)
+    } else if title.split_whitespace().any(|word| word.starts_with(query)) {
+        Some(60)
+    } else if title.contains(query) {
+        Some(40)
+    } else if username.starts_with(query) || url.contains(query) {
+        Some(30)
+    } else if username.contains(query) {
+        Some(20)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_score_ranks_title_matches_first() {
+        assert_eq!(match_score("banco", "Banco", "ana", None), Some(100));
+        assert_eq!(match_score("ban", "Banco Nacional", "ana", None), Some(80));
+        assert_eq!(match_score("nac", "Banco Nacional", "ana", None), Some(60));
+        assert_eq!(match_score("cion", "Banco Nacional", "ana", None), Some(40));
+        assert_eq!(match_score("ana", "Correo", "Ana.Perez", None), Some(30));
+        assert_eq!(match_score("example", "Correo", "ana", Some("https://mail.Example.com")), Some(30));
+        assert_eq!(match_score("perez", "Correo", "ana.perez", None), Some(20));
+    }
+
+    #[test]
+    fn test_match_score_without_match() {
+        assert_eq!(match_score("banco", "Correo", "ana", Some("https://mail.example.com")), None);
+        // Sin consulta coincide todo, con la puntuación más baja
+        assert_eq!(match_score("", "Correo", "ana", None), Some(1));
+    }
+}
@@ -0,0 +1,100 @@
+//! Análisis de seguridad de las contraseñas guardadas
+
+use crate::models::PasswordStrength;
+use crate::strength;
+
+/// Puntaje (0-100) a partir del cual una contraseña se considera media
+pub const MEDIUM_THRESHOLD: u8 = 40;
+/// Puntaje (0-100) a partir del cual una contraseña se considera fuerte
+pub const STRONG_THRESHOLD: u8 = 70;
+
+/// Días sin cambiar a partir de los cuales una contraseña se considera antigua
+pub const OLD_PASSWORD_DAYS: f64 = 365.0;
+
+/// Nivel de fortaleza según el puntaje
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StrengthLevel {
+    Weak,
+    Medium,
+    Strong,
+}
+
+impl StrengthLevel {
+    pub fn from_score(score: u8) -> Self {
+        if score >= STRONG_THRESHOLD {
+            StrengthLevel::Strong
+        } else if score >= MEDIUM_THRESHOLD {
+            StrengthLevel::Medium
+        } else {
+            StrengthLevel::Weak
+        }
+    }
+}
+
+/// Evalúa la fortaleza de una contraseña con mensajes en el idioma activo.
+/// Es la misma estimación que se guarda con cada entrada para las estadísticas y la auditoría.
+pub fn evaluate_strength(password: &str) -> PasswordStrength {
+    strength::estimate(password)
+}
+
+/// Riesgos de una entrada que afectan al puntaje global
+#[derive(Debug, Clone, Copy)]
+pub struct EntryRisk {
+    pub level: StrengthLevel,
+    pub reused: bool,
+    pub old: bool,
+    pub breached: bool,
+}
+
+/// Puntaje global de seguridad (0-100) de la bóveda.
+/// Cada entrada aporta según su fortaleza y se penaliza si está reutilizada, filtrada o es antigua.
+pub fn vault_security_score<I>(entries: I) -> u8
+where
+    I: IntoIterator<Item = EntryRisk>,
+{
+    let mut total = 0.0;
+    let mut count = 0usize;
+    for risk in entries {
+        let mut points = match risk.level {
+            StrengthLevel::Strong => 100.0,
+            StrengthLevel::Medium => 60.0,
+            StrengthLevel::Weak => 20.0,
+        };
+        if risk.breached {
+            points *= 0.2;
+        }
+        if risk.reused {
+            points *= 0.5;
+        }
+        if risk.old {
+            points *= 0.8;
+        }
+        total += points;
+        count += 1;
+    }
+    if count == 0 {
+        return 0;
+    }
+    (total / count as f64).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strength_levels() {
+        assert_eq!(StrengthLevel::from_score(evaluate_strength("abc").score), StrengthLevel::Weak);
+        assert_eq!(StrengthLevel::from_score(evaluate_strength("Xk9#mP2$vL7!qR").score), StrengthLevel::Strong);
+    }
+
+    #[test]
+    fn test_vault_security_score_penalizes_reuse() {
+        let risk = EntryRisk { level: StrengthLevel::Strong, reused: false, old: false, breached: false };
+        let unique = vault_security_score([risk]);
+        let reused = vault_security_score([EntryRisk { reused: true, ..risk }]);
+        assert_eq!(unique, 100);
+        assert_eq!(reused, 50);
+        assert_eq!(vault_security_score(std::iter::empty()), 0);
+    }
+}
@@ -0,0 +1,161 @@
+//! Auditoría de seguridad de la bóveda
+//!
+//! Revisa cada entrada buscando contraseñas débiles, reutilizadas, antiguas o
+//! filtradas y sitios sin HTTPS. El análisis usa los metadatos guardados junto a
+//! cada entrada (puntaje, huella, fecha de cambio, filtraciones), así que solo se
+//! desencriptan el título y el usuario de las entradas con problemas.
+
+use crate::models::{EntryFindings, EntryId, FindingKind, SecurityReport};
+use crate::security::{self, EntryRisk, StrengthLevel};
+
+/// Datos de una entrada necesarios para auditarla
+#[derive(Debug, Clone)]
+pub struct AuditInput {
+    pub entry_id: EntryId,
+    pub url: Option<String>,
+    pub strength_score: u8,
+    pub guesses_log10: Option<f64>,
+    /// Cantidad de otras entradas con la misma huella de contraseña
+    pub reused_with: usize,
+    pub password_age_days: f64,
+    pub breach_count: Option<u64>,
+}
+
+impl AuditInput {
+    /// Riesgos de la entrada para el puntaje global
+    pub fn risk(&self) -> EntryRisk {
+        EntryRisk {
+            level: StrengthLevel::from_score(self.strength_score),
+            reused: self.reused_with > 0,
+            old: self.password_age_days > security::OLD_PASSWORD_DAYS,
+            breached: self.breach_count.unwrap_or(0) > 0,
+        }
+    }
+}
+
+/// Indica si la URL usa HTTP sin cifrar
+pub fn is_unsecured_url(url: &str) -> bool {
+    url.trim().to_ascii_lowercase().starts_with("http://")
+}
+
+/// Problemas detectados en una entrada
+pub fn findings_for(input: &AuditInput) -> Vec<FindingKind> {
+    let risk = input.risk();
+    let mut findings = Vec::new();
+    if risk.breached {
+        findings.push(FindingKind::Breached);
+    }
+    if risk.reused {
+        findings.push(FindingKind::Reused);
+    }
+    if risk.level == StrengthLevel::Weak {
+        findings.push(FindingKind::Weak);
+    }
+    if input.url.as_deref().is_some_and(is_unsecured_url) {
+        findings.push(FindingKind::Unsecured);
+    }
+    if risk.old {
+        findings.push(FindingKind::Old);
+    }
+    findings
+}
+
+/// Gravedad de un problema, para ordenar el informe
+fn severity(kind: FindingKind) -> u32 {
+    match kind {
+        FindingKind::Breached => 16,
+        FindingKind::Reused => 8,
+        FindingKind::Weak => 4,
+        FindingKind::Unsecured => 2,
+        FindingKind::Old => 1,
+    }
+}
+
+/// Genera el informe. `resolve` devuelve el título y el usuario de una entrada y
+/// solo se llama para las entradas con problemas.
+pub fn build_report<E, F>(inputs: Vec<AuditInput>, mut resolve: F) -> Result<SecurityReport, E>
+where
+    F: FnMut(&AuditInput) -> Result<(String, String), E>,
+{
+    let security_score = security::vault_security_score(inputs.iter().map(AuditInput::risk));
+    let mut report = SecurityReport {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        total_entries: inputs.len(),
+        weak_count: 0,
+        reused_count: 0,
+        old_count: 0,
+        breached_count: 0,
+        unsecured_count: 0,
+        security_score,
+        entries: Vec::new(),
+    };
+
+    for input in inputs {
+        let findings = findings_for(&input);
+        if findings.is_empty() {
+            continue;
+        }
+        for kind in &findings {
+            match kind {
+                FindingKind::Weak => report.weak_count += 1,
+                FindingKind::Reused => report.reused_count += 1,
+                FindingKind::Old => report.old_count += 1,
+                FindingKind::Breached => report.breached_count += 1,
+                FindingKind::Unsecured => report.unsecured_count += 1,
+            }
+        }
+        let (title, username) = resolve(&input)?;
+        report.entries.push(EntryFindings {
+            entry_id: input.entry_id,
+            title,
+            username,
+            url: input.url,
+            findings,
+            strength_score: input.strength_score,
+            guesses_log10: input.guesses_log10,
+            reused_with: input.reused_with,
+            password_age_days: input.password_age_days,
+            breach_count: input.breach_count,
+        });
+    }
+
+    report.entries.sort_by_key(|entry| {
+        std::cmp::Reverse(entry.findings.iter().map(|kind| severity(*kind)).sum::<u32>())
+    });
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(score: u8, reused_with: usize, url: Option<&str>) -> AuditInput {
+        AuditInput {
+            entry_id: EntryId::new(),
+            url: url.map(str::to_string),
+            strength_score: score,
+            guesses_log10: None,
+            reused_with,
+            password_age_days: 10.0,
+            breach_count: None,
+        }
+    }
+
+    #[test]
+    fn test_report_only_lists_entries_with_findings() {
+        let inputs = vec![
+            input(90, 0, Some("https://example.com")),
+            input(10, 0, None),
+            input(90, 1, Some("http://example.com")),
+        ];
+        let (weak, reused_http) = (inputs[1].entry_id, inputs[2].entry_id);
+        let report = build_report(inputs, |_| Ok::<_, ()>((String::new(), String::new()))).unwrap();
+
+        assert_eq!(report.total_entries, 3);
+        assert_eq!(report.weak_count, 1);
+        assert_eq!(report.reused_count, 1);
+        assert_eq!(report.unsecured_count, 1);
+        let ids: Vec<_> = report.entries.iter().map(|e| e.entry_id).collect();
+        assert_eq!(ids, vec![reused_http, weak]);
+    }
+}
@@ -13,6 +13,7 @@ export interface DeviceInfo {
   is_trusted: boolean;
   ip_address?: string;
   port?: number;
+  addresses?: string[];
 }
 
 export interface SyncStatus {
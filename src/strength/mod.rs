@@ -0,0 +1,695 @@
+//! Estimador de fortaleza de contraseñas al estilo de zxcvbn
+//!
+//! Busca en la contraseña los patrones que un atacante probaría primero (contraseñas
+//! y palabras comunes, sustituciones l33t, filas del teclado, secuencias,
+//! repeticiones y fechas) y calcula cuántos intentos harían falta para adivinarla
+//! eligiendo la combinación de patrones más barata. Lo que no coincide con ningún
+//! patrón se cuenta por fuerza bruta.
+
+use crate::i18n::{self, Message};
+use crate::models::{CrackTime, CrackTimes, PasswordStrength, PatternKind, PatternMatch};
+use chrono::Datelike;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Solo se analizan los primeros caracteres; más allá cualquier contraseña ya es fuerte
+const MAX_ANALYZED_CHARS: usize = 100;
+const MIN_DICTIONARY_LEN: usize = 3;
+/// Intentos mínimos de cada patrón extra, para no preferir secuencias de muchos patrones pequeños
+const MIN_GUESSES_PER_EXTRA_MATCH: f64 = 10_000.0;
+const MIN_YEAR_SPACE: i32 = 20;
+
+/// Teclas desde las que puede empezar un patrón de teclado y vecinas promedio de cada tecla
+const KEYBOARD_STARTING_KEYS: f64 = 47.0;
+const KEYBOARD_AVERAGE_DEGREE: f64 = 4.6;
+const KEYBOARD_ROWS: &[&str] = &[
+    "1234567890-=",
+    "qwertyuiop[]",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+    "qwertzuiop",
+    "yxcvbnm",
+    "azertyuiop",
+    "qsdfghjklm",
+    "wxcvbn",
+];
+
+const L33T_TABLE: &[(char, &[char])] = &[
+    ('4', &['a']),
+    ('@', &['a']),
+    ('8', &['b']),
+    ('(', &['c']),
+    ('3', &['e']),
+    ('6', &['g']),
+    ('9', &['g']),
+    ('1', &['i', 'l']),
+    ('!', &['i']),
+    ('|', &['i', 'l']),
+    ('0', &['o']),
+    ('$', &['s']),
+    ('5', &['s']),
+    ('7', &['t']),
+    ('+', &['t']),
+    ('2', &['z']),
+];
+/// Máximo de lecturas alternativas de un fragmento con sustituciones ambiguas ('1' puede ser 'i' o 'l')
+const MAX_L33T_VARIANTS: usize = 8;
+
+/// Intentos por segundo de cada escenario de ataque
+const ONLINE_THROTTLED_PER_SECOND: f64 = 100.0 / 3600.0;
+const ONLINE_PER_SECOND: f64 = 10.0;
+const OFFLINE_SLOW_PER_SECOND: f64 = 1e4;
+const OFFLINE_FAST_PER_SECOND: f64 = 1e10;
+
+/// Coincidencia candidata dentro de la contraseña (índices de carácter inclusivos)
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    kind: PatternKind,
+    start: usize,
+    end: usize,
+    guesses: f64,
+    rank: Option<usize>,
+    common_password: bool,
+    l33t: bool,
+    reversed: bool,
+}
+
+impl Candidate {
+    fn new(kind: PatternKind, start: usize, end: usize, guesses: f64) -> Self {
+        Self { kind, start, end, guesses, rank: None, common_password: false, l33t: false, reversed: false }
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+fn ranked(source: &'static str) -> HashMap<&'static str, usize> {
+    let mut ranks = HashMap::new();
+    for (index, word) in source.lines().map(str::trim).filter(|word| !word.is_empty()).enumerate() {
+        ranks.entry(word).or_insert(index + 1);
+    }
+    ranks
+}
+
+fn common_passwords() -> &'static HashMap<&'static str, usize> {
+    static PASSWORDS: OnceLock<HashMap<&'static str, usize>> = OnceLock::new();
+    PASSWORDS.get_or_init(|| ranked(include_str!("common_passwords.txt")))
+}
+
+fn common_words() -> &'static HashMap<&'static str, usize> {
+    static WORDS: OnceLock<HashMap<&'static str, usize>> = OnceLock::new();
+    WORDS.get_or_init(|| ranked(include_str!("words.txt")))
+}
+
+/// Posición de la palabra en las listas comunes y si viene de la lista de contraseñas
+fn lookup(word: &str) -> Option<(usize, bool)> {
+    let password_rank = common_passwords().get(word).map(|rank| (*rank, true));
+    let word_rank = common_words().get(word).map(|rank| (*rank, false));
+    match (password_rank, word_rank) {
+        (Some(password), Some(word)) if word.0 < password.0 => Some(word),
+        (Some(password), _) => Some(password),
+        (None, word) => word,
+    }
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (1..=k).fold(1.0, |acc, i| acc * (n - k + i) as f64 / i as f64)
+}
+
+/// Variantes de mayúsculas que un atacante probaría para el fragmento
+fn uppercase_variations(token: &[char]) -> f64 {
+    let upper = token.iter().filter(|c| c.is_uppercase()).count();
+    let lower = token.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    let first_only = upper == 1 && token.first().is_some_and(|c| c.is_uppercase());
+    let last_only = upper == 1 && token.last().is_some_and(|c| c.is_uppercase());
+    if lower == 0 || first_only || last_only {
+        return 2.0;
+    }
+    (1..=upper.min(lower)).map(|k| binomial(upper + lower, k)).sum()
+}
+
+fn char_cardinality(token: &[char]) -> f64 {
+    let mut cardinality = 0.0;
+    if token.iter().any(|c| c.is_ascii_lowercase()) {
+        cardinality += 26.0;
+    }
+    if token.iter().any(|c| c.is_ascii_uppercase()) {
+        cardinality += 26.0;
+    }
+    if token.iter().any(|c| c.is_ascii_digit()) {
+        cardinality += 10.0;
+    }
+    if token.iter().any(|c| c.is_ascii() && !c.is_ascii_alphanumeric()) {
+        cardinality += 33.0;
+    }
+    if token.iter().any(|c| !c.is_ascii()) {
+        cardinality += 100.0;
+    }
+    cardinality
+}
+
+fn bruteforce_guesses(token: &[char]) -> f64 {
+    let guesses = char_cardinality(token).powi(token.len() as i32);
+    let minimum = if token.len() == 1 { 11.0 } else { 51.0 };
+    guesses.max(minimum)
+}
+
+/// Lecturas del fragmento deshaciendo sustituciones l33t, con la cantidad de sustituciones
+fn unleet_variants(token: &[char]) -> Vec<(String, usize)> {
+    let substitutions = |c: char| L33T_TABLE.iter().find(|(leet, _)| *leet == c).map(|(_, letters)| *letters);
+    if !token.iter().any(|c| substitutions(*c).is_some()) {
+        return Vec::new();
+    }
+    let mut variants = vec![(String::new(), 0usize)];
+    for c in token {
+        match substitutions(*c) {
+            Some(letters) => {
+                variants = variants
+                    .iter()
+                    .flat_map(|(text, subs)| letters.iter().map(move |letter| (format!("{}{}", text, letter), subs + 1)))
+                    .take(MAX_L33T_VARIANTS)
+                    .collect();
+            }
+            None => variants.iter_mut().for_each(|(text, _)| text.push(*c)),
+        }
+    }
+    variants
+}
+
+fn dictionary_matches(chars: &[char], lower: &[char], out: &mut Vec<Candidate>) {
+    let n = lower.len();
+    for start in 0..n {
+        for end in (start + MIN_DICTIONARY_LEN - 1)..n {
+            let original = &chars[start..=end];
+            let token: String = lower[start..=end].iter().collect();
+            let reversed: String = token.chars().rev().collect();
+
+            let mut readings = vec![(token.clone(), 0, false)];
+            if reversed != token {
+                readings.push((reversed, 0, true));
+            }
+            readings.extend(unleet_variants(&lower[start..=end]).into_iter().map(|(text, subs)| (text, subs, false)));
+
+            for (word, subs, is_reversed) in readings {
+                let Some((rank, common_password)) = lookup(&word) else { continue };
+                let mut guesses = rank as f64 * uppercase_variations(original) * 2f64.powi(subs as i32);
+                if is_reversed {
+                    guesses *= 2.0;
+                }
+                out.push(Candidate {
+                    rank: Some(rank),
+                    common_password,
+                    l33t: subs > 0,
+                    reversed: is_reversed,
+                    ..Candidate::new(PatternKind::Dictionary, start, end, guesses)
+                });
+            }
+        }
+    }
+}
+
+fn spatial_guesses(length: usize, turns: usize) -> f64 {
+    let mut guesses = 0.0;
+    for i in 2..=length {
+        for j in 1..=turns.min(i - 1) {
+            guesses += binomial(i - 1, j - 1) * KEYBOARD_STARTING_KEYS * KEYBOARD_AVERAGE_DEGREE.powi(j as i32);
+        }
+    }
+    guesses
+}
+
+fn spatial_matches(chars: &[char], lower: &[char], out: &mut Vec<Candidate>) {
+    let n = lower.len();
+    for row in KEYBOARD_ROWS {
+        let row: Vec<char> = row.chars().collect();
+        let position = |c: char| row.iter().position(|key| *key == c);
+        let mut start = 0;
+        while start < n {
+            let mut end = start;
+            let mut turns = 1;
+            let mut direction = 0i32;
+            while end + 1 < n {
+                let (Some(a), Some(b)) = (position(lower[end]), position(lower[end + 1])) else { break };
+                let step = b as i32 - a as i32;
+                if step.abs() != 1 {
+                    break;
+                }
+                if direction != 0 && step != direction {
+                    turns += 1;
+                }
+                direction = step;
+                end += 1;
+            }
+            if end - start + 1 >= 3 {
+                let guesses = spatial_guesses(end - start + 1, turns) * uppercase_variations(&chars[start..=end]);
+                out.push(Candidate::new(PatternKind::Spatial, start, end, guesses));
+            }
+            start = end.max(start + 1);
+        }
+    }
+}
+
+fn sequence_matches(chars: &[char], lower: &[char], out: &mut Vec<Candidate>) {
+    let same_class = |a: char, b: char| {
+        (a.is_ascii_lowercase() && b.is_ascii_lowercase()) || (a.is_ascii_digit() && b.is_ascii_digit())
+    };
+    let n = lower.len();
+    let mut start = 0;
+    while start + 2 < n {
+        let delta = lower[start + 1] as i32 - lower[start] as i32;
+        if delta == 0 || delta.abs() > 5 || !same_class(lower[start], lower[start + 1]) {
+            start += 1;
+            continue;
+        }
+        let mut end = start + 1;
+        while end + 1 < n
+            && same_class(lower[end], lower[end + 1])
+            && lower[end + 1] as i32 - lower[end] as i32 == delta
+        {
+            end += 1;
+        }
+        if end - start + 1 >= 3 {
+            let first = lower[start];
+            let mut base: f64 = if ['a', 'z', '0', '1', '9'].contains(&first) {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            if delta < 0 {
+                base *= 2.0;
+            }
+            let guesses = base * (end - start + 1) as f64 * uppercase_variations(&chars[start..=end]);
+            out.push(Candidate::new(PatternKind::Sequence, start, end, guesses));
+        }
+        start = end;
+    }
+}
+
+fn repeat_matches(chars: &[char], out: &mut Vec<Candidate>) {
+    let n = chars.len();
+    let mut start = 0;
+    while start < n {
+        let mut best: Option<(usize, usize)> = None;
+        for block_len in 1..=(n - start) / 2 {
+            let block = &chars[start..start + block_len];
+            let mut reps = 1;
+            while start + (reps + 1) * block_len <= n
+                && &chars[start + reps * block_len..start + (reps + 1) * block_len] == block
+            {
+                reps += 1;
+            }
+            let min_reps = if block_len == 1 { 3 } else { 2 };
+            if reps >= min_reps && best.is_none_or(|(len, count)| block_len * reps > len * count) {
+                best = Some((block_len, reps));
+            }
+        }
+        match best {
+            Some((block_len, reps)) => {
+                let (block_guesses, _) = most_guessable(&chars[start..start + block_len]);
+                let end = start + block_len * reps - 1;
+                out.push(Candidate::new(PatternKind::Repeat, start, end, block_guesses * reps as f64));
+                start = end + 1;
+            }
+            None => start += 1,
+        }
+    }
+}
+
+fn year_guesses(year: i32) -> f64 {
+    let reference_year = chrono::Utc::now().year();
+    (year - reference_year).abs().max(MIN_YEAR_SPACE) as f64
+}
+
+fn valid_date(day: u32, month: u32, year: i32) -> bool {
+    (1..=31).contains(&day) && (1..=12).contains(&month) && (1000..=2050).contains(&year)
+}
+
+/// Interpreta un fragmento como fecha (día, mes y año en los órdenes habituales)
+fn parse_date(token: &str) -> Option<(i32, bool)> {
+    let separator = token.chars().find(|c| !c.is_ascii_digit());
+    let parts: Vec<&str> = match separator {
+        Some(separator) if "/-._ ".contains(separator) => token.split(separator).collect(),
+        Some(_) => return None,
+        None => match token.len() {
+            6 => vec![&token[0..2], &token[2..4], &token[4..6]],
+            8 if token.starts_with("19") || token.starts_with("20") => vec![&token[0..4], &token[4..6], &token[6..8]],
+            8 => vec![&token[0..2], &token[2..4], &token[4..8]],
+            _ => return None,
+        },
+    };
+    if parts.len() != 3 || parts.iter().any(|part| part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit())) {
+        return None;
+    }
+    let expand_year = |part: &str| -> Option<i32> {
+        let value: i32 = part.parse().ok()?;
+        match part.len() {
+            2 if value > 50 => Some(1900 + value),
+            2 => Some(2000 + value),
+            4 => Some(value),
+            _ => None,
+        }
+    };
+    let number = |part: &str| part.parse::<u32>().ok();
+    let candidates = [
+        (number(parts[0]), number(parts[1]), expand_year(parts[2])), // día/mes/año
+        (number(parts[1]), number(parts[0]), expand_year(parts[2])), // mes/día/año
+        (number(parts[2]), number(parts[1]), expand_year(parts[0])), // año/mes/día
+    ];
+    candidates
+        .into_iter()
+        .filter_map(|(day, month, year)| Some((day?, month?, year?)))
+        .find(|(day, month, year)| valid_date(*day, *month, *year))
+        .map(|(_, _, year)| (year, separator.is_some()))
+}
+
+fn date_matches(lower: &[char], out: &mut Vec<Candidate>) {
+    let n = lower.len();
+    for start in 0..n {
+        // Años sueltos
+        if start + 4 <= n && lower[start..start + 4].iter().all(|c| c.is_ascii_digit()) {
+            let year: i32 = lower[start..start + 4].iter().collect::<String>().parse().unwrap_or_default();
+            if (1900..=2050).contains(&year) {
+                out.push(Candidate::new(PatternKind::Date, start, start + 3, year_guesses(year)));
+            }
+        }
+        // Fechas completas
+        for end in (start + 5)..n.min(start + 10) {
+            let token: String = lower[start..=end].iter().collect();
+            if let Some((year, with_separator)) = parse_date(&token) {
+                let mut guesses = 365.0 * year_guesses(year);
+                if with_separator {
+                    guesses *= 4.0;
+                }
+                out.push(Candidate::new(PatternKind::Date, start, end, guesses));
+            }
+        }
+    }
+}
+
+fn factorial(n: usize) -> f64 {
+    (1..=n).fold(1.0, |acc, i| acc * i as f64)
+}
+
+/// Paso óptimo para cubrir un prefijo de la contraseña con una cantidad dada de patrones
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    product: f64,
+    candidate: Candidate,
+}
+
+/// Intentos necesarios y secuencia de patrones más barata que cubre toda la contraseña
+fn most_guessable(chars: &[char]) -> (f64, Vec<Candidate>) {
+    let n = chars.len();
+    if n == 0 {
+        return (1.0, Vec::new());
+    }
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+
+    let mut candidates = Vec::new();
+    dictionary_matches(chars, &lower, &mut candidates);
+    spatial_matches(chars, &lower, &mut candidates);
+    sequence_matches(chars, &lower, &mut candidates);
+    repeat_matches(chars, &mut candidates);
+    date_matches(&lower, &mut candidates);
+
+    let mut by_end: Vec<Vec<Candidate>> = vec![Vec::new(); n];
+    for candidate in candidates {
+        by_end[candidate.end].push(candidate);
+    }
+
+    // best[fin][cantidad de patrones] = paso con el menor producto de intentos
+    let mut best: Vec<HashMap<usize, Step>> = vec![HashMap::new(); n];
+    for end in 0..n {
+        let bruteforce = (0..=end).map(|start| {
+            Candidate::new(PatternKind::BruteForce, start, end, bruteforce_guesses(&chars[start..=end]))
+        });
+        for candidate in by_end[end].clone().into_iter().chain(bruteforce) {
+            let previous: Vec<(usize, f64)> = if candidate.start == 0 {
+                vec![(0, 1.0)]
+            } else {
+                best[candidate.start - 1]
+                    .iter()
+                    // Dos tramos de fuerza bruta seguidos son en realidad uno solo
+                    .filter(|(_, step)| {
+                        candidate.kind != PatternKind::BruteForce || step.candidate.kind != PatternKind::BruteForce
+                    })
+                    .map(|(count, step)| (*count, step.product))
+                    .collect()
+            };
+            for (count, product) in previous {
+                let product = product * candidate.guesses;
+                let improves = best[end].get(&(count + 1)).is_none_or(|step| product < step.product);
+                if improves {
+                    best[end].insert(count + 1, Step { product, candidate });
+                }
+            }
+        }
+    }
+
+    let total = |count: usize, product: f64| {
+        factorial(count) * product + MIN_GUESSES_PER_EXTRA_MATCH.powi(count as i32 - 1)
+    };
+    let (mut count, guesses) = best[n - 1]
+        .iter()
+        .map(|(count, step)| (*count, total(*count, step.product)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("toda contraseña no vacía se cubre por fuerza bruta");
+
+    let mut sequence = Vec::with_capacity(count);
+    let mut end = n - 1;
+    while count > 0 {
+        let step = best[end][&count];
+        sequence.push(step.candidate);
+        count -= 1;
+        if step.candidate.start == 0 {
+            break;
+        }
+        end = step.candidate.start - 1;
+    }
+    sequence.reverse();
+    (guesses, sequence)
+}
+
+/// Puntaje de 0 a 4 con los umbrales de zxcvbn
+fn rating(guesses: f64) -> u8 {
+    const DELTA: f64 = 5.0;
+    if guesses < 1e3 + DELTA {
+        0
+    } else if guesses < 1e6 + DELTA {
+        1
+    } else if guesses < 1e8 + DELTA {
+        2
+    } else if guesses < 1e10 + DELTA {
+        3
+    } else {
+        4
+    }
+}
+
+/// Puntaje de 0 a 100 alineado con los niveles de `security`: débil hasta 10⁶ intentos,
+/// media hasta 10¹⁰ y fuerte a partir de ahí
+fn score(guesses_log10: f64) -> u8 {
+    const KNOTS: [(f64, f64); 4] = [(0.0, 0.0), (6.0, 40.0), (10.0, 70.0), (16.0, 100.0)];
+    if guesses_log10 >= KNOTS[3].0 {
+        return 100;
+    }
+    let segment = KNOTS.windows(2).find(|pair| guesses_log10 < pair[1].0).unwrap_or(&KNOTS[2..4]);
+    let ((x0, y0), (x1, y1)) = (segment[0], segment[1]);
+    let value = y0 + (guesses_log10.max(0.0) - x0) * (y1 - y0) / (x1 - x0);
+    value.round().clamp(0.0, 100.0) as u8
+}
+
+fn crack_time(seconds: f64) -> CrackTime {
+    const MINUTE: f64 = 60.0;
+    const HOUR: f64 = MINUTE * 60.0;
+    const DAY: f64 = HOUR * 24.0;
+    const MONTH: f64 = DAY * 31.0;
+    const YEAR: f64 = MONTH * 12.0;
+    const CENTURY: f64 = YEAR * 100.0;
+
+    let units: [(f64, f64, &'static str, &'static str); 6] = [
+        (MINUTE, 1.0, "strength.time.second", "strength.time.seconds"),
+        (HOUR, MINUTE, "strength.time.minute", "strength.time.minutes"),
+        (DAY, HOUR, "strength.time.hour", "strength.time.hours"),
+        (MONTH, DAY, "strength.time.day", "strength.time.days"),
+        (YEAR, MONTH, "strength.time.month", "strength.time.months"),
+        (CENTURY, YEAR, "strength.time.year", "strength.time.years"),
+    ];
+    let message = if seconds < 1.0 {
+        Message::new("strength.time.instant")
+    } else {
+        units
+            .iter()
+            .find(|(limit, ..)| seconds < *limit)
+            .map(|&(_, unit, singular, plural)| {
+                let count = (seconds / unit).round() as u64;
+                if count == 1 {
+                    Message::new(singular)
+                } else {
+                    Message::new(plural).with("count", count)
+                }
+            })
+            .unwrap_or_else(|| Message::new("strength.time.centuries"))
+    };
+    CrackTime { seconds, display: message.render(i18n::current_locale()) }
+}
+
+/// Advertencia y sugerencias dirigidas al patrón más largo encontrado
+fn feedback(chars: &[char], rating: u8, sequence: &[Candidate]) -> (Vec<String>, Vec<String>) {
+    let mut feedback = Vec::new();
+    let mut suggestions = Vec::new();
+    if chars.len() < 8 {
+        feedback.push(i18n::t("strength.tooShort"));
+    }
+    if rating > 2 {
+        return (feedback, suggestions);
+    }
+    suggestions.push(i18n::t("strength.addWords"));
+
+    let longest = sequence
+        .iter()
+        .filter(|candidate| candidate.kind != PatternKind::BruteForce)
+        .max_by_key(|candidate| candidate.len());
+    let Some(longest) = longest else {
+        suggestions.push(i18n::t("strength.useTwelveChars"));
+        return (feedback, suggestions);
+    };
+
+    match longest.kind {
+        PatternKind::Dictionary => {
+            let rank = longest.rank.unwrap_or(usize::MAX);
+            feedback.push(i18n::t(match (longest.common_password, rank) {
+                (true, 0..=10) => "strength.topTenPassword",
+                (true, 11..=100) => "strength.topHundredPassword",
+                (true, _) => "strength.commonPassword",
+                (false, _) => "strength.dictionaryWord",
+            }));
+            let token = &chars[longest.start..=longest.end];
+            if token.first().is_some_and(|c| c.is_uppercase()) {
+                let all_upper = token.iter().all(|c| !c.is_lowercase());
+                suggestions.push(i18n::t(if all_upper { "strength.allUppercase" } else { "strength.capitalization" }));
+            }
+            if longest.reversed {
+                suggestions.push(i18n::t("strength.reversedWords"));
+            }
+            if longest.l33t {
+                suggestions.push(i18n::t("strength.predictableSubstitutions"));
+            }
+        }
+        PatternKind::Spatial => {
+            feedback.push(i18n::t("strength.keyboardPattern"));
+            suggestions.push(i18n::t("strength.avoidKeyboardPatterns"));
+        }
+        PatternKind::Repeat => {
+            feedback.push(i18n::t("strength.repeats"));
+            suggestions.push(i18n::t("strength.avoidRepeats"));
+        }
+        PatternKind::Sequence => {
+            feedback.push(i18n::t("strength.sequences"));
+            suggestions.push(i18n::t("strength.avoidSequences"));
+        }
+        PatternKind::Date if longest.len() == 4 => {
+            feedback.push(i18n::t("strength.recentYears"));
+            suggestions.push(i18n::t("strength.avoidYears"));
+        }
+        PatternKind::Date => {
+            feedback.push(i18n::t("strength.dates"));
+            suggestions.push(i18n::t("strength.avoidDates"));
+        }
+        PatternKind::BruteForce => {}
+    }
+    (feedback, suggestions)
+}
+
+/// Estima cuántos intentos harían falta para adivinar la contraseña
+pub fn estimate(password: &str) -> PasswordStrength {
+    let chars: Vec<char> = password.chars().take(MAX_ANALYZED_CHARS).collect();
+    let (guesses, sequence) = most_guessable(&chars);
+    let guesses_log10 = guesses.log10();
+    let rating = rating(guesses);
+    let (feedback, suggestions) = feedback(&chars, rating, &sequence);
+
+    let patterns = sequence
+        .iter()
+        .map(|candidate| PatternMatch {
+            kind: candidate.kind,
+            token: chars[candidate.start..=candidate.end].iter().collect(),
+            start: candidate.start,
+            end: candidate.end,
+            guesses_log10: candidate.guesses.log10(),
+            rank: candidate.rank,
+            l33t: candidate.l33t,
+            reversed: candidate.reversed,
+        })
+        .collect();
+
+    PasswordStrength {
+        score: score(guesses_log10),
+        rating,
+        guesses_log10,
+        entropy_bits: guesses.log2(),
+        crack_times: CrackTimes {
+            online_throttled: crack_time(guesses / ONLINE_THROTTLED_PER_SECOND),
+            online: crack_time(guesses / ONLINE_PER_SECOND),
+            offline_slow: crack_time(guesses / OFFLINE_SLOW_PER_SECOND),
+            offline_fast: crack_time(guesses / OFFLINE_FAST_PER_SECOND),
+        },
+        patterns,
+        feedback,
+        suggestions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(password: &str) -> Vec<PatternKind> {
+        estimate(password).patterns.iter().map(|pattern| pattern.kind).collect()
+    }
+
+    #[test]
+    fn test_common_passwords_are_weak() {
+        for password in ["password", "123456", "qwerty", "P@ssw0rd", "drowssap"] {
+            let strength = estimate(password);
+            assert!(strength.rating <= 1, "{} debería ser débil ({})", password, strength.guesses_log10);
+            assert_eq!(strength.patterns[0].kind, PatternKind::Dictionary);
+        }
+        assert!(estimate("P@ssw0rd").patterns[0].l33t);
+        assert!(estimate("drowssap").patterns[0].reversed);
+    }
+
+    #[test]
+    fn test_detects_patterns() {
+        assert_eq!(kinds("sdfghj"), vec![PatternKind::Spatial]);
+        assert_eq!(kinds("lmnopq"), vec![PatternKind::Sequence]);
+        assert_eq!(kinds("zzzzzzzz"), vec![PatternKind::Repeat]);
+        assert_eq!(kinds("24/12/1987"), vec![PatternKind::Date]);
+        assert!(kinds("Tormenta1987").contains(&PatternKind::Date));
+    }
+
+    #[test]
+    fn test_random_passwords_are_strong() {
+        let strength = estimate("Xk9#mP2$vL7!qR");
+        assert_eq!(strength.rating, 4);
+        assert!(strength.score >= 70);
+        assert!(strength.suggestions.is_empty());
+        assert!(strength.crack_times.offline_slow.seconds > strength.crack_times.offline_fast.seconds);
+    }
+
+    #[test]
+    fn test_score_is_monotonic() {
+        let scores: Vec<u8> = [0.0, 3.0, 6.0, 8.0, 10.0, 13.0, 20.0].iter().map(|log10| score(*log10)).collect();
+        assert!(scores.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!((score(6.0), score(10.0), score(20.0)), (40, 70, 100));
+    }
+}
@@ -0,0 +1,152 @@
+//! Buzón de sincronización en una carpeta WebDAV (Nextcloud, ownCloud…)
+//!
+//! Cada dispositivo deja en `<carpeta>/<destino>/<origen>.sealed` el lote para
+//! cada uno de sus dispositivos de confianza y recoge los que le dejaron en
+//! `<carpeta>/<este dispositivo>/`. Los lotes van sellados con la clave del
+//! emparejamiento, así que el servidor solo ve bytes encriptados y quién los
+//! deja para quién.
+
+use crate::models::DeviceId;
+use crate::sync::smart_sync::SyncMailbox;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::header::{ETAG, IF_MATCH};
+use reqwest::{Method, StatusCode, Url};
+use std::time::Duration;
+
+/// Buzón en una carpeta WebDAV con autenticación básica
+pub struct WebDavMailbox {
+    client: reqwest::Client,
+    folder: Url,
+    username: String,
+    password: String,
+}
+
+impl WebDavMailbox {
+    /// Falla si `folder` no es una URL http(s)
+    pub fn new(folder: &str, username: &str, password: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("Alohopass/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            client,
+            folder: parse_folder(folder)?,
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    fn request(&self, method: Method, url: Url) -> reqwest::RequestBuilder {
+        self.client.request(method, url).basic_auth(&self.username, Some(&self.password))
+    }
+
+    /// Carpeta con los lotes para `to`
+    fn inbox_url(&self, to: DeviceId) -> Result<Url> {
+        Ok(self.folder.join(&format!("{}/", to))?)
+    }
+
+    /// Lote que `from` deja para `to`
+    fn batch_url(&self, from: DeviceId, to: DeviceId) -> Result<Url> {
+        Ok(self.inbox_url(to)?.join(&format!("{}.sealed", from))?)
+    }
+
+    /// Crea la carpeta si no existe. WebDAV no crea las intermedias, así que
+    /// primero va la del buzón.
+    async fn create_folder(&self, url: Url) -> Result<()> {
+        let response = self.request(Method::from_bytes(b"MKCOL")?, url.clone()).send().await?;
+        match response.status() {
+            // 405: la carpeta ya existe
+            status if status.is_success() || status == StatusCode::METHOD_NOT_ALLOWED => Ok(()),
+            status => Err(anyhow!("El servidor WebDAV respondió {} al crear {}", status, url)),
+        }
+    }
+
+    async fn upload(&self, url: Url, sealed: Vec<u8>) -> Result<StatusCode> {
+        Ok(self.request(Method::PUT, url).body(sealed).send().await?.status())
+    }
+}
+
+#[async_trait]
+impl SyncMailbox for WebDavMailbox {
+    async fn put(&self, from: DeviceId, to: DeviceId, sealed: Vec<u8>) -> Result<()> {
+        let url = self.batch_url(from, to)?;
+        let mut status = self.upload(url.clone(), sealed.clone()).await?;
+        // 409: falta la carpeta del destino
+        if status == StatusCode::CONFLICT {
+            self.create_folder(self.folder.clone()).await?;
+            self.create_folder(self.inbox_url(to)?).await?;
+            status = self.upload(url, sealed).await?;
+        }
+        if !status.is_success() {
+            return Err(anyhow!("El servidor WebDAV respondió {} al dejar el lote para {}", status, to));
+        }
+        Ok(())
+    }
+
+    async fn take(&self, from: DeviceId, to: DeviceId) -> Result<Option<Vec<u8>>> {
+        let url = self.batch_url(from, to)?;
+        let response = self.request(Method::GET, url.clone()).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("El servidor WebDAV respondió {} al recoger el lote de {}", response.status(), from));
+        }
+        let etag = response.headers().get(ETAG).cloned();
+        let sealed = response.bytes().await?.to_vec();
+
+        // Solo se borra el lote recogido: si `from` ya dejó otro, el servidor
+        // responde 412 y el nuevo se queda para la próxima vez
+        let mut delete = self.request(Method::DELETE, url);
+        if let Some(etag) = etag {
+            delete = delete.header(IF_MATCH, etag);
+        }
+        let status = delete.send().await?.status();
+        if !status.is_success() && status != StatusCode::NOT_FOUND && status != StatusCode::PRECONDITION_FAILED {
+            return Err(anyhow!("El servidor WebDAV respondió {} al sacar el lote de {}", status, from));
+        }
+        Ok(Some(sealed))
+    }
+
+    async fn is_waiting(&self, from: DeviceId, to: DeviceId) -> Result<bool> {
+        let response = self.request(Method::HEAD, self.batch_url(from, to)?).send().await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(anyhow!("El servidor WebDAV respondió {} al buscar el lote para {}", status, to)),
+        }
+    }
+}
+
+/// URL de la carpeta del buzón, terminada en `/` para que las rutas de los
+/// lotes queden dentro
+pub fn parse_folder(folder: &str) -> Result<Url> {
+    let folder = folder.trim();
+    let mut url = Url::parse(folder).map_err(|e| anyhow!("URL WebDAV inválida: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("La URL WebDAV tiene que ser http o https"));
+    }
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_live_in_the_recipient_folder() {
+        let mailbox = WebDavMailbox::new("https://nube.example.com/remote.php/dav/files/ana/Alohopass", "ana", "secreto").unwrap();
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        assert_eq!(
+            mailbox.batch_url(laptop, phone).unwrap().as_str(),
+            format!("https://nube.example.com/remote.php/dav/files/ana/Alohopass/{}/{}.sealed", phone, laptop),
+        );
+        assert_eq!(parse_folder("https://nube.example.com/dav/").unwrap().as_str(), "https://nube.example.com/dav/");
+        assert!(parse_folder("ftp://nube.example.com/dav").is_err());
+        assert!(parse_folder("nube.example.com/dav").is_err());
+    }
+}
@@ -1,76 +1,532 @@
-use crate::sync::{SyncManager, SyncConfig, SyncStatus, SyncStats, DeviceInfo};
+use crate::sync::{
+    SyncManager, SyncConfig, SyncStatus, SyncStats, SyncResult, DeviceIdentity, DeviceInfo, DeviceProfile, DeviceType, DeviceLimitReached, Incompatibility, IncomingEntry, PairingStatus,
+    DriveMailbox, DropboxMailbox, OAuthProvider, RelayMailbox, RelayShares, RelaySignaling, S3Mailbox, ShareLink, SharedEntry,
+    WebDavMailbox,
+};
+use crate::sync::p2p_connection::{P2PConnectionStats, TurnServer};
+use crate::sync::identity::PublicIdentity;
+use crate::sync::pairing::{PairedKey, PairingInvite, PAIRING_TIMEOUT};
+use crate::sync::share::{revocation_secret, MAX_SHARE_EXPIRY, MAX_SHARE_VIEWS};
+use crate::crypto::CryptoManager;
+use crate::database::{self, ElementKind};
+use crate::models::{
+    changed_fields, diverging_fields, merge_entry_fields, Category, CategoryId, CloudBackend, CategoryRequest, DeviceId, DeviceSyncStats, EncryptionLevel, EntryField, EntryId, FieldStamps,
+    PasswordEntry, PasswordEntryDto, ShareId, SharedLink, SyncBootstrap, SyncHistoryPage, SyncHistoryRecord, SyncHistoryRequest, SyncMethod,
+    SyncPreferences, Tombstone,
+    TrustedDevice, VersionVector,
+};
+use crate::sync::smart_sync::{
+    discard_resurrections, AppliedChanges, ChangeJournal, ChangeType, ConflictResolution, ConflictResolutionStrategy, DataChange,
+    SyncConflict, SyncMailbox, SyncStore,
+};
+use crate::vault::EntryCipher;
 use crate::AppState;
+use crate::error::{AppError, AppResult};
+use crate::i18n::Message;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
-use tauri::State;
+use qrcode::QrCode;
+use qrcode::render::svg;
+use async_trait::async_trait;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SyncConfigUpdate {
     pub auto_sync: bool,
     pub sync_interval: u64,
     pub discovery_enabled: bool,
     pub allow_incoming_connections: bool,
+    #[serde(default)]
+    pub ask_on_conflict: bool,
+    #[serde(default)]
+    pub encryption_level: EncryptionLevel,
+    #[serde(default)]
+    pub wifi_only: bool,
+    #[serde(default)]
+    pub allowed_networks: Vec<String>,
+    /// Sin él se conserva el límite guardado
+    #[serde(default)]
+    pub max_devices: Option<u32>,
+    #[serde(default)]
+    pub method: SyncMethod,
+    #[serde(default)]
+    pub webdav_url: String,
+    #[serde(default)]
+    pub webdav_username: String,
+    /// Sin ella se conserva la guardada; vacía la borra
+    #[serde(default)]
+    pub webdav_password: Option<String>,
+    #[serde(default)]
+    pub cloud_backend: CloudBackend,
+    #[serde(default)]
+    pub s3_endpoint: String,
+    #[serde(default)]
+    pub s3_region: String,
+    #[serde(default)]
+    pub s3_bucket: String,
+    #[serde(default)]
+    pub s3_prefix: String,
+    #[serde(default)]
+    pub s3_access_key_id: String,
+    /// Sin ella se conserva la guardada; vacía la borra
+    #[serde(default)]
+    pub s3_secret_access_key: Option<String>,
+    #[serde(default)]
+    pub relay_url: String,
+    /// Sin él se conserva el guardado; vacío lo borra
+    #[serde(default)]
+    pub relay_token: Option<String>,
+    #[serde(default)]
+    pub turn_url: String,
+    #[serde(default)]
+    pub turn_username: String,
+    /// Sin ella se conserva la guardada; vacía la borra
+    #[serde(default)]
+    pub turn_credential: Option<String>,
+}
+
+/// Cuentas en la nube conectadas con OAuth
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudAccounts {
+    pub google_drive: bool,
+    pub dropbox: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DeviceTrustRequest {
-    pub device_id: String,
+    pub device_id: DeviceId,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingRequest {
+    pub device_id: DeviceId,
+}
+
+/// Dirección de un dispositivo que no aparece por mDNS
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManualDeviceRequest {
+    /// IPv4 o IPv6; una IPv6 de enlace local lleva la interfaz (`fe80::1%3`)
+    pub ip: String,
+    /// Puerto de señalización que muestra el otro dispositivo
+    pub port: u16,
+}
+
+/// Este dispositivo como lo ven los demás
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalDevice {
+    pub device_id: DeviceId,
+    /// Lo que eligió el usuario; lo que falta se toma del sistema
+    pub profile: DeviceProfile,
+    /// Nombre con el que se anuncia
+    pub name: String,
+    /// Tipo con el que se anuncia
+    pub device_type: DeviceType,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingConfirmation {
+    pub device_id: DeviceId,
+    /// El usuario vio el mismo código en los dos dispositivos
+    pub accepted: bool,
+}
+
+/// Código QR para que otro dispositivo se empareje escaneándolo
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingQr {
+    /// URI `alohopass://pair?…` que lleva el código
+    pub payload: String,
+    /// El código dibujado en SVG
+    pub svg: String,
+    /// Segundos que sigue valiendo
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingQrScan {
+    pub payload: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DeviceRemoveRequest {
-    pub device_id: String,
+    pub device_id: DeviceId,
+}
+
+/// Categorías que no se mandan a un dispositivo de confianza, con sus
+/// subcategorías
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncScope {
+    pub device_id: DeviceId,
+    pub excluded_categories: Vec<CategoryId>,
+}
+
+/// Conflicto pendiente con las dos versiones de la entrada; `None` donde esa
+/// versión la borró
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflictView {
+    pub id: String,
+    pub entry_id: EntryId,
+    pub detected_at: String,
+    pub remote_device: DeviceId,
+    pub local: Option<PasswordEntryDto>,
+    pub remote: Option<PasswordEntryDto>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictResolutionRequest {
+    pub conflict_id: String,
+    pub resolution: ConflictResolution,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendEntryRequest {
+    pub entry_id: EntryId,
+    pub device_id: DeviceId,
+}
+
+/// Respuesta del usuario a una entrada que le mandó otro dispositivo
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncomingEntryResponse {
+    pub id: uuid::Uuid,
+    pub accepted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareEntryRequest {
+    pub entry_id: EntryId,
+    pub expires_in_minutes: u64,
+    pub max_views: u32,
+}
+
+/// Enlace recién compartido. Es la única vez que se ve: la clave que abre la
+/// entrada no se guarda.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedShareLink {
+    pub link: String,
+    pub share: SharedLink,
+}
+
+/// Fila de `settings` con la identidad de este dispositivo, encriptada con la
+/// clave maestra
+const DEVICE_IDENTITY_SETTING: &str = "device_identity";
+
+/// Fila de `settings` con el nombre y el tipo que el usuario le dio a este
+/// dispositivo, en JSON
+const DEVICE_PROFILE_SETTING: &str = "device_profile";
+
+/// Fila de `settings` con la contraseña del buzón WebDAV, encriptada con la
+/// clave maestra
+const WEBDAV_PASSWORD_SETTING: &str = "webdav_password";
+
+/// Fila de `settings` con la clave secreta del bucket S3, encriptada con la
+/// clave maestra
+const S3_SECRET_SETTING: &str = "s3_secret_access_key";
+
+/// Fila de `settings` con el token del relay propio, encriptado con la clave
+/// maestra
+const RELAY_TOKEN_SETTING: &str = "relay_token";
+
+/// Fila de `settings` con la credencial del servidor TURN, encriptada con la
+/// clave maestra
+const TURN_CREDENTIAL_SETTING: &str = "turn_credential";
+
+/// Fila de `settings` con el token de renovación de la cuenta del proveedor,
+/// encriptado con la clave maestra
+fn cloud_token_setting(provider: OAuthProvider) -> &'static str {
+    match provider {
+        OAuthProvider::GoogleDrive => "google_drive_token",
+        OAuthProvider::Dropbox => "dropbox_token",
+    }
+}
+
+/// Gestor de sincronización; falla si todavía no se creó
+fn sync_manager(state: &AppState) -> AppResult<&SyncManager> {
+    state.sync_manager.get().ok_or_else(|| AppError::sync("errors.syncNotInitialized"))
 }
 
 /// Obtener la configuración actual de sincronización
 #[tauri::command]
 pub async fn get_sync_config(
     state: State<'_, AppState>
-) -> Result<SyncConfig, String> {
-    // Por ahora retornamos configuración por defecto
-    // TODO: Implementar cuando el SyncManager esté completamente funcional
-    Ok(SyncConfig::default())
+) -> AppResult<SyncConfig> {
+    Ok(sync_manager(&state)?.get_config().await)
 }
 
 /// Obtener el estado actual de sincronización
 #[tauri::command]
 pub async fn get_sync_status(
     state: State<'_, AppState>
-) -> Result<SyncStatus, String> {
-    // Por ahora retornamos estado por defecto
-    // TODO: Implementar cuando el SyncManager esté completamente funcional
-    Ok(SyncStatus::default())
+) -> AppResult<SyncStatus> {
+    let manager = sync_manager(&state)?;
+    let mut status = manager.get_status().await;
+    status.is_enabled = manager.is_running().await;
+    status.connected_devices = manager.get_connected_devices().await;
+    status.last_sync_time = status.last_sync.map(|last_sync| last_sync.to_rfc3339());
+    Ok(status)
+}
+
+/// Carga de la base los dispositivos de confianza y se los pasa al gestor, que
+/// solo sincroniza con ellos
+async fn load_trusted_devices(state: &AppState) -> AppResult<Vec<TrustedDevice>> {
+    let trusted = state.with_db(|db_manager| {
+        database::list_trusted_devices(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.trustedDevices", e))
+    }).await?;
+    sync_manager(state)?
+        .set_trusted_devices(trusted.iter().map(|device| {
+            let identity = device.key_fingerprint.as_deref().zip(device.static_key.as_deref())
+                .and_then(|(fingerprint, static_key)| PublicIdentity::from_hex(fingerprint, static_key));
+            (device.device_id, identity)
+        }))
+        .await;
+    Ok(trusted)
+}
+
+/// Carga la identidad de este dispositivo y se la pasa al gestor. La primera
+/// vez la crea y la guarda encriptada con la clave maestra.
+async fn load_identity(state: &AppState) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    let stored = state.with_db(|db_manager| {
+        database::load_setting_value(db_manager.get_connection(), DEVICE_IDENTITY_SETTING)
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+
+    let identity = match stored {
+        Some(stored) => {
+            let pem = cipher.decrypt(&stored, "fields.deviceIdentity")?;
+            DeviceIdentity::from_pem(&pem)
+                .map_err(|e| AppError::crypto(Message::new("errors.parseField").with_key("field", "fields.deviceIdentity"), e))?
+        }
+        None => {
+            let identity = DeviceIdentity::generate()
+                .map_err(|e| AppError::internal_with("errors.deviceIdentity", e))?;
+            let encrypted = cipher.encrypt(identity.to_pem().as_bytes(), "fields.deviceIdentity")?;
+            state.with_db(move |db_manager| {
+                database::save_setting_value(db_manager.get_connection(), DEVICE_IDENTITY_SETTING, Some(&encrypted))
+                    .map_err(|e| AppError::database("errors.saveSettings", e))
+            }).await?;
+            log::info!("Identidad de este dispositivo creada: {}", hex::encode(identity.fingerprint()));
+            identity
+        }
+    };
+    sync_manager(state)?.set_identity(Some(identity)).await;
+    load_device_profile(state).await
+}
+
+/// Nombre y tipo que el usuario le dio a este dispositivo; vacío si no los
+/// cambió
+async fn stored_device_profile(state: &AppState) -> AppResult<DeviceProfile> {
+    let stored = state.with_db(|db_manager| {
+        database::load_setting_value(db_manager.get_connection(), DEVICE_PROFILE_SETTING)
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    Ok(stored
+        .and_then(|stored| serde_json::from_str(&stored)
+            .map_err(|e| log::warn!("Perfil de este dispositivo ilegible: {}", e))
+            .ok())
+        .unwrap_or_default())
+}
+
+/// Carga el nombre y el tipo de este dispositivo y se los pasa al gestor, que
+/// los anuncia por mDNS y los manda al emparejarse
+async fn load_device_profile(state: &AppState) -> AppResult<()> {
+    let profile = stored_device_profile(state).await?;
+    sync_manager(state)?.set_device_profile(profile).await
+        .map_err(|e| AppError::sync_with("errors.syncDiscovery", e))
+}
+
+/// Arma el buzón en la nube que esté configurado, con su contraseña, clave
+/// secreta o token, y se lo pasa al gestor. Sin carpeta WebDAV, sin bucket,
+/// sin cuenta conectada o sin relay no hay buzón.
+async fn load_cloud_mailbox(state: &AppState) -> AppResult<()> {
+    let preferences = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .sync
+        .clone();
+    let mut relay_signaling = None;
+    let mailbox: Option<Arc<dyn SyncMailbox>> = match preferences.cloud_backend {
+        CloudBackend::WebDav if !preferences.webdav_url.trim().is_empty() => {
+            let password = load_cloud_secret(state, WEBDAV_PASSWORD_SETTING, "fields.webdavPassword").await?;
+            let mailbox = WebDavMailbox::new(&preferences.webdav_url, &preferences.webdav_username, &password)
+                .map_err(|e| AppError::sync_with("errors.cloudMailbox", e))?;
+            Some(Arc::new(mailbox))
+        }
+        CloudBackend::S3 if !preferences.s3_endpoint.trim().is_empty() && !preferences.s3_bucket.trim().is_empty() => {
+            let secret = load_cloud_secret(state, S3_SECRET_SETTING, "fields.s3SecretKey").await?;
+            let mailbox = S3Mailbox::new(
+                &preferences.s3_endpoint,
+                &preferences.s3_region,
+                &preferences.s3_bucket,
+                &preferences.s3_prefix,
+                &preferences.s3_access_key_id,
+                &secret,
+            ).map_err(|e| AppError::sync_with("errors.cloudMailbox", e))?;
+            Some(Arc::new(mailbox))
+        }
+        CloudBackend::GoogleDrive => {
+            let token = load_cloud_secret(state, cloud_token_setting(OAuthProvider::GoogleDrive), "fields.cloudToken").await?;
+            if token.is_empty() {
+                None
+            } else {
+                let mailbox = DriveMailbox::new(token).map_err(|e| AppError::sync_with("errors.cloudMailbox", e))?;
+                Some(Arc::new(mailbox))
+            }
+        }
+        CloudBackend::Dropbox => {
+            let token = load_cloud_secret(state, cloud_token_setting(OAuthProvider::Dropbox), "fields.cloudToken").await?;
+            if token.is_empty() {
+                None
+            } else {
+                let mailbox = DropboxMailbox::new(token).map_err(|e| AppError::sync_with("errors.cloudMailbox", e))?;
+                Some(Arc::new(mailbox))
+            }
+        }
+        CloudBackend::Relay if !preferences.relay_url.trim().is_empty() => {
+            let token = load_cloud_secret(state, RELAY_TOKEN_SETTING, "fields.relayToken").await?;
+            let mailbox = RelayMailbox::new(&preferences.relay_url, &token)
+                .map_err(|e| AppError::sync_with("errors.cloudMailbox", e))?;
+            // El mismo relay pasa las ofertas WebRTC de los que no se ven en
+            // la red local
+            let signaling = RelaySignaling::new(&preferences.relay_url, &token)
+                .map_err(|e| AppError::sync_with("errors.cloudMailbox", e))?;
+            relay_signaling = Some(Arc::new(signaling));
+            Some(Arc::new(mailbox))
+        }
+        _ => None,
+    };
+    let manager = sync_manager(state)?;
+    manager.set_mailbox(mailbox).await;
+    manager.set_relay_signaling(relay_signaling).await;
+    Ok(())
+}
+
+/// Arma el servidor TURN configurado, con su credencial, y se lo pasa al
+/// gestor para las conexiones que se abran de acá en más
+async fn load_turn_server(state: &AppState) -> AppResult<()> {
+    let preferences = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .sync
+        .clone();
+    let turn = if preferences.turn_url.trim().is_empty() {
+        None
+    } else {
+        Some(TurnServer {
+            urls: vec![preferences.turn_url.trim().to_string()],
+            username: preferences.turn_username.clone(),
+            credential: load_cloud_secret(state, TURN_CREDENTIAL_SETTING, "fields.turnCredential").await?,
+        })
+    };
+    sync_manager(state)?.set_turn_server(turn).await;
+    Ok(())
 }
 
-/// Obtener dispositivos sincronizados
+/// Contraseña o clave del buzón en la nube guardada en `setting`; vacía si
+/// no hay ninguna
+async fn load_cloud_secret(state: &AppState, setting: &'static str, field: &'static str) -> AppResult<String> {
+    let cipher = state.entry_cipher()?;
+    let stored = state.with_db(move |db_manager| {
+        database::load_setting_value(db_manager.get_connection(), setting)
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    match stored {
+        Some(stored) => cipher.decrypt(&stored, field),
+        None => Ok(String::new()),
+    }
+}
+
+/// Guarda encriptada una contraseña o clave del buzón en la nube; vacía la
+/// borra
+async fn save_cloud_secret(state: &AppState, setting: &'static str, field: &'static str, secret: &str) -> AppResult<()> {
+    let encrypted = if secret.is_empty() {
+        None
+    } else {
+        Some(state.entry_cipher()?.encrypt(secret.as_bytes(), field)?)
+    };
+    state.with_db(move |db_manager| {
+        database::save_setting_value(db_manager.get_connection(), setting, encrypted.as_deref())
+            .map_err(|e| AppError::database("errors.saveSettings", e))
+    }).await
+}
+
+/// Obtener los dispositivos conectados y descubiertos, marcando los de
+/// confianza. Los de confianza que no están a la vista aparecen desconectados.
 #[tauri::command]
 pub async fn get_sync_devices(
     state: State<'_, AppState>
-) -> Result<Vec<DeviceInfo>, String> {
-    // Por ahora retornamos lista vacía
-    // TODO: Implementar cuando el SyncManager esté completamente funcional
-    Ok(Vec::new())
+) -> AppResult<Vec<DeviceInfo>> {
+    let manager = sync_manager(&state)?;
+    let mut devices = manager.get_devices().await;
+    let trusted = load_trusted_devices(&state).await?;
+    let trusted_ids: HashSet<DeviceId> = trusted.iter().map(|device| device.device_id).collect();
+    for device in &mut devices {
+        device.is_trusted = trusted_ids.contains(&device.id);
+    }
+    
+    let visible: HashSet<DeviceId> = devices.iter().map(|device| device.id).collect();
+    for device in trusted.into_iter().filter(|device| !visible.contains(&device.device_id)) {
+        let name = device.name.unwrap_or_else(|| device.device_id.to_string());
+        let mut offline = DeviceInfo::offline(device.device_id, name);
+        manager.describe_compatibility(&mut offline).await;
+        devices.push(offline);
+    }
+    Ok(devices)
 }
 
 /// Obtener estadísticas de sincronización
 #[tauri::command]
 pub async fn get_sync_stats(
     state: State<'_, AppState>
-) -> Result<SyncStats, String> {
-    // Por ahora retornamos estadísticas por defecto
-    // TODO: Implementar cuando el SyncManager esté completamente funcional
-    Ok(SyncStats::default())
+) -> AppResult<SyncStats> {
+    let manager = sync_manager(&state)?;
+    let mut stats = manager.get_stats().await;
+    stats.devices_count = manager.get_devices().await.len() as u32;
+    stats.total_passwords = state.with_db(|db_manager| {
+        database::PasswordRepository::new(db_manager.get_connection()).count()
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await? as u64;
+    Ok(stats)
+}
+
+/// Diagnóstico de la conexión directa con un dispositivo: estado, par de
+/// candidatos ICE elegido y si pasa por TURN. `None` si no hay una abierta.
+#[tauri::command]
+pub async fn get_connection_diagnostics(
+    state: State<'_, AppState>,
+    device_id: DeviceId
+) -> AppResult<Option<P2PConnectionStats>> {
+    Ok(sync_manager(&state)?.get_connection_diagnostics(device_id).await)
 }
 
 /// Iniciar sincronización
 #[tauri::command]
 pub async fn start_sync(
     state: State<'_, AppState>
-) -> Result<(), String> {
-    // Por ahora solo simulamos éxito
-    // TODO: Implementar cuando el SyncManager esté completamente funcional
-    log::info!("Sincronización iniciada (simulada)");
+) -> AppResult<()> {
+    load_identity(&state).await?;
+    load_trusted_devices(&state).await?;
+    load_cloud_mailbox(&state).await?;
+    load_turn_server(&state).await?;
+    sync_manager(&state)?.start().await
+        .map_err(|e| AppError::sync_with("errors.syncStart", e))?;
+    log::info!("Sincronización iniciada");
     Ok(())
 }
 
@@ -78,10 +534,81 @@ pub async fn start_sync(
 #[tauri::command]
 pub async fn stop_sync(
     state: State<'_, AppState>
-) -> Result<(), String> {
-    // Por ahora solo simulamos éxito
-    // TODO: Implementar cuando el SyncManager esté completamente funcional
-    log::info!("Sincronización detenida (simulada)");
+) -> AppResult<()> {
+    sync_manager(&state)?.stop().await
+        .map_err(|e| AppError::sync_with("errors.syncStop", e))?;
+    log::info!("Sincronización detenida");
+    Ok(())
+}
+
+/// Historial paginado de sincronizaciones, filtrable por dispositivo y por
+/// las que fallaron
+#[tauri::command]
+pub async fn get_sync_history(
+    state: State<'_, AppState>,
+    request: Option<SyncHistoryRequest>,
+) -> AppResult<SyncHistoryPage> {
+    let request = request.unwrap_or_default();
+    state.unlocked_crypto()?;
+    
+    let (offset, limit) = (request.offset.unwrap_or(0), request.limit);
+    let (records, total) = state.with_db(move |db_manager| {
+        database::list_sync_history(db_manager.get_connection(), &request)
+            .map_err(|e| AppError::database("errors.syncHistory", e))
+    }).await?;
+    
+    Ok(SyncHistoryPage { records, total, offset, limit })
+}
+
+/// Totales de las sincronizaciones con un dispositivo, desde la primera
+#[tauri::command]
+pub async fn get_device_sync_stats(
+    state: State<'_, AppState>,
+    device_id: DeviceId
+) -> AppResult<DeviceSyncStats> {
+    state.unlocked_crypto()?;
+    state.with_db(move |db_manager| {
+        database::device_sync_stats(db_manager.get_connection(), device_id)
+            .map_err(|e| AppError::database("errors.syncHistory", e))
+    }).await
+}
+
+/// Pone en pausa la sincronización sin detener el descubrimiento. La pausa
+/// se guarda y sigue tras reiniciar.
+#[tauri::command]
+pub async fn pause_sync(
+    state: State<'_, AppState>
+) -> AppResult<()> {
+    set_sync_paused(&state, true).await?;
+    log::info!("Sincronización en pausa");
+    Ok(())
+}
+
+/// Reanuda la sincronización en pausa
+#[tauri::command]
+pub async fn resume_sync(
+    state: State<'_, AppState>
+) -> AppResult<()> {
+    set_sync_paused(&state, false).await?;
+    log::info!("Sincronización reanudada");
+    Ok(())
+}
+
+/// Guarda la pausa junto con la configuración y la aplica al gestor
+async fn set_sync_paused(state: &AppState, paused: bool) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    let mut settings = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .clone();
+    settings.sync.paused = paused;
+    let settings = state.with_db(move |db_manager| {
+        database::save_settings(db_manager.get_connection(), &settings)
+            .map_err(|e| AppError::database("errors.saveSettings", e))?;
+        Ok(settings)
+    }).await?;
+    *state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))? = settings;
+    sync_manager(state)?.set_paused(paused).await;
     Ok(())
 }
 
@@ -89,66 +616,427 @@ pub async fn stop_sync(
 #[tauri::command]
 pub async fn start_device_discovery(
     state: State<'_, AppState>
-) -> Result<(), String> {
-    let manager = state.sync_manager.lock().map_err(|e| e.to_string())?;
-    
-    if let Some(_manager) = manager.as_ref() {
-        // Por ahora solo simulamos éxito
-        log::info!("Descubrimiento de dispositivos iniciado");
-        Ok(())
-    } else {
-        Err("Sync manager not initialized".to_string())
-    }
+) -> AppResult<()> {
+    load_device_profile(&state).await?;
+    sync_manager(&state)?.start_discovery().await
+        .map_err(|e| AppError::sync_with("errors.syncDiscovery", e))?;
+    log::info!("Descubrimiento de dispositivos iniciado");
+    Ok(())
 }
 
-/// Sincronizar ahora
+/// Sincronizar ahora con todos los dispositivos conectados
 #[tauri::command]
 pub async fn sync_now(
     state: State<'_, AppState>
-) -> Result<(), String> {
-    let manager = state.sync_manager.lock().map_err(|e| e.to_string())?;
+) -> AppResult<Vec<SyncResult>> {
+    log::info!("Sincronización manual iniciada");
+    load_trusted_devices(&state).await?;
+    let results = sync_manager(&state)?.sync_all_devices().await
+        .map_err(|e| AppError::sync_with("errors.syncFailed", e))?;
+    let failed = results.iter().filter(|result| !result.success).count();
+    log::info!("Sincronización manual terminada: {} dispositivos, {} con errores", results.len(), failed);
+    Ok(results)
+}
+
+/// Guarda la configuración de sincronización junto con la de la aplicación y
+/// la aplica al gestor en marcha
+#[tauri::command]
+pub async fn update_sync_config(
+    state: State<'_, AppState>,
+    mut config: SyncConfigUpdate
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    // Las contraseñas no pasan por el registro
+    let webdav_password = config.webdav_password.take();
+    let s3_secret = config.s3_secret_access_key.take();
+    let relay_token = config.relay_token.take();
+    let turn_credential = config.turn_credential.take();
+    log::info!("Actualizando configuración de sincronización: {:?}", config);
+    if config.sync_interval == 0 {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.sync_interval")));
+    }
+    let webdav_url = config.webdav_url.trim().to_string();
+    if !webdav_url.is_empty() && crate::sync::cloud::parse_folder(&webdav_url).is_err() {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.webdav_url")));
+    }
+    let s3_endpoint = config.s3_endpoint.trim().to_string();
+    if !s3_endpoint.is_empty() && crate::sync::s3::parse_endpoint(&s3_endpoint).is_err() {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.s3_endpoint")));
+    }
+    let s3_bucket = config.s3_bucket.trim().to_string();
+    if s3_bucket.contains('/') {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.s3_bucket")));
+    }
+    let relay_url = config.relay_url.trim().to_string();
+    if !relay_url.is_empty() && crate::sync::relay::parse_url(&relay_url).is_err() {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.relay_url")));
+    }
+    let turn_url = config.turn_url.trim().to_string();
+    if !turn_url.is_empty() && !turn_url.starts_with("turn:") && !turn_url.starts_with("turns:") {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.turn_url")));
+    }
     
-    if let Some(_manager) = manager.as_ref() {
-        // Por ahora solo simulamos éxito
-        log::info!("Sincronización manual iniciada");
-        Ok(())
-    } else {
-        Err("Sync manager not initialized".to_string())
+    let mut settings = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .clone();
+    // Bajar el límite por debajo de los dispositivos que ya hay obliga a
+    // quitar alguno antes
+    let max_devices = config.max_devices.unwrap_or(settings.sync.max_devices);
+    if max_devices == 0 {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.max_devices")));
     }
+    let trusted = load_trusted_devices(&state).await?.len();
+    if (max_devices as usize) < trusted {
+        return Err(AppError::validation(Message::new("errors.deviceLimitBelowTrusted").with("count", trusted)));
+    }
+    settings.sync = SyncPreferences {
+        auto_sync: config.auto_sync,
+        sync_interval: config.sync_interval,
+        discovery_enabled: config.discovery_enabled,
+        allow_incoming_connections: config.allow_incoming_connections,
+        ask_on_conflict: config.ask_on_conflict,
+        encryption_level: config.encryption_level,
+        wifi_only: config.wifi_only,
+        allowed_networks: config.allowed_networks.iter()
+            .map(|network| network.trim().to_string())
+            .filter(|network| !network.is_empty())
+            .collect(),
+        max_devices,
+        paused: settings.sync.paused,
+        method: config.method,
+        webdav_url,
+        webdav_username: config.webdav_username.trim().to_string(),
+        cloud_backend: config.cloud_backend,
+        s3_endpoint,
+        s3_region: config.s3_region.trim().to_string(),
+        s3_bucket,
+        s3_prefix: config.s3_prefix.trim().trim_matches('/').to_string(),
+        s3_access_key_id: config.s3_access_key_id.trim().to_string(),
+        relay_url,
+        turn_url,
+        turn_username: config.turn_username.trim().to_string(),
+    };
+    let settings = state.with_db(move |db_manager| {
+        database::save_settings(db_manager.get_connection(), &settings)
+            .map_err(|e| AppError::database("errors.saveSettings", e))?;
+        Ok(settings)
+    }).await?;
+    let sync_config = SyncConfig::from(&settings.sync);
+    *state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))? = settings;
+    if let Some(password) = webdav_password {
+        save_cloud_secret(&state, WEBDAV_PASSWORD_SETTING, "fields.webdavPassword", &password).await?;
+    }
+    if let Some(secret) = s3_secret {
+        save_cloud_secret(&state, S3_SECRET_SETTING, "fields.s3SecretKey", &secret).await?;
+    }
+    if let Some(token) = relay_token {
+        save_cloud_secret(&state, RELAY_TOKEN_SETTING, "fields.relayToken", &token).await?;
+    }
+    if let Some(credential) = turn_credential {
+        save_cloud_secret(&state, TURN_CREDENTIAL_SETTING, "fields.turnCredential", &credential).await?;
+    }
+    load_cloud_mailbox(&state).await?;
+    load_turn_server(&state).await?;
+    
+    sync_manager(&state)?.update_config(sync_config).await
+        .map_err(|e| AppError::sync_with("errors.syncConfig", e))
 }
 
-/// Actualizar configuración de sincronización
+/// Conecta la cuenta del proveedor: abre su página de autorización en el
+/// navegador, espera a que el usuario acepte y guarda el token de renovación
+/// en la bóveda. El buzón pasa a estar en esa cuenta.
 #[tauri::command]
-pub async fn update_sync_config(
+pub async fn connect_cloud_account(
+    app: AppHandle,
     state: State<'_, AppState>,
-    config: SyncConfigUpdate
-) -> Result<(), String> {
-    let mut manager = state.sync_manager.lock().map_err(|e| e.to_string())?;
+    provider: OAuthProvider
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    let pending = crate::sync::oauth::begin_authorization(provider).await
+        .map_err(|e| AppError::sync_with("errors.cloudAccount", e))?;
+    tauri::api::shell::open(&app.shell_scope(), &pending.url, None)
+        .map_err(|e| AppError::sync_with("errors.cloudAccount", e))?;
+    log::info!("Esperando la autorización de {:?} en el navegador", provider);
+    let token = pending.finish().await
+        .map_err(|e| AppError::sync_with("errors.cloudAccount", e))?;
+    save_cloud_secret(&state, cloud_token_setting(provider), "fields.cloudToken", &token).await?;
+
+    let mut settings = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .clone();
+    settings.sync.cloud_backend = match provider {
+        OAuthProvider::GoogleDrive => CloudBackend::GoogleDrive,
+        OAuthProvider::Dropbox => CloudBackend::Dropbox,
+    };
+    let settings = state.with_db(move |db_manager| {
+        database::save_settings(db_manager.get_connection(), &settings)
+            .map_err(|e| AppError::database("errors.saveSettings", e))?;
+        Ok(settings)
+    }).await?;
+    let sync_config = SyncConfig::from(&settings.sync);
+    *state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))? = settings;
+    load_cloud_mailbox(&state).await?;
+    sync_manager(&state)?.update_config(sync_config).await
+        .map_err(|e| AppError::sync_with("errors.syncConfig", e))?;
+    log::info!("Cuenta de {:?} conectada", provider);
+    Ok(())
+}
+
+/// Olvida el token de la cuenta del proveedor. Si el buzón estaba ahí, deja
+/// de haber buzón en la nube.
+#[tauri::command]
+pub async fn disconnect_cloud_account(
+    state: State<'_, AppState>,
+    provider: OAuthProvider
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    save_cloud_secret(&state, cloud_token_setting(provider), "fields.cloudToken", "").await?;
+    load_cloud_mailbox(&state).await?;
+    log::info!("Cuenta de {:?} desconectada", provider);
+    Ok(())
+}
+
+/// Qué cuentas en la nube tienen un token guardado
+#[tauri::command]
+pub async fn get_cloud_accounts(
+    state: State<'_, AppState>
+) -> AppResult<CloudAccounts> {
+    let connected = |provider| state.with_db(move |db_manager| {
+        database::load_setting_value(db_manager.get_connection(), cloud_token_setting(provider))
+            .map(|stored| stored.is_some())
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    });
+    Ok(CloudAccounts {
+        google_drive: connected(OAuthProvider::GoogleDrive).await?,
+        dropbox: connected(OAuthProvider::Dropbox).await?,
+    })
+}
+
+/// Id, nombre y tipo de este dispositivo
+#[tauri::command]
+pub async fn get_local_device(
+    state: State<'_, AppState>
+) -> AppResult<LocalDevice> {
+    let device_id = state.with_db(|db_manager| {
+        database::local_device_id(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
+    let profile = stored_device_profile(&state).await?;
+    let manager = sync_manager(&state)?;
+    manager.set_device_profile(profile.clone()).await
+        .map_err(|e| AppError::sync_with("errors.syncDiscovery", e))?;
+    let presented = manager.device_profile().await;
+    Ok(LocalDevice {
+        device_id,
+        profile,
+        name: presented.name.unwrap_or_default(),
+        device_type: presented.device_type.unwrap_or(DeviceType::Unknown),
+    })
+}
+
+/// Cambia el nombre y el tipo de este dispositivo. Lo que quede vacío vuelve
+/// a tomarse del sistema.
+#[tauri::command]
+pub async fn update_local_device(
+    state: State<'_, AppState>,
+    request: DeviceProfile
+) -> AppResult<LocalDevice> {
+    let profile = request.normalized()
+        .map_err(|_| AppError::validation(Message::new("errors.invalidSetting").with("setting", "sync.device_name")))?;
+    let stored = serde_json::to_string(&profile)
+        .map_err(|e| AppError::internal_with("errors.saveSettings", e))?;
+    state.with_db(move |db_manager| {
+        database::save_setting_value(db_manager.get_connection(), DEVICE_PROFILE_SETTING, Some(&stored))
+            .map_err(|e| AppError::database("errors.saveSettings", e))
+    }).await?;
+    log::info!("Este dispositivo ahora se presenta como {:?}", profile);
+    get_local_device(state).await
+}
+
+/// Empieza a emparejarse con un dispositivo
+#[tauri::command]
+pub async fn begin_pairing(
+    state: State<'_, AppState>,
+    request: PairingRequest
+) -> AppResult<PairingStatus> {
+    load_identity(&state).await?;
+    sync_manager(&state)?.begin_pairing(request.device_id).await
+        .map_err(|e| pairing_error("errors.pairingStart", e))
+}
+
+/// Agrega a mano un dispositivo por su dirección, para las redes que bloquean
+/// mDNS, y empieza a emparejarse con él
+#[tauri::command]
+pub async fn add_device_manually(
+    state: State<'_, AppState>,
+    request: ManualDeviceRequest
+) -> AppResult<PairingStatus> {
+    load_identity(&state).await?;
+    let manager = sync_manager(&state)?;
+    let device = manager.add_device_manually(&request.ip, request.port).await
+        .map_err(|e| AppError::sync_with("errors.manualDevice", e))?;
+    manager.begin_pairing(device.id).await
+        .map_err(|e| pairing_error("errors.pairingStart", e))
+}
+
+/// Emparejamientos en curso, con el código a comparar cuando ya lo hay
+#[tauri::command]
+pub async fn get_pairings(
+    state: State<'_, AppState>
+) -> AppResult<Vec<PairingStatus>> {
+    Ok(sync_manager(&state)?.get_pairings().await)
+}
+
+/// Cierra un emparejamiento. Si el usuario confirmó que los códigos coinciden,
+/// el dispositivo pasa a ser de confianza con la clave de sincronización
+/// acordada, encriptada con la clave maestra, y la huella de su certificado
+/// queda fijada.
+#[tauri::command]
+pub async fn confirm_pairing(
+    state: State<'_, AppState>,
+    request: PairingConfirmation
+) -> AppResult<()> {
+    let crypto = state.unlocked_crypto()?;
+    let device_id = request.device_id;
+    let Some(paired) = sync_manager(&state)?.confirm_pairing(device_id, request.accepted).await
+        .map_err(|e| pairing_error("errors.pairingConfirm", e))? else {
+        return Ok(());
+    };
+    save_paired_device(&state, &crypto, device_id, paired).await
+}
+
+/// Crea un código QR para que otro dispositivo se empareje escaneándolo. El
+/// código vale para un solo emparejamiento durante unos minutos.
+#[tauri::command]
+pub async fn create_pairing_qr(
+    state: State<'_, AppState>
+) -> AppResult<PairingQr> {
+    load_identity(&state).await?;
+    let manager = sync_manager(&state)?;
+    let device_id = state.with_db(|db_manager| {
+        database::local_device_id(db_manager.get_connection())
+            .map_err(|e| AppError::database("errors.dbQuery", e))
+    }).await?;
     
-    if let Some(_manager) = manager.as_mut() {
-        // Por ahora solo simulamos éxito
-        log::info!("Configuración actualizada: {:?}", config);
-        Ok(())
-    } else {
-        Err("Sync manager not initialized".to_string())
+    let payload = manager.create_pairing_invite(device_id).await
+        .map_err(|e| pairing_error("errors.pairingStart", e))?
+        .to_uri();
+    let svg = QrCode::new(payload.as_bytes())
+        .map_err(|e| AppError::internal_with("errors.pairingQr", e))?
+        .render::<svg::Color>()
+        .min_dimensions(240, 240)
+        .build();
+    Ok(PairingQr { payload, svg, expires_in: PAIRING_TIMEOUT.as_secs() })
+}
+
+/// Se empareja con el dispositivo de un código QR escaneado. El código ya
+/// autentica al otro dispositivo, así que pasa a ser de confianza sin
+/// comparar códigos.
+#[tauri::command]
+pub async fn scan_pairing_qr(
+    state: State<'_, AppState>,
+    request: PairingQrScan
+) -> AppResult<()> {
+    let crypto = state.unlocked_crypto()?;
+    let invite = PairingInvite::from_uri(&request.payload).map_err(|e| {
+        log::warn!("Código QR de emparejamiento inválido: {}", e);
+        AppError::validation("errors.invalidPairingQr")
+    })?;
+    load_identity(&state).await?;
+    let paired = sync_manager(&state)?.join_pairing(&invite).await
+        .map_err(|e| pairing_error("errors.pairingConfirm", e))?;
+    save_paired_device(&state, &crypto, invite.device_id, paired).await
+}
+
+/// Error de un emparejamiento con `key`, salvo el de haber llegado al límite
+/// de dispositivos, que tiene el suyo para que la interfaz ofrezca quitar uno
+fn pairing_error(key: &'static str, error: anyhow::Error) -> AppError {
+    if let Some(incompatibility) = error.downcast_ref::<Incompatibility>() {
+        return AppError::sync(incompatibility_message(incompatibility));
+    }
+    match error.downcast_ref::<DeviceLimitReached>() {
+        Some(limit) => AppError::sync(Message::new("errors.deviceLimitReached").with("max", limit.max_devices)),
+        None => AppError::sync_with(key, error),
+    }
+}
+
+/// Mensaje para el usuario de por qué no se puede sincronizar con un
+/// dispositivo
+fn incompatibility_message(incompatibility: &Incompatibility) -> Message {
+    match incompatibility {
+        Incompatibility::PeerTooOld { version, required } => Message::new("errors.peerTooOld")
+            .with("version", version)
+            .with("required", required),
+        Incompatibility::LocalTooOld { version, required } => Message::new("errors.localTooOld")
+            .with("version", version)
+            .with("required", required),
+        Incompatibility::InvalidVersion { version } => Message::new("errors.invalidAppVersion").with("version", version),
     }
 }
 
+/// Guarda un dispositivo recién emparejado con su clave de sincronización
+/// encriptada con la clave maestra
+async fn save_paired_device(
+    state: &AppState,
+    crypto: &CryptoManager,
+    device_id: DeviceId,
+    paired: PairedKey,
+) -> AppResult<()> {
+    let sync_key = crypto.encrypt_data(&paired.sync_key)
+        .map_err(|e| AppError::crypto(Message::new("errors.encryptField").with_key("field", "fields.syncKey"), e))?
+        .to_compact();
+    let device = TrustedDevice {
+        device_id,
+        name: match paired.peer_device.and_then(|device| device.name) {
+            Some(name) => Some(name),
+            None => device_name(state, device_id).await?,
+        },
+        key_fingerprint: Some(paired.peer_fingerprint),
+        static_key: Some(paired.peer_static_key),
+        trusted_at: chrono::Utc::now().to_rfc3339(),
+    };
+    state.with_db(move |db_manager| {
+        database::add_trusted_device(db_manager.get_connection(), &device, Some(&sync_key))
+            .map_err(|e| AppError::database("errors.trustedDevices", e))
+    }).await?;
+    load_trusted_devices(state).await?;
+    log::info!("Dispositivo emparejado y marcado como confiable: {}", device_id);
+    Ok(())
+}
+
+/// Nombre con el que se ve ahora un dispositivo, si está a la vista
+async fn device_name(state: &AppState, device_id: DeviceId) -> AppResult<Option<String>> {
+    Ok(sync_manager(state)?.get_devices().await.into_iter()
+        .find(|device| device.id == device_id)
+        .map(|device| device.name))
+}
+
 /// Confiar en un dispositivo
 #[tauri::command]
 pub async fn trust_device(
     state: State<'_, AppState>,
     request: DeviceTrustRequest
-) -> Result<(), String> {
-    let manager = state.sync_manager.lock().map_err(|e| e.to_string())?;
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    sync_manager(&state)?.check_device_limit(Some(request.device_id)).await
+        .map_err(|e| pairing_error("errors.trustedDevices", e))?;
     
-    if let Some(_manager) = manager.as_ref() {
-        // Por ahora solo simulamos éxito
-        log::info!("Dispositivo marcado como confiable: {}", request.device_id);
-        Ok(())
-    } else {
-        Err("Sync manager not initialized".to_string())
-    }
+    let device = TrustedDevice {
+        device_id: request.device_id,
+        name: device_name(&state, request.device_id).await?,
+        key_fingerprint: None,
+        static_key: None,
+        trusted_at: chrono::Utc::now().to_rfc3339(),
+    };
+    state.with_db(move |db_manager| {
+        database::add_trusted_device(db_manager.get_connection(), &device, None)
+            .map_err(|e| AppError::database("errors.trustedDevices", e))
+    }).await?;
+    load_trusted_devices(&state).await?;
+    log::info!("Dispositivo marcado como confiable: {}", request.device_id);
+    Ok(())
 }
 
 /// Remover un dispositivo
@@ -156,14 +1044,1100 @@ pub async fn trust_device(
 pub async fn remove_device(
     state: State<'_, AppState>,
     request: DeviceRemoveRequest
-) -> Result<(), String> {
-    let manager = state.sync_manager.lock().map_err(|e| e.to_string())?;
+) -> AppResult<()> {
+    sync_manager(&state)?;
+    state.unlocked_crypto()?;
     
-    if let Some(_manager) = manager.as_ref() {
-        // Por ahora solo simulamos éxito
-        log::info!("Dispositivo removido: {}", request.device_id);
-        Ok(())
+    // Las marcas que solo esperaban a este dispositivo ya se pueden purgar
+    let device_id = request.device_id;
+    state.with_db(move |db_manager| {
+        let conn = db_manager.get_connection();
+        database::remove_trusted_device(conn, device_id)
+            .map_err(|e| AppError::database("errors.trustedDevices", e))?;
+        database::purge_acknowledged_tombstones(conn)
+            .map_err(|e| AppError::database("errors.tombstones", e))
+    }).await?;
+    load_trusted_devices(&state).await?;
+    log::info!("Dispositivo removido: {}", request.device_id);
+    Ok(())
+}
+
+/// Categorías que no se mandan a un dispositivo
+#[tauri::command]
+pub async fn get_sync_scope(
+    state: State<'_, AppState>,
+    device_id: DeviceId,
+) -> AppResult<SyncScope> {
+    state.unlocked_crypto()?;
+    let excluded_categories = state.with_db(move |db_manager| {
+        database::get_sync_scope(db_manager.get_connection(), device_id)
+            .map_err(|e| AppError::database("errors.syncScope", e))
+    }).await?;
+    Ok(SyncScope { device_id, excluded_categories })
+}
+
+/// Cambia las categorías que no se mandan a un dispositivo. Las entradas que
+/// quedan fuera se le piden quitar en la próxima sincronización y las que
+/// vuelven a estar a su alcance se le mandan.
+#[tauri::command]
+pub async fn set_sync_scope(
+    state: State<'_, AppState>,
+    request: SyncScope,
+) -> AppResult<()> {
+    state.unlocked_crypto()?;
+    let SyncScope { device_id, excluded_categories } = request;
+    let count = excluded_categories.len();
+    state.with_db(move |db_manager| {
+        let conn = db_manager.get_connection();
+        database::get_trusted_device(conn, device_id)
+            .map_err(|e| AppError::database("errors.trustedDevices", e))?
+            .ok_or_else(|| AppError::not_found(Message::new("errors.deviceNotTrusted").with("id", device_id)))?;
+        for &id in &excluded_categories {
+            database::get_category(conn, id)
+                .map_err(|e| AppError::database("errors.dbQuery", e))?
+                .ok_or_else(|| AppError::not_found(Message::new("errors.categoryNotFound").with("id", id)))?;
+        }
+        database::set_sync_scope(conn, device_id, &excluded_categories)
+            .map_err(|e| AppError::database("errors.syncScope", e))
+    }).await?;
+    log::info!("{} categorías excluidas de la sincronización con {}", count, device_id);
+    Ok(())
+}
+
+/// Entradas eliminadas desde `since` (todas con `None`), para enviarlas a otro dispositivo
+#[tauri::command]
+pub async fn get_tombstones(
+    state: State<'_, AppState>,
+    since: Option<String>,
+) -> AppResult<Vec<Tombstone>> {
+    state.unlocked_crypto()?;
+    state.with_db(move |db_manager| {
+        database::list_tombstones(db_manager.get_connection(), since.as_deref())
+            .map_err(|e| AppError::database("errors.tombstones", e))
+    }).await
+}
+
+/// Conflictos de sincronización que esperan una decisión del usuario
+#[tauri::command]
+pub async fn get_sync_conflicts(
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SyncConflictView>> {
+    let conflicts = sync_manager(&state)?.get_conflicts().await;
+    let cipher = state.entry_cipher()?;
+    state.with_db(move |db_manager| {
+        let conn = db_manager.get_connection();
+        let repository = database::PasswordRepository::new(conn);
+        let mut views = Vec::with_capacity(conflicts.len());
+        for conflict in &conflicts {
+            let Some(remote_change) = conflict.remote_change() else {
+                continue;
+            };
+            let local = repository.get(conflict.element_id)
+                .map_err(|e| AppError::database("errors.getEntry", e))?
+                .map(|row| cipher.open(row))
+                .transpose()?;
+            views.push(SyncConflictView {
+                id: conflict.id.clone(),
+                entry_id: conflict.element_id,
+                detected_at: conflict.timestamp.to_rfc3339(),
+                remote_device: remote_change.source_device,
+                local: local.map(Into::into),
+                remote: conflict_remote_entry(conn, remote_change)?.map(Into::into),
+            });
+        }
+        Ok(views)
+    }).await
+}
+
+/// Resuelve un conflicto de sincronización. La decisión se guarda como un
+/// cambio nuevo de este dispositivo, que gana a las dos versiones en todos
+/// los demás.
+#[tauri::command]
+pub async fn resolve_sync_conflict(
+    state: State<'_, AppState>,
+    request: ConflictResolutionRequest,
+) -> AppResult<()> {
+    let manager = sync_manager(&state)?;
+    let cipher = state.entry_cipher()?;
+    let ConflictResolutionRequest { conflict_id, resolution } = request;
+    let remote_change = manager.get_conflicts().await.into_iter()
+        .find(|conflict| conflict.id == conflict_id)
+        .and_then(|conflict| conflict.remote_change().cloned())
+        .ok_or_else(|| AppError::not_found("errors.conflictNotFound"))?;
+
+    let chosen = resolution.clone();
+    let changes = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        let changes = apply_conflict_resolution(&tx, &cipher, &remote_change, &chosen)?;
+        tx.commit()?;
+        Ok(changes)
+    }).await?;
+    track_changes(&state, changes).await;
+
+    manager.resolve_conflict(&conflict_id, resolution).await
+        .map_err(|e| AppError::sync_with("errors.syncFailed", e))?;
+    log::info!("Conflicto de sincronización {} resuelto", conflict_id);
+    Ok(())
+}
+
+/// Manda una sola entrada a un dispositivo emparejado, sin sincronizar nada
+/// más con él. Allí espera a que su usuario la acepte.
+#[tauri::command]
+pub async fn send_entry_to_device(
+    state: State<'_, AppState>,
+    request: SendEntryRequest,
+) -> AppResult<()> {
+    let manager = sync_manager(&state)?;
+    let entry_id = request.entry_id;
+    let entry = open_entry(&state, entry_id).await?;
+
+    manager.send_entry(request.device_id, &entry).await
+        .map_err(|e| AppError::sync_with("errors.sendEntry", e))?;
+    log::info!("Entrada {} enviada a {}", entry_id, request.device_id);
+    Ok(())
+}
+
+/// Entrada desencriptada de la bóveda
+async fn open_entry(state: &AppState, entry_id: EntryId) -> AppResult<PasswordEntry> {
+    let cipher = state.entry_cipher()?;
+    state.with_db(move |db_manager| {
+        let row = database::PasswordRepository::new(db_manager.get_connection()).get(entry_id)
+            .map_err(|e| AppError::database("errors.getEntry", e))?
+            .ok_or_else(|| AppError::not_found("errors.entryNotFound"))?;
+        cipher.open(row)
+    }).await
+}
+
+/// Entradas que mandaron otros dispositivos, sin la contraseña
+#[tauri::command]
+pub async fn get_incoming_entries(state: State<'_, AppState>) -> AppResult<Vec<IncomingEntry>> {
+    Ok(sync_manager(&state)?.get_incoming_entries().await)
+}
+
+/// Acepta o rechaza una entrada que mandó otro dispositivo. La aceptada se
+/// guarda como una entrada nueva y sin categoría; devuelve su id.
+#[tauri::command]
+pub async fn respond_to_incoming_entry(
+    state: State<'_, AppState>,
+    request: IncomingEntryResponse,
+) -> AppResult<Option<EntryId>> {
+    let manager = sync_manager(&state)?;
+    let incoming = manager.take_incoming_entry(request.id).await
+        .map_err(|_| AppError::not_found("errors.incomingEntryNotFound"))?;
+    if !request.accepted {
+        log::info!("Entrada recibida de {} rechazada", incoming.from);
+        return Ok(None);
+    }
+
+    let cipher = state.entry_cipher()?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let entry = PasswordEntry {
+        id: EntryId::new(),
+        category_id: None,
+        created_at: now.clone(),
+        updated_at: now.clone(),
+        last_used: None,
+        ..incoming.entry
+    };
+    let entry_id = entry.id;
+    let change = state.with_db(move |db_manager| {
+        let tx = db_manager.get_connection_mut().transaction()?;
+        database::PasswordRepository::new(&tx).insert(&cipher.seal(&entry)?)
+            .map_err(|e| AppError::database("errors.saveEntry", e))?;
+        let change = journal_entry_change(&tx, &cipher, entry_id, ChangeType::Created, &EntryField::ALL, &now)?;
+        tx.commit()?;
+        Ok(change)
+    }).await?;
+    track_changes(&state, change.into_iter().collect()).await;
+
+    log::info!("Entrada recibida de {} guardada como {}", incoming.from, entry_id);
+    Ok(Some(entry_id))
+}
+
+/// Comparte una entrada con un enlace que sirve el relay propio hasta que
+/// caduca, se abre `max_views` veces o se revoca. El enlace lleva la clave
+/// que abre la entrada; el relay no la ve.
+#[tauri::command]
+pub async fn share_entry(
+    state: State<'_, AppState>,
+    request: ShareEntryRequest,
+) -> AppResult<CreatedShareLink> {
+    let expires_in = std::time::Duration::from_secs(request.expires_in_minutes.saturating_mul(60));
+    if expires_in.is_zero() || expires_in > MAX_SHARE_EXPIRY {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "share.expires_in")));
+    }
+    if request.max_views == 0 || request.max_views > MAX_SHARE_VIEWS {
+        return Err(AppError::validation(Message::new("errors.invalidSetting").with("setting", "share.max_views")));
+    }
+    let relay_url = state.settings.lock()
+        .map_err(|_| AppError::state_lock("components.settings"))?
+        .sync
+        .relay_url
+        .trim()
+        .to_string();
+    if relay_url.is_empty() {
+        return Err(AppError::validation("errors.shareNeedsRelay"));
+    }
+    let token = load_cloud_secret(&state, RELAY_TOKEN_SETTING, "fields.relayToken").await?;
+    let entry = open_entry(&state, request.entry_id).await?;
+
+    let id = ShareId::new();
+    let shares = RelayShares::new(&relay_url, &token)
+        .map_err(|e| AppError::sync_with("errors.shareEntry", e))?;
+    let link = ShareLink::generate(shares.share_url(id).map_err(|e| AppError::sync_with("errors.shareEntry", e))?);
+    let sealed = link.seal(&SharedEntry::from(entry))
+        .map_err(|e| AppError::internal_with("errors.shareEntry", e))?;
+    let owner = revocation_secret();
+    shares.publish(id, sealed, expires_in, request.max_views, &owner).await
+        .map_err(|e| AppError::sync_with("errors.shareEntry", e))?;
+
+    let now = chrono::Utc::now();
+    let share = SharedLink {
+        id,
+        entry_id: request.entry_id,
+        url: link.url.to_string(),
+        max_views: request.max_views,
+        created_at: now.to_rfc3339(),
+        expires_at: (now + chrono::Duration::from_std(expires_in).unwrap_or_default()).to_rfc3339(),
+    };
+    let owner = state.entry_cipher()?.encrypt(owner.as_bytes(), "fields.shareOwner")?;
+    let saved = share.clone();
+    state.with_db(move |db_manager| {
+        database::add_shared_link(db_manager.get_connection(), &saved, &owner)
+            .map_err(|e| AppError::database("errors.sharedLinks", e))
+    }).await?;
+
+    log::info!("Entrada {} compartida por enlace hasta {}", share.entry_id, share.expires_at);
+    Ok(CreatedShareLink { link: link.to_link(), share })
+}
+
+/// Enlaces compartidos desde aquí que no caducaron, sin la clave
+#[tauri::command]
+pub async fn get_shared_links(state: State<'_, AppState>) -> AppResult<Vec<SharedLink>> {
+    state.unlocked_crypto()?;
+    let now = chrono::Utc::now().to_rfc3339();
+    state.with_db(move |db_manager| {
+        database::get_shared_links(db_manager.get_connection(), &now)
+            .map_err(|e| AppError::database("errors.sharedLinks", e))
+    }).await
+}
+
+/// Borra del relay una entrada compartida: el enlace deja de abrirse
+#[tauri::command]
+pub async fn revoke_shared_link(
+    state: State<'_, AppState>,
+    id: ShareId,
+) -> AppResult<()> {
+    let cipher = state.entry_cipher()?;
+    let (url, owner) = state.with_db(move |db_manager| {
+        database::shared_link_owner(db_manager.get_connection(), id)
+            .map_err(|e| AppError::database("errors.sharedLinks", e))
+    }).await?
+        .ok_or_else(|| AppError::not_found("errors.sharedLinkNotFound"))?;
+    let owner = cipher.decrypt(&owner, "fields.shareOwner")?;
+    let url = reqwest::Url::parse(&url).map_err(|e| AppError::internal_with("errors.revokeShare", e))?;
+    crate::sync::relay::revoke_share(url, &owner).await
+        .map_err(|e| AppError::sync_with("errors.revokeShare", e))?;
+
+    state.with_db(move |db_manager| {
+        database::remove_shared_link(db_manager.get_connection(), id)
+            .map_err(|e| AppError::database("errors.sharedLinks", e))
+    }).await?;
+    log::info!("Enlace compartido {} revocado", id);
+    Ok(())
+}
+
+/// Abre un enlace compartido, gastando una de sus vistas. No lo guarda en la
+/// bóveda: eso queda para el usuario.
+#[tauri::command]
+pub async fn open_shared_link(link: String) -> AppResult<SharedEntry> {
+    let link = ShareLink::parse(&link)
+        .map_err(|_| AppError::validation("errors.invalidShareLink"))?;
+    let sealed = crate::sync::relay::fetch_share(link.url.clone()).await
+        .map_err(|e| AppError::sync_with("errors.openShare", e))?
+        .ok_or_else(|| AppError::not_found("errors.shareUnavailable"))?;
+    link.open(&sealed).map_err(|e| AppError::crypto("errors.openShare", e))
+}
+
+/// La bóveda de la aplicación vista por la sincronización. Las entradas viajan
+/// desencriptadas dentro del lote, que va encriptado con la clave del
+/// emparejamiento, y aquí se vuelven a encriptar con la clave maestra.
+pub struct VaultSyncStore {
+    app: AppHandle,
+}
+
+impl VaultSyncStore {
+    pub fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+
+    /// Un cambio con el contenido actual de cada elemento que elige `select`;
+    /// `Deleted` si ya no existe. Las entradas que se quitaron por quedar fuera
+    /// de lo que manda otro dispositivo no viajan como eliminaciones.
+    async fn load_elements<F>(&self, select: F) -> anyhow::Result<Vec<DataChange>>
+    where
+        F: FnOnce(&rusqlite::Connection) -> anyhow::Result<Vec<database::VersionedElement>> + Send + 'static,
+    {
+        let state = self.app.state::<AppState>();
+        let cipher = state.entry_cipher()?;
+        let (local_device, current) = state.with_db(move |db_manager| {
+            let conn = db_manager.get_connection();
+            let load = || -> anyhow::Result<_> {
+                let elements = select(conn)?;
+                let local_device = database::local_device_id(conn)?;
+                let repository = database::PasswordRepository::new(conn);
+                let tombstones = database::list_tombstones(conn, None)?;
+                let out_of_scope = database::out_of_scope_entries(conn)?;
+                let mut current = Vec::with_capacity(elements.len());
+                for element in elements {
+                    let stored = match element.kind {
+                        ElementKind::Category => database::get_category(conn, CategoryId::from(*element.element_id.as_uuid()))?
+                            .map(StoredElement::Category),
+                        ElementKind::Entry => repository.get(element.element_id)?.map(StoredElement::Entry),
+                    };
+                    if stored.is_none() && out_of_scope.contains(&element.element_id) {
+                        continue;
+                    }
+                    // Fecha del último cambio para desempatar cambios concurrentes
+                    let changed_at = match &stored {
+                        Some(StoredElement::Entry(row)) => Some(row.updated_at.clone()),
+                        Some(StoredElement::Category(_)) => database::last_sync_change(conn, element.element_id)?
+                            .map(|change| change.timestamp),
+                        None => tombstones.iter()
+                            .find(|tombstone| tombstone.entry_id == element.element_id)
+                            .map(|tombstone| tombstone.deleted_at.clone()),
+                    };
+                    let field_stamps = match &stored {
+                        Some(StoredElement::Entry(_)) => database::get_field_stamps(conn, element.element_id)?,
+                        _ => FieldStamps::new(),
+                    };
+                    current.push((element, stored, changed_at, field_stamps));
+                }
+                Ok((local_device, current))
+            };
+            load().map_err(|e| AppError::database("errors.syncVersions", e))
+        }).await?;
+
+        let mut changes = Vec::with_capacity(current.len());
+        for (element, stored, changed_at, field_stamps) in current {
+            let change_type = if stored.is_some() { ChangeType::Modified } else { ChangeType::Deleted };
+            let element_data = match stored {
+                Some(StoredElement::Entry(row)) => Some(serde_json::to_vec(&cipher.open(row)?)?),
+                Some(StoredElement::Category(category)) => Some(serde_json::to_vec(&category)?),
+                None => None,
+            };
+            let mut change = match element.kind {
+                ElementKind::Category => DataChange::for_category(
+                    CategoryId::from(*element.element_id.as_uuid()), change_type, local_device, element_data, 0, None,
+                ),
+                ElementKind::Entry => DataChange::new(element.element_id, change_type, local_device, element_data, 0, None),
+            };
+            if let Some(changed_at) = changed_at {
+                if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(&changed_at) {
+                    change.timestamp = timestamp.with_timezone(&chrono::Utc);
+                }
+                if change.change_type == ChangeType::Deleted {
+                    change.add_metadata("deleted_at".to_string(), changed_at);
+                }
+            }
+            change.versions = element.versions;
+            change.field_stamps = field_stamps;
+            changes.push(change);
+        }
+        Ok(changes)
+    }
+}
+
+#[async_trait]
+impl SyncStore for VaultSyncStore {
+    async fn local_device_id(&self) -> anyhow::Result<DeviceId> {
+        let state = self.app.state::<AppState>();
+        Ok(state.with_db(|db_manager| {
+            database::local_device_id(db_manager.get_connection())
+                .map_err(|e| AppError::database("errors.dbQuery", e))
+        }).await?)
+    }
+
+    async fn sync_key(&self, device_id: DeviceId) -> anyhow::Result<[u8; 32]> {
+        let state = self.app.state::<AppState>();
+        let cipher = state.entry_cipher()?;
+        let stored = state.with_db(move |db_manager| {
+            database::get_sync_key(db_manager.get_connection(), device_id)
+                .map_err(|e| AppError::database("errors.trustedDevices", e))
+        }).await?
+            .ok_or_else(|| anyhow::anyhow!("El dispositivo {} no tiene clave de sincronización; hay que emparejarlo", device_id))?;
+        cipher.decrypt_bytes(&stored, "fields.syncKey")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("La clave de sincronización de {} no es válida", device_id))
+    }
+
+    async fn knowledge(&self) -> anyhow::Result<VersionVector> {
+        Ok(self.app.state::<AppState>().with_db(|db_manager| {
+            database::local_knowledge(db_manager.get_connection())
+                .map_err(|e| AppError::database("errors.syncVersions", e))
+        }).await?)
+    }
+
+    /// Los elementos que nunca cambiaron desde que hay vectores reciben antes
+    /// su primera versión, si no nunca viajarían
+    async fn load_changes_since(&self, known: &VersionVector) -> anyhow::Result<Vec<DataChange>> {
+        let known = known.clone();
+        self.load_elements(move |conn| {
+            database::seed_element_versions(conn, database::local_device_id(conn)?)?;
+            database::elements_changed_since(conn, &known)
+        }).await
+    }
+
+    /// Aplica los cambios recibidos, las categorías antes que las entradas que
+    /// las usan. Un cambio que ya se vio se descarta y uno posterior a todo lo
+    /// de aquí se aplica. Si son concurrentes, una entrada se combina campo
+    /// por campo y una eliminación solo la borra si no se modificó aquí
+    /// después; de una categoría gana el cambio más reciente del diario. Con
+    /// `AskUser` las entradas concurrentes quedan como conflicto y con
+    /// `AutoMerge` solo las que cambiaron el mismo campo en los dos lados.
+    /// Las entradas que el otro dispositivo dejó fuera de lo que manda se
+    /// quitan sin marca de eliminación, salvo que aquí se editaran después.
+    async fn apply_changes(&self, changes: Vec<DataChange>, strategy: &ConflictResolutionStrategy) -> anyhow::Result<AppliedChanges> {
+        let strategy = strategy.clone();
+        let state = self.app.state::<AppState>();
+        let cipher = state.entry_cipher()?;
+        let mut categories = Vec::new();
+        let mut entries = Vec::with_capacity(changes.len());
+        let mut excluded = Vec::new();
+        for change in changes {
+            if change.change_type == ChangeType::Excluded {
+                if change.category_id().is_none() {
+                    excluded.push(change);
+                }
+                continue;
+            }
+            if change.change_type == ChangeType::Deleted {
+                match change.category_id() {
+                    Some(_) => categories.push((change, None)),
+                    None => entries.push((change, None)),
+                }
+                continue;
+            }
+            let Some(data) = change.element_data.as_deref() else {
+                log::warn!("Descartado cambio remoto de {} sin contenido", change.element_id);
+                continue;
+            };
+            if let Some(category_id) = change.category_id() {
+                let category: Category = serde_json::from_slice(data)?;
+                if category.id != category_id {
+                    log::warn!("Descartado cambio remoto de {} con otra categoría", category_id);
+                    continue;
+                }
+                categories.push((change, Some(category)));
+                continue;
+            }
+            let entry: PasswordEntry = serde_json::from_slice(data)?;
+            if entry.id != change.element_id {
+                log::warn!("Descartado cambio remoto de {} con otra entrada", change.element_id);
+                continue;
+            }
+            entries.push((change, Some(entry)));
+        }
+
+        Ok(state.with_db(move |db_manager| {
+            let tx = db_manager.get_connection_mut().transaction()?;
+            let mut applied = 0;
+            for (change, category) in categories {
+                let order = merge_remote_versions(&tx, &change, ElementKind::Category)?;
+                if matches!(order, Some(Ordering::Less | Ordering::Equal)) {
+                    continue;
+                }
+                if apply_category_change(&tx, &change, category, order.is_some())? {
+                    applied += 1;
+                }
+            }
+
+            let tombstones = database::list_tombstones(&tx, None)
+                .map_err(|e| AppError::database("errors.tombstones", e))?;
+            let kept: HashSet<String> = discard_resurrections(entries.iter().map(|(change, _)| change.clone()).collect(), &tombstones)
+                .into_iter()
+                .map(|change| change.id)
+                .collect();
+
+            let local_device = database::local_device_id(&tx)
+                .map_err(|e| AppError::database("errors.dbQuery", e))?;
+            let out_of_scope = database::out_of_scope_entries(&tx)
+                .map_err(|e| AppError::database("errors.syncScope", e))?;
+            let repository = database::PasswordRepository::new(&tx);
+            let mut conflicts = Vec::new();
+            for (change, entry) in entries {
+                // Una entrada que se quitó por quedar fuera vuelve aunque ya se hubiera visto
+                let returning = entry.is_some() && out_of_scope.contains(&change.element_id);
+                let order = if returning { Some(Ordering::Greater) } else { compare_remote_versions(&tx, &change)? };
+                if matches!(order, Some(Ordering::Less | Ordering::Equal)) {
+                    continue;
+                }
+                let local = repository.get(change.element_id)
+                    .map_err(|e| AppError::database("errors.getEntry", e))?;
+                let concurrent = order.is_none() && !change.versions.is_empty();
+                let escalate = match (&strategy, &entry, &local) {
+                    (ConflictResolutionStrategy::AskUser, entry, local) => concurrent && (local.is_some() || entry.is_some()),
+                    (ConflictResolutionStrategy::AutoMerge, Some(remote), Some(local)) if concurrent => {
+                        !entry_divergences(&tx, &cipher, local.clone(), remote, &change)?.is_empty()
+                    }
+                    _ => false,
+                };
+                // Sin unir el vector: el cambio sigue sin verse hasta que se resuelva
+                if escalate {
+                    let local = local_conflict_change(&tx, change.element_id, local.is_some(), local_device)?;
+                    conflicts.push(SyncConflict::new(change, local));
+                    continue;
+                }
+                merge_remote_versions(&tx, &change, ElementKind::Entry)?;
+                let newer = order.is_some();
+                if !newer && !kept.contains(&change.id) {
+                    continue;
+                }
+                let written = match (entry, local) {
+                    (None, Some(local)) if newer || !is_later(&local.updated_at, &change.timestamp.to_rfc3339()) => {
+                        repository.delete(change.element_id, &change.timestamp.to_rfc3339())
+                            .map_err(|e| AppError::database("errors.deleteEntry", e))?
+                    }
+                    (None, _) => false,
+                    (Some(mut entry), local) => {
+                        // La categoría puede no existir en este dispositivo
+                        if let Some(category_id) = entry.category_id {
+                            if database::get_category(&tx, category_id)
+                                .map_err(|e| AppError::database("errors.dbQuery", e))?
+                                .is_none() {
+                                entry.category_id = None;
+                            }
+                        }
+                        let local_exists = local.is_some();
+                        let (entry, stamps) = match local {
+                            None => (Some(entry), change.field_stamps.clone()),
+                            Some(_) if newer => (Some(entry), change.field_stamps.clone()),
+                            Some(local) => {
+                                // Ediciones concurrentes: de cada campo gana el cambio más reciente
+                                let local = cipher.open(local)?;
+                                let local_stamps = database::get_field_stamps(&tx, change.element_id)
+                                    .map_err(|e| AppError::database("errors.syncVersions", e))?;
+                                let (merged, stamps) = merge_entry_fields(
+                                    &local, &local_stamps, local_device, &entry, &change.field_stamps, change.source_device,
+                                );
+                                let unchanged = changed_fields(&local, &merged).is_empty() && merged.updated_at == local.updated_at;
+                                (Some(merged).filter(|_| !unchanged), stamps)
+                            }
+                        };
+                        database::save_field_stamps(&tx, change.element_id, &stamps)
+                            .map_err(|e| AppError::database("errors.syncVersions", e))?;
+                        match entry {
+                            Some(entry) if local_exists => repository.update(&cipher.seal(&entry)?)
+                                .map_err(|e| AppError::database("errors.updateEntry", e))?,
+                            Some(entry) => repository.insert(&cipher.seal(&entry)?)
+                                .map(|()| true)
+                                .map_err(|e| AppError::database("errors.saveEntry", e))?,
+                            None => false,
+                        }
+                    }
+                };
+                if written && returning {
+                    database::clear_out_of_scope(&tx, change.element_id)
+                        .map_err(|e| AppError::database("errors.syncScope", e))?;
+                }
+                if written {
+                    applied += 1;
+                }
+            }
+
+            let removed_at = chrono::Utc::now().to_rfc3339();
+            for change in excluded {
+                if !matches!(compare_remote_versions(&tx, &change)?, Some(Ordering::Greater | Ordering::Equal)) {
+                    continue;
+                }
+                let discarded = repository.discard(change.element_id)
+                    .map_err(|e| AppError::database("errors.deleteEntry", e))?;
+                if discarded {
+                    database::mark_out_of_scope(&tx, change.element_id, &removed_at)
+                        .map_err(|e| AppError::database("errors.syncScope", e))?;
+                    applied += 1;
+                }
+            }
+            tx.commit()?;
+            log::info!("{} cambios remotos aplicados a la bóveda", applied);
+            Ok(AppliedChanges { applied, conflicts })
+        }).await?)
+    }
+
+    async fn peer_knowledge(&self, device_id: DeviceId) -> anyhow::Result<VersionVector> {
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            database::get_peer_knowledge(db_manager.get_connection(), device_id)
+                .map_err(|e| AppError::database("errors.syncVersions", e))
+        }).await?)
+    }
+
+    async fn save_peer_knowledge(&self, device_id: DeviceId, knowledge: VersionVector) -> anyhow::Result<()> {
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            database::save_peer_knowledge(db_manager.get_connection(), device_id, &knowledge)
+                .map_err(|e| AppError::database("errors.syncVersions", e))
+        }).await?)
+    }
+
+    async fn bootstrap(&self, device_id: DeviceId) -> anyhow::Result<Option<SyncBootstrap>> {
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            database::get_sync_bootstrap(db_manager.get_connection(), device_id)
+                .map_err(|e| AppError::database("errors.syncVersions", e))
+        }).await?)
+    }
+
+    async fn save_bootstrap(&self, device_id: DeviceId, bootstrap: Option<SyncBootstrap>) -> anyhow::Result<()> {
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            let connection = db_manager.get_connection();
+            match &bootstrap {
+                Some(bootstrap) => database::save_sync_bootstrap(connection, device_id, bootstrap),
+                None => database::clear_sync_bootstrap(connection, device_id),
+            }
+            .map_err(|e| AppError::database("errors.syncVersions", e))
+        }).await?)
+    }
+
+    async fn record_sync(&self, record: SyncHistoryRecord) -> anyhow::Result<()> {
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            database::record_sync(db_manager.get_connection(), &record)
+                .map_err(|e| AppError::database("errors.syncHistory", e))
+        }).await?)
+    }
+
+    /// Las categorías viajan siempre. De las entradas excluidas solo va un
+    /// `Excluded` la primera vez, por si el otro dispositivo las tenía, y las
+    /// que vuelven a estar a su alcance van completas aunque ya las hubiera
+    /// visto.
+    async fn scope_changes(&self, device_id: DeviceId, changes: Vec<DataChange>) -> anyhow::Result<Vec<DataChange>> {
+        let state = self.app.state::<AppState>();
+        let (excluded, withheld, newly_excluded) = state.with_db(move |db_manager| {
+            let conn = db_manager.get_connection();
+            let load = || -> anyhow::Result<_> {
+                let excluded = database::excluded_entries(conn, device_id)?;
+                let withheld = database::withheld_entries(conn, device_id)?;
+                let newly_excluded = excluded.difference(&withheld)
+                    .map(|&entry_id| Ok((entry_id, database::get_element_versions(conn, entry_id)?)))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok((excluded, withheld, newly_excluded))
+            };
+            load().map_err(|e| AppError::database("errors.syncScope", e))
+        }).await?;
+        if excluded.is_empty() && withheld.is_empty() {
+            return Ok(changes);
+        }
+
+        let mut scoped: Vec<DataChange> = changes.into_iter()
+            .filter(|change| change.category_id().is_some() || !excluded.contains(&change.element_id))
+            .collect();
+        let local_device = self.local_device_id().await?;
+        for (entry_id, versions) in newly_excluded {
+            let mut change = DataChange::new(entry_id, ChangeType::Excluded, local_device, None, 0, None);
+            change.versions = versions;
+            scoped.push(change);
+        }
+
+        let returning: Vec<EntryId> = withheld.difference(&excluded)
+            .filter(|entry_id| !scoped.iter().any(|change| change.element_id == **entry_id))
+            .copied()
+            .collect();
+        if !returning.is_empty() {
+            scoped.extend(self.load_elements(move |conn| {
+                let repository = database::PasswordRepository::new(conn);
+                let mut elements = Vec::with_capacity(returning.len());
+                let mut gone = Vec::new();
+                for entry_id in returning {
+                    if repository.get(entry_id)?.is_none() {
+                        gone.push(entry_id);
+                        continue;
+                    }
+                    elements.push(database::VersionedElement {
+                        element_id: entry_id,
+                        kind: ElementKind::Entry,
+                        versions: database::get_element_versions(conn, entry_id)?,
+                    });
+                }
+                // Las que se eliminaron mientras estaban excluidas no hace falta mandarlas
+                database::update_withheld_entries(conn, device_id, &[], &gone)?;
+                Ok(elements)
+            }).await?);
+        }
+        Ok(scoped)
+    }
+
+    async fn record_delivery(&self, device_id: DeviceId, changes: &[DataChange]) -> anyhow::Result<()> {
+        let mut withheld = Vec::new();
+        let mut released = Vec::new();
+        for change in changes.iter().filter(|change| change.category_id().is_none()) {
+            match change.change_type {
+                ChangeType::Excluded => withheld.push(change.element_id),
+                _ => released.push(change.element_id),
+            }
+        }
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            let conn = db_manager.get_connection();
+            let record = || -> anyhow::Result<()> {
+                // Solo hace falta tocar las que estaban retenidas
+                let known = database::withheld_entries(conn, device_id)?;
+                let released: Vec<EntryId> = released.iter().copied().filter(|entry_id| known.contains(entry_id)).collect();
+                if !withheld.is_empty() || !released.is_empty() {
+                    database::update_withheld_entries(conn, device_id, &withheld, &released)?;
+                }
+                Ok(())
+            };
+            record().map_err(|e| AppError::database("errors.syncScope", e))
+        }).await?)
+    }
+
+    async fn acknowledge_deletions(&self, device_id: DeviceId, entry_ids: &[EntryId]) -> anyhow::Result<()> {
+        let entry_ids = entry_ids.to_vec();
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            let tx = db_manager.get_connection_mut().transaction()?;
+            let now = chrono::Utc::now().to_rfc3339();
+            let acknowledged = database::acknowledge_tombstones(&tx, device_id, &entry_ids, &now)
+                .map_err(|e| AppError::database("errors.tombstones", e))?;
+            let purged = database::purge_acknowledged_tombstones(&tx)
+                .map_err(|e| AppError::database("errors.tombstones", e))?;
+            tx.commit()?;
+            log::info!("{} eliminaciones confirmadas por {}, {} marcas purgadas", acknowledged, device_id, purged);
+            Ok(())
+        }).await?)
+    }
+
+    async fn is_unlocked(&self) -> bool {
+        self.app.state::<AppState>().crypto_manager.lock()
+            .is_ok_and(|crypto_manager| crypto_manager.is_unlocked())
+    }
+}
+
+/// Contenido actual de un elemento de la bóveda
+enum StoredElement {
+    Entry(database::EncryptedEntryRow),
+    Category(Category),
+}
+
+/// Cómo se compara el vector de un cambio recibido con el del elemento aquí:
+/// `Less` o `Equal` si ya se vio, `Greater` si es posterior y `None` si es
+/// concurrente. Un cambio sin vector, de una versión anterior de la
+/// aplicación, cuenta como concurrente.
+fn compare_remote_versions(conn: &rusqlite::Connection, change: &DataChange) -> AppResult<Option<Ordering>> {
+    if change.versions.is_empty() {
+        return Ok(None);
+    }
+    let local = database::get_element_versions(conn, change.element_id)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    Ok(change.versions.partial_cmp(&local))
+}
+
+/// Une el vector de un cambio recibido al del elemento y devuelve cómo se
+/// comparaba con el de aquí, como [`compare_remote_versions`]
+fn merge_remote_versions(conn: &rusqlite::Connection, change: &DataChange, kind: ElementKind) -> AppResult<Option<Ordering>> {
+    let order = compare_remote_versions(conn, change)?;
+    database::merge_element_versions(conn, change.element_id, kind, &change.versions)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    Ok(order)
+}
+
+/// Campos de la entrada que el cambio remoto y este dispositivo editaron a la
+/// vez con valores distintos
+fn entry_divergences(
+    conn: &rusqlite::Connection,
+    cipher: &EntryCipher,
+    local: database::EncryptedEntryRow,
+    remote: &PasswordEntry,
+    change: &DataChange,
+) -> AppResult<Vec<EntryField>> {
+    let local = cipher.open(local)?;
+    let local_stamps = database::get_field_stamps(conn, change.element_id)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    let local_versions = database::get_element_versions(conn, change.element_id)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    Ok(diverging_fields(&local, &local_stamps, &local_versions, remote, &change.field_stamps, &change.versions))
+}
+
+/// Estado de aquí de una entrada en conflicto: su vector y sus sellos, sin
+/// contenido; la interfaz lo lee de la bóveda
+fn local_conflict_change(
+    conn: &rusqlite::Connection,
+    entry_id: EntryId,
+    exists: bool,
+    local_device: DeviceId,
+) -> AppResult<DataChange> {
+    let change_type = if exists { ChangeType::Modified } else { ChangeType::Deleted };
+    let mut change = DataChange::new(entry_id, change_type, local_device, None, 0, None);
+    change.versions = database::get_element_versions(conn, entry_id)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    change.field_stamps = database::get_field_stamps(conn, entry_id)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    Ok(change)
+}
+
+/// Entrada que trae el cambio remoto de un conflicto; `None` si la borró. Si
+/// su categoría no existe aquí queda sin categoría.
+fn conflict_remote_entry(conn: &rusqlite::Connection, change: &DataChange) -> AppResult<Option<PasswordEntry>> {
+    let Some(data) = change.element_data.as_deref().filter(|_| change.change_type != ChangeType::Deleted) else {
+        return Ok(None);
+    };
+    let mut entry: PasswordEntry = serde_json::from_slice(data)
+        .map_err(|e| AppError::internal_with("errors.syncJournal", e))?;
+    if let Some(category_id) = entry.category_id {
+        if database::get_category(conn, category_id)
+            .map_err(|e| AppError::database("errors.dbQuery", e))?
+            .is_none() {
+            entry.category_id = None;
+        }
+    }
+    Ok(Some(entry))
+}
+
+/// Aplica la resolución de un conflicto y anota en el diario los cambios que
+/// la llevan a los demás dispositivos. Une antes el vector del cambio remoto
+/// para que los cambios nuevos sean posteriores a las dos versiones.
+fn apply_conflict_resolution(
+    conn: &rusqlite::Connection,
+    cipher: &EntryCipher,
+    remote_change: &DataChange,
+    resolution: &ConflictResolution,
+) -> AppResult<Vec<DataChange>> {
+    let entry_id = remote_change.element_id;
+    database::merge_element_versions(conn, entry_id, ElementKind::Entry, &remote_change.versions)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    let repository = database::PasswordRepository::new(conn);
+    let local = repository.get(entry_id)
+        .map_err(|e| AppError::database("errors.getEntry", e))?
+        .map(|row| cipher.open(row))
+        .transpose()?;
+    let local_exists = local.is_some();
+    let remote = conflict_remote_entry(conn, remote_change)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut changes = Vec::new();
+
+    // Lo que queda en la entrada; `None` si se borra
+    let resolved = match (resolution, local, remote) {
+        (ConflictResolution::UseLocal, local, _) => local,
+        (ConflictResolution::UseRemote, _, remote) => remote,
+        (ConflictResolution::Merge, Some(local), Some(remote)) => {
+            let local_device = database::local_device_id(conn)
+                .map_err(|e| AppError::database("errors.dbQuery", e))?;
+            let local_stamps = database::get_field_stamps(conn, entry_id)
+                .map_err(|e| AppError::database("errors.syncVersions", e))?;
+            let (merged, _) = merge_entry_fields(
+                &local, &local_stamps, local_device, &remote, &remote_change.field_stamps, remote_change.source_device,
+            );
+            Some(merged)
+        }
+        (ConflictResolution::CreateNew, local, Some(remote)) => {
+            // Se quedan las dos: la remota como una entrada nueva
+            let copy = PasswordEntry { id: EntryId::new(), created_at: now.clone(), updated_at: now.clone(), ..remote };
+            repository.insert(&cipher.seal(&copy)?)
+                .map_err(|e| AppError::database("errors.saveEntry", e))?;
+            changes.extend(journal_entry_change(conn, cipher, copy.id, ChangeType::Created, &EntryField::ALL, &now)?);
+            local
+        }
+        (ConflictResolution::Delete, _, _) => None,
+        _ => return Err(AppError::validation("errors.conflictResolution")),
+    };
+
+    match resolved {
+        Some(mut entry) => {
+            entry.updated_at = now.clone();
+            let sealed = cipher.seal(&entry)?;
+            if local_exists {
+                repository.update(&sealed).map_err(|e| AppError::database("errors.updateEntry", e))?;
+            } else {
+                repository.insert(&sealed).map_err(|e| AppError::database("errors.saveEntry", e))?;
+            }
+            changes.extend(journal_entry_change(conn, cipher, entry_id, ChangeType::Modified, &EntryField::ALL, &now)?);
+        }
+        None => {
+            if local_exists {
+                repository.delete(entry_id, &now).map_err(|e| AppError::database("errors.deleteEntry", e))?;
+            }
+            changes.extend(journal_entry_change(conn, cipher, entry_id, ChangeType::Deleted, &[], &now)?);
+        }
+    }
+    Ok(changes)
+}
+
+/// Aplica el cambio remoto de una categoría salvo que sea concurrente
+/// (`newer` en falso) y aquí haya uno más reciente. Si su categoría padre no
+/// existe aquí o formaría un ciclo, queda en la raíz. Devuelve si se aplicó.
+fn apply_category_change(conn: &rusqlite::Connection, change: &DataChange, category: Option<Category>, newer: bool) -> AppResult<bool> {
+    let local = database::last_sync_change(conn, change.element_id)
+        .map_err(|e| AppError::database("errors.syncJournal", e))?;
+    if !newer && local.is_some_and(|local| is_later(&local.timestamp, &change.timestamp.to_rfc3339())) {
+        return Ok(false);
+    }
+    let Some(category_id) = change.category_id() else {
+        return Ok(false);
+    };
+
+    let Some(mut category) = category else {
+        // Sus entradas quedan sin categoría, igual que al eliminarla aquí
+        database::PasswordRepository::new(conn)
+            .reassign_category(category_id, None, &change.timestamp.to_rfc3339())
+            .map_err(|e| AppError::database("errors.deleteCategory", e))?;
+        return database::delete_category(conn, category_id)
+            .map_err(|e| AppError::database("errors.deleteCategory", e));
+    };
+    let categories = database::list_categories(conn)
+        .map_err(|e| AppError::database("errors.dbQuery", e))?;
+    category.parent_id = category.parent_id.filter(|parent_id| {
+        categories.iter().any(|existing| existing.id == *parent_id)
+            && !crate::category_tree::creates_cycle(&categories, category_id, *parent_id)
+    });
+    if categories.iter().any(|existing| existing.id == category_id) {
+        let request = CategoryRequest {
+            name: category.name,
+            color: category.color,
+            icon: category.icon,
+            parent_id: category.parent_id,
+        };
+        database::update_category(conn, category_id, &request)
+            .map_err(|e| AppError::database("errors.saveCategory", e))
     } else {
-        Err("Sync manager not initialized".to_string())
+        database::insert_category(conn, &category)
+            .map(|()| true)
+            .map_err(|e| AppError::database("errors.saveCategory", e))
+    }
+}
+
+/// Anota en el diario de sincronización el cambio de una entrada, con su
+/// contenido actual encriptado con la clave maestra, y sella los `fields` que
+/// tocó. Va dentro de la transacción que hace el cambio; devuelve `None` si la
+/// entrada no existe.
+pub fn journal_entry_change(
+    conn: &rusqlite::Connection,
+    cipher: &EntryCipher,
+    entry_id: EntryId,
+    change_type: ChangeType,
+    fields: &[EntryField],
+    changed_at: &str,
+) -> AppResult<Option<DataChange>> {
+    let element_data = if change_type == ChangeType::Deleted {
+        None
+    } else {
+        let Some(row) = database::PasswordRepository::new(conn).get(entry_id)
+            .map_err(|e| AppError::database("errors.getEntry", e))? else {
+            return Ok(None);
+        };
+        let entry = serde_json::to_vec(&cipher.open(row)?)
+            .map_err(|e| AppError::internal_with("errors.syncJournal", e))?;
+        Some(cipher.encrypt(&entry, "fields.syncChange")?.into_bytes())
+    };
+    let (source_device, version, previous_hash) = journal_position(conn, entry_id)?;
+    let mut change = DataChange::new(entry_id, change_type, source_device, element_data, version, previous_hash);
+    if let Ok(changed_at) = chrono::DateTime::parse_from_rfc3339(changed_at) {
+        change.timestamp = changed_at.with_timezone(&chrono::Utc);
+    }
+    if change.change_type == ChangeType::Deleted {
+        change.add_metadata("deleted_at".to_string(), changed_at.to_string());
+    }
+    let change = record_journal_change(conn, change, ElementKind::Entry)?;
+    // Con el número que le tocó al cambio, para saber después quién lo vio
+    database::stamp_entry_fields(conn, entry_id, fields, changed_at, source_device, change.versions.get(source_device))
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    Ok(Some(change))
+}
+
+/// Como [`journal_entry_change`], para una categoría
+pub fn journal_category_change(
+    conn: &rusqlite::Connection,
+    cipher: &EntryCipher,
+    category_id: CategoryId,
+    change_type: ChangeType,
+) -> AppResult<Option<DataChange>> {
+    let element_data = if change_type == ChangeType::Deleted {
+        None
+    } else {
+        let Some(category) = database::get_category(conn, category_id)
+            .map_err(|e| AppError::database("errors.dbQuery", e))? else {
+            return Ok(None);
+        };
+        let category = serde_json::to_vec(&category)
+            .map_err(|e| AppError::internal_with("errors.syncJournal", e))?;
+        Some(cipher.encrypt(&category, "fields.syncChange")?.into_bytes())
+    };
+    let (source_device, version, previous_hash) = journal_position(conn, EntryId::from(*category_id.as_uuid()))?;
+    let change = DataChange::for_category(category_id, change_type, source_device, element_data, version, previous_hash);
+    record_journal_change(conn, change, ElementKind::Category).map(Some)
+}
+
+/// Dispositivo que firma el cambio, versión que le toca al elemento y hash de
+/// su cambio anterior
+fn journal_position(conn: &rusqlite::Connection, element_id: EntryId) -> AppResult<(DeviceId, u64, Option<String>)> {
+    let source_device = database::local_device_id(conn)
+        .map_err(|e| AppError::database("errors.dbQuery", e))?;
+    let last = database::last_sync_change(conn, element_id)
+        .map_err(|e| AppError::database("errors.syncJournal", e))?;
+    Ok(match last {
+        Some(last) => (source_device, last.version as u64 + 1, Some(last.current_hash).filter(|hash| !hash.is_empty())),
+        None => (source_device, 1, None),
+    })
+}
+
+/// Da al elemento el siguiente número del dispositivo local y anota el cambio
+/// con el vector que queda
+fn record_journal_change(conn: &rusqlite::Connection, mut change: DataChange, kind: ElementKind) -> AppResult<DataChange> {
+    change.versions = database::bump_element_version(conn, change.element_id, kind, change.source_device)
+        .map_err(|e| AppError::database("errors.syncVersions", e))?;
+    database::insert_sync_change(conn, &database::SyncChangeRow::from(&change))
+        .map_err(|e| AppError::database("errors.syncJournal", e))?;
+    Ok(change)
+}
+
+/// Pasa al motor de sincronización los cambios que ya se anotaron en el
+/// diario. Si el gestor todavía no existe los cargará del diario al iniciar.
+pub async fn track_changes(state: &AppState, changes: Vec<DataChange>) {
+    let Some(manager) = state.sync_manager.get() else {
+        return;
+    };
+    for change in changes {
+        if let Err(e) = manager.track_change(change).await {
+            log::warn!("No se pudo encolar un cambio para sincronizar: {}", e);
+        }
+    }
+}
+
+/// El diario de cambios vive en la misma base que la bóveda
+#[async_trait]
+impl ChangeJournal for VaultSyncStore {
+    async fn record(&self, change: &DataChange) -> anyhow::Result<()> {
+        let row = database::SyncChangeRow::from(change);
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            database::insert_sync_change(db_manager.get_connection(), &row)
+                .map_err(|e| AppError::database("errors.syncJournal", e))
+        }).await?)
+    }
+
+    async fn mark_synced(&self, change_ids: Vec<String>) -> anyhow::Result<()> {
+        let synced_at = chrono::Utc::now().to_rfc3339();
+        self.app.state::<AppState>().with_db(move |db_manager| {
+            database::mark_sync_changes_synced(db_manager.get_connection(), &change_ids, &synced_at)
+                .map_err(|e| AppError::database("errors.syncJournal", e))
+        }).await?;
+        Ok(())
+    }
+
+    async fn load_pending(&self) -> anyhow::Result<Vec<DataChange>> {
+        let rows = self.app.state::<AppState>().with_db(|db_manager| {
+            database::list_pending_sync_changes(db_manager.get_connection())
+                .map_err(|e| AppError::database("errors.syncJournal", e))
+        }).await?;
+        rows.into_iter().map(DataChange::try_from).collect()
+    }
+
+    async fn prune(&self, cutoff: chrono::DateTime<chrono::Utc>) -> anyhow::Result<usize> {
+        let cutoff = cutoff.to_rfc3339();
+        Ok(self.app.state::<AppState>().with_db(move |db_manager| {
+            database::prune_synced_changes(db_manager.get_connection(), &cutoff)
+                .map_err(|e| AppError::database("errors.syncJournal", e))
+        }).await?)
+    }
+}
+
+/// Si la fecha RFC 3339 `a` es posterior a `b`
+fn is_later(a: &str, b: &str) -> bool {
+    match (chrono::DateTime::parse_from_rfc3339(a), chrono::DateTime::parse_from_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => a > b,
     }
 }
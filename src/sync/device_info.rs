@@ -5,12 +5,19 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use chrono::{DateTime, Utc};
-use uuid::Uuid;
+use crate::models::DeviceId;
 use anyhow::Result;
+use semver::Version;
+
+/// Versión más antigua de Alohopass con la que sincroniza esta. Va en las
+/// capacidades como `min_app_version`.
+pub const MIN_PEER_APP_VERSION: &str = "0.1.0";
 
 /// Tipos de dispositivos soportados
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum DeviceType {
     /// Dispositivo móvil (teléfono, smartphone)
     Mobile,
@@ -83,6 +90,7 @@ impl DeviceType {
 
 /// Estado de conexión del dispositivo
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub enum DeviceStatus {
     /// Dispositivo desconectado
     Disconnected,
@@ -136,10 +144,11 @@ impl DeviceStatus {
 }
 
 /// Información de un dispositivo
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DeviceInfo {
     /// ID único del dispositivo
-    pub id: String,
+    pub id: DeviceId,
     /// Nombre del dispositivo
     pub name: String,
     /// Tipo de dispositivo
@@ -154,6 +163,11 @@ pub struct DeviceInfo {
     pub ip_address: Option<String>,
     /// Puerto de comunicación
     pub port: Option<u16>,
+    /// Direcciones que anunció en la red local, de la preferida a la última.
+    /// Las IPv6 de enlace local llevan la interfaz por la que se llega
+    /// (`fe80::1%3`).
+    #[serde(default)]
+    pub addresses: Vec<String>,
     /// Estado de conexión
     pub status: DeviceStatus,
     /// Última vez que se vio el dispositivo
@@ -162,6 +176,10 @@ pub struct DeviceInfo {
     pub last_sync: Option<DateTime<Utc>>,
     /// Capacidades del dispositivo
     pub capabilities: DeviceCapabilities,
+    /// Por qué no se puede sincronizar con él, si se sabe. Lo completa el
+    /// gestor al listar los dispositivos.
+    #[serde(default)]
+    pub incompatibility: Option<Incompatibility>,
     /// Metadatos adicionales
     pub metadata: HashMap<String, String>,
     /// Dispositivo es confiable (verificado)
@@ -171,14 +189,13 @@ pub struct DeviceInfo {
 }
 
 /// Capacidades del dispositivo
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DeviceCapabilities {
     /// Puede sincronizar contraseñas
     pub can_sync_passwords: bool,
     /// Puede sincronizar configuraciones
     pub can_sync_settings: bool,
-    /// Puede sincronizar archivos
-    pub can_sync_files: bool,
     /// Puede generar contraseñas
     pub can_generate_passwords: bool,
     /// Puede autocompletar en navegadores
@@ -189,20 +206,68 @@ pub struct DeviceCapabilities {
     pub min_app_version: String,
 }
 
+/// Las de esta versión
 impl Default for DeviceCapabilities {
     fn default() -> Self {
         Self {
             can_sync_passwords: true,
             can_sync_settings: true,
-            can_sync_files: false,
             can_generate_passwords: true,
             can_autocomplete: false,
             can_use_shortcuts: false,
-            min_app_version: "1.0.0".to_string(),
+            min_app_version: MIN_PEER_APP_VERSION.to_string(),
         }
     }
 }
 
+impl DeviceCapabilities {
+    /// Por qué esta versión no puede sincronizar con un dispositivo que tiene
+    /// Alohopass `peer_version` y estas capacidades; `None` si puede
+    pub fn incompatibility_with(&self, peer_version: &str) -> Option<Incompatibility> {
+        compatibility(env!("CARGO_PKG_VERSION"), &DeviceCapabilities::default(), peer_version, self).err()
+    }
+}
+
+/// Por qué no se puede sincronizar con un dispositivo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Incompatibility {
+    /// El otro tiene una versión anterior a la mínima que acepta esta
+    #[error("tiene Alohopass {version} y hace falta al menos la {required}")]
+    PeerTooOld { version: String, required: String },
+    /// El otro pide una versión más nueva que la de este dispositivo
+    #[error("pide al menos Alohopass {required} y este dispositivo tiene la {version}")]
+    LocalTooOld { version: String, required: String },
+    /// Una de las versiones no es semver
+    #[error("la versión {version} no se entiende")]
+    InvalidVersion { version: String },
+}
+
+/// Compara las versiones de los dos lados: cada una tiene que llegar a la
+/// mínima que pide el otro
+fn compatibility(
+    local_version: &str,
+    local: &DeviceCapabilities,
+    peer_version: &str,
+    peer: &DeviceCapabilities,
+) -> Result<(), Incompatibility> {
+    let parse = |version: &str| Version::parse(version.trim())
+        .map_err(|_| Incompatibility::InvalidVersion { version: version.to_string() });
+    if parse(peer_version)? < parse(&local.min_app_version)? {
+        return Err(Incompatibility::PeerTooOld {
+            version: peer_version.to_string(),
+            required: local.min_app_version.clone(),
+        });
+    }
+    if parse(local_version)? < parse(&peer.min_app_version)? {
+        return Err(Incompatibility::LocalTooOld {
+            version: local_version.to_string(),
+            required: peer.min_app_version.clone(),
+        });
+    }
+    Ok(())
+}
+
 impl DeviceInfo {
     /// Crear un nuevo dispositivo
     pub fn new(
@@ -213,7 +278,7 @@ impl DeviceInfo {
         app_version: String,
     ) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: DeviceId::new(),
             name,
             device_type,
             os,
@@ -221,10 +286,12 @@ impl DeviceInfo {
             app_version,
             ip_address: None,
             port: None,
+            addresses: Vec::new(),
             status: DeviceStatus::Disconnected,
             last_seen: Some(Utc::now()),
             last_sync: None,
             capabilities: DeviceCapabilities::default(),
+            incompatibility: None,
             metadata: HashMap::new(),
             is_trusted: false,
             is_owner: true, // El dispositivo actual es el propietario
@@ -242,24 +309,49 @@ impl DeviceInfo {
         port: u16,
     ) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: DeviceId::new(),
             name,
             device_type,
             os,
             os_version,
             app_version,
+            addresses: vec![ip_address.clone()],
             ip_address: Some(ip_address),
             port: Some(port),
             status: DeviceStatus::Disconnected,
             last_seen: Some(Utc::now()),
             last_sync: None,
             capabilities: DeviceCapabilities::default(),
+            incompatibility: None,
             metadata: HashMap::new(),
             is_trusted: false,
             is_owner: false, // Dispositivo descubierto en la red
         }
     }
 
+    /// Dispositivo de confianza que ahora no está a la vista
+    pub fn offline(id: DeviceId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            device_type: DeviceType::Unknown,
+            os: "Unknown".to_string(),
+            os_version: "Unknown".to_string(),
+            app_version: "Unknown".to_string(),
+            ip_address: None,
+            port: None,
+            addresses: Vec::new(),
+            status: DeviceStatus::Disconnected,
+            last_seen: None,
+            last_sync: None,
+            capabilities: DeviceCapabilities::default(),
+            incompatibility: None,
+            metadata: HashMap::new(),
+            is_trusted: true,
+            is_owner: false,
+        }
+    }
+
     /// Obtener el nombre de visualización completo
     pub fn display_name(&self) -> String {
         format!("{} {} ({})", self.device_type.emoji(), self.name, self.device_type.display_name())
@@ -275,11 +367,19 @@ impl DeviceInfo {
         self.status.is_connected() && !self.status.is_syncing() && !self.status.has_error()
     }
 
+    /// Por qué no se puede sincronizar con el dispositivo según la versión y
+    /// las capacidades que anunció; `None` si se puede o si todavía no se
+    /// sabe su versión
+    pub fn check_compatibility(&self) -> Option<Incompatibility> {
+        if self.app_version == "Unknown" {
+            return None;
+        }
+        self.capabilities.incompatibility_with(&self.app_version)
+    }
+
     /// Verificar si el dispositivo es compatible
     pub fn is_compatible(&self) -> bool {
-        // Verificar versión mínima requerida
-        // TODO: Implementar comparación de versiones semántica
-        true
+        self.check_compatibility().is_none()
     }
 
     /// Actualizar el estado del dispositivo
@@ -288,6 +388,17 @@ impl DeviceInfo {
         self.last_seen = Some(Utc::now());
     }
 
+    /// Completa un dispositivo conectado con lo que anunció el descubrimiento:
+    /// la dirección si la conexión no la tiene y la última vez que se lo vio
+    pub fn merge_discovered(&mut self, discovered: &DeviceInfo) {
+        if self.ip_address.is_none() {
+            self.ip_address = discovered.ip_address.clone();
+            self.port = discovered.port;
+            self.addresses = discovered.addresses.clone();
+        }
+        self.last_seen = self.last_seen.max(discovered.last_seen);
+    }
+
     /// Marcar como sincronizado
     pub fn mark_synced(&mut self) {
         self.last_sync = Some(Utc::now());
@@ -312,6 +423,7 @@ impl DeviceInfo {
     /// Obtener información de conexión
     pub fn connection_info(&self) -> Option<String> {
         match (&self.ip_address, self.port) {
+            (Some(ip), Some(port)) if ip.contains(':') => Some(format!("[{}]:{}", ip, port)),
             (Some(ip), Some(port)) => Some(format!("{}:{}", ip, port)),
             (Some(ip), None) => Some(ip.clone()),
             (None, Some(port)) => Some(format!("Puerto {}", port)),
@@ -319,6 +431,19 @@ impl DeviceInfo {
         }
     }
 
+    /// Direcciones en las que conectar con el dispositivo en `port`, de la
+    /// preferida a la última, sin las de loopback
+    pub fn socket_addresses(&self, port: u16) -> Vec<SocketAddr> {
+        let addresses = match self.addresses.is_empty() {
+            true => self.ip_address.iter().collect::<Vec<_>>(),
+            false => self.addresses.iter().collect(),
+        };
+        addresses.into_iter()
+            .filter_map(|address| socket_address(address, port))
+            .filter(|address| !address.ip().is_loopback())
+            .collect()
+    }
+
     /// Obtener tiempo desde la última sincronización
     pub fn time_since_last_sync(&self) -> Option<chrono::Duration> {
         self.last_sync.map(|last_sync| Utc::now() - last_sync)
@@ -330,6 +455,44 @@ impl DeviceInfo {
     }
 }
 
+/// `address` en `port`. Acepta IPv6 con o sin corchetes y con la interfaz de
+/// una de enlace local (`fe80::1%3`).
+pub fn socket_address(address: &str, port: u16) -> Option<SocketAddr> {
+    let address = address.trim().trim_start_matches('[').trim_end_matches(']');
+    match address.contains(':') {
+        true => format!("[{}]:{}", address, port).parse().ok(),
+        false => format!("{}:{}", address, port).parse().ok(),
+    }
+}
+
+/// Nombre y tipo que el usuario le dio a este dispositivo. Lo que no eligió
+/// se toma del sistema: el nombre del equipo y el tipo que sugiere.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DeviceProfile {
+    pub name: Option<String>,
+    pub device_type: Option<DeviceType>,
+}
+
+impl DeviceProfile {
+    /// Largo máximo del nombre, en caracteres
+    pub const MAX_NAME_LENGTH: usize = 64;
+
+    /// Sin espacios alrededor del nombre y sin nombre si queda vacío. Falla si
+    /// el nombre es demasiado largo o tiene caracteres de control.
+    pub fn normalized(self) -> Result<Self> {
+        let name = self.name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        if let Some(name) = &name {
+            if name.chars().count() > Self::MAX_NAME_LENGTH || name.chars().any(char::is_control) {
+                return Err(anyhow::anyhow!("Nombre de dispositivo no válido: {:?}", name));
+            }
+        }
+        Ok(Self { name, device_type: self.device_type })
+    }
+}
+
 /// Información del dispositivo local
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalDeviceInfo {
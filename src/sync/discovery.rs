@@ -2,9 +2,18 @@
 //! 
 //! Este módulo implementa el descubrimiento automático de dispositivos
 //! Alohopass en la red local usando mDNS (multicast DNS)
-
+//!
+//! Un dispositivo deja de estar a la vista cuando retira su servicio o
+//! cuando pasa demasiado tiempo sin volver a anunciarse; en los dos casos se
+//! avisa con [`SyncEvent::DeviceDisconnected`].
+//!
+//! Cada dispositivo se anuncia con registros A y AAAA de todas sus
+//! interfaces, así que también se encuentra en redes solo IPv6.
+
+use crate::models::DeviceId;
 use crate::sync::{
-    DeviceInfo, DeviceType, SyncEvent,
+    tls_transport::TLS_PORT_PROPERTY,
+    DeviceCapabilities, DeviceInfo, DeviceProfile, DeviceType, SyncEvent,
 };
 use anyhow::{Result, anyhow};
 use std::{
@@ -13,26 +22,31 @@ use std::{
     time::Duration,
     sync::Arc,
 };
-use tokio::{
-    sync::{mpsc, RwLock},
-    time::interval,
-};
+use tokio::sync::{mpsc, RwLock};
+use if_addrs::IfAddr;
 use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
 use uuid::Uuid;
 use chrono::Utc;
 
-const SERVICE_TYPE: &str = "_alohopass._tcp";
+const SERVICE_TYPE: &str = "_alohopass._tcp.local.";
+
+/// Propiedad TXT con el id del dispositivo que se anuncia
+const DEVICE_ID_PROPERTY: &str = "device_id";
 
 /// Configuración del sistema de descubrimiento
 #[derive(Debug, Clone)]
 pub struct DiscoveryConfig {
+    /// Id de este dispositivo. Va en el anuncio para que los demás lo
+    /// reconozcan y para no descubrirse a sí mismo.
+    pub device_id: Option<DeviceId>,
     pub port: u16,
+    /// Puerto de las conexiones TLS directas; 0 si no se atienden
+    pub tls_port: u16,
     pub device_name: String,
     pub device_type: DeviceType,
     pub os: String,
     pub os_version: String,
     pub app_version: String,
-    pub announce_interval: u64,
     pub ttl: u32,
     pub use_mdns: bool,
 }
@@ -40,19 +54,118 @@ pub struct DiscoveryConfig {
 impl Default for DiscoveryConfig {
     fn default() -> Self {
         Self {
+            device_id: None,
             port: 0,
+            tls_port: 0,
             device_name: whoami::hostname(),
             device_type: detect_device_type(),
             os: whoami::platform().to_string(),
             os_version: "Unknown".to_string(),
             app_version: env!("CARGO_PKG_VERSION").to_string(),
-            announce_interval: 30,
             ttl: 120,
             use_mdns: true,
         }
     }
 }
 
+impl DiscoveryConfig {
+    /// Con el nombre y el tipo que eligió el usuario en vez de los del sistema
+    pub fn with_profile(mut self, profile: &DeviceProfile) -> Self {
+        if let Some(name) = &profile.name {
+            self.device_name = name.clone();
+        }
+        if let Some(device_type) = &profile.device_type {
+            self.device_type = device_type.clone();
+        }
+        self
+    }
+}
+
+/// Dirección IP con la que este equipo sale a la red local. Conectar un socket
+/// UDP no manda ningún paquete, solo elige la interfaz.
+pub fn local_ip() -> Option<IpAddr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(8, 8, 8, 8), 80)).ok()?;
+    socket.local_addr().ok().map(|address| address.ip())
+}
+
+/// Dirección de una interfaz de este equipo
+#[derive(Debug, Clone)]
+struct LocalAddress {
+    ip: IpAddr,
+    netmask: IpAddr,
+    /// Índice de la interfaz, el que va después de `%` en una IPv6 de enlace
+    /// local
+    index: Option<u32>,
+}
+
+impl LocalAddress {
+    /// Si `address` está en la misma subred que esta interfaz
+    fn same_network(&self, address: &IpAddr) -> bool {
+        match (self.ip, self.netmask, address) {
+            (IpAddr::V4(ip), IpAddr::V4(mask), IpAddr::V4(address)) => {
+                u32::from(ip) & u32::from(mask) == u32::from(*address) & u32::from(mask)
+            }
+            (IpAddr::V6(ip), IpAddr::V6(mask), IpAddr::V6(address)) => {
+                u128::from(ip) & u128::from(mask) == u128::from(*address) & u128::from(mask)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Direcciones de las interfaces de este equipo, sin las de loopback
+fn local_addresses() -> Vec<LocalAddress> {
+    let interfaces = if_addrs::get_if_addrs().unwrap_or_else(|e| {
+        log::warn!("No se pudieron leer las interfaces de red: {}", e);
+        Vec::new()
+    });
+    interfaces.into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .map(|interface| LocalAddress {
+            ip: interface.ip(),
+            netmask: match interface.addr {
+                IfAddr::V4(v4) => IpAddr::V4(v4.netmask),
+                IfAddr::V6(v6) => IpAddr::V6(v6.netmask),
+            },
+            index: interface.index,
+        })
+        .collect()
+}
+
+/// Si es una IPv6 de enlace local (`fe80::/10`)
+fn is_link_local_v6(address: &IpAddr) -> bool {
+    matches!(address, IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80)
+}
+
+/// Direcciones por las que conectar con un dispositivo que anunció
+/// `addresses`, de la preferida a la última: primero las de una subred de
+/// este equipo, IPv4 antes que IPv6, y después las demás. Una IPv6 de enlace
+/// local no dice por qué interfaz se llega, así que va al final una vez por
+/// cada interfaz de `local` con IPv6 de enlace local (`fe80::1%3`). Las de
+/// loopback nunca.
+fn ordered_addresses(addresses: &[IpAddr], local: &[LocalAddress]) -> Vec<String> {
+    let same_network = |address: &IpAddr| local.iter().any(|local| local.same_network(address));
+    let mut routable: Vec<IpAddr> = addresses.iter()
+        .filter(|address| !address.is_loopback() && !address.is_unspecified() && !is_link_local_v6(address))
+        .copied()
+        .collect();
+    routable.sort_by_key(|address| (!same_network(address), !address.is_ipv4(), *address));
+    routable.dedup();
+
+    let mut scopes: Vec<u32> = local.iter()
+        .filter(|local| is_link_local_v6(&local.ip))
+        .filter_map(|local| local.index)
+        .collect();
+    scopes.sort_unstable();
+    scopes.dedup();
+    let link_local = addresses.iter()
+        .filter(|address| is_link_local_v6(address))
+        .flat_map(|address| scopes.iter().map(move |scope| format!("{}%{}", address, scope)));
+
+    routable.iter().map(ToString::to_string).chain(link_local).collect()
+}
+
 /// Detectar el tipo de dispositivo basado en el hostname
 fn detect_device_type() -> DeviceType {
     let hostname = whoami::hostname().to_lowercase();
@@ -72,15 +185,30 @@ fn detect_device_type() -> DeviceType {
     }
 }
 
+/// Dispositivos descubiertos y el servicio mDNS con que se anunció cada uno
+#[derive(Default)]
+struct Discovered {
+    devices: HashMap<DeviceId, DeviceInfo>,
+    /// Nombre completo del servicio y dispositivo que lo anuncia
+    services: HashMap<String, DeviceId>,
+}
+
+impl Discovered {
+    /// Saca un dispositivo junto con sus servicios
+    fn remove(&mut self, device_id: DeviceId) -> Option<DeviceInfo> {
+        self.services.retain(|_, id| *id != device_id);
+        self.devices.remove(&device_id)
+    }
+}
+
 /// Sistema de descubrimiento automático de dispositivos
 pub struct DeviceDiscovery {
     config: DiscoveryConfig,
     mdns_daemon: Option<ServiceDaemon>,
     local_service: Option<ServiceInfo>,
-    discovered_devices: Arc<RwLock<HashMap<String, DeviceInfo>>>,
+    discovered: Arc<RwLock<Discovered>>,
     event_sender: mpsc::Sender<SyncEvent>,
     discovery_task: Option<tokio::task::JoinHandle<Result<(), anyhow::Error>>>,
-    announce_task: Option<tokio::task::JoinHandle<Result<(), anyhow::Error>>>,
     is_running: Arc<RwLock<bool>>,
 }
 
@@ -90,10 +218,9 @@ impl DeviceDiscovery {
             config,
             mdns_daemon: None,
             local_service: None,
-            discovered_devices: Arc::new(RwLock::new(HashMap::new())),
+            discovered: Arc::new(RwLock::new(Discovered::default())),
             event_sender,
             discovery_task: None,
-            announce_task: None,
             is_running: Arc::new(RwLock::new(false)),
         }
     }
@@ -107,9 +234,8 @@ impl DeviceDiscovery {
         // Inicializar mDNS
         self.init_mdns().await?;
 
-        // Iniciar tareas
+        // Iniciar la tarea de descubrimiento
         self.start_discovery_task().await?;
-        self.start_announce_task().await?;
 
         // Marcar como ejecutándose
         *self.is_running.write().await = true;
@@ -128,9 +254,6 @@ impl DeviceDiscovery {
         if let Some(task) = self.discovery_task.take() {
             task.abort();
         }
-        if let Some(task) = self.announce_task.take() {
-            task.abort();
-        }
 
         // Limpiar mDNS
         if let Some(daemon) = self.mdns_daemon.take() {
@@ -152,7 +275,10 @@ impl DeviceDiscovery {
         
         // Crear información del servicio
         let hostname = whoami::hostname();
-        let service_name = format!("{}-{}", hostname, Uuid::new_v4().to_string()[..8].to_string());
+        // Con el id el nombre es el mismo en cada arranque y el anuncio nuevo
+        // reemplaza al anterior
+        let suffix = self.config.device_id.map_or_else(Uuid::new_v4, |id| *id.as_uuid());
+        let service_name = format!("{}-{}", hostname, &suffix.to_string()[..8]);
         
         let mut properties = HashMap::new();
         properties.insert("device_type".to_string(), self.config.device_type.to_string());
@@ -160,15 +286,27 @@ impl DeviceDiscovery {
         properties.insert("os_version".to_string(), self.config.os_version.clone());
         properties.insert("app_version".to_string(), self.config.app_version.clone());
         properties.insert("device_name".to_string(), self.config.device_name.clone());
+        // La versión mínima que acepta, para saber antes de emparejarse si se
+        // va a poder sincronizar
+        properties.insert("min_app_version".to_string(), DeviceCapabilities::default().min_app_version);
+        if let Some(device_id) = self.config.device_id {
+            properties.insert(DEVICE_ID_PROPERTY.to_string(), device_id.to_string());
+        }
+        if self.config.tls_port != 0 {
+            properties.insert(TLS_PORT_PROPERTY.to_string(), self.config.tls_port.to_string());
+        }
 
+        // Sin direcciones fijas: el daemon anuncia las de cada interfaz, IPv4
+        // e IPv6, y las actualiza si cambian
         let service_info = ServiceInfo::new(
             SERVICE_TYPE,
             &service_name,
-            &hostname,
-            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            &format!("{}.local.", hostname),
+            (),
             self.config.port,
             properties,
-        )?;
+        )?
+        .enable_addr_auto();
 
         // Registrar el servicio
         daemon.register(service_info.clone())?;
@@ -191,7 +329,8 @@ impl DeviceDiscovery {
             .clone();
 
         let event_sender = self.event_sender.clone();
-        let discovered_devices = self.discovered_devices.clone();
+        let discovered = self.discovered.clone();
+        let local_device = self.config.device_id;
 
         let task = tokio::spawn(async move {
             let receiver = daemon.browse(SERVICE_TYPE)?;
@@ -201,14 +340,15 @@ impl DeviceDiscovery {
                     ServiceEvent::ServiceResolved(info) => {
                         if let Err(e) = Self::handle_service_resolved(
                             info,
+                            local_device,
                             &event_sender,
-                            &discovered_devices
+                            &discovered
                         ).await {
                             log::error!("Error al resolver servicio: {}", e);
                         }
                     }
-                    ServiceEvent::ServiceRemoved(_, _) => {
-                        // TODO: Implementar eliminación de servicios
+                    ServiceEvent::ServiceRemoved(_, fullname) => {
+                        Self::handle_service_removed(&fullname, &event_sender, &discovered).await;
                     }
                     _ => {}
                 }
@@ -220,43 +360,26 @@ impl DeviceDiscovery {
         Ok(())
     }
 
-    /// Iniciar la tarea de anuncio
-    async fn start_announce_task(&mut self) -> Result<()> {
-        if !self.config.use_mdns {
-            return Ok(());
-        }
-
-        let announce_interval = self.config.announce_interval;
-        let event_sender = self.event_sender.clone();
-        let discovered_devices = self.discovered_devices.clone();
-
-        let task = tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(announce_interval));
-            
-            loop {
-                interval.tick().await;
-                
-                // Enviar evento de heartbeat
-                if let Err(e) = event_sender.send(SyncEvent::Heartbeat).await {
-                    log::error!("Error enviando heartbeat: {}", e);
-                    break;
-                }
-            }
-            Ok::<(), anyhow::Error>(())
-        });
-
-        self.announce_task = Some(task);
-        Ok(())
-    }
-
-    /// Manejar servicio resuelto
+    /// Manejar servicio resuelto. Si el servicio o el id que anuncia ya se
+    /// conocían es un nuevo anuncio del mismo dispositivo, quizás por otra
+    /// interfaz: se actualizan sus datos y la última vez que se lo vio, sin
+    /// volver a avisar que se descubrió. El anuncio de `local_device` es el
+    /// de este mismo dispositivo y se ignora.
     async fn handle_service_resolved(
         info: ServiceInfo,
+        local_device: Option<DeviceId>,
         event_sender: &mpsc::Sender<SyncEvent>,
-        discovered_devices: &Arc<RwLock<HashMap<String, DeviceInfo>>>,
+        discovered: &Arc<RwLock<Discovered>>,
     ) -> Result<()> {
         let hostname = whoami::hostname();
         let properties = info.get_properties();
+        let announced_id = properties
+            .get_property_val_str(DEVICE_ID_PROPERTY)
+            .and_then(|id| id.parse::<DeviceId>().ok());
+        if announced_id.is_some() && announced_id == local_device {
+            log::debug!("Ignorado el anuncio de este mismo dispositivo: {}", info.get_fullname());
+            return Ok(());
+        }
         
         // Procesar propiedades TXT
         let device_type = properties
@@ -280,19 +403,52 @@ impl DeviceDiscovery {
             .get_property_val_str("device_name")
             .unwrap_or(&hostname);
 
-        let device_info = DeviceInfo::from_network(
+        let addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+        let addresses = ordered_addresses(&addresses, &local_addresses());
+        let Some(address) = addresses.first().cloned() else {
+            return Err(anyhow!("{} no anunció ninguna dirección a la que conectar", info.get_fullname()));
+        };
+
+        let mut device_info = DeviceInfo::from_network(
             device_name.to_string(),
             device_type,
             os.to_string(),
             os_version.to_string(),
             app_version.to_string(),
-            "127.0.0.1".to_string(), // IP por defecto, se actualizará cuando se conecte
-            0, // Puerto por defecto, se actualizará cuando se conecte
+            address,
+            info.get_port(),
         );
+        device_info.addresses = addresses;
+        // Sin ellas, de una versión anterior, se supone que pide lo mismo
+        // que esta
+        if let Some(min_app_version) = properties.get_property_val_str("min_app_version") {
+            device_info.capabilities.min_app_version = min_app_version.to_string();
+        }
+        if let Some(tls_port) = properties.get_property_val_str(TLS_PORT_PROPERTY).and_then(|port| port.parse::<u16>().ok()) {
+            device_info.metadata.insert(TLS_PORT_PROPERTY.to_string(), tls_port.to_string());
+        }
+
+        let mut discovered = discovered.write().await;
+        let fullname = info.get_fullname().to_string();
+        let known = discovered.services.get(&fullname).copied()
+            .or(announced_id)
+            .filter(|id| discovered.devices.contains_key(id));
+        if let Some(known) = known {
+            device_info.id = known;
+            log::debug!("{} volvió a anunciarse", device_info.name);
+            discovered.services.insert(fullname, known);
+            discovered.devices.insert(known, device_info);
+            return Ok(());
+        }
 
-        // Agregar dispositivo descubierto
-        let mut devices = discovered_devices.write().await;
-        devices.insert(device_info.id.clone(), device_info.clone());
+        // Agregar dispositivo descubierto. Sin id en el anuncio, de una
+        // versión anterior, cada servicio es un dispositivo.
+        if let Some(id) = announced_id {
+            device_info.id = id;
+        }
+        discovered.services.insert(fullname, device_info.id);
+        discovered.devices.insert(device_info.id, device_info.clone());
+        drop(discovered);
 
         // Enviar evento de dispositivo descubierto
         if let Err(e) = event_sender.send(SyncEvent::DeviceDiscovered(device_info)).await {
@@ -302,25 +458,56 @@ impl DeviceDiscovery {
         Ok(())
     }
 
+    /// Saca el dispositivo que retiró el servicio `fullname`, si no sigue
+    /// anunciándose con otro, y avisa que se desconectó
+    async fn handle_service_removed(
+        fullname: &str,
+        event_sender: &mpsc::Sender<SyncEvent>,
+        discovered: &Arc<RwLock<Discovered>>,
+    ) {
+        let removed = {
+            let mut discovered = discovered.write().await;
+            discovered.services.remove(fullname)
+                .filter(|id| !discovered.services.values().any(|other| other == id))
+                .and_then(|id| discovered.remove(id))
+        };
+        let Some(device) = removed else {
+            return;
+        };
+        log::info!("{} dejó de anunciarse en la red", device.name);
+        if let Err(e) = event_sender.send(SyncEvent::DeviceDisconnected(device)).await {
+            log::error!("Error enviando evento de dispositivo desconectado: {}", e);
+        }
+    }
+
     /// Obtener dispositivos descubiertos
     pub async fn get_discovered_devices(&self) -> Vec<DeviceInfo> {
-        let devices = self.discovered_devices.read().await;
-        devices.values().cloned().collect()
+        let discovered = self.discovered.read().await;
+        discovered.devices.values().cloned().collect()
     }
 
-    /// Limpiar dispositivos antiguos
+    /// Saca los dispositivos que no se volvieron a anunciar en `max_age`,
+    /// por ejemplo porque se fueron de la red sin retirar su servicio, y avisa
+    /// que se desconectaron
     pub async fn cleanup_old_devices(&self, max_age: Duration) -> Result<()> {
-        let mut devices = self.discovered_devices.write().await;
         let now = Utc::now();
-        
-        devices.retain(|_, device| {
-            if let Some(last_seen) = device.last_seen {
-                now.signed_duration_since(last_seen).num_seconds() < max_age.as_secs() as i64
-            } else {
-                true
+        let removed: Vec<DeviceInfo> = {
+            let mut discovered = self.discovered.write().await;
+            let stale: Vec<DeviceId> = discovered.devices.values()
+                .filter(|device| device.last_seen.is_some_and(|last_seen| {
+                    now.signed_duration_since(last_seen).num_seconds() >= max_age.as_secs() as i64
+                }))
+                .map(|device| device.id)
+                .collect();
+            stale.into_iter().filter_map(|id| discovered.remove(id)).collect()
+        };
+
+        for device in removed {
+            log::info!("{} no se anunció en {} segundos", device.name, max_age.as_secs());
+            if let Err(e) = self.event_sender.send(SyncEvent::DeviceDisconnected(device)).await {
+                log::error!("Error enviando evento de dispositivo desconectado: {}", e);
             }
-        });
-
+        }
         Ok(())
     }
 }
@@ -347,3 +534,119 @@ impl Drop for DeviceDiscovery {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str) -> ServiceInfo {
+        let properties = HashMap::from([("device_name".to_string(), name.to_string())]);
+        ServiceInfo::new(SERVICE_TYPE, name, "equipo.local.", "192.168.1.20", 7000, properties).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_devices_leave_when_removed_or_stale() {
+        let (sender, mut receiver) = mpsc::channel(10);
+        let discovery = DeviceDiscovery::new(DiscoveryConfig::default(), sender.clone());
+        let laptop = service("portatil");
+
+        // Un nuevo anuncio del mismo servicio solo actualiza cuándo se lo vio
+        DeviceDiscovery::handle_service_resolved(laptop.clone(), None, &sender, &discovery.discovered).await.unwrap();
+        let Some(SyncEvent::DeviceDiscovered(first)) = receiver.recv().await else {
+            panic!("Se esperaba el dispositivo descubierto");
+        };
+        DeviceDiscovery::handle_service_resolved(laptop.clone(), None, &sender, &discovery.discovered).await.unwrap();
+        assert!(receiver.try_recv().is_err());
+        let devices = discovery.get_discovered_devices().await;
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, first.id);
+        assert!(devices[0].last_seen >= first.last_seen);
+        assert_eq!((devices[0].ip_address.as_deref(), devices[0].port), (Some("192.168.1.20"), Some(7000)));
+
+        // Al retirar el servicio se va y se avisa una sola vez
+        DeviceDiscovery::handle_service_removed(laptop.get_fullname(), &sender, &discovery.discovered).await;
+        DeviceDiscovery::handle_service_removed(laptop.get_fullname(), &sender, &discovery.discovered).await;
+        assert!(matches!(receiver.recv().await, Some(SyncEvent::DeviceDisconnected(device)) if device.id == first.id));
+        assert!(receiver.try_recv().is_err());
+        assert!(discovery.get_discovered_devices().await.is_empty());
+
+        // El que deja de anunciarse sin retirar el servicio se va al limpiar
+        let phone = service("telefono");
+        DeviceDiscovery::handle_service_resolved(phone.clone(), None, &sender, &discovery.discovered).await.unwrap();
+        DeviceDiscovery::handle_service_resolved(laptop, None, &sender, &discovery.discovered).await.unwrap();
+        receiver.recv().await.unwrap();
+        receiver.recv().await.unwrap();
+        let stale = {
+            let mut discovered = discovery.discovered.write().await;
+            let id = discovered.services[phone.get_fullname()];
+            discovered.devices.get_mut(&id).unwrap().last_seen = Some(Utc::now() - chrono::Duration::minutes(10));
+            id
+        };
+        discovery.cleanup_old_devices(Duration::from_secs(300)).await.unwrap();
+        assert!(matches!(receiver.recv().await, Some(SyncEvent::DeviceDisconnected(device)) if device.id == stale));
+        let devices = discovery.get_discovered_devices().await;
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "portatil");
+        assert!(!discovery.discovered.read().await.services.contains_key(phone.get_fullname()));
+    }
+
+    #[test]
+    fn test_orders_addresses_and_scopes_link_local() {
+        let parse = |addresses: &[&str]| addresses.iter().map(|address| address.parse().unwrap()).collect::<Vec<IpAddr>>();
+        let interface = |ip: &str, netmask: &str, index: u32| LocalAddress {
+            ip: ip.parse().unwrap(),
+            netmask: netmask.parse().unwrap(),
+            index: Some(index),
+        };
+        let dual_stack = [
+            interface("192.168.1.5", "255.255.255.0", 2),
+            interface("fd00::5", "ffff:ffff:ffff:ffff::", 2),
+            interface("fe80::5", "ffff:ffff:ffff:ffff::", 2),
+            interface("fe80::6", "ffff:ffff:ffff:ffff::", 3),
+        ];
+
+        let announced = parse(&["fe80::20", "2001:db8::20", "fd00::20", "10.0.0.20", "192.168.1.20", "127.0.0.1", "::1"]);
+        assert_eq!(ordered_addresses(&announced, &dual_stack), [
+            "192.168.1.20", "fd00::20", "10.0.0.20", "2001:db8::20", "fe80::20%2", "fe80::20%3",
+        ]);
+
+        // En una red solo IPv6 quedan las IPv6, con la interfaz en las de
+        // enlace local
+        let ipv6_only = &dual_stack[1..3];
+        assert_eq!(ordered_addresses(&parse(&["fe80::20", "fd00::20"]), ipv6_only), ["fd00::20", "fe80::20%2"]);
+        assert!(ordered_addresses(&parse(&["fe80::20", "::1", "127.0.0.1"]), &[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ignores_itself_and_merges_interfaces() {
+        let (sender, mut receiver) = mpsc::channel(10);
+        let discovery = DeviceDiscovery::new(DiscoveryConfig::default(), sender.clone());
+        let (local, laptop) = (DeviceId::new(), DeviceId::new());
+        let announced = |name: &str, id: DeviceId, address: &str| {
+            let properties = HashMap::from([(DEVICE_ID_PROPERTY.to_string(), id.to_string())]);
+            ServiceInfo::new(SERVICE_TYPE, name, "equipo.local.", address, 7000, properties).unwrap()
+        };
+
+        // El anuncio propio no aparece como otro dispositivo
+        let own = announced("propio", local, "192.168.1.5");
+        DeviceDiscovery::handle_service_resolved(own, Some(local), &sender, &discovery.discovered).await.unwrap();
+        assert!(discovery.get_discovered_devices().await.is_empty());
+
+        // El mismo dispositivo por dos interfaces, o con un servicio viejo
+        // todavía vivo, es uno solo y con su id
+        let wifi = announced("portatil-wifi", laptop, "192.168.1.20");
+        let cable = announced("portatil-cable", laptop, "10.0.0.20");
+        DeviceDiscovery::handle_service_resolved(wifi.clone(), Some(local), &sender, &discovery.discovered).await.unwrap();
+        DeviceDiscovery::handle_service_resolved(cable, Some(local), &sender, &discovery.discovered).await.unwrap();
+        assert!(matches!(receiver.recv().await, Some(SyncEvent::DeviceDiscovered(device)) if device.id == laptop));
+        assert!(receiver.try_recv().is_err());
+        let devices = discovery.get_discovered_devices().await;
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, laptop);
+
+        // Mientras siga anunciándose por una interfaz no se va
+        DeviceDiscovery::handle_service_removed(wifi.get_fullname(), &sender, &discovery.discovered).await;
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(discovery.get_discovered_devices().await.len(), 1);
+    }
+}
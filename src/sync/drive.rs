@@ -0,0 +1,171 @@
+//! Buzón de sincronización en Google Drive
+//!
+//! Los lotes van a la carpeta oculta de la aplicación (`appDataFolder`), que
+//! el usuario no ve en su Drive y a la que ninguna otra aplicación tiene
+//! acceso. Drive identifica los archivos por id, así que cada lote se busca por
+//! su nombre, `<destino>-<origen>.sealed`. Al recogerlo se borra solo si sigue
+//! en la misma versión.
+
+use crate::models::DeviceId;
+use crate::sync::oauth::{OAuthProvider, OAuthSession};
+use crate::sync::smart_sync::SyncMailbox;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+
+const FILES_URL: &str = "https://www.googleapis.com/drive/v3/files";
+const UPLOAD_URL: &str = "https://www.googleapis.com/upload/drive/v3/files";
+
+/// Separador de las partes al crear un archivo
+const MULTIPART_BOUNDARY: &str = "alohopass-batch";
+
+#[derive(Debug, Deserialize)]
+struct FileList {
+    files: Vec<DriveFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DriveFile {
+    id: String,
+    /// Aumenta con cada cambio del archivo
+    version: String,
+}
+
+/// Buzón en la carpeta de la aplicación en Google Drive
+pub struct DriveMailbox {
+    session: OAuthSession,
+}
+
+impl DriveMailbox {
+    pub fn new(refresh_token: String) -> Result<Self> {
+        Ok(Self { session: OAuthSession::new(OAuthProvider::GoogleDrive, refresh_token)? })
+    }
+
+    /// Lote que `from` dejó para `to`, si hay uno
+    async fn find(&self, from: DeviceId, to: DeviceId) -> Result<Option<DriveFile>> {
+        let query = format!("name = '{}' and trashed = false", batch_name(from, to));
+        let response = self.session.send(|token| {
+            self.session.client().get(FILES_URL)
+                .bearer_auth(token)
+                .query(&[("spaces", "appDataFolder"), ("q", query.as_str()), ("fields", "files(id,version)")])
+        }).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Google Drive respondió {} al buscar el lote de {} para {}", response.status(), from, to));
+        }
+        let list: FileList = serde_json::from_slice(&response.bytes().await?)?;
+        Ok(list.files.into_iter().next())
+    }
+
+    /// Versión actual del archivo; `None` si ya no existe
+    async fn version(&self, id: &str) -> Result<Option<String>> {
+        let response = self.session.send(|token| {
+            self.session.client().get(format!("{}/{}", FILES_URL, id))
+                .bearer_auth(token)
+                .query(&[("fields", "id,version")])
+        }).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Google Drive respondió {} al consultar un lote", response.status()));
+        }
+        let file: DriveFile = serde_json::from_slice(&response.bytes().await?)?;
+        Ok(Some(file.version))
+    }
+}
+
+/// Nombre del lote que `from` deja para `to`
+fn batch_name(from: DeviceId, to: DeviceId) -> String {
+    format!("{}-{}.sealed", to, from)
+}
+
+/// Cuerpo `multipart/related` para crear un archivo con sus metadatos
+fn multipart_body(metadata: &serde_json::Value, content: &[u8]) -> Vec<u8> {
+    let mut body = format!(
+        "--{0}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{1}\r\n--{0}\r\nContent-Type: application/octet-stream\r\n\r\n",
+        MULTIPART_BOUNDARY, metadata
+    ).into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{}--", MULTIPART_BOUNDARY).as_bytes());
+    body
+}
+
+#[async_trait]
+impl SyncMailbox for DriveMailbox {
+    async fn put(&self, from: DeviceId, to: DeviceId, sealed: Vec<u8>) -> Result<()> {
+        let response = match self.find(from, to).await? {
+            Some(file) => self.session.send(|token| {
+                self.session.client().patch(format!("{}/{}", UPLOAD_URL, file.id))
+                    .bearer_auth(token)
+                    .query(&[("uploadType", "media")])
+                    .header("Content-Type", "application/octet-stream")
+                    .body(sealed.clone())
+            }).await?,
+            None => {
+                let metadata = json!({ "name": batch_name(from, to), "parents": ["appDataFolder"] });
+                let body = multipart_body(&metadata, &sealed);
+                self.session.send(|token| {
+                    self.session.client().post(UPLOAD_URL)
+                        .bearer_auth(token)
+                        .query(&[("uploadType", "multipart")])
+                        .header("Content-Type", format!("multipart/related; boundary={}", MULTIPART_BOUNDARY))
+                        .body(body.clone())
+                }).await?
+            }
+        };
+        if !response.status().is_success() {
+            return Err(anyhow!("Google Drive respondió {} al dejar el lote para {}", response.status(), to));
+        }
+        Ok(())
+    }
+
+    async fn take(&self, from: DeviceId, to: DeviceId) -> Result<Option<Vec<u8>>> {
+        let Some(file) = self.find(from, to).await? else {
+            return Ok(None);
+        };
+        let response = self.session.send(|token| {
+            self.session.client().get(format!("{}/{}", FILES_URL, file.id))
+                .bearer_auth(token)
+                .query(&[("alt", "media")])
+        }).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Google Drive respondió {} al recoger el lote de {}", response.status(), from));
+        }
+        let sealed = response.bytes().await?.to_vec();
+
+        // Drive no borra de forma condicional: se comprueba la versión justo
+        // antes, y si cambió el lote nuevo se queda
+        if self.version(&file.id).await?.as_deref() == Some(file.version.as_str()) {
+            let status = self.session.send(|token| {
+                self.session.client().delete(format!("{}/{}", FILES_URL, file.id)).bearer_auth(token)
+            }).await?.status();
+            if !status.is_success() && status != StatusCode::NOT_FOUND {
+                return Err(anyhow!("Google Drive respondió {} al sacar el lote de {}", status, from));
+            }
+        }
+        Ok(Some(sealed))
+    }
+
+    async fn is_waiting(&self, from: DeviceId, to: DeviceId) -> Result<bool> {
+        Ok(self.find(from, to).await?.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_the_upload_body() {
+        let body = multipart_body(&json!({ "name": "a.sealed" }), b"lote");
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("--alohopass-batch\r\nContent-Type: application/json"));
+        assert!(body.contains("{\"name\":\"a.sealed\"}\r\n--alohopass-batch\r\n"));
+        assert!(body.ends_with("\r\n\r\nlote\r\n--alohopass-batch--"));
+    }
+}
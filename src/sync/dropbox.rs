@@ -0,0 +1,107 @@
+//! Buzón de sincronización en Dropbox
+//!
+//! La aplicación tiene acceso solo a su carpeta (`Aplicaciones/Alohopass`) y
+//! los lotes quedan en `<destino>/<origen>.sealed` dentro de ella. Al
+//! recogerlos se borran solo si siguen en la misma revisión, así no se pierde
+//! el que el otro dispositivo haya dejado mientras tanto.
+
+use crate::models::DeviceId;
+use crate::sync::oauth::{OAuthProvider, OAuthSession};
+use crate::sync::smart_sync::SyncMailbox;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+
+const API_URL: &str = "https://api.dropboxapi.com/2";
+const CONTENT_URL: &str = "https://content.dropboxapi.com/2";
+
+/// Metadatos que devuelve Dropbox al bajar un archivo
+#[derive(Debug, Deserialize)]
+struct FileMetadata {
+    rev: String,
+}
+
+/// Buzón en la carpeta de la aplicación en Dropbox
+pub struct DropboxMailbox {
+    session: OAuthSession,
+}
+
+impl DropboxMailbox {
+    pub fn new(refresh_token: String) -> Result<Self> {
+        Ok(Self { session: OAuthSession::new(OAuthProvider::Dropbox, refresh_token)? })
+    }
+
+    /// Llamada a la API con argumentos JSON; 409 es un error de la ruta, como
+    /// que no exista
+    async fn call(&self, endpoint: &str, args: serde_json::Value) -> Result<StatusCode> {
+        let body = serde_json::to_vec(&args)?;
+        let response = self.session.send(|token| {
+            self.session.client().post(format!("{}/{}", API_URL, endpoint))
+                .bearer_auth(token)
+                .header("Content-Type", "application/json")
+                .body(body.clone())
+        }).await?;
+        Ok(response.status())
+    }
+}
+
+/// Ruta del lote que `from` deja para `to`
+fn batch_path(from: DeviceId, to: DeviceId) -> String {
+    format!("/{}/{}.sealed", to, from)
+}
+
+#[async_trait]
+impl SyncMailbox for DropboxMailbox {
+    async fn put(&self, from: DeviceId, to: DeviceId, sealed: Vec<u8>) -> Result<()> {
+        let args = json!({ "path": batch_path(from, to), "mode": "overwrite", "mute": true }).to_string();
+        let response = self.session.send(|token| {
+            self.session.client().post(format!("{}/files/upload", CONTENT_URL))
+                .bearer_auth(token)
+                .header("Dropbox-API-Arg", args.as_str())
+                .header("Content-Type", "application/octet-stream")
+                .body(sealed.clone())
+        }).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Dropbox respondió {} al dejar el lote para {}", response.status(), to));
+        }
+        Ok(())
+    }
+
+    async fn take(&self, from: DeviceId, to: DeviceId) -> Result<Option<Vec<u8>>> {
+        let path = batch_path(from, to);
+        let args = json!({ "path": path }).to_string();
+        let response = self.session.send(|token| {
+            self.session.client().post(format!("{}/files/download", CONTENT_URL))
+                .bearer_auth(token)
+                .header("Dropbox-API-Arg", args.as_str())
+        }).await?;
+        if response.status() == StatusCode::CONFLICT {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Dropbox respondió {} al recoger el lote de {}", response.status(), from));
+        }
+        let metadata: FileMetadata = response.headers().get("Dropbox-API-Result")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| serde_json::from_str(value).ok())
+            .ok_or_else(|| anyhow!("Dropbox no devolvió la revisión del lote de {}", from))?;
+        let sealed = response.bytes().await?.to_vec();
+
+        // Si cambió de revisión, el lote nuevo se queda (409)
+        let status = self.call("files/delete_v2", json!({ "path": path, "parent_rev": metadata.rev })).await?;
+        if !status.is_success() && status != StatusCode::CONFLICT {
+            return Err(anyhow!("Dropbox respondió {} al sacar el lote de {}", status, from));
+        }
+        Ok(Some(sealed))
+    }
+
+    async fn is_waiting(&self, from: DeviceId, to: DeviceId) -> Result<bool> {
+        match self.call("files/get_metadata", json!({ "path": batch_path(from, to) })).await? {
+            StatusCode::CONFLICT => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(anyhow!("Dropbox respondió {} al buscar el lote para {}", status, to)),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Envío de una sola entrada a otro dispositivo
+//!
+//! A un dispositivo emparejado se le puede mandar una entrada suelta sin
+//! sincronizar nada más. Va por la misma conexión autenticada y cifrada que los
+//! lotes, detrás de [`ENTRY_OFFER`] para que no se confunda con uno.
+//!
+//! Quien la recibe no la guarda enseguida: queda en su [`EntryInbox`] hasta que
+//! el usuario la acepta o la rechaza. Al aceptarla entra en la bóveda como una
+//! entrada nueva, sin relación con la del otro dispositivo, así que los cambios
+//! que se le hagan después no viajan de vuelta. Las que nadie contesta caducan
+//! a los [`INCOMING_ENTRY_TIMEOUT`].
+
+use crate::models::{DeviceId, PasswordEntry};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Comienzo de un mensaje con una entrada; lo sigue la entrada en JSON. Un lote
+/// sellado empieza con un nonce al azar, que no va a coincidir.
+pub const ENTRY_OFFER: &[u8] = b"alohopass-entry\0";
+
+/// Respuesta de quien recibió la entrada y la dejó esperando al usuario
+pub const ENTRY_RECEIVED: &[u8] = b"alohopass-entry-received";
+
+/// Lo que espera una entrada recibida a que el usuario la acepte
+pub const INCOMING_ENTRY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Entradas recibidas que pueden esperar a la vez; las siguientes se rechazan
+const MAX_INCOMING_ENTRIES: usize = 20;
+
+/// Mensaje que lleva `entry`
+pub fn encode_offer(entry: &PasswordEntry) -> Result<Vec<u8>> {
+    Ok([ENTRY_OFFER, &serde_json::to_vec(entry)?].concat())
+}
+
+/// Entrada que lleva `request`; `None` si no es un mensaje de
+/// [`encode_offer`]
+pub fn decode_offer(request: &[u8]) -> Option<Result<PasswordEntry>> {
+    let entry = request.strip_prefix(ENTRY_OFFER)?;
+    Some(serde_json::from_slice(entry).map_err(|e| anyhow!("Entrada recibida inválida: {}", e)))
+}
+
+/// Entrada que mandó otro dispositivo y espera que el usuario la acepte. La
+/// interfaz solo ve lo necesario para decidir, no la contraseña.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncomingEntry {
+    pub id: Uuid,
+    pub from: DeviceId,
+    /// Nombre del dispositivo que la mandó, si está a la vista
+    pub device_name: Option<String>,
+    pub title: String,
+    pub username: String,
+    pub url: Option<String>,
+    pub received_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub entry: PasswordEntry,
+    #[serde(skip)]
+    received: Instant,
+}
+
+impl IncomingEntry {
+    pub fn new(from: DeviceId, device_name: Option<String>, entry: PasswordEntry) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            from,
+            device_name,
+            title: entry.title.clone(),
+            username: entry.username.clone(),
+            url: entry.url.clone().filter(|url| !url.is_empty()),
+            received_at: Utc::now(),
+            entry,
+            received: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.received.elapsed() > INCOMING_ENTRY_TIMEOUT
+    }
+}
+
+/// Entradas recibidas que esperan al usuario
+#[derive(Debug, Default)]
+pub struct EntryInbox {
+    entries: HashMap<Uuid, IncomingEntry>,
+}
+
+impl EntryInbox {
+    /// Deja `entry` esperando. Falla si ya esperan demasiadas.
+    pub fn receive(&mut self, entry: IncomingEntry) -> Result<()> {
+        self.entries.retain(|_, entry| !entry.is_expired());
+        if self.entries.len() >= MAX_INCOMING_ENTRIES {
+            return Err(anyhow!("Ya hay {} entradas recibidas sin contestar", MAX_INCOMING_ENTRIES));
+        }
+        self.entries.insert(entry.id, entry);
+        Ok(())
+    }
+
+    /// Las que siguen esperando, de la más antigua a la más reciente
+    pub fn pending(&mut self) -> Vec<IncomingEntry> {
+        self.entries.retain(|_, entry| !entry.is_expired());
+        let mut pending: Vec<IncomingEntry> = self.entries.values().cloned().collect();
+        pending.sort_by_key(|entry| entry.received_at);
+        pending
+    }
+
+    /// Saca una entrada para aceptarla o rechazarla; `None` si no está o ya
+    /// caducó
+    pub fn take(&mut self, id: Uuid) -> Option<IncomingEntry> {
+        self.entries.remove(&id).filter(|entry| !entry.is_expired())
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str) -> PasswordEntry {
+        PasswordEntry { url: Some(String::new()), ..PasswordEntry::sample(title) }
+    }
+
+    #[test]
+    fn test_offers_wait_for_the_user() {
+        let offer = encode_offer(&entry("Banco")).unwrap();
+        let received = decode_offer(&offer).unwrap().unwrap();
+        assert_eq!((received.title.as_str(), received.password.as_str()), ("Banco", "secreta"));
+        assert!(decode_offer(b"ping").is_none());
+        assert!(decode_offer(&[ENTRY_OFFER, b"{"].concat()).unwrap().is_err());
+
+        let mut inbox = EntryInbox::default();
+        let incoming = IncomingEntry::new(DeviceId::new(), Some("Teléfono".to_string()), received);
+        assert_eq!(incoming.url, None);
+        // La interfaz no ve la contraseña
+        assert!(!serde_json::to_string(&incoming).unwrap().contains("secreta"));
+        let id = incoming.id;
+        inbox.receive(incoming).unwrap();
+        assert_eq!(inbox.pending().len(), 1);
+        assert_eq!(inbox.take(id).map(|incoming| incoming.entry.title), Some("Banco".to_string()));
+        assert!(inbox.take(id).is_none());
+
+        for index in 0..MAX_INCOMING_ENTRIES {
+            inbox.receive(IncomingEntry::new(DeviceId::new(), None, entry(&index.to_string()))).unwrap();
+        }
+        assert!(inbox.receive(IncomingEntry::new(DeviceId::new(), None, entry("otra"))).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Mensajes en trozos por el canal de datos
+//!
+//! Un canal de datos WebRTC no garantiza mensajes de más de 16 KiB entre
+//! implementaciones, y un lote de sincronización puede ocupar varios MB. Cada
+//! mensaje se parte en trozos numerados que llevan en la cabecera:
+//!
+//! - el tipo del mensaje (pedido o respuesta)
+//! - el número del mensaje, para no mezclar los que se mandan a la vez
+//! - la posición del trozo y cuántos son
+//! - el SHA-256 del mensaje entero, que se comprueba al rearmarlo
+//!
+//! El canal es ordenado, así que los trozos de un mensaje llegan en orden;
+//! entre mensajes distintos pueden intercalarse.
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Tamaño máximo de cada trozo, cabecera incluida
+pub const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Tipo, número, posición, cantidad y SHA-256
+const HEADER_SIZE: usize = 1 + 4 + 4 + 4 + 32;
+
+/// Lo que cabe de mensaje en cada trozo
+const CHUNK_BODY: usize = CHUNK_SIZE - HEADER_SIZE;
+
+/// Tamaño máximo de un mensaje rearmado
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Mensajes a medio llegar al mismo tiempo
+const MAX_PENDING_MESSAGES: usize = 8;
+
+/// Tipo de mensaje
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// Lote que hay que responder
+    Request = 0,
+    /// Respuesta al último lote que mandó este extremo
+    Reply = 1,
+}
+
+impl MessageKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Request),
+            1 => Some(Self::Reply),
+            _ => None,
+        }
+    }
+}
+
+/// Parte `message` en trozos de como mucho [`CHUNK_SIZE`] bytes. Un mensaje
+/// vacío es un solo trozo sin contenido.
+pub fn split(kind: MessageKind, message_id: u32, message: &[u8]) -> Result<Vec<Vec<u8>>> {
+    if message.len() > MAX_MESSAGE_SIZE {
+        return Err(anyhow!("El mensaje ocupa {} bytes; el máximo es {}", message.len(), MAX_MESSAGE_SIZE));
+    }
+    let checksum = Sha256::digest(message);
+    let bodies: Vec<&[u8]> = match message.is_empty() {
+        true => vec![&[]],
+        false => message.chunks(CHUNK_BODY).collect(),
+    };
+    let count = bodies.len() as u32;
+
+    Ok(bodies.into_iter().enumerate().map(|(index, body)| {
+        let mut chunk = Vec::with_capacity(HEADER_SIZE + body.len());
+        chunk.push(kind as u8);
+        chunk.extend_from_slice(&message_id.to_be_bytes());
+        chunk.extend_from_slice(&(index as u32).to_be_bytes());
+        chunk.extend_from_slice(&count.to_be_bytes());
+        chunk.extend_from_slice(&checksum);
+        chunk.extend_from_slice(body);
+        chunk
+    }).collect())
+}
+
+/// Mensaje del que todavía faltan trozos
+struct Partial {
+    kind: MessageKind,
+    count: u32,
+    checksum: [u8; 32],
+    data: Vec<u8>,
+}
+
+/// Rearma los mensajes a medida que llegan sus trozos
+#[derive(Default)]
+pub struct Reassembler {
+    pending: HashMap<u32, Partial>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Agrega un trozo y devuelve el mensaje si era el último. Un trozo fuera
+    /// de orden o un mensaje que no coincide con su SHA-256 descartan el
+    /// mensaje entero.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Option<(MessageKind, Vec<u8>)>> {
+        if chunk.len() < HEADER_SIZE {
+            return Err(anyhow!("Trozo de {} bytes, más corto que la cabecera", chunk.len()));
+        }
+        let (header, body) = chunk.split_at(HEADER_SIZE);
+        let kind = MessageKind::from_byte(header[0])
+            .ok_or_else(|| anyhow!("Tipo de mensaje desconocido: {}", header[0]))?;
+        let message_id = u32::from_be_bytes(header[1..5].try_into()?);
+        let index = u32::from_be_bytes(header[5..9].try_into()?);
+        let count = u32::from_be_bytes(header[9..13].try_into()?);
+        let checksum: [u8; 32] = header[13..].try_into()?;
+
+        if count == 0 || count as usize > MAX_MESSAGE_SIZE / CHUNK_BODY + 1 {
+            return Err(anyhow!("El mensaje {} dice tener {} trozos", message_id, count));
+        }
+
+        if index == 0 {
+            if self.pending.len() >= MAX_PENDING_MESSAGES && !self.pending.contains_key(&message_id) {
+                return Err(anyhow!("Demasiados mensajes a medio llegar"));
+            }
+            self.pending.insert(message_id, Partial { kind, count, checksum, data: Vec::new() });
+        }
+        let Some(partial) = self.pending.get_mut(&message_id) else {
+            return Err(anyhow!("Trozo {} del mensaje {} sin el principio", index, message_id));
+        };
+        let expected = partial.data.len().div_ceil(CHUNK_BODY) as u32;
+        if partial.kind != kind || partial.count != count || partial.checksum != checksum || index != expected {
+            self.pending.remove(&message_id);
+            return Err(anyhow!("Trozo {} del mensaje {} fuera de secuencia", index, message_id));
+        }
+        if index + 1 < count && body.len() != CHUNK_BODY {
+            self.pending.remove(&message_id);
+            return Err(anyhow!("Trozo {} del mensaje {} incompleto", index, message_id));
+        }
+        partial.data.extend_from_slice(body);
+        if index + 1 < count {
+            return Ok(None);
+        }
+
+        let partial = self.pending.remove(&message_id)
+            .ok_or_else(|| anyhow!("Mensaje {} desaparecido", message_id))?;
+        if Sha256::digest(&partial.data).as_slice() != partial.checksum {
+            return Err(anyhow!("El mensaje {} no coincide con su SHA-256", message_id));
+        }
+        Ok(Some((partial.kind, partial.data)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reassembles_interleaved_messages() {
+        let big: Vec<u8> = (0..CHUNK_BODY * 3 + 17).map(|i| i as u8).collect();
+        let request = split(MessageKind::Request, 1, &big).unwrap();
+        let reply = split(MessageKind::Reply, 2, b"ok").unwrap();
+        let empty = split(MessageKind::Reply, 3, &[]).unwrap();
+        assert_eq!(request.len(), 4);
+        assert!(request.iter().all(|chunk| chunk.len() <= CHUNK_SIZE));
+
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler.push(&request[0]).unwrap().is_none());
+        assert!(reassembler.push(&request[1]).unwrap().is_none());
+        assert_eq!(reassembler.push(&reply[0]).unwrap(), Some((MessageKind::Reply, b"ok".to_vec())));
+        assert_eq!(reassembler.push(&empty[0]).unwrap(), Some((MessageKind::Reply, Vec::new())));
+        assert!(reassembler.push(&request[2]).unwrap().is_none());
+        assert_eq!(reassembler.push(&request[3]).unwrap(), Some((MessageKind::Request, big)));
+    }
+
+    #[test]
+    fn test_rejects_corrupt_or_out_of_order_chunks() {
+        let message = vec![7u8; CHUNK_BODY + 1];
+        let chunks = split(MessageKind::Request, 9, &message).unwrap();
+
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler.push(&chunks[1]).is_err());
+
+        let mut corrupt = chunks[1].clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        reassembler.push(&chunks[0]).unwrap();
+        assert!(reassembler.push(&corrupt).is_err());
+
+        reassembler.push(&chunks[0]).unwrap();
+        assert!(reassembler.push(&chunks[0][..HEADER_SIZE - 1]).is_err());
+        assert_eq!(reassembler.push(&chunks[1]).unwrap(), Some((MessageKind::Request, message)));
+    }
+}
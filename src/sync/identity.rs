@@ -0,0 +1,192 @@
+//! Identidad de este dispositivo
+//!
+//! Cada dispositivo tiene un certificado propio, con su par de claves, que se
+//! crea una vez y no cambia. Al emparejarse, los dispositivos se pasan la
+//! huella del certificado (su SHA-256, la misma que WebRTC publica en la SDP)
+//! y cada uno guarda la del otro. En las conexiones siguientes el otro
+//! dispositivo tiene que presentar ese mismo certificado: si la huella
+//! cambió, la conexión se rechaza.
+//!
+//! De la clave privada del certificado sale también la clave estática X25519
+//! con la que el dispositivo se autentica en el saludo Noise de cada conexión
+//! (ver [`crate::sync::noise`]). Su parte pública viaja y se fija junto con la
+//! huella al emparejarse.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime};
+use webrtc::dtls::crypto::Certificate;
+use webrtc::peer_connection::certificate::RTCCertificate;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Nombre que va en el certificado; no se usa para verificar nada
+const CERTIFICATE_NAME: &str = "alohopass";
+/// Validez del certificado. Tiene que durar tanto como el emparejamiento.
+const CERTIFICATE_LIFETIME: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+const STATIC_KEY_INFO: &[u8] = b"alohopass-noise-static-key-v1";
+
+/// Certificado con el que este dispositivo se presenta a los demás
+#[derive(Clone)]
+pub struct DeviceIdentity {
+    certificate: RTCCertificate,
+    fingerprint: [u8; 32],
+    static_secret: StaticSecret,
+}
+
+/// Lo que un dispositivo fija del otro al emparejarse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicIdentity {
+    /// SHA-256 del certificado
+    pub fingerprint: [u8; 32],
+    /// Clave pública estática X25519 del saludo Noise
+    pub static_key: [u8; 32],
+}
+
+impl PublicIdentity {
+    /// Lee la huella y la clave estática guardadas en hexadecimal
+    pub fn from_hex(fingerprint: &str, static_key: &str) -> Option<Self> {
+        Some(Self {
+            fingerprint: parse_fingerprint(fingerprint)?,
+            static_key: hex::decode(static_key).ok()?.try_into().ok()?,
+        })
+    }
+}
+
+impl DeviceIdentity {
+    /// Crea una identidad nueva
+    pub fn generate() -> Result<Self> {
+        let certificate = Certificate::generate_self_signed(vec![CERTIFICATE_NAME.to_string()])?;
+        Self::new(RTCCertificate::from_existing(certificate, SystemTime::now() + CERTIFICATE_LIFETIME))
+    }
+
+    /// Lee una identidad guardada con [`DeviceIdentity::to_pem`]
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        Self::new(RTCCertificate::from_pem(pem)?)
+    }
+
+    /// El certificado y su clave privada en PEM, para guardarlos
+    pub fn to_pem(&self) -> String {
+        self.certificate.serialize_pem()
+    }
+
+    fn new(certificate: RTCCertificate) -> Result<Self> {
+        let fingerprint = certificate.get_fingerprints().into_iter()
+            .find(|fingerprint| fingerprint.algorithm == "sha-256")
+            .and_then(|fingerprint| parse_fingerprint(&fingerprint.value))
+            .ok_or_else(|| anyhow!("El certificado del dispositivo no tiene huella SHA-256"))?;
+        // La clave estática sale de la privada del certificado, así no hay
+        // que guardar nada más y las identidades que ya existían la tienen
+        let (_, key) = Self::der(&certificate)?;
+        let mut static_secret = [0u8; 32];
+        Hkdf::<Sha256>::new(None, &key)
+            .expand(STATIC_KEY_INFO, &mut static_secret)
+            .map_err(|e| anyhow!("Error al derivar la clave estática: {}", e))?;
+        Ok(Self { certificate, fingerprint, static_secret: StaticSecret::from(static_secret) })
+    }
+
+    /// SHA-256 del certificado, la huella que los demás dispositivos fijan
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprint
+    }
+
+    /// Clave estática para el saludo Noise
+    pub fn static_secret(&self) -> &StaticSecret {
+        &self.static_secret
+    }
+
+    /// La huella y la clave estática pública, lo que el otro dispositivo
+    /// fija al emparejarse
+    pub fn public(&self) -> PublicIdentity {
+        PublicIdentity {
+            fingerprint: self.fingerprint,
+            static_key: PublicKey::from(&self.static_secret).to_bytes(),
+        }
+    }
+
+    /// Certificado para la conexión WebRTC
+    pub fn certificate(&self) -> RTCCertificate {
+        self.certificate.clone()
+    }
+
+    /// El certificado y su clave privada (PKCS#8) en DER, para presentarse
+    /// por TLS
+    pub fn to_der(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        Self::der(&self.certificate)
+    }
+
+    fn der(certificate: &RTCCertificate) -> Result<(Vec<u8>, Vec<u8>)> {
+        let pem = certificate.serialize_pem();
+        Ok((pem_block(&pem, "CERTIFICATE")?, pem_block(&pem, "PRIVATE_KEY")?))
+    }
+}
+
+/// Contenido del primer bloque `tag` de un PEM
+fn pem_block(pem: &str, tag: &str) -> Result<Vec<u8>> {
+    let begin = format!("-----BEGIN {}-----", tag);
+    let end = format!("-----END {}-----", tag);
+    let body = pem.split_once(&begin)
+        .and_then(|(_, rest)| rest.split_once(&end))
+        .map(|(body, _)| body)
+        .ok_or_else(|| anyhow!("El certificado del dispositivo no tiene el bloque {}", tag))?;
+    let body: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    Ok(STANDARD.decode(body)?)
+}
+
+/// Huella SHA-256 en el formato de la SDP (`AB:CD:…`) o en hexadecimal sin
+/// separadores
+pub fn parse_fingerprint(value: &str) -> Option<[u8; 32]> {
+    hex::decode(value.replace(':', "")).ok()?.try_into().ok()
+}
+
+/// Huella SHA-256 del certificado que anuncia una SDP, la que DTLS comprueba
+/// después contra el certificado que presenta el otro extremo
+pub fn sdp_fingerprint(sdp: &str) -> Option<[u8; 32]> {
+    sdp.lines()
+        .filter_map(|line| line.trim().strip_prefix("a=fingerprint:"))
+        .filter_map(|value| value.split_once(' '))
+        .find(|(algorithm, _)| algorithm.eq_ignore_ascii_case("sha-256"))
+        .and_then(|(_, value)| parse_fingerprint(value.trim()))
+}
+
+/// Comprueba que la huella presentada sea la fijada al emparejarse
+pub fn verify_fingerprint(pinned: &[u8; 32], presented: &[u8; 32]) -> Result<()> {
+    if pinned != presented {
+        return Err(anyhow!("El dispositivo presentó otra identidad que la emparejada"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Digest;
+
+    #[test]
+    fn test_keeps_the_same_fingerprint_when_reloaded() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let reloaded = DeviceIdentity::from_pem(&identity.to_pem()).unwrap();
+        assert_eq!(reloaded.fingerprint(), identity.fingerprint());
+        assert_ne!(DeviceIdentity::generate().unwrap().fingerprint(), identity.fingerprint());
+
+        assert_eq!(reloaded.public(), identity.public());
+        assert_ne!(identity.public().static_key, [0; 32]);
+
+        let pinned = identity.fingerprint();
+        assert!(verify_fingerprint(&pinned, &reloaded.fingerprint()).is_ok());
+        assert!(verify_fingerprint(&pinned, &[0; 32]).is_err());
+
+        let (certificate, _) = reloaded.to_der().unwrap();
+        assert_eq!(Sha256::digest(&certificate).as_slice(), identity.fingerprint());
+    }
+
+    #[test]
+    fn test_reads_the_fingerprint_of_an_sdp() {
+        let fingerprint = [0xab; 32];
+        let value = fingerprint.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(":");
+        let sdp = format!("v=0\r\na=fingerprint:sha-1 00:11\r\na=fingerprint:sha-256 {}\r\n", value);
+        assert_eq!(sdp_fingerprint(&sdp), Some(fingerprint));
+        assert_eq!(sdp_fingerprint("v=0\r\n"), None);
+    }
+}
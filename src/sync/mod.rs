@@ -6,23 +6,60 @@
 //! - Sincronización inteligente de contraseñas
 //! - Fallback en la nube encriptado
 
+pub mod cloud;
 pub mod device_info;
 pub mod discovery;
+pub mod drive;
+pub mod dropbox;
+pub mod entry_transfer;
+pub mod framing;
+pub mod identity;
+pub mod network_policy;
+pub mod noise;
+pub mod oauth;
 pub mod p2p_connection;
+pub mod pairing;
+pub mod relay;
+pub mod retry;
+pub mod s3;
+pub mod share;
+pub mod signaling;
 pub mod smart_sync;
 pub mod sync_manager;
+pub mod tls_transport;
 pub mod commands;
 
-pub use device_info::{DeviceInfo, DeviceType, DeviceStatus};
+pub use cloud::WebDavMailbox;
+pub use device_info::{DeviceCapabilities, DeviceInfo, DeviceProfile, DeviceType, DeviceStatus, Incompatibility};
 pub use discovery::DeviceDiscovery;
+pub use drive::DriveMailbox;
+pub use dropbox::DropboxMailbox;
+pub use entry_transfer::IncomingEntry;
+pub use identity::DeviceIdentity;
+pub use network_policy::NetworkRestriction;
+pub use oauth::OAuthProvider;
 pub use p2p_connection::P2PConnection;
+pub use pairing::{PairedKey, PairingCode, PairingMessage, PairingRole, PairingStatus};
+pub use relay::{RelayMailbox, RelayShares, RelaySignaling};
+pub use retry::{RetryPolicy, SyncRetry};
+pub use s3::S3Mailbox;
+pub use share::{ShareLink, SharedEntry};
 pub use smart_sync::SmartSync;
-pub use sync_manager::SyncManager;
+pub use sync_manager::{DeviceLimitReached, SyncManager};
 pub use commands::*;
 
+pub use crate::models::SyncMethod;
+
+use crate::models::{CloudBackend, DeviceId, EncryptionLevel, SyncPreferences};
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
+/// Gestor de sincronización compartido por los comandos y la extensión del
+/// navegador. Se crea una sola vez al arrancar y todos sus métodos toman
+/// `&self`, así que no hace falta un lock para usarlo.
+pub type SharedSyncManager = Arc<OnceLock<SyncManager>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SyncEvent {
     DeviceDiscovered(DeviceInfo),
@@ -32,16 +69,51 @@ pub enum SyncEvent {
     SyncCompleted(DeviceInfo, u64),
     SyncFailed(DeviceInfo, String),
     ChangesDetected(u64),
-    Heartbeat,
+    /// Un dispositivo conectado respondió el heartbeat
+    Heartbeat(DeviceInfo),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SyncConfig {
     pub auto_sync: bool,
     pub sync_interval: u64, // en minutos
     pub discovery_enabled: bool,
     pub allow_incoming_connections: bool,
     pub auto_discovery: bool, // para compatibilidad
+    /// Los campos editados a la vez en dos dispositivos quedan como conflicto
+    /// para el usuario
+    pub ask_on_conflict: bool,
+    /// Nivel de encriptación de los lotes que se mandan y mínimo de los que
+    /// se aceptan
+    pub encryption_level: EncryptionLevel,
+    /// Sincronizar y buscar dispositivos solo conectado por Wi-Fi
+    pub wifi_only: bool,
+    /// Redes Wi-Fi en las que se sincroniza; vacía = todas
+    pub allowed_networks: Vec<String>,
+    /// Máximo de dispositivos de confianza; para emparejar otro hay que quitar
+    /// alguno antes
+    pub max_devices: u32,
+    /// En pausa no hay sincronizaciones automáticas ni reintentos y se
+    /// rechazan las de otros dispositivos; el descubrimiento sigue
+    pub paused: bool,
+    /// Directamente, por el buzón en la nube o ambos
+    pub method: SyncMethod,
+    /// Carpeta WebDAV del buzón en la nube; vacía = sin configurar
+    pub webdav_url: String,
+    pub webdav_username: String,
+    /// Dónde está el buzón en la nube
+    pub cloud_backend: CloudBackend,
+    pub s3_endpoint: String,
+    pub s3_region: String,
+    pub s3_bucket: String,
+    pub s3_prefix: String,
+    pub s3_access_key_id: String,
+    /// URL del relay propio; vacía = sin configurar
+    pub relay_url: String,
+    /// Servidor TURN de las conexiones P2P; vacío = sin TURN
+    pub turn_url: String,
+    pub turn_username: String,
 }
 
 impl Default for SyncConfig {
@@ -52,11 +124,60 @@ impl Default for SyncConfig {
             discovery_enabled: true,
             allow_incoming_connections: true,
             auto_discovery: true,
+            ask_on_conflict: false,
+            encryption_level: EncryptionLevel::Standard,
+            wifi_only: false,
+            allowed_networks: Vec::new(),
+            max_devices: 10,
+            paused: false,
+            method: SyncMethod::default(),
+            webdav_url: String::new(),
+            webdav_username: String::new(),
+            cloud_backend: CloudBackend::default(),
+            s3_endpoint: String::new(),
+            s3_region: String::new(),
+            s3_bucket: String::new(),
+            s3_prefix: String::new(),
+            s3_access_key_id: String::new(),
+            relay_url: String::new(),
+            turn_url: String::new(),
+            turn_username: String::new(),
+        }
+    }
+}
+
+impl From<&SyncPreferences> for SyncConfig {
+    fn from(preferences: &SyncPreferences) -> Self {
+        Self {
+            auto_sync: preferences.auto_sync,
+            sync_interval: preferences.sync_interval,
+            discovery_enabled: preferences.discovery_enabled,
+            allow_incoming_connections: preferences.allow_incoming_connections,
+            auto_discovery: preferences.discovery_enabled,
+            ask_on_conflict: preferences.ask_on_conflict,
+            encryption_level: preferences.encryption_level,
+            wifi_only: preferences.wifi_only,
+            allowed_networks: preferences.allowed_networks.clone(),
+            max_devices: preferences.max_devices,
+            paused: preferences.paused,
+            method: preferences.method,
+            webdav_url: preferences.webdav_url.clone(),
+            webdav_username: preferences.webdav_username.clone(),
+            cloud_backend: preferences.cloud_backend,
+            s3_endpoint: preferences.s3_endpoint.clone(),
+            s3_region: preferences.s3_region.clone(),
+            s3_bucket: preferences.s3_bucket.clone(),
+            s3_prefix: preferences.s3_prefix.clone(),
+            s3_access_key_id: preferences.s3_access_key_id.clone(),
+            relay_url: preferences.relay_url.clone(),
+            turn_url: preferences.turn_url.clone(),
+            turn_username: preferences.turn_username.clone(),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SyncStatus {
     pub is_enabled: bool,
     pub is_syncing: bool,
@@ -64,8 +185,18 @@ pub struct SyncStatus {
     pub error: Option<String>,
     pub connected_devices: Vec<DeviceInfo>, // Cambiado de u32 a Vec<DeviceInfo>
     pub last_sync: Option<chrono::DateTime<chrono::Utc>>, // para compatibilidad
-    pub sync_method: SyncMethod, // para compatibilidad
+    /// Por dónde se sincroniza, según la configuración
+    pub sync_method: SyncMethod,
     pub auto_sync: bool, // para compatibilidad
+    /// Dispositivos con sincronizaciones fallidas que se están reintentando
+    pub retries: Vec<SyncRetry>,
+    /// Por qué la red actual no permite sincronizar, si no lo permite
+    pub network_restriction: Option<NetworkRestriction>,
+    /// La sincronización está en pausa
+    pub is_paused: bool,
+    /// Dirección y puerto con los que agregar este dispositivo a mano desde
+    /// otro, si está escuchando en la red local
+    pub lan_address: Option<String>,
 }
 
 impl Default for SyncStatus {
@@ -79,11 +210,16 @@ impl Default for SyncStatus {
             last_sync: None,
             sync_method: SyncMethod::Hybrid,
             auto_sync: true,
+            retries: Vec::new(),
+            network_restriction: None,
+            is_paused: false,
+            lan_address: None,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SyncStats {
     pub total_passwords: u64,
     pub synced_passwords: u64,
@@ -94,7 +230,7 @@ pub struct SyncStats {
     pub successful_syncs: u64,
     pub failed_syncs: u64,
     pub total_data_synced: u64,
-    pub devices_synced_with: Vec<String>,
+    pub devices_synced_with: Vec<DeviceId>,
 }
 
 impl Default for SyncStats {
@@ -113,24 +249,11 @@ impl Default for SyncStats {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum SyncMethod {
-    P2P,
-    CloudEncrypted,
-    Hybrid,
-    LocalOnly,
-}
-
-impl Default for SyncMethod {
-    fn default() -> Self {
-        SyncMethod::Hybrid
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SyncResult {
     pub success: bool,
-    pub device_id: String,
+    pub device_id: DeviceId,
     pub elements_synced: u64,
     pub data_size: u64, // en bytes
     pub duration: u64, // en milisegundos
@@ -138,7 +261,7 @@ pub struct SyncResult {
 }
 
 impl SyncResult {
-    pub fn success(device_id: String, elements_synced: u64, data_size: u64, duration: u64) -> Self {
+    pub fn success(device_id: DeviceId, elements_synced: u64, data_size: u64, duration: u64) -> Self {
         Self {
             success: true,
             device_id,
@@ -149,7 +272,7 @@ impl SyncResult {
         }
     }
 
-    pub fn failure(device_id: String, error_message: String) -> Self {
+    pub fn failure(device_id: DeviceId, error_message: String) -> Self {
         Self {
             success: false,
             device_id,
@@ -191,9 +314,44 @@ impl SyncEventHandler for DefaultSyncEventHandler {
             SyncEvent::ChangesDetected(count) => {
                 log::info!("Cambios detectados: {} elementos", count);
             }
-            SyncEvent::Heartbeat => {
-                log::debug!("Heartbeat de sincronización");
+            SyncEvent::Heartbeat(device) => {
+                log::debug!("Heartbeat de: {}", device.name);
             }
         }
     }
 }
+
+/// Evento que recibe la interfaz cada vez que cambia un dispositivo, con el
+/// dispositivo, para que la página de dispositivos no tenga que preguntar
+pub const DEVICE_EVENT: &str = "sync-device";
+
+/// Registra los eventos como [`DefaultSyncEventHandler`] y además pasa a la
+/// interfaz los de los dispositivos
+pub struct AppSyncEventHandler {
+    app: tauri::AppHandle,
+}
+
+impl AppSyncEventHandler {
+    pub fn new(app: tauri::AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+impl SyncEventHandler for AppSyncEventHandler {
+    fn handle_event(&self, event: &SyncEvent) {
+        DefaultSyncEventHandler.handle_event(event);
+        let device = match event {
+            SyncEvent::DeviceDiscovered(device)
+            | SyncEvent::DeviceConnected(device)
+            | SyncEvent::DeviceDisconnected(device)
+            | SyncEvent::SyncStarted(device)
+            | SyncEvent::SyncCompleted(device, _)
+            | SyncEvent::SyncFailed(device, _)
+            | SyncEvent::Heartbeat(device) => device,
+            SyncEvent::ChangesDetected(_) => return,
+        };
+        if let Err(e) = tauri::Manager::emit_all(&self.app, DEVICE_EVENT, device) {
+            log::warn!("No se pudo avisar a la interfaz del cambio en {}: {}", device.name, e);
+        }
+    }
+}
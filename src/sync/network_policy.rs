@@ -0,0 +1,81 @@
+//! Redes en las que se permite sincronizar
+//!
+//! El usuario puede limitar la sincronización al Wi-Fi y, dentro del Wi-Fi, a
+//! unas redes concretas. Con la red actual fuera de lo permitido no se buscan
+//! dispositivos ni se sincroniza, y el estado dice por qué.
+
+use crate::platform::network::{current_network, CurrentNetwork, NetworkKind};
+use crate::sync::SyncConfig;
+use serde::{Deserialize, Serialize};
+
+/// Por qué la red actual no permite sincronizar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum NetworkRestriction {
+    #[error("Solo se sincroniza conectado por Wi-Fi")]
+    NotWifi,
+    #[error("La red Wi-Fi {} no está entre las permitidas", ssid.as_deref().unwrap_or("actual"))]
+    NetworkNotAllowed { ssid: Option<String> },
+    /// Hay restricciones pero no se pudo saber la red; por si acaso no se
+    /// sincroniza
+    #[error("No se pudo comprobar la red actual: {message}")]
+    Undetected { message: String },
+}
+
+/// Si la configuración limita las redes en las que se sincroniza
+pub fn is_restricted(config: &SyncConfig) -> bool {
+    config.wifi_only || !config.allowed_networks.is_empty()
+}
+
+/// Por qué `network` no permite sincronizar con `config`; `None` si lo
+/// permite. La lista de redes solo se aplica al Wi-Fi: las conexiones por
+/// cable no tienen nombre con el que compararlas.
+pub fn network_restriction(config: &SyncConfig, network: &CurrentNetwork) -> Option<NetworkRestriction> {
+    if network.kind != NetworkKind::Wifi {
+        return config.wifi_only.then_some(NetworkRestriction::NotWifi);
+    }
+    let allowed = config.allowed_networks.is_empty()
+        || network.ssid.as_ref().is_some_and(|ssid| config.allowed_networks.contains(ssid));
+    (!allowed).then(|| NetworkRestriction::NetworkNotAllowed { ssid: network.ssid.clone() })
+}
+
+/// Comprueba la red actual; solo se consulta si hay alguna restricción
+pub async fn check_current_network(config: &SyncConfig) -> Option<NetworkRestriction> {
+    if !is_restricted(config) {
+        return None;
+    }
+    match current_network().await {
+        Ok(network) => network_restriction(config, &network),
+        Err(e) => Some(NetworkRestriction::Undetected { message: e.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_restriction() {
+        let wifi = |ssid: &str| CurrentNetwork { kind: NetworkKind::Wifi, ssid: Some(ssid.to_string()) };
+        let cable = CurrentNetwork { kind: NetworkKind::Ethernet, ssid: None };
+
+        let open = SyncConfig::default();
+        assert!(!is_restricted(&open));
+        assert_eq!(network_restriction(&open, &cable), None);
+
+        let wifi_only = SyncConfig { wifi_only: true, ..SyncConfig::default() };
+        assert_eq!(network_restriction(&wifi_only, &cable), Some(NetworkRestriction::NotWifi));
+        assert_eq!(network_restriction(&wifi_only, &wifi("Cafetería")), None);
+
+        let home = SyncConfig { allowed_networks: vec!["Casa".to_string()], ..SyncConfig::default() };
+        assert_eq!(network_restriction(&home, &wifi("Casa")), None);
+        assert_eq!(network_restriction(&home, &cable), None);
+        assert_eq!(
+            network_restriction(&home, &wifi("Cafetería")),
+            Some(NetworkRestriction::NetworkNotAllowed { ssid: Some("Cafetería".to_string()) }),
+        );
+        // Sin el nombre de la red no se puede saber si está permitida
+        let hidden = CurrentNetwork { kind: NetworkKind::Wifi, ssid: None };
+        assert!(network_restriction(&home, &hidden).is_some());
+    }
+}
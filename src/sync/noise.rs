@@ -0,0 +1,442 @@
+//! Saludo Noise de las conexiones de sincronización
+//!
+//! Antes del primer lote, los dos extremos de una conexión hacen un saludo
+//! Noise XX (`Noise_XX_25519_ChaChaPoly_SHA256`) con la clave estática de su
+//! identidad. Cada uno comprueba que la clave estática del otro sea la que
+//! fijó al emparejarse, y del saludo salen las claves de sesión con las que
+//! van cifrados los lotes y sus respuestas. La autenticación es la misma sea
+//! cual sea el transporte: WebRTC, TLS en la red local o el que venga.
+//!
+//! Inicia el saludo quien manda lotes. Como por una conexión pueden mandar
+//! los dos extremos, cada sentido tiene su saludo y su sesión.
+//!
+//! Los mensajes del saludo van por el mismo intercambio que los lotes, con un
+//! byte delante que dice qué son:
+//!
+//! 1. `-> e`: el primero, que se responde con `<- e, ee, s, es`
+//! 2. `-> s, se`: el último, que se responde vacío
+//! 3. un lote cifrado, que se responde con la respuesta cifrada
+//!
+//! A diferencia de los mensajes de transporte de Noise, un lote cifrado puede
+//! pasar de 64 KiB: va entero y el transporte se encarga de partirlo.
+
+use crate::models::DeviceId;
+use crate::sync::smart_sync::SyncResponder;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const PROTOCOL_NAME: &[u8; 32] = b"Noise_XX_25519_ChaChaPoly_SHA256";
+const PROLOGUE: &[u8] = b"alohopass-sync-v1";
+const KEY_SIZE: usize = 32;
+const TAG_SIZE: usize = 16;
+
+/// Primer mensaje del saludo
+const HELLO: u8 = 0;
+/// Último mensaje del saludo
+const FINISH: u8 = 1;
+/// Lote cifrado con la sesión
+const DATA: u8 = 2;
+
+/// Clave y contador de un sentido
+struct CipherState {
+    key: Option<[u8; 32]>,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: Option<[u8; 32]>) -> Self {
+        Self { key, nonce: 0 }
+    }
+
+    fn encrypt(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let Some(key) = &self.key else {
+            return Ok(plaintext.to_vec());
+        };
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .encrypt(&nonce(self.nonce)?, Payload { msg: plaintext, aad: ad })
+            .map_err(|_| anyhow!("Error al cifrar con la sesión Noise"))?;
+        self.nonce += 1;
+        Ok(ciphertext)
+    }
+
+    /// Si el mensaje no se puede descifrar el contador no avanza
+    fn decrypt(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let Some(key) = &self.key else {
+            return Ok(ciphertext.to_vec());
+        };
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(&nonce(self.nonce)?, Payload { msg: ciphertext, aad: ad })
+            .map_err(|_| anyhow!("El mensaje no se pudo descifrar con la sesión Noise"))?;
+        self.nonce += 1;
+        Ok(plaintext)
+    }
+}
+
+/// Nonce de ChaCha20-Poly1305: 4 bytes en cero y el contador
+fn nonce(counter: u64) -> Result<Nonce> {
+    if counter == u64::MAX {
+        return Err(anyhow!("Se agotaron los nonces de la sesión Noise"));
+    }
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    Ok(Nonce::from(nonce))
+}
+
+/// Hash del saludo y clave encadenada
+struct SymmetricState {
+    chaining_key: [u8; 32],
+    hash: [u8; 32],
+    cipher: CipherState,
+}
+
+impl SymmetricState {
+    fn new() -> Self {
+        let mut state = Self {
+            chaining_key: *PROTOCOL_NAME,
+            hash: *PROTOCOL_NAME,
+            cipher: CipherState::new(None),
+        };
+        state.mix_hash(PROLOGUE);
+        state
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        self.hash = Sha256::new().chain_update(self.hash).chain_update(data).finalize().into();
+    }
+
+    fn mix_key(&mut self, input: &[u8]) -> Result<()> {
+        let (chaining_key, key) = hkdf(&self.chaining_key, input)?;
+        self.chaining_key = chaining_key;
+        self.cipher = CipherState::new(Some(key));
+        Ok(())
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let ciphertext = self.cipher.encrypt(&self.hash, plaintext)?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let plaintext = self.cipher.decrypt(&self.hash, ciphertext)?;
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    /// Claves de los dos sentidos: primero la de quien inició el saludo
+    fn split(&self) -> Result<(CipherState, CipherState)> {
+        let (initiator, responder) = hkdf(&self.chaining_key, &[])?;
+        Ok((CipherState::new(Some(initiator)), CipherState::new(Some(responder))))
+    }
+}
+
+/// HKDF de Noise: dos salidas a partir de la clave encadenada
+fn hkdf(chaining_key: &[u8; 32], input: &[u8]) -> Result<([u8; 32], [u8; 32])> {
+    let mut output = [0u8; 2 * KEY_SIZE];
+    Hkdf::<Sha256>::new(Some(chaining_key), input)
+        .expand(&[], &mut output)
+        .map_err(|e| anyhow!("Error al derivar las claves del saludo Noise: {}", e))?;
+    let (first, second) = output.split_at(KEY_SIZE);
+    Ok((first.try_into()?, second.try_into()?))
+}
+
+fn dh(secret: &StaticSecret, public: &[u8]) -> Result<[u8; 32]> {
+    let public: [u8; 32] = public.try_into()
+        .map_err(|_| anyhow!("Clave pública del saludo Noise de longitud inválida"))?;
+    let shared = secret.diffie_hellman(&PublicKey::from(public));
+    if !shared.was_contributory() {
+        return Err(anyhow!("Clave pública del saludo Noise inválida"));
+    }
+    Ok(shared.to_bytes())
+}
+
+/// Sesión de un sentido de la conexión, ya autenticada
+pub struct Session {
+    sender: CipherState,
+    receiver: CipherState,
+}
+
+impl Session {
+    /// Cifra un lote para mandarlo por el transporte
+    pub fn seal(&mut self, message: &[u8]) -> Result<Vec<u8>> {
+        let mut sealed = vec![DATA];
+        sealed.extend(self.sender.encrypt(&[], message)?);
+        Ok(sealed)
+    }
+
+    /// Descifra la respuesta a un lote
+    pub fn open(&mut self, reply: &[u8]) -> Result<Vec<u8>> {
+        self.receiver.decrypt(&[], reply)
+    }
+}
+
+/// Saludo empezado por quien va a mandar lotes
+pub struct Initiator {
+    state: SymmetricState,
+    local: StaticSecret,
+    ephemeral: StaticSecret,
+    remote_static: [u8; 32],
+}
+
+impl Initiator {
+    /// Empieza el saludo con un dispositivo cuya clave estática fijada es
+    /// `remote_static`; devuelve el primer mensaje
+    pub fn start(local: &StaticSecret, remote_static: [u8; 32]) -> Result<(Self, Vec<u8>)> {
+        let ephemeral = StaticSecret::random_from_rng(OsRng);
+        let mut state = SymmetricState::new();
+        let mut message = vec![HELLO];
+
+        // -> e
+        let e = PublicKey::from(&ephemeral).to_bytes();
+        state.mix_hash(&e);
+        message.extend_from_slice(&e);
+        message.extend(state.encrypt_and_hash(&[])?);
+
+        Ok((Self { state, local: local.clone(), ephemeral, remote_static }, message))
+    }
+
+    /// Procesa la respuesta al primer mensaje. Devuelve el último mensaje y la
+    /// sesión, que vale una vez que el otro extremo lo acepte.
+    pub fn finish(self, reply: &[u8]) -> Result<(Session, Vec<u8>)> {
+        let Self { mut state, local, ephemeral, remote_static } = self;
+        if reply.len() < KEY_SIZE + KEY_SIZE + TAG_SIZE + TAG_SIZE {
+            return Err(anyhow!("Respuesta al saludo Noise de {} bytes", reply.len()));
+        }
+
+        // <- e, ee, s, es
+        let (re, rest) = reply.split_at(KEY_SIZE);
+        state.mix_hash(re);
+        state.mix_key(&dh(&ephemeral, re)?)?;
+        let (encrypted_static, payload) = rest.split_at(KEY_SIZE + TAG_SIZE);
+        let rs = state.decrypt_and_hash(encrypted_static)?;
+        state.mix_key(&dh(&ephemeral, &rs)?)?;
+        state.decrypt_and_hash(payload)?;
+        if rs != remote_static {
+            return Err(anyhow!("El dispositivo presentó otra clave estática que la emparejada"));
+        }
+
+        // -> s, se
+        let mut message = vec![FINISH];
+        message.extend(state.encrypt_and_hash(PublicKey::from(&local).as_bytes())?);
+        state.mix_key(&dh(&local, re)?)?;
+        message.extend(state.encrypt_and_hash(&[])?);
+
+        let (sender, receiver) = state.split()?;
+        Ok((Session { sender, receiver }, message))
+    }
+}
+
+/// Saludo de quien atiende, después de responder el primer mensaje
+struct Responding {
+    state: SymmetricState,
+    ephemeral: StaticSecret,
+}
+
+impl Responding {
+    /// Procesa el primer mensaje y devuelve la respuesta
+    fn start(local: &StaticSecret, message: &[u8]) -> Result<(Self, Vec<u8>)> {
+        if message.len() < KEY_SIZE {
+            return Err(anyhow!("Saludo Noise de {} bytes", message.len()));
+        }
+        let mut state = SymmetricState::new();
+
+        // -> e
+        let (re, payload) = message.split_at(KEY_SIZE);
+        state.mix_hash(re);
+        state.decrypt_and_hash(payload)?;
+
+        // <- e, ee, s, es
+        let ephemeral = StaticSecret::random_from_rng(OsRng);
+        let e = PublicKey::from(&ephemeral).to_bytes();
+        state.mix_hash(&e);
+        let mut reply = e.to_vec();
+        state.mix_key(&dh(&ephemeral, re)?)?;
+        reply.extend(state.encrypt_and_hash(PublicKey::from(local).as_bytes())?);
+        state.mix_key(&dh(local, re)?)?;
+        reply.extend(state.encrypt_and_hash(&[])?);
+
+        Ok((Self { state, ephemeral }, reply))
+    }
+
+    /// Procesa el último mensaje; devuelve la sesión y la clave estática que
+    /// presentó quien inició el saludo, que falta comprobar
+    fn finish(self, message: &[u8]) -> Result<(Session, [u8; 32])> {
+        let Self { mut state, ephemeral } = self;
+        if message.len() < KEY_SIZE + TAG_SIZE + TAG_SIZE {
+            return Err(anyhow!("Fin del saludo Noise de {} bytes", message.len()));
+        }
+
+        // -> s, se
+        let (encrypted_static, payload) = message.split_at(KEY_SIZE + TAG_SIZE);
+        let rs = state.decrypt_and_hash(encrypted_static)?;
+        state.mix_key(&dh(&ephemeral, &rs)?)?;
+        state.decrypt_and_hash(payload)?;
+
+        let (receiver, sender) = state.split()?;
+        let rs = rs.try_into().map_err(|_| anyhow!("Clave estática de longitud inválida"))?;
+        Ok((Session { sender, receiver }, rs))
+    }
+}
+
+/// Lo que necesita quien atiende una conexión para autenticar al otro
+/// extremo y responderle
+#[async_trait]
+pub trait NoisePeer: SyncResponder {
+    /// Clave estática de este dispositivo
+    async fn static_secret(&self) -> Result<StaticSecret>;
+
+    /// Comprueba que `static_key` sea la clave fijada al emparejar `from`
+    async fn verify_static_key(&self, from: DeviceId, static_key: &[u8; 32]) -> Result<()>;
+}
+
+enum ResponderState {
+    Idle,
+    Handshaking(Responding),
+    Established(Session),
+}
+
+/// Atiende lo que llega por una conexión: primero el saludo y después los
+/// lotes cifrados, que pasa descifrados a `peer`. Va uno por conexión.
+pub struct NoiseResponder<P: ?Sized> {
+    peer: Arc<P>,
+    state: Mutex<ResponderState>,
+}
+
+impl<P: NoisePeer + ?Sized> NoiseResponder<P> {
+    pub fn new(peer: Arc<P>) -> Self {
+        Self { peer, state: Mutex::new(ResponderState::Idle) }
+    }
+}
+
+#[async_trait]
+impl<P: NoisePeer + ?Sized> SyncResponder for NoiseResponder<P> {
+    async fn respond(&self, from: DeviceId, request: &[u8]) -> Result<Vec<u8>> {
+        let Some((&kind, message)) = request.split_first() else {
+            return Err(anyhow!("Mensaje vacío"));
+        };
+        match kind {
+            // Un saludo nuevo reemplaza la sesión anterior: el otro extremo
+            // la descartó
+            HELLO => {
+                let (responding, reply) = Responding::start(&self.peer.static_secret().await?, message)?;
+                *self.state.lock().await = ResponderState::Handshaking(responding);
+                Ok(reply)
+            }
+            FINISH => {
+                let mut state = self.state.lock().await;
+                let ResponderState::Handshaking(responding) = std::mem::replace(&mut *state, ResponderState::Idle) else {
+                    return Err(anyhow!("Fin del saludo Noise sin su comienzo"));
+                };
+                let (session, static_key) = responding.finish(message)?;
+                self.peer.verify_static_key(from, &static_key).await?;
+                *state = ResponderState::Established(session);
+                Ok(Vec::new())
+            }
+            DATA => {
+                let request = match &mut *self.state.lock().await {
+                    ResponderState::Established(session) => session.receiver.decrypt(&[], message)?,
+                    _ => return Err(anyhow!("Lote de {} sin saludo Noise", from)),
+                };
+                let reply = self.peer.respond(from, &request).await?;
+                match &mut *self.state.lock().await {
+                    ResponderState::Established(session) => session.sender.encrypt(&[], &reply),
+                    _ => Err(anyhow!("La sesión Noise con {} se cerró mientras se respondía", from)),
+                }
+            }
+            _ => Err(anyhow!("Mensaje de tipo desconocido: {}", kind)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Peer {
+        secret: StaticSecret,
+        pinned: [u8; 32],
+    }
+
+    #[async_trait]
+    impl SyncResponder for Peer {
+        async fn respond(&self, _from: DeviceId, request: &[u8]) -> Result<Vec<u8>> {
+            Ok(request.iter().rev().copied().collect())
+        }
+    }
+
+    #[async_trait]
+    impl NoisePeer for Peer {
+        async fn static_secret(&self) -> Result<StaticSecret> {
+            Ok(self.secret.clone())
+        }
+
+        async fn verify_static_key(&self, _from: DeviceId, static_key: &[u8; 32]) -> Result<()> {
+            match *static_key == self.pinned {
+                true => Ok(()),
+                false => Err(anyhow!("otra clave")),
+            }
+        }
+    }
+
+    fn public(secret: &StaticSecret) -> [u8; 32] {
+        PublicKey::from(secret).to_bytes()
+    }
+
+    /// Saludo completo de `laptop` con `responder`
+    async fn handshake(laptop: &StaticSecret, phone: [u8; 32], responder: &NoiseResponder<Peer>) -> Result<Session> {
+        let device = DeviceId::new();
+        let (initiator, hello) = Initiator::start(laptop, phone)?;
+        let (session, finish) = initiator.finish(&responder.respond(device, &hello).await?)?;
+        responder.respond(device, &finish).await?;
+        Ok(session)
+    }
+
+    #[tokio::test]
+    async fn test_exchanges_sealed_batches_after_the_handshake() {
+        let (laptop, phone) = (StaticSecret::random_from_rng(OsRng), StaticSecret::random_from_rng(OsRng));
+        let responder = NoiseResponder::new(Arc::new(Peer { secret: phone.clone(), pinned: public(&laptop) }));
+        let device = DeviceId::new();
+        assert!(responder.respond(device, &[DATA, 1, 2, 3]).await.is_err());
+
+        let mut session = handshake(&laptop, public(&phone), &responder).await.unwrap();
+        for batch in [b"hola".to_vec(), Vec::new(), vec![7; 100_000]] {
+            let sealed = session.seal(&batch).unwrap();
+            assert!(!sealed.windows(4).any(|window| window == b"hola"));
+            let reply = responder.respond(device, &sealed).await.unwrap();
+            assert_eq!(session.open(&reply).unwrap(), batch.iter().rev().copied().collect::<Vec<_>>());
+        }
+
+        // Un lote alterado o repetido no pasa
+        let mut sealed = session.seal(b"lote").unwrap();
+        let replayed = sealed.clone();
+        *sealed.last_mut().unwrap() ^= 1;
+        assert!(responder.respond(device, &sealed).await.is_err());
+        let reply = responder.respond(device, &replayed).await.unwrap();
+        assert_eq!(session.open(&reply).unwrap(), b"etol");
+        assert!(responder.respond(device, &replayed).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_static_keys_that_were_not_paired() {
+        let (laptop, phone, stranger) = (
+            StaticSecret::random_from_rng(OsRng),
+            StaticSecret::random_from_rng(OsRng),
+            StaticSecret::random_from_rng(OsRng),
+        );
+        let responder = NoiseResponder::new(Arc::new(Peer { secret: phone.clone(), pinned: public(&laptop) }));
+
+        // Quien atiende no es el emparejado
+        assert!(handshake(&laptop, public(&stranger), &responder).await.is_err());
+        // Quien llama no es el emparejado
+        assert!(handshake(&stranger, public(&phone), &responder).await.is_err());
+        assert!(handshake(&laptop, public(&phone), &responder).await.is_ok());
+    }
+}
@@ -0,0 +1,283 @@
+//! Inicio de sesión OAuth 2 para los buzones en Google Drive y Dropbox
+//!
+//! Es el flujo para aplicaciones de escritorio: el usuario autoriza en su
+//! navegador y el proveedor lo redirige a un puerto libre de `127.0.0.1`, donde
+//! se recoge el código, protegido con PKCE. Solo se guarda el token de
+//! renovación; los de acceso viven en memoria y se renuevan al caducar.
+//!
+//! Los identificadores de la aplicación en cada proveedor se fijan al compilar
+//! con `ALOHOPASS_GOOGLE_CLIENT_ID`, `ALOHOPASS_GOOGLE_CLIENT_SECRET` (Google
+//! lo pide aunque en una aplicación instalada no es secreto) y
+//! `ALOHOPASS_DROPBOX_APP_KEY`.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{rngs::OsRng, RngCore};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+/// Tiempo que se espera a que el usuario autorice en el navegador
+pub const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Margen con que se renueva el token de acceso antes de que caduque
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Página que ve el usuario en el navegador al terminar
+const DONE_PAGE: &str = "<!doctype html><meta charset=\"utf-8\"><title>Alohopass</title>\
+    <p>Listo: ya puedes cerrar esta pestaña y volver a Alohopass.</p>";
+
+/// Servicio en la nube al que se accede con OAuth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuthProvider {
+    GoogleDrive,
+    Dropbox,
+}
+
+impl OAuthProvider {
+    fn client_id(self) -> Result<&'static str> {
+        let client_id = match self {
+            OAuthProvider::GoogleDrive => option_env!("ALOHOPASS_GOOGLE_CLIENT_ID"),
+            OAuthProvider::Dropbox => option_env!("ALOHOPASS_DROPBOX_APP_KEY"),
+        };
+        client_id.ok_or_else(|| anyhow!("Esta versión de Alohopass se compiló sin acceso a {:?}", self))
+    }
+
+    fn client_secret(self) -> Option<&'static str> {
+        match self {
+            OAuthProvider::GoogleDrive => option_env!("ALOHOPASS_GOOGLE_CLIENT_SECRET"),
+            OAuthProvider::Dropbox => None,
+        }
+    }
+
+    fn authorize_url(self) -> &'static str {
+        match self {
+            OAuthProvider::GoogleDrive => "https://accounts.google.com/o/oauth2/v2/auth",
+            OAuthProvider::Dropbox => "https://www.dropbox.com/oauth2/authorize",
+        }
+    }
+
+    fn token_url(self) -> &'static str {
+        match self {
+            OAuthProvider::GoogleDrive => "https://oauth2.googleapis.com/token",
+            OAuthProvider::Dropbox => "https://api.dropboxapi.com/oauth2/token",
+        }
+    }
+
+    /// Parámetros propios de cada proveedor para obtener un token de
+    /// renovación. En Drive solo se pide la carpeta oculta de la aplicación.
+    fn authorize_params(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            OAuthProvider::GoogleDrive => &[
+                ("scope", "https://www.googleapis.com/auth/drive.appdata"),
+                ("access_type", "offline"),
+                ("prompt", "consent"),
+            ],
+            OAuthProvider::Dropbox => &[("token_access_type", "offline")],
+        }
+    }
+}
+
+/// Respuesta del servidor de tokens
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// Pide tokens al proveedor con los parámetros de `form`
+async fn request_tokens(client: &reqwest::Client, provider: OAuthProvider, mut form: Vec<(&str, String)>) -> Result<TokenResponse> {
+    form.push(("client_id", provider.client_id()?.to_string()));
+    if let Some(secret) = provider.client_secret() {
+        form.push(("client_secret", secret.to_string()));
+    }
+    let response = client.post(provider.token_url()).form(&form).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{:?} rechazó la autorización ({})", provider, response.status()));
+    }
+    Ok(serde_json::from_slice(&response.bytes().await?)?)
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("Alohopass/", env!("CARGO_PKG_VERSION")))
+        .build()?)
+}
+
+/// Acceso a la cuenta del usuario en un proveedor, a partir del token de
+/// renovación guardado
+pub struct OAuthSession {
+    provider: OAuthProvider,
+    refresh_token: String,
+    client: reqwest::Client,
+    /// Token de acceso y cuándo caduca
+    access: Mutex<Option<(String, Instant)>>,
+}
+
+impl OAuthSession {
+    pub fn new(provider: OAuthProvider, refresh_token: String) -> Result<Self> {
+        Ok(Self {
+            provider,
+            refresh_token,
+            client: http_client()?,
+            access: Mutex::new(None),
+        })
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Token de acceso vigente; lo renueva si caducó
+    async fn access_token(&self) -> Result<String> {
+        let mut access = self.access.lock().await;
+        if let Some((token, expires_at)) = access.as_ref() {
+            if Instant::now() + EXPIRY_MARGIN < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+        let tokens = request_tokens(&self.client, self.provider, vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", self.refresh_token.clone()),
+        ]).await?;
+        let expires_at = Instant::now() + Duration::from_secs(tokens.expires_in.unwrap_or(3600));
+        *access = Some((tokens.access_token.clone(), expires_at));
+        Ok(tokens.access_token)
+    }
+
+    /// Manda la petición que arma `request` con el token de acceso. Si el
+    /// proveedor lo rechaza se renueva y se intenta una vez más.
+    pub async fn send(&self, request: impl Fn(&str) -> reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let response = request(&self.access_token().await?).send().await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        *self.access.lock().await = None;
+        Ok(request(&self.access_token().await?).send().await?)
+    }
+}
+
+/// Autorización en curso: el usuario tiene que abrir `url` en el navegador
+pub struct PendingAuthorization {
+    provider: OAuthProvider,
+    listener: TcpListener,
+    redirect_uri: String,
+    verifier: String,
+    state: String,
+    pub url: String,
+}
+
+/// Prepara la autorización: abre el puerto al que vuelve el navegador y arma
+/// la URL que tiene que abrir el usuario
+pub async fn begin_authorization(provider: OAuthProvider) -> Result<PendingAuthorization> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let redirect_uri = format!("http://127.0.0.1:{}/", listener.local_addr()?.port());
+    let verifier = random_token();
+    let state = random_token();
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+    let mut url = Url::parse(provider.authorize_url())?;
+    url.query_pairs_mut()
+        .append_pair("client_id", provider.client_id()?)
+        .append_pair("response_type", "code")
+        .append_pair("redirect_uri", &redirect_uri)
+        .append_pair("code_challenge", &challenge)
+        .append_pair("code_challenge_method", "S256")
+        .append_pair("state", &state)
+        .extend_pairs(provider.authorize_params());
+    Ok(PendingAuthorization {
+        provider,
+        listener,
+        redirect_uri,
+        verifier,
+        state,
+        url: url.to_string(),
+    })
+}
+
+impl PendingAuthorization {
+    /// Espera a que el navegador vuelva con el código y lo cambia por los
+    /// tokens. Devuelve el token de renovación.
+    pub async fn finish(self) -> Result<String> {
+        let code = tokio::time::timeout(AUTHORIZATION_TIMEOUT, self.receive_code()).await
+            .map_err(|_| anyhow!("Se agotó el tiempo para autorizar el acceso a {:?}", self.provider))??;
+        let tokens = request_tokens(&http_client()?, self.provider, vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code),
+            ("redirect_uri", self.redirect_uri.clone()),
+            ("code_verifier", self.verifier.clone()),
+        ]).await?;
+        tokens.refresh_token
+            .ok_or_else(|| anyhow!("{:?} no devolvió un token de renovación", self.provider))
+    }
+
+    /// Atiende las peticiones del navegador hasta que llega la redirección
+    async fn receive_code(&self) -> Result<String> {
+        loop {
+            let (mut stream, _) = self.listener.accept().await?;
+            let mut buffer = vec![0; 8192];
+            let read = stream.read(&mut buffer).await?;
+            let request = String::from_utf8_lossy(&buffer[..read]);
+            let outcome = parse_redirect(&request, &self.state);
+            let (status, body) = match &outcome {
+                Some(_) => ("200 OK", DONE_PAGE),
+                // El navegador también pide el favicon y cosas así
+                None => ("404 Not Found", ""),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status, body.len(), body
+            );
+            stream.write_all(response.as_bytes()).await?;
+            if let Some(outcome) = outcome {
+                return outcome;
+            }
+        }
+    }
+}
+
+/// Código de la redirección en `request`, o el error con que volvió. `None`
+/// si la petición no es la redirección.
+fn parse_redirect(request: &str, expected_state: &str) -> Option<Result<String>> {
+    let target = request.lines().next()?.strip_prefix("GET ")?.split(' ').next()?;
+    let url = Url::parse("http://127.0.0.1").ok()?.join(target).ok()?;
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    if let Some(error) = param("error") {
+        return Some(Err(anyhow!("Autorización rechazada: {}", error)));
+    }
+    let code = param("code")?;
+    if param("state").as_deref() != Some(expected_state) {
+        return Some(Err(anyhow!("La redirección de la autorización no corresponde a esta solicitud")));
+    }
+    Some(Ok(code))
+}
+
+/// Valor aleatorio para el verificador PKCE y el `state`
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_the_redirect() {
+        let request = "GET /?state=abc&code=4%2F0Ab HTTP/1.1\r\nHost: 127.0.0.1:5000\r\n\r\n";
+        assert_eq!(parse_redirect(request, "abc").unwrap().unwrap(), "4/0Ab");
+        assert!(parse_redirect(request, "otro").unwrap().is_err());
+        assert!(parse_redirect("GET /?error=access_denied HTTP/1.1\r\n\r\n", "abc").unwrap().is_err());
+        assert!(parse_redirect("GET /favicon.ico HTTP/1.1\r\n\r\n", "abc").is_none());
+    }
+}
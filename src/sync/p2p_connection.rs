@@ -1,22 +1,69 @@
 //! Conexión P2P usando WebRTC
 //! 
 //! Este módulo implementa la conexión directa entre dispositivos
-//! usando WebRTC para la sincronización de datos
-
+//! usando WebRTC para la sincronización de datos.
+//!
+//! La conexión usa el certificado de este dispositivo para DTLS y solo
+//! acepta al otro extremo si la huella que anuncia es la fijada al
+//! emparejarse. DTLS comprueba después que el certificado que presenta
+//! coincida con esa huella. Antes del primer lote, los dos extremos se
+//! autentican con un saludo [`noise`](crate::sync::noise) y los lotes viajan
+//! cifrados con la sesión que sale de él.
+//!
+//! Quien manda la oferta abre el canal de datos y quien la responde lo recibe.
+//! Los dos extremos pueden mandar lotes por el mismo canal, partidos en trozos
+//! con [`framing`](crate::sync::framing): cada mensaje es un pedido, que
+//! responde el [`SyncResponder`], o la respuesta al último pedido de este
+//! extremo.
+//!
+//! Los trozos salen por una cola que no le pasa más al canal mientras tenga
+//! `max_buffer_size` bytes sin mandar, así una sincronización grande no llena
+//! la memoria: sigue cuando el canal avisa que bajó del umbral.
+//!
+//! Si la conexión se cae en medio de un intercambio,
+//! [`ReconnectingConnection`] la vuelve a abrir esperando cada vez más y manda
+//! otra vez el mismo lote, sin rearmarlo.
+//!
+//! Si la señalización lo permite, los candidatos ICE se mandan a medida que
+//! aparecen. En redes que no dejan pasar nada directo, la conexión puede ir
+//! por un servidor TURN configurado con sus credenciales.
+
+use crate::models::DeviceId;
+use crate::sync::framing::{self, MessageKind, Reassembler};
+use crate::sync::identity::{sdp_fingerprint, verify_fingerprint, DeviceIdentity, PublicIdentity};
+use crate::sync::noise::{Initiator, Session};
+use crate::sync::signaling::{trickle, SignalingChannel, Trickle};
+use crate::sync::retry::RetryPolicy;
+use crate::sync::smart_sync::{SyncResponder, SyncTransport};
 use crate::sync::{DeviceInfo, SyncEvent, SyncEventHandler};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::{
+    fmt,
+    sync::{atomic::{AtomicU32, AtomicUsize, Ordering}, Arc, Mutex, Weak},
+    time::Duration,
+};
+use tokio::sync::{mpsc, Notify, RwLock};
 use webrtc::{
     api::APIBuilder,
     data_channel::data_channel_init::RTCDataChannelInit,
+    data_channel::data_channel_message::DataChannelMessage,
+    data_channel::data_channel_state::RTCDataChannelState,
+    data_channel::RTCDataChannel,
+    ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit},
+    ice_transport::ice_candidate_pair::RTCIceCandidatePair,
+    ice_transport::ice_credential_type::RTCIceCredentialType,
+    ice_transport::ice_server::RTCIceServer,
     peer_connection::configuration::RTCConfiguration,
     peer_connection::peer_connection_state::RTCPeerConnectionState,
+    peer_connection::sdp::session_description::RTCSessionDescription,
     peer_connection::RTCPeerConnection,
 };
 
+/// Nombre del canal de datos que abre quien manda la oferta
+const DATA_CHANNEL_LABEL: &str = "alohopass-sync";
+
 /// Configuración de la conexión P2P
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct P2PConfig {
@@ -24,12 +71,21 @@ pub struct P2PConfig {
     pub port: u16,
     /// ICE servers para NAT traversal
     pub ice_servers: Vec<String>,
+    /// Servidores TURN por los que pasa la conexión si no hay camino directo
+    pub turn_servers: Vec<TurnServer>,
     /// Tiempo de espera para conexión (segundos)
     pub connection_timeout: u64,
-    /// Tamaño máximo del buffer de datos
+    /// Bytes que puede tener el canal de datos sin mandar; a partir de ahí
+    /// los trozos esperan en la cola hasta que baje a la cuarta parte
     pub max_buffer_size: usize,
     /// Usar conexión encriptada
     pub encrypted: bool,
+    /// Veces que se intenta volver a conectar cuando la conexión se cae en
+    /// medio de un intercambio
+    pub max_reconnect_attempts: u32,
+    /// Espera antes del primer intento de reconexión (segundos); se duplica
+    /// en cada uno
+    pub reconnect_delay: u64,
 }
 
 impl Default for P2PConfig {
@@ -40,13 +96,35 @@ impl Default for P2PConfig {
                 "stun:stun.l.google.com:19302".to_string(),
                 "stun:stun1.l.google.com:19302".to_string(),
             ],
+            turn_servers: Vec::new(),
             connection_timeout: 30,
             max_buffer_size: 1024 * 1024, // 1MB
             encrypted: true,
+            max_reconnect_attempts: 3,
+            reconnect_delay: 2,
         }
     }
 }
 
+/// Servidor TURN con sus credenciales
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnServer {
+    /// `turn:` o `turns:`
+    pub urls: Vec<String>,
+    pub username: String,
+    pub credential: String,
+}
+
+impl fmt::Debug for TurnServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // La credencial no pasa por el registro
+        f.debug_struct("TurnServer")
+            .field("urls", &self.urls)
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Estado de la conexión P2P
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum P2PConnectionState {
@@ -91,9 +169,10 @@ pub struct P2PConnection {
     /// Configuración de la conexión
     config: P2PConfig,
     /// Conexión WebRTC
-    peer_connection: Option<RTCPeerConnection>,
-    /// Canal de datos
-    data_channel: Option<Arc<webrtc::data_channel::RTCDataChannel>>,
+    peer_connection: Option<Arc<RTCPeerConnection>>,
+    /// Canal de datos; del lado que responde la oferta llega después, cuando
+    /// el otro extremo lo abre
+    data_channel: Arc<RwLock<Option<Arc<RTCDataChannel>>>>,
     /// Estado de la conexión
     state: Arc<RwLock<P2PConnectionState>>,
     /// Dispositivo remoto
@@ -102,32 +181,84 @@ pub struct P2PConnection {
     event_sender: mpsc::Sender<SyncEvent>,
     /// Manejador de eventos
     event_handler: Arc<dyn SyncEventHandler + Send + Sync>,
-    /// Buffer de datos pendientes
+    /// Rearma los mensajes que llegan en trozos
+    reassembler: Arc<Mutex<Reassembler>>,
+    /// Número del próximo mensaje que sale
+    next_message_id: Arc<AtomicU32>,
+    /// Trozos que esperan a que el canal de datos tenga lugar
+    send_queue: Arc<SendQueue>,
+    /// Respuestas que llegaron y todavía nadie recogió
     pending_data: Arc<RwLock<Vec<Vec<u8>>>>,
+    /// Avisa cuando llega una respuesta a `pending_data`
+    data_received: Arc<Notify>,
+    /// Avisa cada vez que cambia `state`
+    state_changed: Arc<Notify>,
+    /// Certificado con el que se presenta este dispositivo
+    identity: Option<DeviceIdentity>,
+    /// Identidad fijada del dispositivo remoto
+    peer: Option<PublicIdentity>,
+    /// Sesión Noise de los lotes que manda este extremo; se arma con el
+    /// primero
+    session: tokio::sync::Mutex<Option<Session>>,
+    /// Responde los lotes que manda el otro extremo; sin él se descartan
+    responder: Option<Arc<dyn SyncResponder>>,
+    /// Tarea que agrega los candidatos que manda el otro extremo
+    trickle_task: Option<tokio::task::JoinHandle<()>>,
+    /// Candidatos ICE de cada extremo, para el diagnóstico
+    local_candidates: Arc<AtomicUsize>,
+    remote_candidates: Arc<AtomicUsize>,
 }
 
 impl P2PConnection {
     /// Crear una nueva conexión P2P
     pub fn new(config: P2PConfig, event_sender: mpsc::Sender<SyncEvent>) -> Self {
+        let send_queue = Arc::new(SendQueue::new(config.max_buffer_size, Duration::from_secs(config.connection_timeout)));
         Self {
             config,
             peer_connection: None,
-            data_channel: None,
+            data_channel: Arc::new(RwLock::new(None)),
             state: Arc::new(RwLock::new(P2PConnectionState::Disconnected)),
             remote_device: None,
             event_sender,
             event_handler: Arc::new(crate::sync::DefaultSyncEventHandler),
+            reassembler: Arc::new(Mutex::new(Reassembler::new())),
+            next_message_id: Arc::new(AtomicU32::new(0)),
+            send_queue,
             pending_data: Arc::new(RwLock::new(Vec::new())),
+            data_received: Arc::new(Notify::new()),
+            state_changed: Arc::new(Notify::new()),
+            identity: None,
+            peer: None,
+            session: tokio::sync::Mutex::new(None),
+            responder: None,
+            trickle_task: None,
+            local_candidates: Arc::new(AtomicUsize::new(0)),
+            remote_candidates: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Se presenta con `identity` y solo acepta al dispositivo remoto si su
+    /// certificado y su clave estática son los de `peer`
+    pub fn with_identity(mut self, identity: DeviceIdentity, peer: PublicIdentity) -> Self {
+        self.identity = Some(identity);
+        self.peer = Some(peer);
+        self
+    }
+
+    /// Responde con `responder` los lotes que mande el otro extremo
+    pub fn with_responder(mut self, responder: Arc<dyn SyncResponder>) -> Self {
+        self.responder = Some(responder);
+        self
+    }
+
     /// Crear con configuración por defecto
     pub fn new_default(event_sender: mpsc::Sender<SyncEvent>) -> Self {
         Self::new(P2PConfig::default(), event_sender)
     }
 
-    /// Iniciar conexión con un dispositivo
-    pub async fn connect(&mut self, device: DeviceInfo) -> Result<()> {
+    /// Iniciar conexión con un dispositivo: manda la oferta de `local_device`
+    /// por `signaling` y espera a que se establezca la conexión
+    pub async fn connect(&mut self, device: DeviceInfo, local_device: DeviceId, signaling: &dyn SignalingChannel) -> Result<()> {
         if *self.state.read().await == P2PConnectionState::Connected {
             return Err(anyhow!("Ya hay una conexión activa"));
         }
@@ -138,30 +269,112 @@ impl P2PConnection {
         *self.state.write().await = P2PConnectionState::Connecting;
         self.remote_device = Some(device.clone());
 
-        // Crear conexión WebRTC
-        self.create_peer_connection().await?;
+        let result = async {
+            // Crear conexión WebRTC
+            self.create_peer_connection().await?;
 
-        // Crear canal de datos
-        self.create_data_channel().await?;
+            // Crear canal de datos
+            self.create_data_channel().await?;
 
-        // Generar oferta
-        let offer = self.create_offer().await?;
+            // Generar oferta y mandarla al dispositivo remoto
+            let (trickle, route) = match signaling.trickles() {
+                true => {
+                    let (trickle, route) = trickle();
+                    (Some(trickle), Some(route))
+                }
+                false => (None, None),
+            };
+            let remote = self.send_local_candidates(trickle);
+            let offer = self.create_offer(remote.is_none()).await?;
+            let answer = signaling.send_offer(local_device, device.id, offer, route).await?;
+            self.process_answer(answer).await?;
+            self.add_remote_candidates(remote);
+
+            self.wait_until_connected().await
+        }.await;
+
+        if let Err(e) = &result {
+            log::warn!("No se pudo conectar con {}: {}", device.name, e);
+            let _ = self.disconnect().await;
+            *self.state.write().await = P2PConnectionState::Error(e.to_string());
+        }
+        result
+    }
 
-        // TODO: Enviar oferta al dispositivo remoto
-        log::info!("Oferta WebRTC generada, enviando al dispositivo remoto...");
+    /// Responde la oferta de un dispositivo que quiere conectarse y devuelve
+    /// la respuesta SDP que hay que mandarle. La conexión se establece cuando
+    /// el otro extremo la procesa. Con `trickle` la respuesta sale enseguida
+    /// y los candidatos van después. El canal de datos llega cuando el otro
+    /// extremo lo abre; hasta entonces la conexión sigue en `Connecting`.
+    pub async fn accept(&mut self, device: DeviceInfo, offer_sdp: String, trickle: Option<Trickle>) -> Result<String> {
+        if *self.state.read().await == P2PConnectionState::Connected {
+            return Err(anyhow!("Ya hay una conexión activa"));
+        }
+        self.verify_remote_sdp(&offer_sdp)?;
 
-        Ok(())
+        log::info!("Aceptando conexión P2P de: {} ({})", device.name, device.device_type.display_name());
+
+        *self.state.write().await = P2PConnectionState::Connecting;
+        self.remote_device = Some(device);
+
+        let result = async {
+            self.create_peer_connection().await?;
+            let remote = self.send_local_candidates(trickle);
+            let pc = self.peer_connection.clone()
+                .ok_or_else(|| anyhow!("Conexión peer no inicializada"))?;
+            self.receive_data_channel(&pc);
+            self.remote_candidates.fetch_add(count_candidates(&offer_sdp), Ordering::Relaxed);
+            pc.set_remote_description(RTCSessionDescription::offer(offer_sdp)?).await?;
+
+            let answer = pc.create_answer(None).await?;
+            let answer = self.set_local_description(answer, remote.is_none()).await?;
+            self.add_remote_candidates(remote);
+            Ok::<_, anyhow::Error>(answer)
+        }.await;
+
+        if let Err(e) = &result {
+            let _ = self.disconnect().await;
+            *self.state.write().await = P2PConnectionState::Error(e.to_string());
+        }
+        result
+    }
+
+    /// Espera a que la conexión se establezca, como mucho
+    /// `connection_timeout` segundos
+    async fn wait_until_connected(&self) -> Result<()> {
+        let wait = Duration::from_secs(self.config.connection_timeout);
+        tokio::time::timeout(wait, async {
+            loop {
+                let notified = self.state_changed.notified();
+                match &*self.state.read().await {
+                    P2PConnectionState::Connected => return Ok(()),
+                    P2PConnectionState::Error(e) => return Err(anyhow!("{}", e)),
+                    P2PConnectionState::Disconnected => return Err(anyhow!("El dispositivo remoto cerró la conexión")),
+                    _ => {}
+                }
+                notified.await;
+            }
+        }).await
+        .map_err(|_| anyhow!("La conexión no se estableció en {} segundos", wait.as_secs()))?
     }
 
     /// Crear la conexión peer
     async fn create_peer_connection(&mut self) -> Result<()> {
+        let stun = self.config.ice_servers.iter()
+            .map(|server| RTCIceServer {
+                urls: vec![server.clone()],
+                ..Default::default()
+            });
+        let turn = self.config.turn_servers.iter()
+            .map(|server| RTCIceServer {
+                urls: server.urls.clone(),
+                username: server.username.clone(),
+                credential: server.credential.clone(),
+                credential_type: RTCIceCredentialType::Password,
+            });
         let config = RTCConfiguration {
-            ice_servers: self.config.ice_servers.iter()
-                .map(|server| webrtc::ice_transport::ice_server::RTCIceServer {
-                    urls: vec![server.clone()],
-                    ..Default::default()
-                })
-                .collect(),
+            ice_servers: stun.chain(turn).collect(),
+            certificates: self.identity.iter().map(|identity| identity.certificate()).collect(),
             ..Default::default()
         };
 
@@ -169,7 +382,7 @@ impl P2PConnection {
             .with_setting_engine(Default::default())
             .build();
 
-        let peer_connection = api.new_peer_connection(config).await?;
+        let peer_connection = Arc::new(api.new_peer_connection(config).await?);
 
         // Configurar manejadores de eventos
         self.setup_peer_connection_handlers(&peer_connection).await?;
@@ -180,23 +393,28 @@ impl P2PConnection {
 
     /// Configurar manejadores de eventos de la conexión peer
     async fn setup_peer_connection_handlers(&self, pc: &RTCPeerConnection) -> Result<()> {
-        let state = self.state.clone();
-        let event_sender = self.event_sender.clone();
+        let handlers = self.channel_handlers();
+
+        // El par de candidatos elegido dice si la conexión es directa o va por
+        // un servidor TURN
+        pc.sctp().transport().ice_transport().on_selected_candidate_pair_change(Box::new(|pair: RTCIceCandidatePair| {
+            Box::pin(async move {
+                log::info!("Par de candidatos ICE elegido: {}", pair);
+            })
+        }));
 
         // Manejador de cambio de estado
+        // Sin el canal de datos abierto todavía no se puede sincronizar
         pc.on_peer_connection_state_change(Box::new(move |s: RTCPeerConnectionState| {
-            let state = state.clone();
-            let event_sender = event_sender.clone();
-            
+            let handlers = handlers.clone();
             Box::pin(async move {
                 let new_state = match s {
-                    RTCPeerConnectionState::Connected => P2PConnectionState::Connected,
-                    RTCPeerConnectionState::Disconnected => P2PConnectionState::Disconnected,
+                    RTCPeerConnectionState::Connected if handlers.channel_open().await => P2PConnectionState::Connected,
+                    RTCPeerConnectionState::Disconnected | RTCPeerConnectionState::Closed => P2PConnectionState::Disconnected,
                     RTCPeerConnectionState::Failed => P2PConnectionState::Error("Conexión falló".to_string()),
                     _ => P2PConnectionState::Connecting,
                 };
-
-                *state.write().await = new_state.clone();
+                handlers.transition(new_state).await;
             })
         }));
 
@@ -213,68 +431,149 @@ impl P2PConnection {
             ..Default::default()
         };
 
-        let data_channel = pc.create_data_channel("alohopass-sync", Some(data_channel_init)).await?;
-
-        // Configurar manejadores del canal de datos
-        self.setup_data_channel_handlers(&data_channel).await?;
-
-        self.data_channel = Some(data_channel);
+        let data_channel = pc.create_data_channel(DATA_CHANNEL_LABEL, Some(data_channel_init)).await?;
+        self.channel_handlers().attach(data_channel).await;
         Ok(())
     }
 
-    /// Configurar manejadores del canal de datos
-    async fn setup_data_channel_handlers(&self, dc: &Arc<webrtc::data_channel::RTCDataChannel>) -> Result<()> {
-        let pending_data = self.pending_data.clone();
-        let event_sender = self.event_sender.clone();
-
-        // Manejador de datos recibidos
-        dc.on_message(Box::new(move |msg: webrtc::data_channel::data_channel_message::DataChannelMessage| {
+    /// Del lado que responde la oferta, toma el canal de datos que abre el
+    /// otro extremo. Hay que llamarla antes de establecer la descripción
+    /// remota.
+    fn receive_data_channel(&self, pc: &RTCPeerConnection) {
+        let handlers = self.channel_handlers();
+        pc.on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
+            let handlers = handlers.clone();
             Box::pin(async move {
-                match msg.is_string {
-                    true => {
-                        // Mensaje de texto
-                        if let Ok(text) = String::from_utf8(msg.data.to_vec()) {
-                            log::info!("Mensaje de texto recibido: {}", text);
-                            // TODO: Procesar mensaje de texto
-                        }
-                    }
-                    false => {
-                        // Mensaje binario
-                        log::info!("Mensaje binario recibido: {} bytes", msg.data.len());
-                        // TODO: Procesar mensaje binario
-                    }
+                if dc.label() != DATA_CHANNEL_LABEL {
+                    log::warn!("Se cierra un canal de datos inesperado: {}", dc.label());
+                    let _ = dc.close().await;
+                    return;
                 }
+                log::info!("El dispositivo remoto abrió el canal de datos");
+                handlers.attach(dc).await;
             })
         }));
+    }
 
-        Ok(())
+    /// Lo que necesitan los manejadores de la conexión y del canal de datos
+    fn channel_handlers(&self) -> ChannelHandlers {
+        ChannelHandlers {
+            data_channel: self.data_channel.clone(),
+            state: self.state.clone(),
+            state_changed: self.state_changed.clone(),
+            event_sender: self.event_sender.clone(),
+            remote_device: self.remote_device.clone(),
+            reassembler: self.reassembler.clone(),
+            next_message_id: self.next_message_id.clone(),
+            send_queue: self.send_queue.clone(),
+            pending_data: self.pending_data.clone(),
+            data_received: self.data_received.clone(),
+            responder: self.responder.clone(),
+        }
     }
 
     /// Crear oferta WebRTC
-    async fn create_offer(&self) -> Result<String> {
+    async fn create_offer(&self, gather: bool) -> Result<String> {
         let pc = self.peer_connection.as_ref()
             .ok_or_else(|| anyhow!("Conexión peer no inicializada"))?;
 
         let offer = pc.create_offer(None).await?;
+        self.set_local_description(offer, gather).await
+    }
+
+    /// Establece la descripción local y devuelve la SDP que hay que mandar.
+    /// Con `gather` espera a reunir los candidatos ICE, así la SDP ya lleva
+    /// todas las direcciones de este extremo.
+    async fn set_local_description(&self, description: RTCSessionDescription, gather: bool) -> Result<String> {
+        let pc = self.peer_connection.as_ref()
+            .ok_or_else(|| anyhow!("Conexión peer no inicializada"))?;
 
-        // Establecer descripción local
-        pc.set_local_description(offer).await?;
+        // Hay que pedir el aviso antes de empezar a reunirlos
+        let mut gathering_complete = pc.gathering_complete_promise().await;
+        pc.set_local_description(description).await?;
+        if gather {
+            let wait = Duration::from_secs(self.config.connection_timeout);
+            if tokio::time::timeout(wait, gathering_complete.recv()).await.is_err() {
+                log::warn!("No se reunieron todos los candidatos ICE en {} segundos; se manda lo que hay", wait.as_secs());
+            }
+        }
 
-        // Obtener oferta SDP
         let sdp = pc.local_description().await
             .ok_or_else(|| anyhow!("No se pudo obtener la descripción local"))?;
+        if gather {
+            self.local_candidates.fetch_add(count_candidates(&sdp.sdp), Ordering::Relaxed);
+        }
+
+        Ok(sdp.sdp)
+    }
 
-        Ok(sdp.sdp.clone())
+    /// Manda por `trickle` los candidatos de este extremo a medida que
+    /// aparecen y devuelve por dónde llegan los del otro. Hay que llamarla
+    /// antes de establecer la descripción local.
+    fn send_local_candidates(&self, trickle: Option<Trickle>) -> Option<mpsc::UnboundedReceiver<RTCIceCandidateInit>> {
+        let Trickle { local, remote } = trickle?;
+        let pc = self.peer_connection.as_ref()?;
+        let local_candidates = self.local_candidates.clone();
+        pc.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let local = local.clone();
+            let local_candidates = local_candidates.clone();
+            Box::pin(async move {
+                let candidate = match candidate.map(|candidate| candidate.to_json()) {
+                    Some(Ok(candidate)) => Some(candidate),
+                    Some(Err(e)) => {
+                        log::warn!("Candidato ICE local inválido: {}", e);
+                        return;
+                    }
+                    // Se terminaron de reunir
+                    None => None,
+                };
+                if candidate.is_some() {
+                    local_candidates.fetch_add(1, Ordering::Relaxed);
+                }
+                let _ = local.send(candidate);
+            })
+        }));
+        Some(remote)
+    }
+
+    /// Agrega los candidatos del otro extremo a medida que llegan. Hay que
+    /// llamarla después de establecer la descripción remota.
+    fn add_remote_candidates(&mut self, remote: Option<mpsc::UnboundedReceiver<RTCIceCandidateInit>>) {
+        let (Some(mut remote), Some(pc)) = (remote, self.peer_connection.clone()) else {
+            return;
+        };
+        let remote_candidates = self.remote_candidates.clone();
+        self.trickle_task = Some(tokio::spawn(async move {
+            while let Some(candidate) = remote.recv().await {
+                match pc.add_ice_candidate(candidate).await {
+                    Ok(()) => {
+                        remote_candidates.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => log::warn!("Candidato ICE remoto rechazado: {}", e),
+                }
+            }
+        }));
+    }
+
+    /// DTLS solo comprueba el certificado contra la huella de la SDP, así que
+    /// la huella tiene que ser la fijada al emparejarse
+    fn verify_remote_sdp(&self, sdp: &str) -> Result<()> {
+        let pinned = self.peer
+            .ok_or_else(|| anyhow!("El dispositivo remoto no tiene una identidad fijada"))?;
+        let presented = sdp_fingerprint(sdp)
+            .ok_or_else(|| anyhow!("La SDP remota no trae la huella del certificado"))?;
+        verify_fingerprint(&pinned.fingerprint, &presented)
     }
 
     /// Procesar respuesta del dispositivo remoto
     pub async fn process_answer(&mut self, answer_sdp: String) -> Result<()> {
-        let pc = self.peer_connection.as_mut()
+        self.verify_remote_sdp(&answer_sdp)?;
+
+        let pc = self.peer_connection.as_ref()
             .ok_or_else(|| anyhow!("Conexión peer no inicializada"))?;
+        self.remote_candidates.fetch_add(count_candidates(&answer_sdp), Ordering::Relaxed);
 
-        let answer = webrtc::peer_connection::sdp::session_description::RTCSessionDescription::answer(
-            answer_sdp,
-        )?;
+        let answer = RTCSessionDescription::answer(answer_sdp)?;
 
         pc.set_remote_description(answer).await?;
 
@@ -282,16 +581,17 @@ impl P2PConnection {
         Ok(())
     }
 
-    /// Enviar datos a través de la conexión P2P
-    pub async fn send_data(&self, data: Vec<u8>) -> Result<()> {
-        let dc = self.data_channel.as_ref()
+    /// Manda un lote para que lo responda el otro extremo
+    pub async fn send_data(&self, data: &[u8]) -> Result<()> {
+        let dc = self.data_channel.read().await.clone()
             .ok_or_else(|| anyhow!("Canal de datos no disponible"))?;
 
         if *self.state.read().await != P2PConnectionState::Connected {
             return Err(anyhow!("Conexión no está establecida"));
         }
 
-        dc.send(&bytes::Bytes::from(data.clone())).await?;
+        let message_id = self.next_message_id.fetch_add(1, Ordering::Relaxed);
+        self.send_queue.send(&dc, MessageKind::Request, message_id, data).await?;
         log::debug!("Datos enviados: {} bytes", data.len());
 
         Ok(())
@@ -299,7 +599,7 @@ impl P2PConnection {
 
     /// Enviar texto a través de la conexión P2P
     pub async fn send_text(&self, text: String) -> Result<()> {
-        let dc = self.data_channel.as_ref()
+        let dc = self.data_channel.read().await.clone()
             .ok_or_else(|| anyhow!("Canal de datos no disponible"))?;
 
         if *self.state.read().await != P2PConnectionState::Connected {
@@ -320,16 +620,79 @@ impl P2PConnection {
         data
     }
 
+    /// Manda un mensaje y espera la respuesta, sin cifrar
+    async fn request(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.send_data(data).await?;
+        self.next_data().await
+    }
+
+    /// Saludo Noise con el dispositivo remoto: solo sigue si su clave
+    /// estática es la fijada
+    async fn handshake(&self) -> Result<Session> {
+        let (Some(identity), Some(peer)) = (&self.identity, self.peer) else {
+            return Err(anyhow!("El dispositivo remoto no tiene una identidad fijada"));
+        };
+        let (initiator, hello) = Initiator::start(identity.static_secret(), peer.static_key)?;
+        let (session, finish) = initiator.finish(&self.request(&hello).await?)?;
+        self.request(&finish).await?;
+        Ok(session)
+    }
+
+    /// Espera la respuesta del otro extremo, como mucho `connection_timeout`
+    /// segundos. Falla enseguida si la conexión se cae mientras tanto.
+    async fn next_data(&self) -> Result<Vec<u8>> {
+        let wait = Duration::from_secs(self.config.connection_timeout);
+        tokio::time::timeout(wait, async {
+            loop {
+                let state_changed = self.state_changed.notified();
+                let data_received = self.data_received.notified();
+                {
+                    let mut pending = self.pending_data.write().await;
+                    if !pending.is_empty() {
+                        return Ok(pending.remove(0));
+                    }
+                }
+                if !self.is_connected().await {
+                    return Err(anyhow!("La conexión se cortó antes de la respuesta"));
+                }
+                tokio::select! {
+                    _ = data_received => {}
+                    _ = state_changed => {}
+                }
+            }
+        }).await
+        .map_err(|_| anyhow!("El dispositivo remoto no respondió en {} segundos", wait.as_secs()))?
+    }
+
+    /// Cierra la conexión que se reemplaza al reconectar, que ya no se puede
+    /// soltar porque está compartida
+    async fn close(&self) {
+        if let Some(task) = &self.trickle_task {
+            task.abort();
+        }
+        let data_channel = self.data_channel.read().await.clone();
+        if let Some(dc) = data_channel {
+            let _ = dc.close().await;
+        }
+        if let Some(pc) = &self.peer_connection {
+            let _ = pc.close().await;
+        }
+    }
+
     /// Desconectar
     pub async fn disconnect(&mut self) -> Result<()> {
         log::info!("Desconectando conexión P2P...");
 
         // Cerrar canal de datos
-        if let Some(dc) = self.data_channel.take() {
+        let data_channel = self.data_channel.write().await.take();
+        if let Some(dc) = data_channel {
             dc.close().await?;
         }
 
         // Cerrar conexión peer
+        if let Some(task) = self.trickle_task.take() {
+            task.abort();
+        }
         if let Some(pc) = self.peer_connection.take() {
             pc.close().await?;
         }
@@ -370,11 +733,359 @@ impl P2PConnection {
         let is_connected = *state == P2PConnectionState::Connected;
         let remote_device = self.remote_device.clone();
 
+        let buffered_amount = match self.data_channel.read().await.clone() {
+            Some(dc) => dc.buffered_amount().await,
+            None => 0,
+        };
+        let selected_candidate_pair = match &self.peer_connection {
+            Some(pc) => pc.sctp().transport().ice_transport().get_selected_candidate_pair().await
+                .map(|pair| pair.to_string()),
+            None => None,
+        };
+
         P2PConnectionStats {
             state: state.clone(),
             is_connected,
             remote_device,
             pending_data_count: self.pending_data.read().await.len(),
+            send_queue_depth: self.send_queue.depth(),
+            buffered_amount,
+            selected_candidate_pair,
+            local_candidates: self.local_candidates.load(Ordering::Relaxed),
+            remote_candidates: self.remote_candidates.load(Ordering::Relaxed),
+            turn_servers: self.config.turn_servers.len(),
+        }
+    }
+}
+
+#[async_trait]
+impl SyncTransport for P2PConnection {
+    /// Manda el lote cifrado por el canal de datos y espera la respuesta del
+    /// otro extremo. Si algo falla, el próximo lote empieza otro saludo.
+    async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        let mut session = self.session.lock().await;
+        if session.is_none() {
+            *session = Some(self.handshake().await?);
+        }
+        let Some(current) = session.as_mut() else {
+            return Err(anyhow!("No hay sesión Noise"));
+        };
+        let result = match current.seal(&payload) {
+            Ok(sealed) => match self.request(&sealed).await {
+                Ok(reply) => current.open(&reply),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            *session = None;
+        }
+        result
+    }
+
+    async fn diagnostics(&self) -> Option<P2PConnectionStats> {
+        Some(self.get_stats().await)
+    }
+}
+
+/// Abre otra vez la conexión con un dispositivo cuando la anterior se cae
+#[async_trait]
+pub trait Redial: Send + Sync {
+    async fn redial(&self, device_id: DeviceId) -> Result<P2PConnection>;
+}
+
+/// Conexión que se vuelve a abrir si se cae en medio de un intercambio. El
+/// lote que no llegó a responderse se manda otra vez tal cual por la conexión
+/// nueva, así la sincronización sigue donde estaba en vez de empezar de cero.
+pub struct ReconnectingConnection {
+    device_id: DeviceId,
+    connection: RwLock<Arc<P2PConnection>>,
+    redial: Arc<dyn Redial>,
+    policy: RetryPolicy,
+}
+
+impl ReconnectingConnection {
+    /// Los intentos y la espera salen de la configuración de `connection`
+    pub fn new(device_id: DeviceId, connection: P2PConnection, redial: Arc<dyn Redial>) -> Self {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(connection.config.reconnect_delay),
+            max_delay: Duration::from_secs(connection.config.connection_timeout),
+            max_attempts: connection.config.max_reconnect_attempts,
+        };
+        Self {
+            device_id,
+            connection: RwLock::new(Arc::new(connection)),
+            redial,
+            policy,
+        }
+    }
+
+    /// Reemplaza la conexión caída por una nueva; falla si se agotaron los
+    /// intentos
+    async fn reconnect(&self, dropped: &P2PConnection) -> Result<()> {
+        dropped.channel_handlers().transition(P2PConnectionState::Reconnecting).await;
+        let mut last_error = anyhow!("No se intentó reconectar");
+        for attempt in 1..=self.policy.max_attempts {
+            tokio::time::sleep(self.policy.delay(attempt)).await;
+            log::info!("Reconectando con {} (intento {}/{})", self.device_id, attempt, self.policy.max_attempts);
+            match self.redial.redial(self.device_id).await {
+                Ok(connection) => {
+                    dropped.close().await;
+                    *self.connection.write().await = Arc::new(connection);
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::warn!("No se pudo reconectar con {}: {}", self.device_id, e);
+                    last_error = e;
+                }
+            }
+        }
+        dropped.close().await;
+        let error = last_error.context(format!("No se pudo reconectar con {} en {} intentos", self.device_id, self.policy.max_attempts));
+        dropped.channel_handlers().transition(P2PConnectionState::Error(format!("{:#}", error))).await;
+        Err(error)
+    }
+}
+
+#[async_trait]
+impl SyncTransport for ReconnectingConnection {
+    async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        let connection = self.connection.read().await.clone();
+        let error = match connection.exchange(payload.clone()).await {
+            Ok(reply) => return Ok(reply),
+            Err(e) => e,
+        };
+        // Si la conexión sigue en pie el problema es otro
+        if connection.is_connected().await || self.policy.max_attempts == 0 {
+            return Err(error);
+        }
+        log::warn!("Se cortó la conexión con {} en medio del intercambio: {}", self.device_id, error);
+        self.reconnect(&connection).await?;
+
+        let connection = self.connection.read().await.clone();
+        connection.exchange(payload).await
+    }
+
+    async fn diagnostics(&self) -> Option<P2PConnectionStats> {
+        Some(self.connection.read().await.get_stats().await)
+    }
+}
+
+/// Candidatos ICE que trae una SDP
+fn count_candidates(sdp: &str) -> usize {
+    sdp.lines().filter(|line| line.starts_with("a=candidate:")).count()
+}
+
+/// Cola de trozos por mandar. Los mensajes salen de a uno y cada trozo espera
+/// a que el canal de datos tenga menos de `high_water` bytes sin mandar.
+struct SendQueue {
+    high_water: usize,
+    /// Cuánto se espera a que el canal se vacíe antes de dar el envío por
+    /// perdido
+    stall_timeout: Duration,
+    /// Trozos encolados que todavía no se le pasaron al canal
+    queued: AtomicUsize,
+    /// Avisa cuando el canal baja del umbral
+    drained: Notify,
+    /// Un mensaje a la vez
+    sending: tokio::sync::Mutex<()>,
+}
+
+impl SendQueue {
+    fn new(high_water: usize, stall_timeout: Duration) -> Self {
+        Self {
+            high_water: high_water.max(framing::CHUNK_SIZE),
+            stall_timeout,
+            queued: AtomicUsize::new(0),
+            drained: Notify::new(),
+            sending: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Por debajo de esto el canal avisa que se puede seguir mandando
+    fn low_water(&self) -> usize {
+        self.high_water / 4
+    }
+
+    fn depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Manda `message` en trozos por `dc` sin pasarse de `high_water`
+    async fn send(&self, dc: &RTCDataChannel, kind: MessageKind, message_id: u32, message: &[u8]) -> Result<()> {
+        let chunks = framing::split(kind, message_id, message)?;
+        let total = chunks.len();
+        self.queued.fetch_add(total, Ordering::Relaxed);
+
+        let mut sent = 0;
+        let result = async {
+            let _sending = self.sending.lock().await;
+            for chunk in chunks {
+                self.wait_for_room(dc).await?;
+                dc.send(&bytes::Bytes::from(chunk)).await?;
+                sent += 1;
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+            }
+            Ok(())
+        }.await;
+
+        // Lo que no salió ya no está en la cola
+        self.queued.fetch_sub(total - sent, Ordering::Relaxed);
+        result
+    }
+
+    async fn wait_for_room(&self, dc: &RTCDataChannel) -> Result<()> {
+        loop {
+            let drained = self.drained.notified();
+            if dc.buffered_amount().await < self.high_water {
+                return Ok(());
+            }
+            tokio::time::timeout(self.stall_timeout, drained).await
+                .map_err(|_| anyhow!("El canal de datos no se vació en {} segundos", self.stall_timeout.as_secs()))?;
+        }
+    }
+}
+
+/// Estado compartido con los manejadores de WebRTC, que corren fuera de la
+/// conexión
+#[derive(Clone)]
+struct ChannelHandlers {
+    data_channel: Arc<RwLock<Option<Arc<RTCDataChannel>>>>,
+    state: Arc<RwLock<P2PConnectionState>>,
+    state_changed: Arc<Notify>,
+    event_sender: mpsc::Sender<SyncEvent>,
+    remote_device: Option<DeviceInfo>,
+    reassembler: Arc<Mutex<Reassembler>>,
+    next_message_id: Arc<AtomicU32>,
+    send_queue: Arc<SendQueue>,
+    pending_data: Arc<RwLock<Vec<Vec<u8>>>>,
+    data_received: Arc<Notify>,
+    responder: Option<Arc<dyn SyncResponder>>,
+}
+
+impl ChannelHandlers {
+    /// Deja `dc` como el canal de datos de la conexión. La conexión pasa a
+    /// `Connected` cuando el canal se abre y a `Disconnected` cuando se
+    /// cierra.
+    async fn attach(&self, dc: Arc<RTCDataChannel>) {
+        let handlers = self.clone();
+        dc.on_open(Box::new(move || {
+            Box::pin(async move {
+                log::info!("Canal de datos abierto");
+                handlers.transition(P2PConnectionState::Connected).await;
+            })
+        }));
+
+        let handlers = self.clone();
+        dc.on_close(Box::new(move || {
+            let handlers = handlers.clone();
+            Box::pin(async move {
+                log::info!("Canal de datos cerrado");
+                handlers.transition(P2PConnectionState::Disconnected).await;
+            })
+        }));
+
+        let handlers = self.clone();
+        let channel = Arc::downgrade(&dc);
+        dc.on_message(Box::new(move |msg: DataChannelMessage| {
+            let handlers = handlers.clone();
+            let channel = channel.clone();
+            Box::pin(async move {
+                if msg.is_string {
+                    if let Ok(text) = String::from_utf8(msg.data.to_vec()) {
+                        log::info!("Mensaje de texto recibido: {}", text);
+                    }
+                    return;
+                }
+                handlers.receive(channel, &msg.data).await;
+            })
+        }));
+
+        let send_queue = self.send_queue.clone();
+        dc.set_buffered_amount_low_threshold(send_queue.low_water()).await;
+        dc.on_buffered_amount_low(Box::new(move || {
+            let send_queue = send_queue.clone();
+            Box::pin(async move {
+                send_queue.drained.notify_one();
+            })
+        })).await;
+
+        // Si ya estaba abierto, `on_open` no se va a llamar
+        let open = dc.ready_state() == RTCDataChannelState::Open;
+        *self.data_channel.write().await = Some(dc);
+        if open {
+            self.transition(P2PConnectionState::Connected).await;
+        }
+    }
+
+    /// Rearma el mensaje al que pertenece `chunk`. Las respuestas quedan para
+    /// quien espera en `exchange`; los pedidos los responde el
+    /// [`SyncResponder`] aparte, para no frenar los mensajes que siguen.
+    async fn receive(&self, channel: Weak<RTCDataChannel>, chunk: &[u8]) {
+        let message = match self.reassembler.lock() {
+            Ok(mut reassembler) => reassembler.push(chunk),
+            Err(_) => Err(anyhow!("El rearmado de mensajes quedó inconsistente")),
+        };
+        let (kind, message) = match message {
+            Ok(Some(message)) => message,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Se descarta un mensaje del canal de datos: {}", e);
+                return;
+            }
+        };
+        log::info!("Mensaje binario recibido: {} bytes", message.len());
+
+        match kind {
+            MessageKind::Reply => {
+                self.pending_data.write().await.push(message);
+                self.data_received.notify_one();
+            }
+            MessageKind::Request => {
+                let (Some(responder), Some(device)) = (self.responder.clone(), self.remote_device.clone()) else {
+                    log::warn!("Se descarta un lote: no hay quién lo responda");
+                    return;
+                };
+                let request = message;
+                let message_id = self.next_message_id.fetch_add(1, Ordering::Relaxed);
+                let send_queue = self.send_queue.clone();
+                tokio::spawn(async move {
+                    let reply = match responder.respond(device.id, &request).await {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            log::warn!("No se pudo responder el lote de {}: {}", device.name, e);
+                            return;
+                        }
+                    };
+                    let Some(dc) = channel.upgrade() else {
+                        return;
+                    };
+                    if let Err(e) = send_queue.send(&dc, MessageKind::Reply, message_id, &reply).await {
+                        log::warn!("No se pudo mandar la respuesta a {}: {}", device.name, e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Si el canal de datos ya está abierto
+    async fn channel_open(&self) -> bool {
+        self.data_channel.read().await.as_ref()
+            .is_some_and(|dc| dc.ready_state() == RTCDataChannelState::Open)
+    }
+
+    /// Cambia el estado y avisa al gestor al entrar o salir de la conexión
+    async fn transition(&self, new_state: P2PConnectionState) {
+        let previous = std::mem::replace(&mut *self.state.write().await, new_state.clone());
+        self.state_changed.notify_waiters();
+
+        let event = match (previous == P2PConnectionState::Connected, new_state == P2PConnectionState::Connected) {
+            (false, true) => self.remote_device.clone().map(SyncEvent::DeviceConnected),
+            (true, false) => self.remote_device.clone().map(SyncEvent::DeviceDisconnected),
+            _ => None,
+        };
+        if let Some(event) = event {
+            let _ = self.event_sender.send(event).await;
         }
     }
 }
@@ -390,6 +1101,18 @@ pub struct P2PConnectionStats {
     pub remote_device: Option<DeviceInfo>,
     /// Cantidad de datos pendientes
     pub pending_data_count: usize,
+    /// Trozos en la cola esperando a que el canal de datos tenga lugar
+    pub send_queue_depth: usize,
+    /// Bytes que el canal de datos todavía no mandó
+    pub buffered_amount: usize,
+    /// Par de candidatos ICE por el que va la conexión, p. ej.
+    /// `(local) udp host 192.168.1.5:50000 <-> (remote) udp relay 203.0.113.7:3478`
+    pub selected_candidate_pair: Option<String>,
+    /// Candidatos ICE de este extremo y del otro
+    pub local_candidates: usize,
+    pub remote_candidates: usize,
+    /// Servidores TURN configurados
+    pub turn_servers: usize,
 }
 
 impl Default for P2PConnectionStats {
@@ -399,6 +1122,12 @@ impl Default for P2PConnectionStats {
             is_connected: false,
             remote_device: None,
             pending_data_count: 0,
+            send_queue_depth: 0,
+            buffered_amount: 0,
+            selected_candidate_pair: None,
+            local_candidates: 0,
+            remote_candidates: 0,
+            turn_servers: 0,
         }
     }
 }
@@ -440,9 +1169,34 @@ mod tests {
         
         assert_eq!(config.port, 0);
         assert!(!config.ice_servers.is_empty());
+        assert!(config.turn_servers.is_empty());
         assert!(config.encrypted);
     }
 
+    /// Nunca logra volver a conectar
+    struct Unreachable(AtomicUsize);
+
+    #[async_trait]
+    impl Redial for Unreachable {
+        async fn redial(&self, _device_id: DeviceId) -> Result<P2PConnection> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Err(anyhow!("Sin red"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_reconnecting_after_max_attempts() {
+        let (sender, _) = mpsc::channel(10);
+        let config = P2PConfig { reconnect_delay: 0, max_reconnect_attempts: 2, ..P2PConfig::default() };
+        let redial = Arc::new(Unreachable(AtomicUsize::new(0)));
+        let connection = ReconnectingConnection::new(DeviceId::new(), P2PConnection::new(config, sender), redial.clone());
+
+        assert!(connection.exchange(b"lote".to_vec()).await.is_err());
+        assert_eq!(redial.0.load(Ordering::Relaxed), 2);
+        let stats = connection.diagnostics().await.unwrap();
+        assert!(matches!(stats.state, P2PConnectionState::Error(_)));
+    }
+
     #[test]
     fn test_p2p_connection_state_display() {
         let state = P2PConnectionState::Connected;
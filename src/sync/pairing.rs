@@ -0,0 +1,654 @@
+//! Emparejamiento de dispositivos con un código corto
+//!
+//! Antes de sincronizar, los dos dispositivos acuerdan una clave con X25519 y
+//! muestran un código de 6 dígitos (y unos emojis) derivado de las dos claves
+//! públicas. Si el usuario ve el mismo código en las dos pantallas, nadie se
+//! metió en medio, y solo entonces se deriva la clave de sincronización.
+//!
+//! El intercambio sigue tres mensajes:
+//!
+//! 1. Quien inicia manda un compromiso: el hash de su clave pública y un nonce.
+//! 2. Quien responde manda su clave pública y su nonce.
+//! 3. Quien inicia revela su clave y su nonce, que deben coincidir con el
+//!    compromiso.
+//!
+//! Como quien inicia se compromete antes de ver la clave del otro, un atacante
+//! en medio no puede buscar claves que den el mismo código: tiene una sola
+//! oportunidad entre un millón.
+//!
+//! Con un código QR no hace falta comparar nada: el QR lleva la clave pública
+//! de quien lo muestra y un secreto de un solo uso. Quien lo escanea responde
+//! con su clave y una prueba HMAC hecha con el secreto, que solo pudo obtener
+//! viendo la pantalla.
+//!
+//! En los dos casos cada dispositivo manda también la huella de su
+//! certificado y su clave estática del saludo Noise (ver
+//! [`crate::sync::identity`]), que entran en el compromiso, en la prueba y en
+//! el código. Así la identidad que queda fijada es la del dispositivo con el
+//! que el usuario comparó el código.
+//!
+//! Con las claves va el nombre y el tipo que cada dispositivo eligió para sí
+//! ([`DeviceProfile`]), para que el otro lo guarde con ese nombre. No entran
+//! en el código: solo sirven para mostrarlos.
+
+use crate::models::DeviceId;
+use crate::sync::identity::PublicIdentity;
+use crate::sync::{DeviceProfile, DeviceType};
+use anyhow::{anyhow, Result};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+/// Tiempo que puede quedar abierto un emparejamiento sin completarse
+pub const PAIRING_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const COMMIT_CONTEXT: &[u8] = b"alohopass-pairing-commit-v1";
+const CODE_CONTEXT: &[u8] = b"alohopass-pairing-code-v1";
+const SYNC_KEY_INFO: &[u8] = b"alohopass-sync-key-v1";
+const INVITE_CONTEXT: &[u8] = b"alohopass-pairing-qr-v1";
+/// Comienzo de la URI que va en el código QR
+const INVITE_PREFIX: &str = "alohopass://pair?";
+/// Versión del formato de la URI
+const INVITE_VERSION: &str = "3";
+
+/// Emojis del código, 64 para que cada uno salga de 6 bits
+const CODE_EMOJI: [&str; 64] = [
+    "🐶", "🐱", "🐭", "🐹", "🐰", "🦊", "🐻", "🐼",
+    "🐨", "🐯", "🦁", "🐮", "🐷", "🐸", "🐵", "🐔",
+    "🐧", "🐦", "🦆", "🦉", "🐴", "🦄", "🐝", "🐛",
+    "🦋", "🐌", "🐢", "🐍", "🐙", "🦀", "🐬", "🐳",
+    "🌵", "🌲", "🌻", "🍄", "🌙", "⭐", "🔥", "🌈",
+    "🍎", "🍋", "🍌", "🍇", "🍓", "🥕", "🌽", "🍕",
+    "⚽", "🏀", "🎸", "🎲", "🚲", "🚗", "✈️", "🚀",
+    "⏰", "🔑", "🔔", "🎁", "📚", "✏️", "🎈", "⚓",
+];
+
+/// Papel de este dispositivo en el emparejamiento
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PairingRole {
+    Initiator,
+    Responder,
+}
+
+/// Mensajes que se intercambian los dispositivos al emparejarse. Las claves,
+/// huellas, nonces y hashes van en hexadecimal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PairingMessage {
+    /// Compromiso de quien inicia
+    Commit { commitment: String },
+    /// Clave de quien responde
+    #[serde(rename_all = "camelCase")]
+    Key {
+        public_key: String,
+        identity: String,
+        static_key: String,
+        nonce: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<DeviceProfile>,
+    },
+    /// Clave de quien inicia, que debe cumplir el compromiso
+    #[serde(rename_all = "camelCase")]
+    Reveal {
+        public_key: String,
+        identity: String,
+        static_key: String,
+        nonce: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<DeviceProfile>,
+    },
+    /// Clave de quien escaneó un código QR, con la prueba de que lo vio
+    #[serde(rename_all = "camelCase")]
+    Join {
+        public_key: String,
+        identity: String,
+        static_key: String,
+        nonce: String,
+        proof: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<DeviceProfile>,
+    },
+    /// El usuario rechazó el código o canceló
+    Cancel,
+}
+
+impl PairingMessage {
+    /// El mismo mensaje presentando a este dispositivo con `profile`, si es
+    /// uno de los que llevan la clave
+    pub fn with_device(mut self, profile: DeviceProfile) -> Self {
+        if let PairingMessage::Key { device, .. }
+            | PairingMessage::Reveal { device, .. }
+            | PairingMessage::Join { device, .. } = &mut self {
+            *device = Some(profile);
+        }
+        self
+    }
+}
+
+/// Contenido del código QR de emparejamiento, como una URI
+/// `alohopass://pair?v=3&id=…&key=…&cert=…&noise=…&secret=…&addr=…&name=…&type=…`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairingInvite {
+    /// Dispositivo que muestra el código
+    pub device_id: DeviceId,
+    pub public_key: [u8; 32],
+    /// Huella del certificado y clave estática del dispositivo que muestra el
+    /// código
+    pub identity: PublicIdentity,
+    /// Secreto de un solo uso; también es el nonce de quien muestra el código
+    pub secret: [u8; 32],
+    /// Dirección donde se puede contactar al dispositivo, si se conoce
+    pub address: Option<String>,
+    /// Nombre y tipo del dispositivo que muestra el código. El nombre va en
+    /// hexadecimal para no tener que escapar la URI.
+    pub device: Option<DeviceProfile>,
+}
+
+impl PairingInvite {
+    pub fn to_uri(&self) -> String {
+        let mut uri = format!(
+            "{}v={}&id={}&key={}&cert={}&noise={}&secret={}",
+            INVITE_PREFIX,
+            INVITE_VERSION,
+            self.device_id,
+            hex::encode(self.public_key),
+            hex::encode(self.identity.fingerprint),
+            hex::encode(self.identity.static_key),
+            hex::encode(self.secret),
+        );
+        if let Some(address) = &self.address {
+            uri.push_str("&addr=");
+            uri.push_str(address);
+        }
+        if let Some(name) = self.device.as_ref().and_then(|device| device.name.as_ref()) {
+            uri.push_str("&name=");
+            uri.push_str(&hex::encode(name));
+        }
+        if let Some(device_type) = self.device.as_ref().and_then(|device| device.device_type.as_ref()) {
+            uri.push_str("&type=");
+            uri.push_str(&device_type.to_string());
+        }
+        uri
+    }
+
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let query = uri.trim().strip_prefix(INVITE_PREFIX)
+            .ok_or_else(|| anyhow!("No es un código de emparejamiento de Alohopass"))?;
+        let params: HashMap<&str, &str> = query.split('&')
+            .filter_map(|param| param.split_once('='))
+            .collect();
+        let param = |name: &str| params.get(name).copied()
+            .ok_or_else(|| anyhow!("Falta `{}` en el código de emparejamiento", name));
+        if param("v")? != INVITE_VERSION {
+            return Err(anyhow!("Versión de código de emparejamiento no soportada"));
+        }
+        Ok(Self {
+            device_id: param("id")?.parse()?,
+            public_key: decode_array(param("key")?)?,
+            identity: PublicIdentity {
+                fingerprint: decode_array(param("cert")?)?,
+                static_key: decode_array(param("noise")?)?,
+            },
+            secret: decode_array(param("secret")?)?,
+            address: params.get("addr").filter(|address| !address.is_empty()).map(|address| address.to_string()),
+            device: invite_device(&params),
+        })
+    }
+}
+
+/// Nombre y tipo de un código QR; `None` si no trae ninguno de los dos. Lo
+/// que no se entiende se ignora, como en un código de una versión anterior.
+fn invite_device(params: &HashMap<&str, &str>) -> Option<DeviceProfile> {
+    let device = DeviceProfile {
+        name: params.get("name")
+            .and_then(|name| hex::decode(name).ok())
+            .and_then(|name| String::from_utf8(name).ok()),
+        device_type: params.get("type").and_then(|device_type| device_type.parse::<DeviceType>().ok()),
+    }.normalized().ok()?;
+    (device != DeviceProfile::default()).then_some(device)
+}
+
+/// Código que el usuario compara en los dos dispositivos
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingCode {
+    pub digits: String,
+    pub emoji: Vec<String>,
+}
+
+/// Resultado de un emparejamiento confirmado
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairedKey {
+    /// Clave de sincronización compartida con el otro dispositivo
+    pub sync_key: [u8; 32],
+    /// Huella en hexadecimal del certificado del otro dispositivo, la que se
+    /// fija para las conexiones siguientes
+    pub peer_fingerprint: String,
+    /// Clave estática en hexadecimal del otro dispositivo, la que se fija
+    /// para el saludo Noise
+    pub peer_static_key: String,
+    /// Nombre y tipo con los que se presentó el otro dispositivo, si los mandó
+    pub peer_device: Option<DeviceProfile>,
+}
+
+/// Estado de un emparejamiento en curso, para mostrarlo en pantalla
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingStatus {
+    pub device_id: DeviceId,
+    pub role: PairingRole,
+    /// Código a comparar; `None` mientras el otro dispositivo no respondió
+    pub code: Option<PairingCode>,
+    /// Las claves ya están verificadas por un código QR y no hace falta
+    /// comparar el código
+    pub verified: bool,
+}
+
+impl PairingStatus {
+    pub fn new(device_id: DeviceId, session: &PairingSession) -> Self {
+        Self {
+            device_id,
+            role: session.role(),
+            code: session.code(),
+            verified: session.is_verified(),
+        }
+    }
+}
+
+/// Clave, identidad y nonce que mandó el otro dispositivo
+struct Peer {
+    public_key: PublicKey,
+    identity: PublicIdentity,
+    nonce: [u8; 32],
+    device: Option<DeviceProfile>,
+}
+
+impl Peer {
+    fn decode(public_key: &str, identity: &str, static_key: &str, nonce: &str, device: &Option<DeviceProfile>) -> Result<Self> {
+        Ok(Self {
+            public_key: decode_public_key(public_key)?,
+            identity: PublicIdentity {
+                fingerprint: decode_array(identity)?,
+                static_key: decode_array(static_key)?,
+            },
+            nonce: decode_array(nonce)?,
+            // Un nombre que no se puede mostrar no impide emparejarse
+            device: device.clone().and_then(|device| device.normalized().ok()),
+        })
+    }
+}
+
+/// Emparejamiento en curso con un dispositivo
+pub struct PairingSession {
+    role: PairingRole,
+    secret: EphemeralSecret,
+    public_key: PublicKey,
+    /// Huella del certificado y clave estática de este dispositivo
+    identity: PublicIdentity,
+    nonce: [u8; 32],
+    /// Compromiso recibido, mientras se espera que quien inicia lo revele
+    peer_commitment: Option<[u8; 32]>,
+    /// Lo que mandó el otro dispositivo, cuando ya se conoce
+    peer: Option<Peer>,
+    /// El emparejamiento empezó mostrando un código QR
+    invite: bool,
+    /// La clave del otro dispositivo llegó autenticada por el código QR
+    verified: bool,
+    started_at: Instant,
+}
+
+impl PairingSession {
+    fn new(role: PairingRole, identity: PublicIdentity) -> Self {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public_key = PublicKey::from(&secret);
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+        Self {
+            role,
+            secret,
+            public_key,
+            identity,
+            nonce,
+            peer_commitment: None,
+            peer: None,
+            invite: false,
+            verified: false,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Empieza un emparejamiento presentándose con `identity`; el mensaje
+    /// devuelto es el compromiso que hay que mandar al otro dispositivo
+    pub fn initiate(identity: PublicIdentity) -> (Self, PairingMessage) {
+        let session = Self::new(PairingRole::Initiator, identity);
+        let commitment = commitment(&session.public_key, &session.identity, &session.nonce);
+        (session, PairingMessage::Commit { commitment: hex::encode(commitment) })
+    }
+
+    /// Responde al compromiso de otro dispositivo con la clave y la identidad
+    /// propias
+    pub fn respond(message: &PairingMessage, identity: PublicIdentity) -> Result<(Self, PairingMessage)> {
+        let PairingMessage::Commit { commitment } = message else {
+            return Err(anyhow!("El emparejamiento debe empezar con un compromiso"));
+        };
+        let mut session = Self::new(PairingRole::Responder, identity);
+        session.peer_commitment = Some(decode_array(commitment)?);
+        let reply = PairingMessage::Key {
+            public_key: hex::encode(session.public_key.as_bytes()),
+            identity: hex::encode(session.identity.fingerprint),
+            static_key: hex::encode(session.identity.static_key),
+            nonce: hex::encode(session.nonce),
+            device: None,
+        };
+        Ok((session, reply))
+    }
+
+    /// Empieza un emparejamiento por código QR; el secreto del código es el
+    /// nonce de esta sesión
+    pub fn invite(device_id: DeviceId, identity: PublicIdentity, address: Option<String>) -> (Self, PairingInvite) {
+        let mut session = Self::new(PairingRole::Initiator, identity);
+        session.invite = true;
+        let invite = PairingInvite {
+            device_id,
+            public_key: session.public_key.to_bytes(),
+            identity,
+            secret: session.nonce,
+            address,
+            device: None,
+        };
+        (session, invite)
+    }
+
+    /// Responde a un código QR escaneado. Como la clave del código llegó por
+    /// la pantalla, la sesión queda verificada sin comparar códigos.
+    pub fn join(invite: &PairingInvite, identity: PublicIdentity) -> Result<(Self, PairingMessage)> {
+        let mut session = Self::new(PairingRole::Responder, identity);
+        let peer = Peer {
+            public_key: PublicKey::from(invite.public_key),
+            identity: invite.identity,
+            nonce: invite.secret,
+            device: invite.device.clone(),
+        };
+        let proof = invite_proof(&invite.secret, (&session.public_key, &session.identity), (&peer.public_key, &peer.identity))?
+            .finalize()
+            .into_bytes();
+        session.peer = Some(peer);
+        session.verified = true;
+        let reply = PairingMessage::Join {
+            public_key: hex::encode(session.public_key.as_bytes()),
+            identity: hex::encode(session.identity.fingerprint),
+            static_key: hex::encode(session.identity.static_key),
+            nonce: hex::encode(session.nonce),
+            proof: hex::encode(proof),
+            device: None,
+        };
+        Ok((session, reply))
+    }
+
+    /// Procesa un mensaje del otro dispositivo y devuelve la respuesta, si hay
+    /// que mandar alguna
+    pub fn receive(&mut self, message: &PairingMessage) -> Result<Option<PairingMessage>> {
+        if self.peer.is_some() {
+            return Err(anyhow!("El emparejamiento ya tiene las dos claves"));
+        }
+        match (self.role, message) {
+            (PairingRole::Initiator, PairingMessage::Join { public_key, identity, static_key, nonce, proof, device }) if self.invite => {
+                let peer = Peer::decode(public_key, identity, static_key, nonce, device)?;
+                invite_proof(&self.nonce, (&peer.public_key, &peer.identity), (&self.public_key, &self.identity))?
+                    .verify_slice(&hex::decode(proof)?)
+                    .map_err(|_| anyhow!("La prueba del código QR no es válida"))?;
+                self.peer = Some(peer);
+                self.verified = true;
+                Ok(None)
+            }
+            (PairingRole::Initiator, PairingMessage::Key { public_key, identity, static_key, nonce, device }) if !self.invite => {
+                self.peer = Some(Peer::decode(public_key, identity, static_key, nonce, device)?);
+                Ok(Some(PairingMessage::Reveal {
+                    public_key: hex::encode(self.public_key.as_bytes()),
+                    identity: hex::encode(self.identity.fingerprint),
+                    static_key: hex::encode(self.identity.static_key),
+                    nonce: hex::encode(self.nonce),
+                    device: None,
+                }))
+            }
+            (PairingRole::Responder, PairingMessage::Reveal { public_key, identity, static_key, nonce, device }) => {
+                let peer = Peer::decode(public_key, identity, static_key, nonce, device)?;
+                let expected = self.peer_commitment
+                    .ok_or_else(|| anyhow!("No se recibió el compromiso"))?;
+                if !crate::crypto::secure_compare(&commitment(&peer.public_key, &peer.identity, &peer.nonce), &expected) {
+                    return Err(anyhow!("La clave revelada no coincide con el compromiso"));
+                }
+                self.peer = Some(peer);
+                Ok(None)
+            }
+            _ => Err(anyhow!("Mensaje de emparejamiento inesperado")),
+        }
+    }
+
+    pub fn role(&self) -> PairingRole {
+        self.role
+    }
+
+    /// Si las claves llegaron autenticadas por un código QR
+    pub fn is_verified(&self) -> bool {
+        self.verified
+    }
+
+    /// Si ya pasó [`PAIRING_TIMEOUT`] desde que empezó
+    pub fn is_expired(&self) -> bool {
+        self.started_at.elapsed() > PAIRING_TIMEOUT
+    }
+
+    /// Código para comparar, una vez que se conocen las dos claves
+    pub fn code(&self) -> Option<PairingCode> {
+        let transcript = self.transcript()?;
+        let digits = u32::from_be_bytes([transcript[0], transcript[1], transcript[2], transcript[3]]) % 1_000_000;
+        Some(PairingCode {
+            digits: format!("{:06}", digits),
+            emoji: transcript[4..8].iter()
+                .map(|byte| CODE_EMOJI[(byte % 64) as usize].to_string())
+                .collect(),
+        })
+    }
+
+    /// El usuario confirmó que los códigos coinciden: deriva la clave de
+    /// sincronización compartida con el otro dispositivo
+    pub fn confirm(self) -> Result<PairedKey> {
+        let transcript = self.transcript()
+            .ok_or_else(|| anyhow!("El emparejamiento no tiene todavía las dos claves"))?;
+        let peer = self.peer.ok_or_else(|| anyhow!("Falta la clave del otro dispositivo"))?;
+        let shared = self.secret.diffie_hellman(&peer.public_key);
+        if !shared.was_contributory() {
+            return Err(anyhow!("La clave del otro dispositivo no es válida"));
+        }
+        let mut sync_key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&transcript), shared.as_bytes())
+            .expand(SYNC_KEY_INFO, &mut sync_key)
+            .map_err(|e| anyhow!("Error al derivar la clave de sincronización: {}", e))?;
+        Ok(PairedKey {
+            sync_key,
+            peer_fingerprint: hex::encode(peer.identity.fingerprint),
+            peer_static_key: hex::encode(peer.identity.static_key),
+            peer_device: peer.device,
+        })
+    }
+
+    /// Hash de las dos claves, las dos identidades y los dos nonces, en el mismo
+    /// orden en los dos dispositivos
+    fn transcript(&self) -> Option<[u8; 32]> {
+        let peer = self.peer.as_ref()?;
+        let own = (self.public_key.as_bytes(), &self.identity, &self.nonce);
+        let peer = (peer.public_key.as_bytes(), &peer.identity, &peer.nonce);
+        let (
+            (initiator_key, initiator_identity, initiator_nonce),
+            (responder_key, responder_identity, responder_nonce),
+        ) = match self.role {
+            PairingRole::Initiator => (own, peer),
+            PairingRole::Responder => (peer, own),
+        };
+        Some(Sha256::new()
+            .chain_update(CODE_CONTEXT)
+            .chain_update(initiator_key)
+            .chain_update(responder_key)
+            .chain_update(initiator_identity.fingerprint)
+            .chain_update(initiator_identity.static_key)
+            .chain_update(responder_identity.fingerprint)
+            .chain_update(responder_identity.static_key)
+            .chain_update(initiator_nonce)
+            .chain_update(responder_nonce)
+            .finalize()
+            .into())
+    }
+}
+
+fn commitment(public_key: &PublicKey, identity: &PublicIdentity, nonce: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(COMMIT_CONTEXT)
+        .chain_update(public_key.as_bytes())
+        .chain_update(identity.fingerprint)
+        .chain_update(identity.static_key)
+        .chain_update(nonce)
+        .finalize()
+        .into()
+}
+
+/// HMAC del secreto del código QR sobre la clave y la identidad de quien lo
+/// escaneó y las de quien lo muestra
+fn invite_proof(
+    secret: &[u8; 32],
+    (joiner_key, joiner_identity): (&PublicKey, &PublicIdentity),
+    (inviter_key, inviter_identity): (&PublicKey, &PublicIdentity),
+) -> Result<Hmac<Sha256>> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret)
+        .map_err(|e| anyhow!("Error al calcular la prueba del código QR: {}", e))?;
+    mac.update(INVITE_CONTEXT);
+    mac.update(joiner_key.as_bytes());
+    mac.update(&joiner_identity.fingerprint);
+    mac.update(&joiner_identity.static_key);
+    mac.update(inviter_key.as_bytes());
+    mac.update(&inviter_identity.fingerprint);
+    mac.update(&inviter_identity.static_key);
+    Ok(mac)
+}
+
+fn decode_array(value: &str) -> Result<[u8; 32]> {
+    hex::decode(value)?
+        .try_into()
+        .map_err(|_| anyhow!("Valor de emparejamiento de longitud inválida"))
+}
+
+fn decode_public_key(value: &str) -> Result<PublicKey> {
+    decode_array(value).map(PublicKey::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIATOR: PublicIdentity = PublicIdentity { fingerprint: [1; 32], static_key: [4; 32] };
+    const RESPONDER: PublicIdentity = PublicIdentity { fingerprint: [2; 32], static_key: [5; 32] };
+
+    #[test]
+    fn test_both_devices_derive_the_same_code_and_key() {
+        let (mut initiator, commit) = PairingSession::initiate(INITIATOR);
+        let (mut responder, key) = PairingSession::respond(&commit, RESPONDER).unwrap();
+        assert!(responder.code().is_none());
+        let reveal = initiator.receive(&key).unwrap().unwrap();
+        assert_eq!(responder.receive(&reveal).unwrap(), None);
+
+        let code = initiator.code().unwrap();
+        assert_eq!(code.digits.len(), 6);
+        assert_eq!(code.emoji.len(), 4);
+        assert_eq!(responder.code(), Some(code));
+        let (initiator, responder) = (initiator.confirm().unwrap(), responder.confirm().unwrap());
+        assert_eq!(initiator.sync_key, responder.sync_key);
+        assert_eq!(initiator.peer_fingerprint, hex::encode(RESPONDER.fingerprint));
+        assert_eq!(initiator.peer_static_key, hex::encode(RESPONDER.static_key));
+        assert_eq!(responder.peer_fingerprint, hex::encode(INITIATOR.fingerprint));
+        assert_eq!(responder.peer_static_key, hex::encode(INITIATOR.static_key));
+    }
+
+    #[test]
+    fn test_rejects_a_key_that_breaks_the_commitment() {
+        let (mut initiator, commit) = PairingSession::initiate(INITIATOR);
+        let (mut responder, key) = PairingSession::respond(&commit, RESPONDER).unwrap();
+        let (mut other, _) = PairingSession::initiate(INITIATOR);
+        let (_, other_key) = PairingSession::respond(&PairingSession::initiate(INITIATOR).1, RESPONDER).unwrap();
+        let reveal = other.receive(&other_key).unwrap().unwrap();
+
+        assert!(responder.receive(&reveal).is_err());
+        assert!(responder.code().is_none());
+        assert!(PairingSession::respond(&PairingMessage::Cancel, RESPONDER).is_err());
+
+        // Cambiar la huella o la clave estática después del compromiso
+        // tampoco vale
+        let PairingMessage::Reveal { public_key, identity, static_key, nonce, .. } = initiator.receive(&key).unwrap().unwrap() else {
+            panic!("se esperaba la clave revelada");
+        };
+        let swapped = PairingMessage::Reveal {
+            public_key: public_key.clone(),
+            identity: hex::encode([3u8; 32]),
+            static_key: static_key.clone(),
+            nonce: nonce.clone(),
+            device: None,
+        };
+        assert!(responder.receive(&swapped).is_err());
+        let swapped = PairingMessage::Reveal { public_key, identity, static_key: hex::encode([3u8; 32]), nonce, device: None };
+        assert!(responder.receive(&swapped).is_err());
+
+        let json = serde_json::to_string(&reveal).unwrap();
+        assert!(json.starts_with(r#"{"type":"reveal","publicKey":"#));
+        assert_eq!(serde_json::from_str::<PairingMessage>(&json).unwrap(), reveal);
+    }
+
+    #[test]
+    fn test_pairs_through_a_qr_code() {
+        let inviter_id = DeviceId::new();
+        let (mut inviter, invite) = PairingSession::invite(inviter_id, INITIATOR, Some("192.168.1.20:4000".to_string()));
+        let laptop = DeviceProfile { name: Some("Portátil de casa".to_string()), device_type: Some(DeviceType::Laptop) };
+        let invite = PairingInvite { device: Some(laptop.clone()), ..invite };
+        let uri = invite.to_uri();
+        assert!(uri.starts_with("alohopass://pair?v=3&id="));
+        let scanned = PairingInvite::from_uri(&uri).unwrap();
+        assert_eq!(scanned, invite);
+
+        let phone = DeviceProfile { name: Some("Teléfono".to_string()), device_type: None };
+        let (joiner, join) = PairingSession::join(&scanned, RESPONDER).unwrap();
+        let join = join.with_device(phone.clone());
+        assert!(joiner.is_verified());
+        assert!(!inviter.is_verified());
+        assert_eq!(inviter.receive(&join).unwrap(), None);
+        assert!(inviter.is_verified());
+        assert_eq!(inviter.code(), joiner.code());
+        let (inviter, joiner) = (inviter.confirm().unwrap(), joiner.confirm().unwrap());
+        assert_eq!(inviter.sync_key, joiner.sync_key);
+        assert_eq!(inviter.peer_fingerprint, hex::encode(RESPONDER.fingerprint));
+        assert_eq!(inviter.peer_static_key, hex::encode(RESPONDER.static_key));
+        assert_eq!(joiner.peer_fingerprint, hex::encode(INITIATOR.fingerprint));
+        assert_eq!(joiner.peer_static_key, hex::encode(INITIATOR.static_key));
+        assert_eq!(inviter.peer_device, Some(phone));
+        assert_eq!(joiner.peer_device, Some(laptop));
+
+        // Sin el secreto del código la prueba no vale, ni con otra clave
+        // estática
+        let (mut inviter, invite) = PairingSession::invite(inviter_id, INITIATOR, None);
+        let forged = PairingInvite { secret: [0; 32], ..invite.clone() };
+        let (_, join) = PairingSession::join(&forged, RESPONDER).unwrap();
+        assert!(inviter.receive(&join).is_err());
+        let (_, join) = PairingSession::join(&invite, RESPONDER).unwrap();
+        let PairingMessage::Join { public_key, identity, nonce, proof, .. } = join else {
+            panic!("se esperaba la respuesta al código QR");
+        };
+        let swapped = PairingMessage::Join { public_key, identity, static_key: hex::encode([3u8; 32]), nonce, proof, device: None };
+        assert!(inviter.receive(&swapped).is_err());
+        assert!(PairingInvite::from_uri("alohopass://pair?v=2&id=x").is_err());
+        assert_eq!(PairingInvite::from_uri(&invite.to_uri()).unwrap().address, None);
+    }
+}
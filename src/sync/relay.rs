@@ -0,0 +1,340 @@
+//! Buzón de sincronización y señalización en un relay propio
+//!
+//! Para quien no quiere depender de un proveedor: `alohopass-relay` (se compila
+//! con la característica `relay-server`) guarda en memoria los lotes que unos
+//! dispositivos dejan para otros hasta que los recogen. Solo ve bytes sellados
+//! con la clave de cada emparejamiento y quién los deja para quién. También
+//! pasa las ofertas y respuestas WebRTC de los dispositivos que no se ven en la
+//! red local.
+//!
+//! El protocolo es HTTP, para poder ponerlo detrás de cualquier proxy con TLS:
+//!
+//! - `PUT /v1/<destino>/<origen>` deja el lote y reemplaza el anterior
+//! - `GET /v1/<destino>/<origen>` lo devuelve con su `ETag`
+//! - `DELETE /v1/<destino>/<origen>` con `If-Match` lo saca solo si no cambió
+//!   (412 si cambió)
+//! - `HEAD /v1/<destino>/<origen>` dice si está esperando
+//!
+//! Las ofertas y las respuestas van igual en `/v1/offer/<destino>/<origen>` y
+//! `/v1/answer/<destino>/<origen>`. Si el relay tiene token, va en
+//! `Authorization: Bearer`.
+//!
+//! Las entradas compartidas por enlace van en `/v1/share/<id>`:
+//!
+//! - `PUT` la deja, con `X-Share-Expires-In` (segundos), `X-Share-Views` y
+//!   `X-Share-Owner`, el secreto para revocarla
+//! - `GET` la devuelve y gasta una vista, sin token: basta el enlace
+//! - `DELETE` con `X-Share-Owner` la revoca
+//! - `HEAD` dice si sigue disponible
+//!
+//! Caducada o sin vistas, responde 404.
+
+use crate::models::{DeviceId, ShareId};
+use crate::sync::signaling::{reply_to, OfferHandler, SignalMessage, SignalingChannel, TrickleRoute, SIGNALING_TIMEOUT};
+use crate::sync::smart_sync::SyncMailbox;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::header::{ETAG, IF_MATCH};
+use reqwest::{Method, StatusCode, Url};
+use std::time::{Duration, Instant};
+
+/// Versión del protocolo en las rutas
+pub const RELAY_PROTOCOL: &str = "v1";
+
+/// Cada cuánto se mira si llegó la respuesta a una oferta
+const ANSWER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Cliente HTTP del relay, compartido por el buzón y la señalización
+struct RelayClient {
+    client: reqwest::Client,
+    base: Url,
+    token: String,
+}
+
+impl RelayClient {
+    fn new(url: &str, token: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("Alohopass/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            client,
+            base: parse_url(url)?,
+            token: token.to_string(),
+        })
+    }
+
+    fn request(&self, method: Method, url: Url) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        if self.token.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&self.token)
+        }
+    }
+
+    fn url(&self, slot: &str) -> Result<Url> {
+        Ok(self.base.join(&format!("{}/{}", RELAY_PROTOCOL, slot))?)
+    }
+
+    async fn put(&self, slot: &str, body: Vec<u8>) -> Result<()> {
+        let status = self.request(Method::PUT, self.url(slot)?).body(body).send().await?.status();
+        if !status.is_success() {
+            return Err(anyhow!("El relay respondió {} al dejar {}", status, slot));
+        }
+        Ok(())
+    }
+
+    /// Saca lo que haya en el hueco. Si mientras tanto se reemplazó, el relay
+    /// responde 412 al borrar y lo nuevo se queda.
+    async fn take(&self, slot: &str) -> Result<Option<Vec<u8>>> {
+        let url = self.url(slot)?;
+        let response = self.request(Method::GET, url.clone()).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("El relay respondió {} al recoger {}", response.status(), slot));
+        }
+        let etag = response.headers().get(ETAG).cloned()
+            .ok_or_else(|| anyhow!("El relay no devolvió la versión de {}", slot))?;
+        let body = response.bytes().await?.to_vec();
+
+        let status = self.request(Method::DELETE, url).header(IF_MATCH, etag).send().await?.status();
+        if !status.is_success() && status != StatusCode::NOT_FOUND && status != StatusCode::PRECONDITION_FAILED {
+            return Err(anyhow!("El relay respondió {} al sacar {}", status, slot));
+        }
+        Ok(Some(body))
+    }
+
+    /// Vacía el hueco sin mirar lo que hay
+    async fn delete(&self, slot: &str) -> Result<()> {
+        let status = self.request(Method::DELETE, self.url(slot)?).send().await?.status();
+        if !status.is_success() && status != StatusCode::NOT_FOUND {
+            return Err(anyhow!("El relay respondió {} al borrar {}", status, slot));
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, slot: &str) -> Result<bool> {
+        let response = self.request(Method::HEAD, self.url(slot)?).send().await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(anyhow!("El relay respondió {} al buscar {}", status, slot)),
+        }
+    }
+}
+
+/// Hueco del lote que `from` deja para `to`
+fn batch_slot(from: DeviceId, to: DeviceId) -> String {
+    format!("{}/{}", to, from)
+}
+
+/// Hueco de la oferta de `from` para `to`
+fn offer_slot(from: DeviceId, to: DeviceId) -> String {
+    format!("offer/{}/{}", to, from)
+}
+
+/// Hueco de la respuesta de `from` para `to`
+fn answer_slot(from: DeviceId, to: DeviceId) -> String {
+    format!("answer/{}/{}", to, from)
+}
+
+/// Buzón en un relay `alohopass-relay`
+pub struct RelayMailbox {
+    relay: RelayClient,
+}
+
+impl RelayMailbox {
+    /// Falla si `url` no es una URL http(s). Sin token, el relay tiene que
+    /// estar abierto.
+    pub fn new(url: &str, token: &str) -> Result<Self> {
+        Ok(Self { relay: RelayClient::new(url, token)? })
+    }
+}
+
+#[async_trait]
+impl SyncMailbox for RelayMailbox {
+    async fn put(&self, from: DeviceId, to: DeviceId, sealed: Vec<u8>) -> Result<()> {
+        self.relay.put(&batch_slot(from, to), sealed).await
+    }
+
+    async fn take(&self, from: DeviceId, to: DeviceId) -> Result<Option<Vec<u8>>> {
+        self.relay.take(&batch_slot(from, to)).await
+    }
+
+    async fn is_waiting(&self, from: DeviceId, to: DeviceId) -> Result<bool> {
+        self.relay.exists(&batch_slot(from, to)).await
+    }
+}
+
+/// Hueco de una entrada compartida
+fn share_slot(id: ShareId) -> String {
+    format!("share/{}", id)
+}
+
+/// Entradas compartidas por enlace en un relay `alohopass-relay`. El relay
+/// las borra al caducar o agotar sus vistas.
+pub struct RelayShares {
+    relay: RelayClient,
+}
+
+impl RelayShares {
+    pub fn new(url: &str, token: &str) -> Result<Self> {
+        Ok(Self { relay: RelayClient::new(url, token)? })
+    }
+
+    /// Dirección desde la que se lee la entrada `id`
+    pub fn share_url(&self, id: ShareId) -> Result<Url> {
+        self.relay.url(&share_slot(id))
+    }
+
+    /// Deja una entrada sellada que se puede leer `views` veces durante
+    /// `expires_in`. `owner` es lo que hace falta para revocarla.
+    pub async fn publish(&self, id: ShareId, sealed: Vec<u8>, expires_in: Duration, views: u32, owner: &str) -> Result<()> {
+        let status = self.relay.request(Method::PUT, self.share_url(id)?)
+            .header("X-Share-Expires-In", expires_in.as_secs())
+            .header("X-Share-Views", views)
+            .header("X-Share-Owner", owner)
+            .body(sealed)
+            .send().await?
+            .status();
+        if !status.is_success() {
+            return Err(anyhow!("El relay respondió {} al compartir la entrada", status));
+        }
+        Ok(())
+    }
+}
+
+/// Cliente para las entradas compartidas, que no llevan el token del relay:
+/// quien abre el enlace puede no tenerlo, y quien revoca puede haber cambiado
+/// de relay desde que la compartió
+fn share_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("Alohopass/", env!("CARGO_PKG_VERSION")))
+        .build()?)
+}
+
+/// Lee la entrada compartida en `url`, gastando una vista. `None` si caducó,
+/// se agotaron sus vistas o la revocaron.
+pub async fn fetch_share(url: Url) -> Result<Option<Vec<u8>>> {
+    let response = share_client()?.get(url).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!("El relay respondió {} al abrir la entrada compartida", response.status()));
+    }
+    Ok(Some(response.bytes().await?.to_vec()))
+}
+
+/// Borra del relay la entrada compartida en `url`; si ya no estaba, no hay
+/// nada que revocar
+pub async fn revoke_share(url: Url, owner: &str) -> Result<()> {
+    let status = share_client()?.delete(url).header("X-Share-Owner", owner).send().await?.status();
+    if !status.is_success() && status != StatusCode::NOT_FOUND {
+        return Err(anyhow!("El relay respondió {} al revocar la entrada compartida", status));
+    }
+    Ok(())
+}
+
+/// Señalización WebRTC por un relay `alohopass-relay`. Las ofertas quedan en
+/// el relay hasta que el otro dispositivo pasa a buscarlas con
+/// [`RelaySignaling::answer_offers`].
+pub struct RelaySignaling {
+    relay: RelayClient,
+}
+
+impl RelaySignaling {
+    pub fn new(url: &str, token: &str) -> Result<Self> {
+        Ok(Self { relay: RelayClient::new(url, token)? })
+    }
+
+    /// Responde con `handler` las ofertas que `peers` dejaron para `local`
+    pub async fn answer_offers(&self, local: DeviceId, peers: &[DeviceId], handler: &dyn OfferHandler) -> Result<()> {
+        for &peer in peers {
+            let Some(offer) = self.relay.take(&offer_slot(peer, local)).await? else {
+                continue;
+            };
+            let reply = match serde_json::from_slice::<SignalMessage>(&offer) {
+                Ok(message @ SignalMessage::Offer { from, .. }) if from == peer => reply_to(message, handler, None).await,
+                _ => {
+                    log::warn!("Se descarta una oferta inválida de {} en el relay", peer);
+                    continue;
+                }
+            };
+            self.relay.put(&answer_slot(local, peer), serde_json::to_vec(&reply)?).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SignalingChannel for RelaySignaling {
+    /// Con el relay consultado cada segundo no vale la pena: la oferta sale
+    /// con todos los candidatos
+    fn trickles(&self) -> bool {
+        false
+    }
+
+    async fn send_offer(&self, from: DeviceId, to: DeviceId, sdp: String, _route: Option<TrickleRoute>) -> Result<String> {
+        // Una respuesta que quedó de otra vez no es para esta oferta
+        self.relay.delete(&answer_slot(to, from)).await?;
+        let offer = serde_json::to_vec(&SignalMessage::Offer { from, to, sdp })?;
+        self.relay.put(&offer_slot(from, to), offer).await?;
+
+        let deadline = Instant::now() + SIGNALING_TIMEOUT;
+        loop {
+            if let Some(reply) = self.relay.take(&answer_slot(to, from)).await? {
+                return serde_json::from_slice::<SignalMessage>(&reply)?.into_answer(to);
+            }
+            if Instant::now() >= deadline {
+                // Que no la responda tarde, cuando ya nadie espera
+                self.relay.delete(&offer_slot(from, to)).await?;
+                return Err(anyhow!("{} no respondió la oferta por el relay en {} segundos", to, SIGNALING_TIMEOUT.as_secs()));
+            }
+            tokio::time::sleep(ANSWER_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// URL del relay, terminada en `/` para que las rutas del protocolo queden
+/// debajo
+pub fn parse_url(url: &str) -> Result<Url> {
+    let mut url = Url::parse(url.trim()).map_err(|e| anyhow!("URL del relay inválida: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("La URL del relay tiene que ser http o https"));
+    }
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_live_under_the_protocol_version() {
+        let relay = RelayClient::new("https://relay.example.com/alohopass", "").unwrap();
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        assert_eq!(
+            relay.url(&batch_slot(laptop, phone)).unwrap().as_str(),
+            format!("https://relay.example.com/alohopass/v1/{}/{}", phone, laptop),
+        );
+        assert_eq!(
+            relay.url(&offer_slot(laptop, phone)).unwrap().as_str(),
+            format!("https://relay.example.com/alohopass/v1/offer/{}/{}", phone, laptop),
+        );
+        let share = ShareId::new();
+        assert_eq!(
+            RelayShares::new("https://relay.example.com/alohopass/", "").unwrap().share_url(share).unwrap().as_str(),
+            format!("https://relay.example.com/alohopass/v1/share/{}", share),
+        );
+        assert!(parse_url("ws://relay.example.com").is_err());
+        assert!(parse_url("relay.example.com").is_err());
+    }
+}
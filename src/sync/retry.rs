@@ -0,0 +1,93 @@
+//! Reintentos de las sincronizaciones que fallan
+//!
+//! Cuando una sincronización con un dispositivo falla se vuelve a intentar
+//! más tarde, cada vez esperando el doble hasta un máximo. La espera lleva una
+//! parte al azar para que dos dispositivos que fallaron a la vez no vuelvan a
+//! coincidir. Pasados los intentos permitidos se deja de insistir hasta la
+//! siguiente sincronización manual o automática.
+
+use crate::models::DeviceId;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Cuánto se espera entre reintentos y cuántos se hacen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Espera antes del primer reintento
+    pub base_delay: Duration,
+    /// Espera máxima entre reintentos
+    pub max_delay: Duration,
+    /// Reintentos antes de rendirse
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(10 * 60),
+            max_attempts: 6,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Espera sin azar antes del reintento número `attempt` (desde 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// Espera antes del reintento `attempt`: entre la mitad y el total de
+    /// [`RetryPolicy::backoff`]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        let half = backoff / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// Reintentos de la sincronización con un dispositivo, tal como los ve la
+/// interfaz
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRetry {
+    pub device_id: DeviceId,
+    /// Reintentos hechos o programados
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// Cuándo toca el siguiente; `None` si ya se rindió
+    pub next_retry_at: Option<DateTime<Utc>>,
+    /// Error de la última sincronización fallida
+    pub last_error: String,
+}
+
+impl SyncRetry {
+    /// Si se dejó de reintentar
+    pub fn gave_up(&self) -> bool {
+        self.next_retry_at.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doubles_the_wait_up_to_the_maximum() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(10));
+        assert_eq!(policy.backoff(2), Duration::from_secs(20));
+        assert_eq!(policy.backoff(4), Duration::from_secs(80));
+        assert_eq!(policy.backoff(7), Duration::from_secs(600));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(600));
+
+        for attempt in 1..=policy.max_attempts {
+            let delay = policy.delay(attempt);
+            assert!(delay >= policy.backoff(attempt) / 2);
+            assert!(delay <= policy.backoff(attempt));
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Entradas compartidas por enlace
+//!
+//! Una entrada se puede compartir con quien no tiene un dispositivo emparejado
+//! con un enlace que caduca. La sirve el relay propio, que la borra al caducar,
+//! al agotarse sus vistas o cuando se revoca. Va sellada con una clave nueva
+//! que solo está en el fragmento del enlace, lo que va después de `#`, y que no
+//! se le manda al relay: el relay guarda bytes que no puede abrir.
+
+use crate::crypto::{decrypt_data, encrypt_data};
+use crate::models::{PasswordEntry, ShareId};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{rngs::OsRng, RngCore};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Lo máximo que puede durar un enlace; el relay tampoco acepta más
+pub const MAX_SHARE_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Las veces que se puede abrir un enlace, como mucho
+pub const MAX_SHARE_VIEWS: u32 = 100;
+
+/// Largo del nonce al comienzo de la entrada sellada
+const SHARE_NONCE_LEN: usize = 12;
+
+/// Lo que viaja de una entrada compartida: sin ids, categoría ni fechas de
+/// esta bóveda
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedEntry {
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<PasswordEntry> for SharedEntry {
+    fn from(entry: PasswordEntry) -> Self {
+        Self {
+            title: entry.title,
+            username: entry.username,
+            password: entry.password,
+            url: entry.url,
+            notes: entry.notes,
+            tags: entry.tags,
+        }
+    }
+}
+
+/// Enlace a una entrada compartida: dónde la sirve el relay y la clave que la
+/// abre
+pub struct ShareLink {
+    pub url: Url,
+    key: [u8; 32],
+}
+
+impl ShareLink {
+    /// Enlace con una clave nueva para la entrada que sirve `url`
+    pub fn generate(url: Url) -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self { url, key }
+    }
+
+    /// Lee un enlace de [`ShareLink::to_link`]. Falla si no es http(s), si no
+    /// apunta a una entrada compartida o si le falta la clave.
+    pub fn parse(link: &str) -> Result<Self> {
+        let mut url = Url::parse(link.trim()).map_err(|e| anyhow!("Enlace inválido: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("El enlace tiene que ser http o https"));
+        }
+        share_id(&url).ok_or_else(|| anyhow!("El enlace no es de una entrada compartida"))?;
+        let key = url.fragment()
+            .and_then(|fragment| URL_SAFE_NO_PAD.decode(fragment).ok())
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+            .ok_or_else(|| anyhow!("Al enlace le falta la clave"))?;
+        url.set_fragment(None);
+        Ok(Self { url, key })
+    }
+
+    /// El enlace para compartir, con la clave en el fragmento
+    pub fn to_link(&self) -> String {
+        let mut url = self.url.clone();
+        url.set_fragment(Some(&URL_SAFE_NO_PAD.encode(self.key)));
+        url.to_string()
+    }
+
+    /// Sella la entrada con la clave del enlace
+    pub fn seal(&self, entry: &SharedEntry) -> Result<Vec<u8>> {
+        let (ciphertext, nonce) = encrypt_data(&serde_json::to_vec(entry)?, &self.key)?;
+        Ok([nonce, ciphertext].concat())
+    }
+
+    /// Abre una entrada sellada con [`ShareLink::seal`]
+    pub fn open(&self, sealed: &[u8]) -> Result<SharedEntry> {
+        if sealed.len() < SHARE_NONCE_LEN {
+            return Err(anyhow!("Entrada compartida demasiado corta"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(SHARE_NONCE_LEN);
+        let payload = decrypt_data(ciphertext, &self.key, nonce)
+            .map_err(|_| anyhow!("La clave del enlace no abre la entrada compartida"))?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+}
+
+/// Secreto nuevo para revocar una entrada compartida en el relay
+pub fn revocation_secret() -> String {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    hex::encode(secret)
+}
+
+/// Entrada compartida a la que apunta una dirección `.../v1/share/<id>`
+fn share_id(url: &Url) -> Option<ShareId> {
+    let mut segments = url.path_segments()?.rev();
+    let id = segments.next()?.parse().ok()?;
+    (segments.next()? == "share").then_some(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> SharedEntry {
+        SharedEntry {
+            title: "Banco".to_string(),
+            username: "ana".to_string(),
+            password: "secreta".to_string(),
+            url: Some("https://banco.example.com".to_string()),
+            notes: None,
+            tags: vec!["finanzas".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_only_the_link_opens_the_entry() {
+        let id = ShareId::new();
+        let url = Url::parse(&format!("https://relay.example.com/alohopass/v1/share/{}", id)).unwrap();
+        let link = ShareLink::generate(url.clone());
+        let sealed = link.seal(&entry()).unwrap();
+        assert!(!sealed.windows(7).any(|window| window == b"secreta"));
+
+        // La clave va en el fragmento, que no llega al relay
+        let shared = link.to_link();
+        assert!(shared.starts_with(&format!("{}#", url)));
+        let parsed = ShareLink::parse(&shared).unwrap();
+        assert_eq!(parsed.url, url);
+        assert_eq!(share_id(&parsed.url), Some(id));
+        assert_eq!(parsed.open(&sealed).unwrap(), entry());
+
+        assert!(ShareLink::generate(url.clone()).open(&sealed).is_err());
+        assert!(ShareLink::parse(url.as_str()).is_err());
+        assert!(ShareLink::parse(&format!("https://relay.example.com/v1/{}#{}", id, URL_SAFE_NO_PAD.encode([0u8; 32]))).is_err());
+        assert!(ShareLink::parse(&format!("ftp://relay.example.com/v1/share/{}#{}", id, URL_SAFE_NO_PAD.encode([0u8; 32]))).is_err());
+    }
+}
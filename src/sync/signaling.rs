@@ -0,0 +1,428 @@
+//! Señalización para abrir conexiones WebRTC
+//!
+//! Antes de conectarse, los dos extremos tienen que intercambiar la oferta y
+//! la respuesta SDP y los candidatos ICE, las direcciones por las que se puede
+//! llegar a cada extremo.
+//!
+//! En la red local cada dispositivo escucha en un puerto TCP que anuncia por
+//! mDNS; por ahí llega un mensaje JSON por línea con la oferta y vuelve otro
+//! con la respuesta. Después, por la misma conexión, cada extremo manda sus
+//! candidatos a medida que los encuentra (trickle ICE), así la conexión se
+//! prueba sin esperar a los servidores STUN y TURN más lentos.
+//!
+//! Para los que no se ven en la red local, las ofertas pasan por el relay
+//! propio (ver [`crate::sync::relay`]). Ahí no hay trickle: la oferta y la
+//! respuesta se mandan con todos los candidatos ya reunidos.
+//!
+//! Los mensajes no van encriptados: solo llevan direcciones y la huella del
+//! certificado, que se comprueba contra la fijada al emparejarse. Quien los
+//! altere solo consigue que la conexión falle.
+//!
+//! Cuando mDNS está bloqueado el dispositivo se agrega a mano con la
+//! dirección y el puerto de señalización: [`probe`] pregunta en ese puerto
+//! quién escucha y vuelve con lo mismo que se habría anunciado por mDNS.
+
+use crate::models::DeviceId;
+use crate::sync::DeviceInfo;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+
+/// Tiempo para recibir la respuesta a una oferta
+pub const SIGNALING_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tiempo para conectar con cada una de las direcciones de un dispositivo
+/// antes de probar la siguiente
+const ADDRESS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Tamaño máximo de un mensaje; una SDP con todos sus candidatos ocupa unos
+/// pocos KB
+const MAX_MESSAGE_SIZE: u64 = 64 * 1024;
+
+/// Mensaje de señalización
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SignalMessage {
+    /// Oferta SDP de `from` para `to`
+    Offer { from: DeviceId, to: DeviceId, sdp: String },
+    /// Respuesta SDP a la oferta
+    Answer { sdp: String },
+    /// Candidato ICE encontrado después de mandar la oferta o la respuesta
+    Candidate { candidate: RTCIceCandidateInit },
+    /// El extremo que lo manda no tiene más candidatos
+    EndOfCandidates,
+    /// El otro extremo no acepta la conexión
+    Rejected { reason: String },
+    /// Pregunta qué dispositivo escucha en el puerto
+    Probe,
+    /// Respuesta a [`SignalMessage::Probe`]
+    Device { device: Box<DeviceInfo> },
+}
+
+impl SignalMessage {
+    /// SDP de la respuesta de `device`; falla si rechazó la oferta
+    pub fn into_answer(self, device: DeviceId) -> Result<String> {
+        match self {
+            SignalMessage::Answer { sdp } => Ok(sdp),
+            SignalMessage::Rejected { reason } => Err(anyhow!("{} rechazó la conexión: {}", device, reason)),
+            SignalMessage::Offer { .. } => Err(anyhow!("{} respondió con otra oferta", device)),
+            SignalMessage::Candidate { .. } | SignalMessage::EndOfCandidates => {
+                Err(anyhow!("{} mandó candidatos antes de la respuesta", device))
+            }
+            SignalMessage::Probe | SignalMessage::Device { .. } => {
+                Err(anyhow!("{} respondió con otro mensaje", device))
+            }
+        }
+    }
+}
+
+/// Candidatos ICE que una conexión manda y recibe a medida que aparecen
+pub struct Trickle {
+    /// Candidatos de este extremo; `None` avisa de que no hay más
+    pub local: mpsc::UnboundedSender<Option<RTCIceCandidateInit>>,
+    /// Candidatos del otro extremo
+    pub remote: mpsc::UnboundedReceiver<RTCIceCandidateInit>,
+}
+
+/// El otro lado de un [`Trickle`]: lo que la señalización lleva y trae
+pub struct TrickleRoute {
+    local: mpsc::UnboundedReceiver<Option<RTCIceCandidateInit>>,
+    remote: mpsc::UnboundedSender<RTCIceCandidateInit>,
+}
+
+/// Une una conexión con la señalización que lleva sus candidatos
+pub fn trickle() -> (Trickle, TrickleRoute) {
+    let (local_sender, local_receiver) = mpsc::unbounded_channel();
+    let (remote_sender, remote_receiver) = mpsc::unbounded_channel();
+    (
+        Trickle { local: local_sender, remote: remote_receiver },
+        TrickleRoute { local: local_receiver, remote: remote_sender },
+    )
+}
+
+/// Camino por el que la oferta llega al otro dispositivo
+#[async_trait]
+pub trait SignalingChannel: Send + Sync {
+    /// Si lleva candidatos después de la oferta; si no, la oferta tiene que
+    /// salir con todos
+    fn trickles(&self) -> bool;
+
+    /// Manda la oferta de `from` a `to` y espera la respuesta SDP. Si hay
+    /// `route`, después lleva y trae los candidatos que vayan apareciendo.
+    async fn send_offer(&self, from: DeviceId, to: DeviceId, sdp: String, route: Option<TrickleRoute>) -> Result<String>;
+}
+
+/// Quien responde las ofertas que llegan a este dispositivo
+#[async_trait]
+pub trait OfferHandler: Send + Sync {
+    /// Prepara la conexión que pide `from` y devuelve la respuesta SDP. Con
+    /// `trickle` la respuesta puede salir antes de reunir los candidatos.
+    async fn answer_offer(&self, from: DeviceId, to: DeviceId, sdp: String, trickle: Option<Trickle>) -> Result<String>;
+
+    /// Lo que este dispositivo cuenta de sí a quien lo agrega a mano
+    async fn describe(&self) -> Result<DeviceInfo>;
+}
+
+/// Responde la oferta de un mensaje con `handler`; lo que no es una oferta o
+/// no se acepta vuelve como rechazo
+pub async fn reply_to(message: SignalMessage, handler: &dyn OfferHandler, trickle: Option<Trickle>) -> SignalMessage {
+    let SignalMessage::Offer { from, to, sdp } = message else {
+        return SignalMessage::Rejected { reason: "Se esperaba una oferta".to_string() };
+    };
+    match handler.answer_offer(from, to, sdp, trickle).await {
+        Ok(sdp) => SignalMessage::Answer { sdp },
+        Err(e) => {
+            log::warn!("Oferta de {} rechazada: {}", from, e);
+            SignalMessage::Rejected { reason: e.to_string() }
+        }
+    }
+}
+
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<SignalMessage> {
+    let mut line = String::new();
+    reader.take(MAX_MESSAGE_SIZE).read_line(&mut line).await?;
+    if !line.ends_with('\n') {
+        return Err(anyhow!("Mensaje de señalización incompleto o demasiado grande"));
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &SignalMessage) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(writer.flush().await?)
+}
+
+/// Lleva y trae candidatos por una conexión de señalización hasta que los dos
+/// extremos dicen que no tienen más
+async fn exchange_candidates<R, W>(mut reader: R, mut writer: W, route: TrickleRoute) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let TrickleRoute { mut local, remote } = route;
+    let send = async {
+        loop {
+            // Si la conexión se descartó tampoco habrá más candidatos
+            match local.recv().await.flatten() {
+                Some(candidate) => write_message(&mut writer, &SignalMessage::Candidate { candidate }).await?,
+                None => return write_message(&mut writer, &SignalMessage::EndOfCandidates).await,
+            }
+        }
+    };
+    let receive = async {
+        // Al terminar se suelta el canal, así la conexión sabe que no llegan más
+        let remote = remote;
+        loop {
+            match read_message(&mut reader).await? {
+                SignalMessage::Candidate { candidate } => {
+                    let _ = remote.send(candidate);
+                }
+                SignalMessage::EndOfCandidates => return Ok(()),
+                _ => return Err(anyhow!("Se esperaba un candidato ICE")),
+            }
+        }
+    };
+    tokio::time::timeout(SIGNALING_TIMEOUT, async { tokio::try_join!(send, receive).map(|_| ()) }).await
+        .map_err(|_| anyhow!("Los candidatos ICE no terminaron de llegar en {} segundos", SIGNALING_TIMEOUT.as_secs()))?
+}
+
+/// Abre un puerto TCP libre en todas las interfaces. Un solo socket IPv6 que
+/// también acepta IPv4 atiende redes IPv4, solo IPv6 o de las dos; si el
+/// sistema no tiene IPv6 se escucha solo en IPv4.
+pub fn listen_on_all_interfaces() -> Result<TcpListener> {
+    let dual_stack = || -> std::io::Result<Socket> {
+        let socket = Socket::new(Domain::IPV6, Type::STREAM, Some(Protocol::TCP))?;
+        socket.set_only_v6(false)?;
+        socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)).into())?;
+        Ok(socket)
+    };
+    let socket = match dual_stack() {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::debug!("Sin IPv6 ({}), se escucha solo en IPv4", e);
+            let socket = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP))?;
+            socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)).into())?;
+            socket
+        }
+    };
+    socket.listen(128)?;
+    socket.set_nonblocking(true)?;
+    Ok(TcpListener::from_std(socket.into())?)
+}
+
+/// Conecta con la primera de `addresses` que responda, en orden. Así gana la
+/// familia de direcciones que de verdad llega al otro dispositivo.
+async fn connect_first(addresses: &[SocketAddr]) -> Result<TcpStream> {
+    let mut last_error = anyhow!("No hay ninguna dirección a la que conectar");
+    for address in addresses {
+        match tokio::time::timeout(ADDRESS_TIMEOUT, TcpStream::connect(address)).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(e)) => last_error = anyhow!("No se pudo conectar con {}: {}", address, e),
+            Err(_) => last_error = anyhow!("{} no respondió en {} segundos", address, ADDRESS_TIMEOUT.as_secs()),
+        }
+        log::debug!("{}", last_error);
+    }
+    Err(last_error)
+}
+
+/// Pregunta qué dispositivo escucha en `address`, el puerto de señalización
+/// de otro Alohopass
+pub async fn probe(address: SocketAddr) -> Result<DeviceInfo> {
+    let (reader, mut writer) = connect_first(&[address]).await?.into_split();
+    let mut reader = BufReader::new(reader);
+    let reply = tokio::time::timeout(SIGNALING_TIMEOUT, async {
+        write_message(&mut writer, &SignalMessage::Probe).await?;
+        read_message(&mut reader).await
+    }).await
+        .map_err(|_| anyhow!("{} no respondió en {} segundos", address, SIGNALING_TIMEOUT.as_secs()))??;
+    match reply {
+        SignalMessage::Device { device } => Ok(*device),
+        SignalMessage::Rejected { reason } => Err(anyhow!("{} no se deja agregar: {}", address, reason)),
+        _ => Err(anyhow!("{} no respondió como un dispositivo Alohopass", address)),
+    }
+}
+
+/// Señalización directa con un dispositivo de la red local, en las
+/// direcciones que anunció por mDNS
+pub struct LanSignaling {
+    addresses: Vec<SocketAddr>,
+}
+
+impl LanSignaling {
+    /// `addresses` van de la preferida a la última; se usa la primera que
+    /// acepte la conexión
+    pub fn new(addresses: Vec<SocketAddr>) -> Self {
+        Self { addresses }
+    }
+}
+
+#[async_trait]
+impl SignalingChannel for LanSignaling {
+    fn trickles(&self) -> bool {
+        true
+    }
+
+    async fn send_offer(&self, from: DeviceId, to: DeviceId, sdp: String, route: Option<TrickleRoute>) -> Result<String> {
+        let stream = connect_first(&self.addresses).await?;
+        let (reply, reader, writer) = tokio::time::timeout(SIGNALING_TIMEOUT, async {
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            write_message(&mut writer, &SignalMessage::Offer { from, to, sdp }).await?;
+            let reply = read_message(&mut reader).await?;
+            Ok::<_, anyhow::Error>((reply, reader, writer))
+        }).await
+            .map_err(|_| anyhow!("{} no respondió la oferta en {} segundos", to, SIGNALING_TIMEOUT.as_secs()))??;
+        let answer = reply.into_answer(to)?;
+
+        if let Some(route) = route {
+            tokio::spawn(async move {
+                if let Err(e) = exchange_candidates(reader, writer, route).await {
+                    log::warn!("Intercambio de candidatos con {} interrumpido: {}", to, e);
+                }
+            });
+        }
+        Ok(answer)
+    }
+}
+
+/// Puerto TCP en el que este dispositivo recibe ofertas de la red local
+pub struct SignalingServer {
+    port: u16,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl SignalingServer {
+    /// Empieza a escuchar en un puerto libre de todas las interfaces
+    pub async fn start(handler: Arc<dyn OfferHandler>) -> Result<Self> {
+        let listener = listen_on_all_interfaces()?;
+        let port = listener.local_addr()?.port();
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::error!("Error aceptando una conexión de señalización: {}", e);
+                        continue;
+                    }
+                };
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, handler.as_ref()).await {
+                        log::warn!("Señalización con {} fallida: {}", peer, e);
+                    }
+                });
+            }
+        });
+        log::info!("Señalización escuchando en el puerto {}", port);
+        Ok(Self { port, task })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for SignalingServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Atiende una conexión: una oferta, su respuesta y después los candidatos
+async fn serve(stream: TcpStream, handler: &dyn OfferHandler) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let message = tokio::time::timeout(SIGNALING_TIMEOUT, read_message(&mut reader)).await
+        .map_err(|_| anyhow!("No llegó la oferta"))??;
+    let (trickle, route) = trickle();
+    let reply = match message {
+        SignalMessage::Probe => match handler.describe().await {
+            Ok(device) => SignalMessage::Device { device: Box::new(device) },
+            Err(e) => SignalMessage::Rejected { reason: e.to_string() },
+        },
+        message => reply_to(message, handler, Some(trickle)).await,
+    };
+    write_message(&mut writer, &reply).await?;
+    if matches!(reply, SignalMessage::Answer { .. }) {
+        exchange_candidates(reader, writer, route).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    fn candidate(address: &str) -> RTCIceCandidateInit {
+        RTCIceCandidateInit {
+            candidate: format!("candidate:1 1 udp 2130706431 {} 50000 typ host", address),
+            ..Default::default()
+        }
+    }
+
+    #[async_trait]
+    impl OfferHandler for Echo {
+        async fn answer_offer(&self, _from: DeviceId, _to: DeviceId, sdp: String, trickle: Option<Trickle>) -> Result<String> {
+            if sdp.is_empty() {
+                return Err(anyhow!("Oferta vacía"));
+            }
+            // Devuelve cada candidato que le llega y termina con el suyo
+            let mut trickle = trickle.unwrap();
+            tokio::spawn(async move {
+                while let Some(received) = trickle.remote.recv().await {
+                    trickle.local.send(Some(received)).unwrap();
+                }
+                trickle.local.send(Some(candidate("10.0.0.2"))).unwrap();
+                trickle.local.send(None).unwrap();
+            });
+            Ok(format!("respuesta a {}", sdp))
+        }
+
+        async fn describe(&self) -> Result<DeviceInfo> {
+            Ok(DeviceInfo::offline(DeviceId::new(), "eco".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exchanges_offer_and_answer_over_the_lan() {
+        let server = SignalingServer::start(Arc::new(Echo)).await.unwrap();
+        // La primera dirección no responde y la de IPv4 sí, aunque el
+        // servidor escuche en IPv6
+        let channel = LanSignaling::new(vec![
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 1)),
+            SocketAddr::from((Ipv4Addr::LOCALHOST, server.port())),
+        ]);
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+
+        let (mut trickle, route) = trickle();
+        let answer = channel.send_offer(laptop, phone, "v=0".to_string(), Some(route)).await.unwrap();
+        assert_eq!(answer, "respuesta a v=0");
+        assert!(channel.send_offer(laptop, phone, String::new(), None).await.is_err());
+
+        trickle.local.send(Some(candidate("10.0.0.1"))).unwrap();
+        trickle.local.send(None).unwrap();
+        assert_eq!(trickle.remote.recv().await, Some(candidate("10.0.0.1")));
+        assert_eq!(trickle.remote.recv().await, Some(candidate("10.0.0.2")));
+        assert_eq!(trickle.remote.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_tells_who_listens_on_the_port() {
+        let server = SignalingServer::start(Arc::new(Echo)).await.unwrap();
+        let device = probe(SocketAddr::from((Ipv4Addr::LOCALHOST, server.port()))).await.unwrap();
+        assert_eq!(device.name, "eco");
+        assert!(probe(SocketAddr::from((Ipv4Addr::LOCALHOST, 1))).await.is_err());
+    }
+}
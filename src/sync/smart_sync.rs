@@ -5,13 +5,47 @@
 //! - Resolución de conflictos
 //! - Sincronización incremental
 //! - Compresión y optimización de datos
-
-use crate::sync::{DeviceInfo, SyncEvent, SyncEventHandler, SyncResult};
+//!
+//! Una sincronización es un intercambio de lotes: quien la inicia manda los
+//! elementos con versiones que el otro dispositivo todavía no vio, junto con
+//! su propio vector de lo visto, y el otro responde con lo que le falta a él.
+//! Cada lote va encriptado con la clave acordada al emparejarse, con la suite
+//! que corresponde al [`EncryptionLevel`] configurado, y, si los dos
+//! dispositivos lo entienden, comprimido con zstd antes. El acceso a la
+//! bóveda ([`SyncStore`]) y el envío ([`SyncTransport`]) quedan fuera de este
+//! módulo.
+//!
+//! Cuando el otro dispositivo no está a la vista, los lotes pueden ir por un
+//! buzón ([`SyncMailbox`]): cada uno deja ahí el suyo, sellado igual, y
+//! recoge el que le dejó el otro.
+//!
+//! Un dispositivo que todavía no tiene nada, como uno recién emparejado,
+//! empieza por pedir la bóveda entera del otro en páginas de
+//! [`SyncConfig::max_batch_size`] elementos. Después de cada página anota
+//! hasta dónde llegó ([`SyncBootstrap`]), así una copia cortada sigue desde
+//! ahí, y al terminar pasa a la sincronización incremental.
+//!
+//! Los cambios pendientes se guardan también en un diario ([`ChangeJournal`])
+//! para que sobrevivan a un reinicio.
+
+use crate::crypto::{decrypt_data, encrypt_data};
+use crate::database::SyncChangeRow;
+use crate::models::{
+    CategoryId, DeviceId, EncryptionLevel, EntryId, FieldStamps, SyncBootstrap, SyncDirection, SyncHistoryRecord, Tombstone,
+    VersionVector,
+};
+use crate::sync::p2p_connection::P2PConnectionStats;
+use crate::sync::{DeviceCapabilities, SyncEvent, SyncEventHandler, SyncResult};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key as AesKey, Nonce as AesNonce};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -32,6 +66,9 @@ pub enum ChangeType {
     Moved,
     /// Metadatos cambiados
     MetadataChanged,
+    /// Entrada que quedó fuera de lo que se manda al otro dispositivo: la
+    /// quita sin eliminarla
+    Excluded,
 }
 
 impl ChangeType {
@@ -43,6 +80,7 @@ impl ChangeType {
             ChangeType::Deleted => "🗑️",
             ChangeType::Moved => "📁",
             ChangeType::MetadataChanged => "ℹ️",
+            ChangeType::Excluded => "🚫",
         }
     }
 
@@ -54,23 +92,56 @@ impl ChangeType {
             ChangeType::Deleted => "Eliminado",
             ChangeType::Moved => "Movido",
             ChangeType::MetadataChanged => "Metadatos",
+            ChangeType::Excluded => "Excluido",
+        }
+    }
+
+    /// Nombre con que se guarda en el diario
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeType::Created => "created",
+            ChangeType::Modified => "modified",
+            ChangeType::Deleted => "deleted",
+            ChangeType::Moved => "moved",
+            ChangeType::MetadataChanged => "metadata_changed",
+            ChangeType::Excluded => "excluded",
         }
     }
 }
 
+impl std::str::FromStr for ChangeType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "created" => Ok(ChangeType::Created),
+            "modified" => Ok(ChangeType::Modified),
+            "deleted" => Ok(ChangeType::Deleted),
+            "moved" => Ok(ChangeType::Moved),
+            "metadata_changed" => Ok(ChangeType::MetadataChanged),
+            "excluded" => Ok(ChangeType::Excluded),
+            _ => Err(anyhow!("Tipo de cambio desconocido: {}", value)),
+        }
+    }
+}
+
+/// Metadato con la clase de elemento de un cambio; sin él es una entrada
+const ELEMENT_KIND_KEY: &str = "element";
+const CATEGORY_ELEMENT: &str = "category";
+
 /// Cambio en un elemento
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataChange {
     /// ID único del cambio
     pub id: String,
     /// ID del elemento
-    pub element_id: String,
+    pub element_id: EntryId,
     /// Tipo de cambio
     pub change_type: ChangeType,
     /// Timestamp del cambio
     pub timestamp: DateTime<Utc>,
     /// Dispositivo que originó el cambio
-    pub source_device: String,
+    pub source_device: DeviceId,
     /// Datos del elemento (serializados)
     pub element_data: Option<Vec<u8>>,
     /// Metadatos del cambio
@@ -81,14 +152,21 @@ pub struct DataChange {
     pub previous_hash: Option<String>,
     /// Hash del elemento actual
     pub current_hash: String,
+    /// Vector de versiones del elemento después del cambio
+    #[serde(default)]
+    pub versions: VersionVector,
+    /// Último cambio de cada campo de la entrada. Solo viaja en los lotes: la
+    /// bóveda lo completa al cargar los cambios.
+    #[serde(default)]
+    pub field_stamps: FieldStamps,
 }
 
 impl DataChange {
     /// Crear un nuevo cambio
     pub fn new(
-        element_id: String,
+        element_id: EntryId,
         change_type: ChangeType,
-        source_device: String,
+        source_device: DeviceId,
         element_data: Option<Vec<u8>>,
         version: u64,
         previous_hash: Option<String>,
@@ -110,6 +188,8 @@ impl DataChange {
             version,
             previous_hash,
             current_hash,
+            versions: VersionVector::new(),
+            field_stamps: FieldStamps::new(),
         }
     }
 
@@ -121,6 +201,29 @@ impl DataChange {
         format!("{:x}", hasher.finalize())
     }
 
+    /// Cambio de una categoría. Las categorías viajan con su UUID en
+    /// `element_id` y la clase de elemento en los metadatos, así el diario y
+    /// los lotes tienen un solo formato.
+    pub fn for_category(
+        category_id: CategoryId,
+        change_type: ChangeType,
+        source_device: DeviceId,
+        element_data: Option<Vec<u8>>,
+        version: u64,
+        previous_hash: Option<String>,
+    ) -> Self {
+        let element_id = EntryId::from(*category_id.as_uuid());
+        let mut change = Self::new(element_id, change_type, source_device, element_data, version, previous_hash);
+        change.add_metadata(ELEMENT_KIND_KEY.to_string(), CATEGORY_ELEMENT.to_string());
+        change
+    }
+
+    /// Id de la categoría, si el cambio es de una categoría
+    pub fn category_id(&self) -> Option<CategoryId> {
+        (self.get_metadata(ELEMENT_KIND_KEY).map(String::as_str) == Some(CATEGORY_ELEMENT))
+            .then(|| CategoryId::from(*self.element_id.as_uuid()))
+    }
+
     /// Agregar metadato
     pub fn add_metadata(&mut self, key: String, value: String) {
         self.metadata.insert(key, value);
@@ -133,7 +236,7 @@ impl DataChange {
 
     /// Verificar si el cambio es válido
     pub fn is_valid(&self) -> bool {
-        !self.element_id.is_empty() && !self.source_device.is_empty()
+        !self.element_id.as_uuid().is_nil() && !self.source_device.as_uuid().is_nil()
     }
 
     /// Obtener tamaño de los datos
@@ -142,13 +245,52 @@ impl DataChange {
     }
 }
 
+impl From<&DataChange> for SyncChangeRow {
+    fn from(change: &DataChange) -> Self {
+        Self {
+            id: change.id.clone(),
+            element_id: change.element_id,
+            change_type: change.change_type.as_str().to_string(),
+            timestamp: change.timestamp.to_rfc3339(),
+            source_device: change.source_device,
+            element_data: change.element_data.clone(),
+            metadata: serde_json::to_string(&change.metadata).unwrap_or_else(|_| "{}".to_string()),
+            version: change.version as i64,
+            previous_hash: change.previous_hash.clone(),
+            current_hash: change.current_hash.clone(),
+            versions: serde_json::to_string(&change.versions).unwrap_or_else(|_| "{}".to_string()),
+        }
+    }
+}
+
+impl TryFrom<SyncChangeRow> for DataChange {
+    type Error = anyhow::Error;
+
+    fn try_from(row: SyncChangeRow) -> Result<Self> {
+        Ok(Self {
+            change_type: row.change_type.parse()?,
+            timestamp: DateTime::parse_from_rfc3339(&row.timestamp)?.with_timezone(&Utc),
+            metadata: serde_json::from_str(&row.metadata)?,
+            versions: serde_json::from_str(&row.versions)?,
+            field_stamps: FieldStamps::new(),
+            version: row.version as u64,
+            id: row.id,
+            element_id: row.element_id,
+            source_device: row.source_device,
+            element_data: row.element_data,
+            previous_hash: row.previous_hash,
+            current_hash: row.current_hash,
+        })
+    }
+}
+
 /// Conflicto de sincronización
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncConflict {
     /// ID del conflicto
     pub id: String,
     /// ID del elemento en conflicto
-    pub element_id: String,
+    pub element_id: EntryId,
     /// Cambios en conflicto
     pub conflicting_changes: Vec<DataChange>,
     /// Timestamp del conflicto
@@ -159,6 +301,25 @@ pub struct SyncConflict {
     pub resolution: Option<ConflictResolution>,
 }
 
+impl SyncConflict {
+    /// Conflicto pendiente entre un cambio recibido y el estado de aquí
+    pub fn new(remote: DataChange, local: DataChange) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            element_id: remote.element_id,
+            conflicting_changes: vec![remote, local],
+            timestamp: Utc::now(),
+            status: ConflictStatus::Pending,
+            resolution: None,
+        }
+    }
+
+    /// Cambio que llegó del otro dispositivo
+    pub fn remote_change(&self) -> Option<&DataChange> {
+        self.conflicting_changes.first()
+    }
+}
+
 /// Estado del conflicto
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ConflictStatus {
@@ -187,6 +348,357 @@ pub enum ConflictResolution {
     Delete,
 }
 
+/// Longitud del nonce al comienzo de un lote sellado
+const BATCH_NONCE_LEN: usize = 12;
+
+/// Compresión de lotes que entiende este dispositivo
+pub const BATCH_COMPRESSION: &str = "zstd";
+
+/// Comienzo de un marco zstd; un lote en JSON nunca empieza así
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Nivel de compresión de los lotes
+const ZSTD_LEVEL: i32 = 3;
+
+/// Tamaño máximo de un lote descomprimido
+const MAX_BATCH_SIZE: usize = 64 * 1024 * 1024;
+
+/// Cabecera de los lotes de nivel militar. Los de nivel estándar empiezan
+/// directamente por el nonce, como en las versiones anteriores.
+const MILITARY_HEADER: &[u8; 4] = b"ALS\x01";
+
+/// Etiquetas con que se derivan de la clave de sincronización las dos claves
+/// del nivel militar
+const AES_KEY_INFO: &[u8] = b"alohopass-sync-batch-aes-256-gcm";
+const CHACHA_KEY_INFO: &[u8] = b"alohopass-sync-batch-chacha20-poly1305";
+
+/// Lote de cambios que un dispositivo manda a otro
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncBatch {
+    /// Dispositivo que manda el lote
+    pub source_device: DeviceId,
+    /// Lo que vio el dispositivo que manda el lote; el otro responde con lo
+    /// que no está aquí
+    #[serde(default)]
+    pub knowledge: VersionVector,
+    pub changes: Vec<DataChange>,
+    /// Compresiones con que el dispositivo que manda acepta lotes; vacío en
+    /// las versiones que no comprimen
+    #[serde(default)]
+    pub compression: Vec<String>,
+    /// Página de la copia inicial que se pide o que se manda; `None` en la
+    /// sincronización incremental y en las versiones que no copian por
+    /// páginas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotCursor>,
+    /// Versión de Alohopass del dispositivo que manda; `None` en las
+    /// versiones que no la mandan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_version: Option<String>,
+    /// Capacidades del dispositivo que manda, con la versión mínima que
+    /// acepta del otro
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<DeviceCapabilities>,
+    /// Elementos cuyo borrado aplicó el dispositivo que manda, del lote
+    /// anterior del otro; el otro ya puede purgar sus marcas de eliminación
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub acknowledged_deletions: Vec<EntryId>,
+}
+
+/// Hasta dónde va una copia inicial de la bóveda
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotCursor {
+    /// En el pedido, el último elemento recibido; en la respuesta, el último
+    /// que va en la página
+    pub position: Option<String>,
+    /// En la respuesta, si es la última página
+    #[serde(default)]
+    pub complete: bool,
+}
+
+/// Lugar de un cambio en la copia inicial. Las categorías van antes que las
+/// entradas, así cada entrada llega cuando su categoría ya está.
+fn snapshot_position(change: &DataChange) -> String {
+    let kind = if change.category_id().is_some() { 0 } else { 1 };
+    format!("{}{}", kind, change.element_id)
+}
+
+impl SyncBatch {
+    /// Serializa el lote, lo comprime con zstd si `compress` y lo encripta con
+    /// la clave de sincronización y la suite de `level`
+    pub fn seal(&self, sync_key: &[u8; 32], compress: bool, level: EncryptionLevel) -> Result<Vec<u8>> {
+        let mut payload = serde_json::to_vec(self)?;
+        if compress {
+            payload = zstd::bulk::compress(&payload, ZSTD_LEVEL)?;
+        }
+        encrypt_batch(&payload, sync_key, level)
+    }
+
+    /// Si el dispositivo que mandó el lote acepta lotes comprimidos
+    pub fn accepts_compression(&self) -> bool {
+        self.compression.iter().any(|compression| compression == BATCH_COMPRESSION)
+    }
+
+    /// Desencripta un lote sellado con [`SyncBatch::seal`] y devuelve también
+    /// el nivel con que venía. Falla si ese nivel es menor que `minimum`, si el
+    /// lote no viene encriptado o si no lo mandó `source_device`, por ejemplo
+    /// si es un lote propio reenviado.
+    pub fn open(
+        sealed: &[u8],
+        sync_key: &[u8; 32],
+        source_device: DeviceId,
+        minimum: EncryptionLevel,
+    ) -> Result<(Self, EncryptionLevel)> {
+        let (mut payload, level) = decrypt_batch(sealed, sync_key).map_err(|e| {
+            if serde_json::from_slice::<SyncBatch>(sealed).is_ok() {
+                anyhow!("Rechazado un lote de sincronización sin encriptar de {}", source_device)
+            } else {
+                e
+            }
+        })?;
+        if level < minimum {
+            return Err(anyhow!(
+                "El lote de {} usa una encriptación {:?}, menor que la configurada ({:?})",
+                source_device, level, minimum
+            ));
+        }
+        if payload.starts_with(&ZSTD_MAGIC) {
+            payload = zstd::bulk::decompress(&payload, MAX_BATCH_SIZE)
+                .map_err(|e| anyhow!("Lote de sincronización comprimido inválido: {}", e))?;
+        }
+        let batch: Self = serde_json::from_slice(&payload)?;
+        if batch.source_device != source_device {
+            return Err(anyhow!("El lote de sincronización no viene de {}", source_device));
+        }
+        Ok((batch, level))
+    }
+}
+
+/// Separa el nonce del comienzo de `data`
+fn split_nonce(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    if data.len() < BATCH_NONCE_LEN {
+        return Err(anyhow!("Lote de sincronización demasiado corto"));
+    }
+    Ok(data.split_at(BATCH_NONCE_LEN))
+}
+
+/// Clave para `info` derivada de la clave de sincronización
+fn batch_subkey(sync_key: &[u8; 32], info: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, sync_key)
+        .expand(info, &mut key)
+        .map_err(|e| anyhow!("Error al derivar la clave del lote: {}", e))?;
+    Ok(key)
+}
+
+/// Encripta un lote. El nivel estándar es ChaCha20-Poly1305 con la clave de
+/// sincronización; el militar encripta antes con AES-256-GCM, y cada suite
+/// usa su propia clave derivada.
+fn encrypt_batch(payload: &[u8], sync_key: &[u8; 32], level: EncryptionLevel) -> Result<Vec<u8>> {
+    match level {
+        EncryptionLevel::Standard => {
+            let (ciphertext, nonce) = encrypt_data(payload, sync_key)?;
+            Ok([nonce, ciphertext].concat())
+        }
+        EncryptionLevel::Military => {
+            let aes_key = batch_subkey(sync_key, AES_KEY_INFO)?;
+            let mut nonce = [0u8; BATCH_NONCE_LEN];
+            OsRng.fill_bytes(&mut nonce);
+            let inner = Aes256Gcm::new(AesKey::<Aes256Gcm>::from_slice(&aes_key))
+                .encrypt(AesNonce::from_slice(&nonce), payload)
+                .map_err(|e| anyhow!("Error al encriptar el lote: {}", e))?;
+            let chacha_key = batch_subkey(sync_key, CHACHA_KEY_INFO)?;
+            let (ciphertext, outer_nonce) = encrypt_data(&[&nonce[..], &inner].concat(), &chacha_key)?;
+            Ok([&MILITARY_HEADER[..], &outer_nonce, &ciphertext].concat())
+        }
+    }
+}
+
+/// Desencripta un lote sellado con [`encrypt_batch`] y dice con qué nivel
+fn decrypt_batch(sealed: &[u8], sync_key: &[u8; 32]) -> Result<(Vec<u8>, EncryptionLevel)> {
+    if let Some(body) = sealed.strip_prefix(&MILITARY_HEADER[..]) {
+        // Un nonce estándar puede empezar igual que la cabecera: si no abre,
+        // se prueba como estándar
+        if let Ok(payload) = decrypt_military(body, sync_key) {
+            return Ok((payload, EncryptionLevel::Military));
+        }
+    }
+    let (nonce, ciphertext) = split_nonce(sealed)?;
+    Ok((decrypt_data(ciphertext, sync_key, nonce)?, EncryptionLevel::Standard))
+}
+
+fn decrypt_military(body: &[u8], sync_key: &[u8; 32]) -> Result<Vec<u8>> {
+    let (outer_nonce, ciphertext) = split_nonce(body)?;
+    let inner = decrypt_data(ciphertext, &batch_subkey(sync_key, CHACHA_KEY_INFO)?, outer_nonce)?;
+    let (nonce, ciphertext) = split_nonce(&inner)?;
+    let aes_key = batch_subkey(sync_key, AES_KEY_INFO)?;
+    Aes256Gcm::new(AesKey::<Aes256Gcm>::from_slice(&aes_key))
+        .decrypt(AesNonce::from_slice(nonce), ciphertext)
+        .map_err(|e| anyhow!("Error al desencriptar el lote: {}", e))
+}
+
+/// Acceso a la bóveda desde la sincronización. La aplicación lo implementa
+/// sobre la base abierta.
+///
+/// En los cambios `Created` y `Modified` que viajan, `element_data` es la
+/// entrada (`PasswordEntry`) en JSON, sin encriptar con la clave maestra: cada
+/// dispositivo tiene la suya. Lo que protege la entrada en el camino es la
+/// encriptación del lote.
+#[async_trait]
+pub trait SyncStore: Send + Sync {
+    /// Id con el que este dispositivo firma sus lotes
+    async fn local_device_id(&self) -> Result<DeviceId>;
+    /// Clave de sincronización acordada al emparejarse con `device_id`
+    async fn sync_key(&self, device_id: DeviceId) -> Result<[u8; 32]>;
+    /// Lo que vio este dispositivo: el último número de cambio de cada uno
+    async fn knowledge(&self) -> Result<VersionVector>;
+    /// Un cambio por cada elemento con versiones que no están en `known`, con
+    /// su contenido actual y su vector; `Deleted` si ya no existe
+    async fn load_changes_since(&self, known: &VersionVector) -> Result<Vec<DataChange>>;
+    /// Aplica los cambios de otro dispositivo. Los vectores de todos se unen a
+    /// los de aquí, se apliquen o no, salvo los que `strategy` manda dejar
+    /// como conflicto para que decida el usuario.
+    async fn apply_changes(&self, changes: Vec<DataChange>, strategy: &ConflictResolutionStrategy) -> Result<AppliedChanges>;
+    /// Lo que `device_id` dijo haber visto en la última sincronización
+    async fn peer_knowledge(&self, device_id: DeviceId) -> Result<VersionVector>;
+    /// Guarda lo que `device_id` dice haber visto
+    async fn save_peer_knowledge(&self, device_id: DeviceId, knowledge: VersionVector) -> Result<()>;
+    /// Copia inicial que se está recibiendo de `device_id`, si hay una a medias
+    async fn bootstrap(&self, device_id: DeviceId) -> Result<Option<SyncBootstrap>>;
+    /// Anota hasta dónde llegó la copia de `device_id`; `None` cuando terminó
+    async fn save_bootstrap(&self, device_id: DeviceId, bootstrap: Option<SyncBootstrap>) -> Result<()>;
+    /// Si la bóveda está desbloqueada. Bloqueada no hay claves con las que
+    /// sincronizar, así que la sincronización automática espera.
+    async fn is_unlocked(&self) -> bool {
+        true
+    }
+    /// Anota un intento de sincronización en el historial
+    async fn record_sync(&self, _record: SyncHistoryRecord) -> Result<()> {
+        Ok(())
+    }
+    /// Deja en `changes` lo que se manda a `device_id`: las entradas de las
+    /// categorías excluidas se cambian por `Excluded` y se agregan las que
+    /// volvieron a estar a su alcance
+    async fn scope_changes(&self, _device_id: DeviceId, changes: Vec<DataChange>) -> Result<Vec<DataChange>> {
+        Ok(changes)
+    }
+    /// Anota lo que se entregó a `device_id` en un lote de
+    /// [`SyncStore::scope_changes`]
+    async fn record_delivery(&self, _device_id: DeviceId, _changes: &[DataChange]) -> Result<()> {
+        Ok(())
+    }
+    /// Anota que `device_id` aplicó el borrado de `entry_ids` y purga las
+    /// marcas de eliminación que ya confirmaron todos los de confianza
+    async fn acknowledge_deletions(&self, _device_id: DeviceId, _entry_ids: &[EntryId]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Lo que viajó en una sincronización
+#[derive(Debug, Clone, Copy, Default)]
+struct SyncTraffic {
+    /// Cambios enviados
+    sent: usize,
+    /// Cambios recibidos que se aplicaron
+    applied: usize,
+    bytes_sent: usize,
+    bytes_received: usize,
+}
+
+impl SyncTraffic {
+    fn bytes(&self) -> usize {
+        self.bytes_sent + self.bytes_received
+    }
+}
+
+/// Deja en el historial un intento de sincronización. Si no se puede anotar
+/// la sincronización sigue igual.
+async fn record_attempt(
+    store: &dyn SyncStore,
+    device_id: DeviceId,
+    direction: SyncDirection,
+    started_at: DateTime<Utc>,
+    start_time: Instant,
+    result: Result<SyncTraffic, &anyhow::Error>,
+) {
+    let traffic = result.as_ref().copied().unwrap_or_default();
+    let record = SyncHistoryRecord {
+        id: 0,
+        device_id,
+        direction,
+        items_sent: traffic.sent as u64,
+        items_received: traffic.applied as u64,
+        bytes_sent: traffic.bytes_sent as u64,
+        bytes_received: traffic.bytes_received as u64,
+        duration_ms: start_time.elapsed().as_millis() as u64,
+        error: result.err().map(|e| e.to_string()),
+        started_at: started_at.to_rfc3339(),
+    };
+    if let Err(e) = store.record_sync(record).await {
+        log::warn!("No se pudo anotar la sincronización con {} en el historial: {}", device_id, e);
+    }
+}
+
+/// Resultado de aplicar los cambios de otro dispositivo
+#[derive(Debug, Default)]
+pub struct AppliedChanges {
+    /// Cambios aplicados a la bóveda
+    pub applied: usize,
+    /// Cambios que quedaron sin aplicar hasta que el usuario decida
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// Dónde se guardan los cambios pendientes entre un inicio y otro
+#[async_trait]
+pub trait ChangeJournal: Send + Sync {
+    /// Guarda un cambio pendiente
+    async fn record(&self, change: &DataChange) -> Result<()>;
+    /// Marca cambios como sincronizados
+    async fn mark_synced(&self, change_ids: Vec<String>) -> Result<()>;
+    /// Cambios que quedaron sin sincronizar, del más antiguo al más reciente
+    async fn load_pending(&self) -> Result<Vec<DataChange>>;
+    /// Descarta los cambios sincronizados antes de `cutoff`
+    async fn prune(&self, cutoff: DateTime<Utc>) -> Result<usize>;
+}
+
+/// Canal por el que viajan los lotes de sincronización
+#[async_trait]
+pub trait SyncTransport: Send + Sync {
+    /// Manda un lote sellado y devuelve el lote con que responde el otro
+    /// dispositivo
+    async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>>;
+
+    /// Estado de la conexión por debajo, si es WebRTC
+    async fn diagnostics(&self) -> Option<P2PConnectionStats> {
+        None
+    }
+}
+
+/// Responde los lotes que llegan por una conexión; el otro extremo de
+/// [`SyncTransport::exchange`]
+#[async_trait]
+pub trait SyncResponder: Send + Sync {
+    /// Devuelve el lote con que se responde al que mandó `from`
+    async fn respond(&self, from: DeviceId, request: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Buzón donde cada dispositivo deja un lote sellado para otro, por ejemplo
+/// en la nube, para sincronizar sin estar conectados a la vez. Quien guarda el
+/// buzón no puede leer los lotes: van encriptados con la clave del
+/// emparejamiento.
+#[async_trait]
+pub trait SyncMailbox: Send + Sync {
+    /// Deja el lote de `from` para `to`, reemplazando el anterior
+    async fn put(&self, from: DeviceId, to: DeviceId, sealed: Vec<u8>) -> Result<()>;
+    /// Recoge el lote que `from` dejó para `to`, si hay uno, y lo saca del
+    /// buzón. Si mientras tanto `from` dejó otro, el nuevo se queda.
+    async fn take(&self, from: DeviceId, to: DeviceId) -> Result<Option<Vec<u8>>>;
+    /// Si el lote que `from` dejó para `to` sigue sin recoger
+    async fn is_waiting(&self, from: DeviceId, to: DeviceId) -> Result<bool>;
+}
+
 /// Sincronización inteligente
 pub struct SmartSync {
     /// Cambios pendientes de sincronización
@@ -202,7 +714,17 @@ pub struct SmartSync {
     /// Manejador de eventos
     event_handler: Arc<dyn SyncEventHandler + Send + Sync>,
     /// Configuración de sincronización
-    config: SyncConfig,
+    config: RwLock<SyncConfig>,
+    /// Diario donde se guardan los cambios; sin él solo viven en memoria
+    journal: Option<Arc<dyn ChangeJournal>>,
+    /// Dispositivos que dijeron aceptar lotes comprimidos. Al primero que se
+    /// le manda un lote va sin comprimir, por si es una versión anterior.
+    compressing_peers: RwLock<HashSet<DeviceId>>,
+    /// Versión y capacidades que mandó cada dispositivo en su último lote
+    peer_capabilities: RwLock<HashMap<DeviceId, (String, DeviceCapabilities)>>,
+    /// Cambios del último lote que se dejó en el buzón para cada dispositivo.
+    /// Se dan por entregados cuando el otro lo recoge.
+    mailbox_outbox: RwLock<HashMap<DeviceId, Vec<DataChange>>>,
 }
 
 /// Estado de sincronización
@@ -215,7 +737,7 @@ pub struct SyncState {
     /// Próxima sincronización programada
     pub next_sync: Option<DateTime<Utc>>,
     /// Dispositivos sincronizando
-    pub syncing_devices: Vec<String>,
+    pub syncing_devices: Vec<DeviceId>,
     /// Cambios pendientes
     pub pending_changes_count: usize,
     /// Conflictos pendientes
@@ -235,8 +757,8 @@ pub struct SyncConfig {
     pub conflict_resolution_strategy: ConflictResolutionStrategy,
     /// Compresión de datos
     pub enable_compression: bool,
-    /// Encriptación de datos
-    pub enable_encryption: bool,
+    /// Encriptación de los lotes que se mandan y mínima de los que se aceptan
+    pub encryption_level: EncryptionLevel,
     /// Tamaño máximo del batch
     pub max_batch_size: usize,
     /// Tiempo de espera para sincronización (segundos)
@@ -266,7 +788,7 @@ impl Default for SyncConfig {
             auto_resolve_conflicts: true,
             conflict_resolution_strategy: ConflictResolutionStrategy::LatestWins,
             enable_compression: true,
-            enable_encryption: true,
+            encryption_level: EncryptionLevel::Standard,
             max_batch_size: 100,
             sync_timeout: 60,
         }
@@ -296,15 +818,60 @@ impl SmartSync {
             sync_state: Arc::new(RwLock::new(SyncState::default())),
             event_sender,
             event_handler: Arc::new(crate::sync::DefaultSyncEventHandler),
-            config,
+            config: RwLock::new(config),
+            journal: None,
+            compressing_peers: RwLock::new(HashSet::new()),
+            peer_capabilities: RwLock::new(HashMap::new()),
+            mailbox_outbox: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Guarda los cambios en `journal`
+    pub fn with_journal(mut self, journal: Arc<dyn ChangeJournal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Carga del diario los cambios que quedaron sin sincronizar. Los que ya
+    /// estaban en memoria no se repiten. Devuelve cuántos se cargaron.
+    pub async fn load_journal(&self) -> Result<usize> {
+        let Some(journal) = &self.journal else {
+            return Ok(0);
+        };
+        let stored = journal.load_pending().await?;
+        let (loaded, pending_count) = {
+            let mut pending = self.pending_changes.write().await;
+            let mut loaded = 0;
+            for change in stored {
+                if !pending.iter().any(|pending| pending.id == change.id) {
+                    pending.push(change);
+                    loaded += 1;
+                }
+            }
+            pending.sort_by_key(|change| change.timestamp);
+            (loaded, pending.len())
+        };
+        self.sync_state.write().await.pending_changes_count = pending_count;
+        log::info!("{} cambios pendientes cargados del diario de sincronización", loaded);
+        Ok(loaded)
+    }
+
     /// Crear con configuración por defecto
     pub fn new_default(event_sender: mpsc::Sender<SyncEvent>) -> Self {
         Self::new(SyncConfig::default(), event_sender)
     }
 
+    /// Cambia cómo se tratan las ediciones concurrentes de aquí en adelante
+    pub async fn set_conflict_resolution_strategy(&self, strategy: ConflictResolutionStrategy) {
+        self.config.write().await.conflict_resolution_strategy = strategy;
+    }
+
+    /// Nivel de encriptación de los lotes que se mandan y mínimo de los que
+    /// se aceptan
+    pub async fn set_encryption_level(&self, level: EncryptionLevel) {
+        self.config.write().await.encryption_level = level;
+    }
+
     /// Agregar un cambio para sincronización
     pub async fn add_change(&self, change: DataChange) -> Result<()> {
         if !change.is_valid() {
@@ -317,6 +884,22 @@ impl SmartSync {
             change.data_size()
         );
 
+        if let Some(journal) = &self.journal {
+            journal.record(&change).await?;
+        }
+        self.enqueue(change).await
+    }
+
+    /// Agrega un cambio que ya está en el diario porque se anotó en la misma
+    /// transacción que lo hizo
+    pub async fn add_recorded_change(&self, change: DataChange) -> Result<()> {
+        if !change.is_valid() {
+            return Err(anyhow!("Cambio inválido"));
+        }
+        self.enqueue(change).await
+    }
+
+    async fn enqueue(&self, change: DataChange) -> Result<()> {
         // Agregar a cambios pendientes
         {
             let mut pending = self.pending_changes.write().await;
@@ -335,6 +918,36 @@ impl SmartSync {
         Ok(())
     }
 
+    /// Encola como cambios `Deleted` las marcas de eliminación que todavía no
+    /// estén pendientes. Devuelve cuántas se encolaron.
+    pub async fn queue_tombstones(&self, tombstones: &[Tombstone], source_device: DeviceId) -> Result<usize> {
+        let pending = self.get_pending_changes().await;
+        let mut queued = 0;
+        for tombstone in tombstones {
+            let already_pending = pending.iter().any(|change| {
+                change.change_type == ChangeType::Deleted && change.element_id == tombstone.entry_id
+            });
+            if already_pending {
+                continue;
+            }
+            let mut change = DataChange::new(
+                tombstone.entry_id,
+                ChangeType::Deleted,
+                source_device,
+                None,
+                0,
+                None,
+            );
+            if let Ok(deleted_at) = DateTime::parse_from_rfc3339(&tombstone.deleted_at) {
+                change.timestamp = deleted_at.with_timezone(&Utc);
+            }
+            change.add_metadata("deleted_at".to_string(), tombstone.deleted_at.clone());
+            self.add_change(change).await?;
+            queued += 1;
+        }
+        Ok(queued)
+    }
+
     /// Obtener cambios pendientes
     pub async fn get_pending_changes(&self) -> Vec<DataChange> {
         self.pending_changes.read().await.clone()
@@ -350,141 +963,519 @@ impl SmartSync {
         self.conflicts.read().await.clone()
     }
 
-    /// Sincronizar cambios con un dispositivo
-    pub async fn sync_with_device(&self, device: &DeviceInfo) -> Result<SyncResult> {
+    /// Sincroniza con un dispositivo: le manda en un lote encriptado con la
+    /// clave del emparejamiento los elementos que todavía no vio, aplica los
+    /// que devuelve y da por sincronizados los pendientes que ya conoce
+    pub async fn sync_with_device(
+        &self,
+        device_id: DeviceId,
+        store: &dyn SyncStore,
+        transport: &dyn SyncTransport,
+    ) -> Result<SyncResult> {
         let start_time = Instant::now();
-        
-        log::info!("Iniciando sincronización con: {} ({})", 
-            device.name, device.device_type.display_name()
-        );
-
-        // Obtener cambios pendientes
-        let pending_changes = self.get_pending_changes().await;
-        if pending_changes.is_empty() {
-            log::info!("No hay cambios pendientes para sincronizar");
-            return Ok(SyncResult::success(
-                device.id.clone(),
-                0,
-                0,
-                start_time.elapsed().as_millis() as u64,
-            ));
-        }
+        let started_at = Utc::now();
+        log::info!("Iniciando sincronización con {}", device_id);
 
         // Agregar dispositivo a la lista de sincronización
         {
             let mut state = self.sync_state.write().await;
-            if !state.syncing_devices.contains(&device.id) {
-                state.syncing_devices.push(device.id.clone());
+            if !state.syncing_devices.contains(&device_id) {
+                state.syncing_devices.push(device_id);
             }
         }
 
-        // Procesar cambios en batches
-        let mut total_synced = 0;
-        let mut total_data_size = 0;
+        let exchanged = self.exchange_changes(device_id, store, transport).await;
+        self.sync_state.write().await.syncing_devices.retain(|id| id != &device_id);
+        record_attempt(store, device_id, SyncDirection::Outgoing, started_at, start_time, exchanged.as_ref().copied()).await;
+        let traffic = exchanged?;
+
+        let duration = start_time.elapsed().as_millis() as u64;
+        log::info!("Sincronización con {} completada: {} cambios enviados, {} aplicados, {} bytes, {}ms",
+            device_id, traffic.sent, traffic.applied, traffic.bytes(), duration
+        );
+
+        Ok(SyncResult::success(
+            device_id,
+            (traffic.sent + traffic.applied) as u64,
+            traffic.bytes() as u64,
+            duration,
+        ))
+    }
+
+    /// Manda el lote con lo que el otro dispositivo no vio según la última
+    /// sincronización y aplica el que responde. Si este dispositivo todavía no
+    /// tiene nada, o dejó una copia inicial a medias, antes recibe la bóveda
+    /// entera del otro.
+    async fn exchange_changes(
+        &self,
+        device_id: DeviceId,
+        store: &dyn SyncStore,
+        transport: &dyn SyncTransport,
+    ) -> Result<SyncTraffic> {
+        let sync_key = store.sync_key(device_id).await?;
+        let level = self.config.read().await.encryption_level;
+        let mut traffic = SyncTraffic::default();
+        let mut acknowledged = Vec::new();
+        let bootstrap = match store.bootstrap(device_id).await? {
+            Some(bootstrap) => Some(bootstrap),
+            None if store.knowledge().await?.is_empty() => {
+                log::info!("Este dispositivo no tiene nada todavía: se copia la bóveda de {}", device_id);
+                Some(SyncBootstrap::default())
+            }
+            None => None,
+        };
+        let bootstrap = match bootstrap {
+            Some(progress) if !progress.complete => {
+                Some(self.receive_snapshot(device_id, store, transport, progress, &mut traffic, &mut acknowledged).await?)
+            }
+            bootstrap => bootstrap,
+        };
+
+        let known = store.peer_knowledge(device_id).await?;
+        let mut outgoing = self.outgoing_batch(store, device_id, &known).await?;
+        if let Some(progress) = &bootstrap {
+            // Lo que llegó en la copia no cuenta como visto: lo que cambió
+            // allá mientras tanto tiene que volver a llegar
+            outgoing.knowledge = progress.knowledge.clone();
+        }
+        outgoing.acknowledged_deletions = acknowledged;
+        let request = outgoing.seal(&sync_key, self.compress_for(device_id).await, level)?;
+        let reply = transport.exchange(request.clone()).await?;
+        let (incoming, _) = SyncBatch::open(&reply, &sync_key, device_id, level)?;
+        self.remember_peer(device_id, &incoming).await?;
+        if !incoming.acknowledged_deletions.is_empty() {
+            store.acknowledge_deletions(device_id, &incoming.acknowledged_deletions).await?;
+        }
 
-        for batch in pending_changes.chunks(self.config.max_batch_size) {
-            match self.process_batch(batch, device).await {
-                Ok((synced_count, data_size)) => {
-                    total_synced += synced_count;
-                    total_data_size += data_size;
+        let (applied, mut deleted) = self.apply_remote_changes(incoming.changes, store).await?;
+        traffic.applied += applied;
+        self.mark_known_changes(&incoming.knowledge).await?;
+        store.save_peer_knowledge(device_id, incoming.knowledge).await?;
+        store.record_delivery(device_id, &outgoing.changes).await?;
+        if bootstrap.is_some() {
+            store.save_bootstrap(device_id, None).await?;
+            log::info!("Copia inicial de {} terminada", device_id);
+        }
+        traffic.sent += outgoing.changes.len();
+        traffic.bytes_sent += request.len();
+        traffic.bytes_received += reply.len();
+
+        // El otro purga sus marcas de eliminación cuando sabe que aquí se
+        // aplicaron sus borrados
+        while !deleted.is_empty() {
+            deleted = self.send_acknowledgements(device_id, store, transport, deleted, &mut traffic).await?;
+        }
+        Ok(traffic)
+    }
+
+    /// Confirma a `device_id` los borrados suyos que se aplicaron aquí y
+    /// aplica lo que responde, que el otro ya da por entregado. Devuelve los
+    /// borrados de la respuesta, que también hay que confirmar.
+    async fn send_acknowledgements(
+        &self,
+        device_id: DeviceId,
+        store: &dyn SyncStore,
+        transport: &dyn SyncTransport,
+        deleted: Vec<EntryId>,
+        traffic: &mut SyncTraffic,
+    ) -> Result<Vec<EntryId>> {
+        let sync_key = store.sync_key(device_id).await?;
+        let level = self.config.read().await.encryption_level;
+        let mut request = self.batch_with(store, Vec::new()).await?;
+        request.acknowledged_deletions = deleted;
+        let sealed = request.seal(&sync_key, self.compress_for(device_id).await, level)?;
+        let reply = transport.exchange(sealed.clone()).await?;
+        let (incoming, _) = SyncBatch::open(&reply, &sync_key, device_id, level)?;
+        self.remember_peer(device_id, &incoming).await?;
+
+        let (applied, deleted) = self.apply_remote_changes(incoming.changes, store).await?;
+        self.mark_known_changes(&incoming.knowledge).await?;
+        store.save_peer_knowledge(device_id, incoming.knowledge).await?;
+        traffic.applied += applied;
+        traffic.bytes_sent += sealed.len();
+        traffic.bytes_received += reply.len();
+        Ok(deleted)
+    }
+
+    /// Pide a `device_id` las páginas de su bóveda que faltan desde
+    /// `progress` y las aplica, anotando después de cada una hasta dónde
+    /// llegó. Los borrados aplicados se confirman en la página siguiente; los
+    /// de la última quedan en `acknowledged`. Devuelve la copia terminada.
+    async fn receive_snapshot(
+        &self,
+        device_id: DeviceId,
+        store: &dyn SyncStore,
+        transport: &dyn SyncTransport,
+        mut progress: SyncBootstrap,
+        traffic: &mut SyncTraffic,
+        acknowledged: &mut Vec<EntryId>,
+    ) -> Result<SyncBootstrap> {
+        let sync_key = store.sync_key(device_id).await?;
+        let level = self.config.read().await.encryption_level;
+        // Anotada antes de aplicar nada: si se corta en la primera página lo
+        // aplicado no se confunde con lo visto
+        store.save_bootstrap(device_id, Some(progress.clone())).await?;
+
+        while !progress.complete {
+            let mut request = self.batch_with(store, Vec::new()).await?;
+            request.knowledge = progress.knowledge.clone();
+            request.snapshot = Some(SnapshotCursor { position: progress.position.clone(), complete: false });
+            request.acknowledged_deletions = std::mem::take(acknowledged);
+            let sealed = request.seal(&sync_key, self.compress_for(device_id).await, level)?;
+            let reply = transport.exchange(sealed.clone()).await?;
+            let (page, _) = SyncBatch::open(&reply, &sync_key, device_id, level)?;
+            self.remember_peer(device_id, &page).await?;
+            traffic.bytes_sent += sealed.len();
+            traffic.bytes_received += reply.len();
+            let (applied, deleted) = self.apply_remote_changes(page.changes, store).await?;
+            traffic.applied += applied;
+            acknowledged.extend(deleted);
+
+            match page.snapshot {
+                Some(cursor) => {
+                    // Lo que tenía el otro al empezar es lo que la copia trae
+                    if progress.position.is_none() {
+                        progress.knowledge.merge(&page.knowledge);
+                        store.save_peer_knowledge(device_id, page.knowledge).await?;
+                    }
+                    progress.position = cursor.position;
+                    progress.complete = cursor.complete;
                 }
-                Err(e) => {
-                    log::error!("Error al procesar batch: {}", e);
-                    return Ok(SyncResult::failure(
-                        device.id.clone(),
-                        e.to_string(),
-                    ));
+                None => {
+                    // Una versión anterior responde con un lote incremental,
+                    // que ya trae todo lo que no estaba en el vector
+                    log::info!("{} no copia la bóveda por páginas; se usa su lote", device_id);
+                    self.mark_known_changes(&page.knowledge).await?;
+                    progress.knowledge.merge(&page.knowledge);
+                    store.save_peer_knowledge(device_id, page.knowledge).await?;
+                    progress.complete = true;
                 }
             }
+            store.save_bootstrap(device_id, Some(progress.clone())).await?;
+            log::debug!("Página de la copia de {} aplicada (hasta {:?})", device_id, progress.position);
         }
+        Ok(progress)
+    }
 
-        // Marcar cambios como sincronizados
-        self.mark_changes_as_synced(&pending_changes).await?;
+    /// Responde al lote que mandó otro dispositivo con lo que no vio según su
+    /// vector y aplica sus cambios. Es el otro extremo de
+    /// [`SmartSync::sync_with_device`].
+    pub async fn handle_sync_request(
+        &self,
+        device_id: DeviceId,
+        request: &[u8],
+        store: &dyn SyncStore,
+    ) -> Result<Vec<u8>> {
+        let start_time = Instant::now();
+        let started_at = Utc::now();
+        let answered = self.answer_sync_request(device_id, request, store).await;
+        let traffic = answered.as_ref().map(|(_, traffic)| *traffic);
+        record_attempt(store, device_id, SyncDirection::Incoming, started_at, start_time, traffic).await;
+        answered.map(|(reply, _)| reply)
+    }
+
+    /// Aplica el lote de otro dispositivo y arma la respuesta
+    async fn answer_sync_request(
+        &self,
+        device_id: DeviceId,
+        request: &[u8],
+        store: &dyn SyncStore,
+    ) -> Result<(Vec<u8>, SyncTraffic)> {
+        let sync_key = store.sync_key(device_id).await?;
+        let minimum = self.config.read().await.encryption_level;
+        let (incoming, level) = SyncBatch::open(request, &sync_key, device_id, minimum)?;
+        self.remember_peer(device_id, &incoming).await?;
+        if !incoming.acknowledged_deletions.is_empty() {
+            store.acknowledge_deletions(device_id, &incoming.acknowledged_deletions).await?;
+        }
+        if let Some(cursor) = &incoming.snapshot {
+            let page = self.snapshot_page(store, device_id, cursor).await?;
+            let reply = page.seal(&sync_key, self.compress_for(device_id).await, level.max(minimum))?;
+            store.record_delivery(device_id, &page.changes).await?;
+            log::info!("Página de la copia inicial para {}: {} elementos", device_id, page.changes.len());
+            let traffic = SyncTraffic {
+                sent: page.changes.len(),
+                applied: 0,
+                bytes_sent: reply.len(),
+                bytes_received: request.len(),
+            };
+            return Ok((reply, traffic));
+        }
+        let mut outgoing = self.outgoing_batch(store, device_id, &incoming.knowledge).await?;
+
+        let (applied, deleted) = self.apply_remote_changes(incoming.changes, store).await?;
+        outgoing.acknowledged_deletions = deleted;
+        // Lo visto ya incluye lo que acaba de llegar, así no vuelve a pedirlo
+        outgoing.knowledge = store.knowledge().await?;
+        // Se responde con el nivel del lote si es mayor que el de aquí
+        let reply = outgoing.seal(&sync_key, self.compress_for(device_id).await, level.max(minimum))?;
+
+        // Lo que se guarda como visto por el otro es solo lo que dijo; los
+        // cambios pendientes que van en la respuesta se dan por entregados,
+        // salvo los que quedaron fuera de su alcance
+        let mut delivered = incoming.knowledge.clone();
+        for change in outgoing.changes.iter().filter(|change| change.change_type != ChangeType::Excluded) {
+            delivered.merge(&change.versions);
+        }
+        self.mark_known_changes(&delivered).await?;
+        store.save_peer_knowledge(device_id, incoming.knowledge).await?;
+        store.record_delivery(device_id, &outgoing.changes).await?;
+        log::info!("Sincronización pedida por {}: {} cambios enviados, {} aplicados",
+            device_id, outgoing.changes.len(), applied
+        );
+        let traffic = SyncTraffic {
+            sent: outgoing.changes.len(),
+            applied,
+            bytes_sent: reply.len(),
+            bytes_received: request.len(),
+        };
+        Ok((reply, traffic))
+    }
+
+    /// Sincroniza con un dispositivo a través de `mailbox`: aplica el lote que
+    /// dejó para este dispositivo, si hay uno, y le deja otro con lo que no vio
+    /// según su último lote. Sirve para cuando no está a la vista.
+    pub async fn sync_through_mailbox(
+        &self,
+        device_id: DeviceId,
+        store: &dyn SyncStore,
+        mailbox: &dyn SyncMailbox,
+    ) -> Result<SyncResult> {
+        let start_time = Instant::now();
+        let started_at = Utc::now();
+        log::info!("Sincronizando con {} por el buzón en la nube", device_id);
 
-        // Actualizar estado
         {
             let mut state = self.sync_state.write().await;
-            state.last_sync = Some(Utc::now());
-            state.syncing_devices.retain(|id| id != &device.id);
-            state.pending_changes_count = self.pending_changes.read().await.len();
+            if !state.syncing_devices.contains(&device_id) {
+                state.syncing_devices.push(device_id);
+            }
         }
 
+        let exchanged = self.exchange_through_mailbox(device_id, store, mailbox).await;
+        self.sync_state.write().await.syncing_devices.retain(|id| id != &device_id);
+        record_attempt(store, device_id, SyncDirection::Cloud, started_at, start_time, exchanged.as_ref().copied()).await;
+        let traffic = exchanged?;
+
         let duration = start_time.elapsed().as_millis() as u64;
-        
-        log::info!("Sincronización completada: {} elementos, {} bytes, {}ms", 
-            total_synced, total_data_size, duration
+        log::info!("Buzón de {} al día: {} cambios dejados, {} aplicados, {} bytes, {}ms",
+            device_id, traffic.sent, traffic.applied, traffic.bytes(), duration
         );
 
         Ok(SyncResult::success(
-            device.id.clone(),
-            total_synced as u64,
-            total_data_size as u64,
+            device_id,
+            (traffic.sent + traffic.applied) as u64,
+            traffic.bytes() as u64,
             duration,
         ))
     }
 
-    /// Procesar un batch de cambios
-    async fn process_batch(
+    /// Recoge el lote del otro dispositivo y le deja el de este
+    async fn exchange_through_mailbox(
         &self,
-        changes: &[DataChange],
-        device: &DeviceInfo,
-    ) -> Result<(usize, usize)> {
-        let mut synced_count = 0;
-        let mut total_data_size = 0;
-
-        for change in changes {
-            match self.process_change(change, device).await {
-                Ok(data_size) => {
-                    synced_count += 1;
-                    total_data_size += data_size;
-                }
-                Err(e) => {
-                    log::warn!("Error al procesar cambio {}: {}", change.element_id, e);
-                    // Continuar con el siguiente cambio
-                }
+        device_id: DeviceId,
+        store: &dyn SyncStore,
+        mailbox: &dyn SyncMailbox,
+    ) -> Result<SyncTraffic> {
+        let local_device = store.local_device_id().await?;
+        let sync_key = store.sync_key(device_id).await?;
+        let level = self.config.read().await.encryption_level;
+        let mut traffic = SyncTraffic::default();
+        let mut acknowledged = Vec::new();
+
+        if let Some(sealed) = mailbox.take(device_id, local_device).await? {
+            let (incoming, _) = SyncBatch::open(&sealed, &sync_key, device_id, level)?;
+            self.remember_peer(device_id, &incoming).await?;
+            if !incoming.acknowledged_deletions.is_empty() {
+                store.acknowledge_deletions(device_id, &incoming.acknowledged_deletions).await?;
             }
+            let (applied, deleted) = self.apply_remote_changes(incoming.changes, store).await?;
+            traffic.applied = applied;
+            acknowledged = deleted;
+            traffic.bytes_received = sealed.len();
+            self.mark_known_changes(&incoming.knowledge).await?;
+            store.save_peer_knowledge(device_id, incoming.knowledge).await?;
         }
 
-        Ok((synced_count, total_data_size))
+        // Lo que llevaba el lote anterior solo está entregado si el otro lo
+        // recogió; si no, el nuevo lo vuelve a llevar
+        if !mailbox.is_waiting(local_device, device_id).await? {
+            if let Some(delivered) = self.mailbox_outbox.write().await.remove(&device_id) {
+                store.record_delivery(device_id, &delivered).await?;
+            }
+        }
+
+        let known = store.peer_knowledge(device_id).await?;
+        let mut outgoing = self.outgoing_batch(store, device_id, &known).await?;
+        outgoing.acknowledged_deletions = acknowledged;
+        let sealed = outgoing.seal(&sync_key, self.compress_for(device_id).await, level)?;
+        traffic.sent = outgoing.changes.len();
+        traffic.bytes_sent = sealed.len();
+        mailbox.put(local_device, device_id, sealed).await?;
+        self.mailbox_outbox.write().await.insert(device_id, outgoing.changes);
+        Ok(traffic)
     }
 
-    /// Procesar un cambio individual
-    async fn process_change(
-        &self,
-        change: &DataChange,
-        device: &DeviceInfo,
-    ) -> Result<usize> {
-        // TODO: Implementar lógica de sincronización real
-        // Por ahora solo simulamos el procesamiento
-        
-        log::debug!("Procesando cambio: {} {} -> {}", 
-            change.change_type.emoji(), 
-            change.element_id, 
-            device.name
-        );
+    /// Lote para `device_id` con los elementos que no están en `known`
+    async fn outgoing_batch(&self, store: &dyn SyncStore, device_id: DeviceId, known: &VersionVector) -> Result<SyncBatch> {
+        // Los cambios primero: al cargarlos la bóveda puede versionar elementos
+        let changes = store.scope_changes(device_id, store.load_changes_since(known).await?).await?;
+        self.batch_with(store, changes).await
+    }
 
-        // Simular envío de datos
-        let data_size = change.data_size();
-        
-        // Simular latencia de red
-        tokio::time::sleep(Duration::from_millis(10)).await;
+    /// Página de la copia inicial para `device_id` con los elementos que van
+    /// después de `cursor`. El vector es el de ahora, no solo el de la página:
+    /// el otro no lo toma como visto hasta terminar la copia.
+    async fn snapshot_page(&self, store: &dyn SyncStore, device_id: DeviceId, cursor: &SnapshotCursor) -> Result<SyncBatch> {
+        let page_size = self.config.read().await.max_batch_size.max(1);
+        let everything = store.scope_changes(device_id, store.load_changes_since(&VersionVector::new()).await?).await?;
+        let mut remaining: Vec<(String, DataChange)> = everything.into_iter()
+            .map(|change| (snapshot_position(&change), change))
+            .filter(|(position, _)| cursor.position.as_ref().is_none_or(|after| position > after))
+            .collect();
+        remaining.sort_by(|a, b| a.0.cmp(&b.0));
+        let complete = remaining.len() <= page_size;
+        remaining.truncate(page_size);
+
+        let position = remaining.last().map(|(position, _)| position.clone()).or_else(|| cursor.position.clone());
+        let mut page = self.batch_with(store, remaining.into_iter().map(|(_, change)| change).collect()).await?;
+        page.snapshot = Some(SnapshotCursor { position, complete });
+        Ok(page)
+    }
+
+    /// Lote de este dispositivo con `changes` y su vector actual
+    async fn batch_with(&self, store: &dyn SyncStore, changes: Vec<DataChange>) -> Result<SyncBatch> {
+        let compression = if self.config.read().await.enable_compression {
+            vec![BATCH_COMPRESSION.to_string()]
+        } else {
+            Vec::new()
+        };
+        Ok(SyncBatch {
+            source_device: store.local_device_id().await?,
+            knowledge: store.knowledge().await?,
+            changes,
+            compression,
+            snapshot: None,
+            app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            capabilities: Some(DeviceCapabilities::default()),
+            acknowledged_deletions: Vec::new(),
+        })
+    }
 
-        Ok(data_size)
+    /// Anota lo que dice de sí el dispositivo que mandó `batch`. Falla si su
+    /// versión y la de este no son compatibles, antes de aplicar nada; el lote
+    /// de una versión que no manda la suya se acepta como antes.
+    async fn remember_peer(&self, device_id: DeviceId, batch: &SyncBatch) -> Result<()> {
+        self.remember_compression(device_id, batch).await;
+        let (Some(version), Some(capabilities)) = (&batch.app_version, &batch.capabilities) else {
+            return Ok(());
+        };
+        self.peer_capabilities.write().await.insert(device_id, (version.clone(), capabilities.clone()));
+        match capabilities.incompatibility_with(version) {
+            Some(incompatibility) => {
+                log::warn!("No se sincroniza con {}: {}", device_id, incompatibility);
+                Err(incompatibility.into())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Versión y capacidades que mandó `device_id` en su último lote
+    pub async fn peer_capabilities(&self, device_id: DeviceId) -> Option<(String, DeviceCapabilities)> {
+        self.peer_capabilities.read().await.get(&device_id).cloned()
+    }
+
+    /// Anota si el dispositivo que mandó `batch` acepta lotes comprimidos
+    async fn remember_compression(&self, device_id: DeviceId, batch: &SyncBatch) {
+        let mut peers = self.compressing_peers.write().await;
+        if batch.accepts_compression() {
+            peers.insert(device_id);
+        } else {
+            peers.remove(&device_id);
+        }
+    }
+
+    /// Si el lote para `device_id` va comprimido: la compresión tiene que
+    /// estar activada aquí y el otro haber dicho que la acepta
+    async fn compress_for(&self, device_id: DeviceId) -> bool {
+        self.config.read().await.enable_compression && self.compressing_peers.read().await.contains(&device_id)
+    }
+
+    /// Da por sincronizados los cambios pendientes que están en `knowledge`
+    async fn mark_known_changes(&self, knowledge: &VersionVector) -> Result<()> {
+        let known: Vec<DataChange> = self.get_pending_changes().await
+            .into_iter()
+            .filter(|change| !change.versions.exceeds(knowledge))
+            .collect();
+        self.mark_changes_as_synced(&known).await
+    }
+
+    /// Aplica los cambios recibidos. Devuelve cuántos se aplicaron y los
+    /// elementos cuyo borrado se aplicó, que se le confirman al otro
+    async fn apply_remote_changes(&self, changes: Vec<DataChange>, store: &dyn SyncStore) -> Result<(usize, Vec<EntryId>)> {
+        let received = changes.len();
+        let changes: Vec<DataChange> = changes.into_iter().filter(DataChange::is_valid).collect();
+        if changes.len() < received {
+            log::warn!("Descartados {} cambios remotos inválidos", received - changes.len());
+        }
+        if changes.is_empty() {
+            return Ok((0, Vec::new()));
+        }
+        let deleted: Vec<EntryId> = changes.iter()
+            .filter(|change| change.change_type == ChangeType::Deleted)
+            .map(|change| change.element_id)
+            .collect();
+        let strategy = self.config.read().await.conflict_resolution_strategy.clone();
+        let outcome = store.apply_changes(changes, &strategy).await?;
+        // Un borrado que quedó como conflicto todavía no está aplicado
+        let deleted = deleted.into_iter()
+            .filter(|entry_id| !outcome.conflicts.iter().any(|conflict| conflict.element_id == *entry_id))
+            .collect();
+        if !outcome.conflicts.is_empty() {
+            log::info!("{} cambios remotos quedaron como conflicto", outcome.conflicts.len());
+            self.add_conflicts(outcome.conflicts).await;
+        }
+        Ok((outcome.applied, deleted))
+    }
+
+    /// Agrega conflictos pendientes. Si un elemento ya tenía uno pendiente, el
+    /// nuevo lo reemplaza: trae la versión más reciente del otro dispositivo.
+    async fn add_conflicts(&self, new_conflicts: Vec<SyncConflict>) {
+        let pending_count = {
+            let mut conflicts = self.conflicts.write().await;
+            for conflict in new_conflicts {
+                conflicts.retain(|existing| {
+                    existing.element_id != conflict.element_id || existing.status != ConflictStatus::Pending
+                });
+                conflicts.push(conflict);
+            }
+            conflicts.iter().filter(|conflict| conflict.status == ConflictStatus::Pending).count()
+        };
+        self.sync_state.write().await.pending_conflicts_count = pending_count;
     }
 
     /// Marcar cambios como sincronizados
     async fn mark_changes_as_synced(&self, changes: &[DataChange]) -> Result<()> {
-        let mut pending = self.pending_changes.write().await;
-        let mut synced = self.synced_changes.write().await;
-
-        for change in changes {
-            // Remover de pendientes
-            pending.retain(|c| c.id != change.id);
-            
-            // Agregar a sincronizados
-            synced.push(change.clone());
+        if let Some(journal) = self.journal.as_ref().filter(|_| !changes.is_empty()) {
+            journal.mark_synced(changes.iter().map(|change| change.id.clone()).collect()).await?;
         }
+        let pending_count = {
+            let mut pending = self.pending_changes.write().await;
+            let mut synced = self.synced_changes.write().await;
+
+            for change in changes {
+                // Remover de pendientes
+                pending.retain(|c| c.id != change.id);
+                
+                // Agregar a sincronizados
+                synced.push(change.clone());
+            }
+            pending.len()
+        };
 
+        let mut state = self.sync_state.write().await;
+        state.last_sync = Some(Utc::now());
+        state.pending_changes_count = pending_count;
         Ok(())
     }
 
@@ -500,7 +1491,7 @@ impl SmartSync {
                     if self.is_conflict(&remote_change, local_change).await {
                         let conflict = SyncConflict {
                             id: Uuid::new_v4().to_string(),
-                            element_id: remote_change.element_id.clone(),
+                            element_id: remote_change.element_id,
                             conflicting_changes: vec![remote_change.clone(), local_change.clone()],
                             timestamp: Utc::now(),
                             status: ConflictStatus::Pending,
@@ -606,6 +1597,10 @@ impl SmartSync {
         let now = Utc::now();
         let max_age_chrono = chrono::Duration::from_std(max_age)?;
 
+        if let Some(journal) = &self.journal {
+            journal.prune(now - max_age_chrono).await?;
+        }
+
         // Limpiar cambios sincronizados antiguos
         {
             let mut synced = self.synced_changes.write().await;
@@ -628,6 +1623,29 @@ impl SmartSync {
     }
 }
 
+/// Descarta los cambios remotos que volverían a crear o modificar una entrada
+/// eliminada aquí después de ese cambio. Los cambios posteriores a la
+/// eliminación se conservan: la entrada se editó en otro dispositivo y gana.
+pub fn discard_resurrections(remote_changes: Vec<DataChange>, tombstones: &[Tombstone]) -> Vec<DataChange> {
+    let deleted_at: HashMap<EntryId, DateTime<Utc>> = tombstones.iter()
+        .filter_map(|tombstone| {
+            DateTime::parse_from_rfc3339(&tombstone.deleted_at)
+                .ok()
+                .map(|deleted_at| (tombstone.entry_id, deleted_at.with_timezone(&Utc)))
+        })
+        .collect();
+    remote_changes.into_iter()
+        .filter(|change| {
+            let resurrects = change.change_type != ChangeType::Deleted
+                && deleted_at.get(&change.element_id).is_some_and(|deleted_at| change.timestamp <= *deleted_at);
+            if resurrects {
+                log::info!("Descartado cambio remoto de la entrada eliminada {}", change.element_id);
+            }
+            !resurrects
+        })
+        .collect()
+}
+
 /// Estadísticas de sincronización
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncStats {
@@ -642,7 +1660,7 @@ pub struct SyncStats {
     /// Duración de la última sincronización (ms)
     pub last_sync_duration: Option<u64>,
     /// Dispositivos sincronizados
-    pub devices_synced_with: Vec<String>,
+    pub devices_synced_with: Vec<DeviceId>,
 }
 
 impl Default for SyncStats {
@@ -661,6 +1679,7 @@ impl Default for SyncStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sync::device_info::{Incompatibility, MIN_PEER_APP_VERSION};
 
     #[tokio::test]
     async fn test_smart_sync_creation() {
@@ -675,9 +1694,9 @@ mod tests {
     #[tokio::test]
     async fn test_data_change_creation() {
         let change = DataChange::new(
-            "test-element".to_string(),
+            EntryId::new(),
             ChangeType::Created,
-            "test-device".to_string(),
+            DeviceId::new(),
             Some(b"test data".to_vec()),
             1,
             None,
@@ -686,6 +1705,12 @@ mod tests {
         assert!(change.is_valid());
         assert_eq!(change.change_type, ChangeType::Created);
         assert_eq!(change.data_size(), 9);
+        assert_eq!(change.category_id(), None);
+
+        let category_id = CategoryId::new();
+        let change = DataChange::for_category(category_id, ChangeType::Deleted, DeviceId::new(), None, 2, None);
+        assert_eq!(change.category_id(), Some(category_id));
+        assert_eq!(change.element_id.as_uuid(), category_id.as_uuid());
     }
 
     #[test]
@@ -702,9 +1727,9 @@ mod tests {
         let sync = SmartSync::new_default(sender);
         
         let change = DataChange::new(
-            "test-element".to_string(),
+            EntryId::new(),
             ChangeType::Created,
-            "test-device".to_string(),
+            DeviceId::new(),
             Some(b"test data".to_vec()),
             1,
             None,
@@ -714,4 +1739,732 @@ mod tests {
         
         assert_eq!(sync.get_pending_changes().await.len(), 1);
     }
+
+    /// Bóveda en memoria: cada entrada es su vector de versiones y su
+    /// contenido, o `None` si se eliminó
+    struct MemoryStore {
+        device_id: DeviceId,
+        entries: std::sync::Mutex<HashMap<EntryId, (VersionVector, Option<Vec<u8>>)>>,
+        peers: std::sync::Mutex<HashMap<DeviceId, VersionVector>>,
+        bootstraps: std::sync::Mutex<HashMap<DeviceId, SyncBootstrap>>,
+        history: std::sync::Mutex<Vec<SyncHistoryRecord>>,
+        /// Entradas que no se mandan a nadie y las que ya se retuvieron
+        excluded: std::sync::Mutex<HashSet<EntryId>>,
+        withheld: std::sync::Mutex<HashSet<EntryId>>,
+        /// Borrados que confirmó cada dispositivo
+        acknowledged: std::sync::Mutex<Vec<(DeviceId, EntryId)>>,
+        /// Marcas de eliminación; se purgan con la primera confirmación
+        tombstones: std::sync::Mutex<HashSet<EntryId>>,
+    }
+
+    impl MemoryStore {
+        fn new() -> Self {
+            Self {
+                device_id: DeviceId::new(),
+                entries: std::sync::Mutex::new(HashMap::new()),
+                peers: std::sync::Mutex::new(HashMap::new()),
+                bootstraps: std::sync::Mutex::new(HashMap::new()),
+                history: std::sync::Mutex::new(Vec::new()),
+                excluded: std::sync::Mutex::new(HashSet::new()),
+                withheld: std::sync::Mutex::new(HashSet::new()),
+                acknowledged: std::sync::Mutex::new(Vec::new()),
+                tombstones: std::sync::Mutex::new(HashSet::new()),
+            }
+        }
+
+        fn knowledge_now(&self) -> VersionVector {
+            let mut knowledge = VersionVector::new();
+            for (versions, _) in self.entries.lock().unwrap().values() {
+                knowledge.merge(versions);
+            }
+            knowledge
+        }
+
+        /// Crea o modifica una entrada y devuelve el cambio pendiente que lo
+        /// anuncia
+        fn write(&self, id: EntryId, data: &[u8]) -> DataChange {
+            let counter = self.knowledge_now().get(self.device_id) + 1;
+            let mut entries = self.entries.lock().unwrap();
+            let (versions, content) = entries.entry(id).or_default();
+            versions.observe(self.device_id, counter);
+            *content = Some(data.to_vec());
+            let mut change = DataChange::new(id, ChangeType::Modified, self.device_id, None, counter, None);
+            change.versions = versions.clone();
+            change
+        }
+
+        /// Borra una entrada y devuelve el cambio pendiente que lo anuncia
+        fn delete(&self, id: EntryId) -> DataChange {
+            let mut change = self.write(id, &[]);
+            self.entries.lock().unwrap().get_mut(&id).unwrap().1 = None;
+            self.tombstones.lock().unwrap().insert(id);
+            change.change_type = ChangeType::Deleted;
+            change
+        }
+
+        fn create(&self, data: &[u8]) -> DataChange {
+            self.write(EntryId::new(), data)
+        }
+
+        fn get(&self, id: EntryId) -> Option<Vec<u8>> {
+            self.entries.lock().unwrap().get(&id).and_then(|(_, data)| data.clone())
+        }
+    }
+
+    #[async_trait]
+    impl SyncStore for MemoryStore {
+        async fn local_device_id(&self) -> Result<DeviceId> {
+            Ok(self.device_id)
+        }
+
+        async fn sync_key(&self, _device_id: DeviceId) -> Result<[u8; 32]> {
+            Ok([7; 32])
+        }
+
+        async fn knowledge(&self) -> Result<VersionVector> {
+            Ok(self.knowledge_now())
+        }
+
+        async fn load_changes_since(&self, known: &VersionVector) -> Result<Vec<DataChange>> {
+            Ok(self.entries.lock().unwrap().iter()
+                .filter(|(_, (versions, _))| versions.exceeds(known))
+                .map(|(id, (versions, data))| {
+                    let change_type = if data.is_some() { ChangeType::Modified } else { ChangeType::Deleted };
+                    let mut change = DataChange::new(*id, change_type, self.device_id, data.clone(), 0, None);
+                    change.versions = versions.clone();
+                    change
+                })
+                .collect())
+        }
+
+        async fn apply_changes(&self, changes: Vec<DataChange>, strategy: &ConflictResolutionStrategy) -> Result<AppliedChanges> {
+            let mut entries = self.entries.lock().unwrap();
+            let mut outcome = AppliedChanges::default();
+            for change in changes {
+                if change.change_type == ChangeType::Excluded {
+                    outcome.applied += entries.remove(&change.element_id).is_some() as usize;
+                    continue;
+                }
+                let (versions, content) = entries.entry(change.element_id).or_default();
+                match change.versions.partial_cmp(versions) {
+                    Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal) => {}
+                    None if *strategy == ConflictResolutionStrategy::AskUser => {
+                        let mut local = DataChange::new(change.element_id, ChangeType::Modified, self.device_id, None, 0, None);
+                        local.versions = versions.clone();
+                        outcome.conflicts.push(SyncConflict::new(change, local));
+                    }
+                    _ => {
+                        versions.merge(&change.versions);
+                        *content = change.element_data;
+                        outcome.applied += 1;
+                    }
+                }
+            }
+            Ok(outcome)
+        }
+
+        async fn peer_knowledge(&self, device_id: DeviceId) -> Result<VersionVector> {
+            Ok(self.peers.lock().unwrap().get(&device_id).cloned().unwrap_or_default())
+        }
+
+        async fn save_peer_knowledge(&self, device_id: DeviceId, knowledge: VersionVector) -> Result<()> {
+            self.peers.lock().unwrap().entry(device_id).or_default().merge(&knowledge);
+            Ok(())
+        }
+
+        async fn bootstrap(&self, device_id: DeviceId) -> Result<Option<SyncBootstrap>> {
+            Ok(self.bootstraps.lock().unwrap().get(&device_id).cloned())
+        }
+
+        async fn save_bootstrap(&self, device_id: DeviceId, bootstrap: Option<SyncBootstrap>) -> Result<()> {
+            let mut bootstraps = self.bootstraps.lock().unwrap();
+            match bootstrap {
+                Some(bootstrap) => bootstraps.insert(device_id, bootstrap),
+                None => bootstraps.remove(&device_id),
+            };
+            Ok(())
+        }
+
+        async fn record_sync(&self, record: SyncHistoryRecord) -> Result<()> {
+            self.history.lock().unwrap().push(record);
+            Ok(())
+        }
+
+        async fn scope_changes(&self, _device_id: DeviceId, changes: Vec<DataChange>) -> Result<Vec<DataChange>> {
+            let excluded = self.excluded.lock().unwrap().clone();
+            let withheld = self.withheld.lock().unwrap().clone();
+            let mut scoped: Vec<DataChange> = changes.into_iter()
+                .filter(|change| !excluded.contains(&change.element_id))
+                .collect();
+            for id in excluded.difference(&withheld) {
+                scoped.push(DataChange::new(*id, ChangeType::Excluded, self.device_id, None, 0, None));
+            }
+            let returning: Vec<EntryId> = withheld.difference(&excluded)
+                .filter(|id| !scoped.iter().any(|change| change.element_id == **id))
+                .copied()
+                .collect();
+            let entries = self.entries.lock().unwrap();
+            for id in returning {
+                let (versions, data) = &entries[&id];
+                let mut change = DataChange::new(id, ChangeType::Modified, self.device_id, data.clone(), 0, None);
+                change.versions = versions.clone();
+                scoped.push(change);
+            }
+            Ok(scoped)
+        }
+
+        async fn record_delivery(&self, _device_id: DeviceId, changes: &[DataChange]) -> Result<()> {
+            let mut withheld = self.withheld.lock().unwrap();
+            for change in changes {
+                match change.change_type {
+                    ChangeType::Excluded => withheld.insert(change.element_id),
+                    _ => withheld.remove(&change.element_id),
+                };
+            }
+            Ok(())
+        }
+
+        async fn acknowledge_deletions(&self, device_id: DeviceId, entry_ids: &[EntryId]) -> Result<()> {
+            self.acknowledged.lock().unwrap().extend(entry_ids.iter().map(|entry_id| (device_id, *entry_id)));
+            self.tombstones.lock().unwrap().retain(|entry_id| !entry_ids.contains(entry_id));
+            Ok(())
+        }
+    }
+
+    /// Transporte que entrega el lote directamente al otro dispositivo
+    struct Loopback<'a> {
+        from: DeviceId,
+        peer: &'a SmartSync,
+        peer_store: &'a MemoryStore,
+    }
+
+    #[async_trait]
+    impl SyncTransport for Loopback<'_> {
+        async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+            self.peer.handle_sync_request(self.from, &payload, self.peer_store).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exchanges_changes_both_ways() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let (laptop, phone) = (SmartSync::new_default(sender.clone()), SmartSync::new_default(sender));
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let from_laptop = laptop_store.create(b"correo");
+        let from_phone = phone_store.create(b"banco");
+        laptop.add_change(from_laptop.clone()).await.unwrap();
+        phone.add_change(from_phone.clone()).await.unwrap();
+
+        let transport = Loopback { from: laptop_store.device_id, peer: &phone, peer_store: &phone_store };
+        let result = laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.elements_synced, 2);
+        assert!(result.data_size > 0);
+        assert_eq!(phone_store.get(from_laptop.element_id).as_deref(), Some(&b"correo"[..]));
+        assert_eq!(laptop_store.get(from_phone.element_id).as_deref(), Some(&b"banco"[..]));
+        assert!(laptop.get_pending_changes().await.is_empty());
+        assert!(phone.get_pending_changes().await.is_empty());
+        assert!(laptop.get_sync_state().await.syncing_devices.is_empty());
+
+        // Los dos lados anotan el intento en su historial
+        let sent = laptop_store.history.lock().unwrap()[0].clone();
+        let answered = phone_store.history.lock().unwrap()[0].clone();
+        assert_eq!((sent.direction, sent.device_id), (SyncDirection::Outgoing, phone_store.device_id));
+        assert_eq!((answered.direction, answered.device_id), (SyncDirection::Incoming, laptop_store.device_id));
+        assert_eq!((sent.items_sent, sent.items_received, sent.error), (1, 1, None));
+        assert_eq!((sent.bytes_sent, sent.bytes_received), (answered.bytes_received, answered.bytes_sent));
+
+        // Sin cambios nuevos no viaja ningún elemento
+        let result = laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(result.elements_synced, 0);
+
+        // Tras modificar una entrada solo viaja esa
+        laptop_store.write(from_phone.element_id, b"banco nuevo");
+        let result = laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(result.elements_synced, 1);
+        assert_eq!(phone_store.get(from_phone.element_id).as_deref(), Some(&b"banco nuevo"[..]));
+
+        // Un tercer dispositivo recibe del teléfono también lo que hizo el portátil
+        let (tablet, tablet_store) = (SmartSync::new_default(mpsc::channel(10).0), MemoryStore::new());
+        let transport = Loopback { from: tablet_store.device_id, peer: &phone, peer_store: &phone_store };
+        let result = tablet.sync_with_device(phone_store.device_id, &tablet_store, &transport).await.unwrap();
+        assert_eq!(result.elements_synced, 2);
+        assert_eq!(tablet_store.get(from_laptop.element_id).as_deref(), Some(&b"correo"[..]));
+        assert_eq!(tablet_store.knowledge_now(), laptop_store.knowledge_now());
+
+        // Un lote solo se abre con su clave y si viene de quien dice
+        let batch = SyncBatch {
+            source_device: phone_store.device_id,
+            knowledge: VersionVector::new(),
+            changes: vec![from_phone],
+            compression: Vec::new(),
+            snapshot: None,
+            app_version: None,
+            capabilities: None,
+            acknowledged_deletions: Vec::new(),
+        };
+        let standard = EncryptionLevel::Standard;
+        let sealed = batch.seal(&[7; 32], false, standard).unwrap();
+        assert_eq!(SyncBatch::open(&sealed, &[7; 32], phone_store.device_id, standard).unwrap().0.changes.len(), 1);
+        let compressed = batch.seal(&[7; 32], true, standard).unwrap();
+        assert_eq!(SyncBatch::open(&compressed, &[7; 32], phone_store.device_id, standard).unwrap().0.changes.len(), 1);
+        assert!(SyncBatch::open(&sealed, &[8; 32], phone_store.device_id, standard).is_err());
+        assert!(SyncBatch::open(&sealed, &[7; 32], laptop_store.device_id, standard).is_err());
+        assert!(SyncBatch::open(&sealed[..4], &[7; 32], phone_store.device_id, standard).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_applied_deletions_are_acknowledged() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let (laptop, phone) = (SmartSync::new_default(sender.clone()), SmartSync::new_default(sender));
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let kept = laptop_store.create(b"correo");
+        let removed = laptop_store.create(b"banco");
+        let transport = Loopback { from: laptop_store.device_id, peer: &phone, peer_store: &phone_store };
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert!(laptop_store.acknowledged.lock().unwrap().is_empty());
+
+        // El teléfono aplica el borrado y lo confirma en la respuesta
+        laptop.add_change(laptop_store.delete(removed.element_id)).await.unwrap();
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(phone_store.get(removed.element_id), None);
+        assert_eq!(phone_store.get(kept.element_id).as_deref(), Some(&b"correo"[..]));
+        assert_eq!(*laptop_store.acknowledged.lock().unwrap(), vec![(phone_store.device_id, removed.element_id)]);
+        assert!(laptop_store.tombstones.lock().unwrap().is_empty());
+        assert!(phone_store.acknowledged.lock().unwrap().is_empty());
+
+        // El portátil aplica lo que borró el teléfono y se lo confirma en la
+        // misma sincronización, aunque la empezó él
+        phone.add_change(phone_store.delete(kept.element_id)).await.unwrap();
+        let result = laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(result.elements_synced, 1);
+        assert_eq!(laptop_store.get(kept.element_id), None);
+        assert_eq!(*phone_store.acknowledged.lock().unwrap(), vec![(laptop_store.device_id, kept.element_id)]);
+        assert!(phone_store.tombstones.lock().unwrap().is_empty());
+        assert!(phone.get_pending_changes().await.is_empty());
+    }
+
+    /// Transporte que se corta después de `exchanges` intercambios
+    struct Interrupted<'a> {
+        inner: Loopback<'a>,
+        exchanges: std::sync::Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl SyncTransport for Interrupted<'_> {
+        async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+            {
+                let mut exchanges = self.exchanges.lock().unwrap();
+                if *exchanges == 0 {
+                    return Err(anyhow!("Conexión cortada"));
+                }
+                *exchanges -= 1;
+            }
+            self.inner.exchange(payload).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_device_copies_the_vault_in_pages() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let (phone, tablet) = (SmartSync::new_default(sender.clone()), SmartSync::new_default(sender));
+        phone.config.write().await.max_batch_size = 2;
+        let (phone_store, tablet_store) = (MemoryStore::new(), MemoryStore::new());
+        let entries: Vec<EntryId> = (0..5).map(|i| phone_store.create(format!("entrada {}", i).as_bytes()).element_id).collect();
+
+        // Se corta después de dos páginas: lo que llegó queda, y también hasta dónde
+        let interrupted = Interrupted {
+            inner: Loopback { from: tablet_store.device_id, peer: &phone, peer_store: &phone_store },
+            exchanges: std::sync::Mutex::new(2),
+        };
+        assert!(tablet.sync_with_device(phone_store.device_id, &tablet_store, &interrupted).await.is_err());
+        assert_eq!(tablet_store.entries.lock().unwrap().len(), 4);
+        let progress = tablet_store.bootstraps.lock().unwrap()[&phone_store.device_id].clone();
+        assert!(progress.position.is_some() && !progress.complete);
+        assert!(tablet_store.history.lock().unwrap()[0].error.is_some());
+
+        // Lo que cambia mientras tanto en una entrada ya copiada llega igual
+        let copied = *tablet_store.entries.lock().unwrap().keys().next().unwrap();
+        phone_store.write(copied, b"cambiada");
+
+        // Al volver sigue desde la tercera página y pasa a la incremental
+        let transport = Loopback { from: tablet_store.device_id, peer: &phone, peer_store: &phone_store };
+        let result = tablet.sync_with_device(phone_store.device_id, &tablet_store, &transport).await.unwrap();
+        assert_eq!(result.elements_synced, 2);
+        assert_eq!(tablet_store.get(copied).as_deref(), Some(&b"cambiada"[..]));
+        assert!(entries.iter().all(|id| tablet_store.get(*id).is_some()));
+        assert_eq!(tablet_store.knowledge_now(), phone_store.knowledge_now());
+        assert!(tablet_store.bootstraps.lock().unwrap().is_empty());
+
+        // Ya copiada, solo viaja lo nuevo
+        let new = phone_store.create(b"nueva");
+        let result = tablet.sync_with_device(phone_store.device_id, &tablet_store, &transport).await.unwrap();
+        assert_eq!(result.elements_synced, 1);
+        assert_eq!(tablet_store.get(new.element_id).as_deref(), Some(&b"nueva"[..]));
+        assert!(tablet_store.bootstraps.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_excluded_entries_leave_and_come_back() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let (laptop, phone) = (SmartSync::new_default(sender.clone()), SmartSync::new_default(sender));
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let work = laptop_store.create(b"trabajo");
+        let mail = laptop_store.create(b"correo");
+        let transport = Loopback { from: laptop_store.device_id, peer: &phone, peer_store: &phone_store };
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(phone_store.get(work.element_id).as_deref(), Some(&b"trabajo"[..]));
+
+        // Al excluirla el teléfono la quita y deja de recibir sus cambios
+        laptop_store.excluded.lock().unwrap().insert(work.element_id);
+        laptop_store.write(work.element_id, b"trabajo nuevo");
+        laptop_store.write(mail.element_id, b"correo nuevo");
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(phone_store.get(work.element_id), None);
+        assert_eq!(phone_store.get(mail.element_id).as_deref(), Some(&b"correo nuevo"[..]));
+        assert!(laptop_store.withheld.lock().unwrap().contains(&work.element_id));
+
+        // Tampoco le llega cuando es el teléfono el que pide
+        let transport_back = Loopback { from: phone_store.device_id, peer: &laptop, peer_store: &laptop_store };
+        let result = phone.sync_with_device(laptop_store.device_id, &phone_store, &transport_back).await.unwrap();
+        assert_eq!(result.elements_synced, 0);
+        assert_eq!(phone_store.get(work.element_id), None);
+
+        // Al volver a incluirla llega completa aunque no haya cambiado
+        laptop_store.excluded.lock().unwrap().clear();
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(phone_store.get(work.element_id).as_deref(), Some(&b"trabajo nuevo"[..]));
+        assert!(laptop_store.withheld.lock().unwrap().is_empty());
+    }
+
+    /// Buzón en memoria, con un lote por cada par de dispositivos
+    #[derive(Default)]
+    struct MemoryMailbox {
+        batches: std::sync::Mutex<HashMap<(DeviceId, DeviceId), Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl SyncMailbox for MemoryMailbox {
+        async fn put(&self, from: DeviceId, to: DeviceId, sealed: Vec<u8>) -> Result<()> {
+            self.batches.lock().unwrap().insert((from, to), sealed);
+            Ok(())
+        }
+
+        async fn take(&self, from: DeviceId, to: DeviceId) -> Result<Option<Vec<u8>>> {
+            Ok(self.batches.lock().unwrap().remove(&(from, to)))
+        }
+
+        async fn is_waiting(&self, from: DeviceId, to: DeviceId) -> Result<bool> {
+            Ok(self.batches.lock().unwrap().contains_key(&(from, to)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_syncs_through_the_mailbox() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let (laptop, phone) = (SmartSync::new_default(sender.clone()), SmartSync::new_default(sender));
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let (laptop_id, phone_id) = (laptop_store.device_id, phone_store.device_id);
+        let mailbox = MemoryMailbox::default();
+        let mail = laptop_store.create(b"correo");
+        let bank = phone_store.create(b"banco");
+
+        // El portátil deja su lote sin que el teléfono esté a la vista
+        let result = laptop.sync_through_mailbox(phone_id, &laptop_store, &mailbox).await.unwrap();
+        assert_eq!(result.elements_synced, 1);
+        assert!(mailbox.is_waiting(laptop_id, phone_id).await.unwrap());
+        let sealed = mailbox.batches.lock().unwrap()[&(laptop_id, phone_id)].clone();
+        assert!(serde_json::from_slice::<SyncBatch>(&sealed).is_err());
+
+        // El teléfono lo recoge y deja el suyo, que el portátil recoge después
+        let result = phone.sync_through_mailbox(laptop_id, &phone_store, &mailbox).await.unwrap();
+        assert_eq!(result.elements_synced, 2);
+        assert_eq!(phone_store.get(mail.element_id).as_deref(), Some(&b"correo"[..]));
+        assert!(!mailbox.is_waiting(laptop_id, phone_id).await.unwrap());
+        laptop.sync_through_mailbox(phone_id, &laptop_store, &mailbox).await.unwrap();
+        assert_eq!(laptop_store.get(bank.element_id).as_deref(), Some(&b"banco"[..]));
+        assert_eq!(laptop_store.knowledge_now(), phone_store.knowledge_now());
+        assert_eq!(laptop_store.history.lock().unwrap()[0].direction, SyncDirection::Cloud);
+
+        // El borrado que aplica el portátil vuelve confirmado en su próximo lote
+        phone_store.delete(bank.element_id);
+        phone.sync_through_mailbox(laptop_id, &phone_store, &mailbox).await.unwrap();
+        laptop.sync_through_mailbox(phone_id, &laptop_store, &mailbox).await.unwrap();
+        assert_eq!(laptop_store.get(bank.element_id), None);
+        phone.sync_through_mailbox(laptop_id, &phone_store, &mailbox).await.unwrap();
+        assert_eq!(*phone_store.acknowledged.lock().unwrap(), vec![(laptop_id, bank.element_id)]);
+
+        // Una exclusión solo cuenta como entregada cuando el teléfono recoge el
+        // lote; hasta entonces cada lote nuevo la vuelve a llevar
+        laptop_store.excluded.lock().unwrap().insert(mail.element_id);
+        laptop.sync_through_mailbox(phone_id, &laptop_store, &mailbox).await.unwrap();
+        laptop.sync_through_mailbox(phone_id, &laptop_store, &mailbox).await.unwrap();
+        assert!(laptop_store.withheld.lock().unwrap().is_empty());
+        phone.sync_through_mailbox(laptop_id, &phone_store, &mailbox).await.unwrap();
+        assert_eq!(phone_store.get(mail.element_id), None);
+        laptop.sync_through_mailbox(phone_id, &laptop_store, &mailbox).await.unwrap();
+        assert!(laptop_store.withheld.lock().unwrap().contains(&mail.element_id));
+
+        // Un lote que no se puede abrir hace fallar la sincronización
+        mailbox.put(phone_id, laptop_id, b"basura".to_vec()).await.unwrap();
+        assert!(laptop.sync_through_mailbox(phone_id, &laptop_store, &mailbox).await.is_err());
+        assert!(laptop_store.history.lock().unwrap().last().unwrap().error.is_some());
+    }
+
+    /// Texto sin encriptar de un lote sellado
+    fn batch_payload(sealed: &[u8]) -> Vec<u8> {
+        let (nonce, ciphertext) = sealed.split_at(BATCH_NONCE_LEN);
+        decrypt_data(ciphertext, &[7; 32], nonce).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compression_is_negotiated() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let phone = SmartSync::new_default(sender);
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        phone_store.create(&[b'x'; 4096]);
+
+        // Una versión anterior no manda `compression`: la respuesta va en JSON
+        let old_request = serde_json::json!({
+            "sourceDevice": laptop_store.device_id,
+            "knowledge": {},
+            "changes": [],
+        });
+        let (ciphertext, nonce) = encrypt_data(&serde_json::to_vec(&old_request).unwrap(), &[7; 32]).unwrap();
+        let reply = phone.handle_sync_request(laptop_store.device_id, &[nonce, ciphertext].concat(), &phone_store).await.unwrap();
+        assert!(batch_payload(&reply).starts_with(b"{"));
+
+        // Si el lote dice que acepta zstd, la respuesta va comprimida
+        let batch = SyncBatch {
+            source_device: laptop_store.device_id,
+            knowledge: VersionVector::new(),
+            changes: Vec::new(),
+            compression: vec![BATCH_COMPRESSION.to_string()],
+            snapshot: None,
+            app_version: None,
+            capabilities: None,
+            acknowledged_deletions: Vec::new(),
+        };
+        let request = batch.seal(&[7; 32], false, EncryptionLevel::Standard).unwrap();
+        let reply = phone.handle_sync_request(laptop_store.device_id, &request, &phone_store).await.unwrap();
+        assert!(batch_payload(&reply).starts_with(&ZSTD_MAGIC));
+        let (opened, _) = SyncBatch::open(&reply, &[7; 32], phone_store.device_id, EncryptionLevel::Standard).unwrap();
+        assert_eq!(opened.changes.len(), 1);
+        assert!(opened.accepts_compression());
+
+        // Con la compresión desactivada aquí no se comprime ni se ofrece
+        phone.config.write().await.enable_compression = false;
+        let reply = phone.handle_sync_request(laptop_store.device_id, &request, &phone_store).await.unwrap();
+        assert!(batch_payload(&reply).starts_with(b"{"));
+        assert!(!SyncBatch::open(&reply, &[7; 32], phone_store.device_id, EncryptionLevel::Standard).unwrap().0.accepts_compression());
+    }
+
+    #[tokio::test]
+    async fn test_encryption_level_is_enforced() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let phone = SmartSync::new_default(sender);
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let batch = SyncBatch {
+            source_device: laptop_store.device_id,
+            knowledge: VersionVector::new(),
+            changes: Vec::new(),
+            compression: Vec::new(),
+            snapshot: None,
+            app_version: None,
+            capabilities: None,
+            acknowledged_deletions: Vec::new(),
+        };
+
+        // El nivel militar abre con la misma clave y no se confunde con el estándar
+        let military = batch.seal(&[7; 32], true, EncryptionLevel::Military).unwrap();
+        assert!(military.starts_with(MILITARY_HEADER));
+        let (_, level) = SyncBatch::open(&military, &[7; 32], laptop_store.device_id, EncryptionLevel::Military).unwrap();
+        assert_eq!(level, EncryptionLevel::Military);
+        assert!(SyncBatch::open(&military, &[8; 32], laptop_store.device_id, EncryptionLevel::Standard).is_err());
+
+        // Se responde con el nivel más alto de los dos
+        let reply = phone.handle_sync_request(laptop_store.device_id, &military, &phone_store).await.unwrap();
+        assert!(reply.starts_with(MILITARY_HEADER));
+
+        // Con el nivel militar configurado se rechaza el estándar y el texto plano
+        phone.set_encryption_level(EncryptionLevel::Military).await;
+        let standard = batch.seal(&[7; 32], false, EncryptionLevel::Standard).unwrap();
+        assert!(phone.handle_sync_request(laptop_store.device_id, &standard, &phone_store).await.is_err());
+        let plaintext = serde_json::to_vec(&batch).unwrap();
+        let error = SyncBatch::open(&plaintext, &[7; 32], laptop_store.device_id, EncryptionLevel::Standard).unwrap_err();
+        assert!(error.to_string().contains("sin encriptar"));
+    }
+
+    #[tokio::test]
+    async fn test_incompatible_versions_are_refused() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let phone = SmartSync::new_default(sender);
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let from_laptop = laptop_store.create(b"correo");
+        let changes = laptop_store.load_changes_since(&VersionVector::new()).await.unwrap();
+        let batch = |app_version: &str, min_app_version: &str| SyncBatch {
+            source_device: laptop_store.device_id,
+            knowledge: laptop_store.knowledge_now(),
+            changes: changes.clone(),
+            compression: Vec::new(),
+            snapshot: None,
+            app_version: Some(app_version.to_string()),
+            capabilities: Some(DeviceCapabilities {
+                min_app_version: min_app_version.to_string(),
+                ..DeviceCapabilities::default()
+            }),
+            acknowledged_deletions: Vec::new(),
+        };
+
+        // El otro pide una versión más nueva que esta: no se aplica nada
+        let request = batch("99.0.0", "99.0.0").seal(&[7; 32], false, EncryptionLevel::Standard).unwrap();
+        let error = phone.handle_sync_request(laptop_store.device_id, &request, &phone_store).await.unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(Incompatibility::LocalTooOld { .. })));
+        assert_eq!(phone_store.get(from_laptop.element_id), None);
+        let (version, _) = phone.peer_capabilities(laptop_store.device_id).await.unwrap();
+        assert_eq!(version, "99.0.0");
+
+        // Tampoco si el otro es anterior a la mínima o su versión no es semver
+        let request = batch("0.0.9", MIN_PEER_APP_VERSION).seal(&[7; 32], false, EncryptionLevel::Standard).unwrap();
+        let error = phone.handle_sync_request(laptop_store.device_id, &request, &phone_store).await.unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(Incompatibility::PeerTooOld { .. })));
+        let request = batch("nueva", MIN_PEER_APP_VERSION).seal(&[7; 32], false, EncryptionLevel::Standard).unwrap();
+        assert!(phone.handle_sync_request(laptop_store.device_id, &request, &phone_store).await.is_err());
+        assert_eq!(phone_store.get(from_laptop.element_id), None);
+
+        let request = batch("0.10.0", MIN_PEER_APP_VERSION).seal(&[7; 32], false, EncryptionLevel::Standard).unwrap();
+        let reply = phone.handle_sync_request(laptop_store.device_id, &request, &phone_store).await.unwrap();
+        assert_eq!(phone_store.get(from_laptop.element_id).as_deref(), Some(&b"correo"[..]));
+        // La respuesta dice la versión y las capacidades de este dispositivo
+        let (opened, _) = SyncBatch::open(&reply, &[7; 32], phone_store.device_id, EncryptionLevel::Standard).unwrap();
+        assert_eq!(opened.app_version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(opened.capabilities, Some(DeviceCapabilities::default()));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_edits_wait_for_the_user() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let (laptop, phone) = (SmartSync::new_default(sender.clone()), SmartSync::new_default(sender));
+        for sync in [&laptop, &phone] {
+            sync.set_conflict_resolution_strategy(ConflictResolutionStrategy::AskUser).await;
+        }
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let shared = laptop_store.create(b"correo");
+        let transport = Loopback { from: laptop_store.device_id, peer: &phone, peer_store: &phone_store };
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+
+        // Los dos editan la misma entrada sin haber visto la edición del otro
+        laptop_store.write(shared.element_id, "correo del portátil".as_bytes());
+        phone_store.write(shared.element_id, "correo del teléfono".as_bytes());
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(phone_store.get(shared.element_id).as_deref(), Some("correo del teléfono".as_bytes()));
+        let conflicts = phone.get_conflicts().await;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].element_id, shared.element_id);
+        assert_eq!(conflicts[0].remote_change().and_then(|change| change.element_data.as_deref()), Some("correo del portátil".as_bytes()));
+
+        // Si vuelve a llegar, reemplaza al pendiente en vez de duplicarlo
+        laptop.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        assert_eq!(phone.get_sync_state().await.pending_conflicts_count, 1);
+        assert_eq!(laptop.get_conflicts().await.len(), 1);
+        phone.resolve_conflict(&phone.get_conflicts().await[0].id, ConflictResolution::UseLocal).await.unwrap();
+        assert_eq!(phone.get_sync_state().await.pending_conflicts_count, 0);
+    }
+
+    /// Diario en memoria que guarda las filas como lo haría la base
+    #[derive(Default)]
+    struct MemoryJournal {
+        rows: std::sync::Mutex<Vec<(SyncChangeRow, bool)>>,
+    }
+
+    #[async_trait]
+    impl ChangeJournal for MemoryJournal {
+        async fn record(&self, change: &DataChange) -> Result<()> {
+            self.rows.lock().unwrap().push((SyncChangeRow::from(change), false));
+            Ok(())
+        }
+
+        async fn mark_synced(&self, change_ids: Vec<String>) -> Result<()> {
+            for (row, synced) in self.rows.lock().unwrap().iter_mut() {
+                *synced |= change_ids.contains(&row.id);
+            }
+            Ok(())
+        }
+
+        async fn load_pending(&self) -> Result<Vec<DataChange>> {
+            self.rows.lock().unwrap().iter()
+                .filter(|(_, synced)| !synced)
+                .map(|(row, _)| DataChange::try_from(row.clone()))
+                .collect()
+        }
+
+        async fn prune(&self, _cutoff: DateTime<Utc>) -> Result<usize> {
+            let mut rows = self.rows.lock().unwrap();
+            let before = rows.len();
+            rows.retain(|(_, synced)| !synced);
+            Ok(before - rows.len())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_journal_survives_restart() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let journal = Arc::new(MemoryJournal::default());
+        let (laptop_store, phone_store) = (MemoryStore::new(), MemoryStore::new());
+        let laptop = SmartSync::new_default(sender.clone()).with_journal(journal.clone());
+        let mut change = laptop_store.create(b"correo");
+        change.add_metadata("origen".to_string(), "prueba".to_string());
+        laptop.add_change(change.clone()).await.unwrap();
+
+        // Tras reiniciar el cambio sigue pendiente, igual que antes
+        let restarted = SmartSync::new_default(sender.clone()).with_journal(journal.clone());
+        assert_eq!(restarted.load_journal().await.unwrap(), 1);
+        assert_eq!(restarted.load_journal().await.unwrap(), 0);
+        let pending = restarted.get_pending_changes().await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, change.id);
+        assert_eq!(pending[0].change_type, ChangeType::Created);
+        assert_eq!(pending[0].timestamp, change.timestamp);
+        assert_eq!(pending[0].get_metadata("origen").map(String::as_str), Some("prueba"));
+        assert_eq!(restarted.get_sync_state().await.pending_changes_count, 1);
+
+        // Una vez sincronizado ya no se vuelve a cargar
+        let phone = SmartSync::new_default(sender.clone());
+        let transport = Loopback { from: laptop_store.device_id, peer: &phone, peer_store: &phone_store };
+        restarted.sync_with_device(phone_store.device_id, &laptop_store, &transport).await.unwrap();
+        let restarted = SmartSync::new_default(sender).with_journal(journal.clone());
+        assert_eq!(restarted.load_journal().await.unwrap(), 0);
+        restarted.cleanup_old_changes(Duration::from_secs(60)).await.unwrap();
+        assert!(journal.rows.lock().unwrap().is_empty());
+        assert!("renombrado".parse::<ChangeType>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tombstones() {
+        let (sender, _receiver) = mpsc::channel(10);
+        let sync = SmartSync::new_default(sender);
+        let deleted = EntryId::new();
+        let tombstones = vec![Tombstone {
+            entry_id: deleted,
+            deleted_at: "2024-02-01T00:00:00Z".to_string(),
+        }];
+        let this_device = DeviceId::new();
+
+        assert_eq!(sync.queue_tombstones(&tombstones, this_device).await.unwrap(), 1);
+        assert_eq!(sync.queue_tombstones(&tombstones, this_device).await.unwrap(), 0);
+        assert_eq!(sync.get_pending_changes().await[0].change_type, ChangeType::Deleted);
+
+        let change_at = |change_type: ChangeType, timestamp: &str| {
+            let mut change = DataChange::new(deleted, change_type, DeviceId::new(), None, 1, None);
+            change.timestamp = DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc);
+            change
+        };
+        let kept = discard_resurrections(vec![
+            change_at(ChangeType::Modified, "2024-01-15T00:00:00Z"),
+            change_at(ChangeType::Modified, "2024-03-01T00:00:00Z"),
+            change_at(ChangeType::Deleted, "2024-01-20T00:00:00Z"),
+        ], &tombstones);
+        assert_eq!(kept.len(), 2);
+        assert!(kept.iter().all(|change| change.timestamp.to_rfc3339() != "2024-01-15T00:00:00+00:00"));
+    }
 }
@@ -6,22 +6,59 @@
 //! - Sincronización inteligente
 //! - Gestión de eventos y estado
 
+use crate::models::{DeviceId, PasswordEntry};
+use crate::sync::device_info::{socket_address, DeviceNameComparator};
+use crate::sync::discovery::{local_ip, DiscoveryConfig};
+use crate::sync::entry_transfer::{decode_offer, encode_offer, EntryInbox, IncomingEntry, ENTRY_RECEIVED};
+use crate::sync::identity::{verify_fingerprint, DeviceIdentity, PublicIdentity};
+use crate::sync::network_policy::{check_current_network, is_restricted};
+use crate::sync::noise::{NoisePeer, NoiseResponder};
+use crate::sync::pairing::{PairedKey, PairingInvite, PairingMessage, PairingSession, PairingStatus};
+use crate::sync::p2p_connection::{P2PConfig, P2PConnectionStats, ReconnectingConnection, Redial, TurnServer};
+use crate::sync::signaling::{probe, LanSignaling, OfferHandler, SignalingChannel, SignalingServer, Trickle};
+use crate::sync::smart_sync::{
+    ChangeJournal, ConflictResolution, ConflictResolutionStrategy, ConflictStatus, DataChange,
+    SyncConflict, SyncMailbox, SyncResponder, SyncStore, SyncTransport,
+};
+use crate::sync::tls_transport::{TlsConnection, TlsHandler, TlsServer, TLS_PORT_PROPERTY};
 use crate::sync::{
-    DeviceDiscovery, DeviceInfo, SyncEvent, SyncEventHandler, SyncStatus, SyncConfig,
-    SyncMethod, SyncStats, SyncResult, DefaultSyncEventHandler,
+    DeviceDiscovery, DeviceInfo, DeviceProfile, SyncEvent, SyncEventHandler, SyncStatus, SyncConfig,
+    SyncMethod, SyncStats, SyncResult, DefaultSyncEventHandler, P2PConnection, SmartSync,
+    RelaySignaling, RetryPolicy, SyncRetry,
 };
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
+use futures::future::BoxFuture;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
     sync::Arc,
     time::Duration,
 };
 use tokio::{
-    sync::{mpsc, RwLock, Mutex},
+    sync::{mpsc, Notify, RwLock, Mutex},
     time::{interval, timeout},
 };
 use serde::{Deserialize, Serialize};
+use x25519_dalek::StaticSecret;
+
+/// Cada cuánto se miran las ofertas que esperan en el relay
+const RELAY_SIGNALING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Cada cuánto se pregunta a los dispositivos conectados si siguen ahí
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Lo que se espera la respuesta a un heartbeat
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Heartbeats seguidos sin respuesta tras los que un dispositivo se da por
+/// desconectado
+const MAX_MISSED_HEARTBEATS: u32 = 3;
+
+/// Heartbeat que va por la misma conexión que los lotes, y su respuesta. Un
+/// lote sellado nunca es tan corto, así que no se confunden.
+const HEARTBEAT_PING: &[u8] = b"ping";
+const HEARTBEAT_PONG: &[u8] = b"pong";
 
 /// Gestor principal de sincronización
 pub struct SyncManager {
@@ -31,22 +68,82 @@ pub struct SyncManager {
     config: Arc<RwLock<SyncConfig>>,
     /// Sistema de descubrimiento
     discovery: Arc<Mutex<Option<DeviceDiscovery>>>,
+    /// Dispositivos agregados a mano con
+    /// [`SyncManager::add_device_manually`], en las redes que bloquean mDNS
+    manual_devices: Arc<RwLock<HashMap<DeviceId, DeviceInfo>>>,
     /// Dispositivos conectados
-    connected_devices: Arc<RwLock<HashMap<String, DeviceInfo>>>,
+    connected_devices: Arc<RwLock<HashMap<DeviceId, DeviceInfo>>>,
+    /// Dispositivos de confianza, los únicos con los que se sincroniza, con la
+    /// identidad fijada al emparejarlos. Los comandos los cargan de la base
+    /// con [`SyncManager::set_trusted_devices`].
+    trusted_devices: Arc<RwLock<HashMap<DeviceId, Option<PublicIdentity>>>>,
+    /// Identidad de este dispositivo; los comandos la cargan de la base al
+    /// desbloquear con [`SyncManager::set_identity`]
+    identity: Arc<RwLock<Option<DeviceIdentity>>>,
+    /// Nombre y tipo que el usuario eligió para este dispositivo; los
+    /// comandos los cargan con [`SyncManager::set_device_profile`]
+    device_profile: Arc<RwLock<DeviceProfile>>,
     /// Estadísticas de sincronización
     stats: Arc<RwLock<SyncStats>>,
+    /// Cambios pendientes y el intercambio de lotes con otros dispositivos
+    smart_sync: Arc<SmartSync>,
+    /// Bóveda de la que salen y a la que llegan los cambios. Sin ella no se
+    /// puede sincronizar.
+    store: Option<Arc<dyn SyncStore>>,
+    /// Conexión abierta con cada dispositivo
+    transports: Arc<RwLock<HashMap<DeviceId, Arc<dyn SyncTransport>>>>,
+    /// Buzón en la nube por el que se sincroniza con los que no están a la
+    /// vista; los comandos lo arman con [`SyncManager::set_mailbox`]
+    mailbox: Arc<RwLock<Option<Arc<dyn SyncMailbox>>>>,
+    /// Relay por el que se mandan las ofertas WebRTC a los que no se ven en la
+    /// red local; los comandos lo arman con [`SyncManager::set_relay_signaling`]
+    relay_signaling: Arc<RwLock<Option<Arc<RelaySignaling>>>>,
+    /// Servidor TURN para las redes que no dejan conectar directo; los
+    /// comandos lo arman con [`SyncManager::set_turn_server`]
+    turn_server: Arc<RwLock<Option<TurnServer>>>,
+    /// Puerto en el que llegan las ofertas de la red local, anunciado por mDNS
+    signaling_server: Arc<Mutex<Option<SignalingServer>>>,
+    /// Puerto de las conexiones TLS directas de la red local, anunciado por
+    /// mDNS
+    tls_server: Arc<Mutex<Option<TlsServer>>>,
+    /// Reintentos de las sincronizaciones que fallaron, por dispositivo
+    retries: Arc<RwLock<HashMap<DeviceId, SyncRetry>>>,
+    /// Tarea que espera el siguiente reintento de cada dispositivo
+    retry_tasks: Arc<Mutex<HashMap<DeviceId, tokio::task::JoinHandle<()>>>>,
+    /// Cuándo y cuántas veces se reintenta
+    retry_policy: RetryPolicy,
+    /// Se toma mientras se sincroniza con todos, para que la sincronización
+    /// automática no se cruce con una manual
+    sync_round: Arc<Mutex<()>>,
+    /// Avisa a la sincronización automática de que hubo una manual
+    manual_sync: Arc<Notify>,
     /// Canal para eventos de sincronización
     event_sender: mpsc::Sender<SyncEvent>,
-    /// Receptor de eventos
-    event_receiver: Option<mpsc::Receiver<SyncEvent>>,
+    /// Receptor de eventos. La tarea principal lo toma mientras corre y al
+    /// abortarla vuelve a quedar libre, así el gestor se puede reiniciar.
+    event_receiver: Arc<Mutex<mpsc::Receiver<SyncEvent>>>,
     /// Manejador de eventos
     event_handler: Arc<dyn SyncEventHandler + Send + Sync>,
     /// Estado del gestor
     is_running: Arc<RwLock<bool>>,
+    /// Evita que dos llamadas a `start`/`stop` se crucen
+    lifecycle: Mutex<()>,
     /// Tarea principal del gestor
-    manager_task: Option<tokio::task::JoinHandle<()>>,
+    manager_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
     /// Tarea de limpieza
-    cleanup_task: Option<tokio::task::JoinHandle<()>>,
+    cleanup_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Tarea que sincroniza cada `sync_interval` minutos
+    auto_sync_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Tarea que responde las ofertas que esperan en el relay
+    relay_signaling_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Tarea que manda los heartbeats a los dispositivos conectados
+    heartbeat_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Emparejamientos en curso, uno por dispositivo
+    pairings: Mutex<HashMap<DeviceId, PairingSession>>,
+    /// Entradas sueltas que mandaron otros dispositivos y esperan al usuario
+    entry_inbox: Arc<Mutex<EntryInbox>>,
+    /// Código QR mostrado y todavía sin escanear; sirve una sola vez
+    qr_invite: Mutex<Option<PairingSession>>,
 }
 
 impl SyncManager {
@@ -58,14 +155,38 @@ impl SyncManager {
             status: Arc::new(RwLock::new(SyncStatus::default())),
             config: Arc::new(RwLock::new(config)),
             discovery: Arc::new(Mutex::new(None)),
+            manual_devices: Arc::new(RwLock::new(HashMap::new())),
             connected_devices: Arc::new(RwLock::new(HashMap::new())),
+            trusted_devices: Arc::new(RwLock::new(HashMap::new())),
+            identity: Arc::new(RwLock::new(None)),
+            device_profile: Arc::new(RwLock::new(DeviceProfile::default())),
             stats: Arc::new(RwLock::new(SyncStats::default())),
+            smart_sync: Arc::new(SmartSync::new_default(event_sender.clone())),
+            store: None,
+            transports: Arc::new(RwLock::new(HashMap::new())),
+            mailbox: Arc::new(RwLock::new(None)),
+            relay_signaling: Arc::new(RwLock::new(None)),
+            turn_server: Arc::new(RwLock::new(None)),
+            signaling_server: Arc::new(Mutex::new(None)),
+            tls_server: Arc::new(Mutex::new(None)),
+            retries: Arc::new(RwLock::new(HashMap::new())),
+            retry_tasks: Arc::new(Mutex::new(HashMap::new())),
+            retry_policy: RetryPolicy::default(),
+            sync_round: Arc::new(Mutex::new(())),
+            manual_sync: Arc::new(Notify::new()),
             event_sender,
-            event_receiver: Some(event_receiver),
+            event_receiver: Arc::new(Mutex::new(event_receiver)),
             event_handler: Arc::new(DefaultSyncEventHandler),
             is_running: Arc::new(RwLock::new(false)),
-            manager_task: None,
-            cleanup_task: None,
+            lifecycle: Mutex::new(()),
+            manager_task: Mutex::new(None),
+            cleanup_task: Mutex::new(None),
+            auto_sync_task: Mutex::new(None),
+            relay_signaling_task: Mutex::new(None),
+            heartbeat_task: Mutex::new(None),
+            pairings: Mutex::new(HashMap::new()),
+            entry_inbox: Arc::new(Mutex::new(EntryInbox::default())),
+            qr_invite: Mutex::new(None),
         }
     }
 
@@ -74,8 +195,57 @@ impl SyncManager {
         Self::new(SyncConfig::default())
     }
 
+    /// Sincroniza los cambios de `store`
+    pub fn with_store(mut self, store: Arc<dyn SyncStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Guarda los cambios pendientes en `journal`, de donde se cargan al
+    /// iniciar
+    pub fn with_journal(mut self, journal: Arc<dyn ChangeJournal>) -> Self {
+        self.smart_sync = Arc::new(SmartSync::new_default(self.event_sender.clone()).with_journal(journal));
+        self
+    }
+
+    /// Reintenta las sincronizaciones fallidas según `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Lo que necesitan las sincronizaciones, también las de la tarea
+    /// automática
+    fn sync_context(&self) -> SyncContext {
+        SyncContext {
+            config: self.config.clone(),
+            status: self.status.clone(),
+            connected_devices: self.connected_devices.clone(),
+            trusted_devices: self.trusted_devices.clone(),
+            identity: self.identity.clone(),
+            device_profile: self.device_profile.clone(),
+            discovery: self.discovery.clone(),
+            manual_devices: self.manual_devices.clone(),
+            tls_server: self.tls_server.clone(),
+            transports: self.transports.clone(),
+            mailbox: self.mailbox.clone(),
+            relay_signaling: self.relay_signaling.clone(),
+            turn_server: self.turn_server.clone(),
+            smart_sync: self.smart_sync.clone(),
+            store: self.store.clone(),
+            event_sender: self.event_sender.clone(),
+            retries: self.retries.clone(),
+            retry_tasks: self.retry_tasks.clone(),
+            retry_policy: self.retry_policy,
+            sync_round: self.sync_round.clone(),
+            manual_sync: self.manual_sync.clone(),
+            entry_inbox: self.entry_inbox.clone(),
+        }
+    }
+
     /// Iniciar el sistema de sincronización
-    pub async fn start(&mut self) -> Result<()> {
+    pub async fn start(&self) -> Result<()> {
+        let _lifecycle = self.lifecycle.lock().await;
         if *self.is_running.read().await {
             return Ok(());
         }
@@ -90,6 +260,15 @@ impl SyncManager {
             }
         }
 
+        // Recibir las ofertas de la red local en un puerto que anuncia el
+        // descubrimiento
+        let handler: Arc<dyn OfferHandler> = Arc::new(self.sync_context());
+        *self.signaling_server.lock().await = Some(SignalingServer::start(handler).await?);
+        // Y las conexiones TLS directas, para no pasar por WebRTC cuando los
+        // dos están en la misma red
+        let handler: Arc<dyn TlsHandler> = Arc::new(self.sync_context());
+        *self.tls_server.lock().await = Some(TlsServer::start(handler).await?);
+
         // Inicializar sistema de descubrimiento
         {
             let should_init = {
@@ -97,14 +276,21 @@ impl SyncManager {
                 config.auto_discovery
             };
             
-            if should_init {
+            if should_init && self.sync_context().check_network().await.is_ok() {
                 self.init_discovery().await?;
             }
         }
 
+        // Recuperar los cambios que quedaron sin sincronizar
+        self.smart_sync.load_journal().await?;
+        self.apply_smart_sync_config().await;
+
         // Iniciar tareas principales
         self.start_manager_task().await?;
         self.start_cleanup_task().await?;
+        self.restart_auto_sync_task().await;
+        self.start_relay_signaling_task().await;
+        self.start_heartbeat_task().await;
 
         // Marcar como ejecutándose
         *self.is_running.write().await = true;
@@ -120,7 +306,8 @@ impl SyncManager {
     }
 
     /// Detener el sistema de sincronización
-    pub async fn stop(&mut self) -> Result<()> {
+    pub async fn stop(&self) -> Result<()> {
+        let _lifecycle = self.lifecycle.lock().await;
         if !*self.is_running.read().await {
             return Ok(());
         }
@@ -128,17 +315,35 @@ impl SyncManager {
         log::info!("Deteniendo sistema de sincronización...");
 
         // Detener tareas
-        if let Some(task) = self.manager_task.take() {
+        if let Some(task) = self.manager_task.lock().await.take() {
+            task.abort();
+        }
+        if let Some(task) = self.cleanup_task.lock().await.take() {
+            task.abort();
+        }
+        if let Some(task) = self.auto_sync_task.lock().await.take() {
+            task.abort();
+        }
+        if let Some(task) = self.relay_signaling_task.lock().await.take() {
+            task.abort();
+        }
+        if let Some(task) = self.heartbeat_task.lock().await.take() {
             task.abort();
         }
-        if let Some(task) = self.cleanup_task.take() {
+        for (_, task) in self.retry_tasks.lock().await.drain() {
             task.abort();
         }
+        self.retries.write().await.clear();
+        self.pairings.lock().await.clear();
+        *self.qr_invite.lock().await = None;
+        self.entry_inbox.lock().await.clear();
 
         // Detener descubrimiento
         if let Some(mut discovery) = self.discovery.lock().await.take() {
             discovery.stop().await?;
         }
+        *self.signaling_server.lock().await = None;
+        *self.tls_server.lock().await = None;
 
         // Marcar como detenido
         *self.is_running.write().await = false;
@@ -154,34 +359,34 @@ impl SyncManager {
     }
 
     /// Inicializar el sistema de descubrimiento
-    async fn init_discovery(&mut self) -> Result<()> {
-        log::info!("Inicializando sistema de descubrimiento...");
-
-        let config = self.config.read().await;
-        let discovery_config = crate::sync::discovery::DiscoveryConfig::default();
-        
-        let mut discovery = DeviceDiscovery::new(discovery_config, self.event_sender.clone());
-        discovery.start().await?;
-
-        // No necesitamos establecer manejador de eventos personalizado
-        // discovery.set_event_handler(Box::new(DiscoveryEventHandler { event_sender }));
-
-        *self.discovery.lock().await = Some(discovery);
+    async fn init_discovery(&self) -> Result<()> {
+        let port = signaling_port(&self.signaling_server).await;
+        let tls_port = tls_port(&self.tls_server).await;
+        let profile = self.device_profile.read().await.clone();
+        launch_discovery(&self.discovery, self.event_sender.clone(), local_device(&self.store).await, &profile, port, tls_port).await
+    }
 
-        log::info!("Sistema de descubrimiento inicializado correctamente");
-        Ok(())
+    /// Iniciar el descubrimiento de dispositivos si todavía no está activo.
+    /// Falla si la red actual no permite sincronizar.
+    pub async fn start_discovery(&self) -> Result<()> {
+        if self.discovery.lock().await.is_some() {
+            return Ok(());
+        }
+        self.sync_context().check_network().await?;
+        self.init_discovery().await
     }
 
     /// Iniciar la tarea principal del gestor
-    async fn start_manager_task(&mut self) -> Result<()> {
-        let event_receiver = self.event_receiver.take().unwrap();
+    async fn start_manager_task(&self) -> Result<()> {
+        let event_receiver = self.event_receiver.clone();
         let event_handler = self.event_handler.clone();
         let connected_devices = self.connected_devices.clone();
         let stats = self.stats.clone();
         let status = self.status.clone();
+        let retries = self.retries.clone();
 
         let task = tokio::spawn(async move {
-            let mut receiver = event_receiver;
+            let mut receiver = event_receiver.lock().await;
             
             while let Some(event) = receiver.recv().await {
                 // Manejar evento
@@ -192,28 +397,54 @@ impl SyncManager {
                     event,
                     &connected_devices,
                     &stats,
-                    &status
+                    &status,
+                    &retries,
                 ).await {
                     log::error!("Error al procesar evento localmente: {}", e);
                 }
             }
         });
 
-        self.manager_task = Some(task);
+        *self.manager_task.lock().await = Some(task);
         Ok(())
     }
 
     /// Iniciar la tarea de limpieza
-    async fn start_cleanup_task(&mut self) -> Result<()> {
-        let _config = self.config.clone();
+    async fn start_cleanup_task(&self) -> Result<()> {
+        let context = self.sync_context();
         let discovery = self.discovery.clone();
         let connected_devices = self.connected_devices.clone();
+        let signaling_server = self.signaling_server.clone();
+        let tls_server = self.tls_server.clone();
 
         let task = tokio::spawn(async move {
             let mut interval = interval(Duration::from_secs(60)); // Cada minuto
             
             loop {
                 interval.tick().await;
+
+                // Seguir la red: el descubrimiento se detiene al pasar a una
+                // red no permitida y vuelve al regresar a una permitida
+                if is_restricted(&*context.config.read().await) {
+                    let allowed = context.check_network().await.is_ok();
+                    let auto_discovery = context.config.read().await.auto_discovery;
+                    let running = discovery.lock().await.is_some();
+                    if !allowed && running {
+                        if let Some(mut stopped) = discovery.lock().await.take() {
+                            if let Err(e) = stopped.stop().await {
+                                log::error!("Error al detener el descubrimiento: {}", e);
+                            }
+                        }
+                    } else if allowed && auto_discovery && !running {
+                        let port = signaling_port(&signaling_server).await;
+                        let tls_port = tls_port(&tls_server).await;
+                        let local_device = local_device(&context.store).await;
+                        let profile = context.device_profile.read().await.clone();
+                        if let Err(e) = launch_discovery(&discovery, context.event_sender.clone(), local_device, &profile, port, tls_port).await {
+                            log::error!("Error al reanudar el descubrimiento: {}", e);
+                        }
+                    }
+                }
                 
                 // Limpiar dispositivos antiguos
                 if let Some(discovery) = discovery.lock().await.as_mut() {
@@ -237,16 +468,62 @@ impl SyncManager {
             }
         });
 
-        self.cleanup_task = Some(task);
+        *self.cleanup_task.lock().await = Some(task);
         Ok(())
     }
 
+    /// Iniciar la tarea que responde las ofertas que los dispositivos de
+    /// confianza dejan en el relay
+    async fn start_relay_signaling_task(&self) {
+        let context = self.sync_context();
+        let task = tokio::spawn(async move {
+            let mut ticker = interval(RELAY_SIGNALING_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let Some(relay) = context.relay_signaling.read().await.clone() else {
+                    continue;
+                };
+                let config = context.config.read().await.clone();
+                if config.paused || !config.method.uses_p2p() || !config.allow_incoming_connections {
+                    continue;
+                }
+                let Some(store) = context.store.as_ref() else {
+                    continue;
+                };
+                let Ok(local_device) = store.local_device_id().await else {
+                    continue;
+                };
+                let peers: Vec<DeviceId> = context.trusted_devices.read().await.keys().copied().collect();
+                if let Err(e) = relay.answer_offers(local_device, &peers, &context).await {
+                    log::warn!("Error al responder las ofertas del relay: {}", e);
+                }
+            }
+        });
+        *self.relay_signaling_task.lock().await = Some(task);
+    }
+
+    /// Manda heartbeats a los dispositivos con una conexión abierta cada
+    /// [`HEARTBEAT_INTERVAL`]
+    async fn start_heartbeat_task(&self) {
+        let context = self.sync_context();
+        let task = tokio::spawn(async move {
+            let mut ticker = interval(HEARTBEAT_INTERVAL);
+            let mut missed = HashMap::new();
+            loop {
+                ticker.tick().await;
+                context.send_heartbeats(&mut missed).await;
+            }
+        });
+        *self.heartbeat_task.lock().await = Some(task);
+    }
+
     /// Procesar evento localmente
     async fn process_event_locally(
         event: SyncEvent,
-        connected_devices: &Arc<RwLock<HashMap<String, DeviceInfo>>>,
+        connected_devices: &Arc<RwLock<HashMap<DeviceId, DeviceInfo>>>,
         stats: &Arc<RwLock<SyncStats>>,
         status: &Arc<RwLock<SyncStatus>>,
+        retries: &Arc<RwLock<HashMap<DeviceId, SyncRetry>>>,
     ) -> Result<()> {
         match event {
             SyncEvent::DeviceDiscovered(device) => {
@@ -260,9 +537,9 @@ impl SyncManager {
                 log::info!("Dispositivo conectado: {} ({})", device.name, device.device_type.display_name());
                 
                 // Agregar a dispositivos conectados
-                let device_id = device.id.clone();
+                let device_id = device.id;
                 let mut devices = connected_devices.write().await;
-                devices.insert(device_id.clone(), device.clone());
+                devices.insert(device_id, device.clone());
                 
                 // Actualizar estado
                 let mut status = status.write().await;
@@ -311,9 +588,14 @@ impl SyncManager {
             SyncEvent::SyncFailed(device, error) => {
                 log::error!("Sincronización falló con: {} - Error: {}", device.name, error);
                 
-                // Actualizar estado del dispositivo
+                // Si hay un reintento programado el dispositivo queda en espera
+                let retrying = retries.read().await.get(&device.id).is_some_and(|retry| !retry.gave_up());
                 if let Some(device) = connected_devices.write().await.get_mut(&device.id) {
-                    device.update_status(crate::sync::DeviceStatus::Error(error.clone()));
+                    device.update_status(if retrying {
+                        crate::sync::DeviceStatus::Waiting
+                    } else {
+                        crate::sync::DeviceStatus::Error(error.clone())
+                    });
                 }
                 
                 // Actualizar estadísticas
@@ -328,9 +610,16 @@ impl SyncManager {
                 let mut stats = stats.write().await;
                 stats.total_data_synced += count;
             }
-            SyncEvent::Heartbeat => {
-                log::debug!("Heartbeat recibido");
-                // No necesitamos hacer nada especial para el heartbeat
+            SyncEvent::Heartbeat(device) => {
+                log::debug!("{} respondió el heartbeat", device.name);
+
+                // Sigue conectado: se anota cuándo se lo vio
+                if let Some(connected) = connected_devices.write().await.get_mut(&device.id) {
+                    connected.last_seen = connected.last_seen.max(device.last_seen);
+                    if connected.status == crate::sync::DeviceStatus::Disconnected {
+                        connected.update_status(crate::sync::DeviceStatus::Connected);
+                    }
+                }
             }
         }
         Ok(())
@@ -338,7 +627,36 @@ impl SyncManager {
 
     /// Obtener el estado del sistema
     pub async fn get_status(&self) -> SyncStatus {
-        self.status.read().await.clone()
+        let mut status = self.status.read().await.clone();
+        status.retries = self.get_retries().await;
+        let config = self.config.read().await;
+        status.is_paused = config.paused;
+        status.sync_method = config.method;
+        let port = signaling_port(&self.signaling_server).await;
+        status.lan_address = local_ip()
+            .filter(|_| port != 0)
+            .map(|ip| SocketAddr::new(ip, port).to_string());
+        status
+    }
+
+    /// Reintentos de las sincronizaciones fallidas, por dispositivo
+    pub async fn get_retries(&self) -> Vec<SyncRetry> {
+        let mut retries: Vec<SyncRetry> = self.retries.read().await.values().cloned().collect();
+        retries.sort_by_key(|retry| retry.device_id);
+        retries
+    }
+
+    /// Pausa o reanuda la sincronización. En pausa se cancelan los reintentos
+    /// pendientes; el descubrimiento sigue para no perder de vista los
+    /// dispositivos.
+    pub async fn set_paused(&self, paused: bool) {
+        self.config.write().await.paused = paused;
+        if paused {
+            for (_, task) in self.retry_tasks.lock().await.drain() {
+                task.abort();
+            }
+            self.retries.write().await.clear();
+        }
     }
 
     /// Obtener la configuración del sistema
@@ -346,44 +664,145 @@ impl SyncManager {
         self.config.read().await.clone()
     }
 
-    /// Actualizar la configuración
+    /// Actualizar la configuración. Si el gestor está corriendo el cambio se
+    /// aplica enseguida: el descubrimiento se activa o se detiene, también
+    /// según la red actual, y la sincronización automática se vuelve a
+    /// programar con el nuevo intervalo.
     pub async fn update_config(&self, new_config: SyncConfig) -> Result<()> {
-        let mut config = self.config.write().await;
-        *config = new_config;
+        let _lifecycle = self.lifecycle.lock().await;
+        let discovery_enabled = new_config.auto_discovery;
+        *self.config.write().await = new_config;
+        self.apply_smart_sync_config().await;
+        if !*self.is_running.read().await {
+            return Ok(());
+        }
+
+        let network_allowed = self.sync_context().check_network().await.is_ok();
+        if discovery_enabled && network_allowed {
+            if self.discovery.lock().await.is_none() {
+                self.init_discovery().await?;
+            }
+        } else if let Some(mut discovery) = self.discovery.lock().await.take() {
+            discovery.stop().await?;
+            log::info!("Descubrimiento de dispositivos detenido");
+        }
+        self.restart_auto_sync_task().await;
         Ok(())
     }
 
+    /// Pasa a la sincronización la configuración que le toca: cómo tratar las
+    /// ediciones concurrentes y con qué nivel encriptar los lotes. Las
+    /// ediciones se combinan campo por campo y, si el usuario lo pidió, los
+    /// campos editados en los dos lados quedan para que decida.
+    async fn apply_smart_sync_config(&self) {
+        let config = self.config.read().await.clone();
+        let strategy = if config.ask_on_conflict {
+            ConflictResolutionStrategy::AutoMerge
+        } else {
+            ConflictResolutionStrategy::LatestWins
+        };
+        self.smart_sync.set_conflict_resolution_strategy(strategy).await;
+        self.smart_sync.set_encryption_level(config.encryption_level).await;
+    }
+
+    /// Programa la sincronización automática según la configuración actual,
+    /// reemplazando la que hubiera
+    async fn restart_auto_sync_task(&self) {
+        let mut task = self.auto_sync_task.lock().await;
+        if let Some(previous) = task.take() {
+            previous.abort();
+        }
+        let config = self.config.read().await.clone();
+        if !config.auto_sync || config.sync_interval == 0 {
+            log::info!("Sincronización automática desactivada");
+            return;
+        }
+
+        let context = self.sync_context();
+        let period = Duration::from_secs(config.sync_interval * 60);
+        *task = Some(tokio::spawn(async move {
+            let mut ticker = interval(period);
+            // El primer tick es inmediato: la primera sincronización espera un periodo
+            ticker.tick().await;
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    // Una sincronización manual cuenta como la de este periodo
+                    _ = context.manual_sync.notified() => {
+                        ticker.reset();
+                        continue;
+                    }
+                }
+                if let Some(results) = context.scheduled_sync().await {
+                    log::info!("Sincronización automática con {} dispositivos", results.len());
+                }
+            }
+        }));
+        log::info!("Sincronización automática cada {} minutos", config.sync_interval);
+    }
+
     /// Obtener dispositivos conectados
     pub async fn get_connected_devices(&self) -> Vec<DeviceInfo> {
         let devices = self.connected_devices.read().await;
         devices.values().cloned().collect()
     }
 
-    /// Obtener dispositivos descubiertos
+    /// Obtener dispositivos descubiertos, por mDNS o agregados a mano
     pub async fn get_discovered_devices(&self) -> Vec<DeviceInfo> {
-        if let Some(discovery) = self.discovery.lock().await.as_ref() {
+        let mut devices = if let Some(discovery) = self.discovery.lock().await.as_ref() {
             discovery.get_discovered_devices().await
         } else {
             Vec::new()
+        };
+        // Si además se anuncia por mDNS vale lo del anuncio, que está al día
+        for device in self.manual_devices.read().await.values() {
+            if !devices.iter().any(|discovered| discovered.id == device.id) {
+                devices.push(device.clone());
+            }
         }
+        devices
     }
 
     /// Buscar dispositivos
     pub async fn search_devices(&self, query: &str) -> Vec<DeviceInfo> {
-        if let Some(discovery) = self.discovery.lock().await.as_ref() {
-            let devices = discovery.get_discovered_devices().await;
-            let query_lower = query.to_lowercase();
-            
-            devices.into_iter()
-                .filter(|device| {
-                    device.name.to_lowercase().contains(&query_lower) ||
-                    device.os.to_lowercase().contains(&query_lower) ||
-                    device.device_type.to_string().to_lowercase().contains(&query_lower)
-                })
-                .collect()
-        } else {
-            Vec::new()
+        let query_lower = query.to_lowercase();
+        self.get_discovered_devices().await.into_iter()
+            .filter(|device| {
+                device.name.to_lowercase().contains(&query_lower) ||
+                device.os.to_lowercase().contains(&query_lower) ||
+                device.device_type.to_string().to_lowercase().contains(&query_lower)
+            })
+            .collect()
+    }
+
+    /// Agrega a mano el dispositivo que escucha en `ip` y `port`, la dirección
+    /// que muestra en su estado, para las redes que bloquean mDNS. Le pregunta
+    /// quién es y lo deja con los descubiertos, listo para
+    /// [`SyncManager::begin_pairing`].
+    pub async fn add_device_manually(&self, ip: &str, port: u16) -> Result<DeviceInfo> {
+        let address = socket_address(ip, port)
+            .filter(|address| address.port() != 0)
+            .ok_or_else(|| anyhow!("Dirección no válida: {} puerto {}", ip, port))?;
+        self.sync_context().check_network().await?;
+
+        let mut device = probe(address).await?;
+        if local_device(&self.store).await == Some(device.id) {
+            return Err(anyhow!("{} es este mismo dispositivo", address));
+        }
+        let ip = ip.trim().trim_start_matches('[').trim_end_matches(']').to_string();
+        device.ip_address = Some(ip.clone());
+        device.port = Some(port);
+        device.addresses = vec![ip];
+        device.status = crate::sync::DeviceStatus::Disconnected;
+        device.last_seen = Some(chrono::Utc::now());
+        device.is_trusted = self.is_trusted(device.id).await;
+        self.manual_devices.write().await.insert(device.id, device.clone());
+        log::info!("Dispositivo agregado a mano: {} en {}", device.name, address);
+
+        if let Err(e) = self.event_sender.send(SyncEvent::DeviceDiscovered(device.clone())).await {
+            log::error!("Error enviando evento de dispositivo descubierto: {}", e);
         }
+        Ok(device)
     }
 
     /// Obtener estadísticas
@@ -391,53 +810,331 @@ impl SyncManager {
         self.stats.read().await.clone()
     }
 
-    /// Conectar a un dispositivo
-    pub async fn connect_to_device(&self, device_id: &str) -> Result<()> {
-        // TODO: Implementar conexión P2P
+    /// Abre una conexión P2P con un dispositivo de confianza, presentándose
+    /// con la identidad de este dispositivo y exigiendo la que se fijó al
+    /// emparejarlos
+    pub async fn connect_to_device(&self, device_id: DeviceId) -> Result<()> {
         log::info!("Conectando a dispositivo: {}", device_id);
+        self.sync_context().open_connection(device_id).await?;
         Ok(())
     }
 
     /// Desconectar de un dispositivo
-    pub async fn disconnect_from_device(&self, device_id: &str) -> Result<()> {
-        // TODO: Implementar desconexión
+    pub async fn disconnect_from_device(&self, device_id: DeviceId) -> Result<()> {
         log::info!("Desconectando de dispositivo: {}", device_id);
+        self.transports.write().await.remove(&device_id);
         Ok(())
     }
 
-    /// Sincronizar con un dispositivo
-    pub async fn sync_with_device(&self, device_id: &str) -> Result<SyncResult> {
-        // TODO: Implementar sincronización
-        log::info!("Sincronizando con dispositivo: {}", device_id);
-        
-        Ok(SyncResult::success(
-            device_id.to_string(),
-            0,
-            0,
-            0,
-        ))
+    /// Registra la conexión por la que se sincroniza con un dispositivo,
+    /// reemplazando la anterior
+    pub async fn set_transport(&self, device_id: DeviceId, transport: Arc<dyn SyncTransport>) {
+        self.transports.write().await.insert(device_id, transport);
+    }
+
+    /// Reemplaza el buzón en la nube; `None` si no hay uno configurado
+    pub async fn set_mailbox(&self, mailbox: Option<Arc<dyn SyncMailbox>>) {
+        *self.mailbox.write().await = mailbox;
+    }
+
+    /// Reemplaza el relay por el que se mandan y responden las ofertas WebRTC;
+    /// `None` si no hay uno configurado
+    pub async fn set_relay_signaling(&self, relay: Option<Arc<RelaySignaling>>) {
+        *self.relay_signaling.write().await = relay;
+    }
+
+    /// Reemplaza el servidor TURN de las conexiones que se abran de acá en
+    /// más; `None` para conectar solo directo o con STUN
+    pub async fn set_turn_server(&self, turn: Option<TurnServer>) {
+        *self.turn_server.write().await = turn;
+    }
+
+    /// Diagnóstico de la conexión abierta con un dispositivo, con el par de
+    /// candidatos ICE elegido. `None` si no hay conexión o no es WebRTC.
+    pub async fn get_connection_diagnostics(&self, device_id: DeviceId) -> Option<P2PConnectionStats> {
+        let transport = self.transports.read().await.get(&device_id).cloned()?;
+        transport.diagnostics().await
+    }
+
+    /// Reemplaza la lista de dispositivos de confianza, cada uno con su
+    /// identidad si se emparejó
+    pub async fn set_trusted_devices(&self, devices: impl IntoIterator<Item = (DeviceId, Option<PublicIdentity>)>) {
+        let mut trusted = self.trusted_devices.write().await;
+        *trusted = devices.into_iter().collect();
+        log::info!("{} dispositivos de confianza", trusted.len());
+    }
+
+    pub async fn is_trusted(&self, device_id: DeviceId) -> bool {
+        self.trusted_devices.read().await.contains_key(&device_id)
+    }
+
+    /// Reemplaza la identidad con la que este dispositivo se presenta
+    pub async fn set_identity(&self, identity: Option<DeviceIdentity>) {
+        *self.identity.write().await = identity;
+    }
+
+    /// Reemplaza el nombre y el tipo con los que este dispositivo se presenta.
+    /// Si el descubrimiento está activo vuelve a anunciarse con los nuevos.
+    pub async fn set_device_profile(&self, profile: DeviceProfile) -> Result<()> {
+        if *self.device_profile.read().await == profile {
+            return Ok(());
+        }
+        *self.device_profile.write().await = profile;
+        let running = self.discovery.lock().await.take();
+        if let Some(mut discovery) = running {
+            discovery.stop().await?;
+            self.init_discovery().await?;
+        }
+        Ok(())
+    }
+
+    /// Nombre y tipo con los que este dispositivo se presenta: los que eligió
+    /// el usuario o, si no eligió, los del sistema
+    pub async fn device_profile(&self) -> DeviceProfile {
+        presented_profile(&self.device_profile).await
+    }
+
+    /// Identidad de este dispositivo; falla si los comandos no la cargaron
+    pub async fn identity(&self) -> Result<DeviceIdentity> {
+        self.sync_context().identity().await
+    }
+
+    /// Identidad fijada al emparejarse con un dispositivo de confianza. Falla
+    /// si el dispositivo no es de confianza o se confió en él sin emparejarlo.
+    pub async fn pinned_identity(&self, device_id: DeviceId) -> Result<PublicIdentity> {
+        self.sync_context().pinned_identity(device_id).await
     }
 
-    /// Sincronizar con todos los dispositivos
+    /// Comprueba que quepa un dispositivo de confianza más. Los que ya lo son
+    /// siempre caben; `None` es un dispositivo que todavía no se conoce.
+    pub async fn check_device_limit(&self, device_id: Option<DeviceId>) -> Result<()> {
+        let max_devices = self.config.read().await.max_devices;
+        let trusted = self.trusted_devices.read().await;
+        if device_id.is_some_and(|device_id| trusted.contains_key(&device_id)) || trusted.len() < max_devices as usize {
+            return Ok(());
+        }
+        log::warn!("Se rechaza un dispositivo más: ya hay {} de {} de confianza", trusted.len(), max_devices);
+        Err(DeviceLimitReached { max_devices }.into())
+    }
+
+    /// Falla si lo que se sabe de `device_id`, por su anuncio o su último
+    /// lote, dice que no se puede sincronizar con él: así no se empareja en
+    /// vano. Un dispositivo del que no se sabe la versión pasa.
+    pub async fn check_compatible(&self, device_id: DeviceId) -> Result<()> {
+        let incompatibility = self.get_devices().await.into_iter()
+            .find(|device| device.id == device_id)
+            .and_then(|device| device.incompatibility);
+        match incompatibility {
+            Some(incompatibility) => {
+                log::warn!("Se rechaza emparejarse con {}: {}", device_id, incompatibility);
+                Err(incompatibility.into())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Comprueba que el certificado que presentó un dispositivo sea el fijado
+    /// al emparejarse. Cualquier transporte tiene que pasar por aquí antes de
+    /// aceptar datos del otro extremo.
+    pub async fn verify_peer(&self, device_id: DeviceId, presented: &[u8; 32]) -> Result<()> {
+        self.sync_context().verify_peer(device_id, presented).await
+    }
+
+    /// Sincronizar con un dispositivo; falla si no es de confianza
+    pub async fn sync_with_device(&self, device_id: DeviceId) -> Result<SyncResult> {
+        if !self.is_trusted(device_id).await {
+            return Err(anyhow!("El dispositivo {} no es de confianza", device_id));
+        }
+        self.sync_context().sync_device(device_id).await
+    }
+
+    /// Sincronizar con todos los dispositivos. Si la sincronización automática
+    /// está en curso espera a que termine, y la siguiente automática cuenta el
+    /// periodo desde aquí.
     pub async fn sync_all_devices(&self) -> Result<Vec<SyncResult>> {
-        let devices = self.get_connected_devices().await;
-        let mut results = Vec::new();
+        let results = self.sync_context().sync_devices().await?;
+        self.manual_sync.notify_waiters();
+        Ok(results)
+    }
 
-        for device in devices {
-            if device.is_available_for_sync() {
-                match self.sync_with_device(&device.id).await {
-                    Ok(result) => results.push(result),
-                    Err(e) => {
-                        results.push(SyncResult::failure(
-                            device.id.clone(),
-                            e.to_string(),
-                        ));
+    /// Encola un cambio de la bóveda que ya se anotó en el diario
+    pub async fn track_change(&self, change: DataChange) -> Result<()> {
+        self.smart_sync.add_recorded_change(change).await
+    }
+
+    /// Conflictos que esperan una decisión del usuario
+    pub async fn get_conflicts(&self) -> Vec<SyncConflict> {
+        self.smart_sync.get_conflicts().await.into_iter()
+            .filter(|conflict| conflict.status == ConflictStatus::Pending)
+            .collect()
+    }
+
+    /// Marca un conflicto como resuelto; la resolución ya se aplicó a la bóveda
+    pub async fn resolve_conflict(&self, conflict_id: &str, resolution: ConflictResolution) -> Result<()> {
+        self.smart_sync.resolve_conflict(conflict_id, resolution).await
+    }
+
+    /// Responde al lote de cambios que mandó un dispositivo de confianza con
+    /// el lote de este dispositivo
+    pub async fn handle_sync_request(&self, device_id: DeviceId, request: &[u8]) -> Result<Vec<u8>> {
+        self.sync_context().respond(device_id, request).await
+    }
+
+    /// Empieza a emparejarse con un dispositivo conocido. El código aparece
+    /// cuando el otro dispositivo responde.
+    pub async fn begin_pairing(&self, device_id: DeviceId) -> Result<PairingStatus> {
+        if !self.get_devices().await.iter().any(|device| device.id == device_id) {
+            return Err(anyhow!("Dispositivo desconocido: {}", device_id));
+        }
+        self.check_device_limit(Some(device_id)).await?;
+        self.check_compatible(device_id).await?;
+
+        let (session, commit) = PairingSession::initiate(self.identity().await?.public());
+        let status = PairingStatus::new(device_id, &session);
+        self.pairings.lock().await.insert(device_id, session);
+        self.send_pairing_message(device_id, &commit).await?;
+        log::info!("Emparejamiento iniciado con {}", device_id);
+        Ok(status)
+    }
+
+    /// Procesa un mensaje de emparejamiento de otro dispositivo. Un mensaje que
+    /// no encaja cierra el emparejamiento.
+    pub async fn handle_pairing_message(&self, device_id: DeviceId, message: PairingMessage) -> Result<()> {
+        let reply = {
+            let mut pairings = self.pairings.lock().await;
+            pairings.retain(|_, session| !session.is_expired());
+            match &message {
+                PairingMessage::Cancel => {
+                    pairings.remove(&device_id);
+                    log::info!("{} canceló el emparejamiento", device_id);
+                    None
+                }
+                PairingMessage::Commit { .. } => {
+                    self.check_device_limit(Some(device_id)).await?;
+                    self.check_compatible(device_id).await?;
+                    let (session, reply) = PairingSession::respond(&message, self.identity().await?.public())?;
+                    pairings.insert(device_id, session);
+                    log::info!("{} pidió emparejarse", device_id);
+                    Some(reply)
+                }
+                PairingMessage::Join { .. } => {
+                    self.check_device_limit(Some(device_id)).await?;
+                    // El código QR se descarta también si la prueba no vale
+                    let mut session = self.qr_invite.lock().await.take()
+                        .filter(|session| !session.is_expired())
+                        .ok_or_else(|| anyhow!("No hay un código QR de emparejamiento activo"))?;
+                    session.receive(&message)?;
+                    pairings.insert(device_id, session);
+                    log::info!("{} escaneó el código QR de emparejamiento", device_id);
+                    None
+                }
+                _ => {
+                    let session = pairings.get_mut(&device_id)
+                        .ok_or_else(|| anyhow!("No hay un emparejamiento en curso con {}", device_id))?;
+                    match session.receive(&message) {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            pairings.remove(&device_id);
+                            return Err(e);
+                        }
                     }
                 }
             }
+        };
+
+        if let Some(reply) = reply {
+            let reply = reply.with_device(self.device_profile().await);
+            self.send_pairing_message(device_id, &reply).await?;
         }
+        Ok(())
+    }
 
-        Ok(results)
+    /// Crea el código QR de emparejamiento de este dispositivo, que se presenta
+    /// como `device_id`. Reemplaza el código anterior si lo había.
+    pub async fn create_pairing_invite(&self, device_id: DeviceId) -> Result<PairingInvite> {
+        self.check_device_limit(None).await?;
+        let identity = self.identity().await?.public();
+        let (session, mut invite) = PairingSession::invite(device_id, identity, local_ip().map(|ip| ip.to_string()));
+        invite.device = Some(self.device_profile().await);
+        *self.qr_invite.lock().await = Some(session);
+        log::info!("Código QR de emparejamiento creado");
+        Ok(invite)
+    }
+
+    /// Se empareja con el dispositivo de un código QR escaneado y devuelve la
+    /// clave de sincronización acordada
+    pub async fn join_pairing(&self, invite: &PairingInvite) -> Result<PairedKey> {
+        self.check_device_limit(Some(invite.device_id)).await?;
+        let (session, join) = PairingSession::join(invite, self.identity().await?.public())?;
+        let join = join.with_device(self.device_profile().await);
+        self.send_pairing_message(invite.device_id, &join).await?;
+        let paired = session.confirm()?;
+        log::info!("Emparejado con {} por código QR", invite.device_id);
+        Ok(paired)
+    }
+
+    /// Manda `entry` a un dispositivo emparejado sin sincronizar nada más.
+    /// Termina cuando el otro la recibió, no cuando el usuario la acepta.
+    pub async fn send_entry(&self, device_id: DeviceId, entry: &PasswordEntry) -> Result<()> {
+        self.sync_context().send_entry(device_id, entry).await
+    }
+
+    /// Entradas que mandaron otros dispositivos y esperan que el usuario las
+    /// acepte o las rechace
+    pub async fn get_incoming_entries(&self) -> Vec<IncomingEntry> {
+        self.entry_inbox.lock().await.pending()
+    }
+
+    /// Saca una entrada recibida para aceptarla o rechazarla. Falla si no
+    /// está o ya caducó.
+    pub async fn take_incoming_entry(&self, id: uuid::Uuid) -> Result<IncomingEntry> {
+        self.entry_inbox.lock().await.take(id)
+            .ok_or_else(|| anyhow!("No hay una entrada recibida {} esperando", id))
+    }
+
+    /// Emparejamientos en curso, iniciados aquí o por otro dispositivo
+    pub async fn get_pairings(&self) -> Vec<PairingStatus> {
+        let mut pairings = self.pairings.lock().await;
+        pairings.retain(|_, session| !session.is_expired());
+        pairings.iter()
+            .map(|(device_id, session)| PairingStatus::new(*device_id, session))
+            .collect()
+    }
+
+    /// Cierra el emparejamiento con la respuesta del usuario. Si los códigos
+    /// coinciden devuelve la clave de sincronización; si no, avisa al otro
+    /// dispositivo y devuelve `None`.
+    pub async fn confirm_pairing(&self, device_id: DeviceId, accepted: bool) -> Result<Option<PairedKey>> {
+        let session = {
+            let mut pairings = self.pairings.lock().await;
+            pairings.retain(|_, session| !session.is_expired());
+            if accepted && pairings.get(&device_id).is_some_and(|session| session.code().is_none()) {
+                return Err(anyhow!("El emparejamiento con {} todavía no tiene código", device_id));
+            }
+            // Sin sitio el emparejamiento sigue abierto: se puede aceptar
+            // después de quitar otro dispositivo
+            if accepted {
+                self.check_device_limit(Some(device_id)).await?;
+            }
+            pairings.remove(&device_id)
+                .ok_or_else(|| anyhow!("No hay un emparejamiento en curso con {}", device_id))?
+        };
+
+        if !accepted {
+            self.send_pairing_message(device_id, &PairingMessage::Cancel).await?;
+            log::info!("Emparejamiento con {} rechazado", device_id);
+            return Ok(None);
+        }
+        let paired = session.confirm()?;
+        log::info!("Emparejamiento con {} confirmado", device_id);
+        Ok(Some(paired))
+    }
+
+    /// Manda un mensaje de emparejamiento al dispositivo
+    async fn send_pairing_message(&self, device_id: DeviceId, message: &PairingMessage) -> Result<()> {
+        // TODO: Enviar por la conexión P2P cuando esté implementada
+        log::info!("Mensaje de emparejamiento para {}: {:?}", device_id, message);
+        Ok(())
     }
 
     /// Establecer manejador de eventos personalizado
@@ -452,23 +1149,22 @@ impl SyncManager {
 
     /// Obtener todos los dispositivos (conectados y descubiertos)
     pub async fn get_devices(&self) -> Vec<DeviceInfo> {
-        let mut all_devices = Vec::new();
-        
-        // Agregar dispositivos conectados
-        let connected = self.get_connected_devices().await;
-        all_devices.extend(connected);
-        
-        // Agregar dispositivos descubiertos (que no estén ya conectados)
-        let discovered = self.get_discovered_devices().await;
-        let connected_ids: std::collections::HashSet<_> = all_devices.iter().map(|d| d.id.clone()).collect();
-        
-        for device in discovered {
-            if !connected_ids.contains(&device.id) {
-                all_devices.push(device);
-            }
+        let mut devices = merge_devices(self.get_connected_devices().await, self.get_discovered_devices().await);
+        for device in &mut devices {
+            self.describe_compatibility(device).await;
         }
-        
-        all_devices
+        devices
+    }
+
+    /// Completa `device` con la versión y las capacidades que mandó en su
+    /// último lote, que valen más que las del anuncio, y anota si se puede
+    /// sincronizar con él
+    pub async fn describe_compatibility(&self, device: &mut DeviceInfo) {
+        if let Some((app_version, capabilities)) = self.smart_sync.peer_capabilities(device.id).await {
+            device.app_version = app_version;
+            device.capabilities = capabilities;
+        }
+        device.incompatibility = device.check_compatibility();
     }
 
     /// Obtener información del sistema
@@ -486,7 +1182,7 @@ impl SyncManager {
         SystemInfo {
             is_running: *self.is_running.read().await,
             is_enabled: status.is_enabled,
-            sync_method: status.sync_method.clone(),
+            sync_method: config.method,
             auto_sync: status.auto_sync,
             auto_discovery: config.auto_discovery,
             connected_devices: connected_count,
@@ -499,85 +1195,811 @@ impl SyncManager {
     }
 }
 
-/// Manejador de eventos para el descubrimiento
-struct DiscoveryEventHandler {
-    event_sender: mpsc::Sender<SyncEvent>,
+/// Ya hay tantos dispositivos de confianza como permite la configuración
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Ya hay {max_devices} dispositivos de confianza, el máximo permitido; quita alguno antes de agregar otro")]
+pub struct DeviceLimitReached {
+    pub max_devices: u32,
 }
 
-#[async_trait]
-impl SyncEventHandler for DiscoveryEventHandler {
-    fn handle_event(&self, event: &SyncEvent) {
-        // Reenviar evento al gestor principal
-        let event_clone = event.clone();
-        let event_sender = self.event_sender.clone();
-        
-        tokio::spawn(async move {
-            if let Err(e) = event_sender.send(event_clone).await {
-                log::error!("Error al reenviar evento: {}", e);
-            }
-        });
-    }
+/// Lo que necesita una sincronización, separado del gestor para que la tarea
+/// de sincronización automática se lo pueda llevar
+#[derive(Clone)]
+struct SyncContext {
+    config: Arc<RwLock<SyncConfig>>,
+    status: Arc<RwLock<SyncStatus>>,
+    connected_devices: Arc<RwLock<HashMap<DeviceId, DeviceInfo>>>,
+    trusted_devices: Arc<RwLock<HashMap<DeviceId, Option<PublicIdentity>>>>,
+    identity: Arc<RwLock<Option<DeviceIdentity>>>,
+    device_profile: Arc<RwLock<DeviceProfile>>,
+    discovery: Arc<Mutex<Option<DeviceDiscovery>>>,
+    manual_devices: Arc<RwLock<HashMap<DeviceId, DeviceInfo>>>,
+    tls_server: Arc<Mutex<Option<TlsServer>>>,
+    transports: Arc<RwLock<HashMap<DeviceId, Arc<dyn SyncTransport>>>>,
+    mailbox: Arc<RwLock<Option<Arc<dyn SyncMailbox>>>>,
+    relay_signaling: Arc<RwLock<Option<Arc<RelaySignaling>>>>,
+    turn_server: Arc<RwLock<Option<TurnServer>>>,
+    smart_sync: Arc<SmartSync>,
+    store: Option<Arc<dyn SyncStore>>,
+    event_sender: mpsc::Sender<SyncEvent>,
+    retries: Arc<RwLock<HashMap<DeviceId, SyncRetry>>>,
+    retry_tasks: Arc<Mutex<HashMap<DeviceId, tokio::task::JoinHandle<()>>>>,
+    retry_policy: RetryPolicy,
+    sync_round: Arc<Mutex<()>>,
+    manual_sync: Arc<Notify>,
+    entry_inbox: Arc<Mutex<EntryInbox>>,
 }
 
-/// Información del sistema
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SystemInfo {
-    /// Si el sistema está ejecutándose
-    pub is_running: bool,
-    /// Si el sistema está habilitado
-    pub is_enabled: bool,
-    /// Método de sincronización actual
-    pub sync_method: SyncMethod,
-    /// Sincronización automática habilitada
-    pub auto_sync: bool,
-    /// Descubrimiento automático habilitado
-    pub auto_discovery: bool,
-    /// Número de dispositivos conectados
-    pub connected_devices: usize,
-    /// Número de dispositivos descubiertos
-    pub discovered_devices: usize,
-    /// Total de sincronizaciones
-    pub total_syncs: u64,
-    /// Sincronizaciones exitosas
-    pub successful_syncs: u64,
-    /// Sincronizaciones fallidas
-    pub failed_syncs: u64,
-    /// Última sincronización
-    pub last_sync: Option<chrono::DateTime<chrono::Utc>>,
-}
+impl SyncContext {
+    async fn identity(&self) -> Result<DeviceIdentity> {
+        self.identity.read().await.clone()
+            .ok_or_else(|| anyhow!("La identidad de este dispositivo no está cargada"))
+    }
 
-impl Default for SystemInfo {
-    fn default() -> Self {
-        Self {
-            is_running: false,
-            is_enabled: false,
-            sync_method: SyncMethod::Hybrid,
-            auto_sync: true,
-            auto_discovery: true,
-            connected_devices: 0,
-            discovered_devices: 0,
-            total_syncs: 0,
-            successful_syncs: 0,
-            failed_syncs: 0,
-            last_sync: None,
+    async fn pinned_identity(&self, device_id: DeviceId) -> Result<PublicIdentity> {
+        match self.trusted_devices.read().await.get(&device_id) {
+            Some(Some(identity)) => Ok(*identity),
+            Some(None) => Err(anyhow!("El dispositivo {} no tiene una identidad fijada; hay que emparejarlo", device_id)),
+            None => Err(anyhow!("El dispositivo {} no es de confianza", device_id)),
         }
     }
-}
 
-/// Implementar Drop para limpiar recursos
-impl Drop for SyncManager {
-    fn drop(&mut self) {
-        // Intentar detener el gestor si aún está ejecutándose
-        let should_stop = {
-            if let Ok(running) = self.is_running.try_read() {
-                *running
-            } else {
-                false
+    async fn verify_peer(&self, device_id: DeviceId, presented: &[u8; 32]) -> Result<()> {
+        let pinned = self.pinned_identity(device_id).await?;
+        verify_fingerprint(&pinned.fingerprint, presented).map_err(|e| {
+            log::warn!("Conexión rechazada: {} cambió de identidad ({})", device_id, hex::encode(presented));
+            e
+        })
+    }
+
+    /// Lo que se sabe de un dispositivo: si está conectado, se descubrió en
+    /// la red local o se agregó a mano
+    async fn find_device(&self, device_id: DeviceId) -> Option<DeviceInfo> {
+        if let Some(device) = self.connected_devices.read().await.get(&device_id) {
+            return Some(device.clone());
+        }
+        let discovered = match self.discovery.lock().await.as_ref() {
+            Some(discovery) => discovery.get_discovered_devices().await.into_iter().find(|device| device.id == device_id),
+            None => None,
+        };
+        match discovered {
+            Some(device) => Some(device),
+            None => self.manual_devices.read().await.get(&device_id).cloned(),
+        }
+    }
+
+    /// Configuración de las conexiones P2P, con el servidor TURN si hay uno
+    async fn p2p_config(&self) -> P2PConfig {
+        P2PConfig {
+            turn_servers: self.turn_server.read().await.iter().cloned().collect(),
+            ..P2PConfig::default()
+        }
+    }
+
+    /// Comprueba que se acepten conexiones entrantes para `to`, por el
+    /// transporte que sea
+    async fn check_incoming(&self, to: DeviceId) -> Result<()> {
+        let store = self.store.as_ref()
+            .ok_or_else(|| anyhow!("No hay una bóveda que sincronizar"))?;
+        if store.local_device_id().await? != to {
+            return Err(anyhow!("La conexión es para otro dispositivo"));
+        }
+        let config = self.config.read().await.clone();
+        if config.paused {
+            return Err(anyhow!("La sincronización está en pausa"));
+        }
+        if !config.method.uses_p2p() {
+            return Err(anyhow!("La sincronización directa está desactivada ({:?})", config.method));
+        }
+        if !config.allow_incoming_connections {
+            return Err(anyhow!("No se aceptan conexiones entrantes"));
+        }
+        self.check_network().await
+    }
+
+    /// Direcciones en las que el dispositivo recibe ofertas en la red local,
+    /// de la preferida a la última; vacío si no las anunció
+    fn lan_addresses(device: &DeviceInfo) -> Vec<SocketAddr> {
+        match device.port.filter(|&port| port != 0) {
+            Some(port) => device.socket_addresses(port),
+            None => Vec::new(),
+        }
+    }
+
+    /// Direcciones de las conexiones TLS del dispositivo, de la preferida a
+    /// la última; vacío si no anunció el puerto
+    fn tls_addresses(device: &DeviceInfo) -> Vec<SocketAddr> {
+        let port = device.metadata.get(TLS_PORT_PROPERTY).and_then(|port| port.parse::<u16>().ok());
+        match port.filter(|&port| port != 0) {
+            Some(port) => device.socket_addresses(port),
+            None => Vec::new(),
+        }
+    }
+
+    /// Abre una conexión con un dispositivo de confianza y la deja como su
+    /// transporte: directa por TLS si está en la red local y, si no, P2P, que
+    /// se vuelve a abrir si se cae
+    async fn open_connection(&self, device_id: DeviceId) -> Result<Arc<dyn SyncTransport>> {
+        let transport: Arc<dyn SyncTransport> = match self.dial_tls(device_id).await {
+            Some(connection) => Arc::new(connection),
+            None => Arc::new(ReconnectingConnection::new(device_id, self.dial(device_id).await?, Arc::new(self.clone()))),
+        };
+        self.transports.write().await.insert(device_id, transport.clone());
+        Ok(transport)
+    }
+
+    /// Manda una entrada suelta por la conexión con un dispositivo
+    /// emparejado, abriéndola si hace falta
+    async fn send_entry(&self, device_id: DeviceId, entry: &PasswordEntry) -> Result<()> {
+        if !self.trusted_devices.read().await.contains_key(&device_id) {
+            return Err(anyhow!("El dispositivo {} no es de confianza", device_id));
+        }
+        let transport = match self.transports.read().await.get(&device_id).cloned() {
+            Some(transport) => transport,
+            None => self.open_connection(device_id).await?,
+        };
+        let reply = transport.exchange(encode_offer(entry)?).await?;
+        if reply != ENTRY_RECEIVED {
+            return Err(anyhow!("{} no recibió la entrada", device_id));
+        }
+        log::info!("Entrada enviada a {}", device_id);
+        Ok(())
+    }
+
+    /// Manda un heartbeat por cada conexión abierta. Un dispositivo que
+    /// responde queda conectado y con la hora en que se lo vio; uno que no
+    /// responde [`MAX_MISSED_HEARTBEATS`] veces seguidas pierde su conexión y
+    /// se da por desconectado. `missed` lleva la cuenta entre una vuelta y la
+    /// siguiente.
+    async fn send_heartbeats(&self, missed: &mut HashMap<DeviceId, u32>) {
+        let transports: Vec<(DeviceId, Arc<dyn SyncTransport>)> = self.transports.read().await.iter()
+            .map(|(device_id, transport)| (*device_id, transport.clone()))
+            .collect();
+        missed.retain(|device_id, _| transports.iter().any(|(open, _)| open == device_id));
+
+        let pings = transports.into_iter().map(|(device_id, transport)| async move {
+            let reply = tokio::time::timeout(HEARTBEAT_TIMEOUT, transport.exchange(HEARTBEAT_PING.to_vec())).await;
+            (device_id, matches!(reply, Ok(Ok(reply)) if reply == HEARTBEAT_PONG))
+        });
+        for (device_id, answered) in futures::future::join_all(pings).await {
+            if answered {
+                missed.remove(&device_id);
+                self.heartbeat_answered(device_id).await;
+                continue;
+            }
+            let count = missed.entry(device_id).or_insert(0);
+            *count += 1;
+            log::debug!("{} no respondió el heartbeat ({}/{})", device_id, count, MAX_MISSED_HEARTBEATS);
+            if *count >= MAX_MISSED_HEARTBEATS {
+                missed.remove(&device_id);
+                self.heartbeat_lost(device_id).await;
             }
+        }
+    }
+
+    /// Avisa que `device_id` respondió el heartbeat: conectado si no lo
+    /// estaba, y si no, con la hora en que se lo vio
+    async fn heartbeat_answered(&self, device_id: DeviceId) {
+        let connected = self.connected_devices.read().await.get(&device_id).cloned();
+        let event = match connected {
+            Some(mut device) => {
+                device.last_seen = Some(chrono::Utc::now());
+                SyncEvent::Heartbeat(device)
+            }
+            // Las conexiones TLS y las que abrió el otro no pasan por el
+            // evento de conexión de WebRTC
+            None => match self.find_device(device_id).await {
+                Some(mut device) => {
+                    device.update_status(crate::sync::DeviceStatus::Connected);
+                    SyncEvent::DeviceConnected(device)
+                }
+                None => return,
+            },
         };
+        let _ = self.event_sender.send(event).await;
+    }
+
+    /// Cierra la conexión con un dispositivo que dejó de responder los
+    /// heartbeats y lo da por desconectado
+    async fn heartbeat_lost(&self, device_id: DeviceId) {
+        log::info!("{} no respondió {} heartbeats: se da por desconectado", device_id, MAX_MISSED_HEARTBEATS);
+        self.transports.write().await.remove(&device_id);
+        let connected = self.connected_devices.read().await.get(&device_id).cloned();
+        if let Some(mut device) = connected {
+            device.status = crate::sync::DeviceStatus::Disconnected;
+            let _ = self.event_sender.send(SyncEvent::DeviceDisconnected(device)).await;
+        }
+    }
+
+    /// Conecta por TLS con un dispositivo que anunció su puerto en la red
+    /// local, probando sus direcciones en orden hasta que una responda.
+    /// `None` si no lo anunció o no se pudo, para seguir con WebRTC.
+    async fn dial_tls(&self, device_id: DeviceId) -> Option<TlsConnection> {
+        let device = self.find_device(device_id).await?;
+        let addresses = Self::tls_addresses(&device);
+        if addresses.is_empty() {
+            return None;
+        }
+        let connection = async {
+            let peer = self.pinned_identity(device_id).await?;
+            let local_device = self.store.as_ref()
+                .ok_or_else(|| anyhow!("No hay una bóveda que sincronizar"))?
+                .local_device_id().await?;
+            let identity = self.identity().await?;
+            let mut last_error = anyhow!("No hay ninguna dirección a la que conectar");
+            for address in addresses {
+                match TlsConnection::connect(&identity, &peer, local_device, device_id, address).await {
+                    Ok(connection) => return Ok(connection),
+                    Err(e) => {
+                        log::debug!("No se pudo conectar por TLS con {} en {}: {}", device.name, address, e);
+                        last_error = e;
+                    }
+                }
+            }
+            Err(last_error)
+        };
+        match connection.await {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                log::warn!("No se pudo conectar por TLS con {}, se intenta con WebRTC: {}", device.name, e);
+                None
+            }
+        }
+    }
+
+    /// Conecta con un dispositivo de confianza. La oferta va directa si el
+    /// dispositivo está en la red local y, si no, por el relay.
+    async fn dial(&self, device_id: DeviceId) -> Result<P2PConnection> {
+        let peer = self.pinned_identity(device_id).await?;
+        let identity = self.identity().await?;
+        let local_device = self.store.as_ref()
+            .ok_or_else(|| anyhow!("No hay una bóveda que sincronizar"))?
+            .local_device_id().await?;
+        let device = self.find_device(device_id).await
+            .unwrap_or_else(|| DeviceInfo::offline(device_id, device_id.to_string()));
+
+        let addresses = Self::lan_addresses(&device);
+        let signaling: Arc<dyn SignalingChannel> = match addresses.is_empty() {
+            false => Arc::new(LanSignaling::new(addresses)),
+            true => match self.relay_signaling.read().await.clone() {
+                Some(relay) => relay,
+                None => return Err(anyhow!("{} no está en la red local y no hay un relay configurado", device.name)),
+            },
+        };
+
+        let mut connection = P2PConnection::new(self.p2p_config().await, self.event_sender.clone())
+            .with_identity(identity, peer)
+            .with_responder(Arc::new(NoiseResponder::new(Arc::new(self.clone()))));
+        connection.connect(device, local_device, signaling.as_ref()).await?;
+        Ok(connection)
+    }
+
+    /// Sincronización automática con todos. No hace nada en pausa, con la
+    /// bóveda bloqueada, porque sin sus claves no se puede, ni si ya hay otra
+    /// sincronización con todos en curso. Devuelve `None` si no sincronizó.
+    async fn scheduled_sync(&self) -> Option<Vec<SyncResult>> {
+        let store = self.store.as_ref()?;
+        if self.config.read().await.paused {
+            log::info!("Se omite la sincronización automática: está en pausa");
+            return None;
+        }
+        if !store.is_unlocked().await {
+            log::info!("Se omite la sincronización automática: la bóveda está bloqueada");
+            return None;
+        }
+        let Ok(_round) = self.sync_round.try_lock() else {
+            log::info!("Se omite la sincronización automática: ya hay una en curso");
+            return None;
+        };
+        if let Err(e) = self.check_network().await {
+            log::info!("Se omite la sincronización automática: {}", e);
+            return None;
+        }
+        Some(self.sync_available_devices().await)
+    }
+
+    /// Comprueba que la red actual permita sincronizar y deja en el estado el
+    /// motivo si no lo permite
+    async fn check_network(&self) -> Result<()> {
+        let config = self.config.read().await.clone();
+        let restriction = check_current_network(&config).await;
+        self.status.write().await.network_restriction = restriction.clone();
+        match restriction {
+            Some(restriction) => Err(restriction.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Sincroniza con un dispositivo si la red actual lo permite
+    async fn sync_device(&self, device_id: DeviceId) -> Result<SyncResult> {
+        self.check_network().await?;
+        self.try_sync_device(device_id).await
+    }
+
+    /// Sincroniza con un dispositivo. Si falla programa un reintento y si sale
+    /// bien olvida los que hubiera.
+    async fn try_sync_device(&self, device_id: DeviceId) -> Result<SyncResult> {
+        let result = self.exchange_with_device(device_id).await;
+        match &result {
+            Ok(_) => self.clear_retries(device_id).await,
+            Err(e) => self.schedule_retry(device_id, e.to_string()).await,
+        }
+        result
+    }
+
+    /// Programa el siguiente reintento con `device_id`, o se rinde si ya se
+    /// hicieron todos
+    async fn schedule_retry(&self, device_id: DeviceId, error: String) {
+        let attempt = self.retries.read().await.get(&device_id)
+            .filter(|retry| !retry.gave_up())
+            .map_or(1, |retry| retry.attempt + 1);
+        let max_attempts = self.retry_policy.max_attempts;
+        if attempt > max_attempts {
+            log::warn!("Sincronización con {} abandonada tras {} reintentos: {}", device_id, max_attempts, error);
+            if let Some(device) = self.connected_devices.write().await.get_mut(&device_id) {
+                device.update_status(crate::sync::DeviceStatus::Error(error.clone()));
+            }
+            self.retries.write().await.insert(device_id, SyncRetry {
+                device_id,
+                attempt: max_attempts,
+                max_attempts,
+                next_retry_at: None,
+                last_error: error,
+            });
+            return;
+        }
+
+        let delay = self.retry_policy.delay(attempt);
+        let next_retry_at = chrono::Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+        self.retries.write().await.insert(device_id, SyncRetry {
+            device_id,
+            attempt,
+            max_attempts,
+            next_retry_at: Some(next_retry_at),
+            last_error: error,
+        });
+        if let Some(device) = self.connected_devices.write().await.get_mut(&device_id) {
+            device.update_status(crate::sync::DeviceStatus::Waiting);
+        }
+        log::info!("Reintento {}/{} con {} en {}s", attempt, max_attempts, device_id, delay.as_secs());
+
+        let task = tokio::spawn(self.clone().retry_after(device_id, delay));
+        if let Some(previous) = self.retry_tasks.lock().await.insert(device_id, task) {
+            previous.abort();
+        }
+    }
+
+    /// Espera `delay` y vuelve a sincronizar. Va en una caja porque el
+    /// reintento puede programar otro.
+    fn retry_after(self, device_id: DeviceId, delay: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            // Desde aquí la tarea ya no se aborta al programar el siguiente
+            self.retry_tasks.lock().await.remove(&device_id);
+            if !self.trusted_devices.read().await.contains_key(&device_id) || self.config.read().await.paused {
+                self.retries.write().await.remove(&device_id);
+                return;
+            }
+            // En una red no permitida se deja de reintentar hasta la siguiente
+            // sincronización
+            if let Err(e) = self.check_network().await {
+                log::info!("Se cancela el reintento con {}: {}", device_id, e);
+                self.retries.write().await.remove(&device_id);
+                return;
+            }
+            if let Err(e) = self.try_sync_device(device_id).await {
+                log::warn!("Falló el reintento con {}: {}", device_id, e);
+            }
+        })
+    }
+
+    /// Olvida los reintentos con `device_id` y cancela el que esté esperando
+    async fn clear_retries(&self, device_id: DeviceId) {
+        self.retries.write().await.remove(&device_id);
+        if let Some(task) = self.retry_tasks.lock().await.remove(&device_id) {
+            task.abort();
+        }
+    }
+
+    /// Intercambia los cambios con un dispositivo por su conexión y avisa del
+    /// resultado con eventos, de los que salen las estadísticas
+    async fn exchange_with_device(&self, device_id: DeviceId) -> Result<SyncResult> {
+        log::info!("Sincronizando con dispositivo: {}", device_id);
+        let store = self.store.as_ref()
+            .ok_or_else(|| anyhow!("No hay una bóveda que sincronizar"))?;
+        let transport = match self.transports.read().await.get(&device_id).cloned() {
+            Some(transport) => transport,
+            None => self.open_connection(device_id).await?,
+        };
+
+        let device = self.connected_devices.read().await.get(&device_id).cloned();
+        if let Some(device) = &device {
+            let _ = self.event_sender.send(SyncEvent::SyncStarted(device.clone())).await;
+        }
+        let result = self.smart_sync.sync_with_device(device_id, store.as_ref(), transport.as_ref()).await;
+        if let Some(device) = device {
+            let event = match &result {
+                Ok(result) => SyncEvent::SyncCompleted(device, result.elements_synced),
+                Err(e) => SyncEvent::SyncFailed(device, e.to_string()),
+            };
+            let _ = self.event_sender.send(event).await;
+        }
+        result
+    }
+
+    /// Sincroniza con todos, esperando a la sincronización con todos que esté
+    /// en curso. Falla si la red actual no lo permite.
+    async fn sync_devices(&self) -> Result<Vec<SyncResult>> {
+        let _round = self.sync_round.lock().await;
+        self.check_network().await?;
+        Ok(self.sync_available_devices().await)
+    }
+
+    /// Sincroniza con los dispositivos conectados de confianza que estén
+    /// disponibles y, según el método configurado, por el buzón en la nube con
+    /// los de confianza que no se pudieron sincronizar directamente; los
+    /// errores quedan en el resultado de cada dispositivo
+    async fn sync_available_devices(&self) -> Vec<SyncResult> {
+        let method = self.config.read().await.method;
+        let trusted = self.trusted_devices.read().await.clone();
+        let mut results = Vec::new();
+        if method.uses_p2p() {
+            results.extend(self.sync_connected_devices(&trusted).await);
+        }
+        if method.uses_cloud() {
+            let synced: HashSet<DeviceId> = results.iter()
+                .filter(|result| result.success)
+                .map(|result| result.device_id)
+                .collect();
+            let pending: Vec<DeviceId> = trusted.keys().copied()
+                .filter(|device_id| !synced.contains(device_id))
+                .collect();
+            // Por la nube se reemplaza el fallo directo con ese dispositivo
+            let cloud_results = self.sync_through_mailbox(&pending).await;
+            let cloud_devices: HashSet<DeviceId> = cloud_results.iter().map(|result| result.device_id).collect();
+            results.retain(|result| !cloud_devices.contains(&result.device_id));
+            results.extend(cloud_results);
+        }
+        results
+    }
+
+    /// Sincroniza directamente con los dispositivos de confianza a los que se
+    /// llega: los conectados que estén disponibles, los que se ven en la red
+    /// local y, si no se sincroniza por la nube, el resto por el relay
+    async fn sync_connected_devices(&self, trusted: &HashMap<DeviceId, Option<PublicIdentity>>) -> Vec<SyncResult> {
+        let devices: Vec<DeviceInfo> = self.connected_devices.read().await.values().cloned().collect();
+        let mut candidates = Vec::new();
+        for device in devices.into_iter().filter(|device| device.is_available_for_sync()) {
+            if !trusted.contains_key(&device.id) {
+                log::warn!("Se omite {} ({}): no es de confianza", device.name, device.id);
+                continue;
+            }
+            candidates.push(device.id);
+        }
+        if let Some(discovery) = self.discovery.lock().await.as_ref() {
+            for device in discovery.get_discovered_devices().await {
+                if trusted.contains_key(&device.id) && !Self::lan_addresses(&device).is_empty() && !candidates.contains(&device.id) {
+                    candidates.push(device.id);
+                }
+            }
+        }
+        // Si no, los que no se ven esperarían al relay para nada: el buzón los
+        // sincroniza después
+        let through_relay = !self.config.read().await.method.uses_cloud() && self.relay_signaling.read().await.is_some();
+        if through_relay {
+            let hidden: Vec<DeviceId> = trusted.keys().copied()
+                .filter(|device_id| !candidates.contains(device_id))
+                .collect();
+            candidates.extend(hidden);
+        }
+
+        let mut results = Vec::new();
+        for device_id in candidates {
+            let result = match self.try_sync_device(device_id).await {
+                Ok(result) => result,
+                Err(e) => SyncResult::failure(device_id, e.to_string()),
+            };
+            if result.success {
+                if let Some(device) = self.connected_devices.write().await.get_mut(&device_id) {
+                    device.mark_synced();
+                }
+            }
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Sincroniza con `devices` por el buzón en la nube. Sin buzón configurado
+    /// no hace nada.
+    async fn sync_through_mailbox(&self, devices: &[DeviceId]) -> Vec<SyncResult> {
+        let Some(store) = self.store.as_ref() else {
+            return Vec::new();
+        };
+        let Some(mailbox) = self.mailbox.read().await.clone() else {
+            if !devices.is_empty() {
+                log::info!("No hay un buzón en la nube configurado para {} dispositivos", devices.len());
+            }
+            return Vec::new();
+        };
+
+        let mut results = Vec::new();
+        for &device_id in devices {
+            let device = self.connected_devices.read().await.get(&device_id).cloned();
+            if let Some(device) = &device {
+                let _ = self.event_sender.send(SyncEvent::SyncStarted(device.clone())).await;
+            }
+            let result = self.smart_sync.sync_through_mailbox(device_id, store.as_ref(), mailbox.as_ref()).await;
+            if let Some(device) = device {
+                let event = match &result {
+                    Ok(result) => SyncEvent::SyncCompleted(device, result.elements_synced),
+                    Err(e) => SyncEvent::SyncFailed(device, e.to_string()),
+                };
+                let _ = self.event_sender.send(event).await;
+            }
+            results.push(result.unwrap_or_else(|e| {
+                log::warn!("Falló la sincronización con {} por el buzón en la nube: {}", device_id, e);
+                SyncResult::failure(device_id, e.to_string())
+            }));
+        }
+        results
+    }
+}
+
+#[async_trait]
+impl OfferHandler for SyncContext {
+    /// Acepta la conexión de un dispositivo de confianza con las mismas
+    /// condiciones que una sincronización entrante
+    async fn answer_offer(&self, from: DeviceId, to: DeviceId, sdp: String, trickle: Option<Trickle>) -> Result<String> {
+        self.check_incoming(to).await?;
+        let peer = self.pinned_identity(from).await?;
+        let device = self.find_device(from).await
+            .unwrap_or_else(|| DeviceInfo::offline(from, from.to_string()));
+
+        let mut connection = P2PConnection::new(self.p2p_config().await, self.event_sender.clone())
+            .with_identity(self.identity().await?, peer)
+            .with_responder(Arc::new(NoiseResponder::new(Arc::new(self.clone()))));
+        let answer = connection.accept(device, sdp, trickle).await?;
+        let transport = ReconnectingConnection::new(from, connection, Arc::new(self.clone()));
+        self.transports.write().await.insert(from, Arc::new(transport));
+        Ok(answer)
+    }
+
+    /// Lo mismo que se anuncia por mDNS, con el puerto TLS si se atiende
+    async fn describe(&self) -> Result<DeviceInfo> {
+        if !self.config.read().await.allow_incoming_connections {
+            return Err(anyhow!("No se aceptan conexiones entrantes"));
+        }
+        let store = self.store.as_ref()
+            .ok_or_else(|| anyhow!("No hay una bóveda que sincronizar"))?;
+        let local = DiscoveryConfig::default().with_profile(&*self.device_profile.read().await);
+        let mut device = DeviceInfo::new(local.device_name, local.device_type, local.os, local.os_version, local.app_version);
+        device.id = store.local_device_id().await?;
+        let tls_port = tls_port(&self.tls_server).await;
+        if tls_port != 0 {
+            device.metadata.insert(TLS_PORT_PROPERTY.to_string(), tls_port.to_string());
+        }
+        Ok(device)
+    }
+}
+
+#[async_trait]
+impl NoisePeer for SyncContext {
+    async fn static_secret(&self) -> Result<StaticSecret> {
+        Ok(self.identity().await?.static_secret().clone())
+    }
+
+    async fn verify_static_key(&self, from: DeviceId, static_key: &[u8; 32]) -> Result<()> {
+        let pinned = self.pinned_identity(from).await?;
+        if pinned.static_key != *static_key {
+            log::warn!("Saludo rechazado: {} cambió de clave estática ({})", from, hex::encode(static_key));
+            return Err(anyhow!("El dispositivo presentó otra identidad que la emparejada"));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TlsHandler for SyncContext {
+    async fn local_identity(&self) -> Result<DeviceIdentity> {
+        self.identity().await
+    }
+
+    /// Con las mismas condiciones que una oferta WebRTC
+    async fn accept_peer(&self, from: DeviceId, to: DeviceId, presented: &[u8; 32]) -> Result<()> {
+        self.check_incoming(to).await?;
+        self.verify_peer(from, presented).await
+    }
+}
+
+#[async_trait]
+impl Redial for SyncContext {
+    /// Desde este lado, aunque la conexión caída la haya abierto el otro
+    async fn redial(&self, device_id: DeviceId) -> Result<P2PConnection> {
+        self.dial(device_id).await
+    }
+}
+
+#[async_trait]
+impl SyncResponder for SyncContext {
+    /// Responde al lote de un dispositivo de confianza, llegue por la conexión
+    /// que abrió él o por la que abrió este dispositivo
+    async fn respond(&self, from: DeviceId, request: &[u8]) -> Result<Vec<u8>> {
+        if !self.trusted_devices.read().await.contains_key(&from) {
+            return Err(anyhow!("El dispositivo {} no es de confianza", from));
+        }
+        // El heartbeat se responde también en pausa: la conexión sigue
+        if request == HEARTBEAT_PING {
+            return Ok(HEARTBEAT_PONG.to_vec());
+        }
+        // Una entrada suelta espera al usuario, también en pausa
+        if let Some(entry) = decode_offer(request) {
+            let device_name = self.find_device(from).await.map(|device| device.name);
+            let incoming = IncomingEntry::new(from, device_name, entry?);
+            log::info!("{} mandó la entrada {:?}", from, incoming.title);
+            self.entry_inbox.lock().await.receive(incoming)?;
+            return Ok(ENTRY_RECEIVED.to_vec());
+        }
+        let config = self.config.read().await.clone();
+        if config.paused {
+            return Err(anyhow!("La sincronización está en pausa"));
+        }
+        if !config.method.uses_p2p() {
+            return Err(anyhow!("La sincronización directa está desactivada ({:?})", config.method));
+        }
+        self.check_network().await?;
+        let store = self.store.as_ref()
+            .ok_or_else(|| anyhow!("No hay una bóveda que sincronizar"))?;
+        self.smart_sync.handle_sync_request(from, request, store.as_ref()).await
+    }
+}
+
+/// Puerto del servidor de señalización; 0 si no está escuchando
+async fn signaling_port(server: &Mutex<Option<SignalingServer>>) -> u16 {
+    server.lock().await.as_ref().map_or(0, SignalingServer::port)
+}
+
+/// Puerto de las conexiones TLS directas; 0 si no está escuchando
+async fn tls_port(server: &Mutex<Option<TlsServer>>) -> u16 {
+    server.lock().await.as_ref().map_or(0, TlsServer::port)
+}
+
+/// Id de este dispositivo; `None` si no hay bóveda
+async fn local_device(store: &Option<Arc<dyn SyncStore>>) -> Option<DeviceId> {
+    store.as_ref()?.local_device_id().await.ok()
+}
+
+/// Pone en marcha el descubrimiento de dispositivos, anunciando el id
+/// `device_id` con el nombre y el tipo de `profile`, `port` para recibir
+/// ofertas y `tls_port` para las conexiones TLS, y lo deja en `discovery`
+async fn launch_discovery(
+    discovery: &Mutex<Option<DeviceDiscovery>>,
+    event_sender: mpsc::Sender<SyncEvent>,
+    device_id: Option<DeviceId>,
+    profile: &DeviceProfile,
+    port: u16,
+    tls_port: u16,
+) -> Result<()> {
+    log::info!("Inicializando sistema de descubrimiento...");
+
+    let config = DiscoveryConfig { device_id, port, tls_port, ..Default::default() }.with_profile(profile);
+    let mut started = DeviceDiscovery::new(config, event_sender);
+    started.start().await?;
+    *discovery.lock().await = Some(started);
+
+    log::info!("Sistema de descubrimiento inicializado correctamente");
+    Ok(())
+}
+
+/// `profile` completado con el nombre y el tipo del sistema
+async fn presented_profile(profile: &RwLock<DeviceProfile>) -> DeviceProfile {
+    let local = DiscoveryConfig::default().with_profile(&*profile.read().await);
+    DeviceProfile { name: Some(local.device_name), device_type: Some(local.device_type) }
+}
+
+/// Une los dispositivos conectados con los descubiertos, sin repetir los que
+/// están en las dos listas: primero los conectados y después el resto, cada
+/// grupo por nombre
+fn merge_devices(connected: Vec<DeviceInfo>, discovered: Vec<DeviceInfo>) -> Vec<DeviceInfo> {
+    let mut devices: HashMap<DeviceId, DeviceInfo> = connected.into_iter()
+        .map(|device| (device.id, device))
+        .collect();
+    let connected_ids: HashSet<DeviceId> = devices.keys().copied().collect();
+    for device in discovered {
+        match devices.get_mut(&device.id) {
+            Some(connected) => connected.merge_discovered(&device),
+            None => {
+                devices.insert(device.id, device);
+            }
+        }
+    }
+
+    let mut devices: Vec<DeviceInfo> = devices.into_values().collect();
+    devices.sort_by(|a, b| {
+        connected_ids.contains(&b.id).cmp(&connected_ids.contains(&a.id))
+            .then_with(|| DeviceNameComparator::compare(a, b))
+    });
+    devices
+}
+
+/// Manejador de eventos para el descubrimiento
+struct DiscoveryEventHandler {
+    event_sender: mpsc::Sender<SyncEvent>,
+}
+
+#[async_trait]
+impl SyncEventHandler for DiscoveryEventHandler {
+    fn handle_event(&self, event: &SyncEvent) {
+        // Reenviar evento al gestor principal
+        let event_clone = event.clone();
+        let event_sender = self.event_sender.clone();
         
-        if should_stop {
-            let _ = tokio::runtime::Handle::current().block_on(self.stop());
+        tokio::spawn(async move {
+            if let Err(e) = event_sender.send(event_clone).await {
+                log::error!("Error al reenviar evento: {}", e);
+            }
+        });
+    }
+}
+
+/// Información del sistema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemInfo {
+    /// Si el sistema está ejecutándose
+    pub is_running: bool,
+    /// Si el sistema está habilitado
+    pub is_enabled: bool,
+    /// Método de sincronización actual
+    pub sync_method: SyncMethod,
+    /// Sincronización automática habilitada
+    pub auto_sync: bool,
+    /// Descubrimiento automático habilitado
+    pub auto_discovery: bool,
+    /// Número de dispositivos conectados
+    pub connected_devices: usize,
+    /// Número de dispositivos descubiertos
+    pub discovered_devices: usize,
+    /// Total de sincronizaciones
+    pub total_syncs: u64,
+    /// Sincronizaciones exitosas
+    pub successful_syncs: u64,
+    /// Sincronizaciones fallidas
+    pub failed_syncs: u64,
+    /// Última sincronización
+    pub last_sync: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Default for SystemInfo {
+    fn default() -> Self {
+        Self {
+            is_running: false,
+            is_enabled: false,
+            sync_method: SyncMethod::Hybrid,
+            auto_sync: true,
+            auto_discovery: true,
+            connected_devices: 0,
+            discovered_devices: 0,
+            total_syncs: 0,
+            successful_syncs: 0,
+            failed_syncs: 0,
+            last_sync: None,
+        }
+    }
+}
+
+/// Implementar Drop para limpiar recursos
+impl Drop for SyncManager {
+    fn drop(&mut self) {
+        // Aquí no se puede esperar a `stop`: basta con abortar las tareas
+        let tasks = [
+            self.manager_task.get_mut().take(),
+            self.cleanup_task.get_mut().take(),
+            self.auto_sync_task.get_mut().take(),
+            self.relay_signaling_task.get_mut().take(),
+            self.heartbeat_task.get_mut().take(),
+        ];
+        for task in tasks.into_iter().flatten() {
+            task.abort();
         }
     }
 }
@@ -585,6 +2007,9 @@ impl Drop for SyncManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sync::smart_sync::{AppliedChanges, ConflictResolutionStrategy, SyncBatch};
+    use crate::models::{EncryptionLevel, SyncBootstrap, VersionVector};
+    use crate::sync::DeviceType;
 
     #[tokio::test]
     async fn test_sync_manager_creation() {
@@ -603,6 +2028,455 @@ mod tests {
         assert_eq!(manager.get_connected_devices().await.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_sync_manager_restart() {
+        let manager = SyncManager::new(SyncConfig { auto_discovery: false, ..SyncConfig::default() });
+
+        manager.start().await.unwrap();
+        assert!(manager.is_running().await);
+        manager.stop().await.unwrap();
+        assert!(!manager.is_running().await);
+        assert!(!manager.get_status().await.is_enabled);
+
+        // El receptor de eventos vuelve a estar libre tras detener el gestor
+        manager.start().await.unwrap();
+        assert!(manager.is_running().await);
+
+        // La configuración nueva se aplica con el gestor en marcha
+        manager.update_config(SyncConfig { sync_interval: 5, auto_discovery: false, ..SyncConfig::default() }).await.unwrap();
+        assert_eq!(manager.get_config().await.sync_interval, 5);
+        assert!(manager.auto_sync_task.lock().await.is_some());
+        manager.stop().await.unwrap();
+        assert!(manager.auto_sync_task.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pairing_requests() {
+        let manager = SyncManager::new_default();
+        let peer = DeviceId::new();
+        let (_, commit) = PairingSession::initiate(DeviceIdentity::generate().unwrap().public());
+        // Sin identidad cargada no se puede emparejar
+        assert!(manager.handle_pairing_message(peer, commit.clone()).await.is_err());
+        manager.set_identity(Some(DeviceIdentity::generate().unwrap())).await;
+        assert!(manager.begin_pairing(peer).await.is_err());
+
+        manager.handle_pairing_message(peer, commit).await.unwrap();
+        let pairings = manager.get_pairings().await;
+        assert_eq!(pairings.len(), 1);
+        assert!(pairings[0].code.is_none());
+
+        // Sin código no se puede aceptar, pero sí rechazar
+        assert!(manager.confirm_pairing(peer, true).await.is_err());
+        assert_eq!(manager.confirm_pairing(peer, false).await.unwrap(), None);
+        assert!(manager.get_pairings().await.is_empty());
+
+        // Por código QR la clave llega verificada y el código sirve una vez
+        let invite = manager.create_pairing_invite(DeviceId::new()).await.unwrap();
+        assert_eq!(invite.identity, manager.identity().await.unwrap().public());
+        let joiner_identity = DeviceIdentity::generate().unwrap().public();
+        let (joiner, join) = PairingSession::join(&invite, joiner_identity).unwrap();
+        manager.handle_pairing_message(peer, join.clone()).await.unwrap();
+        assert!(manager.get_pairings().await[0].verified);
+        assert!(manager.handle_pairing_message(DeviceId::new(), join).await.is_err());
+        let paired = manager.confirm_pairing(peer, true).await.unwrap().unwrap();
+        assert_eq!(paired.sync_key, joiner.confirm().unwrap().sync_key);
+        assert_eq!(paired.peer_fingerprint, hex::encode(joiner_identity.fingerprint));
+        assert_eq!(paired.peer_static_key, hex::encode(joiner_identity.static_key));
+    }
+
+    #[tokio::test]
+    async fn test_paused_sync_refuses_incoming_requests() {
+        let manager = SyncManager::new(SyncConfig { paused: true, ..SyncConfig::default() })
+            .with_store(Arc::new(EmptyStore(DeviceId::new())));
+        let peer = DeviceId::new();
+        manager.set_trusted_devices([(peer, None)]).await;
+        assert!(manager.get_status().await.is_paused);
+        assert!(manager.sync_context().scheduled_sync().await.is_none());
+        let request = EmptyPeer(peer).exchange(Vec::new()).await.unwrap();
+        assert!(manager.handle_sync_request(peer, &request).await.is_err());
+
+        manager.set_paused(false).await;
+        assert!(!manager.get_status().await.is_paused);
+        assert!(manager.sync_context().scheduled_sync().await.is_some());
+        assert!(manager.handle_sync_request(peer, &request).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_device_limit() {
+        let manager = SyncManager::new(SyncConfig { max_devices: 1, ..SyncConfig::default() });
+        manager.set_identity(Some(DeviceIdentity::generate().unwrap())).await;
+        assert!(manager.check_device_limit(None).await.is_ok());
+
+        let laptop = DeviceId::new();
+        manager.set_trusted_devices([(laptop, None)]).await;
+        assert!(manager.check_device_limit(Some(laptop)).await.is_ok());
+        let error = manager.check_device_limit(Some(DeviceId::new())).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<DeviceLimitReached>(), Some(&DeviceLimitReached { max_devices: 1 }));
+        assert!(manager.create_pairing_invite(DeviceId::new()).await.is_err());
+
+        // Un dispositivo nuevo no puede pedir emparejarse hasta que se quite otro
+        let (_, commit) = PairingSession::initiate(DeviceIdentity::generate().unwrap().public());
+        let phone = DeviceId::new();
+        assert!(manager.handle_pairing_message(phone, commit.clone()).await.is_err());
+        manager.set_trusted_devices(Vec::new()).await;
+        manager.handle_pairing_message(phone, commit).await.unwrap();
+        assert_eq!(manager.get_pairings().await.len(), 1);
+    }
+
+    /// Bóveda sin cambios
+    struct EmptyStore(DeviceId);
+
+    #[async_trait]
+    impl SyncStore for EmptyStore {
+        async fn local_device_id(&self) -> Result<DeviceId> {
+            Ok(self.0)
+        }
+
+        async fn sync_key(&self, _device_id: DeviceId) -> Result<[u8; 32]> {
+            Ok([7; 32])
+        }
+
+        async fn knowledge(&self) -> Result<VersionVector> {
+            Ok(VersionVector::new())
+        }
+
+        async fn load_changes_since(&self, _known: &VersionVector) -> Result<Vec<DataChange>> {
+            Ok(Vec::new())
+        }
+
+        async fn apply_changes(&self, changes: Vec<DataChange>, _strategy: &ConflictResolutionStrategy) -> Result<AppliedChanges> {
+            Ok(AppliedChanges { applied: changes.len(), conflicts: Vec::new() })
+        }
+
+        async fn peer_knowledge(&self, _device_id: DeviceId) -> Result<VersionVector> {
+            Ok(VersionVector::new())
+        }
+
+        async fn save_peer_knowledge(&self, _device_id: DeviceId, _knowledge: VersionVector) -> Result<()> {
+            Ok(())
+        }
+
+        async fn bootstrap(&self, _device_id: DeviceId) -> Result<Option<SyncBootstrap>> {
+            Ok(None)
+        }
+
+        async fn save_bootstrap(&self, _device_id: DeviceId, _bootstrap: Option<SyncBootstrap>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Dispositivo remoto que responde siempre con un lote vacío
+    struct EmptyPeer(DeviceId);
+
+    #[async_trait]
+    impl SyncTransport for EmptyPeer {
+        async fn exchange(&self, _payload: Vec<u8>) -> Result<Vec<u8>> {
+            let batch = SyncBatch { source_device: self.0, knowledge: VersionVector::new(), changes: Vec::new(), compression: Vec::new(), snapshot: None, app_version: None, capabilities: None, acknowledged_deletions: Vec::new() };
+            batch.seal(&[7; 32], false, EncryptionLevel::Standard)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refuses_untrusted_devices() {
+        let manager = SyncManager::new_default().with_store(Arc::new(EmptyStore(DeviceId::new())));
+        let device = DeviceId::new();
+        assert!(manager.sync_with_device(device).await.is_err());
+
+        manager.set_trusted_devices([(device, None)]).await;
+        assert!(manager.is_trusted(device).await);
+        // Sin conexión abierta no hay con qué sincronizar
+        assert!(manager.sync_with_device(device).await.is_err());
+        manager.set_transport(device, Arc::new(EmptyPeer(device))).await;
+        let result = manager.sync_with_device(device).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.elements_synced, 0);
+        assert!(result.data_size > 0);
+
+        manager.set_trusted_devices(Vec::new()).await;
+        assert!(!manager.is_trusted(device).await);
+        assert!(manager.sync_with_device(device).await.is_err());
+        assert!(manager.handle_sync_request(device, &[]).await.is_err());
+    }
+
+    /// Dispositivo remoto que falla las primeras veces y después responde
+    /// con un lote vacío
+    struct FlakyPeer {
+        device: DeviceId,
+        failures: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait]
+    impl SyncTransport for FlakyPeer {
+        async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+            use std::sync::atomic::Ordering;
+            if self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok() {
+                return Err(anyhow!("Conexión perdida"));
+            }
+            EmptyPeer(self.device).exchange(payload).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_syncs_with_backoff() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(20),
+            max_attempts: 2,
+        };
+        let manager = SyncManager::new_default()
+            .with_store(Arc::new(EmptyStore(DeviceId::new())))
+            .with_retry_policy(policy);
+        let device = DeviceId::new();
+        manager.set_trusted_devices([(device, None)]).await;
+        let flaky = |failures| Arc::new(FlakyPeer { device, failures: std::sync::atomic::AtomicU32::new(failures) });
+
+        // Falla dos veces: el segundo reintento sale bien y no queda nada pendiente
+        manager.set_transport(device, flaky(2)).await;
+        assert!(manager.sync_with_device(device).await.is_err());
+        let retries = manager.get_status().await.retries;
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].attempt, 1);
+        assert!(!retries[0].gave_up());
+        assert!(retries[0].last_error.contains("Conexión perdida"));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(manager.get_retries().await.is_empty());
+
+        // Si sigue fallando se rinde tras los reintentos permitidos
+        manager.set_transport(device, flaky(10)).await;
+        assert!(manager.sync_with_device(device).await.is_err());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let retries = manager.get_retries().await;
+        assert_eq!(retries.len(), 1);
+        assert!(retries[0].gave_up());
+        assert_eq!(retries[0].attempt, 2);
+
+        // Una sincronización que sale bien olvida los reintentos
+        manager.set_transport(device, flaky(0)).await;
+        assert!(manager.sync_with_device(device).await.is_ok());
+        assert!(manager.get_retries().await.is_empty());
+    }
+
+    /// Conexión con otro gestor en el mismo proceso, que responde como si le
+    /// llegara por la red
+    struct Loopback {
+        from: DeviceId,
+        peer: SyncManager,
+    }
+
+    #[async_trait]
+    impl SyncTransport for Loopback {
+        async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+            self.peer.handle_sync_request(self.from, &payload).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_track_connected_devices() {
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        let manager = SyncManager::new_default().with_store(Arc::new(EmptyStore(laptop)));
+        manager.start_manager_task().await.unwrap();
+        let mut device = DeviceInfo::new("Teléfono".to_string(), DeviceType::Mobile, "Android".to_string(), "14".to_string(), "1.0.0".to_string());
+        device.id = phone;
+        manager.manual_devices.write().await.insert(phone, device);
+
+        // Responde aunque esté en pausa: pasa a estar conectado
+        let remote = SyncManager::new(SyncConfig { paused: true, ..SyncConfig::default() })
+            .with_store(Arc::new(EmptyStore(phone)));
+        remote.set_trusted_devices([(laptop, None)]).await;
+        manager.set_transport(phone, Arc::new(Loopback { from: laptop, peer: remote })).await;
+        let context = manager.sync_context();
+        let mut missed = HashMap::new();
+        context.send_heartbeats(&mut missed).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let connected = manager.get_connected_devices().await;
+        assert_eq!(connected.len(), 1);
+        assert_eq!(connected[0].status, crate::sync::DeviceStatus::Connected);
+
+        // Un lote no es la respuesta al heartbeat: a la tercera se desconecta
+        manager.set_transport(phone, Arc::new(EmptyPeer(phone))).await;
+        for _ in 1..MAX_MISSED_HEARTBEATS {
+            context.send_heartbeats(&mut missed).await;
+        }
+        assert_eq!(missed.get(&phone), Some(&(MAX_MISSED_HEARTBEATS - 1)));
+        assert!(manager.transports.read().await.contains_key(&phone));
+        context.send_heartbeats(&mut missed).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(missed.is_empty());
+        assert!(!manager.transports.read().await.contains_key(&phone));
+        assert!(manager.get_connected_devices().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sends_a_single_entry_for_the_user_to_accept() {
+        let (laptop, phone) = (DeviceId::new(), DeviceId::new());
+        let manager = SyncManager::new_default().with_store(Arc::new(EmptyStore(laptop)));
+        let entry = PasswordEntry::sample("Banco");
+
+        // Solo a dispositivos de confianza
+        assert!(manager.send_entry(phone, &entry).await.is_err());
+
+        // Llega aunque el otro esté en pausa, y espera a su usuario
+        let remote = SyncManager::new(SyncConfig { paused: true, ..SyncConfig::default() })
+            .with_store(Arc::new(EmptyStore(phone)));
+        remote.set_trusted_devices([(laptop, None)]).await;
+        let inbox = remote.entry_inbox.clone();
+        manager.set_trusted_devices([(phone, None)]).await;
+        manager.set_transport(phone, Arc::new(Loopback { from: laptop, peer: remote })).await;
+        manager.send_entry(phone, &entry).await.unwrap();
+        let pending = inbox.lock().await.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].from, pending[0].title.as_str()), (laptop, "Banco"));
+        assert_eq!(inbox.lock().await.take(pending[0].id).unwrap().entry.password, "secreta");
+
+        // Lo que no es la confirmación de la entrada es un error
+        manager.set_transport(phone, Arc::new(EmptyPeer(phone))).await;
+        assert!(manager.send_entry(phone, &entry).await.is_err());
+    }
+
+    /// Bóveda bloqueada: sin claves no se puede leer nada
+    struct LockedStore;
+
+    #[async_trait]
+    impl SyncStore for LockedStore {
+        async fn local_device_id(&self) -> Result<DeviceId> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn sync_key(&self, _device_id: DeviceId) -> Result<[u8; 32]> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn knowledge(&self) -> Result<VersionVector> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn load_changes_since(&self, _known: &VersionVector) -> Result<Vec<DataChange>> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn apply_changes(&self, _changes: Vec<DataChange>, _strategy: &ConflictResolutionStrategy) -> Result<AppliedChanges> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn peer_knowledge(&self, _device_id: DeviceId) -> Result<VersionVector> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn save_peer_knowledge(&self, _device_id: DeviceId, _knowledge: VersionVector) -> Result<()> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn bootstrap(&self, _device_id: DeviceId) -> Result<Option<SyncBootstrap>> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn save_bootstrap(&self, _device_id: DeviceId, _bootstrap: Option<SyncBootstrap>) -> Result<()> {
+            Err(anyhow!("La bóveda está bloqueada"))
+        }
+
+        async fn is_unlocked(&self) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_sync_waits_for_the_vault_and_manual_syncs() {
+        // Sin bóveda o con ella bloqueada no hay sincronización automática
+        assert!(SyncManager::new_default().sync_context().scheduled_sync().await.is_none());
+        let locked = SyncManager::new_default().with_store(Arc::new(LockedStore));
+        assert!(locked.sync_context().scheduled_sync().await.is_none());
+
+        // Ni mientras hay otra sincronización con todos en curso
+        let manager = SyncManager::new_default().with_store(Arc::new(EmptyStore(DeviceId::new())));
+        let round = manager.sync_round.lock().await;
+        assert!(manager.sync_context().scheduled_sync().await.is_none());
+        drop(round);
+        assert!(manager.sync_context().scheduled_sync().await.is_some());
+        assert!(manager.sync_all_devices().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_verifies_pinned_identities() {
+        let manager = SyncManager::new_default();
+        let (paired, unpaired) = (DeviceId::new(), DeviceId::new());
+        let identity = DeviceIdentity::generate().unwrap().public();
+        manager.set_trusted_devices([(paired, Some(identity)), (unpaired, None)]).await;
+
+        assert!(manager.verify_peer(paired, &identity.fingerprint).await.is_ok());
+        assert!(manager.verify_peer(paired, &[0; 32]).await.is_err());
+        assert!(manager.verify_peer(unpaired, &identity.fingerprint).await.is_err());
+        assert!(manager.verify_peer(DeviceId::new(), &identity.fingerprint).await.is_err());
+
+        let context = manager.sync_context();
+        assert!(context.verify_static_key(paired, &identity.static_key).await.is_ok());
+        assert!(context.verify_static_key(paired, &identity.fingerprint).await.is_err());
+        assert!(context.verify_static_key(unpaired, &identity.static_key).await.is_err());
+    }
+
+    #[test]
+    fn test_merge_devices() {
+        let device = |name: &str| DeviceInfo::from_network(
+            name.to_string(), DeviceType::Laptop, "linux".to_string(), "6".to_string(), "1.0.0".to_string(),
+            "192.168.1.20".to_string(), 4000,
+        );
+        let mut connected = device("portátil");
+        connected.ip_address = None;
+        connected.port = None;
+        connected.last_seen = None;
+        let mut announced = connected.clone();
+        announced.ip_address = Some("192.168.1.30".to_string());
+        announced.port = Some(4100);
+        announced.last_seen = Some(chrono::Utc::now());
+
+        let devices = merge_devices(vec![connected.clone()], vec![device("b"), announced.clone(), device("a")]);
+        let names: Vec<_> = devices.iter().map(|device| device.name.as_str()).collect();
+        assert_eq!(names, ["portátil", "a", "b"]);
+        assert_eq!(devices[0].connection_info().as_deref(), Some("192.168.1.30:4100"));
+        assert_eq!(devices[0].last_seen, announced.last_seen);
+    }
+
+    #[test]
+    fn test_dials_every_announced_address_in_order() {
+        let mut device = DeviceInfo::from_network(
+            "portátil".to_string(), DeviceType::Laptop, "linux".to_string(), "6".to_string(), "1.0.0".to_string(),
+            "fd00::20".to_string(), 4000,
+        );
+        device.addresses = ["fd00::20", "192.168.1.20", "fe80::20%3", "::1"].map(String::from).to_vec();
+        device.metadata.insert(TLS_PORT_PROPERTY.to_string(), "4001".to_string());
+
+        let lan: Vec<String> = SyncContext::lan_addresses(&device).iter().map(ToString::to_string).collect();
+        assert_eq!(lan, ["[fd00::20]:4000", "192.168.1.20:4000", "[fe80::20%3]:4000"]);
+        assert_eq!(SyncContext::tls_addresses(&device)[1], "192.168.1.20:4001".parse().unwrap());
+        assert_eq!(device.connection_info().as_deref(), Some("[fd00::20]:4000"));
+
+        device.metadata.clear();
+        assert!(SyncContext::tls_addresses(&device).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_adds_a_device_by_its_address() {
+        let phone = DeviceId::new();
+        let remote = SyncManager::new_default().with_store(Arc::new(EmptyStore(phone)));
+        let profile = DeviceProfile { name: Some("Teléfono".to_string()), device_type: Some(DeviceType::Mobile) };
+        remote.set_device_profile(profile.clone()).await.unwrap();
+        assert_eq!(remote.device_profile().await, profile);
+        let server = SignalingServer::start(Arc::new(remote.sync_context())).await.unwrap();
+        let manager = SyncManager::new_default().with_store(Arc::new(EmptyStore(DeviceId::new())));
+
+        let device = manager.add_device_manually("127.0.0.1", server.port()).await.unwrap();
+        assert_eq!(device.id, phone);
+        assert_eq!((device.name.as_str(), &device.device_type), ("Teléfono", &DeviceType::Mobile));
+        assert_eq!(device.connection_info(), Some(format!("127.0.0.1:{}", server.port())));
+        assert!(manager.get_devices().await.iter().any(|device| device.id == phone));
+        assert_eq!(manager.sync_context().find_device(phone).await.map(|device| device.id), Some(phone));
+
+        // Ni una dirección inválida ni este mismo dispositivo
+        let own = SignalingServer::start(Arc::new(manager.sync_context())).await.unwrap();
+        assert!(manager.add_device_manually("127.0.0.1", own.port()).await.is_err());
+        assert!(manager.add_device_manually("portátil", 4000).await.is_err());
+        assert!(manager.add_device_manually("127.0.0.1", 0).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_system_info_default() {
         let info = SystemInfo::default();
@@ -0,0 +1,422 @@
+//! Conexión directa por TCP con TLS en la red local
+//!
+//! Cuando los dos dispositivos están en la misma red no hace falta WebRTC:
+//! cada uno escucha en un puerto TCP que anuncia por mDNS y el otro se conecta
+//! directo, sin ICE ni STUN ni canal de datos. Los dos se presentan con el
+//! certificado de su [`DeviceIdentity`], el mismo que usan en WebRTC, y cada
+//! uno lo compara con la huella fijada al emparejarse. No hay autoridades de
+//! certificación de por medio.
+//!
+//! Después del saludo TLS, quien llama manda su id y el de a quién llama y
+//! hace el saludo [`noise`](crate::sync::noise) con la clave estática fijada.
+//! De ahí en adelante manda un lote cifrado por vez, cada uno con su
+//! respuesta. Cada mensaje lleva delante su largo en 4 bytes; las respuestas,
+//! además, un byte que dice si lo que sigue es el lote o el error por el que
+//! no se respondió.
+
+use crate::models::DeviceId;
+use crate::sync::framing::MAX_MESSAGE_SIZE;
+use crate::sync::identity::{DeviceIdentity, PublicIdentity};
+use crate::sync::noise::{Initiator, NoisePeer, NoiseResponder, Session};
+use crate::sync::signaling::listen_on_all_interfaces;
+use crate::sync::smart_sync::{SyncResponder, SyncTransport};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::server::{ClientCertVerified, ClientCertVerifier};
+use rustls::{Certificate, CertificateError, ClientConfig, DistinguishedName, PrivateKey, ServerConfig, ServerName};
+use sha2::{Digest, Sha256};
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_rustls::{client, TlsAcceptor, TlsConnector};
+use uuid::Uuid;
+use x25519_dalek::StaticSecret;
+
+/// Propiedad TXT en la que se anuncia el puerto TLS
+pub const TLS_PORT_PROPERTY: &str = "tls_port";
+
+/// Nombre que se pide en el saludo TLS. No se comprueba: vale la huella.
+const SERVER_NAME: &str = "alohopass";
+
+/// Tiempo para conectar, saludar y que el otro acepte
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tiempo para que llegue la respuesta a un lote
+const EXCHANGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+const REPLY_OK: u8 = 0;
+const REPLY_ERROR: u8 = 1;
+
+/// Lo que necesita el servidor para atender las conexiones
+#[async_trait]
+pub trait TlsHandler: NoisePeer {
+    /// Identidad con la que se presenta este dispositivo
+    async fn local_identity(&self) -> Result<DeviceIdentity>;
+
+    /// Acepta la conexión de `from` para `to`, si `presented` es la huella
+    /// que se fijó al emparejarlo y se aceptan conexiones entrantes
+    async fn accept_peer(&self, from: DeviceId, to: DeviceId, presented: &[u8; 32]) -> Result<()>;
+}
+
+/// Acepta el certificado del que atiende solo si es el fijado
+struct PinnedCertificate([u8; 32]);
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(&end_entity.0).as_slice() != self.0 {
+            return Err(rustls::Error::InvalidCertificate(CertificateError::ApplicationVerificationFailure));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Pide certificado a quien llama y lo deja pasar: hasta el saludo no se sabe
+/// quién es, y ahí se compara con la huella fijada
+struct AnyCertificate;
+
+impl ClientCertVerifier for AnyCertificate {
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+}
+
+fn credentials(identity: &DeviceIdentity) -> Result<(Vec<Certificate>, PrivateKey)> {
+    let (certificate, key) = identity.to_der()?;
+    Ok((vec![Certificate(certificate)], PrivateKey(key)))
+}
+
+/// Conexión TLS con un dispositivo de la red local
+pub struct TlsConnection {
+    address: SocketAddr,
+    hello: Vec<u8>,
+    connector: TlsConnector,
+    static_secret: StaticSecret,
+    peer_static_key: [u8; 32],
+    stream: Mutex<Option<(client::TlsStream<TcpStream>, Session)>>,
+}
+
+impl TlsConnection {
+    /// Se conecta con `remote` en `address`. Falla si no presenta el
+    /// certificado y la clave estática de `peer` o no acepta la conexión.
+    pub async fn connect(
+        identity: &DeviceIdentity,
+        peer: &PublicIdentity,
+        local: DeviceId,
+        remote: DeviceId,
+        address: SocketAddr,
+    ) -> Result<Self> {
+        let (certificates, key) = credentials(identity)?;
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertificate(peer.fingerprint)))
+            .with_client_auth_cert(certificates, key)?;
+
+        let mut hello = local.as_uuid().as_bytes().to_vec();
+        hello.extend_from_slice(remote.as_uuid().as_bytes());
+        let connection = Self {
+            address,
+            hello,
+            connector: TlsConnector::from(Arc::new(config)),
+            static_secret: identity.static_secret().clone(),
+            peer_static_key: peer.static_key,
+            stream: Mutex::new(None),
+        };
+        *connection.stream.lock().await = Some(connection.open().await?);
+        Ok(connection)
+    }
+
+    async fn open(&self) -> Result<(client::TlsStream<TcpStream>, Session)> {
+        let server_name = ServerName::try_from(SERVER_NAME)?;
+        let handshake = async {
+            let tcp = TcpStream::connect(self.address).await?;
+            tcp.set_nodelay(true)?;
+            let mut stream = self.connector.connect(server_name, tcp).await?;
+            write_frame(&mut stream, &self.hello).await?;
+            read_reply(&mut stream).await?
+                .map_err(|e| anyhow!("{} rechazó la conexión: {}", self.address, e))?;
+
+            let (initiator, hello) = Initiator::start(&self.static_secret, self.peer_static_key)?;
+            let reply = round_trip(&mut stream, &hello).await?
+                .map_err(|e| anyhow!("{} rechazó el saludo Noise: {}", self.address, e))?;
+            let (session, finish) = initiator.finish(&reply)?;
+            round_trip(&mut stream, &finish).await?
+                .map_err(|e| anyhow!("{} rechazó el saludo Noise: {}", self.address, e))?;
+            Ok::<_, anyhow::Error>((stream, session))
+        };
+        tokio::time::timeout(CONNECT_TIMEOUT, handshake).await
+            .map_err(|_| anyhow!("{} no respondió en {} segundos", self.address, CONNECT_TIMEOUT.as_secs()))?
+    }
+}
+
+#[async_trait]
+impl SyncTransport for TlsConnection {
+    /// Si la conexión se cortó desde el último lote, se vuelve a abrir una
+    /// vez. Si el otro extremo no respondió el lote se cierra, así el próximo
+    /// empieza con otro saludo.
+    async fn exchange(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        let mut stream = self.stream.lock().await;
+        if let Some((open, session)) = stream.as_mut() {
+            match round_trip(open, &session.seal(&payload)?).await {
+                Ok(Ok(reply)) => return session.open(&reply),
+                Ok(Err(e)) => {
+                    *stream = None;
+                    return Err(anyhow!("{} no respondió el lote: {}", self.address, e));
+                }
+                Err(e) => log::warn!("Se cortó la conexión TLS con {}, se vuelve a abrir: {}", self.address, e),
+            }
+        }
+        *stream = None;
+        let (open, session) = stream.insert(self.open().await?);
+        match round_trip(open, &session.seal(&payload)?).await? {
+            Ok(reply) => session.open(&reply),
+            Err(e) => {
+                *stream = None;
+                Err(anyhow!("{} no respondió el lote: {}", self.address, e))
+            }
+        }
+    }
+}
+
+/// Manda un lote y espera su respuesta. Falla si se cortó la conexión; el
+/// error de dentro es el que mandó el otro extremo.
+async fn round_trip<S>(stream: &mut S, payload: &[u8]) -> Result<std::result::Result<Vec<u8>, String>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    write_frame(stream, payload).await?;
+    tokio::time::timeout(EXCHANGE_TIMEOUT, read_reply(stream)).await
+        .map_err(|_| anyhow!("La respuesta no llegó en {} segundos", EXCHANGE_TIMEOUT.as_secs()))?
+}
+
+/// Puerto TCP en el que este dispositivo atiende las conexiones TLS de la red
+/// local
+pub struct TlsServer {
+    port: u16,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl TlsServer {
+    /// Empieza a escuchar en un puerto libre de todas las interfaces
+    pub async fn start(handler: Arc<dyn TlsHandler>) -> Result<Self> {
+        let listener = listen_on_all_interfaces()?;
+        let port = listener.local_addr()?.port();
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::error!("Error aceptando una conexión TLS: {}", e);
+                        continue;
+                    }
+                };
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, handler).await {
+                        log::warn!("Conexión TLS con {} fallida: {}", peer, e);
+                    }
+                });
+            }
+        });
+        log::info!("Conexiones TLS escuchando en el puerto {}", port);
+        Ok(Self { port, task })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for TlsServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Atiende una conexión: los saludos y después los lotes hasta que se cierre
+async fn serve(stream: TcpStream, handler: Arc<dyn TlsHandler>) -> Result<()> {
+    stream.set_nodelay(true)?;
+    let (certificates, key) = credentials(&handler.local_identity().await?)?;
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(Arc::new(AnyCertificate))
+        .with_single_cert(certificates, key)?;
+
+    let handshake = async {
+        let mut stream = TlsAcceptor::from(Arc::new(config)).accept(stream).await?;
+        let hello = read_frame(&mut stream).await?
+            .ok_or_else(|| anyhow!("Se cerró la conexión antes del saludo"))?;
+        Ok::<_, anyhow::Error>((stream, hello))
+    };
+    let (mut stream, hello) = tokio::time::timeout(CONNECT_TIMEOUT, handshake).await
+        .map_err(|_| anyhow!("No llegó el saludo"))??;
+
+    let presented: [u8; 32] = stream.get_ref().1.peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .map(|certificate| Sha256::digest(&certificate.0).into())
+        .ok_or_else(|| anyhow!("Se conectó sin certificado"))?;
+    if hello.len() != 32 {
+        return Err(anyhow!("Saludo de {} bytes", hello.len()));
+    }
+    let from = DeviceId::from(Uuid::from_slice(&hello[..16])?);
+    let to = DeviceId::from(Uuid::from_slice(&hello[16..])?);
+    if let Err(e) = handler.accept_peer(from, to, &presented).await {
+        write_reply(&mut stream, Err(e.to_string())).await?;
+        return Err(e);
+    }
+    write_reply(&mut stream, Ok(&[])).await?;
+
+    let responder = NoiseResponder::new(handler);
+    while let Some(request) = read_frame(&mut stream).await? {
+        let reply = responder.respond(from, &request).await;
+        if let Err(e) = &reply {
+            log::warn!("No se pudo responder el lote de {}: {}", from, e);
+        }
+        write_reply(&mut stream, reply.as_deref().map_err(|e| e.to_string())).await?;
+    }
+    Ok(())
+}
+
+async fn write_frame<S: AsyncWrite + Unpin>(stream: &mut S, message: &[u8]) -> Result<()> {
+    if message.len() > MAX_MESSAGE_SIZE {
+        return Err(anyhow!("El mensaje ocupa {} bytes; el máximo es {}", message.len(), MAX_MESSAGE_SIZE));
+    }
+    stream.write_all(&(message.len() as u32).to_be_bytes()).await?;
+    stream.write_all(message).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Lee un mensaje; `None` si el otro extremo cerró entre mensajes
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match stream.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(anyhow!("El mensaje dice ocupar {} bytes; el máximo es {}", length, MAX_MESSAGE_SIZE));
+    }
+    let mut message = vec![0u8; length];
+    stream.read_exact(&mut message).await?;
+    Ok(Some(message))
+}
+
+async fn write_reply<S: AsyncWrite + Unpin>(stream: &mut S, reply: std::result::Result<&[u8], String>) -> Result<()> {
+    let mut message = Vec::new();
+    match reply {
+        Ok(body) => {
+            message.push(REPLY_OK);
+            message.extend_from_slice(body);
+        }
+        Err(error) => {
+            message.push(REPLY_ERROR);
+            message.extend_from_slice(error.as_bytes());
+        }
+    }
+    write_frame(stream, &message).await
+}
+
+async fn read_reply<S: AsyncRead + Unpin>(stream: &mut S) -> Result<std::result::Result<Vec<u8>, String>> {
+    let message = read_frame(stream).await?
+        .ok_or_else(|| anyhow!("Se cerró la conexión"))?;
+    match message.split_first() {
+        Some((&REPLY_OK, body)) => Ok(Ok(body.to_vec())),
+        Some((&REPLY_ERROR, error)) => Ok(Err(String::from_utf8_lossy(error).into_owned())),
+        _ => Err(anyhow!("Respuesta inválida")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    struct Peer {
+        identity: DeviceIdentity,
+        pinned: PublicIdentity,
+    }
+
+    #[async_trait]
+    impl SyncResponder for Peer {
+        async fn respond(&self, _from: DeviceId, request: &[u8]) -> Result<Vec<u8>> {
+            match request {
+                b"rechazar" => Err(anyhow!("en pausa")),
+                _ => Ok(request.iter().rev().copied().collect()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl NoisePeer for Peer {
+        async fn static_secret(&self) -> Result<StaticSecret> {
+            Ok(self.identity.static_secret().clone())
+        }
+
+        async fn verify_static_key(&self, _from: DeviceId, static_key: &[u8; 32]) -> Result<()> {
+            match *static_key == self.pinned.static_key {
+                true => Ok(()),
+                false => Err(anyhow!("clave estática distinta")),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TlsHandler for Peer {
+        async fn local_identity(&self) -> Result<DeviceIdentity> {
+            Ok(self.identity.clone())
+        }
+
+        async fn accept_peer(&self, _from: DeviceId, _to: DeviceId, presented: &[u8; 32]) -> Result<()> {
+            match *presented == self.pinned.fingerprint {
+                true => Ok(()),
+                false => Err(anyhow!("identidad distinta")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exchanges_batches_with_the_pinned_peer() {
+        let (laptop, phone) = (DeviceIdentity::generate().unwrap(), DeviceIdentity::generate().unwrap());
+        let server = TlsServer::start(Arc::new(Peer { identity: phone.clone(), pinned: laptop.public() })).await.unwrap();
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, server.port()));
+        let (laptop_id, phone_id) = (DeviceId::new(), DeviceId::new());
+
+        let connection = TlsConnection::connect(&laptop, &phone.public(), laptop_id, phone_id, address).await.unwrap();
+        assert_eq!(connection.exchange(b"hola".to_vec()).await.unwrap(), b"aloh");
+        assert!(connection.exchange(b"rechazar".to_vec()).await.is_err());
+        assert_eq!(connection.exchange(Vec::new()).await.unwrap(), b"");
+
+        // El que atiende no es el emparejado
+        let stranger = DeviceIdentity::generate().unwrap();
+        assert!(TlsConnection::connect(&laptop, &stranger.public(), laptop_id, phone_id, address).await.is_err());
+        // El que llama no es el emparejado
+        assert!(TlsConnection::connect(&stranger, &phone.public(), laptop_id, phone_id, address).await.is_err());
+        // Presenta el certificado emparejado pero otra clave estática
+        let forged = PublicIdentity { static_key: stranger.public().static_key, ..phone.public() };
+        assert!(TlsConnection::connect(&laptop, &forged, laptop_id, phone_id, address).await.is_err());
+    }
+}
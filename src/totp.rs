@@ -0,0 +1,143 @@
+//! Códigos de un solo uso basados en tiempo (TOTP, RFC 6238)
+//!
+//! El secreto de una entrada puede guardarse como base32 plano o como URI
+//! `otpauth://totp/...` con parámetros `secret`, `digits`, `period` y `algorithm`.
+
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Configuración TOTP ya interpretada
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotpConfig {
+    pub secret: Vec<u8>,
+    pub digits: u32,
+    pub period: u64,
+    pub algorithm: Algorithm,
+}
+
+impl TotpConfig {
+    /// Interpreta un secreto base32 o una URI `otpauth://totp/`
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let mut config = TotpConfig {
+            secret: Vec::new(),
+            digits: 6,
+            period: 30,
+            algorithm: Algorithm::Sha1,
+        };
+        
+        let secret = if let Some(rest) = value.strip_prefix("otpauth://") {
+            if !rest.to_lowercase().starts_with("totp/") {
+                return Err(anyhow!("Solo se soportan URIs otpauth de tipo totp"));
+            }
+            let query = rest.split_once('?').map(|(_, q)| q).unwrap_or_default();
+            let mut secret = None;
+            for (key, val) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+                match key.to_lowercase().as_str() {
+                    "secret" => secret = Some(val.to_string()),
+                    "digits" => config.digits = val.parse().map_err(|_| anyhow!("Parámetro digits inválido"))?,
+                    "period" => config.period = val.parse().map_err(|_| anyhow!("Parámetro period inválido"))?,
+                    "algorithm" => config.algorithm = match val.to_uppercase().as_str() {
+                        "SHA1" => Algorithm::Sha1,
+                        "SHA256" => Algorithm::Sha256,
+                        "SHA512" => Algorithm::Sha512,
+                        other => return Err(anyhow!("Algoritmo TOTP no soportado: {}", other)),
+                    },
+                    _ => {}
+                }
+            }
+            secret.ok_or_else(|| anyhow!("La URI otpauth no contiene el parámetro secret"))?
+        } else {
+            value.to_string()
+        };
+        
+        // Los secretos suelen mostrarse en grupos con espacios y sin relleno
+        let normalized: String = secret.chars()
+            .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+            .collect::<String>()
+            .to_uppercase();
+        config.secret = data_encoding::BASE32_NOPAD.decode(normalized.as_bytes())
+            .map_err(|e| anyhow!("Secreto TOTP no es base32 válido: {}", e))?;
+        
+        if config.secret.is_empty() {
+            return Err(anyhow!("El secreto TOTP está vacío"));
+        }
+        if !(6..=8).contains(&config.digits) {
+            return Err(anyhow!("El número de dígitos debe estar entre 6 y 8"));
+        }
+        if config.period == 0 {
+            return Err(anyhow!("El periodo debe ser mayor que cero"));
+        }
+        Ok(config)
+    }
+    
+    /// Genera el código para el instante dado (segundos Unix)
+    pub fn generate(&self, unix_time: u64) -> String {
+        let counter = (unix_time / self.period).to_be_bytes();
+        let digest = match self.algorithm {
+            Algorithm::Sha1 => hmac_digest::<Hmac<sha1::Sha1>>(&self.secret, &counter),
+            Algorithm::Sha256 => hmac_digest::<Hmac<sha2::Sha256>>(&self.secret, &counter),
+            Algorithm::Sha512 => hmac_digest::<Hmac<sha2::Sha512>>(&self.secret, &counter),
+        };
+        
+        // Truncado dinámico (RFC 4226, sección 5.3)
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            digest[offset] & 0x7f,
+            digest[offset + 1],
+            digest[offset + 2],
+            digest[offset + 3],
+        ]);
+        let code = binary % 10u32.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+    
+    /// Genera el código actual y los segundos que le quedan de validez
+    pub fn generate_now(&self) -> (String, u64) {
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        (self.generate(now), self.period - now % self.period)
+    }
+}
+
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key)
+        .expect("HMAC acepta claves de cualquier longitud");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vectores de prueba del apéndice B de la RFC 6238 (secreto "12345678901234567890")
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_rfc6238_sha1_vectors() {
+        let config = TotpConfig::parse(&format!("otpauth://totp/test?secret={}&digits=8", RFC_SECRET)).unwrap();
+        assert_eq!(config.generate(59), "94287082");
+        assert_eq!(config.generate(1111111109), "07081804");
+        assert_eq!(config.generate(20000000000), "65353130");
+    }
+
+    #[test]
+    fn test_parse_plain_secret_with_spaces() {
+        let config = TotpConfig::parse("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap();
+        assert_eq!(config.digits, 6);
+        assert_eq!(config.generate(59), "287082");
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_secret() {
+        assert!(TotpConfig::parse("not base32!").is_err());
+        assert!(TotpConfig::parse("otpauth://hotp/x?secret=GEZDGNBV").is_err());
+    }
+}
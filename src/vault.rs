@@ -0,0 +1,378 @@
+//! Encriptado de las entradas de la bóveda
+//!
+//! Qué campos de una entrada se guardan encriptados, cómo se serializan y qué
+//! metadatos se calculan de la contraseña se decide aquí y en ningún otro
+//! sitio. Los comandos leen y escriben con `database::PasswordRepository` en el
+//! hilo de la base de datos y abren o sellan las filas con `EntryCipher` fuera
+//! de él, en paralelo.
+
+use crate::crypto::{CryptoManager, EncryptedData};
+use crate::database::{EncryptedEntryRow, PasswordMetadata, SealedCredentials, SealedEntry};
+use crate::error::{AppError, AppResult};
+use crate::i18n::Message;
+use crate::models::{EntryEncryption, PasswordEntry, PasswordListRequest, SortDirection, SortField};
+use crate::security;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Título, usuario y contraseña de una entrada, ya desencriptados. Es también lo
+/// que se encripta en el registro de las entradas que se guardan como uno solo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub title: String,
+    pub username: String,
+    pub password: String,
+}
+
+/// Uno de los campos que van en el registro de una entrada
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Credential {
+    Title,
+    Username,
+    Password,
+}
+
+impl Credential {
+    /// Columna en la que se guarda cuando se encripta por separado
+    pub fn name(self) -> &'static str {
+        match self {
+            Credential::Title => "title",
+            Credential::Username => "username",
+            Credential::Password => "password",
+        }
+    }
+
+    /// Valor encriptado de su columna en la fila (vacío si la fila es un registro)
+    pub fn column(self, row: &EncryptedEntryRow) -> &str {
+        match self {
+            Credential::Title => &row.title,
+            Credential::Username => &row.username,
+            Credential::Password => &row.password,
+        }
+    }
+
+    fn field(self) -> &'static str {
+        match self {
+            Credential::Title => "fields.title",
+            Credential::Username => "fields.username",
+            Credential::Password => "fields.password",
+        }
+    }
+}
+
+/// Encripta y desencripta los campos de las entradas con la clave maestra
+#[derive(Clone)]
+pub struct EntryCipher {
+    crypto: CryptoManager,
+    encryption: EntryEncryption,
+}
+
+impl EntryCipher {
+    /// `crypto` tiene que estar desbloqueado
+    pub fn new(crypto: CryptoManager) -> Self {
+        Self { crypto, encryption: EntryEncryption::default() }
+    }
+
+    /// Cómo se encriptan las entradas que se sellan. Las guardadas de la otra
+    /// forma se siguen abriendo igual.
+    pub fn with_encryption(mut self, encryption: EntryEncryption) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    pub fn encryption(&self) -> EntryEncryption {
+        self.encryption
+    }
+
+    /// Encripta un campo y lo codifica en el formato compacto de `EncryptedData`,
+    /// tal como se guarda
+    pub fn encrypt(&self, data: &[u8], field: &'static str) -> AppResult<String> {
+        let encrypted = self.crypto.encrypt_data(data)
+            .map_err(|e| AppError::crypto(Message::new("errors.encryptField").with_key("field", field), e))?;
+        Ok(encrypted.to_compact())
+    }
+
+    /// Desencripta un campo guardado, en formato compacto o en el JSON anterior
+    pub fn decrypt(&self, encrypted: &str, field: &'static str) -> AppResult<String> {
+        String::from_utf8(self.decrypt_bytes(encrypted, field)?)
+            .map_err(|e| AppError::crypto(Message::new("errors.convertField").with_key("field", field), e))
+    }
+
+    /// Como `decrypt`, para datos binarios (adjuntos)
+    pub fn decrypt_bytes(&self, encrypted: &str, field: &'static str) -> AppResult<Vec<u8>> {
+        let encrypted_data = EncryptedData::from_stored(encrypted)
+            .map_err(|e| AppError::crypto(Message::new("errors.parseField").with_key("field", field), e))?;
+        self.crypto.decrypt_data(&encrypted_data)
+            .map_err(|e| AppError::crypto(Message::new("errors.decryptField").with_key("field", field), e))
+    }
+
+    /// Encripta el título, el usuario y la contraseña según el modo del cifrador
+    pub fn seal_credentials(&self, title: &str, username: &str, password: &str) -> AppResult<SealedCredentials> {
+        match self.encryption {
+            EntryEncryption::Fields => Ok(SealedCredentials {
+                title: self.encrypt(title.as_bytes(), "fields.title")?,
+                username: self.encrypt(username.as_bytes(), "fields.username")?,
+                password: self.encrypt(password.as_bytes(), "fields.password")?,
+                record: None,
+            }),
+            EntryEncryption::Record => {
+                let record = serde_json::to_vec(&Credentials {
+                    title: title.to_string(),
+                    username: username.to_string(),
+                    password: password.to_string(),
+                })
+                .map_err(|e| AppError::crypto(Message::new("errors.encryptField").with_key("field", "fields.record"), e))?;
+                Ok(SealedCredentials {
+                    title: String::new(),
+                    username: String::new(),
+                    password: String::new(),
+                    record: Some(self.encrypt(&record, "fields.record")?),
+                })
+            }
+        }
+    }
+
+    fn open_record(&self, record: &str) -> AppResult<Credentials> {
+        serde_json::from_slice(&self.decrypt_bytes(record, "fields.record")?)
+            .map_err(|e| AppError::crypto(Message::new("errors.convertField").with_key("field", "fields.record"), e))
+    }
+
+    /// Título, usuario y contraseña de la fila, desencriptados de sus columnas o
+    /// de una vez del registro
+    pub fn open_credentials(&self, row: &EncryptedEntryRow) -> AppResult<Credentials> {
+        match &row.record {
+            Some(record) => self.open_record(record),
+            None => Ok(Credentials {
+                title: self.decrypt(&row.title, "fields.title")?,
+                username: self.decrypt(&row.username, "fields.username")?,
+                password: self.decrypt(&row.password, "fields.password")?,
+            }),
+        }
+    }
+
+    /// Desencripta un solo campo: de su columna (`column`) o, si la entrada se
+    /// guardó como un registro, del registro
+    pub fn open_credential(&self, column: &str, record: Option<&str>, credential: Credential) -> AppResult<String> {
+        let Some(record) = record else {
+            return self.decrypt(column, credential.field());
+        };
+        let credentials = self.open_record(record)?;
+        Ok(match credential {
+            Credential::Title => credentials.title,
+            Credential::Username => credentials.username,
+            Credential::Password => credentials.password,
+        })
+    }
+
+    /// Título y usuario, para los listados que no necesitan la contraseña
+    pub fn open_title_and_username(&self, title: &str, username: &str, record: Option<&str>) -> AppResult<(String, String)> {
+        match record {
+            Some(record) => self.open_record(record).map(|credentials| (credentials.title, credentials.username)),
+            None => Ok((self.decrypt(title, "fields.title")?, self.decrypt(username, "fields.username")?)),
+        }
+    }
+
+    /// Fortaleza y huella de la contraseña
+    pub fn metadata(&self, password: &str) -> AppResult<PasswordMetadata> {
+        let estimate = security::evaluate_strength(password);
+        let fingerprint = self.crypto.fingerprint(password.as_bytes())
+            .map_err(|e| AppError::crypto(Message::new("errors.encryptField").with_key("field", "fields.password"), e))?;
+        Ok(PasswordMetadata {
+            strength: estimate.score,
+            guesses_log10: estimate.guesses_log10,
+            fingerprint,
+        })
+    }
+
+    /// Encripta el título, el usuario y la contraseña de la entrada y calcula los
+    /// metadatos de la contraseña. El TOTP, la secuencia de auto-type y el
+    /// recuento de filtraciones quedan vacíos.
+    pub fn seal(&self, entry: &PasswordEntry) -> AppResult<SealedEntry> {
+        let credentials = self.seal_credentials(&entry.title, &entry.username, &entry.password)?;
+        Ok(SealedEntry {
+            id: entry.id,
+            title: credentials.title,
+            username: credentials.username,
+            password: credentials.password,
+            record: credentials.record,
+            url: entry.url.clone(),
+            notes: entry.notes.clone(),
+            category_id: entry.category_id,
+            tags: entry.tags.clone(),
+            created_at: entry.created_at.clone(),
+            updated_at: entry.updated_at.clone(),
+            last_used: entry.last_used.clone(),
+            meta: self.metadata(&entry.password)?,
+            password_changed_at: entry.updated_at.clone(),
+            breach_count: None,
+            totp_secret: None,
+            autotype_sequence: None,
+        })
+    }
+
+    pub fn open(&self, row: EncryptedEntryRow) -> AppResult<PasswordEntry> {
+        let Credentials { title, username, password } = self.open_credentials(&row)?;
+        Ok(PasswordEntry {
+            title,
+            username,
+            password,
+            id: row.id,
+            url: row.url,
+            notes: row.notes,
+            category_id: row.category_id,
+            tags: row.tags
+                .and_then(|tags| serde_json::from_str(&tags).ok())
+                .unwrap_or_default(),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            last_used: row.last_used,
+        })
+    }
+
+    /// Desencripta un lote de filas en paralelo conservando el orden original
+    pub fn open_all(&self, rows: Vec<EncryptedEntryRow>) -> AppResult<Vec<PasswordEntry>> {
+        self.open_all_with(rows, |_| {})
+    }
+
+    /// Como `open_all`, llamando a `opened` con cada entrada desencriptada
+    pub fn open_all_with<F>(&self, rows: Vec<EncryptedEntryRow>, opened: F) -> AppResult<Vec<PasswordEntry>>
+    where
+        F: Fn(&PasswordEntry) + Sync,
+    {
+        rows.into_par_iter()
+            .map(|row| {
+                let entry = self.open(row)?;
+                opened(&entry);
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    /// Aplica el orden por título y la paginación en memoria. El título está
+    /// encriptado, así que no se puede ordenar en SQL: se desencriptan solo los
+    /// títulos. Para otros criterios las filas ya vienen ordenadas y paginadas.
+    pub fn paginate(&self, rows: Vec<EncryptedEntryRow>, request: &PasswordListRequest) -> AppResult<Vec<EncryptedEntryRow>> {
+        if request.sort_by.unwrap_or_default() != SortField::Title {
+            return Ok(rows);
+        }
+
+        let mut titled_rows = rows.into_par_iter()
+            .map(|row| {
+                let title = self.open_credential(&row.title, row.record.as_deref(), Credential::Title)?;
+                Ok((title.to_lowercase(), row))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        titled_rows.sort_by(|a, b| a.0.cmp(&b.0));
+        if request.sort_direction.unwrap_or_default() == SortDirection::Desc {
+            titled_rows.reverse();
+        }
+
+        Ok(titled_rows.into_iter()
+            .skip(request.offset.unwrap_or(0))
+            .take(request.limit.unwrap_or(usize::MAX))
+            .map(|(_, row)| row)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use crate::models::CategoryId;
+
+    fn cipher() -> EntryCipher {
+        let mut crypto = CryptoManager::new();
+        crypto.set_master_key("clave maestra de prueba", &[7; 32]).unwrap();
+        EntryCipher::new(crypto)
+    }
+
+    fn entry() -> PasswordEntry {
+        PasswordEntry {
+            password: "correcto caballo batería grapa".to_string(),
+            notes: Some("notas".to_string()),
+            category_id: Some(CategoryId::new()),
+            tags: vec!["personal".to_string()],
+            updated_at: "2024-01-02T00:00:00Z".to_string(),
+            ..PasswordEntry::sample("Correo")
+        }
+    }
+
+    fn row(sealed: SealedEntry) -> EncryptedEntryRow {
+        EncryptedEntryRow {
+            id: sealed.id,
+            title: sealed.title,
+            username: sealed.username,
+            password: sealed.password,
+            url: sealed.url,
+            notes: sealed.notes,
+            category_id: sealed.category_id,
+            tags: Some(serde_json::to_string(&sealed.tags).unwrap()),
+            created_at: sealed.created_at,
+            updated_at: sealed.updated_at,
+            last_used: sealed.last_used,
+            record: sealed.record,
+        }
+    }
+
+    #[test]
+    fn test_seals_and_opens_entries() {
+        let cipher = cipher();
+        let entry = entry();
+
+        let sealed = cipher.seal(&entry).unwrap();
+        assert!(!sealed.password.contains("caballo"));
+        assert_eq!(sealed.category_id, entry.category_id);
+        assert_eq!(sealed.password_changed_at, entry.updated_at);
+        assert_eq!(sealed.meta.fingerprint, cipher.metadata(&entry.password).unwrap().fingerprint);
+
+        assert_eq!(sealed.record, None);
+        let opened = cipher.open(row(sealed)).unwrap();
+        assert_eq!(opened.password, entry.password);
+        assert_eq!(opened.tags, entry.tags);
+        assert!(cipher.decrypt("no es json", "fields.title").is_err());
+    }
+
+    #[test]
+    fn test_seals_entries_as_one_record() {
+        let fields = cipher();
+        let records = fields.clone().with_encryption(EntryEncryption::Record);
+        let entry = entry();
+
+        let sealed = records.seal(&entry).unwrap();
+        assert_eq!((sealed.title.as_str(), sealed.username.as_str(), sealed.password.as_str()), ("", "", ""));
+        let record = sealed.record.clone().unwrap();
+        assert_eq!(records.open_credential("", Some(&record), Credential::Username).unwrap(), "ana");
+        assert_eq!(
+            records.open_title_and_username("", "", Some(&record)).unwrap(),
+            ("Correo".to_string(), "ana".to_string()),
+        );
+
+        // El modo solo decide cómo se sella: las dos formas se abren igual
+        let opened = fields.open(row(sealed)).unwrap();
+        assert_eq!((opened.title, opened.password), (entry.title, entry.password));
+    }
+
+    #[test]
+    fn test_reads_compact_and_legacy_fields() {
+        let cipher = cipher();
+        let compact = cipher.encrypt("secreto".as_bytes(), "fields.password").unwrap();
+        assert!(!EncryptedData::is_legacy(&compact));
+        assert_eq!(cipher.decrypt(&compact, "fields.password").unwrap(), "secreto");
+
+        // Mismo contenido en el JSON de versiones anteriores
+        let parsed = EncryptedData::from_stored(&compact).unwrap();
+        let legacy = serde_json::to_string_pretty(&EncryptedData { salt: vec![0; 32], ..parsed.clone() }).unwrap();
+        assert!(EncryptedData::is_legacy(&legacy));
+        assert_eq!(cipher.decrypt(&legacy, "fields.password").unwrap(), "secreto");
+        assert_eq!(EncryptedData::from_stored(&legacy).unwrap().to_compact(), compact);
+        assert!(legacy.len() > 3 * compact.len());
+
+        // Versión desconocida y campo truncado
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let mut bytes = base64.decode(&compact).unwrap();
+        assert!(cipher.decrypt(&base64.encode(&bytes[..20]), "fields.password").is_err());
+        bytes[0] = 9;
+        assert!(cipher.decrypt(&base64.encode(&bytes), "fields.password").is_err());
+    }
+}